@@ -0,0 +1,94 @@
+//! Client side of the HTTP adapter.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use runtime_router::Page;
+use runtime_transport_http::{HttpAdapter, HttpRequest, HttpResponse, SessionToken};
+use serde_json::{json, Value};
+
+use crate::{transport_error, ClientError, Reply, Transport};
+
+/// Sends commands as `POST /commands/<name>` with the session's bearer
+/// token and CSRF nonce, or with an API key.
+pub struct HttpTransport {
+    adapter: Arc<HttpAdapter>,
+    token: String,
+    csrf_nonce: Option<String>,
+}
+
+impl HttpTransport {
+    pub fn new(adapter: Arc<HttpAdapter>, session: &SessionToken) -> Self {
+        Self {
+            adapter,
+            token: session.token.clone(),
+            csrf_nonce: Some(session.csrf_nonce.clone()),
+        }
+    }
+
+    /// Authenticate with a long-lived API key instead of a session, for
+    /// jobs that cannot ask for one.
+    pub fn with_api_key(adapter: Arc<HttpAdapter>, key: impl Into<String>) -> Self {
+        Self {
+            adapter,
+            token: key.into(),
+            csrf_nonce: None,
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for HttpTransport {
+    async fn send(&self, command: &str, payload: Value) -> Result<Reply, ClientError> {
+        let mut request = HttpRequest::new(
+            "POST",
+            format!("/commands/{command}"),
+            json!({ "command": command, "payload": payload }),
+        )
+        .with_header("Authorization", format!("Bearer {}", self.token));
+        if let Some(nonce) = &self.csrf_nonce {
+            request = request.with_header("X-Csrf-Token", nonce.clone());
+        }
+        let response = self
+            .adapter
+            .dispatch(request)
+            .await
+            .map_err(transport_error)?;
+        if response.status == 429 {
+            return Err(ClientError::Throttled {
+                retry_after: retry_after(&response),
+            });
+        }
+        Ok(Reply {
+            status_code: response.status,
+            page: page(&response),
+            payload: response.body,
+        })
+    }
+}
+
+/// The exact delay from the body, else the whole seconds of `Retry-After`.
+fn retry_after(response: &HttpResponse) -> Duration {
+    if let Some(ms) = response.body["retry_after_ms"].as_u64() {
+        return Duration::from_millis(ms);
+    }
+    let secs = response
+        .headers
+        .get("retry-after")
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(1);
+    Duration::from_secs(secs)
+}
+
+fn page(response: &HttpResponse) -> Option<Page> {
+    let page_size = response.headers.get("x-page-size")?.parse().ok()?;
+    Some(Page {
+        next_cursor: response.headers.get("x-next-cursor").cloned(),
+        page_size,
+        total_estimate: response
+            .headers
+            .get("x-total-estimate")
+            .and_then(|total| total.parse().ok()),
+    })
+}
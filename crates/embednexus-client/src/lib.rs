@@ -0,0 +1,308 @@
+//! Typed async clients for the runtime protocol.
+//!
+//! Each transport adapter wraps commands in its own envelope: HTTP wants a
+//! bearer token and CSRF header, STDIO checksummed frames, UDS negotiated
+//! peer credentials. The [`Transport`] implementations speak those
+//! envelopes; [`Client`] adds what every integrator needs on top of them:
+//! checking reply statuses, retrying throttled requests after the delay the
+//! runtime asked for, walking paged results and following ingest runs.
+
+mod http;
+mod stdio;
+mod uds;
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use runtime_router::{Page, WHOAMI_COMMAND};
+use runtime_transport_error::{AdapterError, TransportError};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use thiserror::Error;
+
+pub use http::HttpTransport;
+pub use runtime_router::SessionInfo;
+pub use stdio::StdioTransport;
+pub use uds::UdsTransport;
+
+/// Command starting an ingest run; see `runtime_commands::START_COMMAND`.
+const START_COMMAND: &str = "ingest.start";
+/// Command reporting an ingest run; see `runtime_commands::STATUS_COMMAND`.
+const STATUS_COMMAND: &str = "ingest.status";
+/// Command searching a repository; see `runtime_commands::SEARCH_COMMAND`.
+const SEARCH_COMMAND: &str = "search.query";
+
+/// Longest wait a status poll asks the runtime for.
+const STATUS_POLL_MS: u64 = 30_000;
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    /// The adapter refused the request before or while routing it.
+    #[error("{kind} error ({status_code}): {message}")]
+    Transport {
+        status_code: u16,
+        kind: &'static str,
+        message: String,
+    },
+    /// The command answered with a status outside 2xx.
+    #[error("command failed with status {status_code}")]
+    Command { status_code: u16, payload: Value },
+    /// The runtime is shedding load and asked for a retry after the delay.
+    #[error("throttled; retry after {}ms", retry_after.as_millis())]
+    Throttled { retry_after: Duration },
+    /// A reply did not have the expected shape.
+    #[error("unexpected reply: {0}")]
+    Decode(String),
+}
+
+impl ClientError {
+    /// HTTP-like status code of the failure, when there is one.
+    #[must_use]
+    pub const fn status_code(&self) -> Option<u16> {
+        match self {
+            Self::Transport { status_code, .. } | Self::Command { status_code, .. } => {
+                Some(*status_code)
+            }
+            Self::Throttled { .. } => Some(429),
+            Self::Decode(_) => None,
+        }
+    }
+}
+
+/// Convert an adapter error, keeping the throttling hint when present.
+pub(crate) fn transport_error<A: AdapterError>(err: TransportError<A>) -> ClientError {
+    if let Some(retry_after) = err.retry_after() {
+        return ClientError::Throttled { retry_after };
+    }
+    ClientError::Transport {
+        status_code: err.status_code(),
+        kind: err.kind(),
+        message: err.to_string(),
+    }
+}
+
+/// What a command answered.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reply {
+    pub status_code: u16,
+    pub payload: Value,
+    /// Set when the payload is one page of a larger result.
+    pub page: Option<Page>,
+}
+
+impl Reply {
+    /// Deserialize the payload into `T`.
+    pub fn decode<T: DeserializeOwned>(&self) -> Result<T, ClientError> {
+        T::deserialize(&self.payload).map_err(|err| ClientError::Decode(err.to_string()))
+    }
+}
+
+/// Carries one command to the runtime and its reply back.
+#[async_trait]
+pub trait Transport: Send + Sync {
+    /// Send `command`; throttling surfaces as [`ClientError::Throttled`],
+    /// any routed reply as a [`Reply`] whatever its status.
+    async fn send(&self, command: &str, payload: Value) -> Result<Reply, ClientError>;
+}
+
+/// How a [`Client`] retries throttled requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; zero disables retrying.
+    pub max_retries: u32,
+    /// Longest delay worth waiting; longer hints fail straight away.
+    pub max_wait: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            max_wait: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Progress of an ingest run, as `ingest.status` reports it.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct RunStatus {
+    pub run_id: String,
+    pub repo_id: String,
+    /// `running`, `completed`, `failed` or `cancelled`.
+    pub state: String,
+    pub files_changed: usize,
+    pub files_planned: usize,
+    pub chunks_embedded: usize,
+    #[serde(default)]
+    pub eta_ms: Option<u64>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl RunStatus {
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.state != "running"
+    }
+}
+
+/// Sends commands over a [`Transport`].
+pub struct Client<T> {
+    transport: T,
+    retry: RetryPolicy,
+}
+
+impl<T: Transport> Client<T> {
+    pub fn new(transport: T) -> Self {
+        Self {
+            transport,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    #[must_use]
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Send `command`, retrying while the runtime throttles it, and fail
+    /// with [`ClientError::Command`] unless the reply is a success.
+    pub async fn call(&self, command: &str, payload: Value) -> Result<Reply, ClientError> {
+        let mut retries = 0;
+        loop {
+            match self.transport.send(command, payload.clone()).await {
+                Err(ClientError::Throttled { retry_after })
+                    if retries < self.retry.max_retries && retry_after <= self.retry.max_wait =>
+                {
+                    retries += 1;
+                    tokio::time::sleep(retry_after).await;
+                }
+                Ok(reply) if !(200..=299).contains(&reply.status_code) => {
+                    return Err(ClientError::Command {
+                        status_code: reply.status_code,
+                        payload: reply.payload,
+                    });
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// [`call`](Self::call), deserializing the payload into `R`.
+    pub async fn call_as<R: DeserializeOwned>(
+        &self,
+        command: &str,
+        payload: Value,
+    ) -> Result<R, ClientError> {
+        self.call(command, payload).await?.decode()
+    }
+
+    /// The principal, capabilities, token and peer the runtime sees this
+    /// client as.
+    pub async fn whoami(&self) -> Result<SessionInfo, ClientError> {
+        self.call_as(WHOAMI_COMMAND, Value::Null).await
+    }
+
+    /// Walk a paged command page by page, starting from `payload`.
+    pub fn pages(&self, command: &str, payload: Value) -> Pages<'_, T> {
+        Pages {
+            client: self,
+            command: command.to_string(),
+            payload,
+            cursor: None,
+            done: false,
+        }
+    }
+
+    /// Pages of the hits for `query` in `repo_id`; each page's payload
+    /// holds a `hits` array.
+    pub fn search(&self, repo_id: &str, query: &str, page_size: usize) -> Pages<'_, T> {
+        self.pages(
+            SEARCH_COMMAND,
+            json!({ "repo_id": repo_id, "query": query, "page_size": page_size }),
+        )
+    }
+
+    /// Start ingesting `repo_id`.
+    pub async fn start_ingest(&self, repo_id: &str) -> Result<RunStatus, ClientError> {
+        self.call_as(START_COMMAND, json!({ "repo_id": repo_id }))
+            .await
+    }
+
+    /// Follow the run `run_id` until it finishes.
+    pub fn watch_run(&self, run_id: &str) -> RunWatcher<'_, T> {
+        RunWatcher {
+            client: self,
+            run_id: run_id.to_string(),
+            polled: false,
+            done: false,
+        }
+    }
+}
+
+/// Pages of a paged command, fetched as they are asked for.
+pub struct Pages<'a, T> {
+    client: &'a Client<T>,
+    command: String,
+    payload: Value,
+    cursor: Option<String>,
+    done: bool,
+}
+
+impl<T: Transport> Pages<'_, T> {
+    /// The next page, or `None` after the last one or an error.
+    pub async fn next(&mut self) -> Option<Result<Reply, ClientError>> {
+        if self.done {
+            return None;
+        }
+        let mut payload = self.payload.clone();
+        if let (Some(cursor), Value::Object(fields)) = (&self.cursor, &mut payload) {
+            fields.insert("cursor".into(), Value::String(cursor.clone()));
+        }
+        let result = self.client.call(&self.command, payload).await;
+        self.cursor = match &result {
+            Ok(reply) => reply
+                .page
+                .as_ref()
+                .and_then(|page| page.next_cursor.clone()),
+            Err(_) => None,
+        };
+        self.done = self.cursor.is_none();
+        Some(result)
+    }
+}
+
+/// Statuses of an ingest run, each long-polled until it changes.
+pub struct RunWatcher<'a, T> {
+    client: &'a Client<T>,
+    run_id: String,
+    polled: bool,
+    done: bool,
+}
+
+impl<T: Transport> RunWatcher<'_, T> {
+    /// The next status of the run; the first is returned at once, later
+    /// ones when the run makes progress. `None` once a finished status or
+    /// an error has been returned.
+    pub async fn next(&mut self) -> Option<Result<RunStatus, ClientError>> {
+        if self.done {
+            return None;
+        }
+        let mut payload = json!({ "run_id": self.run_id });
+        if self.polled {
+            payload["wait_ms"] = json!(STATUS_POLL_MS);
+        }
+        self.polled = true;
+        let result: Result<RunStatus, ClientError> =
+            self.client.call_as(STATUS_COMMAND, payload).await;
+        self.done = result.as_ref().map_or(true, RunStatus::is_finished);
+        Some(result)
+    }
+}
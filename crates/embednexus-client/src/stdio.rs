@@ -0,0 +1,73 @@
+//! Client side of the STDIO adapter.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use runtime_router::Page;
+use runtime_transport_stdio::{SessionToken, StdioAdapter, StreamReader};
+use serde_json::{json, Value};
+
+use crate::{transport_error, ClientError, Reply, Transport};
+
+/// Sends commands as checksummed frames signed with the session token,
+/// reassembling responses the adapter streams over several frames.
+pub struct StdioTransport {
+    adapter: Arc<StdioAdapter>,
+    session: SessionToken,
+}
+
+impl StdioTransport {
+    pub fn new(adapter: Arc<StdioAdapter>, session: SessionToken) -> Self {
+        Self { adapter, session }
+    }
+}
+
+#[async_trait]
+impl Transport for StdioTransport {
+    async fn send(&self, command: &str, payload: Value) -> Result<Reply, ClientError> {
+        let codec = self.adapter.codec();
+        let frame = codec
+            .encode(
+                &json!({ "command": command, "payload": payload }),
+                &self.session,
+            )
+            .map_err(transport_error)?;
+        let frames = self
+            .adapter
+            .dispatch_frame_streamed(frame)
+            .await
+            .map_err(transport_error)?;
+        let mut reader = StreamReader::default();
+        let mut body = None;
+        for frame in frames {
+            let (part, _) = codec
+                .decode(&frame.map_err(transport_error)?)
+                .map_err(transport_error)?;
+            body = reader.push(part).map_err(transport_error)?;
+        }
+        let mut body =
+            body.ok_or_else(|| ClientError::Decode("stream ended before its last frame".into()))?;
+        if body["status"] == "throttled" {
+            return Err(ClientError::Throttled {
+                retry_after: Duration::from_millis(body["retry_after_ms"].as_u64().unwrap_or(0)),
+            });
+        }
+        let status_code = body["status_code"]
+            .as_u64()
+            .and_then(|code| u16::try_from(code).ok())
+            .ok_or_else(|| ClientError::Decode("frame without a status code".into()))?;
+        let page = match body.get_mut("page") {
+            Some(page) => Some(
+                serde_json::from_value::<Page>(page.take())
+                    .map_err(|err| ClientError::Decode(err.to_string()))?,
+            ),
+            None => None,
+        };
+        Ok(Reply {
+            status_code,
+            payload: body["payload"].take(),
+            page,
+        })
+    }
+}
@@ -0,0 +1,67 @@
+//! Client side of the UDS adapter.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use runtime_router::Page;
+use runtime_transport_uds::{PeerCredentials, SessionToken, UdsAdapter, UdsRequest};
+use serde_json::{json, Value};
+
+use crate::{transport_error, ClientError, Reply, Transport};
+
+/// Sends commands as the peer it negotiated, with the session token.
+pub struct UdsTransport {
+    adapter: Arc<UdsAdapter>,
+    peer: PeerCredentials,
+    token: String,
+}
+
+impl UdsTransport {
+    /// Negotiate `peer` with the adapter, then send as it.
+    pub fn connect(
+        adapter: Arc<UdsAdapter>,
+        peer: PeerCredentials,
+        session: &SessionToken,
+    ) -> Result<Self, ClientError> {
+        adapter.negotiate_peer(&peer).map_err(transport_error)?;
+        Ok(Self {
+            adapter,
+            peer,
+            token: session.token.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl Transport for UdsTransport {
+    async fn send(&self, command: &str, payload: Value) -> Result<Reply, ClientError> {
+        let request = UdsRequest::new(
+            self.peer.clone(),
+            self.token.clone(),
+            json!({ "command": command, "payload": payload }),
+        );
+        let mut payload = self
+            .adapter
+            .dispatch(request)
+            .await
+            .map_err(transport_error)?;
+        // The adapter folds the page envelope into object payloads and
+        // does not pass the status code on, so routed replies count as
+        // successes.
+        let page = match payload
+            .as_object_mut()
+            .and_then(|fields| fields.remove("page"))
+        {
+            Some(page) => Some(
+                serde_json::from_value::<Page>(page)
+                    .map_err(|err| ClientError::Decode(err.to_string()))?,
+            ),
+            None => None,
+        };
+        Ok(Reply {
+            status_code: 200,
+            payload,
+            page,
+        })
+    }
+}
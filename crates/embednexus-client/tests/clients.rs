@@ -0,0 +1,306 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use embednexus_client::{
+    Client, ClientError, HttpTransport, RetryPolicy, StdioTransport, Transport, UdsTransport,
+};
+use runtime_router::{
+    CommandHandler, HandlerRouter, PageRequest, RateLimit, RouterError, RouterResponse,
+    SessionContext, SharedRouter,
+};
+use runtime_transport_http::{ApiKeyStore, HttpAdapter, HttpConfig};
+use runtime_transport_stdio::{StdioAdapter, StdioConfig};
+use runtime_transport_uds::{PeerCredentials, UdsAdapter, UdsConfig};
+use serde_json::{json, Value};
+
+/// Pages through the numbers 0..5.
+struct NumbersHandler;
+
+#[async_trait]
+impl CommandHandler for NumbersHandler {
+    async fn handle(
+        &self,
+        _ctx: &SessionContext,
+        payload: Value,
+    ) -> Result<RouterResponse, RouterError> {
+        let request: PageRequest =
+            serde_json::from_value(payload).map_err(|err| RouterError::InvalidRequest {
+                detail: err.to_string(),
+            })?;
+        let (numbers, page) = request.paginate((0..5).collect::<Vec<u32>>(), 2, 10)?;
+        Ok(RouterResponse::paged(json!({ "numbers": numbers }), page))
+    }
+}
+
+/// Reports a run that finishes on the third poll.
+#[derive(Default)]
+struct StatusHandler {
+    polls: AtomicUsize,
+}
+
+#[async_trait]
+impl CommandHandler for StatusHandler {
+    async fn handle(
+        &self,
+        _ctx: &SessionContext,
+        payload: Value,
+    ) -> Result<RouterResponse, RouterError> {
+        let poll = self.polls.fetch_add(1, Ordering::SeqCst);
+        let state = if poll < 2 { "running" } else { "completed" };
+        Ok(RouterResponse::ok(json!({
+            "run_id": payload["run_id"],
+            "repo_id": "repo",
+            "state": state,
+            "files_changed": 4,
+            "files_planned": poll * 2,
+            "chunks_embedded": poll * 10,
+        })))
+    }
+}
+
+/// Answers a search with more hits than one 4 KiB STDIO frame holds.
+struct SearchHandler;
+
+#[async_trait]
+impl CommandHandler for SearchHandler {
+    async fn handle(
+        &self,
+        _ctx: &SessionContext,
+        payload: Value,
+    ) -> Result<RouterResponse, RouterError> {
+        let hits: Vec<Value> = (0..200)
+            .map(|n| json!({ "repo_id": payload["repo_id"], "key": format!("src/module_{n}.rs") }))
+            .collect();
+        Ok(RouterResponse::ok(json!({ "hits": hits })))
+    }
+}
+
+fn router(limit: Option<RateLimit>) -> SharedRouter {
+    let mut router = HandlerRouter::new();
+    router
+        .register("numbers.list", Arc::new(NumbersHandler))
+        .register("ingest.status", Arc::new(StatusHandler::default()))
+        .register("search.query", Arc::new(SearchHandler));
+    if let Some(limit) = limit {
+        router.limit_rate(limit);
+    }
+    Arc::new(router)
+}
+
+fn http_adapter(router: SharedRouter) -> HttpAdapter {
+    HttpAdapter::bind(
+        HttpConfig {
+            host: "127.0.0.1".into(),
+            port: 9443,
+            tls_required: true,
+            allowed_principals: vec!["alice".into()],
+            token_secret: "client-http".into(),
+            require_csrf: true,
+        },
+        router,
+    )
+    .unwrap()
+}
+
+fn http_client(router: SharedRouter) -> Client<HttpTransport> {
+    let adapter = http_adapter(router);
+    let session = adapter
+        .issue_session_token("alice", &[])
+        .expect("token issuance works");
+    Client::new(HttpTransport::new(Arc::new(adapter), &session))
+}
+
+fn stdio_client(router: SharedRouter) -> Client<StdioTransport> {
+    let adapter = StdioAdapter::bind(
+        StdioConfig {
+            max_frame_length: 4096,
+            allowed_principals: vec!["alice".into()],
+            token_secret: "client-stdio".into(),
+        },
+        router,
+    )
+    .unwrap();
+    let session = adapter
+        .issue_session_token("alice")
+        .expect("token issuance works");
+    Client::new(StdioTransport::new(Arc::new(adapter), session))
+}
+
+fn uds_adapter(router: SharedRouter) -> Arc<UdsAdapter> {
+    Arc::new(
+        UdsAdapter::bind(
+            UdsConfig {
+                socket_path: "/tmp/client.sock".into(),
+                allowed_principals: vec!["alice".into()],
+                allowed_uids: vec![1000],
+                token_secret: "client-uds".into(),
+            },
+            router,
+        )
+        .unwrap(),
+    )
+}
+
+fn peer(uid: u32) -> PeerCredentials {
+    PeerCredentials {
+        uid,
+        pid: 42,
+        process_name: "client-test".into(),
+    }
+}
+
+fn uds_client(router: SharedRouter) -> Client<UdsTransport> {
+    let adapter = uds_adapter(router);
+    let session = adapter
+        .issue_session_token("alice", &[])
+        .expect("token issuance works");
+    Client::new(UdsTransport::connect(adapter, peer(1000), &session).expect("peer accepted"))
+}
+
+async fn collect_numbers<T: Transport>(client: &Client<T>) -> Vec<u64> {
+    let mut pages = client.pages("numbers.list", json!({}));
+    let mut numbers = Vec::new();
+    while let Some(reply) = pages.next().await {
+        let reply = reply.expect("page");
+        assert_eq!(reply.page.as_ref().map(|page| page.page_size), Some(2));
+        numbers.extend(
+            reply.payload["numbers"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|n| n.as_u64().unwrap()),
+        );
+    }
+    numbers
+}
+
+#[tokio::test]
+async fn every_transport_walks_pages() {
+    let expected = vec![0, 1, 2, 3, 4];
+    assert_eq!(collect_numbers(&http_client(router(None))).await, expected);
+    assert_eq!(collect_numbers(&stdio_client(router(None))).await, expected);
+    assert_eq!(collect_numbers(&uds_client(router(None))).await, expected);
+}
+
+#[tokio::test]
+async fn whoami_reports_the_session_on_every_transport() {
+    let http = http_client(router(None))
+        .whoami()
+        .await
+        .expect("http whoami");
+    let stdio = stdio_client(router(None))
+        .whoami()
+        .await
+        .expect("stdio whoami");
+    let uds = uds_client(router(None)).whoami().await.expect("uds whoami");
+    for info in [&http, &stdio, &uds] {
+        assert_eq!(info.principal, "alice");
+        assert!(info.token_id.is_some());
+        assert!(info.expires_at.is_some());
+    }
+    assert!(http
+        .peer
+        .as_deref()
+        .is_some_and(|peer| peer.starts_with("http://127.0.0.1:9443")));
+    assert_eq!(stdio.peer.as_deref(), Some("stdio"));
+    assert_eq!(uds.peer.as_deref(), Some("uds://client-test"));
+}
+
+#[tokio::test]
+async fn stdio_clients_reassemble_streamed_responses() {
+    let client = stdio_client(router(None));
+    let reply = client
+        .search("repo", "parse", 500)
+        .next()
+        .await
+        .expect("one page")
+        .expect("search");
+    let hits = reply.payload["hits"].as_array().expect("hits");
+    assert_eq!(hits.len(), 200);
+    assert_eq!(hits[199]["key"], json!("src/module_199.rs"));
+    assert!(serde_json::to_vec(&reply.payload).unwrap().len() > 4096);
+}
+
+#[tokio::test]
+async fn http_clients_can_authenticate_with_an_api_key() {
+    let keys = Arc::new(ApiKeyStore::in_memory());
+    let issued = keys
+        .issue("alice", &["ingest".into()], "ci")
+        .expect("key issuance works");
+    let adapter = http_adapter(router(None)).with_api_keys(keys);
+    let client = Client::new(HttpTransport::with_api_key(Arc::new(adapter), issued.key));
+    let info = client.whoami().await.expect("api key whoami");
+    assert_eq!(info.principal, "alice");
+    assert_eq!(info.capabilities, vec!["ingest".to_string()]);
+    assert_eq!(info.token_id, None);
+}
+
+#[tokio::test]
+async fn throttled_calls_retry_after_the_hint() {
+    let limit = RateLimit {
+        burst: 1,
+        per_second: 50.0,
+    };
+    let client = http_client(router(Some(limit)));
+    client.call("numbers.list", json!({})).await.expect("first");
+    // The second call is throttled for about 20ms, then retried.
+    client
+        .call("numbers.list", json!({}))
+        .await
+        .expect("retried");
+
+    let client = stdio_client(router(Some(limit))).with_retry(RetryPolicy {
+        max_retries: 0,
+        ..RetryPolicy::default()
+    });
+    client.call("numbers.list", json!({})).await.expect("first");
+    let err = client
+        .call("numbers.list", json!({}))
+        .await
+        .expect_err("retries disabled");
+    assert!(
+        matches!(err, ClientError::Throttled { retry_after } if retry_after <= Duration::from_millis(20))
+    );
+    assert_eq!(err.status_code(), Some(429));
+}
+
+#[tokio::test]
+async fn watch_run_follows_a_run_until_it_finishes() {
+    let client = uds_client(router(None));
+    let mut watcher = client.watch_run("run-1");
+    let mut states = Vec::new();
+    while let Some(status) = watcher.next().await {
+        let status = status.expect("status");
+        assert_eq!(status.run_id, "run-1");
+        states.push(status.state);
+    }
+    assert_eq!(states, ["running", "running", "completed"]);
+}
+
+#[tokio::test]
+async fn adapter_and_command_failures_are_typed() {
+    let client = http_client(router(None));
+    let err = client
+        .call("missing", json!({}))
+        .await
+        .expect_err("unknown command");
+    assert!(matches!(
+        err,
+        ClientError::Transport {
+            status_code: 404,
+            kind: "router",
+            ..
+        }
+    ));
+
+    let adapter = uds_adapter(router(None));
+    let session = adapter
+        .issue_session_token("alice", &[])
+        .expect("token issuance works");
+    let err = UdsTransport::connect(adapter, peer(7), &session)
+        .err()
+        .expect("unknown uid rejected");
+    assert_eq!(err.status_code(), Some(401));
+}
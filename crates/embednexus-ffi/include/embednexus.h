@@ -0,0 +1,39 @@
+/*
+ * C ABI of the embedded EmbedNexus runtime (crates/embednexus-ffi).
+ *
+ * Every string passed in is NUL-terminated UTF-8. Every string returned
+ * must be released with embednexus_string_free.
+ */
+#ifndef EMBEDNEXUS_H
+#define EMBEDNEXUS_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct EmbedNexusRuntime EmbedNexusRuntime;
+
+/*
+ * Open the runtime described by a .toml or .json config file. Returns NULL
+ * on failure and, when error is not NULL, stores a message in *error.
+ */
+EmbedNexusRuntime *embednexus_runtime_open(const char *config_path, char **error);
+
+/*
+ * Run a request {"command": ..., "payload": ...} and return the JSON reply.
+ * Safe to call from several threads at once. Returns NULL only when
+ * runtime is NULL.
+ */
+char *embednexus_runtime_dispatch(const EmbedNexusRuntime *runtime, const char *request);
+
+/* Release a string returned by this library. NULL is ignored. */
+void embednexus_string_free(char *text);
+
+/* Close a runtime once no dispatch on it is in progress. NULL is ignored. */
+void embednexus_runtime_close(EmbedNexusRuntime *runtime);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* EMBEDNEXUS_H */
@@ -0,0 +1,209 @@
+//! C ABI for embedding the runtime in-process.
+//!
+//! Editors and other non-Rust hosts load this library instead of spawning
+//! the runtime as a subprocess. The surface is deliberately small and
+//! string-based; `include/embednexus.h` declares it:
+//!
+//! - [`embednexus_runtime_open`] loads a [`RuntimeConfig`] file (`.toml` or
+//!   `.json`) and opens an [`EmbeddedRuntime`] over its state directory.
+//! - [`embednexus_runtime_dispatch`] takes a JSON request
+//!   `{ "command": ..., "payload": ... }` and returns a JSON reply shaped
+//!   like the STDIO transport's: `{ "status": "ok", "status_code",
+//!   "payload", "page"? }` on success, `{ "status": "error" | "throttled",
+//!   "status_code", "error", "retry_after_ms"? }` on failure.
+//! - [`embednexus_string_free`] releases every string the library returns.
+//! - [`embednexus_runtime_close`] shuts the runtime down.
+//!
+//! A runtime may be used from several host threads at once. Ingest runs
+//! keep going on the runtime's own worker threads between calls.
+
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+use std::ptr;
+
+use runtime_commands::{EmbeddedRuntime, RuntimeConfig};
+use runtime_router::{RouterError, RouterResponse};
+use serde_json::{json, Value};
+
+/// An open runtime; opaque to C.
+pub struct EmbedNexusRuntime {
+    tokio: tokio::runtime::Runtime,
+    inner: EmbeddedRuntime,
+}
+
+impl EmbedNexusRuntime {
+    fn open(config_path: &Path) -> Result<Self, String> {
+        let config = RuntimeConfig::load(config_path).map_err(|err| err.to_string())?;
+        let tokio = tokio::runtime::Builder::new_multi_thread()
+            .enable_time()
+            .build()
+            .map_err(|err| err.to_string())?;
+        let inner = {
+            let _guard = tokio.enter();
+            EmbeddedRuntime::open(config).map_err(|err| err.to_string())?
+        };
+        Ok(Self { tokio, inner })
+    }
+
+    fn dispatch(&self, request: &str) -> Value {
+        let request: Value = match serde_json::from_str(request) {
+            Ok(request) => request,
+            Err(err) => return error_reply(400, &format!("invalid request: {err}")),
+        };
+        let Some(command) = request.get("command").and_then(Value::as_str) else {
+            return error_reply(400, "invalid request: missing string field `command`");
+        };
+        let payload = request.get("payload").cloned().unwrap_or(Value::Null);
+        reply(self.tokio.block_on(self.inner.dispatch(command, payload)))
+    }
+}
+
+fn reply(result: Result<RouterResponse, RouterError>) -> Value {
+    match result {
+        Ok(response) => {
+            let status = if (200..=299).contains(&response.status_code) {
+                "ok"
+            } else {
+                "error"
+            };
+            let mut body = json!({
+                "status": status,
+                "status_code": response.status_code,
+                "payload": response.payload,
+            });
+            if let Some(page) = &response.page {
+                body["page"] = json!(page);
+            }
+            body
+        }
+        Err(err) => {
+            let mut body = error_reply(err.status_code(), &err.to_string());
+            if let Some(wait) = err.retry_after() {
+                body["status"] = json!("throttled");
+                body["retry_after_ms"] = json!(u64::try_from(wait.as_millis()).unwrap_or(u64::MAX));
+            }
+            body
+        }
+    }
+}
+
+fn error_reply(status_code: u16, message: &str) -> Value {
+    json!({
+        "status": "error",
+        "status_code": status_code,
+        "error": message,
+    })
+}
+
+/// Hand `text` to C. Interior NULs cannot occur in serialized JSON; in
+/// error messages they are dropped rather than truncating the string.
+fn into_c_string(text: String) -> *mut c_char {
+    CString::new(text)
+        .unwrap_or_else(|err| {
+            let mut bytes = err.into_vec();
+            bytes.retain(|&byte| byte != 0);
+            CString::new(bytes).expect("NULs removed")
+        })
+        .into_raw()
+}
+
+/// # Safety
+///
+/// `text` must be null or point to a NUL-terminated string.
+unsafe fn read_c_str<'a>(text: *const c_char, what: &str) -> Result<&'a str, String> {
+    if text.is_null() {
+        return Err(format!("{what} is null"));
+    }
+    CStr::from_ptr(text)
+        .to_str()
+        .map_err(|err| format!("{what} is not UTF-8: {err}"))
+}
+
+/// Open the runtime described by the config file at `config_path`.
+///
+/// Returns null on failure; when `error` is not null, `*error` then holds
+/// a message to release with [`embednexus_string_free`].
+///
+/// # Safety
+///
+/// `config_path` must point to a NUL-terminated string, and `error` must
+/// be null or valid for a pointer write.
+#[no_mangle]
+pub unsafe extern "C" fn embednexus_runtime_open(
+    config_path: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut EmbedNexusRuntime {
+    let opened = read_c_str(config_path, "config path").and_then(|path| {
+        catch_unwind(|| EmbedNexusRuntime::open(Path::new(path)))
+            .unwrap_or_else(|_| Err("runtime panicked while opening".into()))
+    });
+    match opened {
+        Ok(runtime) => {
+            if !error.is_null() {
+                *error = ptr::null_mut();
+            }
+            Box::into_raw(Box::new(runtime))
+        }
+        Err(message) => {
+            if !error.is_null() {
+                *error = into_c_string(message);
+            }
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Run one JSON request and return the JSON reply, which the caller
+/// releases with [`embednexus_string_free`]. Failures, including a
+/// malformed request, are reported in the reply; the result is null only
+/// when `runtime` is.
+///
+/// # Safety
+///
+/// `runtime` must be null or a pointer returned by
+/// [`embednexus_runtime_open`] that has not been closed, and `request`
+/// must point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn embednexus_runtime_dispatch(
+    runtime: *const EmbedNexusRuntime,
+    request: *const c_char,
+) -> *mut c_char {
+    let Some(runtime) = runtime.as_ref() else {
+        return ptr::null_mut();
+    };
+    let body = match read_c_str(request, "request") {
+        Ok(request) => catch_unwind(AssertUnwindSafe(|| runtime.dispatch(request)))
+            .unwrap_or_else(|_| error_reply(500, "internal error: runtime panicked")),
+        Err(message) => error_reply(400, &format!("invalid request: {message}")),
+    };
+    into_c_string(body.to_string())
+}
+
+/// Release a string returned by this library. Null is ignored.
+///
+/// # Safety
+///
+/// `text` must be null or a string returned by this library that has not
+/// been released yet.
+#[no_mangle]
+pub unsafe extern "C" fn embednexus_string_free(text: *mut c_char) {
+    if !text.is_null() {
+        drop(CString::from_raw(text));
+    }
+}
+
+/// Close a runtime; ingest runs still in progress stop with it. Null is
+/// ignored.
+///
+/// # Safety
+///
+/// `runtime` must be null or a pointer returned by
+/// [`embednexus_runtime_open`] that has not been closed, with no dispatch
+/// on it still in progress.
+#[no_mangle]
+pub unsafe extern "C" fn embednexus_runtime_close(runtime: *mut EmbedNexusRuntime) {
+    if !runtime.is_null() {
+        drop(Box::from_raw(runtime));
+    }
+}
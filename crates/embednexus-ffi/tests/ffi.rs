@@ -0,0 +1,76 @@
+use std::ffi::{c_char, CStr, CString};
+use std::fs;
+use std::ptr;
+
+use embednexus_ffi::{
+    embednexus_runtime_close, embednexus_runtime_dispatch, embednexus_runtime_open,
+    embednexus_string_free, EmbedNexusRuntime,
+};
+use serde_json::{json, Value};
+
+unsafe fn take_string(text: *mut c_char) -> String {
+    assert!(!text.is_null());
+    let owned = CStr::from_ptr(text).to_str().expect("utf-8").to_owned();
+    embednexus_string_free(text);
+    owned
+}
+
+unsafe fn call(runtime: *const EmbedNexusRuntime, request: &str) -> Value {
+    let request = CString::new(request).unwrap();
+    let reply = take_string(embednexus_runtime_dispatch(runtime, request.as_ptr()));
+    serde_json::from_str(&reply).expect("reply is JSON")
+}
+
+#[test]
+fn c_hosts_open_a_runtime_from_a_config_file_and_dispatch_json() {
+    let dir = tempfile::tempdir().expect("state dir");
+    let root = tempfile::tempdir().expect("workspace root");
+    let config = dir.path().join("runtime.toml");
+    fs::write(&config, "state_dir = \"state\"\ndimensions = 8\n").unwrap();
+    let config = CString::new(config.to_str().unwrap()).unwrap();
+
+    unsafe {
+        let mut error = ptr::null_mut();
+        let runtime = embednexus_runtime_open(config.as_ptr(), &mut error);
+        assert!(!runtime.is_null());
+        assert!(error.is_null());
+
+        let request = json!({
+            "command": "workspace.register",
+            "payload": { "repo_id": "repo", "root_path": root.path() },
+        });
+        let reply = call(runtime, &request.to_string());
+        assert_eq!(reply["status"], "ok", "{reply}");
+        assert_eq!(reply["status_code"], 200);
+
+        let reply = call(runtime, r#"{ "command": "no.such.command" }"#);
+        assert_eq!(reply["status"], "error");
+        assert_eq!(reply["status_code"], 404);
+
+        let reply = call(runtime, "not json");
+        assert_eq!(reply["status_code"], 400);
+        let reply = call(runtime, r#"{ "payload": {} }"#);
+        assert_eq!(reply["status_code"], 400);
+
+        embednexus_runtime_close(runtime);
+    }
+    assert!(dir.path().join("state").is_dir());
+}
+
+#[test]
+fn open_failures_return_null_with_a_message() {
+    let missing = CString::new("/nonexistent/embednexus/runtime.toml").unwrap();
+    unsafe {
+        let mut error = ptr::null_mut();
+        let runtime = embednexus_runtime_open(missing.as_ptr(), &mut error);
+        assert!(runtime.is_null());
+        assert!(take_string(error).contains("runtime.toml"));
+
+        // Callers that do not want the message may pass null.
+        assert!(embednexus_runtime_open(missing.as_ptr(), ptr::null_mut()).is_null());
+        assert!(embednexus_runtime_open(ptr::null(), ptr::null_mut()).is_null());
+        assert!(embednexus_runtime_dispatch(ptr::null(), missing.as_ptr()).is_null());
+        embednexus_runtime_close(ptr::null_mut());
+        embednexus_string_free(ptr::null_mut());
+    }
+}
@@ -0,0 +1,160 @@
+//! Python bindings for the ingestion pipeline and the embedded runtime.
+//!
+//! The `embednexus` module exposes each ingestion stage as a function over
+//! plain Python values (`scan_workspace`, `plan_chunks`,
+//! `sanitize_chunks`, `embed_chunks`) and a `Runtime` class that runs the
+//! router commands in-process against a state directory. Values cross the
+//! boundary as JSON, so they arrive as dicts and lists.
+
+mod stages;
+
+use std::path::PathBuf;
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use runtime_commands::{EmbeddedRuntime, RuntimeConfig};
+use serde_json::Value;
+
+use crate::stages::StageError;
+
+create_exception!(
+    embednexus,
+    EmbedNexusError,
+    PyException,
+    "Raised when a stage or runtime command fails."
+);
+
+impl From<StageError> for PyErr {
+    fn from(err: StageError) -> Self {
+        match err {
+            StageError::Input(_) => PyValueError::new_err(err.to_string()),
+            other => EmbedNexusError::new_err(other.to_string()),
+        }
+    }
+}
+
+fn to_py(py: Python<'_>, value: &Value) -> PyResult<PyObject> {
+    let text =
+        serde_json::to_string(value).map_err(|err| PyValueError::new_err(err.to_string()))?;
+    Ok(py
+        .import_bound("json")?
+        .call_method1("loads", (text,))?
+        .unbind())
+}
+
+fn from_py(value: &Bound<'_, PyAny>) -> PyResult<Value> {
+    let text: String = value
+        .py()
+        .import_bound("json")?
+        .call_method1("dumps", (value,))?
+        .extract()?;
+    serde_json::from_str(&text).map_err(|err| PyValueError::new_err(err.to_string()))
+}
+
+/// Enumerate the files under `root` as workspace `repo_id`; with
+/// `state_dir`, only the files changed since the previous scan.
+#[pyfunction]
+#[pyo3(signature = (repo_id, root, state_dir = None))]
+fn scan_workspace(
+    py: Python<'_>,
+    repo_id: &str,
+    root: PathBuf,
+    state_dir: Option<PathBuf>,
+) -> PyResult<PyObject> {
+    let workspace = py.allow_threads(|| stages::scan(repo_id, &root, state_dir.as_deref()))?;
+    to_py(py, &workspace)
+}
+
+/// Split a workspace returned by `scan_workspace` into chunks.
+#[pyfunction]
+fn plan_chunks(py: Python<'_>, workspace: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+    let workspace = from_py(workspace)?;
+    let chunks = py.allow_threads(|| stages::plan(workspace))?;
+    to_py(py, &chunks)
+}
+
+/// Redact chunks returned by `plan_chunks`.
+#[pyfunction]
+fn sanitize_chunks(py: Python<'_>, chunks: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+    let chunks = from_py(chunks)?;
+    let sanitized = py.allow_threads(|| stages::sanitize(chunks))?;
+    to_py(py, &sanitized)
+}
+
+/// Embed chunks returned by `sanitize_chunks` with the hash encoder.
+#[pyfunction]
+#[pyo3(signature = (chunks, encoder_id = "local-hash", dimensions = 384))]
+fn embed_chunks(
+    py: Python<'_>,
+    chunks: &Bound<'_, PyAny>,
+    encoder_id: &str,
+    dimensions: usize,
+) -> PyResult<PyObject> {
+    let chunks = from_py(chunks)?;
+    let batch = py.allow_threads(|| stages::embed(chunks, encoder_id, dimensions))?;
+    to_py(py, &batch)
+}
+
+/// The runtime commands over a state directory, run in this process.
+#[pyclass(module = "embednexus")]
+struct Runtime {
+    tokio: tokio::runtime::Runtime,
+    inner: EmbeddedRuntime,
+}
+
+#[pymethods]
+impl Runtime {
+    #[new]
+    #[pyo3(signature = (state_dir, dimensions = None))]
+    fn new(state_dir: PathBuf, dimensions: Option<usize>) -> PyResult<Self> {
+        let mut config = RuntimeConfig::new(state_dir);
+        if let Some(dimensions) = dimensions {
+            config.dimensions = dimensions;
+        }
+        let tokio = tokio::runtime::Builder::new_multi_thread()
+            .enable_time()
+            .build()
+            .map_err(|err| EmbedNexusError::new_err(err.to_string()))?;
+        // Opening spawns nothing, but keep it on the runtime like every
+        // later call.
+        let _guard = tokio.enter();
+        let inner = EmbeddedRuntime::open(config)
+            .map_err(|err| EmbedNexusError::new_err(err.to_string()))?;
+        Ok(Self { tokio, inner })
+    }
+
+    /// Run `command` with `payload` and return the reply payload. Failures
+    /// raise `EmbedNexusError` with the message and status code as args.
+    #[pyo3(signature = (command, payload = None))]
+    fn call(
+        &self,
+        py: Python<'_>,
+        command: &str,
+        payload: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<PyObject> {
+        let payload = payload.map(from_py).transpose()?.unwrap_or(Value::Null);
+        let reply = py.allow_threads(|| self.tokio.block_on(self.inner.dispatch(command, payload)));
+        match reply {
+            Ok(response) => to_py(py, &response.payload),
+            Err(err) => Err(EmbedNexusError::new_err((
+                err.to_string(),
+                err.status_code(),
+            ))),
+        }
+    }
+}
+
+#[pymodule]
+fn embednexus(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add(
+        "EmbedNexusError",
+        m.py().get_type_bound::<EmbedNexusError>(),
+    )?;
+    m.add_function(wrap_pyfunction!(scan_workspace, m)?)?;
+    m.add_function(wrap_pyfunction!(plan_chunks, m)?)?;
+    m.add_function(wrap_pyfunction!(sanitize_chunks, m)?)?;
+    m.add_function(wrap_pyfunction!(embed_chunks, m)?)?;
+    m.add_class::<Runtime>()?;
+    Ok(())
+}
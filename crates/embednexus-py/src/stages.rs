@@ -0,0 +1,171 @@
+//! The ingestion stages over JSON values, in the shapes the Python
+//! functions take and return. Each stage's output is the next one's input:
+//! a scanned workspace, its planned chunks, the sanitized chunks and the
+//! embedded batch.
+
+use std::path::Path;
+
+use ingestion_embedding::{EmbeddingConfig, EmbeddingError, EmbeddingGenerator};
+use ingestion_planning::{
+    ChunkPlan, ChunkPlanner, PlannedChunk, PlannerConfig, PlanningError, RetryPolicy,
+};
+use ingestion_sanitization::{SanitizationConfig, SanitizationError, SanitizedChunk, Sanitizer};
+use ingestion_workspace::{
+    EnumeratorConfig, RepoType, WorkspaceDescriptor, WorkspaceEnumerator, WorkspaceError,
+    WorkspaceIndex, WorkspaceRecord,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub(crate) enum StageError {
+    #[error("invalid input: {0}")]
+    Input(#[from] serde_json::Error),
+    #[error("workspace error: {0}")]
+    Workspace(#[from] WorkspaceError),
+    #[error("planning error: {0}")]
+    Planning(#[from] PlanningError),
+    #[error("sanitization error: {0}")]
+    Sanitization(#[from] SanitizationError),
+    #[error("embedding error: {0}")]
+    Embedding(#[from] EmbeddingError),
+}
+
+/// A planned chunk with its payload, as [`plan`] returns it.
+#[derive(Debug, Serialize, Deserialize)]
+struct Chunk {
+    plan_id: String,
+    repo_id: String,
+    chunker_config: String,
+    source_span: String,
+    hash: String,
+    payload: String,
+}
+
+/// Enumerate the files under `root` as workspace `repo_id`, honouring its
+/// ignore files. With `state_dir`, the scan is incremental against the
+/// file index kept there and only returns files added or modified since the
+/// previous scan; without it every file is returned.
+pub(crate) fn scan(
+    repo_id: &str,
+    root: &Path,
+    state_dir: Option<&Path>,
+) -> Result<Value, StageError> {
+    let record = WorkspaceRecord {
+        repo_id: repo_id.to_string(),
+        root_path: root.to_path_buf(),
+        repo_type: RepoType::Git,
+        manifest_cursor: None,
+        ignore_rules: Vec::new(),
+        archives: Vec::new(),
+        latency_windows: Vec::new(),
+        files: Vec::new(),
+        tenant_id: None,
+        generated_code: None,
+    };
+    let enumerator = WorkspaceEnumerator::new(EnumeratorConfig::default());
+    let scan = match state_dir {
+        Some(state_dir) => {
+            let previous = WorkspaceIndex::load(state_dir, repo_id)?;
+            let scan = enumerator.scan_changes(&record, &previous)?;
+            // The stage has no downstream of its own, so the index advances
+            // as soon as the scan is handed back.
+            scan.commit(state_dir)?;
+            scan
+        }
+        None => enumerator.scan_changes(&record, &WorkspaceIndex::new(repo_id))?,
+    };
+    Ok(serde_json::to_value(scan.descriptor)?)
+}
+
+/// Split a scanned workspace into chunks with the default profiles.
+pub(crate) fn plan(workspace: Value) -> Result<Value, StageError> {
+    let descriptor: WorkspaceDescriptor = serde_json::from_value(workspace)?;
+    let planner = ChunkPlanner::new(PlannerConfig::default().with_default_profiles());
+    let chunks: Vec<Chunk> = planner
+        .plan_chunks(&descriptor)?
+        .into_iter()
+        .map(|chunk| {
+            let payload = chunk.payload().to_string();
+            let plan = chunk.into_plan();
+            Chunk {
+                plan_id: plan.plan_id,
+                repo_id: plan.repo_id,
+                chunker_config: plan.chunker_config,
+                source_span: plan.source_span,
+                hash: plan.hash,
+                payload,
+            }
+        })
+        .collect();
+    Ok(serde_json::to_value(chunks)?)
+}
+
+/// Redact planned chunks with the default rules.
+pub(crate) fn sanitize(chunks: Value) -> Result<Value, StageError> {
+    let chunks: Vec<Chunk> = serde_json::from_value(chunks)?;
+    let planned: Vec<PlannedChunk> = chunks
+        .into_iter()
+        .map(|chunk| {
+            PlannedChunk::new(
+                ChunkPlan {
+                    plan_id: chunk.plan_id,
+                    repo_id: chunk.repo_id,
+                    chunker_config: chunk.chunker_config,
+                    source_span: chunk.source_span,
+                    hash: chunk.hash,
+                    retry_policy: RetryPolicy::default(),
+                    tenant_id: None,
+                },
+                chunk.payload,
+            )
+        })
+        .collect();
+    let batch = Sanitizer::new(SanitizationConfig::default())?.apply_batch(&planned)?;
+    Ok(serde_json::to_value(batch.chunks)?)
+}
+
+/// Embed sanitized chunks with the built-in hash encoder.
+pub(crate) fn embed(
+    chunks: Value,
+    encoder_id: &str,
+    dimensions: usize,
+) -> Result<Value, StageError> {
+    let chunks: Vec<SanitizedChunk> = serde_json::from_value(chunks)?;
+    let batch = EmbeddingGenerator::new(EmbeddingConfig::new(encoder_id.to_string(), dimensions))
+        .encode(&chunks)?;
+    Ok(json!({
+        "encoder_id": batch.encoder_id,
+        "vectors": batch.vectors,
+        "chunks": batch.chunks,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stages_chain_from_scan_to_vectors() {
+        let root = tempfile::tempdir().expect("workspace root");
+        std::fs::write(
+            root.path().join("lib.rs"),
+            "fn connect() { let token = \"hunter2\"; }\n",
+        )
+        .unwrap();
+
+        let workspace = scan("repo", root.path(), None).expect("scan");
+        assert_eq!(workspace["files"][0]["path"], "lib.rs");
+        let chunks = plan(workspace).expect("plan");
+        assert_eq!(chunks[0]["plan_id"], "repo::lib.rs::0");
+        let sanitized = sanitize(chunks).expect("sanitize");
+        let payload = sanitized[0]["scrubbed_payload"].as_str().unwrap();
+        assert!(!payload.contains("hunter2"), "{payload}");
+        let batch = embed(sanitized, "hash", 4).expect("embed");
+        assert_eq!(batch["vectors"][0].as_array().map(Vec::len), Some(4));
+        assert_eq!(batch["chunks"][0]["plan_id"], "repo::lib.rs::0");
+
+        assert!(matches!(plan(json!({})), Err(StageError::Input(_))));
+    }
+}
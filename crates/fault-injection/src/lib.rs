@@ -0,0 +1,342 @@
+//! Deterministic failure points for pipeline and transport tests.
+//!
+//! A [`Scenario`] file lists the faults a test run should see; a
+//! [`FaultInjector`] built from it decides, hit by hit, whether a failure
+//! point trips. Because rules count hits instead of sampling, a scenario
+//! reproduces the same failures on every run:
+//!
+//! ```toml
+//! name = "flaky-ledger"
+//!
+//! [[fault]]
+//! point = "queue_offline"   # queue_offline | store_io | token_expiry | frame_corruption
+//! sequences = [2]           # queue_offline only: sequences it applies to
+//! after = 1                 # let this many matching hits through first
+//! times = 1                 # trip this many times; omit to trip forever
+//!
+//! [[fault]]
+//! point = "store_io"
+//! operations = ["upsert"]   # store_io only: Store methods it applies to
+//! message = "disk full"
+//! ```
+//!
+//! The failure points are wired in as follows:
+//!
+//! - [`FaultyQueue`] wraps a [`ManifestQueue`](ingestion_manifest::ManifestQueue)
+//!   and fails deliveries at [`FaultPoint::QueueOffline`].
+//! - [`FaultyStore`] wraps a [`Store`](storage_vector::Store) and fails
+//!   calls with [`StoreError::Io`](storage_vector::StoreError::Io) at
+//!   [`FaultPoint::StoreIo`].
+//! - [`FaultInjector::token_ttl`] shortens a session TTL to zero at
+//!   [`FaultPoint::TokenExpiry`]; pass it to an adapter's
+//!   `with_session_ttl` to issue already-expired tokens.
+//! - [`FaultInjector::corrupt_frame`] flips the final byte of an encoded
+//!   frame at [`FaultPoint::FrameCorruption`], which for STDIO frames lands
+//!   in the checksum.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+mod queue;
+mod store;
+
+pub use queue::FaultyQueue;
+pub use store::FaultyStore;
+
+/// Where a fault can be injected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FaultPoint {
+    /// The downstream manifest queue refuses a delivery.
+    QueueOffline,
+    /// A vector store call fails with an I/O error.
+    StoreIo,
+    /// A session token is issued already expired.
+    TokenExpiry,
+    /// An encoded transport frame is damaged in flight.
+    FrameCorruption,
+}
+
+impl FaultPoint {
+    fn default_message(self) -> &'static str {
+        match self {
+            Self::QueueOffline => "injected queue outage",
+            Self::StoreIo => "injected store I/O error",
+            Self::TokenExpiry => "injected token expiry",
+            Self::FrameCorruption => "injected frame corruption",
+        }
+    }
+}
+
+impl fmt::Display for FaultPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::QueueOffline => "queue_offline",
+            Self::StoreIo => "store_io",
+            Self::TokenExpiry => "token_expiry",
+            Self::FrameCorruption => "frame_corruption",
+        })
+    }
+}
+
+/// One `[[fault]]` entry of a scenario.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FaultRule {
+    pub point: FaultPoint,
+    /// Matching hits let through before the rule starts tripping.
+    #[serde(default)]
+    pub after: u64,
+    /// Times the rule trips; `None` trips on every later hit.
+    #[serde(default)]
+    pub times: Option<u64>,
+    /// Replay sequences the rule applies to; empty matches all. Only
+    /// meaningful for [`FaultPoint::QueueOffline`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sequences: Vec<u64>,
+    /// Store methods (`upsert`, `get`, `delete`, ...) the rule applies to;
+    /// empty matches all. Only meaningful for [`FaultPoint::StoreIo`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub operations: Vec<String>,
+    /// Error text reported when the rule trips.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl FaultRule {
+    fn matches(&self, point: FaultPoint, site: Site<'_>) -> bool {
+        self.point == point
+            && (self.sequences.is_empty()
+                || site
+                    .sequence
+                    .is_some_and(|seq| self.sequences.contains(&seq)))
+            && (self.operations.is_empty()
+                || site
+                    .operation
+                    .is_some_and(|op| self.operations.iter().any(|o| o == op)))
+    }
+
+    fn trips_on(&self, hit: u64) -> bool {
+        hit >= self.after && self.times.map_or(true, |times| hit - self.after < times)
+    }
+}
+
+/// Errors raised while loading a scenario.
+#[derive(Debug, Error)]
+pub enum ScenarioError {
+    #[error("reading {path}: {source}")]
+    Read { path: PathBuf, source: io::Error },
+    #[error("invalid scenario: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("invalid fault {index}: {reason}")]
+    Invalid { index: usize, reason: String },
+}
+
+/// A named list of faults, loaded from TOML.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, rename = "fault")]
+    pub faults: Vec<FaultRule>,
+}
+
+impl Scenario {
+    /// Load and validate the scenario file at `path`.
+    pub fn load(path: &Path) -> Result<Self, ScenarioError> {
+        let source = fs::read_to_string(path).map_err(|source| ScenarioError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_toml_str(&source)
+    }
+
+    /// Parse and validate a scenario.
+    pub fn from_toml_str(source: &str) -> Result<Self, ScenarioError> {
+        let scenario: Self = toml::from_str(source)?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    /// Reject filters on points that never report them, which would make
+    /// the rule silently never trip.
+    pub fn validate(&self) -> Result<(), ScenarioError> {
+        for (index, rule) in self.faults.iter().enumerate() {
+            let reason = if !rule.sequences.is_empty() && rule.point != FaultPoint::QueueOffline {
+                format!("`sequences` does not apply to {}", rule.point)
+            } else if !rule.operations.is_empty() && rule.point != FaultPoint::StoreIo {
+                format!("`operations` does not apply to {}", rule.point)
+            } else {
+                continue;
+            };
+            return Err(ScenarioError::Invalid { index, reason });
+        }
+        Ok(())
+    }
+
+    /// Rules for `point`, in file order.
+    pub fn rules(&self, point: FaultPoint) -> impl Iterator<Item = &FaultRule> {
+        self.faults.iter().filter(move |rule| rule.point == point)
+    }
+}
+
+/// What a failure point reports about the call being checked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Site<'a> {
+    pub sequence: Option<u64>,
+    pub operation: Option<&'a str>,
+}
+
+impl<'a> Site<'a> {
+    /// A delivery of the replay entry with `sequence`.
+    pub const fn sequence(sequence: u64) -> Self {
+        Self {
+            sequence: Some(sequence),
+            operation: None,
+        }
+    }
+
+    /// A call to the store method named `operation`.
+    pub const fn operation(operation: &'a str) -> Self {
+        Self {
+            sequence: None,
+            operation: Some(operation),
+        }
+    }
+}
+
+/// A fault that tripped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InjectedFault {
+    pub point: FaultPoint,
+    /// Index of the tripping rule in [`Scenario::faults`].
+    pub rule: usize,
+    /// Zero-based count of the rule's matching hits before this one.
+    pub hit: u64,
+    pub message: String,
+}
+
+impl fmt::Display for InjectedFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for InjectedFault {}
+
+#[derive(Debug, Default)]
+struct InjectorState {
+    /// Matching hits seen per rule.
+    hits: Vec<u64>,
+    injected: Vec<InjectedFault>,
+}
+
+/// Decides when the failure points of a [`Scenario`] trip. Shared between
+/// the wrappers of one test run through an `Arc`.
+#[derive(Debug, Default)]
+pub struct FaultInjector {
+    scenario: Scenario,
+    state: Mutex<InjectorState>,
+}
+
+impl FaultInjector {
+    pub fn new(scenario: Scenario) -> Self {
+        let state = InjectorState {
+            hits: vec![0; scenario.faults.len()],
+            injected: Vec::new(),
+        };
+        Self {
+            scenario,
+            state: Mutex::new(state),
+        }
+    }
+
+    /// An injector that never trips.
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    pub fn scenario(&self) -> &Scenario {
+        &self.scenario
+    }
+
+    /// Record a hit on `point` and return the fault to raise, if any. Every
+    /// matching rule counts the hit; the first one in file order that trips
+    /// supplies the fault.
+    pub fn trip(&self, point: FaultPoint, site: Site<'_>) -> Option<InjectedFault> {
+        let mut state = self.state();
+        let mut fault = None;
+        for (index, rule) in self.scenario.faults.iter().enumerate() {
+            if !rule.matches(point, site) {
+                continue;
+            }
+            let hit = state.hits[index];
+            state.hits[index] += 1;
+            if fault.is_none() && rule.trips_on(hit) {
+                fault = Some(InjectedFault {
+                    point,
+                    rule: index,
+                    hit,
+                    message: describe(rule, site),
+                });
+            }
+        }
+        if let Some(fault) = &fault {
+            state.injected.push(fault.clone());
+        }
+        fault
+    }
+
+    /// Every fault raised so far, in order.
+    pub fn injected(&self) -> Vec<InjectedFault> {
+        self.state().injected.clone()
+    }
+
+    /// `ttl`, or zero when [`FaultPoint::TokenExpiry`] trips.
+    pub fn token_ttl(&self, ttl: Duration) -> Duration {
+        match self.trip(FaultPoint::TokenExpiry, Site::default()) {
+            Some(_) => Duration::ZERO,
+            None => ttl,
+        }
+    }
+
+    /// Flip the final byte of `frame` when [`FaultPoint::FrameCorruption`]
+    /// trips, returning whether it did. Empty frames are left alone.
+    pub fn corrupt_frame(&self, frame: &mut [u8]) -> bool {
+        let Some(last) = frame.last_mut() else {
+            return false;
+        };
+        if self
+            .trip(FaultPoint::FrameCorruption, Site::default())
+            .is_none()
+        {
+            return false;
+        }
+        *last ^= 0xff;
+        true
+    }
+
+    fn state(&self) -> MutexGuard<'_, InjectorState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+fn describe(rule: &FaultRule, site: Site<'_>) -> String {
+    let message = rule
+        .message
+        .as_deref()
+        .unwrap_or_else(|| rule.point.default_message());
+    match (site.sequence, site.operation) {
+        (Some(sequence), _) => format!("{message} for sequence {sequence}"),
+        (None, Some(operation)) => format!("{message} during {operation}"),
+        (None, None) => message.to_string(),
+    }
+}
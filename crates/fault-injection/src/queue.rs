@@ -0,0 +1,37 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use ingestion_manifest::ManifestQueue;
+use storage_ledger::ReplayEntry;
+
+use crate::{FaultInjector, FaultPoint, Site};
+
+/// Manifest queue that refuses deliveries at [`FaultPoint::QueueOffline`]
+/// before handing them to `inner`.
+#[derive(Debug)]
+pub struct FaultyQueue<Q: ?Sized> {
+    inner: Arc<Q>,
+    injector: Arc<FaultInjector>,
+}
+
+impl<Q: ManifestQueue + ?Sized> FaultyQueue<Q> {
+    pub fn new(inner: Arc<Q>, injector: Arc<FaultInjector>) -> Self {
+        Self { inner, injector }
+    }
+
+    pub fn inner(&self) -> &Arc<Q> {
+        &self.inner
+    }
+}
+
+impl<Q: ManifestQueue + ?Sized> ManifestQueue for FaultyQueue<Q> {
+    fn send(&self, entry: ReplayEntry) -> Result<()> {
+        if let Some(fault) = self
+            .injector
+            .trip(FaultPoint::QueueOffline, Site::sequence(entry.sequence))
+        {
+            return Err(fault.into());
+        }
+        self.inner.send(entry)
+    }
+}
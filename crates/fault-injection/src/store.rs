@@ -0,0 +1,111 @@
+use std::sync::Arc;
+
+use storage_ledger::ReplayEntry;
+use storage_vector::{IntegrityReport, KeyPage, ReplayRecord, ReplayStats, Store, StoreError};
+
+use crate::{FaultInjector, FaultPoint, Site};
+
+/// Store whose calls fail with [`StoreError::Io`] at [`FaultPoint::StoreIo`];
+/// the site operation is the [`Store`] method name. Calls that are not
+/// failed go to `inner` unchanged, so its own batching and verification
+/// still apply.
+pub struct FaultyStore<S> {
+    inner: S,
+    injector: Arc<FaultInjector>,
+}
+
+impl<S: Store> FaultyStore<S> {
+    pub fn new(inner: S, injector: Arc<FaultInjector>) -> Self {
+        Self { inner, injector }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn check(&self, operation: &str) -> Result<(), StoreError> {
+        match self
+            .injector
+            .trip(FaultPoint::StoreIo, Site::operation(operation))
+        {
+            Some(fault) => Err(StoreError::Io(fault.message)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<S: Store> Store for FaultyStore<S> {
+    fn upsert(&self, repo_id: &str, key: &str, payload: &[u8]) -> Result<ReplayEntry, StoreError> {
+        self.check("upsert")?;
+        self.inner.upsert(repo_id, key, payload)
+    }
+
+    fn get(&self, repo_id: &str, key: &str) -> Result<Option<Vec<u8>>, StoreError> {
+        self.check("get")?;
+        self.inner.get(repo_id, key)
+    }
+
+    fn replay<I: IntoIterator<Item = ReplayEntry>>(
+        &self,
+        entries: I,
+    ) -> Result<ReplayStats, StoreError> {
+        self.check("replay")?;
+        self.inner.replay(entries)
+    }
+
+    fn replay_records<I: IntoIterator<Item = ReplayRecord>>(
+        &self,
+        records: I,
+    ) -> Result<ReplayStats, StoreError> {
+        self.check("replay_records")?;
+        self.inner.replay_records(records)
+    }
+
+    fn delete(&self, repo_id: &str, key: &str) -> Result<ReplayEntry, StoreError> {
+        self.check("delete")?;
+        self.inner.delete(repo_id, key)
+    }
+
+    fn list_repos(&self) -> Result<Vec<String>, StoreError> {
+        self.check("list_repos")?;
+        self.inner.list_repos()
+    }
+
+    fn list_keys(
+        &self,
+        repo_id: &str,
+        prefix: &str,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<KeyPage, StoreError> {
+        self.check("list_keys")?;
+        self.inner.list_keys(repo_id, prefix, cursor, limit)
+    }
+
+    fn verify(&self, repo_id: &str) -> Result<IntegrityReport, StoreError> {
+        self.check("verify")?;
+        self.inner.verify(repo_id)
+    }
+
+    fn upsert_batch<K: AsRef<str>, P: AsRef<[u8]>>(
+        &self,
+        repo_id: &str,
+        records: &[(K, P)],
+    ) -> Result<Vec<ReplayEntry>, StoreError> {
+        self.check("upsert_batch")?;
+        self.inner.upsert_batch(repo_id, records)
+    }
+
+    fn get_many<K: AsRef<str>>(
+        &self,
+        repo_id: &str,
+        keys: &[K],
+    ) -> Result<Vec<Option<Vec<u8>>>, StoreError> {
+        self.check("get_many")?;
+        self.inner.get_many(repo_id, keys)
+    }
+}
@@ -0,0 +1,185 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use fault_injection::{
+    FaultInjector, FaultPoint, FaultyQueue, FaultyStore, Scenario, ScenarioError, Site,
+};
+use ingestion_manifest::ManifestQueue;
+use runtime_router::{RecordingRouter, SharedRouter};
+use runtime_transport_stdio::{
+    StdioAdapter, StdioConfig, StdioFrame, TransportError, DEFAULT_SESSION_TTL,
+};
+use serde_json::json;
+use storage_ledger::ReplayEntry;
+use storage_vector::{Store, StoreError, VectorStore};
+
+fn injector(source: &str) -> Arc<FaultInjector> {
+    Arc::new(FaultInjector::new(Scenario::from_toml_str(source).unwrap()))
+}
+
+fn entry(sequence: u64) -> ReplayEntry {
+    ReplayEntry {
+        sequence,
+        repo_id: "repo-alpha".into(),
+        delayed_ms: 0,
+        payload_checksum_before: "aaa".into(),
+        payload_checksum_after: "bbb".into(),
+        status: "buffered".into(),
+        sealed_payload: None,
+        signature: None,
+        tenant_id: None,
+    }
+}
+
+#[derive(Debug, Default)]
+struct CollectingQueue {
+    sent: Mutex<Vec<u64>>,
+}
+
+impl ManifestQueue for CollectingQueue {
+    fn send(&self, entry: ReplayEntry) -> anyhow::Result<()> {
+        self.sent.lock().unwrap().push(entry.sequence);
+        Ok(())
+    }
+}
+
+#[test]
+fn fixture_scenario_loads() {
+    let path = Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../../tests/fixtures/ingestion/fault-scenarios/queue-flap.toml"
+    ));
+    let scenario = Scenario::load(path).unwrap();
+    assert_eq!(scenario.name, "queue-flap");
+    let rule = scenario.rules(FaultPoint::QueueOffline).next().unwrap();
+    assert_eq!((rule.sequences.as_slice(), rule.times), (&[3][..], Some(2)));
+}
+
+#[test]
+fn filters_on_the_wrong_point_are_rejected() {
+    let err = Scenario::from_toml_str(
+        r#"
+        [[fault]]
+        point = "store_io"
+        sequences = [1]
+        "#,
+    )
+    .unwrap_err();
+    assert!(matches!(err, ScenarioError::Invalid { index: 0, .. }));
+    assert!(Scenario::from_toml_str("[[fault]]\npoint = \"disk_full\"").is_err());
+}
+
+#[test]
+fn rules_trip_inside_their_hit_window_only() {
+    let injector = injector(
+        r#"
+        [[fault]]
+        point = "token_expiry"
+        after = 1
+        times = 2
+        "#,
+    );
+    let tripped: Vec<bool> = (0..5)
+        .map(|_| {
+            injector
+                .trip(FaultPoint::TokenExpiry, Site::default())
+                .is_some()
+        })
+        .collect();
+    assert_eq!(tripped, [false, true, true, false, false]);
+    let hits: Vec<u64> = injector.injected().iter().map(|f| f.hit).collect();
+    assert_eq!(hits, [1, 2]);
+}
+
+#[test]
+fn queue_faults_target_sequences_and_leave_the_rest_to_the_inner_queue() {
+    let injector = injector(
+        r#"
+        [[fault]]
+        point = "queue_offline"
+        sequences = [2]
+        times = 1
+        "#,
+    );
+    let queue = FaultyQueue::new(Arc::new(CollectingQueue::default()), injector);
+
+    queue.send(entry(1)).unwrap();
+    let err = queue.send(entry(2)).unwrap_err();
+    assert_eq!(err.to_string(), "injected queue outage for sequence 2");
+    queue.send(entry(2)).unwrap();
+    assert_eq!(*queue.inner().sent.lock().unwrap(), [1, 2]);
+}
+
+#[test]
+fn store_faults_surface_as_io_errors_for_the_named_operations() {
+    let injector = injector(
+        r#"
+        [[fault]]
+        point = "store_io"
+        operations = ["upsert"]
+        times = 1
+        message = "disk full"
+        "#,
+    );
+    let store = FaultyStore::new(VectorStore::new(), Arc::clone(&injector));
+
+    let err = store.upsert("repo", "a", b"one").unwrap_err();
+    assert!(matches!(&err, StoreError::Io(msg) if msg == "disk full during upsert"));
+    assert_eq!(store.get("repo", "a").unwrap(), None);
+    store.upsert("repo", "a", b"one").unwrap();
+    assert_eq!(
+        store.get("repo", "a").unwrap().as_deref(),
+        Some(&b"one"[..])
+    );
+    assert_eq!(injector.injected().len(), 1);
+}
+
+fn stdio_adapter() -> StdioAdapter {
+    let config = StdioConfig {
+        max_frame_length: 4096,
+        allowed_principals: vec!["alice".into()],
+        token_secret: "fault-injection".into(),
+    };
+    StdioAdapter::bind(config, Arc::new(RecordingRouter::default()) as SharedRouter).unwrap()
+}
+
+#[tokio::test]
+async fn expired_tokens_and_corrupted_frames_are_rejected_by_stdio() {
+    let injector = injector(
+        r#"
+        [[fault]]
+        point = "token_expiry"
+        times = 1
+
+        [[fault]]
+        point = "frame_corruption"
+        times = 1
+        "#,
+    );
+
+    let ttl = injector.token_ttl(DEFAULT_SESSION_TTL);
+    assert_eq!(ttl, Duration::ZERO);
+    let adapter = stdio_adapter().with_session_ttl(ttl);
+    let token = adapter.issue_session_token("alice").unwrap();
+    let err = adapter.codec().encode(&json!({}), &token).unwrap_err();
+    assert!(matches!(err, TransportError::Unauthorized(msg) if msg.contains("expired")));
+
+    let adapter = stdio_adapter().with_session_ttl(injector.token_ttl(DEFAULT_SESSION_TTL));
+    let token = adapter.issue_session_token("alice").unwrap();
+    let frame = adapter
+        .codec()
+        .encode(&json!({ "command": "status" }), &token)
+        .unwrap();
+    let mut payload = frame.payload.clone();
+    assert!(injector.corrupt_frame(&mut payload));
+    let err = adapter
+        .dispatch_frame(StdioFrame { payload })
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("checksum mismatch"));
+
+    let mut payload = frame.payload;
+    assert!(!injector.corrupt_frame(&mut payload));
+    adapter.codec().decode(&StdioFrame { payload }).unwrap();
+}
@@ -0,0 +1,55 @@
+//! `EmbeddingGenerator::encode` over a batch of sanitized chunks at common
+//! model dimensions.
+//!
+//! Run with `cargo bench -p ingestion-embedding --bench embedding_encode`.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use ingestion_embedding::{EmbeddingConfig, EmbeddingGenerator};
+use ingestion_planning::{ChunkPlan, PlannedChunk, RetryPolicy};
+use ingestion_sanitization::{SanitizationConfig, SanitizedChunk, Sanitizer};
+
+const BATCH: usize = 256;
+const DIMENSIONS: [usize; 3] = [384, 768, 1536];
+
+fn chunks() -> Vec<SanitizedChunk> {
+    let sanitizer = Sanitizer::new(SanitizationConfig::default()).expect("sanitizer");
+    let body = "fn handler(request: Request) -> Response { route(request) }\n".repeat(64);
+    (0..BATCH)
+        .map(|index| {
+            let plan = ChunkPlan {
+                plan_id: format!("bench::src/lib.rs::{index}"),
+                repo_id: "bench".into(),
+                chunker_config: "bytes=4096;max=256".into(),
+                source_span: "src/lib.rs:0-4096".into(),
+                hash: format!("hash-{index}"),
+                retry_policy: RetryPolicy::default(),
+                tenant_id: None,
+            };
+            sanitizer
+                .apply(&PlannedChunk::new(plan, format!("// {index}\n{body}")))
+                .expect("sanitize")
+        })
+        .collect()
+}
+
+fn embedding_encode(c: &mut Criterion) {
+    let chunks = chunks();
+    let mut group = c.benchmark_group("embedding_encode");
+    group.throughput(Throughput::Elements(BATCH as u64));
+    for dimensions in DIMENSIONS {
+        let generator = EmbeddingGenerator::new(EmbeddingConfig::new("bench".into(), dimensions));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(dimensions),
+            &chunks,
+            |b, chunks| {
+                b.iter(|| generator.encode(black_box(chunks)).expect("encode"));
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, embedding_encode);
+criterion_main!(benches);
@@ -0,0 +1,33 @@
+//! Backend-agnostic embedding interface.
+
+use async_trait::async_trait;
+use ingestion_sanitization::SanitizedChunk;
+
+use crate::{EmbeddingBatch, EmbeddingError};
+
+/// Embedding backend that turns sanitized chunks into vectors.
+///
+/// [`EmbeddingGenerator`](crate::EmbeddingGenerator) is the default
+/// hash-derived implementation; model-backed encoders implement this trait so
+/// callers can swap them in behind an `Arc<dyn Embedder>`.
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    /// Identifier recorded on every batch this backend produces.
+    fn encoder_id(&self) -> &str;
+
+    /// Length of every vector this backend produces.
+    fn dimensions(&self) -> usize;
+
+    /// Encode `chunks` into one batch, one vector per chunk in input order.
+    async fn encode_batch(
+        &self,
+        chunks: &[SanitizedChunk],
+    ) -> Result<EmbeddingBatch, EmbeddingError>;
+
+    /// Cheap liveness check used by
+    /// [`FailoverEmbedder`](crate::FailoverEmbedder) before sending traffic
+    /// back to a backend it marked unhealthy.
+    async fn health_check(&self) -> Result<(), EmbeddingError> {
+        Ok(())
+    }
+}
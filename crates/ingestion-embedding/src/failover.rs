@@ -0,0 +1,249 @@
+//! Ordered failover across embedding backends.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use ingestion_sanitization::SanitizedChunk;
+use tokio::time::Instant;
+
+use crate::{Embedder, EmbeddingBatch, EmbeddingError};
+
+/// When a backend is taken out of rotation and how often it is re-probed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FailoverConfig {
+    /// Consecutive failures after which a backend is marked unhealthy.
+    pub failure_threshold: u32,
+    /// Minimum wait before an unhealthy backend is probed again.
+    pub probe_interval: Duration,
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 3,
+            probe_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Point-in-time view of one backend behind a [`FailoverEmbedder`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackendHealth {
+    pub encoder_id: String,
+    pub healthy: bool,
+    pub consecutive_failures: u32,
+    /// Batches this backend produced.
+    pub batches_served: u64,
+    /// Failed encode attempts and probes.
+    pub failures: u64,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug)]
+struct BackendState {
+    health: BackendHealth,
+    /// Set while unhealthy: earliest time the next probe may run.
+    next_probe: Option<Instant>,
+}
+
+/// [`Embedder`] that tries backends in priority order, e.g. a remote API
+/// followed by a local model.
+///
+/// A backend that fails `failure_threshold` times in a row is skipped until
+/// `probe_interval` has passed and its [`Embedder::health_check`] succeeds.
+/// If every backend is out of rotation they are all tried anyway. The
+/// `encoder_id` of each returned batch names the backend that served it.
+pub struct FailoverEmbedder {
+    backends: Vec<Arc<dyn Embedder>>,
+    config: FailoverConfig,
+    state: Mutex<Vec<BackendState>>,
+}
+
+impl std::fmt::Debug for FailoverEmbedder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FailoverEmbedder")
+            .field("config", &self.config)
+            .field("health", &self.health())
+            .finish_non_exhaustive()
+    }
+}
+
+impl FailoverEmbedder {
+    /// Backends are listed from most to least preferred and must agree on
+    /// dimensions.
+    pub fn new(
+        backends: Vec<Arc<dyn Embedder>>,
+        config: FailoverConfig,
+    ) -> Result<Self, EmbeddingError> {
+        let Some(primary) = backends.first() else {
+            return Err(EmbeddingError::Backend(
+                "failover needs at least one backend".into(),
+            ));
+        };
+        if let Some(other) = backends
+            .iter()
+            .find(|backend| backend.dimensions() != primary.dimensions())
+        {
+            return Err(EmbeddingError::Backend(format!(
+                "backend {} produces {} dimensions, primary {} produces {}",
+                other.encoder_id(),
+                other.dimensions(),
+                primary.encoder_id(),
+                primary.dimensions()
+            )));
+        }
+        let state = backends
+            .iter()
+            .map(|backend| BackendState {
+                health: BackendHealth {
+                    encoder_id: backend.encoder_id().to_string(),
+                    healthy: true,
+                    consecutive_failures: 0,
+                    batches_served: 0,
+                    failures: 0,
+                    last_error: None,
+                },
+                next_probe: None,
+            })
+            .collect();
+        Ok(Self {
+            backends,
+            config,
+            state: Mutex::new(state),
+        })
+    }
+
+    /// Health and serving counters for every backend, in priority order.
+    #[must_use]
+    pub fn health(&self) -> Vec<BackendHealth> {
+        self.lock()
+            .iter()
+            .map(|state| state.health.clone())
+            .collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<BackendState>> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Backends to try, in order: healthy ones plus unhealthy ones due for a
+    /// probe (flagged `true`), or every backend when none qualify.
+    fn candidates(&self) -> Vec<(usize, bool)> {
+        let now = Instant::now();
+        let state = self.lock();
+        let ready: Vec<(usize, bool)> = state
+            .iter()
+            .enumerate()
+            .filter_map(|(index, backend)| match backend.next_probe {
+                None => Some((index, false)),
+                Some(due) if due <= now => Some((index, true)),
+                Some(_) => None,
+            })
+            .collect();
+        if ready.is_empty() {
+            (0..state.len()).map(|index| (index, false)).collect()
+        } else {
+            ready
+        }
+    }
+
+    fn record_success(&self, index: usize) {
+        let mut state = self.lock();
+        let backend = &mut state[index];
+        if !backend.health.healthy {
+            tracing::info!(encoder_id = %backend.health.encoder_id, "embedding backend recovered");
+        }
+        backend.health.healthy = true;
+        backend.health.consecutive_failures = 0;
+        backend.health.batches_served += 1;
+        backend.next_probe = None;
+    }
+
+    fn record_failure(&self, index: usize, err: &EmbeddingError) {
+        let mut state = self.lock();
+        let backend = &mut state[index];
+        backend.health.failures += 1;
+        backend.health.consecutive_failures += 1;
+        backend.health.last_error = Some(err.to_string());
+        if backend.health.consecutive_failures >= self.config.failure_threshold {
+            if backend.health.healthy {
+                tracing::warn!(
+                    encoder_id = %backend.health.encoder_id,
+                    failures = backend.health.consecutive_failures,
+                    "embedding backend marked unhealthy"
+                );
+            }
+            backend.health.healthy = false;
+            backend.next_probe = Some(Instant::now() + self.config.probe_interval);
+        }
+    }
+}
+
+#[async_trait]
+impl Embedder for FailoverEmbedder {
+    /// Identifier of the primary backend.
+    fn encoder_id(&self) -> &str {
+        self.backends[0].encoder_id()
+    }
+
+    fn dimensions(&self) -> usize {
+        self.backends[0].dimensions()
+    }
+
+    async fn encode_batch(
+        &self,
+        chunks: &[SanitizedChunk],
+    ) -> Result<EmbeddingBatch, EmbeddingError> {
+        let mut errors = Vec::new();
+        for (index, probe) in self.candidates() {
+            let backend = &self.backends[index];
+            if probe {
+                if let Err(err) = backend.health_check().await {
+                    self.record_failure(index, &err);
+                    errors.push(format!("{}: {err}", backend.encoder_id()));
+                    continue;
+                }
+            }
+            match backend.encode_batch(chunks).await {
+                Ok(batch) => {
+                    self.record_success(index);
+                    if index > 0 {
+                        tracing::warn!(
+                            encoder_id = %backend.encoder_id(),
+                            primary = %self.encoder_id(),
+                            chunks = chunks.len(),
+                            "embedding batch served by fallback backend"
+                        );
+                    } else {
+                        tracing::debug!(
+                            encoder_id = %backend.encoder_id(),
+                            chunks = chunks.len(),
+                            "embedding batch served"
+                        );
+                    }
+                    return Ok(batch);
+                }
+                Err(err) => {
+                    self.record_failure(index, &err);
+                    errors.push(format!("{}: {err}", backend.encoder_id()));
+                }
+            }
+        }
+        Err(EmbeddingError::Backend(format!(
+            "all embedding backends failed: {}",
+            errors.join("; ")
+        )))
+    }
+
+    /// Healthy while any backend is healthy.
+    async fn health_check(&self) -> Result<(), EmbeddingError> {
+        if self.health().iter().any(|backend| backend.healthy) {
+            Ok(())
+        } else {
+            Err(EmbeddingError::Backend(
+                "no healthy embedding backend".into(),
+            ))
+        }
+    }
+}
@@ -1,13 +1,78 @@
 //! Embedding orchestration placeholders.
 
+use std::path::PathBuf;
+
+use async_trait::async_trait;
 use blake3::Hasher;
+use half::f16;
 use ingestion_sanitization::SanitizedChunk;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+pub mod embedder;
+pub mod failover;
+#[cfg(feature = "candle")]
+pub mod local;
+pub mod remote;
+
+pub use embedder::Embedder;
+pub use failover::{BackendHealth, FailoverConfig, FailoverEmbedder};
+#[cfg(feature = "candle")]
+pub use local::LocalEmbedder;
+#[cfg(feature = "remote")]
+pub use remote::ReqwestTransport;
+pub use remote::{
+    ApiKey, HttpReply, HttpRequest, HttpTransport, RemoteApi, RemoteConfig, RemoteEmbedder,
+};
+
+/// Compute device for model-backed embedders.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EmbeddingDevice {
+    #[default]
+    Cpu,
+    /// CUDA GPU by ordinal.
+    Cuda(usize),
+    /// Apple Metal GPU by ordinal.
+    Metal(usize),
+}
+
+/// How token states are reduced to one vector by model-backed embedders.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Pooling {
+    /// Average over non-padding tokens.
+    #[default]
+    Mean,
+    /// Hidden state of the first (`[CLS]`) token.
+    Cls,
+}
+
+/// Precision of emitted vector components.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VectorDtype {
+    #[default]
+    F32,
+    /// Components rounded to half precision so indexes storing `f16` lose
+    /// nothing further on conversion.
+    F16,
+}
+
 #[derive(Debug, Clone)]
 pub struct EmbeddingConfig {
     pub encoder_id: String,
     pub dimensions: usize,
+    /// Directory holding `config.json`, `tokenizer.json`, and
+    /// `model.safetensors` for local model backends.
+    pub model_path: Option<PathBuf>,
+    pub device: EmbeddingDevice,
+    /// Chunks sent through the model per forward pass.
+    pub batch_size: usize,
+    /// Tokens kept per chunk; longer chunks are truncated.
+    pub max_sequence_length: usize,
+    pub pooling: Pooling,
+    /// Scale every vector to unit L2 norm, as cosine and dot-product indexes
+    /// expect.
+    pub normalize: bool,
+    pub dtype: VectorDtype,
 }
 
 impl EmbeddingConfig {
@@ -16,6 +81,102 @@ impl EmbeddingConfig {
         Self {
             encoder_id,
             dimensions,
+            model_path: None,
+            device: EmbeddingDevice::Cpu,
+            batch_size: 32,
+            max_sequence_length: 256,
+            pooling: Pooling::Mean,
+            normalize: false,
+            dtype: VectorDtype::F32,
+        }
+    }
+
+    #[must_use]
+    pub fn with_model_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.model_path = Some(path.into());
+        self
+    }
+
+    #[must_use]
+    pub const fn with_device(mut self, device: EmbeddingDevice) -> Self {
+        self.device = device;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_max_sequence_length(mut self, tokens: usize) -> Self {
+        self.max_sequence_length = tokens;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_pooling(mut self, pooling: Pooling) -> Self {
+        self.pooling = pooling;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_normalization(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_dtype(mut self, dtype: VectorDtype) -> Self {
+        self.dtype = dtype;
+        self
+    }
+
+    /// Apply normalization and dtype rounding to backend output for `chunks`.
+    pub(crate) fn finish(
+        &self,
+        mut vectors: Vec<Vec<f32>>,
+        chunks: &[SanitizedChunk],
+    ) -> EmbeddingBatch {
+        for vector in &mut vectors {
+            if self.normalize {
+                let norm = vector.iter().map(|value| value * value).sum::<f32>().sqrt();
+                if norm > 0.0 {
+                    vector.iter_mut().for_each(|value| *value /= norm);
+                }
+            }
+            if self.dtype == VectorDtype::F16 {
+                vector
+                    .iter_mut()
+                    .for_each(|value| *value = f16::from_f32(*value).to_f32());
+            }
+        }
+        EmbeddingBatch {
+            encoder_id: self.encoder_id.clone(),
+            vectors,
+            chunks: chunks.iter().map(ChunkRef::from).collect(),
+            compression_fingerprint: batch_fingerprint(chunks),
+            dtype: self.dtype,
+        }
+    }
+}
+
+/// Provenance of one embedded chunk, linking a stored vector back to the plan
+/// and source lines it came from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ChunkRef {
+    pub plan_id: String,
+    pub source_span: String,
+    pub hash: String,
+}
+
+impl From<&SanitizedChunk> for ChunkRef {
+    fn from(chunk: &SanitizedChunk) -> Self {
+        Self {
+            plan_id: chunk.plan_id.clone(),
+            source_span: chunk.source_span.clone(),
+            hash: chunk.hash.clone(),
         }
     }
 }
@@ -24,15 +185,24 @@ impl EmbeddingConfig {
 pub struct EmbeddingBatch {
     pub encoder_id: String,
     pub vectors: Vec<Vec<f32>>,
+    /// Provenance for each entry of `vectors`, in the same order.
+    pub chunks: Vec<ChunkRef>,
     pub compression_fingerprint: String,
+    /// Precision the vector components were rounded to.
+    pub dtype: VectorDtype,
 }
 
 #[derive(Debug, Error)]
 pub enum EmbeddingError {
     #[error("embedding dimensions must be non-zero")]
     InvalidDimensions,
+    #[error("embedding backend failed: {0}")]
+    Backend(String),
+    #[error("embedding model could not be loaded: {0}")]
+    Model(String),
 }
 
+/// Default [`Embedder`] producing deterministic hash-derived vectors.
 #[derive(Debug, Clone)]
 pub struct EmbeddingGenerator {
     config: EmbeddingConfig,
@@ -48,20 +218,11 @@ impl EmbeddingGenerator {
         if self.config.dimensions == 0 {
             return Err(EmbeddingError::InvalidDimensions);
         }
-        let mut vectors = Vec::with_capacity(chunks.len());
-        let mut fingerprint_hasher = Hasher::new();
-        for chunk in chunks {
-            let vector = self.vector_for_chunk(chunk);
-            fingerprint_hasher.update(chunk.plan_id.as_bytes());
-            fingerprint_hasher.update(chunk.scrubbed_payload.as_bytes());
-            vectors.push(vector);
-        }
-        let fingerprint = fingerprint_hasher.finalize().to_hex().to_string();
-        Ok(EmbeddingBatch {
-            encoder_id: self.config.encoder_id.clone(),
-            vectors,
-            compression_fingerprint: format!("comp:{fingerprint}"),
-        })
+        let vectors = chunks
+            .iter()
+            .map(|chunk| self.vector_for_chunk(chunk))
+            .collect();
+        Ok(self.config.finish(vectors, chunks))
     }
 
     fn vector_for_chunk(&self, chunk: &SanitizedChunk) -> Vec<f32> {
@@ -82,3 +243,31 @@ impl EmbeddingGenerator {
         vector
     }
 }
+
+#[async_trait]
+impl Embedder for EmbeddingGenerator {
+    fn encoder_id(&self) -> &str {
+        &self.config.encoder_id
+    }
+
+    fn dimensions(&self) -> usize {
+        self.config.dimensions
+    }
+
+    async fn encode_batch(
+        &self,
+        chunks: &[SanitizedChunk],
+    ) -> Result<EmbeddingBatch, EmbeddingError> {
+        self.encode(chunks)
+    }
+}
+
+/// Fingerprint over the plan ids and payloads that went into a batch.
+pub(crate) fn batch_fingerprint(chunks: &[SanitizedChunk]) -> String {
+    let mut hasher = Hasher::new();
+    for chunk in chunks {
+        hasher.update(chunk.plan_id.as_bytes());
+        hasher.update(chunk.scrubbed_payload.as_bytes());
+    }
+    format!("comp:{}", hasher.finalize().to_hex())
+}
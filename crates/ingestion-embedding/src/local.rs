@@ -0,0 +1,182 @@
+//! Local sentence-transformer backend running on candle.
+
+use std::fs;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use candle_core::{Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config, DTYPE};
+use ingestion_sanitization::SanitizedChunk;
+use tokenizers::{PaddingParams, PaddingStrategy, Tokenizer, TruncationParams};
+
+use crate::{Embedder, EmbeddingBatch, EmbeddingConfig, EmbeddingDevice, EmbeddingError, Pooling};
+
+/// BERT-family sentence encoder loaded from a local model directory.
+///
+/// Chunks are tokenized with truncation at `max_sequence_length`, run through
+/// the model `batch_size` at a time, and pooled per `config.pooling`.
+pub struct LocalEmbedder {
+    config: EmbeddingConfig,
+    model: Arc<LoadedModel>,
+}
+
+struct LoadedModel {
+    bert: BertModel,
+    tokenizer: Tokenizer,
+    device: Device,
+    batch_size: usize,
+    pooling: Pooling,
+}
+
+impl std::fmt::Debug for LocalEmbedder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LocalEmbedder")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LocalEmbedder {
+    /// Load the model under `config.model_path` onto `config.device`.
+    pub fn load(config: EmbeddingConfig) -> Result<Self, EmbeddingError> {
+        if config.dimensions == 0 {
+            return Err(EmbeddingError::InvalidDimensions);
+        }
+        if config.batch_size == 0 || config.max_sequence_length == 0 {
+            return Err(EmbeddingError::Model(
+                "batch_size and max_sequence_length must be non-zero".into(),
+            ));
+        }
+        let dir = config
+            .model_path
+            .as_deref()
+            .ok_or_else(|| EmbeddingError::Model("model_path is not set".into()))?;
+        let device = match config.device {
+            EmbeddingDevice::Cpu => Ok(Device::Cpu),
+            EmbeddingDevice::Cuda(ordinal) => Device::new_cuda(ordinal),
+            EmbeddingDevice::Metal(ordinal) => Device::new_metal(ordinal),
+        }
+        .map_err(model_error)?;
+
+        let bert_config: Config = fs::read(dir.join("config.json"))
+            .map_err(model_error)
+            .and_then(|bytes| serde_json::from_slice(&bytes).map_err(model_error))?;
+        let mut tokenizer =
+            Tokenizer::from_file(dir.join("tokenizer.json")).map_err(model_error)?;
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: config.max_sequence_length,
+                ..TruncationParams::default()
+            }))
+            .map_err(model_error)?;
+        tokenizer.with_padding(Some(PaddingParams {
+            strategy: PaddingStrategy::BatchLongest,
+            ..PaddingParams::default()
+        }));
+        let weights = fs::read(dir.join("model.safetensors")).map_err(model_error)?;
+        let vb =
+            VarBuilder::from_buffered_safetensors(weights, DTYPE, &device).map_err(model_error)?;
+        let bert = BertModel::load(vb, &bert_config).map_err(model_error)?;
+        tracing::info!(
+            model = %dir.display(),
+            device = ?config.device,
+            "loaded local embedding model"
+        );
+        Ok(Self {
+            model: Arc::new(LoadedModel {
+                bert,
+                tokenizer,
+                device,
+                batch_size: config.batch_size,
+                pooling: config.pooling,
+            }),
+            config,
+        })
+    }
+}
+
+impl LoadedModel {
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        let mut vectors = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(self.batch_size) {
+            let encodings = self
+                .tokenizer
+                .encode_batch(batch.iter().map(String::as_str).collect::<Vec<_>>(), true)
+                .map_err(backend_error)?;
+            let ids = encodings
+                .iter()
+                .map(|encoding| Tensor::new(encoding.get_ids(), &self.device))
+                .collect::<Result<Vec<_>, _>>()
+                .and_then(|rows| Tensor::stack(&rows, 0))
+                .map_err(backend_error)?;
+            let mask = encodings
+                .iter()
+                .map(|encoding| Tensor::new(encoding.get_attention_mask(), &self.device))
+                .collect::<Result<Vec<_>, _>>()
+                .and_then(|rows| Tensor::stack(&rows, 0))
+                .map_err(backend_error)?;
+            let pooled = self.pool(&ids, &mask).map_err(backend_error)?;
+            vectors.extend(pooled);
+        }
+        Ok(vectors)
+    }
+
+    /// Reduce token states to one vector per sequence: the first token for
+    /// CLS pooling, otherwise the mean over non-padding tokens.
+    fn pool(&self, ids: &Tensor, mask: &Tensor) -> candle_core::Result<Vec<Vec<f32>>> {
+        let token_types = ids.zeros_like()?;
+        let hidden = self.bert.forward(ids, &token_types, Some(mask))?;
+        if self.pooling == Pooling::Cls {
+            return hidden.narrow(1, 0, 1)?.squeeze(1)?.to_vec2::<f32>();
+        }
+        let weights = mask.to_dtype(hidden.dtype())?.unsqueeze(2)?;
+        let summed = hidden.broadcast_mul(&weights)?.sum(1)?;
+        let counts = weights.sum(1)?.affine(1.0, 1e-9)?;
+        summed.broadcast_div(&counts)?.to_vec2::<f32>()
+    }
+}
+
+#[async_trait]
+impl Embedder for LocalEmbedder {
+    fn encoder_id(&self) -> &str {
+        &self.config.encoder_id
+    }
+
+    fn dimensions(&self) -> usize {
+        self.config.dimensions
+    }
+
+    async fn encode_batch(
+        &self,
+        chunks: &[SanitizedChunk],
+    ) -> Result<EmbeddingBatch, EmbeddingError> {
+        let texts: Vec<String> = chunks
+            .iter()
+            .map(|chunk| chunk.scrubbed_payload.clone())
+            .collect();
+        let model = Arc::clone(&self.model);
+        let vectors = tokio::task::spawn_blocking(move || model.embed(&texts))
+            .await
+            .map_err(backend_error)??;
+        if let Some(vector) = vectors
+            .iter()
+            .find(|vector| vector.len() != self.config.dimensions)
+        {
+            return Err(EmbeddingError::Backend(format!(
+                "model produced {} dimensions, expected {}",
+                vector.len(),
+                self.config.dimensions
+            )));
+        }
+        Ok(self.config.finish(vectors, chunks))
+    }
+}
+
+fn model_error(err: impl std::fmt::Display) -> EmbeddingError {
+    EmbeddingError::Model(err.to_string())
+}
+
+fn backend_error(err: impl std::fmt::Display) -> EmbeddingError {
+    EmbeddingError::Backend(err.to_string())
+}
@@ -0,0 +1,432 @@
+//! Remote embedding backend for OpenAI-compatible and Ollama HTTP APIs.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use ingestion_sanitization::SanitizedChunk;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{Embedder, EmbeddingBatch, EmbeddingConfig, EmbeddingError};
+
+/// Wire format spoken by the remote endpoint.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RemoteApi {
+    /// `POST {endpoint}/embeddings` with `{ model, input }`, answered with
+    /// `{ data: [{ index, embedding }] }`.
+    #[default]
+    OpenAi,
+    /// `POST {endpoint}/api/embed` with `{ model, input }`, answered with
+    /// `{ embeddings: [[..]] }`.
+    Ollama,
+}
+
+/// Secret sent as a bearer token; never printed.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ApiKey(String);
+
+impl ApiKey {
+    #[must_use]
+    pub fn new(key: impl Into<String>) -> Self {
+        Self(key.into())
+    }
+
+    /// Read the key from environment variable `var`.
+    pub fn from_env(var: &str) -> Result<Self, EmbeddingError> {
+        std::env::var(var)
+            .map(Self)
+            .map_err(|_| EmbeddingError::Backend(format!("environment variable {var} is not set")))
+    }
+
+    #[must_use]
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ApiKey(<redacted>)")
+    }
+}
+
+/// Connection settings for a [`RemoteEmbedder`].
+#[derive(Debug, Clone)]
+pub struct RemoteConfig {
+    /// Base URL, e.g. `https://api.openai.com/v1` or `http://localhost:11434`.
+    pub endpoint: String,
+    pub api: RemoteApi,
+    /// Model name sent with every request.
+    pub model: String,
+    pub api_key: Option<ApiKey>,
+    /// Per-request timeout.
+    pub timeout: Duration,
+    /// Retries after the first attempt for rate limits, server errors, and
+    /// timeouts.
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RemoteConfig {
+    #[must_use]
+    pub fn new(endpoint: impl Into<String>, api: RemoteApi, model: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            api,
+            model: model.into(),
+            api_key: None,
+            timeout: Duration::from_secs(30),
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+
+    #[must_use]
+    pub fn with_api_key(mut self, key: ApiKey) -> Self {
+        self.api_key = Some(key);
+        self
+    }
+
+    #[must_use]
+    pub const fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_retries(mut self, max_retries: u32, initial_backoff: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    fn url(&self) -> String {
+        let base = self.endpoint.trim_end_matches('/');
+        match self.api {
+            RemoteApi::OpenAi => format!("{base}/embeddings"),
+            RemoteApi::Ollama => format!("{base}/api/embed"),
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff)
+    }
+}
+
+/// JSON POST issued by the remote backend.
+///
+/// `Debug` redacts credential-bearing header values and elides the body, so
+/// requests can be logged without leaking the API key or chunk text.
+#[derive(Clone)]
+pub struct HttpRequest {
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    pub timeout: Duration,
+}
+
+/// Headers whose values never appear in [`HttpRequest`]'s `Debug` output.
+const SENSITIVE_HEADERS: &[&str] = &["authorization", "proxy-authorization", "x-api-key"];
+
+impl fmt::Debug for HttpRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let headers: Vec<(&str, &str)> = self
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                let sensitive = SENSITIVE_HEADERS
+                    .iter()
+                    .any(|header| header.eq_ignore_ascii_case(name));
+                (
+                    name.as_str(),
+                    if sensitive {
+                        "<redacted>"
+                    } else {
+                        value.as_str()
+                    },
+                )
+            })
+            .collect();
+        f.debug_struct("HttpRequest")
+            .field("url", &self.url)
+            .field("headers", &headers)
+            .field("body_len", &self.body.len())
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+/// Response as seen by the remote backend.
+#[derive(Debug, Clone)]
+pub struct HttpReply {
+    pub status: u16,
+    /// Parsed `Retry-After` header, when present.
+    pub retry_after: Option<Duration>,
+    pub body: Vec<u8>,
+}
+
+/// Minimal HTTP client the remote backend sends requests through.
+///
+/// `ReqwestTransport` (feature `remote`) is the production implementation;
+/// tests supply scripted transports.
+#[async_trait]
+pub trait HttpTransport: Send + Sync {
+    async fn post(&self, request: HttpRequest) -> Result<HttpReply, String>;
+}
+
+/// [`Embedder`] that calls a hosted or local embedding API.
+///
+/// Chunks are sent `batch_size` at a time. HTTP 429, 5xx, transport failures,
+/// and timeouts are retried with exponential backoff, honouring
+/// `Retry-After` when the server provides it; other statuses fail at once.
+pub struct RemoteEmbedder {
+    config: EmbeddingConfig,
+    remote: RemoteConfig,
+    transport: Arc<dyn HttpTransport>,
+}
+
+impl fmt::Debug for RemoteEmbedder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RemoteEmbedder")
+            .field("config", &self.config)
+            .field("remote", &self.remote)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RemoteEmbedder {
+    pub fn new(
+        config: EmbeddingConfig,
+        remote: RemoteConfig,
+        transport: Arc<dyn HttpTransport>,
+    ) -> Result<Self, EmbeddingError> {
+        if config.dimensions == 0 {
+            return Err(EmbeddingError::InvalidDimensions);
+        }
+        if config.batch_size == 0 {
+            return Err(EmbeddingError::Backend(
+                "batch_size must be non-zero".into(),
+            ));
+        }
+        Ok(Self {
+            config,
+            remote,
+            transport,
+        })
+    }
+
+    /// Remote backend using the bundled `reqwest` client.
+    #[cfg(feature = "remote")]
+    pub fn with_default_transport(
+        config: EmbeddingConfig,
+        remote: RemoteConfig,
+    ) -> Result<Self, EmbeddingError> {
+        Self::new(config, remote, Arc::new(ReqwestTransport::new()?))
+    }
+
+    async fn embed(
+        &self,
+        texts: &[&str],
+        max_retries: u32,
+    ) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        let body = serde_json::to_vec(&json!({ "model": self.remote.model, "input": texts }))
+            .map_err(|err| EmbeddingError::Backend(err.to_string()))?;
+        let mut headers = vec![("content-type".to_string(), "application/json".to_string())];
+        if let Some(key) = &self.remote.api_key {
+            headers.push((
+                "authorization".to_string(),
+                format!("Bearer {}", key.expose()),
+            ));
+        }
+        let request = HttpRequest {
+            url: self.remote.url(),
+            headers,
+            body,
+            timeout: self.remote.timeout,
+        };
+
+        let mut attempt = 0;
+        loop {
+            let outcome =
+                tokio::time::timeout(self.remote.timeout, self.transport.post(request.clone()))
+                    .await;
+            let (reason, retry_after) = match outcome {
+                Ok(Ok(reply)) if (200..300).contains(&reply.status) => {
+                    return parse_vectors(self.remote.api, &reply.body, texts.len());
+                }
+                Ok(Ok(reply)) if reply.status == 429 || reply.status >= 500 => {
+                    (format!("HTTP {}", reply.status), reply.retry_after)
+                }
+                Ok(Ok(reply)) => {
+                    return Err(EmbeddingError::Backend(format!(
+                        "{} returned HTTP {}: {}",
+                        request.url,
+                        reply.status,
+                        String::from_utf8_lossy(&reply.body)
+                    )));
+                }
+                Ok(Err(err)) => (err, None),
+                Err(_) => (format!("timed out after {:?}", self.remote.timeout), None),
+            };
+            if attempt >= max_retries {
+                return Err(EmbeddingError::Backend(format!(
+                    "{} failed after {} attempts: {reason}",
+                    request.url,
+                    attempt + 1
+                )));
+            }
+            let delay = retry_after
+                .unwrap_or_else(|| self.remote.backoff(attempt))
+                .min(self.remote.max_backoff);
+            tracing::warn!(
+                url = %request.url,
+                attempt = attempt + 1,
+                delay_ms = delay.as_millis() as u64,
+                %reason,
+                "retrying embedding request"
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[async_trait]
+impl Embedder for RemoteEmbedder {
+    fn encoder_id(&self) -> &str {
+        &self.config.encoder_id
+    }
+
+    fn dimensions(&self) -> usize {
+        self.config.dimensions
+    }
+
+    async fn encode_batch(
+        &self,
+        chunks: &[SanitizedChunk],
+    ) -> Result<EmbeddingBatch, EmbeddingError> {
+        let mut vectors = Vec::with_capacity(chunks.len());
+        for batch in chunks.chunks(self.config.batch_size) {
+            let texts: Vec<&str> = batch
+                .iter()
+                .map(|chunk| chunk.scrubbed_payload.as_str())
+                .collect();
+            vectors.extend(self.embed(&texts, self.remote.max_retries).await?);
+        }
+        if let Some(vector) = vectors
+            .iter()
+            .find(|vector| vector.len() != self.config.dimensions)
+        {
+            return Err(EmbeddingError::Backend(format!(
+                "model produced {} dimensions, expected {}",
+                vector.len(),
+                self.config.dimensions
+            )));
+        }
+        Ok(self.config.finish(vectors, chunks))
+    }
+
+    /// Embed a single probe string with no retries.
+    async fn health_check(&self) -> Result<(), EmbeddingError> {
+        self.embed(&["health-check"], 0).await.map(drop)
+    }
+}
+
+#[derive(Deserialize)]
+struct OpenAiResponse {
+    data: Vec<OpenAiEmbedding>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiEmbedding {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Deserialize)]
+struct OllamaResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+fn parse_vectors(
+    api: RemoteApi,
+    body: &[u8],
+    expected: usize,
+) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+    let invalid =
+        |err: serde_json::Error| EmbeddingError::Backend(format!("invalid response: {err}"));
+    let vectors = match api {
+        RemoteApi::OpenAi => {
+            let mut response: OpenAiResponse = serde_json::from_slice(body).map_err(invalid)?;
+            response.data.sort_by_key(|item| item.index);
+            response
+                .data
+                .into_iter()
+                .map(|item| item.embedding)
+                .collect::<Vec<_>>()
+        }
+        RemoteApi::Ollama => {
+            let response: OllamaResponse = serde_json::from_slice(body).map_err(invalid)?;
+            response.embeddings
+        }
+    };
+    if vectors.len() != expected {
+        return Err(EmbeddingError::Backend(format!(
+            "response held {} embeddings for {expected} inputs",
+            vectors.len()
+        )));
+    }
+    Ok(vectors)
+}
+
+/// [`HttpTransport`] backed by `reqwest` with rustls.
+#[cfg(feature = "remote")]
+#[derive(Debug, Clone)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "remote")]
+impl ReqwestTransport {
+    pub fn new() -> Result<Self, EmbeddingError> {
+        reqwest::Client::builder()
+            .build()
+            .map(|client| Self { client })
+            .map_err(|err| EmbeddingError::Backend(err.to_string()))
+    }
+}
+
+#[cfg(feature = "remote")]
+#[async_trait]
+impl HttpTransport for ReqwestTransport {
+    async fn post(&self, request: HttpRequest) -> Result<HttpReply, String> {
+        let mut builder = self
+            .client
+            .post(&request.url)
+            .timeout(request.timeout)
+            .body(request.body);
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        let response = builder.send().await.map_err(|err| err.to_string())?;
+        let status = response.status().as_u16();
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map(Duration::from_secs);
+        let body = response.bytes().await.map_err(|err| err.to_string())?;
+        Ok(HttpReply {
+            status,
+            retry_after,
+            body: body.to_vec(),
+        })
+    }
+}
@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use ingestion_embedding::{
+    ChunkRef, Embedder, EmbeddingBatch, EmbeddingConfig, EmbeddingError, EmbeddingGenerator,
+    VectorDtype,
+};
+use ingestion_planning::{ChunkPlan, PlannedChunk, RetryPolicy};
+use ingestion_sanitization::{SanitizationConfig, SanitizedChunk, Sanitizer};
+
+fn sanitized_chunk(index: usize, payload: &str) -> SanitizedChunk {
+    let plan = ChunkPlan {
+        plan_id: format!("repo-zeta::src/lib.rs::{index}"),
+        repo_id: "repo-zeta".into(),
+        chunker_config: "size=256".into(),
+        source_span: "src/lib.rs:1-80".into(),
+        hash: format!("hash-{index}"),
+        retry_policy: RetryPolicy::default(),
+        tenant_id: None,
+    };
+    Sanitizer::new(SanitizationConfig::default())
+        .expect("sanitizer config should compile")
+        .apply(&PlannedChunk::new(plan, payload))
+        .expect("sanitization should succeed")
+}
+
+/// Backend that embeds every chunk as its payload length, for swap tests.
+struct LengthEmbedder;
+
+#[async_trait]
+impl Embedder for LengthEmbedder {
+    fn encoder_id(&self) -> &str {
+        "length"
+    }
+
+    fn dimensions(&self) -> usize {
+        1
+    }
+
+    async fn encode_batch(
+        &self,
+        chunks: &[SanitizedChunk],
+    ) -> Result<EmbeddingBatch, EmbeddingError> {
+        Ok(EmbeddingBatch {
+            encoder_id: self.encoder_id().into(),
+            vectors: chunks
+                .iter()
+                .map(|chunk| vec![chunk.scrubbed_payload.len() as f32])
+                .collect(),
+            chunks: chunks.iter().map(ChunkRef::from).collect(),
+            compression_fingerprint: "comp:length".into(),
+            dtype: VectorDtype::F32,
+        })
+    }
+}
+
+#[tokio::test]
+async fn hash_generator_is_the_default_embedder() {
+    let generator = EmbeddingGenerator::new(EmbeddingConfig::new("encoder-h".into(), 6));
+    let chunks = vec![sanitized_chunk(0, "alpha"), sanitized_chunk(1, "beta")];
+    let sync_batch = generator.encode(&chunks).expect("encoding should succeed");
+
+    let embedder: Arc<dyn Embedder> = Arc::new(generator);
+    assert_eq!(embedder.encoder_id(), "encoder-h");
+    assert_eq!(embedder.dimensions(), 6);
+    let batch = embedder
+        .encode_batch(&chunks)
+        .await
+        .expect("encoding should succeed");
+    assert_eq!(batch.vectors, sync_batch.vectors);
+    assert_eq!(
+        batch.compression_fingerprint,
+        sync_batch.compression_fingerprint
+    );
+}
+
+#[tokio::test]
+async fn custom_backends_swap_in_behind_the_trait() {
+    let backends: Vec<Arc<dyn Embedder>> = vec![
+        Arc::new(EmbeddingGenerator::new(EmbeddingConfig::new(
+            "encoder-h".into(),
+            3,
+        ))),
+        Arc::new(LengthEmbedder),
+    ];
+    let chunks = vec![sanitized_chunk(0, "abcd")];
+    for backend in backends {
+        let batch = backend
+            .encode_batch(&chunks)
+            .await
+            .expect("encoding should succeed");
+        assert_eq!(batch.encoder_id, backend.encoder_id());
+        assert_eq!(batch.vectors.len(), 1);
+        assert_eq!(batch.vectors[0].len(), backend.dimensions());
+    }
+}
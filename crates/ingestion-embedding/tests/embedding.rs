@@ -1,6 +1,6 @@
 #![allow(unknown_lints)]
 #![allow(clippy::cloned_ref_to_slice_refs)]
-use ingestion_embedding::{EmbeddingConfig, EmbeddingGenerator};
+use ingestion_embedding::{ChunkRef, EmbeddingConfig, EmbeddingGenerator};
 use ingestion_planning::{ChunkPlan, PlannedChunk, RetryPolicy};
 use ingestion_sanitization::{SanitizationConfig, Sanitizer};
 
@@ -12,9 +12,11 @@ fn sanitized_chunk(payload: &str) -> ingestion_sanitization::SanitizedChunk {
         source_span: "src/lib.rs:1-80".into(),
         hash: "ff00ff".into(),
         retry_policy: RetryPolicy::default(),
+        tenant_id: None,
     };
     let planned = PlannedChunk::new(plan, payload);
-    let sanitizer = Sanitizer::new(SanitizationConfig::default());
+    let sanitizer =
+        Sanitizer::new(SanitizationConfig::default()).expect("sanitizer config should compile");
     sanitizer
         .apply(&planned)
         .expect("sanitization should succeed")
@@ -44,4 +46,13 @@ fn embedding_batch_contains_metadata() {
     let batch = generator.encode(&chunks).expect("encoding should succeed");
     assert_eq!(batch.vectors.len(), 3);
     assert!(batch.compression_fingerprint.starts_with("comp:"));
+    assert_eq!(batch.chunks.len(), batch.vectors.len());
+    assert_eq!(
+        batch.chunks[0],
+        ChunkRef {
+            plan_id: "repo-epsilon::src/lib.rs::0".into(),
+            source_span: "src/lib.rs:1-80".into(),
+            hash: "ff00ff".into(),
+        }
+    );
 }
@@ -0,0 +1,181 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use ingestion_embedding::{
+    Embedder, EmbeddingBatch, EmbeddingConfig, EmbeddingError, EmbeddingGenerator, FailoverConfig,
+    FailoverEmbedder,
+};
+use ingestion_planning::{ChunkPlan, PlannedChunk, RetryPolicy};
+use ingestion_sanitization::{SanitizationConfig, SanitizedChunk, Sanitizer};
+
+/// Backend whose availability the test toggles.
+struct Flaky {
+    inner: EmbeddingGenerator,
+    encoder_id: String,
+    up: AtomicBool,
+    calls: AtomicUsize,
+    probes: AtomicUsize,
+}
+
+impl Flaky {
+    fn new(encoder_id: &str, up: bool) -> Arc<Self> {
+        Arc::new(Self {
+            inner: EmbeddingGenerator::new(EmbeddingConfig::new(encoder_id.into(), 4)),
+            encoder_id: encoder_id.into(),
+            up: AtomicBool::new(up),
+            calls: AtomicUsize::new(0),
+            probes: AtomicUsize::new(0),
+        })
+    }
+
+    fn set_up(&self, up: bool) {
+        self.up.store(up, Ordering::SeqCst);
+    }
+
+    fn check(&self) -> Result<(), EmbeddingError> {
+        if self.up.load(Ordering::SeqCst) {
+            Ok(())
+        } else {
+            Err(EmbeddingError::Backend(format!(
+                "{} is down",
+                self.encoder_id
+            )))
+        }
+    }
+}
+
+#[async_trait]
+impl Embedder for Flaky {
+    fn encoder_id(&self) -> &str {
+        &self.encoder_id
+    }
+
+    fn dimensions(&self) -> usize {
+        4
+    }
+
+    async fn encode_batch(
+        &self,
+        chunks: &[SanitizedChunk],
+    ) -> Result<EmbeddingBatch, EmbeddingError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        self.check()?;
+        self.inner.encode(chunks)
+    }
+
+    async fn health_check(&self) -> Result<(), EmbeddingError> {
+        self.probes.fetch_add(1, Ordering::SeqCst);
+        self.check()
+    }
+}
+
+fn sanitized_chunk(payload: &str) -> SanitizedChunk {
+    let plan = ChunkPlan {
+        plan_id: "repo-failover::src/lib.rs::0".into(),
+        repo_id: "repo-failover".into(),
+        chunker_config: "size=256".into(),
+        source_span: "src/lib.rs:1-40".into(),
+        hash: "hash-0".into(),
+        retry_policy: RetryPolicy::default(),
+        tenant_id: None,
+    };
+    Sanitizer::new(SanitizationConfig::default())
+        .expect("sanitizer config should compile")
+        .apply(&PlannedChunk::new(plan, payload))
+        .expect("sanitization should succeed")
+}
+
+fn failover(primary: &Arc<Flaky>, fallback: &Arc<Flaky>) -> FailoverEmbedder {
+    FailoverEmbedder::new(
+        vec![
+            Arc::clone(primary) as Arc<dyn Embedder>,
+            Arc::clone(fallback) as Arc<dyn Embedder>,
+        ],
+        FailoverConfig {
+            failure_threshold: 2,
+            probe_interval: Duration::from_secs(10),
+        },
+    )
+    .expect("matching dimensions")
+}
+
+#[tokio::test(start_paused = true)]
+async fn failing_primary_is_skipped_then_probed_back() {
+    let primary = Flaky::new("remote", false);
+    let fallback = Flaky::new("local", true);
+    let embedder = failover(&primary, &fallback);
+    let chunks = [sanitized_chunk("fn main() {}")];
+
+    for _ in 0..3 {
+        let batch = embedder.encode_batch(&chunks).await.expect("fallback");
+        assert_eq!(batch.encoder_id, "local");
+    }
+    // Two failures mark the primary unhealthy; the third batch skips it.
+    assert_eq!(primary.calls.load(Ordering::SeqCst), 2);
+    let health = embedder.health();
+    assert!(!health[0].healthy);
+    assert_eq!(health[0].failures, 2);
+    assert!(health[0]
+        .last_error
+        .as_deref()
+        .is_some_and(|err| err.contains("remote is down")));
+    assert_eq!(health[1].batches_served, 3);
+
+    primary.set_up(true);
+    embedder.encode_batch(&chunks).await.expect("fallback");
+    assert_eq!(
+        primary.probes.load(Ordering::SeqCst),
+        0,
+        "probe not yet due"
+    );
+
+    tokio::time::advance(Duration::from_secs(10)).await;
+    let batch = embedder.encode_batch(&chunks).await.expect("primary");
+    assert_eq!(batch.encoder_id, "remote");
+    assert_eq!(primary.probes.load(Ordering::SeqCst), 1);
+    let health = embedder.health();
+    assert!(health[0].healthy);
+    assert_eq!(health[0].batches_served, 1);
+}
+
+#[tokio::test]
+async fn all_backends_failing_reports_every_error() {
+    let primary = Flaky::new("remote", false);
+    let fallback = Flaky::new("local", false);
+    let embedder = failover(&primary, &fallback);
+    let err = embedder
+        .encode_batch(&[sanitized_chunk("fn main() {}")])
+        .await
+        .expect_err("nothing available");
+    let EmbeddingError::Backend(detail) = err else {
+        panic!("expected backend error");
+    };
+    assert!(detail.contains("remote is down") && detail.contains("local is down"));
+
+    // With everything out of rotation, backends are still attempted.
+    embedder
+        .encode_batch(&[sanitized_chunk("fn main() {}")])
+        .await
+        .expect_err("still down");
+    assert!(embedder.health_check().await.is_err());
+    fallback.set_up(true);
+    let batch = embedder
+        .encode_batch(&[sanitized_chunk("fn main() {}")])
+        .await
+        .expect("fallback recovered");
+    assert_eq!(batch.encoder_id, "local");
+    assert!(embedder.health_check().await.is_ok());
+}
+
+#[test]
+fn backends_must_agree_on_dimensions() {
+    let wide: Arc<dyn Embedder> = Arc::new(EmbeddingGenerator::new(EmbeddingConfig::new(
+        "wide".into(),
+        8,
+    )));
+    let narrow: Arc<dyn Embedder> = Flaky::new("narrow", true);
+    assert!(FailoverEmbedder::new(vec![wide, narrow], FailoverConfig::default()).is_err());
+    assert!(FailoverEmbedder::new(Vec::new(), FailoverConfig::default()).is_err());
+}
@@ -0,0 +1,179 @@
+#![cfg(feature = "candle")]
+use std::path::Path;
+
+use candle_core::Device;
+use candle_nn::{VarBuilder, VarMap};
+use candle_transformers::models::bert::{BertModel, Config, DTYPE};
+use ingestion_embedding::{
+    Embedder, EmbeddingConfig, EmbeddingDevice, EmbeddingError, LocalEmbedder, Pooling,
+};
+use ingestion_planning::{ChunkPlan, PlannedChunk, RetryPolicy};
+use ingestion_sanitization::{SanitizationConfig, SanitizedChunk, Sanitizer};
+
+const HIDDEN: usize = 8;
+
+const BERT_CONFIG: &str = r#"{
+    "vocab_size": 16,
+    "hidden_size": 8,
+    "num_hidden_layers": 1,
+    "num_attention_heads": 2,
+    "intermediate_size": 16,
+    "hidden_act": "gelu",
+    "hidden_dropout_prob": 0.0,
+    "max_position_embeddings": 32,
+    "type_vocab_size": 2,
+    "initializer_range": 0.02,
+    "layer_norm_eps": 1e-12,
+    "pad_token_id": 0,
+    "position_embedding_type": "absolute",
+    "use_cache": false,
+    "classifier_dropout": null,
+    "model_type": "bert"
+}"#;
+
+const TOKENIZER: &str = r#"{
+    "version": "1.0",
+    "truncation": null,
+    "padding": null,
+    "added_tokens": [],
+    "normalizer": null,
+    "pre_tokenizer": { "type": "Whitespace" },
+    "post_processor": null,
+    "decoder": null,
+    "model": {
+        "type": "WordLevel",
+        "vocab": {
+            "[PAD]": 0, "[UNK]": 1, "fn": 2, "let": 3, "struct": 4,
+            "impl": 5, "main": 6, "value": 7, "return": 8
+        },
+        "unk_token": "[UNK]"
+    }
+}"#;
+
+/// Write a randomly initialised one-layer BERT so tests need no download.
+fn write_tiny_model(dir: &Path) {
+    std::fs::write(dir.join("config.json"), BERT_CONFIG).expect("write config");
+    std::fs::write(dir.join("tokenizer.json"), TOKENIZER).expect("write tokenizer");
+    let config: Config = serde_json::from_str(BERT_CONFIG).expect("bert config");
+    let varmap = VarMap::new();
+    BertModel::load(
+        VarBuilder::from_varmap(&varmap, DTYPE, &Device::Cpu),
+        &config,
+    )
+    .expect("initialise model");
+    varmap
+        .save(dir.join("model.safetensors"))
+        .expect("write weights");
+}
+
+fn sanitized_chunk(index: usize, payload: &str) -> SanitizedChunk {
+    let plan = ChunkPlan {
+        plan_id: format!("repo-local::src/main.rs::{index}"),
+        repo_id: "repo-local".into(),
+        chunker_config: "size=256".into(),
+        source_span: "src/main.rs:1-40".into(),
+        hash: format!("hash-{index}"),
+        retry_policy: RetryPolicy::default(),
+        tenant_id: None,
+    };
+    Sanitizer::new(SanitizationConfig::default())
+        .expect("sanitizer config should compile")
+        .apply(&PlannedChunk::new(plan, payload))
+        .expect("sanitization should succeed")
+}
+
+fn config(dir: &Path) -> EmbeddingConfig {
+    EmbeddingConfig::new("local-bert".into(), HIDDEN)
+        .with_model_path(dir)
+        .with_device(EmbeddingDevice::Cpu)
+}
+
+#[tokio::test]
+async fn local_model_embeds_batches_with_mean_pooling() {
+    let dir = tempfile::tempdir().expect("model dir");
+    write_tiny_model(dir.path());
+    let chunks = vec![
+        sanitized_chunk(0, "fn main"),
+        sanitized_chunk(1, "let value return value"),
+        sanitized_chunk(2, "struct impl fn"),
+    ];
+
+    let single = LocalEmbedder::load(config(dir.path()).with_batch_size(1)).expect("load model");
+    let batched = LocalEmbedder::load(config(dir.path()).with_batch_size(8)).expect("load model");
+    let one = single.encode_batch(&chunks).await.expect("encode");
+    let many = batched.encode_batch(&chunks).await.expect("encode");
+
+    assert_eq!(one.encoder_id, "local-bert");
+    assert_eq!(one.vectors.len(), 3);
+    assert!(one.vectors.iter().all(|vector| vector.len() == HIDDEN));
+    for (left, right) in one.vectors.iter().zip(&many.vectors) {
+        for (a, b) in left.iter().zip(right) {
+            assert!(
+                (a - b).abs() < 1e-4,
+                "padding must not change pooled vectors"
+            );
+        }
+    }
+    assert_ne!(one.vectors[0], one.vectors[1]);
+}
+
+#[tokio::test]
+async fn long_chunks_are_truncated_to_max_sequence_length() {
+    let dir = tempfile::tempdir().expect("model dir");
+    write_tiny_model(dir.path());
+    let embedder =
+        LocalEmbedder::load(config(dir.path()).with_max_sequence_length(2)).expect("load model");
+    let batch = embedder
+        .encode_batch(&[
+            sanitized_chunk(0, "fn main"),
+            sanitized_chunk(1, "fn main let value struct impl"),
+        ])
+        .await
+        .expect("encode");
+    assert_eq!(batch.vectors[0], batch.vectors[1]);
+}
+
+#[tokio::test]
+async fn misconfigured_models_are_rejected() {
+    assert!(matches!(
+        LocalEmbedder::load(EmbeddingConfig::new("local-bert".into(), HIDDEN)),
+        Err(EmbeddingError::Model(_))
+    ));
+
+    let dir = tempfile::tempdir().expect("model dir");
+    write_tiny_model(dir.path());
+    let wrong_dims = LocalEmbedder::load(
+        EmbeddingConfig::new("local-bert".into(), 4).with_model_path(dir.path()),
+    )
+    .expect("load model");
+    assert!(matches!(
+        wrong_dims
+            .encode_batch(&[sanitized_chunk(0, "fn main")])
+            .await,
+        Err(EmbeddingError::Backend(_))
+    ));
+}
+
+#[tokio::test]
+async fn cls_pooling_and_normalization_are_configurable() {
+    let dir = tempfile::tempdir().expect("model dir");
+    write_tiny_model(dir.path());
+    let chunks = [sanitized_chunk(0, "fn main let value")];
+    let mean = LocalEmbedder::load(config(dir.path()))
+        .expect("load model")
+        .encode_batch(&chunks)
+        .await
+        .expect("encode");
+    let cls = LocalEmbedder::load(
+        config(dir.path())
+            .with_pooling(Pooling::Cls)
+            .with_normalization(true),
+    )
+    .expect("load model")
+    .encode_batch(&chunks)
+    .await
+    .expect("encode");
+    assert_ne!(mean.vectors, cls.vectors);
+    let norm: f32 = cls.vectors[0].iter().map(|value| value * value).sum();
+    assert!((norm.sqrt() - 1.0).abs() < 1e-5);
+}
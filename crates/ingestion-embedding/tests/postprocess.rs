@@ -0,0 +1,70 @@
+use ingestion_embedding::{EmbeddingConfig, EmbeddingGenerator, VectorDtype};
+use ingestion_planning::{ChunkPlan, PlannedChunk, RetryPolicy};
+use ingestion_sanitization::{SanitizationConfig, SanitizedChunk, Sanitizer};
+
+fn sanitized_chunk(index: usize, payload: &str) -> SanitizedChunk {
+    let plan = ChunkPlan {
+        plan_id: format!("repo-eta::src/lib.rs::{index}"),
+        repo_id: "repo-eta".into(),
+        chunker_config: "size=256".into(),
+        source_span: "src/lib.rs:1-80".into(),
+        hash: format!("hash-{index}"),
+        retry_policy: RetryPolicy::default(),
+        tenant_id: None,
+    };
+    Sanitizer::new(SanitizationConfig::default())
+        .expect("sanitizer config should compile")
+        .apply(&PlannedChunk::new(plan, payload))
+        .expect("sanitization should succeed")
+}
+
+fn norm(vector: &[f32]) -> f32 {
+    vector.iter().map(|value| value * value).sum::<f32>().sqrt()
+}
+
+#[test]
+fn defaults_leave_vectors_untouched() {
+    let config = EmbeddingConfig::new("encoder-p".into(), 16);
+    let chunks = vec![sanitized_chunk(0, "alpha"), sanitized_chunk(1, "beta")];
+    let batch = EmbeddingGenerator::new(config.clone())
+        .encode(&chunks)
+        .expect("encoding should succeed");
+    assert_eq!(batch.dtype, VectorDtype::F32);
+    assert!(batch
+        .vectors
+        .iter()
+        .all(|vector| vector.iter().all(|value| (0.0..=1.0).contains(value))));
+    assert!(batch
+        .vectors
+        .iter()
+        .any(|vector| (norm(vector) - 1.0).abs() > 1e-3));
+}
+
+#[test]
+fn normalization_produces_unit_vectors() {
+    let config = EmbeddingConfig::new("encoder-p".into(), 16).with_normalization(true);
+    let batch = EmbeddingGenerator::new(config)
+        .encode(&[sanitized_chunk(0, "alpha"), sanitized_chunk(1, "beta")])
+        .expect("encoding should succeed");
+    for vector in &batch.vectors {
+        assert!((norm(vector) - 1.0).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn f16_output_is_exactly_representable_in_half_precision() {
+    let config = EmbeddingConfig::new("encoder-p".into(), 16)
+        .with_normalization(true)
+        .with_dtype(VectorDtype::F16);
+    let full = EmbeddingGenerator::new(config.clone().with_dtype(VectorDtype::F32))
+        .encode(&[sanitized_chunk(0, "alpha")])
+        .expect("encoding should succeed");
+    let half = EmbeddingGenerator::new(config)
+        .encode(&[sanitized_chunk(0, "alpha")])
+        .expect("encoding should succeed");
+    assert_eq!(half.dtype, VectorDtype::F16);
+    for (rounded, exact) in half.vectors[0].iter().zip(&full.vectors[0]) {
+        assert_eq!(half::f16::from_f32(*rounded).to_f32(), *rounded);
+        assert!((rounded - exact).abs() < 1e-3);
+    }
+}
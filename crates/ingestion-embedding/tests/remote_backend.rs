@@ -0,0 +1,221 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use ingestion_embedding::{
+    ApiKey, Embedder, EmbeddingConfig, EmbeddingError, HttpReply, HttpRequest, HttpTransport,
+    RemoteApi, RemoteConfig, RemoteEmbedder,
+};
+use ingestion_planning::{ChunkPlan, PlannedChunk, RetryPolicy};
+use ingestion_sanitization::{SanitizationConfig, SanitizedChunk, Sanitizer};
+use serde_json::{json, Value};
+
+/// Scripted reply: a response, a transport error, or a request that never answers.
+enum Step {
+    Reply(u16, Option<u64>, Value),
+    Fail(&'static str),
+    Hang,
+}
+
+#[derive(Default)]
+struct ScriptedTransport {
+    steps: Mutex<VecDeque<Step>>,
+    requests: Mutex<Vec<HttpRequest>>,
+}
+
+impl ScriptedTransport {
+    fn new(steps: Vec<Step>) -> Arc<Self> {
+        Arc::new(Self {
+            steps: Mutex::new(steps.into()),
+            requests: Mutex::default(),
+        })
+    }
+
+    fn requests(&self) -> Vec<HttpRequest> {
+        self.requests.lock().expect("requests").clone()
+    }
+}
+
+#[async_trait]
+impl HttpTransport for ScriptedTransport {
+    async fn post(&self, request: HttpRequest) -> Result<HttpReply, String> {
+        self.requests.lock().expect("requests").push(request);
+        let step = self.steps.lock().expect("steps").pop_front();
+        match step.expect("unexpected request") {
+            Step::Reply(status, retry_after, body) => Ok(HttpReply {
+                status,
+                retry_after: retry_after.map(Duration::from_secs),
+                body: serde_json::to_vec(&body).expect("body"),
+            }),
+            Step::Fail(reason) => Err(reason.into()),
+            Step::Hang => std::future::pending().await,
+        }
+    }
+}
+
+fn sanitized_chunk(index: usize, payload: &str) -> SanitizedChunk {
+    let plan = ChunkPlan {
+        plan_id: format!("repo-remote::src/lib.rs::{index}"),
+        repo_id: "repo-remote".into(),
+        chunker_config: "size=256".into(),
+        source_span: "src/lib.rs:1-40".into(),
+        hash: format!("hash-{index}"),
+        retry_policy: RetryPolicy::default(),
+        tenant_id: None,
+    };
+    Sanitizer::new(SanitizationConfig::default())
+        .expect("sanitizer config should compile")
+        .apply(&PlannedChunk::new(plan, payload))
+        .expect("sanitization should succeed")
+}
+
+fn openai_reply(vectors: &[[f32; 2]]) -> Step {
+    // Deliberately reversed to check that `index` decides the order.
+    let data: Vec<Value> = vectors
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(index, embedding)| json!({ "index": index, "embedding": embedding }))
+        .collect();
+    Step::Reply(200, None, json!({ "data": data }))
+}
+
+fn embedder(transport: Arc<ScriptedTransport>, remote: RemoteConfig) -> RemoteEmbedder {
+    RemoteEmbedder::new(
+        EmbeddingConfig::new("remote-openai".into(), 2).with_batch_size(2),
+        remote,
+        transport,
+    )
+    .expect("valid config")
+}
+
+#[tokio::test]
+async fn openai_requests_are_batched_and_authenticated() {
+    let transport = ScriptedTransport::new(vec![
+        openai_reply(&[[0.1, 0.2], [0.3, 0.4]]),
+        openai_reply(&[[0.5, 0.6]]),
+    ]);
+    let remote = RemoteConfig::new(
+        "https://api.example.test/v1/",
+        RemoteApi::OpenAi,
+        "text-embed",
+    )
+    .with_api_key(ApiKey::new("sk-test"));
+    assert!(!format!("{remote:?}").contains("sk-test"));
+    let embedder = embedder(Arc::clone(&transport), remote);
+
+    let chunks: Vec<_> = ["alpha", "beta", "gamma"]
+        .iter()
+        .enumerate()
+        .map(|(index, payload)| sanitized_chunk(index, payload))
+        .collect();
+    let batch = embedder.encode_batch(&chunks).await.expect("encode");
+    assert_eq!(
+        batch.vectors,
+        vec![vec![0.1, 0.2], vec![0.3, 0.4], vec![0.5, 0.6]]
+    );
+    assert_eq!(batch.encoder_id, "remote-openai");
+
+    let requests = transport.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].url, "https://api.example.test/v1/embeddings");
+    assert!(requests[0]
+        .headers
+        .contains(&("authorization".into(), "Bearer sk-test".into())));
+    let logged = format!("{:?}", requests[0]);
+    assert!(!logged.contains("sk-test"));
+    assert!(!logged.contains("alpha"));
+    assert!(logged.contains("application/json"));
+    let body: Value = serde_json::from_slice(&requests[0].body).expect("json body");
+    assert_eq!(
+        body,
+        json!({ "model": "text-embed", "input": ["alpha", "beta"] })
+    );
+}
+
+#[tokio::test]
+async fn ollama_responses_are_parsed() {
+    let transport = ScriptedTransport::new(vec![Step::Reply(
+        200,
+        None,
+        json!({ "embeddings": [[1.0, 0.0]] }),
+    )]);
+    let embedder = embedder(
+        Arc::clone(&transport),
+        RemoteConfig::new(
+            "http://localhost:11434",
+            RemoteApi::Ollama,
+            "nomic-embed-text",
+        ),
+    );
+    let batch = embedder
+        .encode_batch(&[sanitized_chunk(0, "alpha")])
+        .await
+        .expect("encode");
+    assert_eq!(batch.vectors, vec![vec![1.0, 0.0]]);
+    assert_eq!(
+        transport.requests()[0].url,
+        "http://localhost:11434/api/embed"
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn rate_limits_and_timeouts_are_retried() {
+    let transport = ScriptedTransport::new(vec![
+        Step::Reply(429, Some(7), json!({ "error": "slow down" })),
+        Step::Hang,
+        Step::Fail("connection reset"),
+        openai_reply(&[[0.1, 0.2]]),
+    ]);
+    let remote = RemoteConfig::new("https://api.example.test/v1", RemoteApi::OpenAi, "m")
+        .with_timeout(Duration::from_secs(5))
+        .with_retries(3, Duration::from_secs(1));
+    let embedder = embedder(Arc::clone(&transport), remote);
+
+    let started = tokio::time::Instant::now();
+    let batch = embedder
+        .encode_batch(&[sanitized_chunk(0, "alpha")])
+        .await
+        .expect("encode after retries");
+    assert_eq!(batch.vectors, vec![vec![0.1, 0.2]]);
+    assert_eq!(transport.requests().len(), 4);
+    // Retry-After (7s) + timeout (5s) + backoff (2s) + backoff (4s).
+    assert_eq!(started.elapsed(), Duration::from_secs(18));
+}
+
+#[tokio::test(start_paused = true)]
+async fn retries_are_bounded_and_client_errors_fail_fast() {
+    let transport = ScriptedTransport::new(vec![
+        Step::Reply(503, None, json!({})),
+        Step::Reply(503, None, json!({})),
+    ]);
+    let remote = RemoteConfig::new("https://api.example.test/v1", RemoteApi::OpenAi, "m")
+        .with_retries(1, Duration::from_millis(10));
+    let err = embedder(Arc::clone(&transport), remote)
+        .encode_batch(&[sanitized_chunk(0, "alpha")])
+        .await
+        .expect_err("server keeps failing");
+    assert!(matches!(err, EmbeddingError::Backend(ref detail) if detail.contains("2 attempts")));
+
+    let transport =
+        ScriptedTransport::new(vec![Step::Reply(401, None, json!({ "error": "bad key" }))]);
+    let err = embedder(
+        Arc::clone(&transport),
+        RemoteConfig::new("https://api.example.test/v1", RemoteApi::OpenAi, "m"),
+    )
+    .encode_batch(&[sanitized_chunk(0, "alpha")])
+    .await
+    .expect_err("unauthorised");
+    assert!(matches!(err, EmbeddingError::Backend(ref detail) if detail.contains("HTTP 401")));
+    assert_eq!(transport.requests().len(), 1);
+}
+
+#[test]
+fn api_keys_load_from_the_environment() {
+    std::env::set_var("EMBEDNEXUS_TEST_EMBED_KEY", "sk-env");
+    let key = ApiKey::from_env("EMBEDNEXUS_TEST_EMBED_KEY").expect("key present");
+    assert_eq!(key.expose(), "sk-env");
+    assert_eq!(format!("{key:?}"), "ApiKey(<redacted>)");
+    assert!(ApiKey::from_env("EMBEDNEXUS_TEST_MISSING_KEY").is_err());
+}
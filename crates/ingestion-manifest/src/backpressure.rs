@@ -0,0 +1,83 @@
+//! Backpressure from the manifest emitter to the stages feeding it.
+
+use tokio::sync::watch;
+
+use crate::ManifestError;
+
+/// Offline buffer fill level as last reported by the emitter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferOccupancy {
+    pub buffered: usize,
+    /// `retention_max_entries`; entries beyond it evict the oldest.
+    pub capacity: usize,
+    /// Whether the most recent send reached the queue.
+    pub queue_online: bool,
+}
+
+impl BufferOccupancy {
+    /// Fraction of the buffer in use.
+    #[must_use]
+    pub fn ratio(&self) -> f64 {
+        if self.capacity == 0 {
+            return 1.0;
+        }
+        self.buffered as f64 / self.capacity as f64
+    }
+}
+
+/// Handle for pausing planning and embedding while the emitter is backed up.
+///
+/// Obtained from [`ManifestEmitter::backpressure`]; the emitter publishes its
+/// occupancy after every emit and flush, so a paused caller resumes once the
+/// queue is reachable again or a flush brings the buffer below the high-water
+/// mark.
+///
+/// [`ManifestEmitter::backpressure`]: crate::ManifestEmitter::backpressure
+#[derive(Debug, Clone)]
+pub struct Backpressure {
+    occupancy: watch::Receiver<BufferOccupancy>,
+    high_water: usize,
+}
+
+impl Backpressure {
+    pub(crate) fn new(occupancy: watch::Receiver<BufferOccupancy>, high_water: usize) -> Self {
+        Self {
+            occupancy,
+            high_water,
+        }
+    }
+
+    #[must_use]
+    pub fn occupancy(&self) -> BufferOccupancy {
+        *self.occupancy.borrow()
+    }
+
+    #[must_use]
+    pub fn high_water(&self) -> usize {
+        self.high_water
+    }
+
+    /// Whether new work may be admitted without risking eviction.
+    #[must_use]
+    pub fn is_ready(&self) -> bool {
+        admits(&self.occupancy(), self.high_water)
+    }
+
+    /// Wait until new work may be admitted.
+    ///
+    /// Fails with [`ManifestError::QueueOffline`] if the emitter is dropped
+    /// while the gate is closed, since nothing will drain the buffer.
+    pub async fn ready(&self) -> Result<(), ManifestError> {
+        let mut occupancy = self.occupancy.clone();
+        let high_water = self.high_water;
+        occupancy
+            .wait_for(|current| admits(current, high_water))
+            .await
+            .map(|_| ())
+            .map_err(|_| ManifestError::QueueOffline("manifest emitter closed".into()))
+    }
+}
+
+fn admits(occupancy: &BufferOccupancy, high_water: usize) -> bool {
+    occupancy.queue_online || occupancy.buffered < high_water
+}
@@ -0,0 +1,273 @@
+//! Asynchronous, batched manifest emission.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+
+use async_trait::async_trait;
+use ingestion_embedding::EmbeddingBatch;
+use storage_ledger::{OfflineReplayBuffer, ReadyReplayEntry, ReplayEntry};
+use tokio::sync::Semaphore;
+use tokio::task::{JoinHandle, JoinSet};
+
+use crate::{records, ManifestDiff, ManifestEmitterConfig, ManifestError, ManifestQueue};
+
+/// Queue that accepts many replay entries per call.
+#[async_trait]
+pub trait AsyncManifestQueue: Send + Sync {
+    /// Deliver `entries`, which are in ascending sequence order. A failure
+    /// means none of them were accepted.
+    async fn send_batch(&self, entries: Vec<ReplayEntry>) -> anyhow::Result<()>;
+}
+
+/// Adapter exposing a per-entry [`ManifestQueue`] as an
+/// [`AsyncManifestQueue`].
+#[derive(Debug)]
+pub struct SyncQueue<Q: ?Sized>(pub Arc<Q>);
+
+#[async_trait]
+impl<Q> AsyncManifestQueue for SyncQueue<Q>
+where
+    Q: ManifestQueue + ?Sized,
+{
+    async fn send_batch(&self, entries: Vec<ReplayEntry>) -> anyhow::Result<()> {
+        entries.into_iter().try_for_each(|entry| self.0.send(entry))
+    }
+}
+
+/// Batching limits for a [`BatchedManifestEmitter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchConfig {
+    /// Entries per `send_batch` call; reaching it while emitting triggers a
+    /// flush.
+    pub max_batch_entries: usize,
+    /// Period of the background flush started by
+    /// [`BatchedManifestEmitter::spawn_flusher`].
+    pub flush_interval: Duration,
+    /// `send_batch` calls allowed at once across all flushes.
+    pub max_in_flight: usize,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            max_batch_entries: 256,
+            flush_interval: Duration::from_secs(1),
+            max_in_flight: 4,
+        }
+    }
+}
+
+/// Outcome of one [`BatchedManifestEmitter::flush`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FlushReport {
+    pub batches_sent: usize,
+    pub entries_sent: usize,
+    /// Entries moved to the offline buffer because their batch failed.
+    pub entries_buffered: usize,
+}
+
+#[derive(Debug)]
+struct Pending {
+    entries: Vec<ReplayEntry>,
+    next_sequence: u64,
+}
+
+/// Manifest emitter that queues entries and ships them in batches.
+///
+/// Entries are collected by [`emit`](Self::emit) and sent by
+/// [`flush`](Self::flush), together with anything waiting in the offline
+/// buffer, in chunks of `max_batch_entries`. With `max_in_flight > 1`
+/// batches may arrive out of order; consumers order by `sequence`. Failed
+/// batches go to the offline buffer and are retried on the next flush.
+pub struct BatchedManifestEmitter<Q: AsyncManifestQueue + ?Sized> {
+    batching: BatchConfig,
+    buffer: OfflineReplayBuffer,
+    queue: Arc<Q>,
+    pending: Mutex<Pending>,
+    in_flight: Arc<Semaphore>,
+    #[cfg(feature = "encryption")]
+    sealer: Option<Arc<crate::seal::ManifestSealer>>,
+}
+
+impl<Q: AsyncManifestQueue + ?Sized> std::fmt::Debug for BatchedManifestEmitter<Q> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BatchedManifestEmitter")
+            .field("batching", &self.batching)
+            .field("buffer", &self.buffer)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Q> BatchedManifestEmitter<Q>
+where
+    Q: AsyncManifestQueue + ?Sized + 'static,
+{
+    pub fn new(
+        config: &ManifestEmitterConfig,
+        batching: BatchConfig,
+        buffer: OfflineReplayBuffer,
+        queue: Arc<Q>,
+    ) -> Result<Self, ManifestError> {
+        if batching.max_batch_entries == 0 || batching.max_in_flight == 0 {
+            return Err(ManifestError::Buffer(
+                "max_batch_entries and max_in_flight must be non-zero".into(),
+            ));
+        }
+        let next_sequence = buffer
+            .max_sequence()
+            .map_or(config.sequence_start, |buffered| {
+                (buffered + 1).max(config.sequence_start)
+            });
+        Ok(Self {
+            batching,
+            buffer,
+            queue,
+            pending: Mutex::new(Pending {
+                entries: Vec::new(),
+                next_sequence,
+            }),
+            in_flight: Arc::new(Semaphore::new(batching.max_in_flight)),
+            #[cfg(feature = "encryption")]
+            sealer: None,
+        })
+    }
+
+    /// Encrypt and sign every entry emitted from now on.
+    #[cfg(feature = "encryption")]
+    #[must_use]
+    pub fn with_sealer(mut self, sealer: Arc<crate::seal::ManifestSealer>) -> Self {
+        self.sealer = Some(sealer);
+        self
+    }
+
+    /// Entries waiting for the next flush.
+    #[must_use]
+    pub fn pending(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Queue the entry for `diff`, flushing once a full batch is waiting.
+    /// Returns the sequence assigned to the entry.
+    pub async fn emit(
+        &self,
+        diff: ManifestDiff,
+        batch: EmbeddingBatch,
+    ) -> Result<u64, ManifestError> {
+        records::check_batch(&batch)?;
+        let (sequence, full) = {
+            let mut pending = self.lock();
+            let sequence = pending.next_sequence;
+            let entry = diff.replay_entry(sequence);
+            #[cfg(feature = "encryption")]
+            let entry = match &self.sealer {
+                Some(sealer) => sealer.seal(&diff, entry)?,
+                None => entry,
+            };
+            pending.next_sequence += 1;
+            pending.entries.push(entry);
+            (
+                sequence,
+                pending.entries.len() >= self.batching.max_batch_entries,
+            )
+        };
+        if full {
+            self.flush().await?;
+        }
+        Ok(sequence)
+    }
+
+    /// Send everything pending or buffered offline.
+    ///
+    /// Returns [`ManifestError::QueueOffline`] if any batch failed; its
+    /// entries are then in the offline buffer.
+    pub async fn flush(&self) -> Result<FlushReport, ManifestError> {
+        let mut outgoing: Vec<(ReplayEntry, Option<SystemTime>)> = self
+            .buffer
+            .drain_ready()
+            .into_iter()
+            .map(|ready| (ready.entry, Some(ready.inserted_at)))
+            .collect();
+        outgoing.extend(
+            std::mem::take(&mut self.lock().entries)
+                .into_iter()
+                .map(|entry| (entry, None)),
+        );
+        outgoing.sort_by_key(|(entry, _)| entry.sequence);
+
+        let mut tasks = JoinSet::new();
+        let mut report = FlushReport::default();
+        let mut remaining = outgoing.into_iter().peekable();
+        while remaining.peek().is_some() {
+            let batch: Vec<_> = remaining
+                .by_ref()
+                .take(self.batching.max_batch_entries)
+                .collect();
+            let permit = Arc::clone(&self.in_flight)
+                .acquire_owned()
+                .await
+                .map_err(|err| ManifestError::QueueOffline(err.to_string()))?;
+            let queue = Arc::clone(&self.queue);
+            tasks.spawn(async move {
+                let entries = batch
+                    .iter()
+                    .map(|(entry, _)| ReplayEntry {
+                        status: "emitted".into(),
+                        ..entry.clone()
+                    })
+                    .collect();
+                let outcome = queue.send_batch(entries).await;
+                drop(permit);
+                (batch, outcome)
+            });
+        }
+
+        let mut failure = None;
+        while let Some(joined) = tasks.join_next().await {
+            let (batch, outcome) =
+                joined.map_err(|err| ManifestError::QueueOffline(err.to_string()))?;
+            match outcome {
+                Ok(()) => {
+                    report.batches_sent += 1;
+                    report.entries_sent += batch.len();
+                }
+                Err(err) => {
+                    report.entries_buffered += batch.len();
+                    for (mut entry, inserted_at) in batch {
+                        entry.status = "buffered".into();
+                        match inserted_at {
+                            Some(inserted_at) => {
+                                self.buffer.requeue(ReadyReplayEntry { entry, inserted_at })
+                            }
+                            None => self.buffer.push(entry),
+                        }
+                        .map_err(|error| ManifestError::Buffer(error.to_string()))?;
+                    }
+                    failure.get_or_insert(err.to_string());
+                }
+            }
+        }
+        match failure {
+            Some(err) => Err(ManifestError::QueueOffline(err)),
+            None => Ok(report),
+        }
+    }
+
+    /// Flush every `flush_interval` until the returned task is aborted.
+    pub fn spawn_flusher(self: &Arc<Self>) -> JoinHandle<()> {
+        let emitter = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(emitter.batching.flush_interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                if let Err(err) = emitter.flush().await {
+                    tracing::warn!(error = %err, "periodic manifest flush failed");
+                }
+            }
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Pending> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
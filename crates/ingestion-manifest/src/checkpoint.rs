@@ -0,0 +1,138 @@
+//! Durable record of the last manifest entry delivered per repository.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ManifestError;
+
+/// Checkpoint file layout version. A mismatch fails the resume rather than
+/// replaying from a misread position.
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct CheckpointFile {
+    version: u32,
+    /// Last sequence the queue accepted, per repository.
+    repos: BTreeMap<String, u64>,
+}
+
+/// Last successfully emitted sequence per repository.
+///
+/// A [`ManifestEmitter`](crate::ManifestEmitter) built with
+/// [`resume_from_checkpoint`](crate::ManifestEmitter::resume_from_checkpoint)
+/// advances the checkpoint after every accepted entry, numbers new entries
+/// after the highest checkpointed sequence and drops buffered entries the
+/// checkpoint shows were already delivered.
+#[derive(Debug)]
+pub struct ManifestCheckpoint {
+    path: Option<PathBuf>,
+    state: Mutex<CheckpointFile>,
+}
+
+impl Default for ManifestCheckpoint {
+    fn default() -> Self {
+        Self::in_memory()
+    }
+}
+
+impl ManifestCheckpoint {
+    #[must_use]
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            state: Mutex::new(CheckpointFile {
+                version: CHECKPOINT_VERSION,
+                ..CheckpointFile::default()
+            }),
+        }
+    }
+
+    /// Open a checkpoint persisted at `path`, creating it on first write.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, ManifestError> {
+        let path = path.into();
+        let state = match fs::read(&path) {
+            Ok(bytes) => {
+                let file: CheckpointFile = serde_json::from_slice(&bytes).map_err(|err| {
+                    ManifestError::Checkpoint(format!("{}: {err}", path.display()))
+                })?;
+                if file.version != CHECKPOINT_VERSION {
+                    return Err(ManifestError::Checkpoint(format!(
+                        "{}: unsupported checkpoint version {}",
+                        path.display(),
+                        file.version
+                    )));
+                }
+                file
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => CheckpointFile {
+                version: CHECKPOINT_VERSION,
+                ..CheckpointFile::default()
+            },
+            Err(err) => {
+                return Err(ManifestError::Checkpoint(format!(
+                    "{}: {err}",
+                    path.display()
+                )))
+            }
+        };
+        Ok(Self {
+            path: Some(path),
+            state: Mutex::new(state),
+        })
+    }
+
+    #[must_use]
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Last sequence delivered for `repo_id`.
+    #[must_use]
+    pub fn last_emitted(&self, repo_id: &str) -> Option<u64> {
+        self.lock().repos.get(repo_id).copied()
+    }
+
+    /// Highest sequence delivered for any repository.
+    #[must_use]
+    pub fn high_water(&self) -> Option<u64> {
+        self.lock().repos.values().copied().max()
+    }
+
+    /// Whether the entry `sequence` of `repo_id` was already delivered.
+    #[must_use]
+    pub fn covers(&self, repo_id: &str, sequence: u64) -> bool {
+        self.last_emitted(repo_id)
+            .is_some_and(|last| sequence <= last)
+    }
+
+    /// Record that `sequence` was delivered for `repo_id`. Older sequences
+    /// never move the checkpoint backwards.
+    pub fn record(&self, repo_id: &str, sequence: u64) -> Result<(), ManifestError> {
+        let mut state = self.lock();
+        let last = state.repos.entry(repo_id.to_string()).or_insert(sequence);
+        if *last > sequence {
+            return Ok(());
+        }
+        *last = sequence;
+        self.persist(&state)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CheckpointFile> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn persist(&self, state: &CheckpointFile) -> Result<(), ManifestError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let io =
+            |err: std::io::Error| ManifestError::Checkpoint(format!("{}: {err}", path.display()));
+        let bytes = serde_json::to_vec_pretty(state)
+            .map_err(|err| ManifestError::Checkpoint(format!("serializing checkpoint: {err}")))?;
+        storage_atomic::write(path, &bytes).map_err(io)
+    }
+}
@@ -0,0 +1,134 @@
+//! Router commands for inspecting and requeueing manifest dead letters.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use runtime_router::{CommandHandler, HandlerRouter, RouterError, RouterResponse, SessionContext};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use storage_ledger::OfflineReplayBuffer;
+
+use crate::{DeadLetter, DeadLetterQueue, ManifestError};
+
+/// Command listing dead-lettered replay entries.
+pub const DEAD_LETTERS_COMMAND: &str = "manifest.dead_letters";
+/// Command moving a dead letter back into the offline buffer (`{ sequence }`).
+pub const REQUEUE_COMMAND: &str = "manifest.requeue";
+
+/// Capability required for every dead-letter command.
+pub const REPLAY_ADMIN_CAPABILITY: &str = "manifest.replay_admin";
+
+/// Register the dead-letter commands on `router`; requeued entries go to
+/// `buffer`, which should be the emitter's own (see
+/// [`ManifestEmitter::buffer`](crate::ManifestEmitter::buffer)).
+pub fn register_commands(
+    router: &mut HandlerRouter,
+    dead_letters: Arc<DeadLetterQueue>,
+    buffer: OfflineReplayBuffer,
+) {
+    router
+        .register_with_capabilities(
+            DEAD_LETTERS_COMMAND,
+            vec![REPLAY_ADMIN_CAPABILITY.into()],
+            Arc::new(ListHandler {
+                dead_letters: Arc::clone(&dead_letters),
+            }),
+        )
+        .register_with_capabilities(
+            REQUEUE_COMMAND,
+            vec![REPLAY_ADMIN_CAPABILITY.into()],
+            Arc::new(RequeueHandler {
+                dead_letters,
+                buffer,
+            }),
+        );
+}
+
+#[derive(Debug, Deserialize)]
+struct RequeueRequest {
+    sequence: u64,
+}
+
+struct ListHandler {
+    dead_letters: Arc<DeadLetterQueue>,
+}
+
+#[async_trait]
+impl CommandHandler for ListHandler {
+    async fn handle(
+        &self,
+        ctx: &SessionContext,
+        _payload: Value,
+    ) -> Result<RouterResponse, RouterError> {
+        let letters: Vec<Value> = self
+            .dead_letters
+            .list()
+            .iter()
+            .filter(|letter| ctx.can_access_tenant(letter.entry.tenant_id.as_deref()))
+            .map(describe)
+            .collect();
+        Ok(RouterResponse::ok(json!({ "dead_letters": letters })))
+    }
+}
+
+struct RequeueHandler {
+    dead_letters: Arc<DeadLetterQueue>,
+    buffer: OfflineReplayBuffer,
+}
+
+#[async_trait]
+impl CommandHandler for RequeueHandler {
+    async fn handle(
+        &self,
+        ctx: &SessionContext,
+        payload: Value,
+    ) -> Result<RouterResponse, RouterError> {
+        let request: RequeueRequest =
+            serde_json::from_value(payload).map_err(|err| RouterError::InvalidRequest {
+                detail: err.to_string(),
+            })?;
+        // Another tenant's letter is answered as if it did not exist.
+        let visible = self
+            .dead_letters
+            .get(request.sequence)
+            .is_some_and(|letter| ctx.can_access_tenant(letter.entry.tenant_id.as_deref()));
+        if !visible {
+            return Err(router_error(ManifestError::UnknownDeadLetter(
+                request.sequence,
+            )));
+        }
+        let letter = self
+            .dead_letters
+            .requeue(request.sequence, &self.buffer)
+            .map_err(router_error)?;
+        tracing::info!(
+            principal = %ctx.principal,
+            sequence = request.sequence,
+            repo_id = %letter.entry.repo_id,
+            "dead letter requeued"
+        );
+        Ok(RouterResponse::ok(describe(&letter)))
+    }
+}
+
+fn describe(letter: &DeadLetter) -> Value {
+    json!({
+        "sequence": letter.entry.sequence,
+        "repo_id": letter.entry.repo_id,
+        "attempts": letter.attempts,
+        "last_error": letter.last_error,
+        "checksum_before": letter.entry.payload_checksum_before,
+        "checksum_after": letter.entry.payload_checksum_after,
+    })
+}
+
+fn router_error(err: ManifestError) -> RouterError {
+    match err {
+        ManifestError::UnknownDeadLetter(_) => RouterError::NotFound {
+            detail: err.to_string(),
+        },
+        other => RouterError::Internal {
+            detail: other.to_string(),
+        },
+    }
+}
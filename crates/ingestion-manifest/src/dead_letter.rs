@@ -0,0 +1,214 @@
+//! Dead-lettering of replay entries the queue keeps rejecting.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use storage_ledger::{OfflineReplayBuffer, ReplayEntry};
+
+use crate::ManifestError;
+
+/// Version of the dead-letter file layout; other versions fail to open.
+pub const DEAD_LETTER_VERSION: u32 = 1;
+
+/// Status of an entry put back into the offline buffer from the dead-letter
+/// queue. Such entries are delivered even when a checkpoint already covers
+/// their sequence.
+pub const REQUEUED_STATUS: &str = "requeued";
+
+/// When to give up on an entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeadLetterPolicy {
+    /// Failed sends, including the initial emit, before an entry is
+    /// dead-lettered.
+    pub max_attempts: u32,
+}
+
+impl Default for DeadLetterPolicy {
+    fn default() -> Self {
+        Self { max_attempts: 5 }
+    }
+}
+
+/// Entry removed from replay after exhausting its attempts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub entry: ReplayEntry,
+    pub attempts: u32,
+    pub last_error: String,
+    pub dead_lettered_at: SystemTime,
+}
+
+/// Destination for dead-lettered entries.
+///
+/// Implemented by [`DeadLetterQueue`] and by any `Fn(DeadLetter)` callback.
+pub trait DeadLetterSink: Send + Sync {
+    fn dead_letter(&self, letter: DeadLetter) -> Result<(), ManifestError>;
+}
+
+impl<F> DeadLetterSink for F
+where
+    F: Fn(DeadLetter) + Send + Sync,
+{
+    fn dead_letter(&self, letter: DeadLetter) -> Result<(), ManifestError> {
+        self(letter);
+        Ok(())
+    }
+}
+
+/// Dead-letter policy and sink attached to a
+/// [`ManifestEmitter`](crate::ManifestEmitter).
+#[derive(Clone)]
+pub(crate) struct DeadLetters {
+    pub(crate) policy: DeadLetterPolicy,
+    pub(crate) sink: Arc<dyn DeadLetterSink>,
+}
+
+impl fmt::Debug for DeadLetters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeadLetters")
+            .field("policy", &self.policy)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct DeadLetterFile {
+    version: u32,
+    letters: BTreeMap<u64, DeadLetter>,
+}
+
+/// Dead letters keyed by sequence, optionally persisted for inspection and
+/// requeueing through the router commands.
+#[derive(Debug)]
+pub struct DeadLetterQueue {
+    path: Option<PathBuf>,
+    letters: Mutex<BTreeMap<u64, DeadLetter>>,
+}
+
+impl Default for DeadLetterQueue {
+    fn default() -> Self {
+        Self::in_memory()
+    }
+}
+
+impl DeadLetterQueue {
+    #[must_use]
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            letters: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Open a queue persisted at `path`, creating it on first write.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, ManifestError> {
+        let path = path.into();
+        let letters = match fs::read(&path) {
+            Ok(bytes) => {
+                let file: DeadLetterFile = serde_json::from_slice(&bytes).map_err(|err| {
+                    ManifestError::DeadLetter(format!("{}: {err}", path.display()))
+                })?;
+                if file.version != DEAD_LETTER_VERSION {
+                    return Err(ManifestError::DeadLetter(format!(
+                        "{}: unsupported dead-letter version {}",
+                        path.display(),
+                        file.version
+                    )));
+                }
+                file.letters
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => {
+                return Err(ManifestError::DeadLetter(format!(
+                    "{}: {err}",
+                    path.display()
+                )))
+            }
+        };
+        Ok(Self {
+            path: Some(path),
+            letters: Mutex::new(letters),
+        })
+    }
+
+    #[must_use]
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Dead letters in sequence order.
+    #[must_use]
+    pub fn list(&self) -> Vec<DeadLetter> {
+        self.lock().values().cloned().collect()
+    }
+
+    /// The dead letter for `sequence`, if any.
+    #[must_use]
+    pub fn get(&self, sequence: u64) -> Option<DeadLetter> {
+        self.lock().get(&sequence).cloned()
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Move the entry `sequence` back into `buffer` for the next flush.
+    pub fn requeue(
+        &self,
+        sequence: u64,
+        buffer: &OfflineReplayBuffer,
+    ) -> Result<DeadLetter, ManifestError> {
+        let mut letters = self.lock();
+        let letter = letters
+            .get(&sequence)
+            .cloned()
+            .ok_or(ManifestError::UnknownDeadLetter(sequence))?;
+        buffer
+            .push(ReplayEntry {
+                status: REQUEUED_STATUS.into(),
+                ..letter.entry.clone()
+            })
+            .map_err(|error| ManifestError::Buffer(error.to_string()))?;
+        letters.remove(&sequence);
+        self.persist(&letters)?;
+        Ok(letter)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<u64, DeadLetter>> {
+        self.letters.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn persist(&self, letters: &BTreeMap<u64, DeadLetter>) -> Result<(), ManifestError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let io =
+            |err: std::io::Error| ManifestError::DeadLetter(format!("{}: {err}", path.display()));
+        let file = DeadLetterFile {
+            version: DEAD_LETTER_VERSION,
+            letters: letters.clone(),
+        };
+        let bytes = serde_json::to_vec_pretty(&file)
+            .map_err(|err| ManifestError::DeadLetter(format!("serializing dead letters: {err}")))?;
+        storage_atomic::write(path, &bytes).map_err(io)
+    }
+}
+
+impl DeadLetterSink for DeadLetterQueue {
+    fn dead_letter(&self, letter: DeadLetter) -> Result<(), ManifestError> {
+        let mut letters = self.lock();
+        letters.insert(letter.entry.sequence, letter);
+        self.persist(&letters)
+    }
+}
@@ -0,0 +1,120 @@
+//! Computing [`ManifestDiff`]s from manifest snapshots and chunk plans.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::SystemTime;
+
+use ingestion_planning::{ChunkPlan, FileChunks, ManifestChunk, PlanManifest};
+
+use crate::{ManifestDiff, ManifestError};
+
+impl ManifestDiff {
+    /// Diff the chunk plans of a new run against the previous manifest
+    /// snapshot, stamped with the current time.
+    pub fn compute(previous: &PlanManifest, plans: &[ChunkPlan]) -> Result<Self, ManifestError> {
+        Self::compute_at(previous, plans, SystemTime::now())
+    }
+
+    /// [`ManifestDiff::compute`] with an explicit `applied_at`.
+    ///
+    /// `plans` must cover the whole repository. Chunks are ordered by file
+    /// path and chunk index regardless of input order, so the added list and
+    /// `checksum_after` match what [`PlanManifest::checksum`] reports for the
+    /// same plans.
+    pub fn compute_at(
+        previous: &PlanManifest,
+        plans: &[ChunkPlan],
+        applied_at: SystemTime,
+    ) -> Result<Self, ManifestError> {
+        let repo_id = &previous.repo_id;
+        let tenant_id = plans.first().and_then(|plan| plan.tenant_id.clone());
+        let mut files: BTreeMap<&str, Vec<(usize, &ChunkPlan)>> = BTreeMap::new();
+        let mut seen = HashSet::new();
+        for plan in plans {
+            if &plan.repo_id != repo_id {
+                return Err(ManifestError::InvalidPlan(format!(
+                    "{} belongs to {}, not {repo_id}",
+                    plan.plan_id, plan.repo_id
+                )));
+            }
+            if plan.tenant_id != tenant_id {
+                return Err(ManifestError::InvalidPlan(format!(
+                    "{} belongs to another tenant than the rest of the run",
+                    plan.plan_id
+                )));
+            }
+            if !seen.insert(plan.plan_id.as_str()) {
+                return Err(ManifestError::InvalidPlan(format!(
+                    "duplicate plan id {}",
+                    plan.plan_id
+                )));
+            }
+            let (path, index) = split_plan_id(repo_id, &plan.plan_id)?;
+            files.entry(path).or_default().push((index, plan));
+        }
+
+        let mut current = PlanManifest::new(repo_id.clone());
+        let mut ordered = Vec::with_capacity(plans.len());
+        for (path, mut chunks) in files {
+            chunks.sort_by_key(|(index, _)| *index);
+            current.files.insert(
+                path.to_string(),
+                FileChunks {
+                    content_hash: String::new(),
+                    chunks: chunks
+                        .iter()
+                        .map(|(_, plan)| ManifestChunk {
+                            plan_id: plan.plan_id.clone(),
+                            chunker_config: plan.chunker_config.clone(),
+                            source_span: plan.source_span.clone(),
+                            hash: plan.hash.clone(),
+                        })
+                        .collect(),
+                },
+            );
+            ordered.extend(chunks.into_iter().map(|(_, plan)| plan));
+        }
+
+        let previous_hashes: HashMap<&str, &str> = previous
+            .files
+            .values()
+            .flat_map(|file| &file.chunks)
+            .map(|chunk| (chunk.plan_id.as_str(), chunk.hash.as_str()))
+            .collect();
+        let added_chunks = ordered
+            .iter()
+            .filter(|plan| previous_hashes.get(plan.plan_id.as_str()) != Some(&plan.hash.as_str()))
+            .map(|plan| plan.plan_id.clone())
+            .collect();
+        let removed_chunks = previous
+            .files
+            .values()
+            .flat_map(|file| &file.chunks)
+            .filter(|chunk| !seen.contains(chunk.plan_id.as_str()))
+            .map(|chunk| chunk.plan_id.clone())
+            .collect();
+
+        Ok(Self {
+            repo_id: repo_id.clone(),
+            tenant_id,
+            applied_at,
+            added_chunks,
+            removed_chunks,
+            checksum_before: previous.checksum(),
+            checksum_after: current.checksum(),
+        })
+    }
+}
+
+/// Split `<repo_id>::<path>::<index>` into its path and chunk index.
+fn split_plan_id<'a>(repo_id: &str, plan_id: &'a str) -> Result<(&'a str, usize), ManifestError> {
+    plan_id
+        .strip_prefix(repo_id)
+        .and_then(|rest| rest.strip_prefix("::"))
+        .and_then(|rest| rest.rsplit_once("::"))
+        .and_then(|(path, index)| Some((path, index.parse().ok()?)))
+        .ok_or_else(|| {
+            ManifestError::InvalidPlan(format!(
+                "{plan_id} is not of the form {repo_id}::<path>::<index>"
+            ))
+        })
+}
@@ -0,0 +1,273 @@
+//! Fan-out of manifest entries to several queues.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use storage_ledger::{OfflineReplayBuffer, ReplayEntry};
+
+use crate::{ManifestQueue, REQUEUED_STATUS};
+
+/// How many targets must accept an entry for the fan-out send to succeed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuccessPolicy {
+    All,
+    Any,
+    /// At least this many targets.
+    Quorum(usize),
+}
+
+impl SuccessPolicy {
+    fn required(self, targets: usize) -> usize {
+        match self {
+            Self::All => targets,
+            Self::Any => 1,
+            Self::Quorum(required) => required,
+        }
+    }
+}
+
+/// Delivery state of one fan-out target.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TargetStatus {
+    pub name: String,
+    /// Entries waiting in the target's own backlog.
+    pub backlog: usize,
+    pub last_delivered: Option<u64>,
+    pub delivered: u64,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+}
+
+struct Target {
+    queue: Arc<dyn ManifestQueue>,
+    backlog: OfflineReplayBuffer,
+    status: Mutex<TargetStatus>,
+}
+
+impl Target {
+    fn lock(&self) -> MutexGuard<'_, TargetStatus> {
+        self.status.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn already_delivered(&self, entry: &ReplayEntry) -> bool {
+        entry.status != REQUEUED_STATUS
+            && self
+                .lock()
+                .last_delivered
+                .is_some_and(|last| entry.sequence <= last)
+    }
+
+    fn send(&self, entry: &ReplayEntry) -> anyhow::Result<()> {
+        let result = self.queue.send(entry.clone());
+        let mut status = self.lock();
+        match &result {
+            Ok(()) => {
+                status.last_delivered = status.last_delivered.max(Some(entry.sequence));
+                status.delivered += 1;
+                status.consecutive_failures = 0;
+                status.last_error = None;
+            }
+            Err(err) => {
+                status.consecutive_failures += 1;
+                status.last_error = Some(err.to_string());
+            }
+        }
+        result
+    }
+
+    /// Deliver the backlog in order, stopping at the first failure.
+    fn flush(&self) -> anyhow::Result<()> {
+        let mut drained: VecDeque<_> = self.backlog.drain_ready().into();
+        while let Some(ready) = drained.pop_front() {
+            if self.already_delivered(&ready.entry) {
+                continue;
+            }
+            if let Err(err) = self.send(&ready.entry) {
+                drained.push_front(ready);
+                for remaining in drained {
+                    self.backlog.requeue(remaining)?;
+                }
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Add `entry` to the backlog unless it is already waiting there.
+    fn hold(&self, entry: &ReplayEntry) -> anyhow::Result<()> {
+        let waiting = self.backlog.drain_ready();
+        let held = waiting
+            .iter()
+            .any(|ready| ready.entry.sequence == entry.sequence);
+        for ready in waiting {
+            self.backlog.requeue(ready)?;
+        }
+        if !held {
+            self.backlog.push(entry.clone())?;
+        }
+        Ok(())
+    }
+
+    fn sync_backlog(&self) {
+        self.lock().backlog = self.backlog.len();
+    }
+}
+
+/// [`ManifestQueue`] that forwards each entry to several named targets.
+///
+/// Every target has its own backlog and retry state: an entry a target
+/// rejects waits in that target's backlog and is retried, in order, before
+/// the target is sent anything newer, so a slow target never blocks the
+/// others. `send` succeeds once the [`SuccessPolicy`] is met. When it fails
+/// the emitter buffers the entry and sends it again later; targets that
+/// already hold or delivered it are not sent a second copy.
+pub struct FanOutQueue {
+    policy: SuccessPolicy,
+    targets: Vec<(String, Target)>,
+    retention_max_entries: usize,
+    retention_max_age: std::time::Duration,
+}
+
+impl fmt::Debug for FanOutQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FanOutQueue")
+            .field("policy", &self.policy)
+            .field("targets", &self.status())
+            .finish()
+    }
+}
+
+impl FanOutQueue {
+    /// Fan-out whose per-target backlogs use the given retention limits.
+    #[must_use]
+    pub fn new(
+        policy: SuccessPolicy,
+        retention_max_entries: usize,
+        retention_max_age: std::time::Duration,
+    ) -> Self {
+        Self {
+            policy,
+            targets: Vec::new(),
+            retention_max_entries,
+            retention_max_age,
+        }
+    }
+
+    /// Add a target with an in-memory backlog.
+    #[must_use]
+    pub fn with_target(self, name: impl Into<String>, queue: Arc<dyn ManifestQueue>) -> Self {
+        let backlog = OfflineReplayBuffer::new(self.retention_max_entries, self.retention_max_age);
+        self.with_target_backlog(name, queue, backlog)
+    }
+
+    /// Add a target with a caller-supplied backlog, e.g. a journaled
+    /// [`OfflineReplayBuffer::open`].
+    #[must_use]
+    pub fn with_target_backlog(
+        mut self,
+        name: impl Into<String>,
+        queue: Arc<dyn ManifestQueue>,
+        backlog: OfflineReplayBuffer,
+    ) -> Self {
+        let name = name.into();
+        let status = TargetStatus {
+            name: name.clone(),
+            backlog: backlog.len(),
+            ..TargetStatus::default()
+        };
+        self.targets.push((
+            name,
+            Target {
+                queue,
+                backlog,
+                status: Mutex::new(status),
+            },
+        ));
+        self
+    }
+
+    #[must_use]
+    pub fn policy(&self) -> SuccessPolicy {
+        self.policy
+    }
+
+    /// Delivery state of every target, in registration order.
+    #[must_use]
+    pub fn status(&self) -> Vec<TargetStatus> {
+        self.targets
+            .iter()
+            .map(|(_, target)| target.lock().clone())
+            .collect()
+    }
+
+    /// Retry every target's backlog. Returns the names of targets that still
+    /// have entries waiting.
+    pub fn flush(&self) -> Vec<String> {
+        self.targets
+            .iter()
+            .filter_map(|(name, target)| {
+                let result = target.flush();
+                target.sync_backlog();
+                if let Err(err) = result {
+                    tracing::debug!(queue = %name, error = %err, "fan-out backlog flush failed");
+                }
+                (!target.backlog.is_empty()).then(|| name.clone())
+            })
+            .collect()
+    }
+}
+
+impl ManifestQueue for FanOutQueue {
+    fn send(&self, entry: ReplayEntry) -> anyhow::Result<()> {
+        let required = self.policy.required(self.targets.len());
+        if required == 0 || required > self.targets.len() {
+            anyhow::bail!(
+                "{:?} cannot be met by {} fan-out targets",
+                self.policy,
+                self.targets.len()
+            );
+        }
+        let mut accepted = 0;
+        let mut failures = Vec::new();
+        for (name, target) in &self.targets {
+            if target.already_delivered(&entry) {
+                accepted += 1;
+                continue;
+            }
+            // Older entries go first; the backlog may also deliver this one.
+            let outcome = target.flush().and_then(|()| {
+                if target.already_delivered(&entry) {
+                    Ok(())
+                } else {
+                    target.send(&entry)
+                }
+            });
+            if let Err(err) = outcome {
+                target.hold(&entry)?;
+                failures.push(format!("{name}: {err}"));
+            } else {
+                accepted += 1;
+            }
+            target.sync_backlog();
+        }
+        if accepted >= required {
+            if !failures.is_empty() {
+                tracing::warn!(
+                    sequence = entry.sequence,
+                    accepted,
+                    failures = %failures.join("; "),
+                    "manifest entry reached only some fan-out targets"
+                );
+            }
+            Ok(())
+        } else {
+            anyhow::bail!(
+                "{accepted} of {} fan-out targets accepted entry {} (need {required}): {}",
+                self.targets.len(),
+                entry.sequence,
+                failures.join("; ")
+            )
+        }
+    }
+}
@@ -1,12 +1,42 @@
 //! Manifest emitter and replay scaffolding.
 
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::SystemTime;
 
 use ingestion_embedding::EmbeddingBatch;
-use storage_ledger::{OfflineReplayBuffer, ReplayEntry};
+use ingestion_planning::PlanDiff;
+use storage_ledger::{OfflineReplayBuffer, ReadyReplayEntry, ReplayEntry};
 use thiserror::Error;
+use tokio::sync::watch;
+
+pub mod backpressure;
+pub mod batch;
+pub mod checkpoint;
+pub mod commands;
+pub mod dead_letter;
+pub mod diff;
+pub mod fanout;
+pub mod migration;
+pub mod records;
+#[cfg(feature = "encryption")]
+pub mod seal;
+
+pub use backpressure::{Backpressure, BufferOccupancy};
+pub use batch::{AsyncManifestQueue, BatchConfig, BatchedManifestEmitter, FlushReport, SyncQueue};
+pub use checkpoint::{ManifestCheckpoint, CHECKPOINT_VERSION};
+pub use dead_letter::{
+    DeadLetter, DeadLetterPolicy, DeadLetterQueue, DeadLetterSink, DEAD_LETTER_VERSION,
+    REQUEUED_STATUS,
+};
+pub use fanout::{FanOutQueue, SuccessPolicy, TargetStatus};
+pub use migration::{
+    DualWriter, EncoderMigration, MigrationProgress, MigrationRegistry, MIGRATION_VERSION,
+};
+pub use records::{load_record, persist_batch, record_key, VectorRecord};
+#[cfg(feature = "encryption")]
+pub use seal::{ManifestPayload, ManifestSealer};
 
 pub trait ManifestQueue: Send + Sync {
     fn send(&self, entry: ReplayEntry) -> anyhow::Result<()>;
@@ -18,11 +48,15 @@ pub struct ManifestEmitterConfig {
     pub encryption_key: String,
     pub retention_max_entries: usize,
     pub retention_max_age: std::time::Duration,
+    /// Journal for the offline buffer; when set, buffered entries survive a
+    /// restart of [`ManifestEmitter::from_config`].
+    pub offline_buffer_path: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone)]
 pub struct ManifestDiff {
     pub repo_id: String,
+    pub tenant_id: Option<String>,
     pub applied_at: SystemTime,
     pub added_chunks: Vec<String>,
     pub removed_chunks: Vec<String>,
@@ -30,21 +64,87 @@ pub struct ManifestDiff {
     pub checksum_after: String,
 }
 
+impl ManifestDiff {
+    /// Pending replay entry for this diff at `sequence`.
+    pub(crate) fn replay_entry(&self, sequence: u64) -> ReplayEntry {
+        let delayed_ms = self
+            .applied_at
+            .elapsed()
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or(0);
+
+        ReplayEntry {
+            sequence,
+            repo_id: self.repo_id.clone(),
+            tenant_id: self.tenant_id.clone(),
+            delayed_ms,
+            payload_checksum_before: self.checksum_before.clone(),
+            payload_checksum_after: self.checksum_after.clone(),
+            status: String::from("pending"),
+            sealed_payload: None,
+            signature: None,
+        }
+    }
+}
+
+impl From<&PlanDiff> for ManifestDiff {
+    /// Build the manifest diff for an incremental plan, stamped with the current time.
+    fn from(diff: &PlanDiff) -> Self {
+        Self {
+            repo_id: diff.repo_id.clone(),
+            tenant_id: diff.tenant_id.clone(),
+            applied_at: SystemTime::now(),
+            added_chunks: diff.added.iter().map(|plan| plan.plan_id.clone()).collect(),
+            removed_chunks: diff.removed.clone(),
+            checksum_before: diff.checksum_before.clone(),
+            checksum_after: diff.checksum_after.clone(),
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum ManifestError {
     #[error("manifest queue offline: {0}")]
     QueueOffline(String),
     #[error("offline buffer error: {0}")]
     Buffer(String),
+    #[error("embedding batch rejected: {0}")]
+    InvalidBatch(String),
+    #[error("vector store error: {0}")]
+    Store(String),
+    #[error("embedding failed: {0}")]
+    Embedding(String),
+    #[error("encoder migration error: {0}")]
+    Migration(String),
+    #[error("invalid chunk plan: {0}")]
+    InvalidPlan(String),
+    #[error("manifest sealing error: {0}")]
+    Sealing(String),
+    #[error("manifest signature mismatch for sequence {0}")]
+    SignatureMismatch(u64),
+    #[error("manifest checkpoint error: {0}")]
+    Checkpoint(String),
+    #[error("dead-letter queue error: {0}")]
+    DeadLetter(String),
+    #[error("no dead letter with sequence {0}")]
+    UnknownDeadLetter(u64),
 }
 
 #[derive(Debug)]
 pub struct ManifestEmitter<Q: ManifestQueue + ?Sized> {
-    #[allow(dead_code)]
     config: ManifestEmitterConfig,
     buffer: OfflineReplayBuffer,
     queue: Arc<Q>,
     next_sequence: u64,
+    checkpoint: Option<Arc<ManifestCheckpoint>>,
+    dead_letters: Option<dead_letter::DeadLetters>,
+    /// Failed sends per buffered sequence; only tracked with dead letters.
+    attempts: HashMap<u64, u32>,
+    queue_online: bool,
+    high_water: usize,
+    occupancy: watch::Sender<BufferOccupancy>,
+    #[cfg(feature = "encryption")]
+    sealer: Option<Arc<seal::ManifestSealer>>,
 }
 
 impl<Q> ManifestEmitter<Q>
@@ -58,79 +158,290 @@ where
             .map_or(config.sequence_start, |buffered| {
                 buffered.max(config.sequence_start)
             });
+        let capacity = config.retention_max_entries;
+        let (occupancy, _) = watch::channel(BufferOccupancy {
+            buffered: buffer.len(),
+            capacity,
+            queue_online: true,
+        });
         Self {
             config,
             buffer,
             queue,
             next_sequence,
+            checkpoint: None,
+            dead_letters: None,
+            attempts: HashMap::new(),
+            queue_online: true,
+            high_water: (capacity * 9 / 10).max(1),
+            occupancy,
+            #[cfg(feature = "encryption")]
+            sealer: None,
+        }
+    }
+
+    /// Hand entries that fail `policy.max_attempts` sends to `sink` instead
+    /// of retrying them forever. Attempt counts are kept in memory and
+    /// restart from zero with the emitter.
+    #[must_use]
+    pub fn with_dead_letters(
+        mut self,
+        policy: DeadLetterPolicy,
+        sink: Arc<dyn DeadLetterSink>,
+    ) -> Self {
+        self.dead_letters = Some(dead_letter::DeadLetters { policy, sink });
+        self
+    }
+
+    /// Buffered entries at which [`Backpressure::ready`] starts holding
+    /// callers back while the queue is offline; defaults to 90% of
+    /// `retention_max_entries`.
+    #[must_use]
+    pub fn with_high_water(mut self, high_water: usize) -> Self {
+        self.high_water = high_water.max(1);
+        self
+    }
+
+    #[must_use]
+    pub fn occupancy(&self) -> BufferOccupancy {
+        BufferOccupancy {
+            buffered: self.buffer.len(),
+            capacity: self.config.retention_max_entries,
+            queue_online: self.queue_online,
+        }
+    }
+
+    /// Gate for the stages feeding this emitter, updated on every emit and
+    /// flush.
+    #[must_use]
+    pub fn backpressure(&self) -> Backpressure {
+        Backpressure::new(self.occupancy.subscribe(), self.high_water)
+    }
+
+    /// Offline buffer shared with this emitter, e.g. for requeueing dead
+    /// letters through [`commands::register_commands`].
+    #[must_use]
+    pub fn buffer(&self) -> &OfflineReplayBuffer {
+        &self.buffer
+    }
+
+    /// Encrypt and sign every entry emitted from now on.
+    #[cfg(feature = "encryption")]
+    #[must_use]
+    pub fn with_sealer(mut self, sealer: Arc<seal::ManifestSealer>) -> Self {
+        self.sealer = Some(sealer);
+        self
+    }
+
+    /// Build the emitter together with its offline buffer, recovering
+    /// journaled entries when `offline_buffer_path` is set.
+    pub fn from_config(
+        config: ManifestEmitterConfig,
+        queue: Arc<Q>,
+    ) -> Result<Self, ManifestError> {
+        let buffer = match &config.offline_buffer_path {
+            Some(path) => OfflineReplayBuffer::open(
+                path.clone(),
+                config.retention_max_entries,
+                config.retention_max_age,
+            )
+            .map_err(|error| ManifestError::Buffer(error.to_string()))?,
+            None => {
+                OfflineReplayBuffer::new(config.retention_max_entries, config.retention_max_age)
+            }
+        };
+        Ok(Self::new(config, buffer, queue))
+    }
+
+    /// [`ManifestEmitter::from_config`] that continues after the last
+    /// entries recorded in `checkpoint` and keeps it up to date, so a
+    /// restart neither re-emits delivered entries nor skips sequences.
+    pub fn resume_from_checkpoint(
+        config: ManifestEmitterConfig,
+        checkpoint: Arc<ManifestCheckpoint>,
+        queue: Arc<Q>,
+    ) -> Result<Self, ManifestError> {
+        let mut emitter = Self::from_config(config, queue)?;
+        if let Some(last) = checkpoint.high_water() {
+            emitter.next_sequence = emitter.next_sequence.max(last + 1);
         }
+        emitter.checkpoint = Some(checkpoint);
+        Ok(emitter)
+    }
+
+    #[must_use]
+    pub fn checkpoint(&self) -> Option<&Arc<ManifestCheckpoint>> {
+        self.checkpoint.as_ref()
+    }
+
+    /// Emit the manifest entry for `diff`; `batch` must carry one chunk ref
+    /// per vector so the persisted vectors remain traceable.
+    pub fn emit(&mut self, diff: ManifestDiff, batch: EmbeddingBatch) -> Result<(), ManifestError> {
+        let result = self.emit_entry(diff, batch);
+        self.publish_occupancy();
+        result
+    }
+
+    pub fn flush_offline(&mut self) -> Result<(), ManifestError> {
+        let result = self.flush_buffer();
+        self.publish_occupancy();
+        result
     }
 
-    pub fn emit(
+    fn emit_entry(
         &mut self,
         diff: ManifestDiff,
-        _batch: EmbeddingBatch,
+        batch: EmbeddingBatch,
     ) -> Result<(), ManifestError> {
+        records::check_batch(&batch)?;
+        tracing::debug!(
+            repo_id = %diff.repo_id,
+            encoder_id = %batch.encoder_id,
+            chunks = batch.chunks.len(),
+            "emitting manifest entry"
+        );
         let sequence = self.next_sequence;
-        let mut entry = self.build_entry(&diff, sequence);
-        let mut send_entry = entry.clone();
-        send_entry.status = "emitted".into();
-        match self.queue.send(send_entry) {
+        let entry = diff.replay_entry(sequence);
+        #[cfg(feature = "encryption")]
+        let entry = match &self.sealer {
+            Some(sealer) => sealer.seal(&diff, entry)?,
+            None => entry,
+        };
+        let send_entry = ReplayEntry {
+            status: "emitted".into(),
+            ..entry.clone()
+        };
+        let sent = self.queue.send(send_entry);
+        self.queue_online = sent.is_ok();
+        match sent {
             Ok(()) => {
                 self.next_sequence = sequence + 1;
-                Ok(())
+                self.record_checkpoint(&entry)
             }
             Err(err) => {
-                entry.status = "buffered".into();
-                self.buffer
-                    .push(entry)
-                    .map_err(|error| ManifestError::Buffer(error.to_string()))?;
                 self.next_sequence = sequence + 1;
+                if !self.give_up(&entry, &err)? {
+                    if self.buffer.len() >= self.config.retention_max_entries {
+                        tracing::warn!(
+                            repo_id = %entry.repo_id,
+                            sequence,
+                            "offline buffer full; evicting oldest manifest entry"
+                        );
+                    }
+                    self.buffer
+                        .push(ReplayEntry {
+                            status: "buffered".into(),
+                            ..entry
+                        })
+                        .map_err(|error| ManifestError::Buffer(error.to_string()))?;
+                }
                 Err(ManifestError::QueueOffline(err.to_string()))
             }
         }
     }
 
-    pub fn flush_offline(&mut self) -> Result<(), ManifestError> {
+    fn flush_buffer(&mut self) -> Result<(), ManifestError> {
         let mut drained: VecDeque<_> = self.buffer.drain_ready().into();
-        while let Some(mut ready) = drained.pop_front() {
+        while let Some(ready) = drained.pop_front() {
             let sequence = ready.entry.sequence;
-            ready.entry.status = "emitted".into();
-            if let Err(err) = self.queue.send(ready.entry.clone()) {
-                // push back into buffer to retry later and preserve ordering
-                ready.entry.status = "buffered".into();
-                self.buffer
-                    .requeue(ready)
-                    .map_err(|error| ManifestError::Buffer(error.to_string()))?;
-
-                while let Some(mut remaining) = drained.pop_front() {
-                    remaining.entry.status = "buffered".into();
-                    self.buffer
-                        .requeue(remaining)
-                        .map_err(|error| ManifestError::Buffer(error.to_string()))?;
+            if ready.entry.status != REQUEUED_STATUS
+                && self
+                    .checkpoint
+                    .as_ref()
+                    .is_some_and(|checkpoint| checkpoint.covers(&ready.entry.repo_id, sequence))
+            {
+                tracing::debug!(
+                    repo_id = %ready.entry.repo_id,
+                    sequence,
+                    "dropping buffered manifest entry already delivered"
+                );
+                continue;
+            }
+            let send_entry = ReplayEntry {
+                status: "emitted".into(),
+                ..ready.entry.clone()
+            };
+            let sent = self.queue.send(send_entry);
+            self.queue_online = sent.is_ok();
+            if let Err(err) = sent {
+                match self.give_up(&ready.entry, &err) {
+                    // A poison entry must not hold back the rest of the buffer.
+                    Ok(true) => continue,
+                    Ok(false) => {
+                        // push back into buffer to retry later and preserve ordering
+                        drained.push_front(ready);
+                        self.requeue_all(drained)?;
+                        return Err(ManifestError::QueueOffline(err.to_string()));
+                    }
+                    Err(sink_err) => {
+                        drained.push_front(ready);
+                        self.requeue_all(drained)?;
+                        return Err(sink_err);
+                    }
                 }
-
-                return Err(ManifestError::QueueOffline(err.to_string()));
             }
+            self.attempts.remove(&sequence);
             self.next_sequence = self.next_sequence.max(sequence + 1);
+            if let Err(err) = self.record_checkpoint(&ready.entry) {
+                self.requeue_all(drained)?;
+                return Err(err);
+            }
         }
         Ok(())
     }
 
-    fn build_entry(&self, diff: &ManifestDiff, sequence: u64) -> ReplayEntry {
-        let delayed_ms = diff
-            .applied_at
-            .elapsed()
-            .map(|elapsed| elapsed.as_millis() as u64)
-            .unwrap_or(0);
+    fn requeue_all(&self, drained: VecDeque<ReadyReplayEntry>) -> Result<(), ManifestError> {
+        for mut remaining in drained {
+            if remaining.entry.status != REQUEUED_STATUS {
+                remaining.entry.status = "buffered".into();
+            }
+            self.buffer
+                .requeue(remaining)
+                .map_err(|error| ManifestError::Buffer(error.to_string()))?;
+        }
+        Ok(())
+    }
 
-        ReplayEntry {
-            sequence,
-            repo_id: diff.repo_id.clone(),
-            delayed_ms,
-            payload_checksum_before: diff.checksum_before.clone(),
-            payload_checksum_after: diff.checksum_after.clone(),
-            status: String::from("pending"),
+    /// Count a failed send of `entry` and dead-letter it once the policy is
+    /// exhausted. Returns whether the entry was dead-lettered.
+    fn give_up(&mut self, entry: &ReplayEntry, err: &anyhow::Error) -> Result<bool, ManifestError> {
+        let Some(dead_letters) = &self.dead_letters else {
+            return Ok(false);
+        };
+        let attempts = self.attempts.entry(entry.sequence).or_insert(0);
+        *attempts += 1;
+        if *attempts < dead_letters.policy.max_attempts {
+            return Ok(false);
+        }
+        let attempts = *attempts;
+        tracing::warn!(
+            repo_id = %entry.repo_id,
+            sequence = entry.sequence,
+            attempts,
+            error = %err,
+            "dead-lettering manifest entry"
+        );
+        dead_letters.sink.dead_letter(DeadLetter {
+            entry: ReplayEntry {
+                status: "dead_lettered".into(),
+                ..entry.clone()
+            },
+            attempts,
+            last_error: err.to_string(),
+            dead_lettered_at: SystemTime::now(),
+        })?;
+        self.attempts.remove(&entry.sequence);
+        Ok(true)
+    }
+
+    fn publish_occupancy(&self) {
+        self.occupancy.send_replace(self.occupancy());
+    }
+
+    fn record_checkpoint(&self, entry: &ReplayEntry) -> Result<(), ManifestError> {
+        match &self.checkpoint {
+            Some(checkpoint) => checkpoint.record(&entry.repo_id, entry.sequence),
+            None => Ok(()),
         }
     }
 }
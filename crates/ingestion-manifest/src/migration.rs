@@ -0,0 +1,364 @@
+//! Dual-write migration between embedding encoders.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use ingestion_embedding::Embedder;
+use ingestion_sanitization::SanitizedChunk;
+use serde::{Deserialize, Serialize};
+use storage_ledger::ReplayEntry;
+use storage_vector::Store;
+
+use crate::records::persist_batch;
+use crate::ManifestError;
+
+/// Version of the migration registry layout this build understands.
+pub const MIGRATION_VERSION: u32 = 1;
+
+/// In-flight switch of one repository from `from_encoder` to `to_encoder`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncoderMigration {
+    pub from_encoder: String,
+    pub to_encoder: String,
+    /// Chunks the new encoder must cover before cut-over is allowed.
+    pub expected_chunks: usize,
+    /// Plan ids already written with the new encoder.
+    pub migrated: BTreeSet<String>,
+}
+
+/// Progress of a repository's migration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationProgress {
+    pub repo_id: String,
+    pub from_encoder: String,
+    pub to_encoder: String,
+    pub migrated: usize,
+    pub expected: usize,
+}
+
+impl MigrationProgress {
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.migrated >= self.expected
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct MigrationFile {
+    version: u32,
+    /// Encoder whose vectors search should read, per repository.
+    active: BTreeMap<String, String>,
+    migrations: BTreeMap<String, EncoderMigration>,
+}
+
+/// Per-repository active encoder and migration progress.
+///
+/// Search reads [`MigrationRegistry::active_encoder`]; [`cut_over`] flips it
+/// in a single persisted write once the new encoder covers every chunk.
+///
+/// [`cut_over`]: MigrationRegistry::cut_over
+#[derive(Debug)]
+pub struct MigrationRegistry {
+    path: Option<PathBuf>,
+    state: Mutex<MigrationFile>,
+}
+
+impl Default for MigrationRegistry {
+    fn default() -> Self {
+        Self::in_memory()
+    }
+}
+
+impl MigrationRegistry {
+    #[must_use]
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            state: Mutex::new(MigrationFile {
+                version: MIGRATION_VERSION,
+                ..MigrationFile::default()
+            }),
+        }
+    }
+
+    /// Open a registry persisted at `path`, creating it on first write.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, ManifestError> {
+        let path = path.into();
+        let state = match fs::read(&path) {
+            Ok(bytes) => {
+                let file: MigrationFile = serde_json::from_slice(&bytes).map_err(|err| {
+                    ManifestError::Migration(format!("{}: {err}", path.display()))
+                })?;
+                if file.version != MIGRATION_VERSION {
+                    return Err(ManifestError::Migration(format!(
+                        "{}: unsupported migration registry version {}",
+                        path.display(),
+                        file.version
+                    )));
+                }
+                file
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => MigrationFile {
+                version: MIGRATION_VERSION,
+                ..MigrationFile::default()
+            },
+            Err(err) => {
+                return Err(ManifestError::Migration(format!(
+                    "{}: {err}",
+                    path.display()
+                )))
+            }
+        };
+        Ok(Self {
+            path: Some(path),
+            state: Mutex::new(state),
+        })
+    }
+
+    #[must_use]
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Encoder whose vectors should serve queries for `repo_id`.
+    #[must_use]
+    pub fn active_encoder(&self, repo_id: &str) -> Option<String> {
+        self.lock().active.get(repo_id).cloned()
+    }
+
+    /// Record the encoder a repository is served from outside a migration.
+    pub fn set_active(&self, repo_id: &str, encoder_id: &str) -> Result<(), ManifestError> {
+        let mut state = self.lock();
+        if state.migrations.contains_key(repo_id) {
+            return Err(ManifestError::Migration(format!(
+                "{repo_id} is mid-migration; cut over or abort first"
+            )));
+        }
+        state
+            .active
+            .insert(repo_id.to_string(), encoder_id.to_string());
+        self.persist(&state)
+    }
+
+    /// Begin dual-writing `repo_id` with `to_encoder` alongside the active
+    /// encoder.
+    pub fn start(
+        &self,
+        repo_id: &str,
+        from_encoder: &str,
+        to_encoder: &str,
+        expected_chunks: usize,
+    ) -> Result<(), ManifestError> {
+        if from_encoder == to_encoder {
+            return Err(ManifestError::Migration(format!(
+                "{repo_id} already uses {to_encoder}"
+            )));
+        }
+        let mut state = self.lock();
+        if let Some(existing) = state.migrations.get(repo_id) {
+            return Err(ManifestError::Migration(format!(
+                "{repo_id} is already migrating to {}",
+                existing.to_encoder
+            )));
+        }
+        match state.active.get(repo_id) {
+            Some(active) if active != from_encoder => {
+                return Err(ManifestError::Migration(format!(
+                    "{repo_id} is served by {active}, not {from_encoder}"
+                )));
+            }
+            Some(_) => {}
+            None => {
+                state
+                    .active
+                    .insert(repo_id.to_string(), from_encoder.to_string());
+            }
+        }
+        state.migrations.insert(
+            repo_id.to_string(),
+            EncoderMigration {
+                from_encoder: from_encoder.to_string(),
+                to_encoder: to_encoder.to_string(),
+                expected_chunks,
+                migrated: BTreeSet::new(),
+            },
+        );
+        tracing::info!(
+            %repo_id,
+            %from_encoder,
+            %to_encoder,
+            expected_chunks,
+            "encoder migration started"
+        );
+        self.persist(&state)
+    }
+
+    /// Note that `encoder_id` wrote vectors for `plan_ids`; only writes by
+    /// the migration target count towards progress.
+    pub fn record<'a>(
+        &self,
+        repo_id: &str,
+        encoder_id: &str,
+        plan_ids: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Option<MigrationProgress>, ManifestError> {
+        let mut state = self.lock();
+        let Some(migration) = state.migrations.get_mut(repo_id) else {
+            return Ok(None);
+        };
+        if migration.to_encoder != encoder_id {
+            return Ok(Some(progress(repo_id, migration)));
+        }
+        migration
+            .migrated
+            .extend(plan_ids.into_iter().map(str::to_string));
+        let progress = progress(repo_id, migration);
+        self.persist(&state)?;
+        Ok(Some(progress))
+    }
+
+    #[must_use]
+    pub fn progress(&self, repo_id: &str) -> Option<MigrationProgress> {
+        self.lock()
+            .migrations
+            .get(repo_id)
+            .map(|migration| progress(repo_id, migration))
+    }
+
+    /// Make the new encoder active and end the migration.
+    ///
+    /// Fails unless every expected chunk has been written with the new
+    /// encoder; on success returns the encoder that was retired.
+    pub fn cut_over(&self, repo_id: &str) -> Result<String, ManifestError> {
+        let mut state = self.lock();
+        let Some(migration) = state.migrations.get(repo_id) else {
+            return Err(ManifestError::Migration(format!(
+                "{repo_id} has no migration in progress"
+            )));
+        };
+        let current = progress(repo_id, migration);
+        if !current.is_complete() {
+            return Err(ManifestError::Migration(format!(
+                "{repo_id} has {} of {} chunks on {}",
+                current.migrated, current.expected, current.to_encoder
+            )));
+        }
+        let mut next = state.clone();
+        next.migrations.remove(repo_id);
+        next.active
+            .insert(repo_id.to_string(), current.to_encoder.clone());
+        self.persist(&next)?;
+        *state = next;
+        tracing::info!(
+            %repo_id,
+            from_encoder = %current.from_encoder,
+            to_encoder = %current.to_encoder,
+            "encoder migration cut over"
+        );
+        Ok(current.from_encoder)
+    }
+
+    /// Abandon a migration, leaving the old encoder active.
+    pub fn abort(&self, repo_id: &str) -> Result<Option<EncoderMigration>, ManifestError> {
+        let mut state = self.lock();
+        let removed = state.migrations.remove(repo_id);
+        if removed.is_some() {
+            self.persist(&state)?;
+        }
+        Ok(removed)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MigrationFile> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn persist(&self, state: &MigrationFile) -> Result<(), ManifestError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let io =
+            |err: std::io::Error| ManifestError::Migration(format!("{}: {err}", path.display()));
+        let bytes = serde_json::to_vec_pretty(state).map_err(|err| {
+            ManifestError::Migration(format!("serializing migration registry: {err}"))
+        })?;
+        storage_atomic::write(path, &bytes).map_err(io)
+    }
+}
+
+fn progress(repo_id: &str, migration: &EncoderMigration) -> MigrationProgress {
+    MigrationProgress {
+        repo_id: repo_id.to_string(),
+        from_encoder: migration.from_encoder.clone(),
+        to_encoder: migration.to_encoder.clone(),
+        migrated: migration.migrated.len(),
+        expected: migration.expected_chunks,
+    }
+}
+
+/// Encodes chunks with both the current and the next encoder and persists
+/// both sets of vectors, recording progress in a [`MigrationRegistry`].
+pub struct DualWriter {
+    current: Arc<dyn Embedder>,
+    next: Arc<dyn Embedder>,
+    registry: Arc<MigrationRegistry>,
+}
+
+impl std::fmt::Debug for DualWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DualWriter")
+            .field("current", &self.current.encoder_id())
+            .field("next", &self.next.encoder_id())
+            .finish_non_exhaustive()
+    }
+}
+
+impl DualWriter {
+    pub fn new(
+        current: Arc<dyn Embedder>,
+        next: Arc<dyn Embedder>,
+        registry: Arc<MigrationRegistry>,
+    ) -> Result<Self, ManifestError> {
+        if current.encoder_id() == next.encoder_id() {
+            return Err(ManifestError::Migration(format!(
+                "dual-write needs two encoders, got {} twice",
+                current.encoder_id()
+            )));
+        }
+        Ok(Self {
+            current,
+            next,
+            registry,
+        })
+    }
+
+    #[must_use]
+    pub fn registry(&self) -> &Arc<MigrationRegistry> {
+        &self.registry
+    }
+
+    /// Encode `chunks` with both encoders and write every vector to `store`.
+    ///
+    /// Nothing is written unless both encoders succeed.
+    pub async fn write<S: Store>(
+        &self,
+        store: &S,
+        repo_id: &str,
+        chunks: &[SanitizedChunk],
+    ) -> Result<Vec<ReplayEntry>, ManifestError> {
+        let (current, next) = tokio::join!(
+            self.current.encode_batch(chunks),
+            self.next.encode_batch(chunks)
+        );
+        let current = current.map_err(|err| ManifestError::Embedding(err.to_string()))?;
+        let next = next.map_err(|err| ManifestError::Embedding(err.to_string()))?;
+        let mut entries = persist_batch(store, repo_id, &current)?;
+        entries.extend(persist_batch(store, repo_id, &next)?);
+        self.registry.record(
+            repo_id,
+            &next.encoder_id,
+            next.chunks.iter().map(|chunk| chunk.plan_id.as_str()),
+        )?;
+        Ok(entries)
+    }
+}
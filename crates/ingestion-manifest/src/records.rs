@@ -0,0 +1,91 @@
+//! Persisting embedding batches as traceable vector records.
+
+use ingestion_embedding::{ChunkRef, EmbeddingBatch};
+use serde::{Deserialize, Serialize};
+use storage_ledger::ReplayEntry;
+use storage_vector::Store;
+
+use crate::ManifestError;
+
+/// One stored vector together with the chunk it was computed from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VectorRecord {
+    pub chunk: ChunkRef,
+    pub encoder_id: String,
+    pub vector: Vec<f32>,
+}
+
+impl VectorRecord {
+    /// Pair every vector in `batch` with its provenance.
+    pub fn from_batch(batch: &EmbeddingBatch) -> Result<Vec<Self>, ManifestError> {
+        check_batch(batch)?;
+        Ok(batch
+            .chunks
+            .iter()
+            .zip(&batch.vectors)
+            .map(|(chunk, vector)| Self {
+                chunk: chunk.clone(),
+                encoder_id: batch.encoder_id.clone(),
+                vector: vector.clone(),
+            })
+            .collect())
+    }
+}
+
+/// Store key for the vector `encoder_id` produced for `plan_id`, so vectors
+/// from different encoders can coexist.
+#[must_use]
+pub fn record_key(encoder_id: &str, plan_id: &str) -> String {
+    format!("{encoder_id}::{plan_id}")
+}
+
+/// Write each vector of `batch` to `store` under its [`record_key`],
+/// returning the replay entries for the writes.
+pub fn persist_batch<S: Store>(
+    store: &S,
+    repo_id: &str,
+    batch: &EmbeddingBatch,
+) -> Result<Vec<ReplayEntry>, ManifestError> {
+    VectorRecord::from_batch(batch)?
+        .iter()
+        .map(|record| {
+            let payload =
+                serde_json::to_vec(record).map_err(|err| ManifestError::Store(err.to_string()))?;
+            store
+                .upsert(
+                    repo_id,
+                    &record_key(&record.encoder_id, &record.chunk.plan_id),
+                    &payload,
+                )
+                .map_err(|err| ManifestError::Store(err.to_string()))
+        })
+        .collect()
+}
+
+/// Load the record `encoder_id` stored for `plan_id`, if any.
+pub fn load_record<S: Store>(
+    store: &S,
+    repo_id: &str,
+    encoder_id: &str,
+    plan_id: &str,
+) -> Result<Option<VectorRecord>, ManifestError> {
+    store
+        .get(repo_id, &record_key(encoder_id, plan_id))
+        .map_err(|err| ManifestError::Store(err.to_string()))?
+        .map(|bytes| {
+            serde_json::from_slice(&bytes).map_err(|err| ManifestError::Store(err.to_string()))
+        })
+        .transpose()
+}
+
+/// Reject batches whose provenance does not line up with their vectors.
+pub(crate) fn check_batch(batch: &EmbeddingBatch) -> Result<(), ManifestError> {
+    if batch.chunks.len() != batch.vectors.len() {
+        return Err(ManifestError::InvalidBatch(format!(
+            "{} chunk refs for {} vectors",
+            batch.chunks.len(),
+            batch.vectors.len()
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,184 @@
+//! Encrypted and signed manifest entries.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+use storage_ledger::ReplayEntry;
+use storage_vector::encryption::{Encrypter, KeyHandle};
+use storage_vector::kms::KeyManager;
+use storage_vector::tenant_namespace;
+
+use crate::{ManifestDiff, ManifestEmitterConfig, ManifestError};
+
+/// Domain separator for deriving the signing key from the sealing key.
+const SIGNING_CONTEXT: &str = "embednexus manifest signature v1";
+
+/// Diff contents carried encrypted inside a sealed [`ReplayEntry`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestPayload {
+    pub applied_at: SystemTime,
+    pub added_chunks: Vec<String>,
+    pub removed_chunks: Vec<String>,
+    pub checksum_before: String,
+    pub checksum_after: String,
+}
+
+impl From<&ManifestDiff> for ManifestPayload {
+    fn from(diff: &ManifestDiff) -> Self {
+        Self {
+            applied_at: diff.applied_at,
+            added_chunks: diff.added_chunks.clone(),
+            removed_chunks: diff.removed_chunks.clone(),
+            checksum_before: diff.checksum_before.clone(),
+            checksum_after: diff.checksum_after.clone(),
+        }
+    }
+}
+
+/// Seals manifest payloads on emit and verifies them downstream.
+///
+/// The payload is encrypted with the configured [`Encrypter`], bound to the
+/// entry's repository and sequence as AAD. The signature is a keyed BLAKE3
+/// hash, under a key derived from the same secret, over every field except
+/// `status`, prefixed with the key id so consumers can look the key up after
+/// rotation.
+pub struct ManifestSealer {
+    encrypter: Arc<dyn Encrypter>,
+    keys: Arc<dyn KeyManager>,
+    key_id: String,
+}
+
+impl fmt::Debug for ManifestSealer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ManifestSealer")
+            .field("key_id", &self.key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ManifestSealer {
+    /// Seal with the key `key_id` held by `keys`.
+    pub fn new(
+        encrypter: Arc<dyn Encrypter>,
+        keys: Arc<dyn KeyManager>,
+        key_id: impl Into<String>,
+    ) -> Self {
+        Self {
+            encrypter,
+            keys,
+            key_id: key_id.into(),
+        }
+    }
+
+    /// Seal with the key named by `config.encryption_key`, failing early if
+    /// `keys` does not hold it.
+    pub fn from_config(
+        config: &ManifestEmitterConfig,
+        encrypter: Arc<dyn Encrypter>,
+        keys: Arc<dyn KeyManager>,
+    ) -> Result<Self, ManifestError> {
+        keys.get(&config.encryption_key)
+            .map_err(|err| ManifestError::Sealing(format!("{}: {err}", config.encryption_key)))?;
+        Ok(Self::new(encrypter, keys, config.encryption_key.clone()))
+    }
+
+    #[must_use]
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// Attach the encrypted payload of `diff` and a signature to `entry`.
+    pub fn seal(
+        &self,
+        diff: &ManifestDiff,
+        mut entry: ReplayEntry,
+    ) -> Result<ReplayEntry, ManifestError> {
+        let key = self
+            .keys
+            .get(&self.key_id)
+            .map_err(|err| ManifestError::Sealing(format!("{}: {err}", self.key_id)))?;
+        let plaintext = serde_json::to_vec(&ManifestPayload::from(diff))
+            .map_err(|err| ManifestError::Sealing(format!("serializing payload: {err}")))?;
+        let envelope = self
+            .encrypter
+            .seal(&key, &plaintext, aad(&entry).as_bytes())
+            .map_err(ManifestError::Sealing)?;
+        entry.sealed_payload = Some(STANDARD.encode(envelope));
+        entry.signature = Some(format!("{}:{}", key.key_id, sign(&key, &entry)));
+        Ok(entry)
+    }
+
+    /// Check that `entry` was signed with a key known to this sealer and
+    /// has not been altered since.
+    pub fn verify(&self, entry: &ReplayEntry) -> Result<KeyHandle, ManifestError> {
+        let (key_id, digest) = entry
+            .signature
+            .as_deref()
+            .and_then(|signature| signature.rsplit_once(':'))
+            .ok_or(ManifestError::SignatureMismatch(entry.sequence))?;
+        let key = self
+            .keys
+            .get(key_id)
+            .map_err(|err| ManifestError::Sealing(format!("{key_id}: {err}")))?;
+        let expected = blake3::Hash::from_hex(digest)
+            .map_err(|_| ManifestError::SignatureMismatch(entry.sequence))?;
+        // `blake3::Hash` equality is constant time.
+        if expected != signature_hash(&key, entry) {
+            return Err(ManifestError::SignatureMismatch(entry.sequence));
+        }
+        Ok(key)
+    }
+
+    /// Verify `entry` and decrypt its payload.
+    pub fn open(&self, entry: &ReplayEntry) -> Result<ManifestPayload, ManifestError> {
+        let key = self.verify(entry)?;
+        let sealed = entry.sealed_payload.as_deref().ok_or_else(|| {
+            ManifestError::Sealing(format!("entry {} carries no payload", entry.sequence))
+        })?;
+        let envelope = STANDARD
+            .decode(sealed)
+            .map_err(|err| ManifestError::Sealing(format!("entry {}: {err}", entry.sequence)))?;
+        let plaintext = self
+            .encrypter
+            .open(&key, &envelope, aad(entry).as_bytes())
+            .map_err(ManifestError::Sealing)?;
+        serde_json::from_slice(&plaintext)
+            .map_err(|err| ManifestError::Sealing(format!("entry {}: {err}", entry.sequence)))
+    }
+}
+
+fn aad(entry: &ReplayEntry) -> String {
+    let namespace = tenant_namespace(entry.tenant_id.as_deref(), &entry.repo_id);
+    format!("{namespace}:{}", entry.sequence)
+}
+
+fn sign(key: &KeyHandle, entry: &ReplayEntry) -> String {
+    signature_hash(key, entry).to_hex().to_string()
+}
+
+fn signature_hash(key: &KeyHandle, entry: &ReplayEntry) -> blake3::Hash {
+    let signing_key = blake3::derive_key(SIGNING_CONTEXT, key.key_bytes.as_ref());
+    let mut hasher = blake3::Hasher::new_keyed(&signing_key);
+    hasher.update(&entry.sequence.to_le_bytes());
+    hasher.update(&entry.delayed_ms.to_le_bytes());
+    for field in [
+        entry.repo_id.as_str(),
+        entry.payload_checksum_before.as_str(),
+        entry.payload_checksum_after.as_str(),
+        entry.sealed_payload.as_deref().unwrap_or_default(),
+    ] {
+        hasher.update(&(field.len() as u64).to_le_bytes());
+        hasher.update(field.as_bytes());
+    }
+    // Appended only when set so entries signed before tenants existed
+    // still verify.
+    if let Some(tenant_id) = &entry.tenant_id {
+        hasher.update(&(tenant_id.len() as u64).to_le_bytes());
+        hasher.update(tenant_id.as_bytes());
+    }
+    hasher.finalize()
+}
@@ -0,0 +1,131 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use ingestion_embedding::{EmbeddingBatch, EmbeddingConfig, EmbeddingGenerator};
+use ingestion_manifest::{
+    BufferOccupancy, ManifestDiff, ManifestEmitter, ManifestEmitterConfig, ManifestError,
+    ManifestQueue,
+};
+use ingestion_planning::{ChunkPlan, PlannedChunk, RetryPolicy};
+use ingestion_sanitization::{SanitizationConfig, Sanitizer};
+use storage_ledger::{OfflineReplayBuffer, ReplayEntry};
+
+#[derive(Default)]
+struct TestQueue {
+    offline: Mutex<bool>,
+}
+
+impl ManifestQueue for TestQueue {
+    fn send(&self, _entry: ReplayEntry) -> anyhow::Result<()> {
+        if *self.offline.lock().unwrap() {
+            anyhow::bail!("queue offline");
+        }
+        Ok(())
+    }
+}
+
+fn emitter(queue: Arc<TestQueue>) -> ManifestEmitter<TestQueue> {
+    ManifestEmitter::new(
+        ManifestEmitterConfig {
+            sequence_start: 1,
+            encryption_key: "test-key".into(),
+            retention_max_entries: 4,
+            retention_max_age: Duration::from_secs(60),
+            offline_buffer_path: None,
+        },
+        OfflineReplayBuffer::new(4, Duration::from_secs(60)),
+        queue,
+    )
+}
+
+fn diff(index: usize) -> ManifestDiff {
+    ManifestDiff {
+        repo_id: "repo-pressure".into(),
+        applied_at: SystemTime::now(),
+        added_chunks: vec![format!("chunk-{index}")],
+        removed_chunks: vec![],
+        checksum_before: format!("before-{index}"),
+        checksum_after: format!("after-{index}"),
+        tenant_id: None,
+    }
+}
+
+fn embedding() -> EmbeddingBatch {
+    let plan = ChunkPlan {
+        plan_id: "repo-pressure::src/lib.rs::0".into(),
+        repo_id: "repo-pressure".into(),
+        chunker_config: "size=256".into(),
+        source_span: "src/lib.rs:1-20".into(),
+        hash: "hash-0".into(),
+        retry_policy: RetryPolicy::default(),
+        tenant_id: None,
+    };
+    let chunk = Sanitizer::new(SanitizationConfig::default())
+        .expect("sanitizer config should compile")
+        .apply(&PlannedChunk::new(plan, "fn main() {}"))
+        .expect("sanitization should succeed");
+    EmbeddingGenerator::new(EmbeddingConfig::new("encoder-a".into(), 4))
+        .encode(&[chunk])
+        .expect("encoding should succeed")
+}
+
+#[tokio::test]
+async fn gate_closes_near_capacity_and_reopens_after_flush() {
+    let queue = Arc::new(TestQueue::default());
+    *queue.offline.lock().unwrap() = true;
+    let mut emitter = emitter(queue.clone()).with_high_water(3);
+    let gate = emitter.backpressure();
+    assert!(gate.is_ready());
+
+    for index in 0..3 {
+        assert!(gate.is_ready(), "admitted before entry {index}");
+        assert!(matches!(
+            emitter.emit(diff(index), embedding()),
+            Err(ManifestError::QueueOffline(_))
+        ));
+    }
+    assert_eq!(
+        gate.occupancy(),
+        BufferOccupancy {
+            buffered: 3,
+            capacity: 4,
+            queue_online: false,
+        }
+    );
+    assert!((gate.occupancy().ratio() - 0.75).abs() < f64::EPSILON);
+    assert!(!gate.is_ready());
+
+    let waiter = tokio::spawn({
+        let gate = gate.clone();
+        async move { gate.ready().await }
+    });
+    tokio::task::yield_now().await;
+    assert!(!waiter.is_finished());
+
+    *queue.offline.lock().unwrap() = false;
+    emitter.flush_offline().expect("flush");
+    waiter.await.expect("join").expect("gate opens");
+    assert_eq!(emitter.occupancy().buffered, 0);
+    assert!(emitter.occupancy().queue_online);
+}
+
+#[tokio::test]
+async fn dropping_the_emitter_releases_waiters_with_an_error() {
+    let queue = Arc::new(TestQueue::default());
+    *queue.offline.lock().unwrap() = true;
+    let mut emitter = emitter(queue).with_high_water(1);
+    let gate = emitter.backpressure();
+    let _ = emitter.emit(diff(0), embedding());
+    assert!(!gate.is_ready());
+    drop(emitter);
+    assert!(matches!(
+        gate.ready().await,
+        Err(ManifestError::QueueOffline(_))
+    ));
+}
+
+#[test]
+fn default_high_water_is_ninety_percent_of_retention() {
+    let emitter = emitter(Arc::new(TestQueue::default()));
+    assert_eq!(emitter.backpressure().high_water(), 3);
+}
@@ -0,0 +1,220 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use async_trait::async_trait;
+use ingestion_embedding::{EmbeddingBatch, EmbeddingConfig, EmbeddingGenerator};
+use ingestion_manifest::{
+    AsyncManifestQueue, BatchConfig, BatchedManifestEmitter, ManifestDiff, ManifestEmitterConfig,
+    ManifestError, ManifestQueue, SyncQueue,
+};
+use ingestion_planning::{ChunkPlan, PlannedChunk, RetryPolicy};
+use ingestion_sanitization::{SanitizationConfig, Sanitizer};
+use storage_ledger::{OfflineReplayBuffer, ReplayEntry};
+
+#[derive(Default)]
+struct BatchQueue {
+    batches: Mutex<Vec<Vec<ReplayEntry>>>,
+    offline: AtomicBool,
+    in_flight: AtomicUsize,
+    peak_in_flight: AtomicUsize,
+}
+
+impl BatchQueue {
+    fn sequences(&self) -> Vec<u64> {
+        let mut sequences: Vec<u64> = self
+            .batches
+            .lock()
+            .unwrap()
+            .iter()
+            .flatten()
+            .map(|entry| entry.sequence)
+            .collect();
+        sequences.sort_unstable();
+        sequences
+    }
+}
+
+#[async_trait]
+impl AsyncManifestQueue for BatchQueue {
+    async fn send_batch(&self, entries: Vec<ReplayEntry>) -> anyhow::Result<()> {
+        let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak_in_flight.fetch_max(now, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        if self.offline.load(Ordering::SeqCst) {
+            anyhow::bail!("queue offline");
+        }
+        assert!(entries.iter().all(|entry| entry.status == "emitted"));
+        self.batches.lock().unwrap().push(entries);
+        Ok(())
+    }
+}
+
+fn config() -> ManifestEmitterConfig {
+    ManifestEmitterConfig {
+        sequence_start: 1,
+        encryption_key: "test-key".into(),
+        retention_max_entries: 64,
+        retention_max_age: Duration::from_secs(60),
+        offline_buffer_path: None,
+    }
+}
+
+fn buffer() -> OfflineReplayBuffer {
+    OfflineReplayBuffer::new(64, Duration::from_secs(60))
+}
+
+fn diff(index: usize) -> ManifestDiff {
+    ManifestDiff {
+        repo_id: "repo-batch".into(),
+        applied_at: SystemTime::now(),
+        added_chunks: vec![format!("chunk-{index}")],
+        removed_chunks: vec![],
+        checksum_before: format!("before-{index}"),
+        checksum_after: format!("after-{index}"),
+        tenant_id: None,
+    }
+}
+
+fn embedding() -> EmbeddingBatch {
+    let plan = ChunkPlan {
+        plan_id: "repo-batch::src/lib.rs::0".into(),
+        repo_id: "repo-batch".into(),
+        chunker_config: "size=256".into(),
+        source_span: "src/lib.rs:1-20".into(),
+        hash: "hash-0".into(),
+        retry_policy: RetryPolicy::default(),
+        tenant_id: None,
+    };
+    let chunk = Sanitizer::new(SanitizationConfig::default())
+        .expect("sanitizer config should compile")
+        .apply(&PlannedChunk::new(plan, "fn main() {}"))
+        .expect("sanitization should succeed");
+    EmbeddingGenerator::new(EmbeddingConfig::new("encoder-b".into(), 4))
+        .encode(&[chunk])
+        .expect("encoding should succeed")
+}
+
+#[tokio::test(start_paused = true)]
+async fn full_batches_flush_immediately() {
+    let queue = Arc::new(BatchQueue::default());
+    let emitter = BatchedManifestEmitter::new(
+        &config(),
+        BatchConfig {
+            max_batch_entries: 4,
+            ..BatchConfig::default()
+        },
+        buffer(),
+        Arc::clone(&queue),
+    )
+    .expect("valid batching");
+
+    for index in 0..3 {
+        emitter.emit(diff(index), embedding()).await.expect("emit");
+    }
+    assert_eq!(emitter.pending(), 3);
+    assert!(queue.sequences().is_empty());
+    // The fourth entry completes a batch and triggers a flush.
+    assert_eq!(emitter.emit(diff(3), embedding()).await.expect("emit"), 4);
+    assert_eq!(emitter.pending(), 0);
+    assert_eq!(queue.batches.lock().unwrap().len(), 1);
+    assert_eq!(queue.sequences(), vec![1, 2, 3, 4]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn failed_batches_are_buffered_and_resent_concurrently() {
+    let queue = Arc::new(BatchQueue::default());
+    queue.offline.store(true, Ordering::SeqCst);
+    let emitter = BatchedManifestEmitter::new(
+        &config(),
+        BatchConfig {
+            max_batch_entries: 3,
+            flush_interval: Duration::from_secs(1),
+            max_in_flight: 2,
+        },
+        buffer(),
+        Arc::clone(&queue),
+    )
+    .expect("valid batching");
+
+    for index in 0..10 {
+        match emitter.emit(diff(index), embedding()).await {
+            Ok(_) | Err(ManifestError::QueueOffline(_)) => {}
+            Err(err) => panic!("unexpected error: {err}"),
+        }
+    }
+    assert!(matches!(
+        emitter.flush().await,
+        Err(ManifestError::QueueOffline(_))
+    ));
+    assert!(queue.sequences().is_empty());
+
+    queue.offline.store(false, Ordering::SeqCst);
+    let report = emitter.flush().await.expect("flush");
+    assert_eq!(report.entries_sent, 10);
+    assert_eq!(report.batches_sent, 4);
+    assert_eq!(report.entries_buffered, 0);
+    assert_eq!(queue.sequences(), (1..=10).collect::<Vec<_>>());
+    assert_eq!(queue.peak_in_flight.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test(start_paused = true)]
+async fn background_flusher_drains_on_interval() {
+    let queue = Arc::new(BatchQueue::default());
+    let emitter = Arc::new(
+        BatchedManifestEmitter::new(
+            &config(),
+            BatchConfig {
+                flush_interval: Duration::from_secs(5),
+                ..BatchConfig::default()
+            },
+            buffer(),
+            Arc::clone(&queue),
+        )
+        .expect("valid batching"),
+    );
+    let flusher = emitter.spawn_flusher();
+    emitter.emit(diff(0), embedding()).await.expect("emit");
+    tokio::time::sleep(Duration::from_secs(6)).await;
+    assert_eq!(queue.sequences(), vec![1]);
+    flusher.abort();
+}
+
+#[derive(Default)]
+struct EntryQueue(Mutex<Vec<ReplayEntry>>);
+
+impl ManifestQueue for EntryQueue {
+    fn send(&self, entry: ReplayEntry) -> anyhow::Result<()> {
+        self.0.lock().unwrap().push(entry);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn sync_queues_adapt_to_batches() {
+    let inner = Arc::new(EntryQueue::default());
+    let emitter = BatchedManifestEmitter::new(
+        &config(),
+        BatchConfig::default(),
+        buffer(),
+        Arc::new(SyncQueue(Arc::clone(&inner))),
+    )
+    .expect("valid batching");
+    emitter.emit(diff(0), embedding()).await.expect("emit");
+    emitter.emit(diff(1), embedding()).await.expect("emit");
+    emitter.flush().await.expect("flush");
+    let sequences: Vec<u64> = inner.0.lock().unwrap().iter().map(|e| e.sequence).collect();
+    assert_eq!(sequences, vec![1, 2]);
+
+    assert!(BatchedManifestEmitter::new(
+        &config(),
+        BatchConfig {
+            max_in_flight: 0,
+            ..BatchConfig::default()
+        },
+        buffer(),
+        Arc::new(SyncQueue(inner)),
+    )
+    .is_err());
+}
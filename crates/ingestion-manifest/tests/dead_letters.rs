@@ -0,0 +1,268 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use ingestion_embedding::{EmbeddingBatch, EmbeddingConfig, EmbeddingGenerator};
+use ingestion_manifest::commands::{
+    self, DEAD_LETTERS_COMMAND, REPLAY_ADMIN_CAPABILITY, REQUEUE_COMMAND,
+};
+use ingestion_manifest::{
+    DeadLetter, DeadLetterPolicy, DeadLetterQueue, ManifestCheckpoint, ManifestDiff,
+    ManifestEmitter, ManifestEmitterConfig, ManifestError, ManifestQueue,
+};
+use ingestion_planning::{ChunkPlan, PlannedChunk, RetryPolicy};
+use ingestion_sanitization::{SanitizationConfig, Sanitizer};
+use runtime_router::{CommandRouter, HandlerRouter, RouterCommand, SessionContext};
+use serde_json::json;
+use storage_ledger::{OfflineReplayBuffer, ReplayEntry};
+
+/// Queue that rejects everything while offline and poisoned checksums always.
+#[derive(Default)]
+struct TestQueue {
+    sent: Mutex<Vec<u64>>,
+    offline: Mutex<bool>,
+    poison: Mutex<HashSet<String>>,
+}
+
+impl ManifestQueue for TestQueue {
+    fn send(&self, entry: ReplayEntry) -> anyhow::Result<()> {
+        if *self.offline.lock().unwrap() {
+            anyhow::bail!("queue offline");
+        }
+        if self
+            .poison
+            .lock()
+            .unwrap()
+            .contains(&entry.payload_checksum_after)
+        {
+            anyhow::bail!("payload rejected");
+        }
+        self.sent.lock().unwrap().push(entry.sequence);
+        Ok(())
+    }
+}
+
+fn config() -> ManifestEmitterConfig {
+    ManifestEmitterConfig {
+        sequence_start: 1,
+        encryption_key: "test-key".into(),
+        retention_max_entries: 16,
+        retention_max_age: Duration::from_secs(60),
+        offline_buffer_path: None,
+    }
+}
+
+fn diff(suffix: &str) -> ManifestDiff {
+    ManifestDiff {
+        repo_id: "repo-dlq".into(),
+        applied_at: SystemTime::now(),
+        added_chunks: vec![format!("chunk-{suffix}")],
+        removed_chunks: vec![],
+        checksum_before: format!("before-{suffix}"),
+        checksum_after: format!("after-{suffix}"),
+        tenant_id: None,
+    }
+}
+
+fn embedding() -> EmbeddingBatch {
+    let plan = ChunkPlan {
+        plan_id: "repo-dlq::src/lib.rs::0".into(),
+        repo_id: "repo-dlq".into(),
+        chunker_config: "size=256".into(),
+        source_span: "src/lib.rs:1-20".into(),
+        hash: "hash-0".into(),
+        retry_policy: RetryPolicy::default(),
+        tenant_id: None,
+    };
+    let chunk = Sanitizer::new(SanitizationConfig::default())
+        .expect("sanitizer config should compile")
+        .apply(&PlannedChunk::new(plan, "fn main() {}"))
+        .expect("sanitization should succeed");
+    EmbeddingGenerator::new(EmbeddingConfig::new("encoder-a".into(), 4))
+        .encode(&[chunk])
+        .expect("encoding should succeed")
+}
+
+fn emit_offline(emitter: &mut ManifestEmitter<TestQueue>, suffixes: &[&str]) {
+    for suffix in suffixes {
+        assert!(matches!(
+            emitter.emit(diff(suffix), embedding()),
+            Err(ManifestError::QueueOffline(_))
+        ));
+    }
+}
+
+#[test]
+fn poison_entries_are_dead_lettered_without_blocking_the_buffer() {
+    let queue = Arc::new(TestQueue::default());
+    queue.poison.lock().unwrap().insert("after-poison".into());
+    *queue.offline.lock().unwrap() = true;
+    let dead_letters = Arc::new(DeadLetterQueue::in_memory());
+    let mut emitter = ManifestEmitter::new(
+        config(),
+        OfflineReplayBuffer::new(16, Duration::from_secs(60)),
+        queue.clone(),
+    )
+    .with_dead_letters(DeadLetterPolicy { max_attempts: 3 }, dead_letters.clone());
+    emit_offline(&mut emitter, &["a", "poison", "b"]);
+    *queue.offline.lock().unwrap() = false;
+
+    // Second attempt: the poison entry still holds back what follows it.
+    assert!(matches!(
+        emitter.flush_offline(),
+        Err(ManifestError::QueueOffline(_))
+    ));
+    assert_eq!(*queue.sent.lock().unwrap(), vec![1]);
+    assert!(dead_letters.is_empty());
+
+    // Third attempt exhausts the policy and the buffer drains past it.
+    emitter.flush_offline().expect("flush");
+    assert_eq!(*queue.sent.lock().unwrap(), vec![1, 3]);
+    assert!(emitter.buffer().is_empty());
+    let letters = dead_letters.list();
+    assert_eq!(letters.len(), 1);
+    assert_eq!(letters[0].entry.sequence, 2);
+    assert_eq!(letters[0].attempts, 3);
+    assert_eq!(letters[0].last_error, "payload rejected");
+}
+
+#[test]
+fn callback_sinks_receive_dead_letters() {
+    let queue = Arc::new(TestQueue::default());
+    *queue.offline.lock().unwrap() = true;
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let sink = {
+        let received = Arc::clone(&received);
+        move |letter: DeadLetter| received.lock().unwrap().push(letter.entry.sequence)
+    };
+    let mut emitter = ManifestEmitter::new(
+        config(),
+        OfflineReplayBuffer::new(16, Duration::from_secs(60)),
+        queue,
+    )
+    .with_dead_letters(DeadLetterPolicy { max_attempts: 1 }, Arc::new(sink));
+    emit_offline(&mut emitter, &["a", "b"]);
+    assert_eq!(*received.lock().unwrap(), vec![1, 2]);
+    assert!(emitter.buffer().is_empty());
+}
+
+#[tokio::test]
+async fn router_commands_list_and_requeue_dead_letters() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("dead-letters.json");
+    let queue = Arc::new(TestQueue::default());
+    queue.poison.lock().unwrap().insert("after-poison".into());
+    let dead_letters = Arc::new(DeadLetterQueue::open(&path).expect("open"));
+    let mut emitter = ManifestEmitter::resume_from_checkpoint(
+        config(),
+        Arc::new(ManifestCheckpoint::in_memory()),
+        queue.clone(),
+    )
+    .expect("emitter")
+    .with_dead_letters(DeadLetterPolicy { max_attempts: 1 }, dead_letters.clone());
+    emit_offline(&mut emitter, &["poison"]);
+    emitter.emit(diff("a"), embedding()).expect("emit");
+    assert_eq!(DeadLetterQueue::open(&path).expect("reopen").len(), 1);
+
+    let mut router = HandlerRouter::new();
+    commands::register_commands(&mut router, dead_letters.clone(), emitter.buffer().clone());
+    let admin = SessionContext::new("ops", vec![REPLAY_ADMIN_CAPABILITY.into()]);
+    let reader = SessionContext::new("reader", vec!["search".into()]);
+
+    let err = router
+        .dispatch(reader, RouterCommand::new(DEAD_LETTERS_COMMAND, json!({})))
+        .await
+        .expect_err("admin capability required");
+    assert_eq!(err.status_code(), 401);
+
+    let listed = router
+        .dispatch(
+            admin.clone(),
+            RouterCommand::new(DEAD_LETTERS_COMMAND, json!({})),
+        )
+        .await
+        .expect("list dead letters");
+    assert_eq!(listed.payload["dead_letters"][0]["sequence"], json!(1));
+    assert_eq!(listed.payload["dead_letters"][0]["attempts"], json!(1));
+
+    let err = router
+        .dispatch(
+            admin.clone(),
+            RouterCommand::new(REQUEUE_COMMAND, json!({ "sequence": 42 })),
+        )
+        .await
+        .expect_err("unknown sequence");
+    assert_eq!(err.status_code(), 404);
+
+    // Once fixed upstream, the entry is delivered even though the
+    // checkpoint has moved past its sequence.
+    queue.poison.lock().unwrap().clear();
+    router
+        .dispatch(
+            admin,
+            RouterCommand::new(REQUEUE_COMMAND, json!({ "sequence": 1 })),
+        )
+        .await
+        .expect("requeue");
+    assert!(dead_letters.is_empty());
+    emitter.flush_offline().expect("flush");
+    assert_eq!(*queue.sent.lock().unwrap(), vec![2, 1]);
+}
+
+#[tokio::test]
+async fn tenant_sessions_only_see_and_requeue_their_own_dead_letters() {
+    let queue = Arc::new(TestQueue::default());
+    queue.poison.lock().unwrap().insert("after-a".into());
+    queue.poison.lock().unwrap().insert("after-b".into());
+    let dead_letters = Arc::new(DeadLetterQueue::in_memory());
+    let mut emitter = ManifestEmitter::resume_from_checkpoint(
+        config(),
+        Arc::new(ManifestCheckpoint::in_memory()),
+        queue,
+    )
+    .expect("emitter")
+    .with_dead_letters(DeadLetterPolicy { max_attempts: 1 }, dead_letters.clone());
+    for (suffix, tenant_id) in [("a", "team-a"), ("b", "team-b")] {
+        let diff = ManifestDiff {
+            tenant_id: Some(tenant_id.into()),
+            ..diff(suffix)
+        };
+        let _ = emitter.emit(diff, embedding());
+    }
+    assert_eq!(dead_letters.len(), 2);
+
+    let mut router = HandlerRouter::new();
+    commands::register_commands(&mut router, dead_letters.clone(), emitter.buffer().clone());
+    let tenant = |tenant_id: &str| {
+        SessionContext::new("ops", vec![REPLAY_ADMIN_CAPABILITY.into()]).with_tenant(tenant_id)
+    };
+
+    let listed = router
+        .dispatch(
+            tenant("team-b"),
+            RouterCommand::new(DEAD_LETTERS_COMMAND, json!({})),
+        )
+        .await
+        .expect("list dead letters");
+    assert_eq!(listed.payload["dead_letters"].as_array().unwrap().len(), 1);
+    assert_eq!(listed.payload["dead_letters"][0]["sequence"], json!(2));
+
+    let err = router
+        .dispatch(
+            tenant("team-b"),
+            RouterCommand::new(REQUEUE_COMMAND, json!({ "sequence": 1 })),
+        )
+        .await
+        .expect_err("another tenant's dead letter");
+    assert_eq!(err.status_code(), 404);
+    assert!(dead_letters.get(1).is_some());
+
+    router
+        .dispatch(
+            tenant("team-a"),
+            RouterCommand::new(REQUEUE_COMMAND, json!({ "sequence": 1 })),
+        )
+        .await
+        .expect("requeue own dead letter");
+    assert!(dead_letters.get(1).is_none());
+}
@@ -0,0 +1,151 @@
+use std::sync::Arc;
+
+use ingestion_embedding::{Embedder, EmbeddingConfig, EmbeddingGenerator};
+use ingestion_manifest::{load_record, DualWriter, ManifestError, MigrationRegistry};
+use ingestion_planning::{ChunkPlan, PlannedChunk, RetryPolicy};
+use ingestion_sanitization::{SanitizationConfig, SanitizedChunk, Sanitizer};
+use storage_vector::VectorStore;
+
+fn sanitized_chunk(index: usize) -> SanitizedChunk {
+    let plan = ChunkPlan {
+        plan_id: format!("repo-mig::src/lib.rs::{index}"),
+        repo_id: "repo-mig".into(),
+        chunker_config: "size=256".into(),
+        source_span: format!("src/lib.rs:{}-{}", index * 10 + 1, index * 10 + 10),
+        hash: format!("hash-{index}"),
+        retry_policy: RetryPolicy::default(),
+        tenant_id: None,
+    };
+    Sanitizer::new(SanitizationConfig::default())
+        .expect("sanitizer config should compile")
+        .apply(&PlannedChunk::new(plan, format!("fn item_{index}() {{}}")))
+        .expect("sanitization should succeed")
+}
+
+fn encoder(encoder_id: &str, dimensions: usize) -> Arc<dyn Embedder> {
+    Arc::new(EmbeddingGenerator::new(EmbeddingConfig::new(
+        encoder_id.into(),
+        dimensions,
+    )))
+}
+
+#[tokio::test]
+async fn dual_write_keeps_both_encoders_until_cut_over() {
+    let registry = Arc::new(MigrationRegistry::in_memory());
+    registry
+        .set_active("repo-mig", "encoder-v1")
+        .expect("initial encoder");
+    registry
+        .start("repo-mig", "encoder-v1", "encoder-v2", 3)
+        .expect("start migration");
+    let writer = DualWriter::new(
+        encoder("encoder-v1", 4),
+        encoder("encoder-v2", 8),
+        Arc::clone(&registry),
+    )
+    .expect("distinct encoders");
+    let store = VectorStore::new();
+
+    let entries = writer
+        .write(
+            &store,
+            "repo-mig",
+            &[sanitized_chunk(0), sanitized_chunk(1)],
+        )
+        .await
+        .expect("dual write");
+    assert_eq!(entries.len(), 4);
+    let old = load_record(&store, "repo-mig", "encoder-v1", "repo-mig::src/lib.rs::0")
+        .expect("load")
+        .expect("old vector kept");
+    let new = load_record(&store, "repo-mig", "encoder-v2", "repo-mig::src/lib.rs::0")
+        .expect("load")
+        .expect("new vector written");
+    assert_eq!(old.vector.len(), 4);
+    assert_eq!(new.vector.len(), 8);
+
+    let progress = registry.progress("repo-mig").expect("in progress");
+    assert_eq!((progress.migrated, progress.expected), (2, 3));
+    assert!(matches!(
+        registry.cut_over("repo-mig"),
+        Err(ManifestError::Migration(_))
+    ));
+    assert_eq!(
+        registry.active_encoder("repo-mig").as_deref(),
+        Some("encoder-v1")
+    );
+
+    // Re-writing a chunk does not count twice.
+    writer
+        .write(
+            &store,
+            "repo-mig",
+            &[sanitized_chunk(1), sanitized_chunk(2)],
+        )
+        .await
+        .expect("dual write");
+    assert!(registry
+        .progress("repo-mig")
+        .expect("progress")
+        .is_complete());
+    assert_eq!(
+        registry.cut_over("repo-mig").expect("cut over"),
+        "encoder-v1"
+    );
+    assert_eq!(
+        registry.active_encoder("repo-mig").as_deref(),
+        Some("encoder-v2")
+    );
+    assert!(registry.progress("repo-mig").is_none());
+}
+
+#[test]
+fn registry_state_survives_reopen() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("state").join("migrations.json");
+    {
+        let registry = MigrationRegistry::open(&path).expect("open");
+        registry
+            .start("repo-a", "encoder-v1", "encoder-v2", 1)
+            .expect("start");
+        registry
+            .record("repo-a", "encoder-v1", ["ignored"])
+            .expect("record");
+        registry
+            .record("repo-a", "encoder-v2", ["repo-a::0"])
+            .expect("record");
+        registry
+            .start("repo-b", "encoder-v1", "encoder-v3", 5)
+            .expect("start");
+    }
+
+    let registry = MigrationRegistry::open(&path).expect("reopen");
+    let progress = registry.progress("repo-a").expect("persisted");
+    assert_eq!(progress.migrated, 1);
+    assert!(registry
+        .start("repo-a", "encoder-v1", "encoder-v4", 1)
+        .is_err());
+    registry.cut_over("repo-a").expect("complete");
+    registry.abort("repo-b").expect("abort");
+
+    let registry = MigrationRegistry::open(&path).expect("reopen");
+    assert_eq!(
+        registry.active_encoder("repo-a").as_deref(),
+        Some("encoder-v2")
+    );
+    assert_eq!(
+        registry.active_encoder("repo-b").as_deref(),
+        Some("encoder-v1")
+    );
+    assert!(registry.progress("repo-b").is_none());
+}
+
+#[test]
+fn dual_write_requires_distinct_encoders() {
+    assert!(DualWriter::new(
+        encoder("encoder-v1", 4),
+        encoder("encoder-v1", 4),
+        Arc::new(MigrationRegistry::in_memory()),
+    )
+    .is_err());
+}
@@ -0,0 +1,169 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use ingestion_embedding::{EmbeddingBatch, EmbeddingConfig, EmbeddingGenerator};
+use ingestion_manifest::{
+    FanOutQueue, ManifestDiff, ManifestEmitter, ManifestEmitterConfig, ManifestError,
+    ManifestQueue, SuccessPolicy,
+};
+use ingestion_planning::{ChunkPlan, PlannedChunk, RetryPolicy};
+use ingestion_sanitization::{SanitizationConfig, Sanitizer};
+use storage_ledger::{OfflineReplayBuffer, ReplayEntry};
+
+#[derive(Default)]
+struct TestQueue {
+    sent: Mutex<Vec<u64>>,
+    offline: Mutex<bool>,
+}
+
+impl TestQueue {
+    fn set_offline(&self, offline: bool) {
+        *self.offline.lock().unwrap() = offline;
+    }
+
+    fn sent(&self) -> Vec<u64> {
+        self.sent.lock().unwrap().clone()
+    }
+}
+
+impl ManifestQueue for TestQueue {
+    fn send(&self, entry: ReplayEntry) -> anyhow::Result<()> {
+        if *self.offline.lock().unwrap() {
+            anyhow::bail!("queue offline");
+        }
+        self.sent.lock().unwrap().push(entry.sequence);
+        Ok(())
+    }
+}
+
+fn emitter(queue: Arc<FanOutQueue>) -> ManifestEmitter<FanOutQueue> {
+    ManifestEmitter::new(
+        ManifestEmitterConfig {
+            sequence_start: 1,
+            encryption_key: "test-key".into(),
+            retention_max_entries: 16,
+            retention_max_age: Duration::from_secs(60),
+            offline_buffer_path: None,
+        },
+        OfflineReplayBuffer::new(16, Duration::from_secs(60)),
+        queue,
+    )
+}
+
+fn fan_out(policy: SuccessPolicy, targets: &[(&str, &Arc<TestQueue>)]) -> Arc<FanOutQueue> {
+    let queue = targets.iter().fold(
+        FanOutQueue::new(policy, 16, Duration::from_secs(60)),
+        |queue, (name, target)| queue.with_target(*name, Arc::clone(target) as _),
+    );
+    Arc::new(queue)
+}
+
+fn diff(index: usize) -> ManifestDiff {
+    ManifestDiff {
+        repo_id: "repo-fan".into(),
+        applied_at: SystemTime::now(),
+        added_chunks: vec![format!("chunk-{index}")],
+        removed_chunks: vec![],
+        checksum_before: format!("before-{index}"),
+        checksum_after: format!("after-{index}"),
+        tenant_id: None,
+    }
+}
+
+fn embedding() -> EmbeddingBatch {
+    let plan = ChunkPlan {
+        plan_id: "repo-fan::src/lib.rs::0".into(),
+        repo_id: "repo-fan".into(),
+        chunker_config: "size=256".into(),
+        source_span: "src/lib.rs:1-20".into(),
+        hash: "hash-0".into(),
+        retry_policy: RetryPolicy::default(),
+        tenant_id: None,
+    };
+    let chunk = Sanitizer::new(SanitizationConfig::default())
+        .expect("sanitizer config should compile")
+        .apply(&PlannedChunk::new(plan, "fn main() {}"))
+        .expect("sanitization should succeed");
+    EmbeddingGenerator::new(EmbeddingConfig::new("encoder-a".into(), 4))
+        .encode(&[chunk])
+        .expect("encoding should succeed")
+}
+
+#[test]
+fn all_policy_retries_only_the_targets_that_missed_an_entry() {
+    let ledger = Arc::new(TestQueue::default());
+    let remote = Arc::new(TestQueue::default());
+    remote.set_offline(true);
+    let queue = fan_out(
+        SuccessPolicy::All,
+        &[("ledger", &ledger), ("remote", &remote)],
+    );
+    let mut emitter = emitter(queue.clone());
+
+    assert!(matches!(
+        emitter.emit(diff(1), embedding()),
+        Err(ManifestError::QueueOffline(_))
+    ));
+    assert_eq!(ledger.sent(), vec![1]);
+    let status = queue.status();
+    assert_eq!(status[1].name, "remote");
+    assert_eq!(status[1].backlog, 1);
+    assert_eq!(status[1].consecutive_failures, 1);
+
+    // The emitter's retry fails again but does not double-buffer the entry.
+    emitter.flush_offline().expect_err("remote still offline");
+    assert_eq!(queue.status()[1].backlog, 1);
+
+    remote.set_offline(false);
+    emitter.flush_offline().expect("flush");
+    assert_eq!(ledger.sent(), vec![1]);
+    assert_eq!(remote.sent(), vec![1]);
+    assert_eq!(queue.status()[1].last_delivered, Some(1));
+    assert_eq!(queue.status()[1].backlog, 0);
+}
+
+#[test]
+fn any_policy_keeps_lagging_targets_in_order() {
+    let ledger = Arc::new(TestQueue::default());
+    let remote = Arc::new(TestQueue::default());
+    remote.set_offline(true);
+    let queue = fan_out(
+        SuccessPolicy::Any,
+        &[("ledger", &ledger), ("remote", &remote)],
+    );
+    let mut emitter = emitter(queue.clone());
+
+    emitter.emit(diff(1), embedding()).expect("ledger accepts");
+    emitter.emit(diff(2), embedding()).expect("ledger accepts");
+    assert!(emitter.buffer().is_empty());
+    assert_eq!(queue.status()[1].backlog, 2);
+    assert_eq!(queue.flush(), vec!["remote".to_string()]);
+
+    remote.set_offline(false);
+    emitter.emit(diff(3), embedding()).expect("emit");
+    assert_eq!(remote.sent(), vec![1, 2, 3]);
+    assert_eq!(ledger.sent(), vec![1, 2, 3]);
+    assert!(queue.flush().is_empty());
+}
+
+#[test]
+fn quorum_policy_counts_accepting_targets() {
+    let targets: Vec<Arc<TestQueue>> = (0..3).map(|_| Arc::default()).collect();
+    targets[2].set_offline(true);
+    let named = [("a", &targets[0]), ("b", &targets[1]), ("c", &targets[2])];
+
+    let mut quorum = emitter(fan_out(SuccessPolicy::Quorum(2), &named));
+    quorum.emit(diff(1), embedding()).expect("two of three");
+
+    targets[1].set_offline(true);
+    assert!(matches!(
+        quorum.emit(diff(2), embedding()),
+        Err(ManifestError::QueueOffline(_))
+    ));
+
+    let mut impossible = emitter(fan_out(SuccessPolicy::Quorum(4), &named));
+    let err = impossible
+        .emit(diff(1), embedding())
+        .expect_err("quorum exceeds targets");
+    assert!(err.to_string().contains("cannot be met"));
+}
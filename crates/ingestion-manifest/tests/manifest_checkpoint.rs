@@ -0,0 +1,192 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use ingestion_embedding::{EmbeddingBatch, EmbeddingConfig, EmbeddingGenerator};
+use ingestion_manifest::{
+    ManifestCheckpoint, ManifestDiff, ManifestEmitter, ManifestEmitterConfig, ManifestError,
+    ManifestQueue,
+};
+use ingestion_planning::{ChunkPlan, PlannedChunk, RetryPolicy};
+use ingestion_sanitization::{SanitizationConfig, Sanitizer};
+use storage_ledger::{OfflineReplayBuffer, ReplayEntry};
+
+#[derive(Default)]
+struct TestQueue {
+    sent: Mutex<Vec<ReplayEntry>>,
+    offline: Mutex<bool>,
+}
+
+impl TestQueue {
+    fn sequences(&self) -> Vec<(String, u64)> {
+        self.sent
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|entry| (entry.repo_id.clone(), entry.sequence))
+            .collect()
+    }
+}
+
+impl ManifestQueue for TestQueue {
+    fn send(&self, entry: ReplayEntry) -> anyhow::Result<()> {
+        if *self.offline.lock().unwrap() {
+            anyhow::bail!("queue offline");
+        }
+        self.sent.lock().unwrap().push(entry);
+        Ok(())
+    }
+}
+
+fn config(dir: &tempfile::TempDir) -> ManifestEmitterConfig {
+    ManifestEmitterConfig {
+        sequence_start: 1,
+        encryption_key: "test-key".into(),
+        retention_max_entries: 8,
+        retention_max_age: Duration::from_secs(60),
+        offline_buffer_path: Some(dir.path().join("offline.jsonl")),
+    }
+}
+
+fn diff(repo_id: &str, suffix: &str) -> ManifestDiff {
+    ManifestDiff {
+        repo_id: repo_id.into(),
+        applied_at: SystemTime::now(),
+        added_chunks: vec![format!("chunk-{suffix}")],
+        removed_chunks: vec![],
+        checksum_before: format!("before-{suffix}"),
+        checksum_after: format!("after-{suffix}"),
+        tenant_id: None,
+    }
+}
+
+fn embedding() -> EmbeddingBatch {
+    let plan = ChunkPlan {
+        plan_id: "repo-a::src/lib.rs::0".into(),
+        repo_id: "repo-a".into(),
+        chunker_config: "size=256".into(),
+        source_span: "src/lib.rs:1-20".into(),
+        hash: "hash-0".into(),
+        retry_policy: RetryPolicy::default(),
+        tenant_id: None,
+    };
+    let chunk = Sanitizer::new(SanitizationConfig::default())
+        .expect("sanitizer config should compile")
+        .apply(&PlannedChunk::new(plan, "fn main() {}"))
+        .expect("sanitization should succeed");
+    EmbeddingGenerator::new(EmbeddingConfig::new("encoder-a".into(), 4))
+        .encode(&[chunk])
+        .expect("encoding should succeed")
+}
+
+#[test]
+fn resumed_emitter_continues_after_checkpoint() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let checkpoint_path = dir.path().join("checkpoint.json");
+    let queue = Arc::new(TestQueue::default());
+    {
+        let checkpoint = Arc::new(ManifestCheckpoint::open(&checkpoint_path).expect("open"));
+        let mut emitter =
+            ManifestEmitter::resume_from_checkpoint(config(&dir), checkpoint, queue.clone())
+                .expect("emitter");
+        emitter
+            .emit(diff("repo-a", "1"), embedding())
+            .expect("emit");
+        emitter
+            .emit(diff("repo-b", "2"), embedding())
+            .expect("emit");
+        emitter
+            .emit(diff("repo-a", "3"), embedding())
+            .expect("emit");
+    }
+
+    let checkpoint = Arc::new(ManifestCheckpoint::open(&checkpoint_path).expect("reopen"));
+    assert_eq!(checkpoint.last_emitted("repo-a"), Some(3));
+    assert_eq!(checkpoint.last_emitted("repo-b"), Some(2));
+    assert_eq!(checkpoint.high_water(), Some(3));
+
+    let mut emitter =
+        ManifestEmitter::resume_from_checkpoint(config(&dir), checkpoint, queue.clone())
+            .expect("emitter");
+    emitter
+        .emit(diff("repo-b", "4"), embedding())
+        .expect("emit");
+    assert_eq!(
+        queue.sequences(),
+        vec![
+            ("repo-a".into(), 1),
+            ("repo-b".into(), 2),
+            ("repo-a".into(), 3),
+            ("repo-b".into(), 4),
+        ]
+    );
+}
+
+#[test]
+fn delivered_entries_left_in_the_buffer_are_not_re_emitted() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let checkpoint =
+        Arc::new(ManifestCheckpoint::open(dir.path().join("checkpoint.json")).unwrap());
+    let queue = Arc::new(TestQueue::default());
+    *queue.offline.lock().unwrap() = true;
+    {
+        let mut emitter = ManifestEmitter::resume_from_checkpoint(
+            config(&dir),
+            checkpoint.clone(),
+            queue.clone(),
+        )
+        .expect("emitter");
+        for suffix in ["1", "2", "3"] {
+            assert!(matches!(
+                emitter.emit(diff("repo-a", suffix), embedding()),
+                Err(ManifestError::QueueOffline(_))
+            ));
+        }
+    }
+    // Another process delivered the first two entries before crashing.
+    checkpoint.record("repo-a", 2).expect("record");
+    assert!(checkpoint.record("repo-a", 1).is_ok());
+    assert_eq!(checkpoint.last_emitted("repo-a"), Some(2));
+
+    *queue.offline.lock().unwrap() = false;
+    let mut emitter =
+        ManifestEmitter::resume_from_checkpoint(config(&dir), checkpoint.clone(), queue.clone())
+            .expect("emitter");
+    emitter.flush_offline().expect("flush");
+    emitter
+        .emit(diff("repo-a", "4"), embedding())
+        .expect("emit");
+    assert_eq!(
+        queue.sequences(),
+        vec![("repo-a".into(), 3), ("repo-a".into(), 4)]
+    );
+    assert_eq!(checkpoint.last_emitted("repo-a"), Some(4));
+}
+
+#[test]
+fn emitters_without_checkpoint_are_unchanged() {
+    let queue = Arc::new(TestQueue::default());
+    let mut emitter = ManifestEmitter::new(
+        ManifestEmitterConfig {
+            offline_buffer_path: None,
+            ..config(&tempfile::tempdir().expect("tempdir"))
+        },
+        OfflineReplayBuffer::new(8, Duration::from_secs(60)),
+        queue.clone(),
+    );
+    assert!(emitter.checkpoint().is_none());
+    emitter
+        .emit(diff("repo-a", "1"), embedding())
+        .expect("emit");
+    assert_eq!(queue.sequences(), vec![("repo-a".into(), 1)]);
+}
+
+#[test]
+fn checkpoint_rejects_unknown_versions() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("checkpoint.json");
+    std::fs::write(&path, r#"{"version":99,"repos":{}}"#).expect("write");
+    assert!(matches!(
+        ManifestCheckpoint::open(&path),
+        Err(ManifestError::Checkpoint(_))
+    ));
+}
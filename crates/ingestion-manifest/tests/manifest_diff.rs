@@ -0,0 +1,83 @@
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use ingestion_manifest::{ManifestDiff, ManifestError};
+use ingestion_planning::{ChunkPlan, ChunkPlanner, PlanManifest, PlannerConfig};
+use ingestion_workspace::{RepoType, WorkspaceDescriptor, WorkspaceFile};
+
+fn workspace(files: Vec<WorkspaceFile>) -> WorkspaceDescriptor {
+    WorkspaceDescriptor {
+        repo_id: "repo-diff".into(),
+        root_path: PathBuf::from("/tmp/repo-diff"),
+        repo_type: RepoType::Git,
+        manifest_cursor: None,
+        ignore_stack: vec![],
+        archives: vec![],
+        latency_windows: vec![],
+        files,
+        tenant_id: None,
+    }
+}
+
+#[test]
+fn computed_diff_matches_incremental_planner() {
+    let planner = ChunkPlanner::new(PlannerConfig::default());
+    let first = workspace(vec![
+        WorkspaceFile::new("docs/guide.md", "# Guide"),
+        WorkspaceFile::new("src/lib.rs", "pub fn one() {}"),
+    ]);
+    let baseline = planner
+        .plan_incremental(&PlanManifest::new("repo-diff"), &first)
+        .expect("plan")
+        .manifest;
+
+    let second = workspace(vec![
+        WorkspaceFile::new("docs/guide.md", "# Guide\nupdated"),
+        WorkspaceFile::new("src/main.rs", "fn main() {}"),
+    ]);
+    let plan_diff = planner.plan_incremental(&baseline, &second).expect("plan");
+    let mut plans = planner.plan(&second).expect("plan");
+    plans.reverse();
+
+    let at = SystemTime::now();
+    let computed = ManifestDiff::compute_at(&baseline, &plans, at).expect("diff");
+    let expected = ManifestDiff::from(&plan_diff);
+    assert_eq!(computed.applied_at, at);
+    assert_eq!(computed.added_chunks, expected.added_chunks);
+    assert_eq!(computed.removed_chunks, expected.removed_chunks);
+    assert_eq!(computed.checksum_before, expected.checksum_before);
+    assert_eq!(computed.checksum_after, expected.checksum_after);
+
+    let again = ManifestDiff::compute_at(&baseline, &plans, at).expect("diff");
+    assert_eq!(again.checksum_after, computed.checksum_after);
+
+    let unchanged = ManifestDiff::compute(&plan_diff.manifest, &plans).expect("diff");
+    assert!(unchanged.added_chunks.is_empty() && unchanged.removed_chunks.is_empty());
+    assert_eq!(unchanged.checksum_before, unchanged.checksum_after);
+}
+
+#[test]
+fn foreign_duplicate_or_malformed_plans_are_rejected() {
+    let planner = ChunkPlanner::new(PlannerConfig::default());
+    let plans = planner
+        .plan(&workspace(vec![WorkspaceFile::new(
+            "src/lib.rs",
+            "pub fn one() {}",
+        )]))
+        .expect("plan");
+    let previous = PlanManifest::new("repo-diff");
+
+    let mut foreign: Vec<ChunkPlan> = plans.clone();
+    foreign[0].repo_id = "repo-other".into();
+    let mut duplicated = plans.clone();
+    duplicated.push(plans[0].clone());
+    let mut malformed = plans.clone();
+    malformed[0].plan_id = "repo-diff::src/lib.rs".into();
+
+    for bad in [foreign, duplicated, malformed] {
+        assert!(matches!(
+            ManifestDiff::compute(&previous, &bad),
+            Err(ManifestError::InvalidPlan(_))
+        ));
+    }
+}
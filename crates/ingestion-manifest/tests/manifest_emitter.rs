@@ -7,8 +7,11 @@ use std::{
 
 use ingestion_embedding::{EmbeddingConfig, EmbeddingGenerator};
 use ingestion_manifest::{ManifestDiff, ManifestEmitter, ManifestEmitterConfig, ManifestQueue};
-use ingestion_planning::{ChunkPlan, PlannedChunk, RetryPolicy};
+use ingestion_planning::{
+    ChunkPlan, ChunkPlanner, PlanManifest, PlannedChunk, PlannerConfig, RetryPolicy,
+};
 use ingestion_sanitization::{SanitizationConfig, Sanitizer};
+use ingestion_workspace::{RepoType, WorkspaceDescriptor, WorkspaceFile};
 use storage_ledger::{OfflineReplayBuffer, ReplayEntry};
 
 #[derive(Default)]
@@ -87,9 +90,11 @@ fn sanitized_payload() -> ingestion_sanitization::SanitizedChunk {
         source_span: "docs/spec.md:1-200".into(),
         hash: "aa55aa".into(),
         retry_policy: RetryPolicy::default(),
+        tenant_id: None,
     };
     let planned = PlannedChunk::new(plan, "# Spec\nSECRET token");
-    let sanitizer = Sanitizer::new(SanitizationConfig::default());
+    let sanitizer =
+        Sanitizer::new(SanitizationConfig::default()).expect("sanitizer config should compile");
     sanitizer
         .apply(&planned)
         .expect("sanitization should succeed")
@@ -111,6 +116,7 @@ fn emitter_flushes_offline_buffer_against_golden() {
         encryption_key: "test-key".into(),
         retention_max_entries: 128,
         retention_max_age: Duration::from_millis(120_000),
+        offline_buffer_path: None,
     };
     let generator = EmbeddingGenerator::new(EmbeddingConfig::new("encoder-z".into(), 6));
     let sanitized = sanitized_payload();
@@ -131,6 +137,7 @@ fn emitter_flushes_offline_buffer_against_golden() {
         removed_chunks: vec![],
         checksum_before: checksum_before.clone(),
         checksum_after: checksum_after.clone(),
+        tenant_id: None,
     };
 
     // Emit while queue is offline, forcing a buffer write.
@@ -169,6 +176,9 @@ fn emitter_respects_configured_sequence_floor_over_buffered_entries() {
                 payload_checksum_before: format!("before-{sequence}"),
                 payload_checksum_after: format!("after-{sequence}"),
                 status: "buffered".into(),
+                sealed_payload: None,
+                signature: None,
+                tenant_id: None,
             })
             .expect("buffer push should succeed");
     }
@@ -178,6 +188,7 @@ fn emitter_respects_configured_sequence_floor_over_buffered_entries() {
         encryption_key: "test-key".into(),
         retention_max_entries: 8,
         retention_max_age: Duration::from_secs(60),
+        offline_buffer_path: None,
     };
 
     let generator = EmbeddingGenerator::new(EmbeddingConfig::new("encoder-z".into(), 6));
@@ -190,6 +201,7 @@ fn emitter_respects_configured_sequence_floor_over_buffered_entries() {
         removed_chunks: vec![],
         checksum_before: "before-floor".into(),
         checksum_after: "after-floor".into(),
+        tenant_id: None,
     };
 
     emitter
@@ -218,6 +230,7 @@ fn emitter_records_delay_based_on_applied_at() {
         encryption_key: "test-key".into(),
         retention_max_entries: 16,
         retention_max_age: Duration::from_secs(60),
+        offline_buffer_path: None,
     };
 
     let generator = EmbeddingGenerator::new(EmbeddingConfig::new("encoder-z".into(), 6));
@@ -231,6 +244,7 @@ fn emitter_records_delay_based_on_applied_at() {
         removed_chunks: vec![],
         checksum_before: "before".into(),
         checksum_after: "after".into(),
+        tenant_id: None,
     };
 
     let before_emit = SystemTime::now();
@@ -268,6 +282,7 @@ fn emitter_records_delay_based_on_applied_at() {
         removed_chunks: vec![],
         checksum_before: "before".into(),
         checksum_after: "after".into(),
+        tenant_id: None,
     };
 
     emitter
@@ -301,6 +316,9 @@ fn flush_offline_handles_queue_failure_mid_flush() {
                 payload_checksum_before: format!("before-{sequence}"),
                 payload_checksum_after: format!("after-{sequence}"),
                 status: "buffered".into(),
+                sealed_payload: None,
+                signature: None,
+                tenant_id: None,
             })
             .expect("buffer push should succeed");
     }
@@ -312,6 +330,7 @@ fn flush_offline_handles_queue_failure_mid_flush() {
         encryption_key: "test-key".into(),
         retention_max_entries: 8,
         retention_max_age: Duration::from_secs(60),
+        offline_buffer_path: None,
     };
 
     let generator = EmbeddingGenerator::new(EmbeddingConfig::new("encoder-z".into(), 6));
@@ -324,6 +343,7 @@ fn flush_offline_handles_queue_failure_mid_flush() {
         removed_chunks: vec![],
         checksum_before: "before-mid".into(),
         checksum_after: "after-mid".into(),
+        tenant_id: None,
     };
 
     emitter
@@ -357,6 +377,9 @@ fn emit_during_active_flush_queues_follow_up() {
                 payload_checksum_before: format!("before-{sequence}"),
                 payload_checksum_after: format!("after-{sequence}"),
                 status: "buffered".into(),
+                sealed_payload: None,
+                signature: None,
+                tenant_id: None,
             })
             .expect("buffer push should succeed");
     }
@@ -366,6 +389,7 @@ fn emit_during_active_flush_queues_follow_up() {
         encryption_key: "test-key".into(),
         retention_max_entries: 8,
         retention_max_age: Duration::from_secs(60),
+        offline_buffer_path: None,
     };
 
     let generator = EmbeddingGenerator::new(EmbeddingConfig::new("encoder-z".into(), 6));
@@ -383,6 +407,7 @@ fn emit_during_active_flush_queues_follow_up() {
         removed_chunks: vec![],
         checksum_before: "before-new".into(),
         checksum_after: "after-new".into(),
+        tenant_id: None,
     };
     emitter
         .emit(
@@ -401,3 +426,114 @@ fn emit_during_active_flush_queues_follow_up() {
     let sequences: Vec<u64> = collected.iter().map(|entry| entry.sequence).collect();
     assert_eq!(sequences, vec![60, 61, 70]);
 }
+
+#[test]
+fn plan_diff_feeds_emitter_directly() {
+    let workspace = WorkspaceDescriptor {
+        repo_id: "repo-plan".into(),
+        root_path: PathBuf::from("/tmp/repo-plan"),
+        repo_type: RepoType::Git,
+        manifest_cursor: None,
+        ignore_stack: vec![],
+        archives: vec![],
+        latency_windows: vec![],
+        files: vec![WorkspaceFile::new("docs/spec.md", "# Spec")],
+        tenant_id: None,
+    };
+    let plan_diff = ChunkPlanner::new(PlannerConfig::default())
+        .plan_incremental(&PlanManifest::new("repo-plan"), &workspace)
+        .expect("planning should succeed");
+
+    let queue = Arc::new(TestQueue::default());
+    let config = ManifestEmitterConfig {
+        sequence_start: 1,
+        encryption_key: "test-key".into(),
+        retention_max_entries: 8,
+        retention_max_age: Duration::from_secs(60),
+        offline_buffer_path: None,
+    };
+    let generator = EmbeddingGenerator::new(EmbeddingConfig::new("encoder-z".into(), 6));
+    let mut emitter = ManifestEmitter::new(
+        config,
+        OfflineReplayBuffer::new(8, Duration::from_secs(60)),
+        queue.clone(),
+    );
+
+    let diff = ManifestDiff::from(&plan_diff);
+    assert_eq!(diff.added_chunks, vec!["repo-plan::docs/spec.md::0"]);
+    emitter
+        .emit(
+            diff,
+            generator
+                .encode(&[sanitized_payload()])
+                .expect("encoding should succeed"),
+        )
+        .expect("emit should succeed");
+
+    let collected = queue.collected();
+    assert_eq!(
+        collected[0].payload_checksum_before,
+        plan_diff.checksum_before
+    );
+    assert_eq!(
+        collected[0].payload_checksum_after,
+        plan_diff.checksum_after
+    );
+}
+
+#[test]
+fn durable_buffer_survives_emitter_restart() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let config = ManifestEmitterConfig {
+        sequence_start: 1,
+        encryption_key: "test-key".into(),
+        retention_max_entries: 8,
+        retention_max_age: Duration::from_secs(60),
+        offline_buffer_path: Some(dir.path().join("offline.jsonl")),
+    };
+    let generator = EmbeddingGenerator::new(EmbeddingConfig::new("encoder-z".into(), 6));
+    let diff = |suffix: &str| ManifestDiff {
+        repo_id: "repo-durable".into(),
+        applied_at: SystemTime::now(),
+        added_chunks: vec![format!("chunk-{suffix}")],
+        removed_chunks: vec![],
+        checksum_before: format!("before-{suffix}"),
+        checksum_after: format!("after-{suffix}"),
+        tenant_id: None,
+    };
+
+    let offline = Arc::new(TestQueue::default());
+    *offline.fail.lock().unwrap() = true;
+    {
+        let mut emitter =
+            ManifestEmitter::from_config(config.clone(), offline.clone()).expect("emitter");
+        for suffix in ["a", "b"] {
+            emitter
+                .emit(
+                    diff(suffix),
+                    generator
+                        .encode(&[sanitized_payload()])
+                        .expect("encoding should succeed"),
+                )
+                .expect_err("queue offline should error");
+        }
+    }
+
+    // A new process picks up the journaled entries and continues numbering.
+    let online = Arc::new(TestQueue::default());
+    let mut emitter = ManifestEmitter::from_config(config, online.clone()).expect("emitter");
+    emitter.flush_offline().expect("flush should succeed");
+    emitter
+        .emit(
+            diff("c"),
+            generator
+                .encode(&[sanitized_payload()])
+                .expect("encoding should succeed"),
+        )
+        .expect("emit should succeed");
+    let collected = online.collected();
+    let sequences: Vec<u64> = collected.iter().map(|entry| entry.sequence).collect();
+    assert_eq!(sequences, vec![1, 2, 3]);
+    assert_eq!(collected[0].payload_checksum_after, "after-a");
+    assert!(collected.iter().all(|entry| entry.status == "emitted"));
+}
@@ -0,0 +1,196 @@
+#![cfg(feature = "encryption")]
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use ingestion_embedding::{EmbeddingBatch, EmbeddingConfig, EmbeddingGenerator};
+use ingestion_manifest::{
+    BatchConfig, BatchedManifestEmitter, ManifestDiff, ManifestEmitter, ManifestEmitterConfig,
+    ManifestError, ManifestPayload, ManifestQueue, ManifestSealer, SyncQueue,
+};
+use ingestion_planning::{ChunkPlan, PlannedChunk, RetryPolicy};
+use ingestion_sanitization::{SanitizationConfig, Sanitizer};
+use storage_ledger::{OfflineReplayBuffer, ReplayEntry};
+use storage_vector::encryption::aes_gcm::AesGcmEncrypter;
+use storage_vector::kms::InMemoryKeyManager;
+
+#[derive(Default)]
+struct CollectingQueue(Mutex<Vec<ReplayEntry>>);
+
+impl ManifestQueue for CollectingQueue {
+    fn send(&self, entry: ReplayEntry) -> anyhow::Result<()> {
+        self.0.lock().unwrap().push(entry);
+        Ok(())
+    }
+}
+
+fn config() -> ManifestEmitterConfig {
+    ManifestEmitterConfig {
+        sequence_start: 1,
+        encryption_key: "manifest-key-1".into(),
+        retention_max_entries: 16,
+        retention_max_age: Duration::from_secs(60),
+        offline_buffer_path: None,
+    }
+}
+
+fn keys() -> Arc<InMemoryKeyManager> {
+    Arc::new(InMemoryKeyManager::new_with_secret(
+        "manifest-key-1",
+        [7u8; 32],
+    ))
+}
+
+fn sealer(keys: Arc<InMemoryKeyManager>) -> Arc<ManifestSealer> {
+    Arc::new(
+        ManifestSealer::from_config(&config(), Arc::new(AesGcmEncrypter::new()), keys)
+            .expect("key is registered"),
+    )
+}
+
+fn diff() -> ManifestDiff {
+    ManifestDiff {
+        repo_id: "repo-sealed".into(),
+        applied_at: SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+        added_chunks: vec!["repo-sealed::src/secret.rs::0".into()],
+        removed_chunks: vec!["repo-sealed::src/old.rs::0".into()],
+        checksum_before: "before".into(),
+        checksum_after: "after".into(),
+        tenant_id: None,
+    }
+}
+
+fn embedding() -> EmbeddingBatch {
+    let plan = ChunkPlan {
+        plan_id: "repo-sealed::src/secret.rs::0".into(),
+        repo_id: "repo-sealed".into(),
+        chunker_config: "size=256".into(),
+        source_span: "src/secret.rs:1-20".into(),
+        hash: "hash-0".into(),
+        retry_policy: RetryPolicy::default(),
+        tenant_id: None,
+    };
+    let chunk = Sanitizer::new(SanitizationConfig::default())
+        .expect("sanitizer config should compile")
+        .apply(&PlannedChunk::new(plan, "fn main() {}"))
+        .expect("sanitization should succeed");
+    EmbeddingGenerator::new(EmbeddingConfig::new("encoder-a".into(), 4))
+        .encode(&[chunk])
+        .expect("encoding should succeed")
+}
+
+fn emit_sealed(keys: Arc<InMemoryKeyManager>) -> ReplayEntry {
+    let queue = Arc::new(CollectingQueue::default());
+    let mut emitter = ManifestEmitter::new(
+        config(),
+        OfflineReplayBuffer::new(16, Duration::from_secs(60)),
+        Arc::clone(&queue),
+    )
+    .with_sealer(sealer(keys));
+    emitter.emit(diff(), embedding()).expect("emit succeeds");
+    let mut sent = queue.0.lock().unwrap();
+    assert_eq!(sent.len(), 1);
+    sent.remove(0)
+}
+
+#[test]
+fn emitted_entries_are_encrypted_and_verifiable() {
+    let keys = keys();
+    let entry = emit_sealed(Arc::clone(&keys));
+    let sealed = entry.sealed_payload.as_deref().expect("payload is sealed");
+    assert!(!sealed.contains("secret.rs"));
+    assert!(entry
+        .signature
+        .as_deref()
+        .is_some_and(|signature| signature.starts_with("manifest-key-1:")));
+
+    let payload = sealer(keys).open(&entry).expect("entry verifies");
+    assert_eq!(
+        payload,
+        ManifestPayload {
+            applied_at: diff().applied_at,
+            added_chunks: diff().added_chunks,
+            removed_chunks: diff().removed_chunks,
+            checksum_before: "before".into(),
+            checksum_after: "after".into(),
+        }
+    );
+}
+
+#[test]
+fn tampered_or_foreign_entries_fail_verification() {
+    let keys = keys();
+    let entry = emit_sealed(Arc::clone(&keys));
+    let verifier = sealer(Arc::clone(&keys));
+
+    // Status changes in transit and is not covered by the signature.
+    let relabelled = ReplayEntry {
+        status: "replayed".into(),
+        ..entry.clone()
+    };
+    assert!(verifier.verify(&relabelled).is_ok());
+
+    for tampered in [
+        ReplayEntry {
+            sequence: entry.sequence + 1,
+            ..entry.clone()
+        },
+        ReplayEntry {
+            payload_checksum_after: "forged".into(),
+            ..entry.clone()
+        },
+        ReplayEntry {
+            signature: None,
+            ..entry.clone()
+        },
+    ] {
+        assert!(matches!(
+            verifier.open(&tampered),
+            Err(ManifestError::SignatureMismatch(_))
+        ));
+    }
+
+    // Same key id, different secret: the origin cannot be confirmed.
+    let impostor = sealer(Arc::new(InMemoryKeyManager::new_with_secret(
+        "manifest-key-1",
+        [9u8; 32],
+    )));
+    assert!(matches!(
+        impostor.verify(&entry),
+        Err(ManifestError::SignatureMismatch(_))
+    ));
+}
+
+#[test]
+fn sealing_requires_a_known_key() {
+    let missing = ManifestSealer::from_config(
+        &ManifestEmitterConfig {
+            encryption_key: "unknown".into(),
+            ..config()
+        },
+        Arc::new(AesGcmEncrypter::new()),
+        keys(),
+    );
+    assert!(matches!(missing, Err(ManifestError::Sealing(_))));
+}
+
+#[tokio::test]
+async fn batched_emitter_seals_entries() {
+    let keys = keys();
+    let inner = Arc::new(CollectingQueue::default());
+    let emitter = BatchedManifestEmitter::new(
+        &config(),
+        BatchConfig::default(),
+        OfflineReplayBuffer::new(16, Duration::from_secs(60)),
+        Arc::new(SyncQueue(Arc::clone(&inner))),
+    )
+    .expect("valid batching")
+    .with_sealer(sealer(Arc::clone(&keys)));
+    emitter.emit(diff(), embedding()).await.expect("emit");
+    emitter.flush().await.expect("flush");
+
+    let sent = inner.0.lock().unwrap();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].status, "emitted");
+    let payload = sealer(keys).open(&sent[0]).expect("entry verifies");
+    assert_eq!(payload.added_chunks, diff().added_chunks);
+}
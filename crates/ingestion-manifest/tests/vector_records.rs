@@ -0,0 +1,103 @@
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use ingestion_embedding::{EmbeddingConfig, EmbeddingGenerator};
+use ingestion_manifest::{
+    load_record, persist_batch, ManifestDiff, ManifestEmitter, ManifestEmitterConfig,
+    ManifestError, ManifestQueue, VectorRecord,
+};
+use ingestion_planning::{ChunkPlan, PlannedChunk, RetryPolicy};
+use ingestion_sanitization::{SanitizationConfig, SanitizedChunk, Sanitizer};
+use storage_ledger::{OfflineReplayBuffer, ReplayEntry};
+use storage_vector::VectorStore;
+
+struct NullQueue;
+
+impl ManifestQueue for NullQueue {
+    fn send(&self, _entry: ReplayEntry) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+fn sanitized_chunk(index: usize, span: &str) -> SanitizedChunk {
+    let plan = ChunkPlan {
+        plan_id: format!("repo-trace::src/lib.rs::{index}"),
+        repo_id: "repo-trace".into(),
+        chunker_config: "size=256".into(),
+        source_span: span.into(),
+        hash: format!("hash-{index}"),
+        retry_policy: RetryPolicy::default(),
+        tenant_id: None,
+    };
+    Sanitizer::new(SanitizationConfig::default())
+        .expect("sanitizer config should compile")
+        .apply(&PlannedChunk::new(plan, format!("fn item_{index}() {{}}")))
+        .expect("sanitization should succeed")
+}
+
+#[test]
+fn persisted_vectors_trace_back_to_source_lines() {
+    let chunks = vec![
+        sanitized_chunk(0, "src/lib.rs:1-20"),
+        sanitized_chunk(1, "src/lib.rs:21-40"),
+    ];
+    let batch = EmbeddingGenerator::new(EmbeddingConfig::new("encoder-t".into(), 4))
+        .encode(&chunks)
+        .expect("encoding should succeed");
+    let store = VectorStore::new();
+    let entries = persist_batch(&store, "repo-trace", &batch).expect("persist");
+    assert_eq!(entries.len(), 2);
+
+    let record = load_record(
+        &store,
+        "repo-trace",
+        "encoder-t",
+        "repo-trace::src/lib.rs::1",
+    )
+    .expect("load")
+    .expect("record stored");
+    assert_eq!(record.chunk.source_span, "src/lib.rs:21-40");
+    assert_eq!(record.chunk.hash, "hash-1");
+    assert_eq!(record.encoder_id, "encoder-t");
+    assert_eq!(record.vector, batch.vectors[1]);
+    assert!(load_record(&store, "repo-trace", "encoder-t", "missing")
+        .expect("load")
+        .is_none());
+}
+
+#[test]
+fn batches_without_matching_provenance_are_rejected() {
+    let mut batch = EmbeddingGenerator::new(EmbeddingConfig::new("encoder-t".into(), 4))
+        .encode(&[sanitized_chunk(0, "src/lib.rs:1-20")])
+        .expect("encoding should succeed");
+    batch.chunks.clear();
+    assert!(matches!(
+        VectorRecord::from_batch(&batch),
+        Err(ManifestError::InvalidBatch(_))
+    ));
+
+    let mut emitter = ManifestEmitter::new(
+        ManifestEmitterConfig {
+            sequence_start: 1,
+            encryption_key: "test-key".into(),
+            retention_max_entries: 8,
+            retention_max_age: Duration::from_secs(60),
+            offline_buffer_path: None,
+        },
+        OfflineReplayBuffer::new(8, Duration::from_secs(60)),
+        Arc::new(NullQueue),
+    );
+    let diff = ManifestDiff {
+        repo_id: "repo-trace".into(),
+        applied_at: SystemTime::now(),
+        added_chunks: vec!["repo-trace::src/lib.rs::0".into()],
+        removed_chunks: vec![],
+        checksum_before: "before".into(),
+        checksum_after: "after".into(),
+        tenant_id: None,
+    };
+    assert!(matches!(
+        emitter.emit(diff, batch),
+        Err(ManifestError::InvalidBatch(_))
+    ));
+}
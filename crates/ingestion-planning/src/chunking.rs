@@ -0,0 +1,211 @@
+//! Chunk boundary selection for [`ChunkStrategy`](crate::ChunkStrategy).
+
+use std::fmt;
+use std::ops::Range;
+
+/// How file content is divided into chunks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChunkStrategy {
+    /// Fixed-size byte windows, snapped back to UTF-8 character boundaries.
+    #[default]
+    Bytes,
+    /// Whole lines packed up to the target size.
+    Lines,
+    /// Blank-line separated paragraphs packed up to the target size.
+    Paragraphs,
+    /// Sentences (terminated by `.`, `!`, or `?` plus whitespace) packed up to the target size.
+    Sentences,
+}
+
+impl fmt::Display for ChunkStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Bytes => "bytes",
+            Self::Lines => "lines",
+            Self::Paragraphs => "paragraphs",
+            Self::Sentences => "sentences",
+        })
+    }
+}
+
+/// Compute chunk byte ranges over `text`.
+///
+/// Boundary-aware strategies greedily pack whole units up to `target` bytes;
+/// units larger than `target` are split at character boundaries. Consecutive
+/// chunks share at most `overlap` bytes, always starting on a unit boundary.
+pub(crate) fn chunk_ranges(
+    text: &str,
+    strategy: ChunkStrategy,
+    target: usize,
+    overlap: usize,
+) -> Vec<Range<usize>> {
+    let target = target.max(1);
+    let units: Vec<Range<usize>> = match strategy {
+        ChunkStrategy::Bytes => char_windows(text, 0..text.len(), target),
+        ChunkStrategy::Lines => split_after(text, |bytes, index| bytes[index] == b'\n'),
+        ChunkStrategy::Paragraphs => split_after(text, |bytes, index| {
+            bytes[index] == b'\n'
+                && index > 0
+                && bytes[index - 1] == b'\n'
+                && bytes.get(index + 1) != Some(&b'\n')
+        }),
+        ChunkStrategy::Sentences => split_after(text, |bytes, index| {
+            bytes[index].is_ascii_whitespace()
+                && bytes
+                    .get(index + 1)
+                    .map_or(true, |next| !next.is_ascii_whitespace())
+                && bytes[..index]
+                    .iter()
+                    .rev()
+                    .find(|byte| !byte.is_ascii_whitespace())
+                    .is_some_and(|byte| matches!(byte, b'.' | b'!' | b'?'))
+        }),
+    };
+    let units: Vec<Range<usize>> = units
+        .into_iter()
+        .flat_map(|unit| char_windows(text, unit, target))
+        .collect();
+
+    let mut ranges = Vec::new();
+    let mut index = 0;
+    while index < units.len() {
+        let start = units[index].start;
+        let mut end = units[index].end;
+        let mut next = index + 1;
+        while next < units.len() && units[next].end - start <= target {
+            end = units[next].end;
+            next += 1;
+        }
+        ranges.push(start..end);
+        if next >= units.len() {
+            break;
+        }
+        index = (index + 1..next)
+            .find(|candidate| end - units[*candidate].start <= overlap)
+            .unwrap_or(next);
+    }
+    ranges
+}
+
+/// Split `text` into units ending just after every byte where `is_boundary` holds.
+fn split_after(text: &str, is_boundary: impl Fn(&[u8], usize) -> bool) -> Vec<Range<usize>> {
+    let bytes = text.as_bytes();
+    let mut units = Vec::new();
+    let mut start = 0;
+    for index in 0..bytes.len() {
+        if is_boundary(bytes, index) {
+            units.push(start..index + 1);
+            start = index + 1;
+        }
+    }
+    if start < bytes.len() {
+        units.push(start..bytes.len());
+    }
+    units
+}
+
+/// Cut `range` into windows of at most `target` bytes without splitting characters.
+fn char_windows(text: &str, range: Range<usize>, target: usize) -> Vec<Range<usize>> {
+    let mut windows = Vec::new();
+    let mut start = range.start;
+    while start < range.end {
+        let mut end = (start + target).min(range.end);
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        if end == start {
+            // A single character wider than the target still forms its own window.
+            end = start + 1;
+            while !text.is_char_boundary(end) {
+                end += 1;
+            }
+        }
+        windows.push(start..end);
+        start = end;
+    }
+    windows
+}
+
+/// Maps byte offsets to 1-based `line:column` positions (columns count characters).
+pub(crate) struct LineIndex<'a> {
+    text: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub(crate) fn new(text: &'a str) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(
+            text.bytes()
+                .enumerate()
+                .filter(|(_, byte)| *byte == b'\n')
+                .map(|(index, _)| index + 1),
+        );
+        Self { text, line_starts }
+    }
+
+    pub(crate) fn position(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|start| *start <= offset) - 1;
+        let column = self.text[self.line_starts[line]..offset].chars().count() + 1;
+        (line + 1, column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slices<'a>(text: &'a str, ranges: &[Range<usize>]) -> Vec<&'a str> {
+        ranges.iter().map(|range| &text[range.clone()]).collect()
+    }
+
+    #[test]
+    fn byte_windows_never_split_characters() {
+        let text = "héllo wörld";
+        let ranges = chunk_ranges(text, ChunkStrategy::Bytes, 2, 0);
+        assert!(ranges.iter().all(|range| text.get(range.clone()).is_some()));
+        assert_eq!(slices(text, &ranges).concat(), text);
+    }
+
+    #[test]
+    fn lines_pack_up_to_target_with_overlap() {
+        let text = "one\ntwo\nthree\nfour\n";
+        let ranges = chunk_ranges(text, ChunkStrategy::Lines, 9, 0);
+        assert_eq!(
+            slices(text, &ranges),
+            vec!["one\ntwo\n", "three\n", "four\n"]
+        );
+
+        let ranges = chunk_ranges(text, ChunkStrategy::Lines, 12, 6);
+        assert_eq!(
+            slices(text, &ranges),
+            vec!["one\ntwo\n", "two\nthree\n", "three\nfour\n"]
+        );
+    }
+
+    #[test]
+    fn paragraphs_and_sentences_split_on_boundaries() {
+        let text = "First para.\n\nSecond para.\n";
+        let ranges = chunk_ranges(text, ChunkStrategy::Paragraphs, 16, 0);
+        assert_eq!(
+            slices(text, &ranges),
+            vec!["First para.\n\n", "Second para.\n"]
+        );
+
+        let text = "One. Two! Three? Four";
+        let ranges = chunk_ranges(text, ChunkStrategy::Sentences, 8, 0);
+        assert_eq!(
+            slices(text, &ranges),
+            vec!["One. ", "Two! ", "Three? ", "Four"]
+        );
+    }
+
+    #[test]
+    fn line_index_reports_character_columns() {
+        let index = LineIndex::new("ab\nçd\n");
+        assert_eq!(index.position(0), (1, 1));
+        assert_eq!(index.position(3), (2, 1));
+        assert_eq!(index.position(5), (2, 2));
+        assert_eq!(index.position(7), (3, 1));
+    }
+}
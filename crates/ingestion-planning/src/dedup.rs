@@ -0,0 +1,239 @@
+//! Chunk deduplication across files and repositories.
+
+use std::collections::HashMap;
+
+use ingestion_workspace::WorkspaceDescriptor;
+
+use crate::{ChunkPlanner, PlannedChunk, PlanningError};
+
+/// Settings for MinHash-based near-duplicate detection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NearDupConfig {
+    /// Number of whitespace-separated tokens per shingle.
+    pub shingle_tokens: usize,
+    /// Number of MinHash permutations in each signature.
+    pub permutations: usize,
+    /// Estimated Jaccard similarity at or above which chunks are collapsed.
+    pub threshold: f32,
+}
+
+impl Default for NearDupConfig {
+    fn default() -> Self {
+        Self {
+            shingle_tokens: 5,
+            permutations: 64,
+            threshold: 0.85,
+        }
+    }
+}
+
+/// How chunks are grouped by the dedup pass.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DedupMode {
+    /// Collapse chunks with identical content hashes.
+    Exact,
+    /// Collapse chunks whose estimated shingle similarity meets the threshold.
+    NearDuplicate(NearDupConfig),
+}
+
+/// A representative chunk plus every source span it stands in for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DedupedChunk {
+    /// First chunk of the group in input order; it is the one to embed.
+    pub chunk: PlannedChunk,
+    /// Plan ids of every member, starting with the representative.
+    pub plan_ids: Vec<String>,
+    /// Source spans of every member, prefixed with their repository id.
+    pub source_spans: Vec<String>,
+}
+
+impl DedupedChunk {
+    fn new(chunk: PlannedChunk) -> Self {
+        let mut group = Self {
+            plan_ids: Vec::new(),
+            source_spans: Vec::new(),
+            chunk: chunk.clone(),
+        };
+        group.absorb(&chunk);
+        group
+    }
+
+    fn absorb(&mut self, chunk: &PlannedChunk) {
+        let plan = chunk.plan();
+        self.plan_ids.push(plan.plan_id.clone());
+        self.source_spans
+            .push(format!("{}:{}", plan.repo_id, plan.source_span));
+    }
+
+    /// Number of chunks collapsed into this group.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.plan_ids.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.plan_ids.is_empty()
+    }
+}
+
+/// Collapse `chunks` according to `mode`, preserving first-seen order.
+///
+/// Chunks are only grouped with chunks of the same tenant, so a
+/// representative never stands in for another tenant's content.
+#[must_use]
+pub fn dedup_chunks(chunks: Vec<PlannedChunk>, mode: DedupMode) -> Vec<DedupedChunk> {
+    match mode {
+        DedupMode::Exact => dedup_exact(chunks),
+        DedupMode::NearDuplicate(config) => dedup_near(chunks, &config),
+    }
+}
+
+fn dedup_exact(chunks: Vec<PlannedChunk>) -> Vec<DedupedChunk> {
+    let mut groups: Vec<DedupedChunk> = Vec::new();
+    let mut by_hash: HashMap<(Option<String>, String), usize> = HashMap::new();
+    for chunk in chunks {
+        let key = (chunk.plan().tenant_id.clone(), chunk.plan().hash.clone());
+        match by_hash.get(&key) {
+            Some(index) => groups[*index].absorb(&chunk),
+            None => {
+                by_hash.insert(key, groups.len());
+                groups.push(DedupedChunk::new(chunk));
+            }
+        }
+    }
+    groups
+}
+
+/// Group chunks by MinHash similarity to each group's representative.
+///
+/// Signatures are split into LSH bands and only groups of the chunk's tenant
+/// sharing a band bucket with it are compared, so the pass stays close to
+/// linear instead of comparing every chunk against every group. The first
+/// candidate in input order that meets the threshold absorbs the chunk.
+fn dedup_near(chunks: Vec<PlannedChunk>, config: &NearDupConfig) -> Vec<DedupedChunk> {
+    let seeds = permutation_seeds(config.permutations.max(1));
+    let rows = band_rows(seeds.len(), config.threshold);
+    let mut groups: Vec<(Vec<u64>, DedupedChunk)> = Vec::new();
+    let mut buckets: HashMap<(Option<String>, usize, u64), Vec<usize>> = HashMap::new();
+    for chunk in chunks {
+        let signature = minhash(chunk.payload(), config.shingle_tokens.max(1), &seeds);
+        let tenant_id = &chunk.plan().tenant_id;
+        let keys: Vec<(Option<String>, usize, u64)> = signature
+            .chunks_exact(rows)
+            .enumerate()
+            .map(|(band, values)| (tenant_id.clone(), band, band_hash(values)))
+            .collect();
+        let mut candidates: Vec<usize> = keys
+            .iter()
+            .filter_map(|key| buckets.get(key))
+            .flatten()
+            .copied()
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+        let matched = candidates
+            .into_iter()
+            .find(|&index| similarity(&groups[index].0, &signature) >= config.threshold);
+        match matched {
+            Some(index) => groups[index].1.absorb(&chunk),
+            None => {
+                for key in keys {
+                    buckets.entry(key).or_default().push(groups.len());
+                }
+                groups.push((signature, DedupedChunk::new(chunk)));
+            }
+        }
+    }
+    groups.into_iter().map(|(_, group)| group).collect()
+}
+
+/// Rows per LSH band for `permutations` values at `threshold`.
+///
+/// With `b` bands of `r` rows, pairs become candidates around similarity
+/// `(1/b)^(1/r)`. The widest bands that keep that point at 80% of the
+/// threshold are chosen, so pairs at the threshold are almost always
+/// compared while unrelated chunks rarely are.
+fn band_rows(permutations: usize, threshold: f32) -> usize {
+    let target = f64::from(threshold.clamp(0.0, 1.0)) * 0.8;
+    (1..=permutations)
+        .take_while(|&rows| {
+            let bands = (permutations / rows) as f64;
+            (1.0 / bands).powf(1.0 / rows as f64) <= target
+        })
+        .last()
+        .unwrap_or(1)
+}
+
+fn band_hash(values: &[u64]) -> u64 {
+    values.iter().fold(0xCBF2_9CE4_8422_2325, |hash, value| {
+        (hash ^ value).wrapping_mul(0x0000_0100_0000_01B3)
+    })
+}
+
+fn permutation_seeds(count: usize) -> Vec<(u64, u64)> {
+    let mut state = 0x9E37_79B9_7F4A_7C15_u64;
+    (0..count)
+        .map(|_| (splitmix64(&mut state) | 1, splitmix64(&mut state)))
+        .collect()
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+fn minhash(payload: &str, shingle_tokens: usize, seeds: &[(u64, u64)]) -> Vec<u64> {
+    let tokens: Vec<&str> = payload.split_whitespace().collect();
+    let shingles: Vec<u64> = if tokens.len() <= shingle_tokens {
+        vec![shingle_hash(&tokens)]
+    } else {
+        tokens.windows(shingle_tokens).map(shingle_hash).collect()
+    };
+    seeds
+        .iter()
+        .map(|(multiplier, offset)| {
+            shingles
+                .iter()
+                .map(|shingle| shingle.wrapping_mul(*multiplier).wrapping_add(*offset))
+                .min()
+                .unwrap_or(u64::MAX)
+        })
+        .collect()
+}
+
+fn shingle_hash(tokens: &[&str]) -> u64 {
+    let mut hasher = blake3::Hasher::new();
+    for token in tokens {
+        hasher.update(token.as_bytes());
+        hasher.update(b" ");
+    }
+    let digest = hasher.finalize();
+    let mut bytes = [0_u8; 8];
+    bytes.copy_from_slice(&digest.as_bytes()[..8]);
+    u64::from_le_bytes(bytes)
+}
+
+fn similarity(left: &[u64], right: &[u64]) -> f32 {
+    let matching = left.iter().zip(right).filter(|(a, b)| a == b).count();
+    matching as f32 / left.len().max(1) as f32
+}
+
+impl ChunkPlanner {
+    /// Plan every workspace and collapse duplicate chunks across all of them,
+    /// never across tenants.
+    pub fn plan_deduped(
+        &self,
+        workspaces: &[WorkspaceDescriptor],
+        mode: DedupMode,
+    ) -> Result<Vec<DedupedChunk>, PlanningError> {
+        let mut chunks = Vec::new();
+        for workspace in workspaces {
+            chunks.extend(self.plan_chunks(workspace)?);
+        }
+        Ok(dedup_chunks(chunks, mode))
+    }
+}
@@ -0,0 +1,199 @@
+//! Incremental planning against the manifest recorded by a previous run.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use blake3::Hasher;
+use ingestion_workspace::WorkspaceDescriptor;
+use serde::{Deserialize, Serialize};
+
+use crate::{plan_id, ChunkPlan, ChunkPlanner, PlannedChunk, PlanningError, RetryPolicy};
+
+/// Chunk recorded in a [`PlanManifest`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestChunk {
+    pub plan_id: String,
+    /// Settings of the profile the chunk was planned with.
+    #[serde(default)]
+    pub chunker_config: String,
+    pub source_span: String,
+    pub hash: String,
+}
+
+/// Chunks planned for one file together with the hash of its full content.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileChunks {
+    pub content_hash: String,
+    pub chunks: Vec<ManifestChunk>,
+}
+
+/// Plan state persisted between runs so unchanged files can be skipped.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanManifest {
+    pub repo_id: String,
+    /// Fingerprint of the planner settings, including per-kind profiles;
+    /// reuse requires a match.
+    pub chunker_config: String,
+    pub files: BTreeMap<String, FileChunks>,
+}
+
+impl PlanManifest {
+    /// Empty manifest used for the first run of a repository.
+    #[must_use]
+    pub fn new(repo_id: impl Into<String>) -> Self {
+        Self {
+            repo_id: repo_id.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Deterministic checksum over every chunk id and hash, in path order.
+    #[must_use]
+    pub fn checksum(&self) -> String {
+        let mut hasher = Hasher::new();
+        for chunk in self.files.values().flat_map(|file| &file.chunks) {
+            hasher.update(chunk.plan_id.as_bytes());
+            hasher.update(b"\0");
+            hasher.update(chunk.hash.as_bytes());
+            hasher.update(b"\n");
+        }
+        hasher.finalize().to_hex().to_string()
+    }
+
+    fn chunk_hashes(&self) -> HashMap<&str, &str> {
+        self.files
+            .values()
+            .flat_map(|file| &file.chunks)
+            .map(|chunk| (chunk.plan_id.as_str(), chunk.hash.as_str()))
+            .collect()
+    }
+}
+
+/// Difference between a previous manifest and the current workspace plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanDiff {
+    pub repo_id: String,
+    /// Tenant owning the repository, copied from the workspace.
+    pub tenant_id: Option<String>,
+    /// Chunks that are new or whose content changed under the same plan id.
+    pub added: Vec<ChunkPlan>,
+    /// Plan ids from the previous manifest that no longer exist.
+    pub removed: Vec<String>,
+    /// Number of chunks carried over unchanged.
+    pub unchanged: usize,
+    pub checksum_before: String,
+    pub checksum_after: String,
+    /// Manifest to persist for the next incremental run.
+    pub manifest: PlanManifest,
+}
+
+impl PlanDiff {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl ChunkPlanner {
+    /// Plan `workspace` relative to `previous`, returning only added or changed
+    /// chunks plus the plan ids that disappeared.
+    ///
+    /// `workspace` must list every file in the repository; files missing from
+    /// it are treated as deleted. Files whose content hash matches the
+    /// previous manifest reuse the recorded spans and chunk hashes instead of
+    /// being re-chunked. Plan ids are numbered per file, so adding, removing
+    /// or reordering files leaves the chunks of every other file untouched.
+    /// When the chunker settings changed, nothing is reused and every chunk
+    /// is reported as added.
+    pub fn plan_incremental(
+        &self,
+        previous: &PlanManifest,
+        workspace: &WorkspaceDescriptor,
+    ) -> Result<PlanDiff, PlanningError> {
+        self.check_archive_quotas(workspace)?;
+        let chunker_config = self.chunker_config();
+        let reusable =
+            previous.repo_id == workspace.repo_id && previous.chunker_config == chunker_config;
+        let files = self.config.ordering.order(&workspace.files);
+
+        let mut manifest = PlanManifest {
+            repo_id: workspace.repo_id.clone(),
+            chunker_config: chunker_config.clone(),
+            files: BTreeMap::new(),
+        };
+        let mut plans: Vec<ChunkPlan> = Vec::new();
+        for file in files {
+            let content_hash = blake3::hash(file.content.as_bytes()).to_hex().to_string();
+            let reused = previous
+                .files
+                .get(&file.path)
+                .filter(|prior| reusable && prior.content_hash == content_hash);
+            let file_plans: Vec<ChunkPlan> = match reused {
+                Some(prior) => prior
+                    .chunks
+                    .iter()
+                    .enumerate()
+                    .map(|(offset, chunk)| ChunkPlan {
+                        plan_id: plan_id(&workspace.repo_id, &file.path, offset),
+                        repo_id: workspace.repo_id.clone(),
+                        tenant_id: workspace.tenant_id.clone(),
+                        chunker_config: chunk.chunker_config.clone(),
+                        source_span: chunk.source_span.clone(),
+                        hash: chunk.hash.clone(),
+                        retry_policy: RetryPolicy::default(),
+                    })
+                    .collect(),
+                None => self
+                    .plan_file(workspace, file)
+                    .into_iter()
+                    .map(PlannedChunk::into_plan)
+                    .collect(),
+            };
+            manifest.files.insert(
+                file.path.clone(),
+                FileChunks {
+                    content_hash,
+                    chunks: file_plans
+                        .iter()
+                        .map(|plan| ManifestChunk {
+                            plan_id: plan.plan_id.clone(),
+                            chunker_config: plan.chunker_config.clone(),
+                            source_span: plan.source_span.clone(),
+                            hash: plan.hash.clone(),
+                        })
+                        .collect(),
+                },
+            );
+            plans.extend(file_plans);
+        }
+
+        let previous_hashes = if reusable {
+            previous.chunk_hashes()
+        } else {
+            HashMap::new()
+        };
+        let current_ids: HashSet<&str> = plans.iter().map(|plan| plan.plan_id.as_str()).collect();
+        let removed = previous
+            .files
+            .values()
+            .flat_map(|file| &file.chunks)
+            .filter(|chunk| !current_ids.contains(chunk.plan_id.as_str()))
+            .map(|chunk| chunk.plan_id.clone())
+            .collect();
+        let total = plans.len();
+        let added: Vec<ChunkPlan> = plans
+            .into_iter()
+            .filter(|plan| previous_hashes.get(plan.plan_id.as_str()) != Some(&plan.hash.as_str()))
+            .collect();
+
+        Ok(PlanDiff {
+            repo_id: workspace.repo_id.clone(),
+            tenant_id: workspace.tenant_id.clone(),
+            unchanged: total - added.len(),
+            added,
+            removed,
+            checksum_before: previous.checksum(),
+            checksum_after: manifest.checksum(),
+            manifest,
+        })
+    }
+}
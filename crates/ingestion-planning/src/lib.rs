@@ -1,10 +1,33 @@
 //! Chunk planner placeholder logic.
 
+use std::collections::BTreeMap;
+use std::time::Duration;
+
 use blake3::Hasher;
-use ingestion_workspace::{ArchiveDescriptor, WorkspaceDescriptor};
+use ingestion_workspace::{ArchiveDescriptor, FileKind, WorkspaceDescriptor, WorkspaceFile};
 use storage_vector::{ArchiveQuotaTracker, ArchiveSample, QuotaError, QuotaLimits};
 use thiserror::Error;
 
+pub mod chunking;
+pub mod dedup;
+pub mod incremental;
+pub mod priority;
+pub mod profile;
+#[cfg(feature = "native")]
+pub mod retry;
+pub mod stream;
+
+pub use chunking::ChunkStrategy;
+pub use dedup::{dedup_chunks, DedupMode, DedupedChunk, NearDupConfig};
+pub use incremental::{FileChunks, ManifestChunk, PlanDiff, PlanManifest};
+pub use priority::{priority_score, PlanOrder, PriorityWeights};
+pub use profile::{default_profiles, ChunkProfile};
+#[cfg(feature = "native")]
+pub use retry::{ChunkError, ChunkReport, RetryExecutor};
+pub use stream::{PlanBatch, PlanCursor, PlanIter, PlannedBatch};
+
+use crate::chunking::{chunk_ranges, LineIndex};
+
 #[derive(Debug, Clone)]
 pub struct PlannerConfig {
     pub target_chunk_bytes: usize,
@@ -13,6 +36,14 @@ pub struct PlannerConfig {
     pub quota_entries_max: Option<u64>,
     pub quota_nesting_max: Option<u32>,
     pub quota_latency_budget_ms: Option<u64>,
+    /// Boundary selection used when slicing file content.
+    pub chunk_strategy: ChunkStrategy,
+    /// Maximum bytes shared between consecutive chunks of the same file.
+    pub overlap_bytes: usize,
+    /// Overrides keyed by detected file kind; other kinds use the settings above.
+    pub profiles: BTreeMap<FileKind, ChunkProfile>,
+    /// Order in which files are planned and emitted.
+    pub ordering: PlanOrder,
 }
 
 impl PlannerConfig {
@@ -25,14 +56,77 @@ impl PlannerConfig {
             quota_entries_max: None,
             quota_nesting_max: None,
             quota_latency_budget_ms: None,
+            chunk_strategy: ChunkStrategy::Bytes,
+            overlap_bytes: 0,
+            profiles: BTreeMap::new(),
+            ordering: PlanOrder::Path,
         }
     }
+
+    /// Apply [`default_profiles`] on top of the current settings.
+    #[must_use]
+    pub fn with_default_profiles(mut self) -> Self {
+        self.profiles = default_profiles();
+        self
+    }
+
+    /// Profile used for files of `kind`.
+    #[must_use]
+    pub fn profile_for(&self, kind: FileKind) -> ChunkProfile {
+        self.profiles.get(&kind).copied().unwrap_or(ChunkProfile {
+            target_chunk_bytes: self.target_chunk_bytes,
+            overlap_bytes: self.overlap_bytes,
+            chunk_strategy: self.chunk_strategy,
+            skip: false,
+        })
+    }
+}
+
+impl Default for PlannerConfig {
+    fn default() -> Self {
+        Self::new(1024, 64)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RetryPolicy {
     pub max_attempts: u32,
+    /// Delay before the first retry; doubles on every further attempt.
     pub backoff_ms: u64,
+    /// Upper bound on the exponential delay, before jitter.
+    pub max_backoff_ms: u64,
+    /// Maximum extra delay added to each retry to spread out retry storms.
+    pub jitter_ms: u64,
+}
+
+impl RetryPolicy {
+    /// Delay to wait after failed attempt number `attempt` (1-based).
+    ///
+    /// Jitter is derived from `seed` (typically the plan id) so retries of
+    /// different chunks spread out while staying reproducible.
+    #[must_use]
+    pub fn delay_for(&self, attempt: u32, seed: &str) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(32);
+        let base = self
+            .backoff_ms
+            .saturating_mul(1_u64 << exponent)
+            .min(self.max_backoff_ms.max(self.backoff_ms));
+        let jitter = if self.jitter_ms == 0 {
+            0
+        } else {
+            let mut hasher = Hasher::new();
+            hasher.update(seed.as_bytes());
+            hasher.update(&attempt.to_le_bytes());
+            let mut bytes = [0_u8; 8];
+            bytes.copy_from_slice(&hasher.finalize().as_bytes()[..8]);
+            let sample = u64::from_le_bytes(bytes);
+            // A `u64::MAX` jitter already spans every sample.
+            self.jitter_ms
+                .checked_add(1)
+                .map_or(sample, |span| sample % span)
+        };
+        Duration::from_millis(base.saturating_add(jitter))
+    }
 }
 
 impl Default for RetryPolicy {
@@ -40,6 +134,8 @@ impl Default for RetryPolicy {
         Self {
             max_attempts: 3,
             backoff_ms: 1_000,
+            max_backoff_ms: 30_000,
+            jitter_ms: 250,
         }
     }
 }
@@ -48,6 +144,8 @@ impl Default for RetryPolicy {
 pub struct ChunkPlan {
     pub plan_id: String,
     pub repo_id: String,
+    /// Tenant owning the repository, copied from the workspace.
+    pub tenant_id: Option<String>,
     pub chunker_config: String,
     pub source_span: String,
     pub hash: String,
@@ -77,6 +175,11 @@ impl PlannedChunk {
     pub fn payload(&self) -> &str {
         &self.payload
     }
+
+    #[must_use]
+    pub fn into_plan(self) -> ChunkPlan {
+        self.plan
+    }
 }
 
 #[derive(Debug, Error)]
@@ -87,6 +190,8 @@ pub enum PlanningError {
     QuotaExceeded {
         diagnostics: storage_vector::QuotaDiagnostics,
     },
+    #[error("invalid plan cursor: {detail}")]
+    InvalidCursor { detail: String },
 }
 
 #[derive(Debug, Clone)]
@@ -100,58 +205,108 @@ impl ChunkPlanner {
         Self { config }
     }
 
+    /// Plan a workspace as a single batch, truncated to `max_chunks_per_batch`.
+    ///
+    /// Use [`ChunkPlanner::plan_iter`] to receive every chunk in batches.
     pub fn plan(&self, workspace: &WorkspaceDescriptor) -> Result<Vec<ChunkPlan>, PlanningError> {
+        Ok(self
+            .plan_chunks(workspace)?
+            .into_iter()
+            .map(PlannedChunk::into_plan)
+            .collect())
+    }
+
+    /// Same as [`ChunkPlanner::plan`], but keeps each chunk's payload alongside its plan.
+    pub fn plan_chunks(
+        &self,
+        workspace: &WorkspaceDescriptor,
+    ) -> Result<Vec<PlannedChunk>, PlanningError> {
         self.check_archive_quotas(workspace)?;
-        let chunk_size = self.config.target_chunk_bytes.max(1);
-        let mut files = workspace.files.clone();
-        files.sort_by(|a, b| a.path.cmp(&b.path));
-        let mut plans = Vec::new();
-        let mut global_index: usize = 0;
-        for file in &files {
-            let bytes = file.content.as_bytes();
-            if bytes.is_empty() {
-                let plan_id = format!("{}::{}::{}", workspace.repo_id, file.path, global_index);
-                plans.push(ChunkPlan {
-                    plan_id,
-                    repo_id: workspace.repo_id.clone(),
-                    chunker_config: format!(
-                        "bytes={chunk_size};max={}",
-                        self.config.max_chunks_per_batch
-                    ),
-                    source_span: format!("{}:0-0", file.path),
-                    hash: blake3::hash(&[]).to_hex().to_string(),
-                    retry_policy: RetryPolicy::default(),
-                });
-                global_index += 1;
-                continue;
-            }
-            let mut offset = 0usize;
-            while offset < bytes.len() {
-                let end = (offset + chunk_size).min(bytes.len());
-                let slice = &bytes[offset..end];
+        let mut chunks = Vec::new();
+        for file in self.config.ordering.order(&workspace.files) {
+            chunks.extend(self.plan_file(workspace, file));
+        }
+        if chunks.len() > self.config.max_chunks_per_batch {
+            tracing::warn!(
+                repo_id = %workspace.repo_id,
+                planned = chunks.len(),
+                kept = self.config.max_chunks_per_batch,
+                "truncating plan to max_chunks_per_batch; use plan_iter to stream every chunk"
+            );
+            chunks.truncate(self.config.max_chunks_per_batch);
+        }
+        Ok(chunks)
+    }
+
+    /// Chunk a single file with its kind's profile. Skipped kinds yield no
+    /// chunks; empty files yield one.
+    fn plan_file(
+        &self,
+        workspace: &WorkspaceDescriptor,
+        file: &WorkspaceFile,
+    ) -> Vec<PlannedChunk> {
+        let profile = self.config.profile_for(file.file_kind);
+        if profile.skip {
+            return Vec::new();
+        }
+        let chunker_config = profile.describe(self.config.max_chunks_per_batch);
+        let text = file.content.as_str();
+        let mut ranges = chunk_ranges(
+            text,
+            profile.chunk_strategy,
+            profile.target_chunk_bytes.max(1),
+            profile.overlap_bytes,
+        );
+        if ranges.is_empty() {
+            ranges.push(0..0);
+        }
+        let lines = LineIndex::new(text);
+        let repo_id = &workspace.repo_id;
+        ranges
+            .into_iter()
+            .enumerate()
+            .map(|(offset, range)| {
+                let payload = &text[range.clone()];
                 let mut hasher = Hasher::new();
-                hasher.update(slice);
-                let hash = hasher.finalize().to_hex().to_string();
-                let plan_id = format!("{}::{}::{}", workspace.repo_id, file.path, global_index);
-                plans.push(ChunkPlan {
-                    plan_id,
-                    repo_id: workspace.repo_id.clone(),
-                    chunker_config: format!(
-                        "bytes={chunk_size};max={}",
-                        self.config.max_chunks_per_batch
+                hasher.update(payload.as_bytes());
+                let plan = ChunkPlan {
+                    plan_id: plan_id(repo_id, &file.path, offset),
+                    repo_id: repo_id.clone(),
+                    tenant_id: workspace.tenant_id.clone(),
+                    chunker_config: chunker_config.clone(),
+                    source_span: source_span(
+                        profile.chunk_strategy,
+                        &file.path,
+                        &lines,
+                        range.start,
+                        range.end,
                     ),
-                    source_span: format!("{}:{}-{}", file.path, offset, end),
-                    hash,
+                    hash: hasher.finalize().to_hex().to_string(),
                     retry_policy: RetryPolicy::default(),
-                });
-                offset = end;
-                global_index += 1;
+                };
+                PlannedChunk::new(plan, payload)
+            })
+            .collect()
+    }
+
+    /// Settings fingerprint covering the base config and every profile override.
+    fn chunker_config(&self) -> String {
+        let mut config = self
+            .config
+            .profile_for(FileKind::Unknown)
+            .describe(self.config.max_chunks_per_batch);
+        for (kind, profile) in &self.config.profiles {
+            if profile.skip {
+                config.push_str(&format!("|{}=skip", kind.as_str()));
+            } else {
+                config.push_str(&format!(
+                    "|{}:{}",
+                    kind.as_str(),
+                    profile.describe(self.config.max_chunks_per_batch)
+                ));
             }
         }
-        if plans.len() > self.config.max_chunks_per_batch {
-            plans.truncate(self.config.max_chunks_per_batch);
-        }
-        Ok(plans)
+        config
     }
 
     fn check_archive_quotas(&self, workspace: &WorkspaceDescriptor) -> Result<(), PlanningError> {
@@ -188,3 +343,26 @@ impl ChunkPlanner {
         }
     }
 }
+
+/// Id of the chunk at `offset` within `path`. Numbering restarts in every
+/// file, so adding, removing or reordering other files never renumbers it.
+fn plan_id(repo_id: &str, path: &str, offset: usize) -> String {
+    format!("{repo_id}::{path}::{offset}")
+}
+
+/// Byte strategies keep `path:start-end` offsets; boundary-aware strategies
+/// report `path:line:col-line:col` with an exclusive end position.
+fn source_span(
+    strategy: ChunkStrategy,
+    path: &str,
+    lines: &LineIndex<'_>,
+    start: usize,
+    end: usize,
+) -> String {
+    if strategy == ChunkStrategy::Bytes {
+        return format!("{path}:{start}-{end}");
+    }
+    let (start_line, start_column) = lines.position(start);
+    let (end_line, end_column) = lines.position(end);
+    format!("{path}:{start_line}:{start_column}-{end_line}:{end_column}")
+}
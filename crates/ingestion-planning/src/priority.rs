@@ -0,0 +1,99 @@
+//! File ordering policy for plan emission.
+
+use std::cmp::Ordering;
+
+use ingestion_workspace::{FileKind, WorkspaceFile};
+
+/// Weights combined into a per-file priority score; higher scores plan first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PriorityWeights {
+    /// Bonus for top-level `README*` files.
+    pub readme: f64,
+    /// Bonus for prose files and anything under a `docs/` or `doc/` directory.
+    pub documentation: f64,
+    /// Scale of the recency bonus, which decays from 1.0 for the newest file.
+    pub recency: f64,
+    /// Age relative to the newest file at which the recency bonus halves.
+    pub recency_half_life_ms: u64,
+}
+
+impl Default for PriorityWeights {
+    fn default() -> Self {
+        Self {
+            readme: 3.0,
+            documentation: 1.0,
+            recency: 2.0,
+            recency_half_life_ms: 24 * 60 * 60 * 1_000,
+        }
+    }
+}
+
+/// Order in which files are planned and therefore emitted in batches.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum PlanOrder {
+    /// Lexicographic path order.
+    #[default]
+    Path,
+    /// Highest priority score first, ties broken by path.
+    ///
+    /// Only the emission order changes; plan ids are numbered within each
+    /// file, so touching a file never renumbers the chunks of another.
+    Priority(PriorityWeights),
+}
+
+impl PlanOrder {
+    /// Sort `files` for planning.
+    pub(crate) fn order<'a>(&self, files: &'a [WorkspaceFile]) -> Vec<&'a WorkspaceFile> {
+        let mut ordered: Vec<&WorkspaceFile> = files.iter().collect();
+        match self {
+            Self::Path => ordered.sort_by(|a, b| a.path.cmp(&b.path)),
+            Self::Priority(weights) => {
+                let newest = files.iter().filter_map(|file| file.mtime_ms).max();
+                let mut scored: Vec<(f64, &WorkspaceFile)> = ordered
+                    .into_iter()
+                    .map(|file| (priority_score(file, weights, newest), file))
+                    .collect();
+                scored.sort_by(|(left_score, left), (right_score, right)| {
+                    right_score
+                        .partial_cmp(left_score)
+                        .unwrap_or(Ordering::Equal)
+                        .then_with(|| left.path.cmp(&right.path))
+                });
+                ordered = scored.into_iter().map(|(_, file)| file).collect();
+            }
+        }
+        ordered
+    }
+}
+
+/// Score one file; `newest` is the most recent modification time in the workspace.
+#[must_use]
+pub fn priority_score(file: &WorkspaceFile, weights: &PriorityWeights, newest: Option<u64>) -> f64 {
+    let mut score = 0.0;
+    if is_readme(&file.path) {
+        score += weights.readme;
+    }
+    if is_documentation(file) {
+        score += weights.documentation;
+    }
+    if let (Some(mtime), Some(newest)) = (file.mtime_ms, newest) {
+        let age = newest.saturating_sub(mtime) as f64;
+        let half_life = weights.recency_half_life_ms.max(1) as f64;
+        score += weights.recency * 0.5_f64.powf(age / half_life);
+    }
+    score
+}
+
+fn is_readme(path: &str) -> bool {
+    !path.contains('/') && path.to_ascii_lowercase().starts_with("readme")
+}
+
+fn is_documentation(file: &WorkspaceFile) -> bool {
+    matches!(file.file_kind, FileKind::Markdown | FileKind::Text)
+        || file
+            .path
+            .split('/')
+            .rev()
+            .skip(1)
+            .any(|segment| segment == "docs" || segment == "doc")
+}
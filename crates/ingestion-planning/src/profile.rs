@@ -0,0 +1,84 @@
+//! Per-file-kind chunking profiles.
+
+use std::collections::BTreeMap;
+
+use ingestion_workspace::FileKind;
+
+use crate::ChunkStrategy;
+
+/// Chunking settings applied to files of one [`FileKind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkProfile {
+    pub target_chunk_bytes: usize,
+    pub overlap_bytes: usize,
+    pub chunk_strategy: ChunkStrategy,
+    /// Files of this kind produce no chunks at all.
+    pub skip: bool,
+}
+
+impl ChunkProfile {
+    #[must_use]
+    pub const fn new(target_chunk_bytes: usize, chunk_strategy: ChunkStrategy) -> Self {
+        Self {
+            target_chunk_bytes,
+            overlap_bytes: 0,
+            chunk_strategy,
+            skip: false,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_overlap(mut self, overlap_bytes: usize) -> Self {
+        self.overlap_bytes = overlap_bytes;
+        self
+    }
+
+    /// Profile that excludes matching files from planning.
+    #[must_use]
+    pub const fn skip() -> Self {
+        Self {
+            target_chunk_bytes: 0,
+            overlap_bytes: 0,
+            chunk_strategy: ChunkStrategy::Bytes,
+            skip: true,
+        }
+    }
+
+    /// Encoded settings recorded as a plan's `chunker_config`.
+    pub(crate) fn describe(&self, max_chunks_per_batch: usize) -> String {
+        let chunk_size = self.target_chunk_bytes.max(1);
+        match self.chunk_strategy {
+            ChunkStrategy::Bytes if self.overlap_bytes == 0 => {
+                format!("bytes={chunk_size};max={max_chunks_per_batch}")
+            }
+            strategy => format!(
+                "{strategy}={chunk_size};overlap={};max={max_chunks_per_batch}",
+                self.overlap_bytes
+            ),
+        }
+    }
+}
+
+/// Suggested profiles: 2 KB line chunks for code, 8 KB paragraph chunks for
+/// prose, and no chunks for lockfiles, generated sources, or binaries.
+#[must_use]
+pub fn default_profiles() -> BTreeMap<FileKind, ChunkProfile> {
+    let code = ChunkProfile::new(2 * 1024, ChunkStrategy::Lines).with_overlap(256);
+    let prose = ChunkProfile::new(8 * 1024, ChunkStrategy::Paragraphs);
+    let config = ChunkProfile::new(2 * 1024, ChunkStrategy::Lines);
+    BTreeMap::from([
+        (FileKind::Rust, code),
+        (FileKind::Python, code),
+        (FileKind::JavaScript, code),
+        (FileKind::TypeScript, code),
+        (FileKind::Shell, code),
+        (FileKind::Markdown, prose),
+        (FileKind::Text, prose),
+        (FileKind::Toml, config),
+        (FileKind::Json, config),
+        (FileKind::Yaml, config),
+        (FileKind::Lockfile, ChunkProfile::skip()),
+        (FileKind::Generated, ChunkProfile::skip()),
+        (FileKind::Binary, ChunkProfile::skip()),
+    ])
+}
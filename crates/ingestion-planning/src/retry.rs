@@ -0,0 +1,111 @@
+//! Execution of per-chunk work under each plan's [`RetryPolicy`](crate::RetryPolicy).
+
+use std::future::Future;
+use std::time::Duration;
+
+use thiserror::Error;
+
+use crate::ChunkPlan;
+
+/// Failure reported by a chunk processing closure.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ChunkError {
+    /// Transient failure (timeouts, rate limits); the attempt may be retried.
+    #[error("retryable chunk failure: {0}")]
+    Retryable(String),
+    /// Permanent failure; retrying cannot succeed.
+    #[error("fatal chunk failure: {0}")]
+    Fatal(String),
+}
+
+impl ChunkError {
+    #[must_use]
+    pub const fn is_retryable(&self) -> bool {
+        matches!(self, Self::Retryable(_))
+    }
+}
+
+/// Final outcome of processing one chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkReport<T> {
+    pub plan_id: String,
+    /// Attempts made, including the final one.
+    pub attempts: u32,
+    /// Total backoff waited between attempts.
+    pub waited: Duration,
+    pub result: Result<T, ChunkError>,
+}
+
+impl<T> ChunkReport<T> {
+    #[must_use]
+    pub const fn succeeded(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+/// Runs chunk processing closures, retrying retryable failures with backoff.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RetryExecutor;
+
+impl RetryExecutor {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self
+    }
+
+    /// Process `plan`, retrying per its policy until success, a fatal error,
+    /// or `max_attempts` is exhausted. `process` receives the 1-based attempt.
+    pub async fn run<T, F, Fut>(&self, plan: &ChunkPlan, mut process: F) -> ChunkReport<T>
+    where
+        F: FnMut(&ChunkPlan, u32) -> Fut,
+        Fut: Future<Output = Result<T, ChunkError>>,
+    {
+        let policy = &plan.retry_policy;
+        let max_attempts = policy.max_attempts.max(1);
+        let mut waited = Duration::ZERO;
+        let mut attempt = 1;
+        loop {
+            let result = process(plan, attempt).await;
+            match result {
+                Err(error) if error.is_retryable() && attempt < max_attempts => {
+                    let delay = policy.delay_for(attempt, &plan.plan_id);
+                    tracing::warn!(
+                        plan_id = %plan.plan_id,
+                        attempt,
+                        delay_ms = delay.as_millis() as u64,
+                        %error,
+                        "retrying chunk"
+                    );
+                    tokio::time::sleep(delay).await;
+                    waited += delay;
+                    attempt += 1;
+                }
+                result => {
+                    return ChunkReport {
+                        plan_id: plan.plan_id.clone(),
+                        attempts: attempt,
+                        waited,
+                        result,
+                    };
+                }
+            }
+        }
+    }
+
+    /// Process every plan in order, returning one report per plan.
+    pub async fn run_all<T, F, Fut>(
+        &self,
+        plans: &[ChunkPlan],
+        mut process: F,
+    ) -> Vec<ChunkReport<T>>
+    where
+        F: FnMut(&ChunkPlan, u32) -> Fut,
+        Fut: Future<Output = Result<T, ChunkError>>,
+    {
+        let mut reports = Vec::with_capacity(plans.len());
+        for plan in plans {
+            reports.push(self.run(plan, &mut process).await);
+        }
+        reports
+    }
+}
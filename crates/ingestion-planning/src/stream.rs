@@ -0,0 +1,165 @@
+//! Batched plan iteration with resumable continuation cursors.
+
+use std::collections::VecDeque;
+
+use ingestion_workspace::{WorkspaceDescriptor, WorkspaceFile};
+use serde::{Deserialize, Serialize};
+
+use crate::{ChunkPlan, ChunkPlanner, PlannedChunk, PlanningError};
+
+/// Position from which planning can resume.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanCursor {
+    pub repo_id: String,
+    /// Index into the file list in planning order.
+    pub file_index: usize,
+    /// Chunks of that file already emitted.
+    pub chunk_offset: usize,
+}
+
+/// One batch of plans and the cursor for the batch after it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanBatch {
+    pub plans: Vec<ChunkPlan>,
+    /// `None` once every chunk of the workspace has been emitted.
+    pub next: Option<PlanCursor>,
+}
+
+/// One batch of chunks with their payloads, as
+/// [`PlanIter::next_chunks`] yields them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedBatch {
+    pub chunks: Vec<PlannedChunk>,
+    /// `None` once every chunk of the workspace has been emitted.
+    pub next: Option<PlanCursor>,
+}
+
+/// Iterator yielding [`PlanBatch`]es of at most `max_chunks_per_batch` plans.
+///
+/// Files are chunked lazily, one at a time, so memory stays bounded by the
+/// largest file rather than the whole workspace.
+#[derive(Debug)]
+pub struct PlanIter<'a> {
+    planner: &'a ChunkPlanner,
+    workspace: &'a WorkspaceDescriptor,
+    files: Vec<&'a WorkspaceFile>,
+    file_index: usize,
+    pending: VecDeque<PlannedChunk>,
+    consumed: usize,
+}
+
+impl<'a> PlanIter<'a> {
+    fn cursor(&self) -> Option<PlanCursor> {
+        if !self.pending.is_empty() {
+            return Some(PlanCursor {
+                repo_id: self.workspace.repo_id.clone(),
+                file_index: self.file_index - 1,
+                chunk_offset: self.consumed,
+            });
+        }
+        (self.file_index < self.files.len()).then(|| PlanCursor {
+            repo_id: self.workspace.repo_id.clone(),
+            file_index: self.file_index,
+            chunk_offset: 0,
+        })
+    }
+
+    fn load_file(&mut self, skip: usize) {
+        let file = self.files[self.file_index];
+        self.pending = self
+            .planner
+            .plan_file(self.workspace, file)
+            .into_iter()
+            .skip(skip)
+            .collect();
+        self.consumed = skip;
+        self.file_index += 1;
+    }
+}
+
+impl PlanIter<'_> {
+    /// Like [`Iterator::next`], but keeps each chunk's payload alongside
+    /// its plan for sanitization.
+    pub fn next_chunks(&mut self) -> Option<PlannedBatch> {
+        let batch_size = self.planner.config.max_chunks_per_batch.max(1);
+        let mut chunks = Vec::with_capacity(batch_size);
+        while chunks.len() < batch_size {
+            if self.pending.is_empty() {
+                if self.file_index >= self.files.len() {
+                    break;
+                }
+                self.load_file(0);
+            }
+            if let Some(chunk) = self.pending.pop_front() {
+                chunks.push(chunk);
+                self.consumed += 1;
+            }
+        }
+        if chunks.is_empty() {
+            return None;
+        }
+        Some(PlannedBatch {
+            chunks,
+            next: self.cursor(),
+        })
+    }
+}
+
+impl Iterator for PlanIter<'_> {
+    type Item = PlanBatch;
+
+    fn next(&mut self) -> Option<PlanBatch> {
+        let batch = self.next_chunks()?;
+        Some(PlanBatch {
+            plans: batch
+                .chunks
+                .into_iter()
+                .map(PlannedChunk::into_plan)
+                .collect(),
+            next: batch.next,
+        })
+    }
+}
+
+impl ChunkPlanner {
+    /// Stream the plans for `workspace` in batches without truncation.
+    pub fn plan_iter<'a>(
+        &'a self,
+        workspace: &'a WorkspaceDescriptor,
+    ) -> Result<PlanIter<'a>, PlanningError> {
+        self.plan_iter_from(workspace, None)
+    }
+
+    /// Resume streaming from a cursor returned by an earlier batch.
+    pub fn plan_iter_from<'a>(
+        &'a self,
+        workspace: &'a WorkspaceDescriptor,
+        cursor: Option<&PlanCursor>,
+    ) -> Result<PlanIter<'a>, PlanningError> {
+        self.check_archive_quotas(workspace)?;
+        let files = self.config.ordering.order(&workspace.files);
+        let mut iter = PlanIter {
+            planner: self,
+            workspace,
+            files,
+            file_index: 0,
+            pending: VecDeque::new(),
+            consumed: 0,
+        };
+        if let Some(cursor) = cursor {
+            if cursor.repo_id != workspace.repo_id || cursor.file_index > iter.files.len() {
+                return Err(PlanningError::InvalidCursor {
+                    detail: format!(
+                        "cursor for '{}' at file {} does not match workspace '{}'",
+                        cursor.repo_id, cursor.file_index, workspace.repo_id
+                    ),
+                });
+            }
+            iter.file_index = cursor.file_index;
+            if cursor.chunk_offset > 0 && iter.file_index < iter.files.len() {
+                iter.load_file(cursor.chunk_offset);
+            }
+        }
+        Ok(iter)
+    }
+}
@@ -140,6 +140,7 @@ fn archive_quota_violation_matches_fixture() {
         archives: vec![archive],
         latency_windows: vec![],
         files: vec![WorkspaceFile::new("archive.tar", "placeholder")],
+        tenant_id: None,
     };
 
     let planner = ChunkPlanner::new(PlannerConfig {
@@ -149,6 +150,7 @@ fn archive_quota_violation_matches_fixture() {
         quota_entries_max: Some(profile.entries_max),
         quota_nesting_max: Some(profile.nesting_max),
         quota_latency_budget_ms: Some(profile.latency_budget_ms),
+        ..PlannerConfig::default()
     });
     match planner.plan(&descriptor) {
         Err(PlanningError::QuotaExceeded { diagnostics }) => {
@@ -185,6 +187,7 @@ fn deterministic_chunk_batches_use_sorted_filenames() {
             WorkspaceFile::new("src/lib.rs", "pub fn add(a: i32, b: i32) -> i32 { a + b }"),
             WorkspaceFile::new("README.md", "# sample"),
         ],
+        tenant_id: None,
     };
     let planner = ChunkPlanner::new(PlannerConfig {
         target_chunk_bytes: baseline.bytes as usize / 2,
@@ -193,6 +196,7 @@ fn deterministic_chunk_batches_use_sorted_filenames() {
         quota_entries_max: None,
         quota_nesting_max: None,
         quota_latency_budget_ms: None,
+        ..PlannerConfig::default()
     });
 
     let plans = planner.plan(&descriptor).expect("planning should succeed");
@@ -201,8 +205,8 @@ fn deterministic_chunk_batches_use_sorted_filenames() {
         plan_ids,
         vec![
             "repo-delta::README.md::0",
-            "repo-delta::src/bin.rs::1",
-            "repo-delta::src/lib.rs::2",
+            "repo-delta::src/bin.rs::0",
+            "repo-delta::src/lib.rs::0",
         ]
     );
 }
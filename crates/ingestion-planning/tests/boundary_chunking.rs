@@ -0,0 +1,55 @@
+use std::path::PathBuf;
+
+use ingestion_planning::{ChunkPlanner, ChunkStrategy, PlannerConfig};
+use ingestion_workspace::{RepoType, WorkspaceDescriptor, WorkspaceFile};
+
+fn descriptor(files: Vec<WorkspaceFile>) -> WorkspaceDescriptor {
+    WorkspaceDescriptor {
+        repo_id: "repo-chunks".into(),
+        root_path: PathBuf::from("/tmp/repo-chunks"),
+        repo_type: RepoType::Git,
+        manifest_cursor: None,
+        ignore_stack: vec![],
+        archives: vec![],
+        latency_windows: vec![],
+        files,
+        tenant_id: None,
+    }
+}
+
+#[test]
+fn line_strategy_reports_line_column_spans_with_overlap() {
+    let planner = ChunkPlanner::new(PlannerConfig {
+        target_chunk_bytes: 24,
+        chunk_strategy: ChunkStrategy::Lines,
+        overlap_bytes: 12,
+        ..PlannerConfig::default()
+    });
+    let content = "fn a() {}\nfn b() {}\nfn c() {}\n";
+    let plans = planner
+        .plan(&descriptor(vec![WorkspaceFile::new("src/lib.rs", content)]))
+        .expect("planning should succeed");
+
+    let spans: Vec<_> = plans.iter().map(|plan| plan.source_span.as_str()).collect();
+    assert_eq!(spans, vec!["src/lib.rs:1:1-3:1", "src/lib.rs:2:1-4:1"]);
+    assert_eq!(plans[0].chunker_config, "lines=24;overlap=12;max=64");
+}
+
+#[test]
+fn byte_strategy_keeps_utf8_characters_intact() {
+    let planner = ChunkPlanner::new(PlannerConfig {
+        target_chunk_bytes: 3,
+        ..PlannerConfig::default()
+    });
+    let content = "aéé";
+    let plans = planner
+        .plan(&descriptor(vec![WorkspaceFile::new("notes.txt", content)]))
+        .expect("planning should succeed");
+
+    let spans: Vec<_> = plans.iter().map(|plan| plan.source_span.as_str()).collect();
+    assert_eq!(spans, vec!["notes.txt:0-3", "notes.txt:3-5"]);
+    assert_eq!(
+        plans[0].hash,
+        blake3::hash("aé".as_bytes()).to_hex().to_string()
+    );
+}
@@ -0,0 +1,168 @@
+use std::path::PathBuf;
+
+use ingestion_planning::{ChunkPlanner, ChunkStrategy, DedupMode, NearDupConfig, PlannerConfig};
+use ingestion_workspace::{RepoType, WorkspaceDescriptor, WorkspaceFile};
+
+fn descriptor(repo_id: &str, files: Vec<WorkspaceFile>) -> WorkspaceDescriptor {
+    WorkspaceDescriptor {
+        repo_id: repo_id.into(),
+        root_path: PathBuf::from(format!("/tmp/{repo_id}")),
+        repo_type: RepoType::Git,
+        manifest_cursor: None,
+        ignore_stack: vec![],
+        archives: vec![],
+        latency_windows: vec![],
+        files,
+        tenant_id: None,
+    }
+}
+
+const VENDORED: &str =
+    "pub fn clamp(value: i32, low: i32, high: i32) -> i32 { value.max(low).min(high) }";
+
+#[test]
+fn exact_dedup_collapses_identical_chunks_across_repos() {
+    let planner = ChunkPlanner::new(PlannerConfig::default());
+    let groups = planner
+        .plan_deduped(
+            &[
+                descriptor(
+                    "repo-a",
+                    vec![
+                        WorkspaceFile::new("src/util.rs", VENDORED),
+                        WorkspaceFile::new("vendor/util.rs", VENDORED),
+                    ],
+                ),
+                descriptor(
+                    "repo-b",
+                    vec![
+                        WorkspaceFile::new("third_party/util.rs", VENDORED),
+                        WorkspaceFile::new("src/main.rs", "fn main() {}"),
+                    ],
+                ),
+            ],
+            DedupMode::Exact,
+        )
+        .expect("planning should succeed");
+
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[0].len(), 3);
+    assert_eq!(groups[0].chunk.plan().plan_id, "repo-a::src/util.rs::0");
+    let len = VENDORED.len();
+    assert_eq!(
+        groups[0].source_spans,
+        vec![
+            format!("repo-a:src/util.rs:0-{len}"),
+            format!("repo-a:vendor/util.rs:0-{len}"),
+            format!("repo-b:third_party/util.rs:0-{len}"),
+        ]
+    );
+    assert_eq!(groups[1].plan_ids, vec!["repo-b::src/main.rs::0"]);
+}
+
+#[test]
+fn near_duplicate_mode_groups_lightly_edited_chunks() {
+    let original = "the quick brown fox jumps over the lazy dog while the cat sleeps \
+                    on the warm mat near the door of the old house by the river";
+    let edited = original.replace("river", "stream");
+    let unrelated = "completely different content about vector stores and manifest \
+                     replay with nothing in common with the other paragraphs at all";
+    let planner = ChunkPlanner::new(PlannerConfig {
+        target_chunk_bytes: 4096,
+        chunk_strategy: ChunkStrategy::Paragraphs,
+        ..PlannerConfig::default()
+    });
+    let workspace = descriptor(
+        "repo-docs",
+        vec![
+            WorkspaceFile::new("a.md", original),
+            WorkspaceFile::new("b.md", edited),
+            WorkspaceFile::new("c.md", unrelated),
+        ],
+    );
+
+    let exact = planner
+        .plan_deduped(std::slice::from_ref(&workspace), DedupMode::Exact)
+        .expect("exact dedup");
+    assert_eq!(exact.len(), 3);
+
+    let near = planner
+        .plan_deduped(
+            &[workspace],
+            DedupMode::NearDuplicate(NearDupConfig {
+                shingle_tokens: 3,
+                permutations: 128,
+                threshold: 0.7,
+            }),
+        )
+        .expect("near dedup");
+    assert_eq!(near.len(), 2);
+    assert_eq!(
+        near[0].plan_ids,
+        vec!["repo-docs::a.md::0", "repo-docs::b.md::0"]
+    );
+}
+
+#[test]
+fn near_duplicates_are_found_among_many_unrelated_chunks() {
+    let paragraph = |seed: usize| {
+        (0..40)
+            .map(|word| format!("w{}", seed * 7_919 + word * 104_729))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let mut files: Vec<_> = (0..500)
+        .map(|seed| WorkspaceFile::new(format!("notes/{seed:03}.md"), paragraph(seed)))
+        .collect();
+    let copied = paragraph(42).replacen("w", "x", 1);
+    files.push(WorkspaceFile::new("z-copy.md", copied));
+    let planner = ChunkPlanner::new(PlannerConfig {
+        target_chunk_bytes: 4096,
+        chunk_strategy: ChunkStrategy::Paragraphs,
+        max_chunks_per_batch: 1_000,
+        ..PlannerConfig::default()
+    });
+
+    let groups = planner
+        .plan_deduped(
+            &[descriptor("repo-notes", files)],
+            DedupMode::NearDuplicate(NearDupConfig::default()),
+        )
+        .expect("near dedup");
+    assert_eq!(groups.len(), 500);
+    let merged: Vec<_> = groups.iter().filter(|group| group.len() > 1).collect();
+    assert_eq!(merged.len(), 1);
+    assert_eq!(
+        merged[0].plan_ids,
+        vec!["repo-notes::notes/042.md::0", "repo-notes::z-copy.md::0"]
+    );
+}
+
+#[test]
+fn chunks_of_different_tenants_are_never_collapsed_together() {
+    let tenant = |repo_id: &str, tenant_id: &str, path: &str| WorkspaceDescriptor {
+        tenant_id: Some(tenant_id.into()),
+        ..descriptor(repo_id, vec![WorkspaceFile::new(path, VENDORED)])
+    };
+    let workspaces = [
+        tenant("repo-a", "team-a", "src/util.rs"),
+        tenant("repo-b", "team-b", "src/util.rs"),
+        tenant("repo-c", "team-a", "vendor/util.rs"),
+    ];
+    let planner = ChunkPlanner::new(PlannerConfig::default());
+    for mode in [
+        DedupMode::Exact,
+        DedupMode::NearDuplicate(NearDupConfig::default()),
+    ] {
+        let groups = planner
+            .plan_deduped(&workspaces, mode)
+            .expect("planning should succeed");
+        assert_eq!(groups.len(), 2, "{mode:?}");
+        assert_eq!(
+            groups[0].plan_ids,
+            vec!["repo-a::src/util.rs::0", "repo-c::vendor/util.rs::0"]
+        );
+        assert_eq!(groups[1].plan_ids, vec!["repo-b::src/util.rs::0"]);
+        assert_eq!(groups[1].chunk.plan().tenant_id.as_deref(), Some("team-b"));
+    }
+}
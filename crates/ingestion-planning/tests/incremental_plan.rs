@@ -0,0 +1,141 @@
+use std::path::PathBuf;
+
+use ingestion_planning::{ChunkPlanner, ChunkStrategy, PlanManifest, PlannerConfig};
+use ingestion_workspace::{RepoType, WorkspaceDescriptor, WorkspaceFile};
+
+fn descriptor(files: Vec<WorkspaceFile>) -> WorkspaceDescriptor {
+    WorkspaceDescriptor {
+        repo_id: "repo-incremental".into(),
+        root_path: PathBuf::from("/tmp/repo-incremental"),
+        repo_type: RepoType::Git,
+        manifest_cursor: None,
+        ignore_stack: vec![],
+        archives: vec![],
+        latency_windows: vec![],
+        files,
+        tenant_id: None,
+    }
+}
+
+fn planner() -> ChunkPlanner {
+    ChunkPlanner::new(PlannerConfig {
+        target_chunk_bytes: 16,
+        chunk_strategy: ChunkStrategy::Lines,
+        ..PlannerConfig::default()
+    })
+}
+
+#[test]
+fn incremental_plan_emits_only_changed_chunks_and_removals() {
+    let planner = planner();
+    let first = planner
+        .plan_incremental(
+            &PlanManifest::new("repo-incremental"),
+            &descriptor(vec![
+                WorkspaceFile::new("a.md", "alpha line one\nalpha line two\n"),
+                WorkspaceFile::new("b.md", "bravo\n"),
+                WorkspaceFile::new("c.md", "charlie\n"),
+            ]),
+        )
+        .expect("first plan");
+    assert_eq!(first.added.len(), 4);
+    assert!(first.removed.is_empty());
+    assert_eq!(first.checksum_before, PlanManifest::new("x").checksum());
+
+    let unchanged = planner
+        .plan_incremental(
+            &first.manifest,
+            &descriptor(vec![
+                WorkspaceFile::new("a.md", "alpha line one\nalpha line two\n"),
+                WorkspaceFile::new("b.md", "bravo\n"),
+                WorkspaceFile::new("c.md", "charlie\n"),
+            ]),
+        )
+        .expect("unchanged plan");
+    assert!(unchanged.is_empty());
+    assert_eq!(unchanged.unchanged, 4);
+    assert_eq!(unchanged.checksum_after, first.checksum_after);
+
+    let second = planner
+        .plan_incremental(
+            &first.manifest,
+            &descriptor(vec![
+                WorkspaceFile::new("a.md", "alpha line one\nalpha line two\n"),
+                WorkspaceFile::new("b.md", "bravo edited\n"),
+            ]),
+        )
+        .expect("second plan");
+    let added: Vec<_> = second
+        .added
+        .iter()
+        .map(|plan| plan.plan_id.as_str())
+        .collect();
+    assert_eq!(added, vec!["repo-incremental::b.md::0"]);
+    assert_eq!(second.removed, vec!["repo-incremental::c.md::0"]);
+    assert_eq!(second.unchanged, 2);
+    assert_eq!(second.checksum_before, first.checksum_after);
+    assert_ne!(second.checksum_after, second.checksum_before);
+}
+
+#[test]
+fn inserting_an_early_file_leaves_later_chunks_alone() {
+    let planner = planner();
+    let first = planner
+        .plan_incremental(
+            &PlanManifest::new("repo-incremental"),
+            &descriptor(vec![
+                WorkspaceFile::new("b.md", "bravo line one\nbravo line two\n"),
+                WorkspaceFile::new("c.md", "charlie\n"),
+            ]),
+        )
+        .expect("first plan");
+
+    let second = planner
+        .plan_incremental(
+            &first.manifest,
+            &descriptor(vec![
+                WorkspaceFile::new("a.md", "alpha\n"),
+                WorkspaceFile::new("b.md", "bravo line one\nbravo line two\n"),
+                WorkspaceFile::new("c.md", "charlie\n"),
+            ]),
+        )
+        .expect("second plan");
+    let added: Vec<_> = second
+        .added
+        .iter()
+        .map(|plan| plan.plan_id.as_str())
+        .collect();
+    assert_eq!(added, vec!["repo-incremental::a.md::0"]);
+    assert!(second.removed.is_empty());
+    assert_eq!(second.unchanged, first.added.len());
+}
+
+#[test]
+fn changed_chunker_settings_disable_reuse() {
+    let files = vec![WorkspaceFile::new("a.md", "alpha\n")];
+    let first = planner()
+        .plan_incremental(
+            &PlanManifest::new("repo-incremental"),
+            &descriptor(files.clone()),
+        )
+        .expect("first plan");
+    let rechunked = ChunkPlanner::new(PlannerConfig {
+        target_chunk_bytes: 32,
+        chunk_strategy: ChunkStrategy::Lines,
+        ..PlannerConfig::default()
+    })
+    .plan_incremental(&first.manifest, &descriptor(files))
+    .expect("rechunked plan");
+    assert_eq!(
+        rechunked.manifest.chunker_config,
+        "lines=32;overlap=0;max=64"
+    );
+    // Every chunk is re-emitted under the new settings, even where its
+    // content and span came out the same.
+    assert_eq!(rechunked.added.len(), first.added.len());
+    assert_eq!(rechunked.unchanged, 0);
+    assert!(rechunked
+        .added
+        .iter()
+        .all(|plan| plan.chunker_config == "lines=32;overlap=0;max=64"));
+}
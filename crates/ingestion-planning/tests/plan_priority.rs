@@ -0,0 +1,122 @@
+use std::path::PathBuf;
+
+use ingestion_planning::{ChunkPlanner, PlanManifest, PlanOrder, PlannerConfig, PriorityWeights};
+use ingestion_workspace::{RepoType, WorkspaceDescriptor, WorkspaceFile};
+
+const HOUR_MS: u64 = 60 * 60 * 1_000;
+
+fn descriptor(files: Vec<WorkspaceFile>) -> WorkspaceDescriptor {
+    WorkspaceDescriptor {
+        repo_id: "repo-priority".into(),
+        root_path: PathBuf::from("/tmp/repo-priority"),
+        repo_type: RepoType::Git,
+        manifest_cursor: None,
+        ignore_stack: vec![],
+        archives: vec![],
+        latency_windows: vec![],
+        files,
+        tenant_id: None,
+    }
+}
+
+fn workspace() -> WorkspaceDescriptor {
+    let now = 1_700_000_000_000;
+    descriptor(vec![
+        WorkspaceFile::new("src/old.rs", "fn old() {}").with_mtime_ms(now - 240 * HOUR_MS),
+        WorkspaceFile::new("src/new.rs", "fn new() {}").with_mtime_ms(now),
+        WorkspaceFile::new("docs/guide.md", "# Guide").with_mtime_ms(now - 240 * HOUR_MS),
+        WorkspaceFile::new("README.md", "# Readme").with_mtime_ms(now - 240 * HOUR_MS),
+    ])
+}
+
+fn planned_paths(config: PlannerConfig) -> Vec<String> {
+    ChunkPlanner::new(config)
+        .plan(&workspace())
+        .expect("planning should succeed")
+        .into_iter()
+        .map(|plan| plan.source_span.split(':').next().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn path_order_remains_the_default() {
+    assert_eq!(
+        planned_paths(PlannerConfig::default()),
+        vec!["README.md", "docs/guide.md", "src/new.rs", "src/old.rs"]
+    );
+}
+
+#[test]
+fn priority_order_puts_readme_docs_and_recent_files_first() {
+    let config = PlannerConfig {
+        ordering: PlanOrder::Priority(PriorityWeights::default()),
+        ..PlannerConfig::default()
+    };
+    assert_eq!(
+        planned_paths(config),
+        vec!["README.md", "src/new.rs", "docs/guide.md", "src/old.rs"]
+    );
+}
+
+#[test]
+fn weights_are_configurable_and_batches_follow_priority() {
+    let weights = PriorityWeights {
+        readme: 0.0,
+        documentation: 0.0,
+        recency: 1.0,
+        ..PriorityWeights::default()
+    };
+    let planner = ChunkPlanner::new(PlannerConfig {
+        ordering: PlanOrder::Priority(weights),
+        ..PlannerConfig::new(1024, 1)
+    });
+    let workspace = workspace();
+    let batches: Vec<_> = planner
+        .plan_iter(&workspace)
+        .expect("stream should start")
+        .collect();
+    assert_eq!(batches.len(), 4);
+    assert_eq!(batches[0].plans[0].plan_id, "repo-priority::src/new.rs::0");
+    assert_eq!(batches[1].plans[0].plan_id, "repo-priority::README.md::0");
+
+    let resumed: Vec<_> = planner
+        .plan_iter_from(&workspace, batches[1].next.as_ref())
+        .expect("cursor should resume")
+        .flat_map(|batch| batch.plans)
+        .collect();
+    assert_eq!(
+        resumed,
+        batches[2..]
+            .iter()
+            .flat_map(|batch| batch.plans.clone())
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn touching_a_file_reorders_batches_without_renumbering_chunks() {
+    let planner = ChunkPlanner::new(PlannerConfig {
+        ordering: PlanOrder::Priority(PriorityWeights::default()),
+        ..PlannerConfig::default()
+    });
+    let first = planner
+        .plan_incremental(&PlanManifest::new("repo-priority"), &workspace())
+        .expect("first plan");
+
+    // `src/old.rs` becomes the newest file and jumps ahead in the order.
+    let mut touched = workspace();
+    touched.files[0].mtime_ms = Some(1_700_000_000_000 + HOUR_MS);
+    let spans: Vec<_> = planner
+        .plan(&touched)
+        .expect("planning should succeed")
+        .into_iter()
+        .map(|plan| plan.source_span)
+        .collect();
+    assert!(spans[0].starts_with("README.md") && spans[1].starts_with("src/old.rs"));
+
+    let replanned = planner
+        .plan_incremental(&first.manifest, &touched)
+        .expect("replan");
+    assert!(replanned.is_empty());
+    assert_eq!(replanned.checksum_after, first.checksum_after);
+}
@@ -0,0 +1,107 @@
+use std::path::PathBuf;
+
+use ingestion_planning::{ChunkPlanner, PlanCursor, PlannerConfig, PlanningError};
+use ingestion_workspace::{RepoType, WorkspaceDescriptor, WorkspaceFile};
+
+fn descriptor() -> WorkspaceDescriptor {
+    WorkspaceDescriptor {
+        repo_id: "repo-stream".into(),
+        root_path: PathBuf::from("/tmp/repo-stream"),
+        repo_type: RepoType::Git,
+        manifest_cursor: None,
+        ignore_stack: vec![],
+        archives: vec![],
+        latency_windows: vec![],
+        files: vec![
+            WorkspaceFile::new("b.txt", "bbbbbbbbbb"),
+            WorkspaceFile::new("a.txt", "aaaaaaa"),
+            WorkspaceFile::new("c.txt", "cc"),
+        ],
+        tenant_id: None,
+    }
+}
+
+fn planner() -> ChunkPlanner {
+    ChunkPlanner::new(PlannerConfig {
+        target_chunk_bytes: 4,
+        max_chunks_per_batch: 2,
+        ..PlannerConfig::default()
+    })
+}
+
+#[test]
+fn plan_iter_streams_every_chunk_in_bounded_batches() {
+    let planner = planner();
+    let workspace = descriptor();
+    let truncated = planner.plan(&workspace).expect("plan");
+    assert_eq!(truncated.len(), 2);
+
+    let batches: Vec<_> = planner.plan_iter(&workspace).expect("iter").collect();
+    assert_eq!(batches.len(), 3);
+    assert!(batches.iter().all(|batch| batch.plans.len() <= 2));
+    let ids: Vec<_> = batches
+        .iter()
+        .flat_map(|batch| batch.plans.iter().map(|plan| plan.plan_id.clone()))
+        .collect();
+    assert_eq!(
+        ids,
+        vec![
+            "repo-stream::a.txt::0",
+            "repo-stream::a.txt::1",
+            "repo-stream::b.txt::0",
+            "repo-stream::b.txt::1",
+            "repo-stream::b.txt::2",
+            "repo-stream::c.txt::0",
+        ]
+    );
+    assert!(batches.last().unwrap().next.is_none());
+    assert_eq!(
+        batches[1].next,
+        Some(PlanCursor {
+            repo_id: "repo-stream".into(),
+            file_index: 1,
+            chunk_offset: 2,
+        })
+    );
+}
+
+#[test]
+fn plan_iter_resumes_from_cursor() {
+    let planner = planner();
+    let workspace = descriptor();
+    let mut iter = planner.plan_iter(&workspace).expect("iter");
+    let first = iter.next().expect("first batch");
+    let second = iter.next().expect("second batch");
+
+    let resumed: Vec<_> = planner
+        .plan_iter_from(&workspace, first.next.as_ref())
+        .expect("resume")
+        .collect();
+    assert_eq!(resumed[0], second);
+    let remaining: usize = resumed.iter().map(|batch| batch.plans.len()).sum();
+    assert_eq!(remaining, 4);
+
+    let foreign = PlanCursor {
+        repo_id: "other".into(),
+        file_index: 0,
+        chunk_offset: 0,
+    };
+    assert!(matches!(
+        planner.plan_iter_from(&workspace, Some(&foreign)),
+        Err(PlanningError::InvalidCursor { .. })
+    ));
+}
+
+#[test]
+fn next_chunks_keeps_payloads_with_their_plans() {
+    let planner = planner();
+    let workspace = descriptor();
+    let mut iter = planner.plan_iter(&workspace).expect("iter");
+    let batch = iter.next_chunks().expect("first batch");
+    let payloads: Vec<_> = batch.chunks.iter().map(|chunk| chunk.payload()).collect();
+    assert_eq!(payloads, vec!["aaaa", "aaa"]);
+    assert_eq!(batch.chunks[0].plan().plan_id, "repo-stream::a.txt::0");
+
+    let rest = iter.next().expect("plans continue after chunks");
+    assert_eq!(rest.plans[0].plan_id, "repo-stream::b.txt::0");
+}
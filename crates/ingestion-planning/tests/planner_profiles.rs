@@ -0,0 +1,115 @@
+use std::path::PathBuf;
+
+use ingestion_planning::{ChunkPlanner, ChunkProfile, ChunkStrategy, PlanManifest, PlannerConfig};
+use ingestion_workspace::{FileKind, RepoType, WorkspaceDescriptor, WorkspaceFile};
+
+fn descriptor(files: Vec<WorkspaceFile>) -> WorkspaceDescriptor {
+    WorkspaceDescriptor {
+        repo_id: "repo-profiles".into(),
+        root_path: PathBuf::from("/tmp/repo-profiles"),
+        repo_type: RepoType::Git,
+        manifest_cursor: None,
+        ignore_stack: vec![],
+        archives: vec![],
+        latency_windows: vec![],
+        files,
+        tenant_id: None,
+    }
+}
+
+fn workspace() -> WorkspaceDescriptor {
+    descriptor(vec![
+        WorkspaceFile::new("Cargo.lock", "[[package]]\nname = \"demo\"\n"),
+        WorkspaceFile::new(
+            "README.md",
+            "# Demo\n\nFirst paragraph.\n\nSecond paragraph.\n",
+        ),
+        WorkspaceFile::new("src/lib.rs", "fn a() {}\nfn b() {}\nfn c() {}\n"),
+    ])
+}
+
+#[test]
+fn profiles_select_settings_by_file_kind() {
+    let mut config = PlannerConfig::new(8, 64);
+    config
+        .profiles
+        .insert(FileKind::Rust, ChunkProfile::new(20, ChunkStrategy::Lines));
+    config.profiles.insert(
+        FileKind::Markdown,
+        ChunkProfile::new(1024, ChunkStrategy::Paragraphs),
+    );
+    config
+        .profiles
+        .insert(FileKind::Lockfile, ChunkProfile::skip());
+    let plans = ChunkPlanner::new(config)
+        .plan(&workspace())
+        .expect("planning should succeed");
+
+    assert!(plans
+        .iter()
+        .all(|plan| !plan.plan_id.contains("Cargo.lock")));
+    let readme: Vec<_> = plans
+        .iter()
+        .filter(|plan| plan.plan_id.contains("README.md"))
+        .collect();
+    assert_eq!(readme.len(), 1);
+    assert_eq!(readme[0].chunker_config, "paragraphs=1024;overlap=0;max=64");
+    let code: Vec<_> = plans
+        .iter()
+        .filter(|plan| plan.plan_id.contains("src/lib.rs"))
+        .map(|plan| plan.source_span.as_str())
+        .collect();
+    assert_eq!(code, vec!["src/lib.rs:1:1-3:1", "src/lib.rs:3:1-4:1"]);
+    assert_eq!(plans[0].plan_id, "repo-profiles::README.md::0");
+}
+
+#[test]
+fn unlisted_kinds_fall_back_to_base_settings() {
+    let config = PlannerConfig::new(16, 64);
+    assert_eq!(
+        config.profile_for(FileKind::Rust),
+        ChunkProfile::new(16, ChunkStrategy::Bytes)
+    );
+
+    let defaults = PlannerConfig::default().with_default_profiles();
+    assert!(defaults.profile_for(FileKind::Lockfile).skip);
+    assert_eq!(
+        defaults.profile_for(FileKind::Rust).target_chunk_bytes,
+        2048
+    );
+    assert_eq!(
+        defaults.profile_for(FileKind::Markdown).target_chunk_bytes,
+        8192
+    );
+}
+
+#[test]
+fn streaming_and_incremental_planning_honour_profiles() {
+    let planner = ChunkPlanner::new(PlannerConfig::new(8, 2).with_default_profiles());
+    let workspace = workspace();
+    let streamed: Vec<_> = planner
+        .plan_iter(&workspace)
+        .expect("stream should start")
+        .flat_map(|batch| batch.plans)
+        .collect();
+    assert!(streamed
+        .iter()
+        .all(|plan| !plan.plan_id.contains("Cargo.lock")));
+
+    let first = planner
+        .plan_incremental(&PlanManifest::new("repo-profiles"), &workspace)
+        .expect("first run should plan");
+    assert_eq!(first.added, streamed);
+    assert!(!first.manifest.files["Cargo.lock"].content_hash.is_empty());
+    assert!(first.manifest.files["Cargo.lock"].chunks.is_empty());
+
+    let second = planner
+        .plan_incremental(&first.manifest, &workspace)
+        .expect("second run should plan");
+    assert!(second.is_empty());
+
+    let rebased = ChunkPlanner::new(PlannerConfig::new(8, 2))
+        .plan_incremental(&first.manifest, &workspace)
+        .expect("changing profiles should re-plan");
+    assert!(!rebased.is_empty());
+}
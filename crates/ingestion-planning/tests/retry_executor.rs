@@ -0,0 +1,109 @@
+#![cfg(feature = "native")]
+use std::time::Duration;
+
+use ingestion_planning::{ChunkError, ChunkPlan, RetryExecutor, RetryPolicy};
+
+fn plan(plan_id: &str, retry_policy: RetryPolicy) -> ChunkPlan {
+    ChunkPlan {
+        plan_id: plan_id.into(),
+        repo_id: "repo-retry".into(),
+        chunker_config: "bytes=1024;max=64".into(),
+        source_span: "src/lib.rs:0-10".into(),
+        hash: "hash".into(),
+        retry_policy,
+        tenant_id: None,
+    }
+}
+
+fn policy(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_attempts,
+        backoff_ms: 100,
+        max_backoff_ms: 250,
+        jitter_ms: 0,
+    }
+}
+
+#[test]
+fn delays_grow_exponentially_within_cap_and_jitter_is_bounded() {
+    let policy = policy(5);
+    let delays: Vec<_> = (1..=4)
+        .map(|attempt| policy.delay_for(attempt, "p"))
+        .collect();
+    assert_eq!(
+        delays,
+        [100, 200, 250, 250].map(Duration::from_millis).to_vec()
+    );
+
+    let jittered = RetryPolicy {
+        jitter_ms: 50,
+        ..policy
+    };
+    for attempt in 1..=4 {
+        let delay = jittered.delay_for(attempt, "p");
+        assert!(delay >= policy.delay_for(attempt, "p"));
+        assert!(delay <= policy.delay_for(attempt, "p") + Duration::from_millis(50));
+        assert_eq!(delay, jittered.delay_for(attempt, "p"));
+    }
+}
+
+#[test]
+fn maximal_jitter_does_not_overflow() {
+    let unbounded = RetryPolicy {
+        jitter_ms: u64::MAX,
+        ..policy(3)
+    };
+    for attempt in 1..=3 {
+        assert!(unbounded.delay_for(attempt, "p") >= policy(3).delay_for(attempt, "p"));
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn retryable_failures_are_retried_until_success() {
+    let executor = RetryExecutor::new();
+    let report = executor
+        .run(&plan("p-1", policy(3)), |_, attempt| async move {
+            if attempt < 3 {
+                Err(ChunkError::Retryable("rate limited".into()))
+            } else {
+                Ok(attempt * 10)
+            }
+        })
+        .await;
+
+    assert_eq!(report.result, Ok(30));
+    assert_eq!(report.attempts, 3);
+    assert_eq!(report.waited, Duration::from_millis(300));
+}
+
+#[tokio::test(start_paused = true)]
+async fn fatal_errors_and_exhausted_attempts_are_reported_per_chunk() {
+    let plans = vec![
+        plan("fatal", policy(5)),
+        plan("flaky", policy(2)),
+        plan("ok", policy(2)),
+    ];
+    let reports = RetryExecutor::new()
+        .run_all(&plans, |plan, _| {
+            let result = match plan.plan_id.as_str() {
+                "fatal" => Err(ChunkError::Fatal("bad payload".into())),
+                "flaky" => Err(ChunkError::Retryable("timeout".into())),
+                _ => Ok(()),
+            };
+            async move { result }
+        })
+        .await;
+
+    let summary: Vec<_> = reports
+        .iter()
+        .map(|report| (report.plan_id.as_str(), report.attempts, report.succeeded()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![("fatal", 1, false), ("flaky", 2, false), ("ok", 1, true)]
+    );
+    assert_eq!(
+        reports[1].result,
+        Err(ChunkError::Retryable("timeout".into()))
+    );
+}
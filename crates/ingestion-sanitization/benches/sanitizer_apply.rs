@@ -0,0 +1,60 @@
+//! `Sanitizer::apply` on single large chunks, clean and with findings.
+//!
+//! Run with `cargo bench -p ingestion-sanitization --bench sanitizer_apply`.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use ingestion_planning::{ChunkPlan, PlannedChunk, RetryPolicy};
+use ingestion_sanitization::{PiiConfig, SanitizationConfig, Sanitizer};
+
+const CHUNK_SIZES: [usize; 2] = [64 * 1024, 1024 * 1024];
+
+fn chunk(size: usize, secrets: bool) -> PlannedChunk {
+    let mut payload = String::with_capacity(size);
+    let mut line = 0;
+    while payload.len() < size {
+        if secrets && line % 50 == 0 {
+            payload.push_str("let key = \"API_KEY=abc123\"; // ops@example.com\n");
+        } else {
+            payload.push_str("fn handler(request: Request) -> Response { route(request) }\n");
+        }
+        line += 1;
+    }
+    PlannedChunk::new(
+        ChunkPlan {
+            plan_id: format!("bench::src/lib.rs::{size}"),
+            repo_id: "bench".into(),
+            chunker_config: format!("bytes={size};max=1"),
+            source_span: format!("src/lib.rs:0-{size}"),
+            hash: String::new(),
+            retry_policy: RetryPolicy::default(),
+            tenant_id: None,
+        },
+        payload,
+    )
+}
+
+fn sanitizer_apply(c: &mut Criterion) {
+    let sanitizer = Sanitizer::new(SanitizationConfig {
+        pii: PiiConfig::all(),
+        ..SanitizationConfig::default()
+    })
+    .expect("default patterns compile");
+
+    let mut group = c.benchmark_group("sanitizer_apply");
+    group.sample_size(20);
+    for size in CHUNK_SIZES {
+        group.throughput(Throughput::Bytes(size as u64));
+        for (label, secrets) in [("clean", false), ("findings", true)] {
+            let chunk = chunk(size, secrets);
+            group.bench_with_input(BenchmarkId::new(label, size), &chunk, |b, chunk| {
+                b.iter(|| sanitizer.apply(black_box(chunk)).expect("sanitize"));
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, sanitizer_apply);
+criterion_main!(benches);
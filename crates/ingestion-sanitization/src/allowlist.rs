@@ -0,0 +1,93 @@
+//! Allowlist entries that suppress known false-positive redactions.
+
+use ingestion_planning::ChunkPlan;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::SanitizationError;
+
+/// Exception for matches that must not be redacted.
+///
+/// A redaction match is suppressed when `pattern` matches the whole matched
+/// text and the chunk falls inside the optional repository and path scope.
+/// Patterns are anchored at both ends, so an entry for a placeholder never
+/// spares a real secret that merely contains it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AllowlistEntry {
+    pub pattern: String,
+    /// Why the match is safe; carried into the suppressed-findings report.
+    pub justification: String,
+    #[serde(default)]
+    pub repo_id: Option<String>,
+    /// Repository-relative path prefix, e.g. `docs/`.
+    #[serde(default)]
+    pub path_prefix: Option<String>,
+}
+
+impl AllowlistEntry {
+    pub fn new(pattern: impl Into<String>, justification: impl Into<String>) -> Self {
+        Self {
+            pattern: pattern.into(),
+            justification: justification.into(),
+            repo_id: None,
+            path_prefix: None,
+        }
+    }
+
+    #[must_use]
+    pub fn for_repo(mut self, repo_id: impl Into<String>) -> Self {
+        self.repo_id = Some(repo_id.into());
+        self
+    }
+
+    #[must_use]
+    pub fn under_path(mut self, path_prefix: impl Into<String>) -> Self {
+        self.path_prefix = Some(path_prefix.into());
+        self
+    }
+
+    /// Compile `pattern` into the full-match regex the redactor applies.
+    pub(crate) fn compile(&self) -> Result<Regex, SanitizationError> {
+        let invalid = |_| SanitizationError::InvalidPattern(self.pattern.clone());
+        // Checked on its own first: wrapping can make an unbalanced pattern
+        // such as `a)(b` parse.
+        Regex::new(&self.pattern).map_err(invalid)?;
+        Regex::new(&format!("^(?:{})$", self.pattern)).map_err(invalid)
+    }
+
+    pub(crate) fn applies_to(&self, plan: &ChunkPlan) -> bool {
+        self.repo_id
+            .as_deref()
+            .map_or(true, |repo_id| repo_id == plan.repo_id)
+            && self
+                .path_prefix
+                .as_deref()
+                .map_or(true, |prefix| chunk_path(plan).starts_with(prefix))
+    }
+}
+
+/// Redaction matches left in place because of an allowlist entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SuppressedFinding {
+    /// Label of the rule that matched.
+    pub rule: String,
+    pub justification: String,
+    pub count: usize,
+}
+
+/// Repository-relative path of the chunk, recovered from its plan id
+/// (`repo::path::index`) or, failing that, its source span.
+pub(crate) fn chunk_path(plan: &ChunkPlan) -> &str {
+    plan.plan_id
+        .strip_prefix(plan.repo_id.as_str())
+        .and_then(|rest| rest.strip_prefix("::"))
+        .and_then(|rest| rest.rsplit_once("::"))
+        .map_or_else(
+            || {
+                plan.source_span
+                    .split_once(':')
+                    .map_or(plan.source_span.as_str(), |(path, _)| path)
+            },
+            |(path, _)| path,
+        )
+}
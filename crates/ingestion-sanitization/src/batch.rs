@@ -0,0 +1,76 @@
+//! Parallel sanitization of chunk batches with an aggregated report.
+
+use std::collections::BTreeMap;
+
+use ingestion_planning::PlannedChunk;
+use ingestion_workspace::parallel::{parallel_map, resolve_workers};
+
+use crate::allowlist::chunk_path;
+use crate::{SanitizationError, SanitizedChunk, Sanitizer};
+
+/// Totals across every chunk of a batch.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SanitizationReport {
+    pub chunks: usize,
+    /// Redacted matches per rule label.
+    pub per_pattern: BTreeMap<String, usize>,
+    /// Redacted matches per repository-relative file path.
+    pub per_file: BTreeMap<String, usize>,
+    /// Matches left in place by allowlist entries.
+    pub suppressed: usize,
+    /// Chunks flagged for review, e.g. `script-reviewed`.
+    pub flagged: usize,
+    /// Chunks withheld by screening.
+    pub skipped: usize,
+}
+
+impl SanitizationReport {
+    fn record(&mut self, path: &str, chunk: &SanitizedChunk) {
+        self.chunks += 1;
+        for finding in &chunk.findings {
+            *self.per_pattern.entry(finding.label.clone()).or_default() += finding.count;
+            *self.per_file.entry(path.to_string()).or_default() += finding.count;
+        }
+        self.suppressed += chunk
+            .suppressed
+            .iter()
+            .map(|finding| finding.count)
+            .sum::<usize>();
+        if chunk.is_skipped() {
+            self.skipped += 1;
+        } else if chunk.validation_status != "clean" {
+            self.flagged += 1;
+        }
+    }
+}
+
+/// Sanitized chunks in input order plus their aggregated report.
+#[derive(Debug, Clone)]
+pub struct SanitizedBatch {
+    pub chunks: Vec<SanitizedChunk>,
+    pub report: SanitizationReport,
+}
+
+impl Sanitizer {
+    /// Sanitize `chunks` across `config.workers` threads.
+    ///
+    /// Output order matches input order; the first failing chunk, in input
+    /// order, aborts the batch.
+    pub fn apply_batch(
+        &self,
+        chunks: &[PlannedChunk],
+    ) -> Result<SanitizedBatch, SanitizationError> {
+        let workers = resolve_workers(self.config.workers);
+        let results = parallel_map(chunks, workers, |chunk| self.apply(chunk));
+        let mut batch = SanitizedBatch {
+            chunks: Vec::with_capacity(chunks.len()),
+            report: SanitizationReport::default(),
+        };
+        for (chunk, result) in chunks.iter().zip(results) {
+            let sanitized = result?;
+            batch.report.record(chunk_path(chunk.plan()), &sanitized);
+            batch.chunks.push(sanitized);
+        }
+        Ok(batch)
+    }
+}
@@ -0,0 +1,198 @@
+//! Router commands for reviewing chunks held in a [`QuarantineStore`].
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use runtime_router::{CommandHandler, HandlerRouter, RouterError, RouterResponse, SessionContext};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{QuarantineStore, QuarantinedChunk, SanitizationError};
+
+/// Command listing chunks awaiting review.
+pub const PENDING_COMMAND: &str = "sanitization.pending";
+/// Command approving a quarantined chunk (`{ plan_id, note? }`).
+pub const APPROVE_COMMAND: &str = "sanitization.approve";
+/// Command rejecting a quarantined chunk (`{ plan_id, note? }`).
+pub const REJECT_COMMAND: &str = "sanitization.reject";
+
+/// Capability required for every quarantine command.
+pub const REVIEW_CAPABILITY: &str = "sanitization.review";
+
+/// Command recovering the secret behind a vault token (`{ token }`).
+#[cfg(feature = "vault")]
+pub const REVEAL_COMMAND: &str = "sanitization.reveal";
+/// Capability required to de-reference vault tokens.
+#[cfg(feature = "vault")]
+pub const REVEAL_CAPABILITY: &str = "sanitization.reveal";
+
+/// Register the quarantine review commands on `router`.
+pub fn register_commands(router: &mut HandlerRouter, store: Arc<QuarantineStore>) {
+    router
+        .register_with_capabilities(
+            PENDING_COMMAND,
+            vec![REVIEW_CAPABILITY.into()],
+            Arc::new(PendingHandler {
+                store: Arc::clone(&store),
+            }),
+        )
+        .register_with_capabilities(
+            APPROVE_COMMAND,
+            vec![REVIEW_CAPABILITY.into()],
+            Arc::new(ReviewHandler {
+                store: Arc::clone(&store),
+                approve: true,
+            }),
+        )
+        .register_with_capabilities(
+            REJECT_COMMAND,
+            vec![REVIEW_CAPABILITY.into()],
+            Arc::new(ReviewHandler {
+                store,
+                approve: false,
+            }),
+        );
+}
+
+/// Register the vault de-reference command on `router`.
+#[cfg(feature = "vault")]
+pub fn register_vault_commands(router: &mut HandlerRouter, vault: Arc<crate::TokenVault>) {
+    router.register_with_capabilities(
+        REVEAL_COMMAND,
+        vec![REVEAL_CAPABILITY.into()],
+        Arc::new(RevealHandler { vault }),
+    );
+}
+
+#[derive(Debug, Deserialize)]
+struct ReviewRequest {
+    plan_id: String,
+    #[serde(default)]
+    note: Option<String>,
+}
+
+struct PendingHandler {
+    store: Arc<QuarantineStore>,
+}
+
+#[async_trait]
+impl CommandHandler for PendingHandler {
+    async fn handle(
+        &self,
+        ctx: &SessionContext,
+        _payload: Value,
+    ) -> Result<RouterResponse, RouterError> {
+        let pending: Vec<Value> = self
+            .store
+            .pending()
+            .iter()
+            .filter(|entry| ctx.can_access_tenant(entry.chunk.tenant_id.as_deref()))
+            .map(describe)
+            .collect();
+        Ok(RouterResponse::ok(json!({ "pending": pending })))
+    }
+}
+
+struct ReviewHandler {
+    store: Arc<QuarantineStore>,
+    approve: bool,
+}
+
+#[async_trait]
+impl CommandHandler for ReviewHandler {
+    async fn handle(
+        &self,
+        ctx: &SessionContext,
+        payload: Value,
+    ) -> Result<RouterResponse, RouterError> {
+        let request: ReviewRequest =
+            serde_json::from_value(payload).map_err(|err| RouterError::InvalidRequest {
+                detail: err.to_string(),
+            })?;
+        // Another tenant's chunk is answered as if it were not quarantined.
+        let visible = self
+            .store
+            .get(&request.plan_id)
+            .is_some_and(|entry| ctx.can_access_tenant(entry.chunk.tenant_id.as_deref()));
+        if !visible {
+            return Err(router_error(SanitizationError::UnknownChunk(
+                request.plan_id,
+            )));
+        }
+        let reviewed = if self.approve {
+            self.store
+                .approve(&request.plan_id, &ctx.principal, request.note)
+        } else {
+            self.store
+                .reject(&request.plan_id, &ctx.principal, request.note)
+        }
+        .map_err(router_error)?;
+        Ok(RouterResponse::ok(describe(&reviewed)))
+    }
+}
+
+#[cfg(feature = "vault")]
+#[derive(Debug, Deserialize)]
+struct RevealRequest {
+    token: String,
+}
+
+#[cfg(feature = "vault")]
+struct RevealHandler {
+    vault: Arc<crate::TokenVault>,
+}
+
+#[cfg(feature = "vault")]
+#[async_trait]
+impl CommandHandler for RevealHandler {
+    async fn handle(
+        &self,
+        ctx: &SessionContext,
+        payload: Value,
+    ) -> Result<RouterResponse, RouterError> {
+        let request: RevealRequest =
+            serde_json::from_value(payload).map_err(|err| RouterError::InvalidRequest {
+                detail: err.to_string(),
+            })?;
+        let owner = self.vault.tenant(&request.token).map_err(router_error)?;
+        if !ctx.can_access_tenant(owner.as_deref()) {
+            return Err(router_error(SanitizationError::UnknownToken(request.token)));
+        }
+        let (category, secret) = self.vault.reveal(&request.token).map_err(router_error)?;
+        tracing::warn!(
+            principal = %ctx.principal,
+            token = %request.token,
+            category = %category,
+            "vault token revealed"
+        );
+        Ok(RouterResponse::ok(json!({
+            "token": request.token,
+            "category": category,
+            "secret": secret,
+        })))
+    }
+}
+
+fn describe(entry: &QuarantinedChunk) -> Value {
+    json!({
+        "plan_id": entry.chunk.plan_id,
+        "validation_status": entry.chunk.validation_status,
+        "state": entry.state,
+        "reviewer": entry.reviewer,
+        "note": entry.note,
+        "findings": entry.chunk.redaction_log,
+    })
+}
+
+fn router_error(err: SanitizationError) -> RouterError {
+    match err {
+        SanitizationError::UnknownChunk(_) | SanitizationError::UnknownToken(_) => {
+            RouterError::NotFound {
+                detail: err.to_string(),
+            }
+        }
+        other => RouterError::Internal {
+            detail: other.to_string(),
+        },
+    }
+}
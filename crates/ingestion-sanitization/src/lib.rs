@@ -1,14 +1,54 @@
 //! Sanitization filters and validation logic.
 
-use blake3::Hasher;
+use std::fmt;
+use std::sync::Arc;
+
 use ingestion_planning::PlannedChunk;
-use regex::Regex;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+pub mod allowlist;
+pub mod batch;
+#[cfg(feature = "native")]
+pub mod commands;
+pub mod pii;
+#[cfg(feature = "native")]
+pub mod quarantine;
+pub mod redactor;
+pub mod ruleset;
+pub mod screening;
+#[cfg(feature = "native")]
+pub mod telemetry;
+#[cfg(feature = "vault")]
+pub mod vault;
+
+pub use allowlist::{AllowlistEntry, SuppressedFinding};
+pub use batch::{SanitizationReport, SanitizedBatch};
+pub use pii::{PiiConfig, PiiKind};
+#[cfg(feature = "native")]
+pub use quarantine::{QuarantineStore, QuarantinedChunk, ReviewState, QUARANTINE_VERSION};
+pub use redactor::{Finding, PatternRedactor, Redaction, Redactor};
+#[cfg(feature = "native")]
+pub use ruleset::ReloadingSanitizer;
+pub use ruleset::{RedactionRule, Ruleset, ScriptIndicatorRule, Severity};
+pub use screening::{ScreenVerdict, ScreeningConfig};
+#[cfg(feature = "vault")]
+pub use vault::{TokenVault, TOKEN_PREFIX, VAULT_VERSION};
+
 #[derive(Debug, Clone)]
 pub struct SanitizationConfig {
     pub redact_patterns: Vec<String>,
     pub script_indicators: Vec<String>,
+    /// Named rules, typically loaded from a [`Ruleset`] file.
+    pub rules: Vec<RedactionRule>,
+    /// Exceptions for known false positives.
+    pub allowlist: Vec<AllowlistEntry>,
+    /// Personal data detectors, logged under their own category.
+    pub pii: PiiConfig,
+    /// Threads used by [`Sanitizer::apply_batch`]; `0` uses every available core.
+    pub workers: usize,
+    /// Binary, base64, and minified content screening run before redaction.
+    pub screening: ScreeningConfig,
 }
 
 impl Default for SanitizationConfig {
@@ -21,56 +61,139 @@ impl Default for SanitizationConfig {
                 r#"(?i)token\s*[:=]\s*['"][^'"]+['"]"#.into(),
             ],
             script_indicators: vec!["#!/bin".into(), "#!/usr/bin/env".into()],
+            rules: Vec::new(),
+            allowlist: Vec::new(),
+            pii: PiiConfig::default(),
+            workers: 0,
+            screening: ScreeningConfig::default(),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SanitizedChunk {
     pub plan_id: String,
+    /// Source location and content hash copied from the chunk plan so
+    /// downstream vectors stay traceable to source lines.
+    #[serde(default)]
+    pub source_span: String,
+    #[serde(default)]
+    pub hash: String,
+    /// Tenant owning the chunk, copied from the plan; `None` for tenant-less
+    /// repositories.
+    #[serde(default)]
+    pub tenant_id: Option<String>,
     pub scrubbed_payload: String,
     pub redaction_log: Vec<String>,
+    /// Structured form of `redaction_log`.
+    pub findings: Vec<Finding>,
     pub validation_status: String,
+    /// Matches left unredacted by allowlist entries.
+    pub suppressed: Vec<SuppressedFinding>,
+}
+
+impl SanitizedChunk {
+    /// Whether screening withheld the chunk; its payload is then empty.
+    #[must_use]
+    pub fn is_skipped(&self) -> bool {
+        self.validation_status.starts_with("skipped-")
+    }
 }
 
 #[derive(Debug, Error)]
 pub enum SanitizationError {
     #[error("invalid redaction pattern: {0}")]
     InvalidPattern(String),
+    #[error("invalid sanitization ruleset: {0}")]
+    Ruleset(String),
+    #[error("redactor failed: {0}")]
+    Redactor(String),
+    #[error("quarantine store error: {0}")]
+    Quarantine(String),
+    #[error("no pending quarantined chunk: {0}")]
+    UnknownChunk(String),
+    #[error("redaction vault error: {0}")]
+    Vault(String),
+    #[error("unknown vault token: {0}")]
+    UnknownToken(String),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct Sanitizer {
     config: SanitizationConfig,
+    builtin: PatternRedactor,
+    redactors: Vec<Arc<dyn Redactor>>,
+}
+
+impl fmt::Debug for Sanitizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let redactors: Vec<&str> = self.redactors.iter().map(|r| r.name()).collect();
+        f.debug_struct("Sanitizer")
+            .field("config", &self.config)
+            .field("redactors", &redactors)
+            .finish()
+    }
 }
 
 impl Sanitizer {
+    /// Compile every configured pattern once, rejecting invalid ones up front.
+    pub fn new(config: SanitizationConfig) -> Result<Self, SanitizationError> {
+        let builtin = PatternRedactor::new(&config)?;
+        Ok(Self {
+            config,
+            builtin,
+            redactors: Vec::new(),
+        })
+    }
+
+    /// Tokenize redacted matches into `vault` so privileged reviewers can
+    /// recover them; without a vault matches become `[REDACTED]`.
+    #[cfg(feature = "vault")]
     #[must_use]
-    pub const fn new(config: SanitizationConfig) -> Self {
-        Self { config }
+    pub fn with_vault(mut self, vault: Arc<vault::TokenVault>) -> Self {
+        self.builtin = self.builtin.with_vault(vault);
+        self
     }
 
+    /// Append a custom redactor, run after the built-in pattern redactor.
+    #[must_use]
+    pub fn with_redactor(mut self, redactor: Arc<dyn Redactor>) -> Self {
+        self.redactors.push(redactor);
+        self
+    }
+
+    /// Screen, redact, and validate one chunk.
+    ///
+    /// Chunks rejected by screening are returned with an empty payload and a
+    /// `skipped-*` validation status instead of being redacted.
     pub fn apply(&self, chunk: &PlannedChunk) -> Result<SanitizedChunk, SanitizationError> {
+        let verdict = self
+            .config
+            .screening
+            .screen(allowlist::chunk_path(chunk.plan()), chunk.payload());
+        if let Some(status) = verdict.status() {
+            return Ok(SanitizedChunk {
+                plan_id: chunk.plan().plan_id.clone(),
+                source_span: chunk.plan().source_span.clone(),
+                hash: chunk.plan().hash.clone(),
+                tenant_id: chunk.plan().tenant_id.clone(),
+                scrubbed_payload: String::new(),
+                redaction_log: Vec::new(),
+                findings: Vec::new(),
+                validation_status: status.to_string(),
+                suppressed: Vec::new(),
+            });
+        }
+        let chain = std::iter::once(&self.builtin as &dyn Redactor)
+            .chain(self.redactors.iter().map(|redactor| redactor.as_ref()));
         let mut scrubbed = chunk.payload().to_string();
-        let mut redaction_log = Vec::new();
-        for pattern in &self.config.redact_patterns {
-            let regex = Regex::new(pattern)
-                .map_err(|_| SanitizationError::InvalidPattern(pattern.clone()))?;
-            let matches: Vec<String> = regex
-                .find_iter(&scrubbed)
-                .map(|mat| mat.as_str().to_string())
-                .collect();
-            if !matches.is_empty() {
-                let mut hasher = Hasher::new();
-                for capture in &matches {
-                    hasher.update(capture.as_bytes());
-                    hasher.update(&[0u8]);
-                }
-                let digest = hasher.finalize().to_hex().to_string();
-                let count = matches.len();
-                redaction_log.push(format!("{pattern} => count={count}, digest={digest}"));
-                scrubbed = regex.replace_all(&scrubbed, "[REDACTED]").into_owned();
-            }
+        let mut findings = Vec::new();
+        let mut suppressed = Vec::new();
+        for redactor in chain {
+            let redaction = redactor.redact(chunk.plan(), &scrubbed)?;
+            scrubbed = redaction.scrubbed;
+            findings.extend(redaction.findings);
+            suppressed.extend(redaction.suppressed);
         }
 
         let mut validation_status = String::from("clean");
@@ -83,9 +206,14 @@ impl Sanitizer {
 
         Ok(SanitizedChunk {
             plan_id: chunk.plan().plan_id.clone(),
+            source_span: chunk.plan().source_span.clone(),
+            hash: chunk.plan().hash.clone(),
+            tenant_id: chunk.plan().tenant_id.clone(),
             scrubbed_payload: scrubbed,
-            redaction_log,
+            redaction_log: findings.iter().map(ToString::to_string).collect(),
+            findings,
             validation_status,
+            suppressed,
         })
     }
 }
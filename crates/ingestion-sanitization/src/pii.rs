@@ -0,0 +1,98 @@
+//! Detectors for personally identifiable information.
+
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use serde::{Deserialize, Serialize};
+
+/// Class of personal data recognised by the PII pass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PiiKind {
+    Email,
+    Phone,
+    Iban,
+    IpAddress,
+}
+
+impl PiiKind {
+    pub const ALL: [Self; 4] = [Self::Email, Self::Phone, Self::Iban, Self::IpAddress];
+
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Email => "email",
+            Self::Phone => "phone",
+            Self::Iban => "iban",
+            Self::IpAddress => "ip_address",
+        }
+    }
+
+    pub(crate) const fn pattern(self) -> &'static str {
+        match self {
+            Self::Email => r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b",
+            Self::Phone => r"(?:\+\d{1,3}[\s.-]?)?(?:\(\d{3}\)|\b\d{3})[\s.-]?\d{3}[\s.-]\d{4}\b",
+            Self::Iban => r"\b[A-Z]{2}\d{2}(?:\s?[A-Z0-9]{4}){2,7}(?:\s?[A-Z0-9]{1,3})?\b",
+            Self::IpAddress => {
+                r"\b(?:\d{1,3}\.){3}\d{1,3}\b|\b(?:[0-9A-Fa-f]{1,4}:){2,7}(?::|[0-9A-Fa-f]{1,4})\b"
+            }
+        }
+    }
+
+    /// Confirm a regex match so near-misses (invalid octets, bad IBAN
+    /// checksums, short digit runs) are not redacted.
+    pub(crate) fn confirm(self, candidate: &str) -> bool {
+        match self {
+            Self::Email => true,
+            Self::Phone => {
+                let digits = candidate.chars().filter(char::is_ascii_digit).count();
+                (10..=15).contains(&digits)
+            }
+            Self::Iban => iban_checksum_valid(candidate),
+            Self::IpAddress => {
+                candidate.parse::<Ipv4Addr>().is_ok() || candidate.parse::<Ipv6Addr>().is_ok()
+            }
+        }
+    }
+}
+
+impl fmt::Display for PiiKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// PII detectors to run; disabled by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PiiConfig {
+    #[serde(default)]
+    pub kinds: Vec<PiiKind>,
+}
+
+impl PiiConfig {
+    /// Enable every supported detector.
+    #[must_use]
+    pub fn all() -> Self {
+        Self {
+            kinds: PiiKind::ALL.to_vec(),
+        }
+    }
+}
+
+/// ISO 13616 mod-97 check.
+fn iban_checksum_valid(candidate: &str) -> bool {
+    let compact: Vec<char> = candidate.chars().filter(|c| !c.is_whitespace()).collect();
+    if !(15..=34).contains(&compact.len()) {
+        return false;
+    }
+    let (head, tail) = compact.split_at(4);
+    let mut remainder = 0_u32;
+    for c in tail.iter().chain(head) {
+        let Some(value) = c.to_digit(36) else {
+            return false;
+        };
+        let digits = if value >= 10 { 100 } else { 10 };
+        remainder = (remainder * digits + value) % 97;
+    }
+    remainder == 1
+}
@@ -0,0 +1,235 @@
+//! Holding area for chunks that need human review before embedding.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{SanitizationError, SanitizedChunk};
+
+/// Version of the quarantine file layout this build reads and writes.
+pub const QUARANTINE_VERSION: u32 = 1;
+
+/// Review state of a quarantined chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewState {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+/// A flagged chunk and its review trail.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuarantinedChunk {
+    pub chunk: SanitizedChunk,
+    pub state: ReviewState,
+    pub reviewer: Option<String>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct QuarantineFile {
+    version: u32,
+    entries: BTreeMap<String, QuarantinedChunk>,
+}
+
+/// Store that holds flagged chunks back from embedding until approved.
+///
+/// Chunks whose `validation_status` is listed in `review_statuses` are held;
+/// approved chunks are released through [`QuarantineStore::release_approved`].
+#[derive(Debug)]
+pub struct QuarantineStore {
+    path: Option<PathBuf>,
+    review_statuses: Vec<String>,
+    entries: Mutex<BTreeMap<String, QuarantinedChunk>>,
+}
+
+impl Default for QuarantineStore {
+    fn default() -> Self {
+        Self::in_memory()
+    }
+}
+
+impl QuarantineStore {
+    /// Store without persistence that quarantines `script-reviewed` chunks.
+    #[must_use]
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            review_statuses: vec!["script-reviewed".into()],
+            entries: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Open a store persisted at `path`, creating it on first write.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, SanitizationError> {
+        let path = path.into();
+        let entries = match fs::read(&path) {
+            Ok(bytes) => {
+                let file: QuarantineFile = serde_json::from_slice(&bytes).map_err(|err| {
+                    SanitizationError::Quarantine(format!("{}: {err}", path.display()))
+                })?;
+                if file.version != QUARANTINE_VERSION {
+                    return Err(SanitizationError::Quarantine(format!(
+                        "{}: unsupported quarantine version {}",
+                        path.display(),
+                        file.version
+                    )));
+                }
+                file.entries
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => {
+                return Err(SanitizationError::Quarantine(format!(
+                    "{}: {err}",
+                    path.display()
+                )))
+            }
+        };
+        Ok(Self {
+            path: Some(path),
+            entries: Mutex::new(entries),
+            ..Self::in_memory()
+        })
+    }
+
+    /// Replace the validation statuses that trigger quarantine.
+    #[must_use]
+    pub fn with_review_statuses(mut self, statuses: Vec<String>) -> Self {
+        self.review_statuses = statuses;
+        self
+    }
+
+    #[must_use]
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Hold flagged chunks and return the ones that may be embedded now.
+    pub fn admit(
+        &self,
+        chunks: Vec<SanitizedChunk>,
+    ) -> Result<Vec<SanitizedChunk>, SanitizationError> {
+        let (held, admitted): (Vec<_>, Vec<_>) = chunks
+            .into_iter()
+            .partition(|chunk| self.review_statuses.contains(&chunk.validation_status));
+        if !held.is_empty() {
+            let mut entries = self.lock();
+            let mut next = entries.clone();
+            for chunk in held {
+                tracing::info!(
+                    plan_id = %chunk.plan_id,
+                    status = %chunk.validation_status,
+                    "quarantining chunk for review"
+                );
+                next.insert(
+                    chunk.plan_id.clone(),
+                    QuarantinedChunk {
+                        chunk,
+                        state: ReviewState::Pending,
+                        reviewer: None,
+                        note: None,
+                    },
+                );
+            }
+            self.persist(&next)?;
+            *entries = next;
+        }
+        Ok(admitted)
+    }
+
+    /// Chunks awaiting review, in plan id order.
+    #[must_use]
+    pub fn pending(&self) -> Vec<QuarantinedChunk> {
+        self.lock()
+            .values()
+            .filter(|entry| entry.state == ReviewState::Pending)
+            .cloned()
+            .collect()
+    }
+
+    /// The entry held for `plan_id`, in any review state.
+    #[must_use]
+    pub fn get(&self, plan_id: &str) -> Option<QuarantinedChunk> {
+        self.lock().get(plan_id).cloned()
+    }
+
+    pub fn approve(
+        &self,
+        plan_id: &str,
+        reviewer: &str,
+        note: Option<String>,
+    ) -> Result<QuarantinedChunk, SanitizationError> {
+        self.review(plan_id, ReviewState::Approved, reviewer, note)
+    }
+
+    pub fn reject(
+        &self,
+        plan_id: &str,
+        reviewer: &str,
+        note: Option<String>,
+    ) -> Result<QuarantinedChunk, SanitizationError> {
+        self.review(plan_id, ReviewState::Rejected, reviewer, note)
+    }
+
+    /// Remove approved chunks from the store and hand them to the embedder.
+    pub fn release_approved(&self) -> Result<Vec<SanitizedChunk>, SanitizationError> {
+        let mut entries = self.lock();
+        let (approved, next): (BTreeMap<_, _>, BTreeMap<_, _>) = entries
+            .clone()
+            .into_iter()
+            .partition(|(_, entry)| entry.state == ReviewState::Approved);
+        self.persist(&next)?;
+        *entries = next;
+        Ok(approved.into_values().map(|entry| entry.chunk).collect())
+    }
+
+    fn review(
+        &self,
+        plan_id: &str,
+        state: ReviewState,
+        reviewer: &str,
+        note: Option<String>,
+    ) -> Result<QuarantinedChunk, SanitizationError> {
+        let mut entries = self.lock();
+        let mut next = entries.clone();
+        let entry = next
+            .get_mut(plan_id)
+            .filter(|entry| entry.state == ReviewState::Pending)
+            .ok_or_else(|| SanitizationError::UnknownChunk(plan_id.to_string()))?;
+        entry.state = state;
+        entry.reviewer = Some(reviewer.to_string());
+        entry.note = note;
+        let reviewed = entry.clone();
+        self.persist(&next)?;
+        *entries = next;
+        Ok(reviewed)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<String, QuarantinedChunk>> {
+        self.entries.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn persist(
+        &self,
+        entries: &BTreeMap<String, QuarantinedChunk>,
+    ) -> Result<(), SanitizationError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let io = |err: std::io::Error| {
+            SanitizationError::Quarantine(format!("{}: {err}", path.display()))
+        };
+        let file = QuarantineFile {
+            version: QUARANTINE_VERSION,
+            entries: entries.clone(),
+        };
+        let bytes = serde_json::to_vec_pretty(&file).map_err(|err| {
+            SanitizationError::Quarantine(format!("serializing quarantine: {err}"))
+        })?;
+        storage_atomic::write(path, &bytes).map_err(io)
+    }
+}
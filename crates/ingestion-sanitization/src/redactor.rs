@@ -0,0 +1,294 @@
+//! Redactor extension point and the built-in pattern redactor.
+
+use std::fmt;
+#[cfg(feature = "vault")]
+use std::sync::Arc;
+
+use blake3::Hasher;
+use ingestion_planning::ChunkPlan;
+use regex::{Captures, Regex, RegexSet};
+use serde::{Deserialize, Serialize};
+
+use crate::allowlist::{AllowlistEntry, SuppressedFinding};
+#[cfg(feature = "vault")]
+use crate::vault::TokenVault;
+use crate::{PiiKind, SanitizationConfig, SanitizationError};
+
+/// Matches removed by one detector within a chunk.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Finding {
+    pub label: String,
+    /// Class of data removed, e.g. `secret` or `pii:email`.
+    pub category: String,
+    pub count: usize,
+    /// Digest over the removed text so audits can correlate without the secret.
+    pub digest: String,
+}
+
+impl Finding {
+    /// Summarise `matches`, hashing each one into the digest.
+    pub fn from_matches(
+        label: impl Into<String>,
+        category: impl Into<String>,
+        matches: &[String],
+    ) -> Self {
+        let mut hasher = Hasher::new();
+        for capture in matches {
+            hasher.update(capture.as_bytes());
+            hasher.update(&[0u8]);
+        }
+        Self {
+            label: label.into(),
+            category: category.into(),
+            count: matches.len(),
+            digest: hasher.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} => count={}, digest={}, category={}",
+            self.label, self.count, self.digest, self.category
+        )
+    }
+}
+
+/// Output of a single [`Redactor`] pass.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Redaction {
+    pub scrubbed: String,
+    pub findings: Vec<Finding>,
+    pub suppressed: Vec<SuppressedFinding>,
+}
+
+impl Redaction {
+    /// Pass-through result for redactors that found nothing.
+    pub fn unchanged(text: impl Into<String>) -> Self {
+        Self {
+            scrubbed: text.into(),
+            ..Self::default()
+        }
+    }
+}
+
+/// Detector that scrubs chunk text; the [`Sanitizer`](crate::Sanitizer) runs
+/// redactors in order, feeding each one the previous pass's output.
+pub trait Redactor: Send + Sync {
+    /// Identifier used in diagnostics.
+    fn name(&self) -> &str;
+
+    fn redact(&self, plan: &ChunkPlan, text: &str) -> Result<Redaction, SanitizationError>;
+}
+
+/// Built-in redactor for secret patterns, named rules, and PII detectors,
+/// honouring the configured allowlist.
+///
+/// Patterns are compiled once; a [`RegexSet`] pass over the original text
+/// selects which detectors run, so clean chunks cost a single scan.
+#[derive(Debug, Clone)]
+pub struct PatternRedactor {
+    detectors: Vec<Detector>,
+    /// All detector patterns, used to skip detectors that cannot match a chunk.
+    prefilter: RegexSet,
+    allowlist: Vec<(Regex, AllowlistEntry)>,
+    /// When set, matches become reversible vault tokens instead of `[REDACTED]`.
+    #[cfg(feature = "vault")]
+    vault: Option<Arc<TokenVault>>,
+}
+
+impl PatternRedactor {
+    /// Compile every pattern in `config`, failing on the first invalid one.
+    pub fn new(config: &SanitizationConfig) -> Result<Self, SanitizationError> {
+        let mut detectors = Vec::new();
+        for pattern in &config.redact_patterns {
+            detectors.push(Detector::compile(
+                pattern.clone(),
+                "secret".into(),
+                pattern,
+            )?);
+        }
+        for rule in config.rules.iter().filter(|rule| rule.enabled) {
+            let label = format!("{} [{}] {}", rule.name, rule.severity, rule.pattern);
+            detectors.push(Detector::compile(label, "secret".into(), &rule.pattern)?);
+        }
+        for kind in &config.pii.kinds {
+            let mut detector =
+                Detector::compile(format!("pii:{kind}"), format!("pii:{kind}"), kind.pattern())?;
+            detector.pii = Some(*kind);
+            detectors.push(detector);
+        }
+        let allowlist = config
+            .allowlist
+            .iter()
+            .map(|entry| entry.compile().map(|regex| (regex, entry.clone())))
+            .collect::<Result<_, _>>()?;
+        let prefilter = RegexSet::new(detectors.iter().map(|detector| detector.regex.as_str()))
+            .map_err(|err| SanitizationError::InvalidPattern(err.to_string()))?;
+        Ok(Self {
+            detectors,
+            prefilter,
+            allowlist,
+            #[cfg(feature = "vault")]
+            vault: None,
+        })
+    }
+
+    /// Replace matches with tokens sealed in `vault`.
+    #[cfg(feature = "vault")]
+    #[must_use]
+    pub fn with_vault(mut self, vault: Arc<TokenVault>) -> Self {
+        self.vault = Some(vault);
+        self
+    }
+
+    #[cfg(feature = "vault")]
+    fn replacement(
+        &self,
+        plan: &ChunkPlan,
+        category: &str,
+        text: &str,
+    ) -> Result<String, SanitizationError> {
+        match &self.vault {
+            Some(vault) => vault.tokenize(plan.tenant_id.as_deref(), category, text),
+            None => Ok(String::from("[REDACTED]")),
+        }
+    }
+
+    #[cfg(not(feature = "vault"))]
+    #[allow(clippy::unnecessary_wraps, clippy::unused_self)]
+    fn replacement(
+        &self,
+        _plan: &ChunkPlan,
+        _category: &str,
+        _text: &str,
+    ) -> Result<String, SanitizationError> {
+        Ok(String::from("[REDACTED]"))
+    }
+}
+
+impl PatternRedactor {
+    /// Scrub `text` outside any chunk, such as a telemetry message: only
+    /// allowlist entries scoped to no repository or path apply, and
+    /// matches always become `[REDACTED]`, never vault tokens.
+    pub fn redact_text(&self, text: &str) -> Result<Redaction, SanitizationError> {
+        let allowlist: Vec<_> = self
+            .allowlist
+            .iter()
+            .filter(|(_, entry)| entry.repo_id.is_none() && entry.path_prefix.is_none())
+            .collect();
+        self.scrub(text, &allowlist, |_, _| Ok(String::from("[REDACTED]")))
+    }
+
+    fn scrub(
+        &self,
+        text: &str,
+        allowlist: &[&(Regex, AllowlistEntry)],
+        replacement: impl Fn(&str, &str) -> Result<String, SanitizationError>,
+    ) -> Result<Redaction, SanitizationError> {
+        let mut redaction = Redaction::unchanged(text);
+        let candidates = self.prefilter.matches(text);
+        if !candidates.matched_any() {
+            return Ok(redaction);
+        }
+        for detector in candidates.iter().map(|index| &self.detectors[index]) {
+            let mut matches: Vec<String> = Vec::new();
+            let mut failure = None;
+            let replaced = detector
+                .regex
+                .replace_all(&redaction.scrubbed, |caps: &Captures<'_>| {
+                    let text = &caps[0];
+                    if !detector.confirm(text) {
+                        return text.to_string();
+                    }
+                    let allowed = allowlist.iter().find(|(allow, _)| allow.is_match(text));
+                    if let Some((_, entry)) = allowed {
+                        record_suppressed(
+                            &mut redaction.suppressed,
+                            &detector.label,
+                            &entry.justification,
+                        );
+                        text.to_string()
+                    } else {
+                        matches.push(text.to_string());
+                        replacement(&detector.category, text).unwrap_or_else(|err| {
+                            failure.get_or_insert(err);
+                            String::from("[REDACTED]")
+                        })
+                    }
+                })
+                .into_owned();
+            if let Some(err) = failure {
+                return Err(err);
+            }
+            if !matches.is_empty() {
+                redaction.findings.push(Finding::from_matches(
+                    detector.label.clone(),
+                    detector.category.clone(),
+                    &matches,
+                ));
+                redaction.scrubbed = replaced;
+            }
+        }
+        Ok(redaction)
+    }
+}
+
+impl Redactor for PatternRedactor {
+    fn name(&self) -> &str {
+        "patterns"
+    }
+
+    fn redact(&self, plan: &ChunkPlan, text: &str) -> Result<Redaction, SanitizationError> {
+        let allowlist: Vec<_> = self
+            .allowlist
+            .iter()
+            .filter(|(_, entry)| entry.applies_to(plan))
+            .collect();
+        self.scrub(text, &allowlist, |category, text| {
+            self.replacement(plan, category, text)
+        })
+    }
+}
+
+/// Compiled matcher for one redaction pattern or PII detector.
+#[derive(Debug, Clone)]
+struct Detector {
+    label: String,
+    category: String,
+    regex: Regex,
+    pii: Option<PiiKind>,
+}
+
+impl Detector {
+    fn compile(label: String, category: String, pattern: &str) -> Result<Self, SanitizationError> {
+        let regex = Regex::new(pattern)
+            .map_err(|_| SanitizationError::InvalidPattern(pattern.to_string()))?;
+        Ok(Self {
+            label,
+            category,
+            regex,
+            pii: None,
+        })
+    }
+
+    fn confirm(&self, candidate: &str) -> bool {
+        self.pii.map_or(true, |kind| kind.confirm(candidate))
+    }
+}
+
+fn record_suppressed(suppressed: &mut Vec<SuppressedFinding>, rule: &str, justification: &str) {
+    match suppressed
+        .iter_mut()
+        .find(|finding| finding.rule == rule && finding.justification == justification)
+    {
+        Some(finding) => finding.count += 1,
+        None => suppressed.push(SuppressedFinding {
+            rule: rule.to_string(),
+            justification: justification.to_string(),
+            count: 1,
+        }),
+    }
+}
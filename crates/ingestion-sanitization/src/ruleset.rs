@@ -0,0 +1,210 @@
+//! Externally managed redaction rulesets with hot-reload support.
+
+use std::fmt;
+#[cfg(feature = "native")]
+use std::fs;
+#[cfg(feature = "native")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "native")]
+use std::sync::{Arc, RwLock};
+#[cfg(feature = "native")]
+use std::time::SystemTime;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "native")]
+use crate::Sanitizer;
+use crate::{AllowlistEntry, PiiConfig, SanitizationConfig, SanitizationError, ScreeningConfig};
+
+/// Impact assigned to findings produced by a rule.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    #[default]
+    Medium,
+    High,
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+            Self::Critical => "critical",
+        })
+    }
+}
+
+const fn enabled_by_default() -> bool {
+    true
+}
+
+/// Named redaction pattern.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedactionRule {
+    pub name: String,
+    pub pattern: String,
+    #[serde(default)]
+    pub severity: Severity,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+/// Named prefix marking a chunk as an executable script.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScriptIndicatorRule {
+    pub name: String,
+    pub indicator: String,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+/// Detection rules loaded from a TOML or YAML file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Ruleset {
+    #[serde(default)]
+    pub rules: Vec<RedactionRule>,
+    #[serde(default)]
+    pub script_indicators: Vec<ScriptIndicatorRule>,
+    #[serde(default)]
+    pub allowlist: Vec<AllowlistEntry>,
+    #[serde(default)]
+    pub pii: PiiConfig,
+    #[serde(default)]
+    pub screening: ScreeningConfig,
+}
+
+impl Ruleset {
+    pub fn from_toml_str(source: &str) -> Result<Self, SanitizationError> {
+        toml::from_str(source).map_err(|err| SanitizationError::Ruleset(err.to_string()))
+    }
+
+    pub fn from_yaml_str(source: &str) -> Result<Self, SanitizationError> {
+        serde_yaml::from_str(source).map_err(|err| SanitizationError::Ruleset(err.to_string()))
+    }
+
+    /// Load a ruleset, choosing the format from the file extension
+    /// (`.toml`, `.yaml`, or `.yml`) and validating every enabled pattern.
+    #[cfg(feature = "native")]
+    pub fn load(path: &Path) -> Result<Self, SanitizationError> {
+        let source = fs::read_to_string(path)
+            .map_err(|err| SanitizationError::Ruleset(format!("{}: {err}", path.display())))?;
+        let ruleset = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml_str(&source),
+            Some("yaml" | "yml") => Self::from_yaml_str(&source),
+            _ => Err(SanitizationError::Ruleset(format!(
+                "{}: unsupported ruleset format",
+                path.display()
+            ))),
+        }?;
+        ruleset.validate()?;
+        Ok(ruleset)
+    }
+
+    /// Compile every enabled pattern and allowlist entry, failing on the
+    /// first invalid one.
+    pub fn validate(&self) -> Result<(), SanitizationError> {
+        for rule in self.rules.iter().filter(|rule| rule.enabled) {
+            Regex::new(&rule.pattern)
+                .map_err(|_| SanitizationError::InvalidPattern(rule.pattern.clone()))?;
+        }
+        for entry in &self.allowlist {
+            entry.compile()?;
+        }
+        Ok(())
+    }
+}
+
+impl SanitizationConfig {
+    /// Configuration containing only the enabled rules of `ruleset`.
+    #[must_use]
+    pub fn from_ruleset(ruleset: &Ruleset) -> Self {
+        Self {
+            redact_patterns: Vec::new(),
+            script_indicators: ruleset
+                .script_indicators
+                .iter()
+                .filter(|indicator| indicator.enabled)
+                .map(|indicator| indicator.indicator.clone())
+                .collect(),
+            rules: ruleset
+                .rules
+                .iter()
+                .filter(|rule| rule.enabled)
+                .cloned()
+                .collect(),
+            allowlist: ruleset.allowlist.clone(),
+            pii: ruleset.pii.clone(),
+            workers: 0,
+            screening: ruleset.screening.clone(),
+        }
+    }
+}
+
+/// Sanitizer rebuilt whenever its ruleset file changes on disk.
+///
+/// Callers poll [`ReloadingSanitizer::reload_if_changed`] (for example from a
+/// workspace watcher tick); a ruleset that fails to parse or validate leaves
+/// the previous sanitizer in place.
+#[cfg(feature = "native")]
+#[derive(Debug)]
+pub struct ReloadingSanitizer {
+    path: PathBuf,
+    state: RwLock<(Option<SystemTime>, Arc<Sanitizer>)>,
+}
+
+#[cfg(feature = "native")]
+impl ReloadingSanitizer {
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, SanitizationError> {
+        let path = path.into();
+        let modified = modified_at(&path);
+        let sanitizer = Self::build(&path)?;
+        Ok(Self {
+            path,
+            state: RwLock::new((modified, Arc::new(sanitizer))),
+        })
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Sanitizer built from the most recently loaded ruleset.
+    #[must_use]
+    pub fn current(&self) -> Arc<Sanitizer> {
+        let state = self.state.read().unwrap_or_else(|err| err.into_inner());
+        Arc::clone(&state.1)
+    }
+
+    /// Reload the ruleset if its modification time changed; returns whether
+    /// a new sanitizer was installed.
+    pub fn reload_if_changed(&self) -> Result<bool, SanitizationError> {
+        let modified = modified_at(&self.path);
+        {
+            let state = self.state.read().unwrap_or_else(|err| err.into_inner());
+            if state.0 == modified {
+                return Ok(false);
+            }
+        }
+        let sanitizer = Self::build(&self.path)?;
+        let mut state = self.state.write().unwrap_or_else(|err| err.into_inner());
+        *state = (modified, Arc::new(sanitizer));
+        tracing::info!(path = %self.path.display(), "reloaded sanitization ruleset");
+        Ok(true)
+    }
+
+    fn build(path: &Path) -> Result<Sanitizer, SanitizationError> {
+        let ruleset = Ruleset::load(path)?;
+        Sanitizer::new(SanitizationConfig::from_ruleset(&ruleset))
+    }
+}
+
+#[cfg(feature = "native")]
+fn modified_at(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
@@ -0,0 +1,119 @@
+//! Pre-sanitization screening for content that cannot be usefully embedded.
+
+use serde::{Deserialize, Serialize};
+
+/// Thresholds for the screening pass.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScreeningConfig {
+    pub enabled: bool,
+    /// Share of control or replacement characters above which content is binary.
+    pub max_control_ratio: f32,
+    /// Longest run of base64 alphabet characters tolerated before content is
+    /// treated as an encoded blob.
+    pub max_base64_run: usize,
+    /// Lines longer than this count towards the minified verdict.
+    pub minified_line_bytes: usize,
+    /// Share of bytes on over-long lines above which content is minified.
+    pub minified_ratio: f32,
+    /// File extensions the minified check applies to; prose such as long
+    /// markdown paragraphs is never treated as minified.
+    pub minified_extensions: Vec<String>,
+}
+
+impl Default for ScreeningConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_control_ratio: 0.1,
+            max_base64_run: 1_024,
+            minified_line_bytes: 1_000,
+            minified_ratio: 0.5,
+            minified_extensions: ["js", "mjs", "cjs", "css", "json"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        }
+    }
+}
+
+/// Outcome of screening one chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreenVerdict {
+    Embeddable,
+    Binary,
+    Base64,
+    Minified,
+}
+
+impl ScreenVerdict {
+    /// Validation status recorded for skipped chunks.
+    #[must_use]
+    pub const fn status(self) -> Option<&'static str> {
+        match self {
+            Self::Embeddable => None,
+            Self::Binary => Some("skipped-binary"),
+            Self::Base64 => Some("skipped-base64"),
+            Self::Minified => Some("skipped-minified"),
+        }
+    }
+}
+
+impl ScreeningConfig {
+    /// Classify `text` read from `path`; disabled screening accepts everything.
+    ///
+    /// The binary and base64 checks apply to every file, while the minified
+    /// check only runs for paths matching `minified_extensions`.
+    #[must_use]
+    pub fn screen(&self, path: &str, text: &str) -> ScreenVerdict {
+        if !self.enabled || text.is_empty() {
+            return ScreenVerdict::Embeddable;
+        }
+        let chars = text.chars().count();
+        let control = text
+            .chars()
+            .filter(|c| (c.is_control() && !c.is_whitespace()) || *c == char::REPLACEMENT_CHARACTER)
+            .count();
+        if control as f32 / chars as f32 > self.max_control_ratio {
+            return ScreenVerdict::Binary;
+        }
+        if longest_base64_run(text) > self.max_base64_run {
+            return ScreenVerdict::Base64;
+        }
+        if !self.minifiable(path) {
+            return ScreenVerdict::Embeddable;
+        }
+        let long_line_bytes: usize = text
+            .lines()
+            .map(str::len)
+            .filter(|len| *len > self.minified_line_bytes)
+            .sum();
+        if long_line_bytes as f32 / text.len() as f32 > self.minified_ratio {
+            return ScreenVerdict::Minified;
+        }
+        ScreenVerdict::Embeddable
+    }
+
+    fn minifiable(&self, path: &str) -> bool {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        name.rsplit_once('.').is_some_and(|(_, extension)| {
+            self.minified_extensions
+                .iter()
+                .any(|candidate| candidate.eq_ignore_ascii_case(extension))
+        })
+    }
+}
+
+fn longest_base64_run(text: &str) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'+' | b'/' | b'=') {
+            current += 1;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+    longest
+}
@@ -0,0 +1,43 @@
+//! The sanitizer's redaction patterns as a telemetry [`Redact`].
+//!
+//! Adapters built `with_redactor` pass every telemetry message through
+//! [`PatternRedactor::redact_text`], so the secrets, named rules and PII
+//! detectors configured for chunks are kept out of telemetry too. A
+//! [`ReloadingSanitizer`] redacts with its latest ruleset. Custom
+//! [`Redactor`](crate::Redactor)s need a chunk plan and do not apply.
+
+use std::sync::Arc;
+
+use runtime_telemetry::{Redact, SharedRedactor};
+
+use crate::ruleset::ReloadingSanitizer;
+use crate::{PatternRedactor, SanitizationConfig, SanitizationError, Sanitizer};
+
+/// What a message becomes when it cannot be scrubbed.
+const UNSCRUBBABLE: &str = "[REDACTED]";
+
+/// Telemetry redactor using `config`'s patterns.
+pub fn telemetry_redactor(
+    config: &SanitizationConfig,
+) -> Result<SharedRedactor, SanitizationError> {
+    Ok(Arc::new(PatternRedactor::new(config)?))
+}
+
+impl Redact for PatternRedactor {
+    fn redact(&self, text: &str) -> String {
+        self.redact_text(text)
+            .map_or_else(|_| UNSCRUBBABLE.to_string(), |redaction| redaction.scrubbed)
+    }
+}
+
+impl Redact for Sanitizer {
+    fn redact(&self, text: &str) -> String {
+        Redact::redact(&self.builtin, text)
+    }
+}
+
+impl Redact for ReloadingSanitizer {
+    fn redact(&self, text: &str) -> String {
+        Redact::redact(&*self.current(), text)
+    }
+}
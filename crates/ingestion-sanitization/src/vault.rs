@@ -0,0 +1,332 @@
+//! Reversible redaction: secrets swapped for opaque tokens, sealed at rest.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+use storage_vector::encryption::{peek_key_id, Encrypter};
+use storage_vector::kms::{KeyManager, KeyScope};
+
+use crate::SanitizationError;
+
+/// Layout version stamped into the vault log header. Version 1 vaults, which
+/// stored every entry in a single JSON document, are converted on open; any
+/// other version fails instead of risking unreadable tokens.
+pub const VAULT_VERSION: u32 = 2;
+
+/// Prefix of every token emitted by a [`TokenVault`].
+pub const TOKEN_PREFIX: &str = "[TOKEN:";
+
+/// Context string separating the token MAC key from the sealing key.
+const TOKEN_CONTEXT: &str = "embednexus vault token v1";
+
+/// Hex digits of the keyed digest kept in each token (128 bits).
+const TOKEN_HEX_DIGITS: usize = 32;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct VaultEntry {
+    category: String,
+    /// Tenant whose chunk held the secret; only its sessions may reveal it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tenant_id: Option<String>,
+    /// Base64 of the sealed envelope; the token and tenant are bound in as AAD.
+    envelope: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct VaultHeader {
+    version: u32,
+}
+
+/// One line of the vault log after the header.
+#[derive(Debug, Serialize, Deserialize)]
+struct VaultRecord {
+    token: String,
+    #[serde(flatten)]
+    entry: VaultEntry,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LegacyVaultFile {
+    version: u32,
+    entries: BTreeMap<String, VaultEntry>,
+}
+
+/// Encrypted token-to-secret mapping used when redaction must be reversible.
+///
+/// Tokens are derived from the secret and its tenant with a hash keyed by a
+/// MAC key derived from the scope's current key, so the same secret maps to
+/// the same token across a tenant's chunks and runs until the key rotates,
+/// and never to another tenant's token. Secrets are sealed with the
+/// configured [`Encrypter`] and can only be recovered through
+/// [`TokenVault::reveal`]. Persisted vaults are an append-only log: a header
+/// line followed by one JSON line per secret.
+pub struct TokenVault {
+    encrypter: Arc<dyn Encrypter>,
+    keys: Arc<dyn KeyManager>,
+    scope: KeyScope,
+    path: Option<PathBuf>,
+    entries: Mutex<BTreeMap<String, VaultEntry>>,
+}
+
+impl fmt::Debug for TokenVault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenVault")
+            .field("scope", &self.scope)
+            .field("path", &self.path)
+            .field("tokens", &self.lock().len())
+            .finish()
+    }
+}
+
+impl TokenVault {
+    /// Vault kept in memory only, sealing with keys from `scope`.
+    pub fn in_memory(
+        encrypter: Arc<dyn Encrypter>,
+        keys: Arc<dyn KeyManager>,
+        scope: KeyScope,
+    ) -> Self {
+        Self {
+            encrypter,
+            keys,
+            scope,
+            path: None,
+            entries: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Open a vault persisted at `path`, creating it on first write.
+    pub fn open(
+        path: impl Into<PathBuf>,
+        encrypter: Arc<dyn Encrypter>,
+        keys: Arc<dyn KeyManager>,
+        scope: KeyScope,
+    ) -> Result<Self, SanitizationError> {
+        let path = path.into();
+        let entries = match fs::read(&path) {
+            Ok(bytes) => load(&path, &bytes)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => {
+                return Err(SanitizationError::Vault(format!(
+                    "{}: {err}",
+                    path.display()
+                )))
+            }
+        };
+        Ok(Self {
+            path: Some(path),
+            entries: Mutex::new(entries),
+            ..Self::in_memory(encrypter, keys, scope)
+        })
+    }
+
+    #[must_use]
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Number of distinct secrets held.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Swap `secret`, found in a chunk owned by `tenant_id`, for its token,
+    /// sealing it on first sight.
+    pub fn tokenize(
+        &self,
+        tenant_id: Option<&str>,
+        category: &str,
+        secret: &str,
+    ) -> Result<String, SanitizationError> {
+        let key = self
+            .keys
+            .current(&self.scope)
+            .map_err(SanitizationError::Vault)?;
+        let mac_key = blake3::derive_key(TOKEN_CONTEXT, key.key_bytes.as_ref());
+        let mut hasher = blake3::Hasher::new_keyed(&mac_key);
+        if let Some(tenant_id) = tenant_id {
+            hasher.update(&(tenant_id.len() as u64).to_le_bytes());
+            hasher.update(tenant_id.as_bytes());
+        }
+        let digest = hasher.update(secret.as_bytes()).finalize();
+        let token = format!("{TOKEN_PREFIX}{}]", &digest.to_hex()[..TOKEN_HEX_DIGITS]);
+        let mut entries = self.lock();
+        if entries.contains_key(&token) {
+            return Ok(token);
+        }
+        let envelope = self
+            .encrypter
+            .seal(&key, secret.as_bytes(), &aad(&token, tenant_id))
+            .map_err(SanitizationError::Vault)?;
+        let entry = VaultEntry {
+            category: category.to_string(),
+            tenant_id: tenant_id.map(str::to_owned),
+            envelope: STANDARD.encode(envelope),
+        };
+        self.append(&token, &entry)?;
+        entries.insert(token.clone(), entry);
+        Ok(token)
+    }
+
+    /// Tenant owning the secret behind `token`, checked before revealing it
+    /// to a tenant session.
+    pub fn tenant(&self, token: &str) -> Result<Option<String>, SanitizationError> {
+        self.lock()
+            .get(token)
+            .map(|entry| entry.tenant_id.clone())
+            .ok_or_else(|| SanitizationError::UnknownToken(token.to_string()))
+    }
+
+    /// Recover the secret behind `token` along with its finding category.
+    pub fn reveal(&self, token: &str) -> Result<(String, String), SanitizationError> {
+        let entry = self
+            .lock()
+            .get(token)
+            .cloned()
+            .ok_or_else(|| SanitizationError::UnknownToken(token.to_string()))?;
+        let envelope = STANDARD
+            .decode(&entry.envelope)
+            .map_err(|err| SanitizationError::Vault(format!("{token}: {err}")))?;
+        let key_id = peek_key_id(&envelope)
+            .ok_or_else(|| SanitizationError::Vault(format!("{token}: malformed envelope")))?;
+        let key = self.keys.get(&key_id).map_err(SanitizationError::Vault)?;
+        let secret = self
+            .encrypter
+            .open(&key, &envelope, &aad(token, entry.tenant_id.as_deref()))
+            .map_err(SanitizationError::Vault)?;
+        let secret = String::from_utf8(secret)
+            .map_err(|err| SanitizationError::Vault(format!("{token}: {err}")))?;
+        Ok((entry.category, secret))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<String, VaultEntry>> {
+        self.entries.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Append one record to the vault log, writing the header first when the
+    /// log is new. A failed write is truncated away so later appends start on
+    /// a clean line.
+    fn append(&self, token: &str, entry: &VaultEntry) -> Result<(), SanitizationError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let io =
+            |err: std::io::Error| SanitizationError::Vault(format!("{}: {err}", path.display()));
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(io)?;
+        let base = file.metadata().map_err(io)?.len();
+        let mut bytes = Vec::new();
+        if base == 0 {
+            bytes.extend(render_line(&VaultHeader {
+                version: VAULT_VERSION,
+            })?);
+        }
+        bytes.extend(render_line(&VaultRecord {
+            token: token.to_string(),
+            entry: entry.clone(),
+        })?);
+        if let Err(err) = file.write_all(&bytes).and_then(|()| file.sync_data()) {
+            let _ = file.set_len(base);
+            return Err(io(err));
+        }
+        if base == 0 {
+            if let Some(parent) = path.parent() {
+                storage_atomic::sync_dir(parent).map_err(io)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Additional data sealed with a secret: the token, followed by the owning
+/// tenant so an edited log cannot move a secret to another tenant.
+fn aad(token: &str, tenant_id: Option<&str>) -> Vec<u8> {
+    let mut aad = token.as_bytes().to_vec();
+    if let Some(tenant_id) = tenant_id {
+        aad.push(b'@');
+        aad.extend_from_slice(tenant_id.as_bytes());
+    }
+    aad
+}
+
+fn render_line<T: Serialize>(value: &T) -> Result<Vec<u8>, SanitizationError> {
+    let mut line = serde_json::to_vec(value)
+        .map_err(|err| SanitizationError::Vault(format!("serializing vault: {err}")))?;
+    line.push(b'\n');
+    Ok(line)
+}
+
+/// Read a vault log, dropping a torn trailing record and converting version 1
+/// documents to the log layout in place.
+fn load(path: &Path, bytes: &[u8]) -> Result<BTreeMap<String, VaultEntry>, SanitizationError> {
+    let invalid = |err: String| SanitizationError::Vault(format!("{}: {err}", path.display()));
+    if let Ok(legacy) = serde_json::from_slice::<LegacyVaultFile>(bytes) {
+        if legacy.version != 1 {
+            return Err(invalid(format!(
+                "unsupported vault version {}",
+                legacy.version
+            )));
+        }
+        let mut log = render_line(&VaultHeader {
+            version: VAULT_VERSION,
+        })?;
+        for (token, entry) in &legacy.entries {
+            log.extend(render_line(&VaultRecord {
+                token: token.clone(),
+                entry: entry.clone(),
+            })?);
+        }
+        storage_atomic::write(path, &log).map_err(|err| invalid(err.to_string()))?;
+        return Ok(legacy.entries);
+    }
+    let complete = bytes
+        .iter()
+        .rposition(|byte| *byte == b'\n')
+        .map_or(0, |last| last + 1);
+    let mut lines = bytes[..complete]
+        .split(|byte| *byte == b'\n')
+        .filter(|line| !line.is_empty());
+    let header: VaultHeader = match lines.next() {
+        Some(line) => serde_json::from_slice(line).map_err(|err| invalid(err.to_string()))?,
+        None => VaultHeader {
+            version: VAULT_VERSION,
+        },
+    };
+    if header.version != VAULT_VERSION {
+        return Err(invalid(format!(
+            "unsupported vault version {}",
+            header.version
+        )));
+    }
+    let mut entries = BTreeMap::new();
+    for line in lines {
+        let record: VaultRecord =
+            serde_json::from_slice(line).map_err(|err| invalid(err.to_string()))?;
+        entries.insert(record.token, record.entry);
+    }
+    if complete < bytes.len() {
+        let file = OpenOptions::new()
+            .write(true)
+            .open(path)
+            .map_err(|err| invalid(err.to_string()))?;
+        file.set_len(complete as u64)
+            .and_then(|()| file.sync_data())
+            .map_err(|err| invalid(err.to_string()))?;
+    }
+    Ok(entries)
+}
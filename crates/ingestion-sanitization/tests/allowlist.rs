@@ -0,0 +1,116 @@
+use ingestion_planning::{ChunkPlan, PlannedChunk, RetryPolicy};
+use ingestion_sanitization::{AllowlistEntry, Ruleset, SanitizationConfig, Sanitizer};
+
+const PAYLOAD: &str = "password = \"example\"\npassword = \"hunter2\"";
+
+fn chunk(repo_id: &str, path: &str) -> PlannedChunk {
+    PlannedChunk::new(
+        ChunkPlan {
+            plan_id: format!("{repo_id}::{path}::0"),
+            repo_id: repo_id.into(),
+            chunker_config: "bytes=1024;max=64".into(),
+            source_span: format!("{path}:0-42"),
+            hash: "hash".into(),
+            retry_policy: RetryPolicy::default(),
+            tenant_id: None,
+        },
+        PAYLOAD,
+    )
+}
+
+fn sanitizer(entry: AllowlistEntry) -> Sanitizer {
+    Sanitizer::new(SanitizationConfig {
+        allowlist: vec![entry],
+        ..SanitizationConfig::default()
+    })
+    .expect("sanitizer config should compile")
+}
+
+#[test]
+fn allowlisted_matches_are_kept_and_reported() {
+    let sanitizer = sanitizer(
+        AllowlistEntry::new(r#"password = "example""#, "documentation placeholder")
+            .under_path("docs/"),
+    );
+    let sanitized = sanitizer
+        .apply(&chunk("repo-allow", "docs/setup.md"))
+        .expect("sanitization should succeed");
+
+    assert_eq!(
+        sanitized.scrubbed_payload,
+        "password = \"example\"\n[REDACTED]"
+    );
+    assert_eq!(sanitized.redaction_log.len(), 1);
+    assert!(sanitized.redaction_log[0].contains("=> count=1"));
+    assert_eq!(sanitized.suppressed.len(), 1);
+    assert_eq!(sanitized.suppressed[0].count, 1);
+    assert_eq!(
+        sanitized.suppressed[0].justification,
+        "documentation placeholder"
+    );
+    assert!(sanitized.suppressed[0].rule.contains("password"));
+}
+
+#[test]
+fn entries_only_apply_within_their_repo_and_path_scope() {
+    let sanitizer = sanitizer(
+        AllowlistEntry::new(r#"password = "example""#, "fixture")
+            .for_repo("repo-allow")
+            .under_path("docs/"),
+    );
+    for (repo_id, path) in [
+        ("repo-allow", "src/config.rs"),
+        ("repo-other", "docs/setup.md"),
+    ] {
+        let sanitized = sanitizer
+            .apply(&chunk(repo_id, path))
+            .expect("sanitization should succeed");
+        assert_eq!(sanitized.scrubbed_payload, "[REDACTED]\n[REDACTED]");
+        assert!(sanitized.suppressed.is_empty());
+    }
+}
+
+#[test]
+fn entries_must_match_the_whole_finding() {
+    let sanitizer = sanitizer(AllowlistEntry::new("example", "placeholder"));
+    let secret = PlannedChunk::new(
+        chunk("repo-allow", "src/config.rs").into_plan(),
+        "password = \"example_real_secret\"",
+    );
+    let sanitized = sanitizer
+        .apply(&secret)
+        .expect("sanitization should succeed");
+    assert_eq!(sanitized.scrubbed_payload, "[REDACTED]");
+    assert!(sanitized.suppressed.is_empty());
+}
+
+#[test]
+fn rulesets_carry_allowlist_entries() {
+    let ruleset = Ruleset::from_toml_str(
+        r#"
+[[rules]]
+name = "password"
+pattern = "password = \"[^\"]+\""
+
+[[allowlist]]
+pattern = 'password = "example"'
+justification = "sample config"
+repo_id = "repo-allow"
+"#,
+    )
+    .expect("ruleset should parse");
+    ruleset.validate().expect("ruleset should validate");
+
+    let sanitized = Sanitizer::new(SanitizationConfig::from_ruleset(&ruleset))
+        .expect("sanitizer config should compile")
+        .apply(&chunk("repo-allow", "README.md"))
+        .expect("sanitization should succeed");
+    assert_eq!(
+        sanitized.scrubbed_payload,
+        "password = \"example\"\n[REDACTED]"
+    );
+    assert_eq!(
+        sanitized.suppressed[0].rule,
+        "password [medium] password = \"[^\"]+\""
+    );
+}
@@ -0,0 +1,99 @@
+use ingestion_planning::{ChunkPlan, PlannedChunk, RetryPolicy};
+use ingestion_sanitization::{AllowlistEntry, SanitizationConfig, SanitizationError, Sanitizer};
+
+fn chunk(path: &str, index: usize, payload: &str) -> PlannedChunk {
+    PlannedChunk::new(
+        ChunkPlan {
+            plan_id: format!("repo-batch::{path}::{index}"),
+            repo_id: "repo-batch".into(),
+            chunker_config: "bytes=1024;max=64".into(),
+            source_span: format!("{path}:0-64"),
+            hash: format!("hash-{index}"),
+            retry_policy: RetryPolicy::default(),
+            tenant_id: None,
+        },
+        payload,
+    )
+}
+
+fn batch() -> Vec<PlannedChunk> {
+    (0..64)
+        .map(|index| match index % 4 {
+            0 => chunk("src/secrets.rs", index, "SECRET-A and SECRET-B"),
+            1 => chunk("src/config.rs", index, "API_KEY=abc"),
+            2 => chunk("docs/setup.md", index, "password = \"example\""),
+            _ => chunk("scripts/run.sh", index, "#!/bin/sh\necho ok"),
+        })
+        .collect()
+}
+
+fn sanitizer(workers: usize) -> Sanitizer {
+    Sanitizer::new(SanitizationConfig {
+        workers,
+        allowlist: vec![AllowlistEntry::new(
+            r#"password = "example""#,
+            "docs placeholder",
+        )],
+        ..SanitizationConfig::default()
+    })
+    .expect("sanitizer config should compile")
+}
+
+#[test]
+fn batch_output_is_ordered_and_matches_sequential_apply() {
+    let chunks = batch();
+    let parallel = sanitizer(8)
+        .apply_batch(&chunks)
+        .expect("batch should succeed");
+    let single = sanitizer(1);
+    let sequential: Vec<_> = chunks
+        .iter()
+        .map(|chunk| single.apply(chunk).expect("apply should succeed"))
+        .collect();
+
+    let ids: Vec<_> = parallel
+        .chunks
+        .iter()
+        .map(|chunk| chunk.plan_id.clone())
+        .collect();
+    let expected: Vec<_> = chunks
+        .iter()
+        .map(|chunk| chunk.plan().plan_id.clone())
+        .collect();
+    assert_eq!(ids, expected);
+    for (left, right) in parallel.chunks.iter().zip(&sequential) {
+        assert_eq!(left.scrubbed_payload, right.scrubbed_payload);
+        assert_eq!(left.redaction_log, right.redaction_log);
+    }
+}
+
+#[test]
+fn batch_report_aggregates_patterns_files_and_flags() {
+    let report = sanitizer(4)
+        .apply_batch(&batch())
+        .expect("batch should succeed")
+        .report;
+
+    assert_eq!(report.chunks, 64);
+    assert_eq!(report.per_pattern["SECRET[-_A-Z0-9]+"], 32);
+    assert_eq!(report.per_pattern["API_KEY[=:][A-Za-z0-9_-]+"], 16);
+    assert_eq!(report.per_file["src/secrets.rs"], 32);
+    assert_eq!(report.per_file["src/config.rs"], 16);
+    assert!(!report.per_file.contains_key("docs/setup.md"));
+    assert_eq!(report.suppressed, 16);
+    assert_eq!(report.flagged, 16);
+}
+
+#[test]
+fn empty_batches_and_invalid_configs_are_handled() {
+    let empty = sanitizer(2).apply_batch(&[]).expect("empty batch");
+    assert!(empty.chunks.is_empty());
+    assert_eq!(empty.report.chunks, 0);
+
+    let err = Sanitizer::new(SanitizationConfig {
+        allowlist: vec![AllowlistEntry::new("(", "broken")],
+        ..SanitizationConfig::default()
+    })
+    .expect_err("invalid allowlist pattern");
+    assert!(matches!(err, SanitizationError::InvalidPattern(_)));
+}
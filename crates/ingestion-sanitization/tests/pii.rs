@@ -0,0 +1,83 @@
+use ingestion_planning::{ChunkPlan, PlannedChunk, RetryPolicy};
+use ingestion_sanitization::{PiiConfig, PiiKind, Ruleset, SanitizationConfig, Sanitizer};
+
+fn chunk(payload: &str) -> PlannedChunk {
+    PlannedChunk::new(
+        ChunkPlan {
+            plan_id: "repo-pii::docs/contacts.md::0".into(),
+            repo_id: "repo-pii".into(),
+            chunker_config: "bytes=1024;max=64".into(),
+            source_span: "docs/contacts.md:0-128".into(),
+            hash: "hash".into(),
+            retry_policy: RetryPolicy::default(),
+            tenant_id: None,
+        },
+        payload,
+    )
+}
+
+fn pii_sanitizer(pii: PiiConfig) -> Sanitizer {
+    Sanitizer::new(SanitizationConfig {
+        pii,
+        ..SanitizationConfig::default()
+    })
+    .expect("sanitizer config should compile")
+}
+
+#[test]
+fn pii_detectors_redact_and_tag_each_category() {
+    let payload = "mail ops@example.com or call +1 (555) 123-4567\n\
+                   pay GB82 WEST 1234 5698 7654 32 from 10.0.0.12";
+    let sanitized = pii_sanitizer(PiiConfig::all())
+        .apply(&chunk(payload))
+        .expect("sanitization should succeed");
+
+    assert_eq!(
+        sanitized.scrubbed_payload,
+        "mail [REDACTED] or call [REDACTED]\npay [REDACTED] from [REDACTED]"
+    );
+    for kind in PiiKind::ALL {
+        let tag = format!("category=pii:{kind}");
+        assert!(
+            sanitized
+                .redaction_log
+                .iter()
+                .any(|entry| entry.contains(&tag) && entry.contains("count=1")),
+            "missing {tag} in {:?}",
+            sanitized.redaction_log
+        );
+    }
+}
+
+#[test]
+fn pii_detection_is_opt_in_and_rejects_near_misses() {
+    let payload = "ops@example.com 999.1.1.1 GB00 WEST 1234 5698 7654 32 ext 12345";
+    let untouched = pii_sanitizer(PiiConfig::default())
+        .apply(&chunk(payload))
+        .expect("sanitization should succeed");
+    assert_eq!(untouched.scrubbed_payload, payload);
+
+    let sanitized = pii_sanitizer(PiiConfig::all())
+        .apply(&chunk(payload))
+        .expect("sanitization should succeed");
+    assert_eq!(
+        sanitized.scrubbed_payload,
+        "[REDACTED] 999.1.1.1 GB00 WEST 1234 5698 7654 32 ext 12345"
+    );
+}
+
+#[test]
+fn secrets_are_tagged_separately_and_rulesets_select_pii_kinds() {
+    let ruleset = Ruleset::from_yaml_str("pii:\n  kinds: [email]\n").expect("ruleset should parse");
+    let config = SanitizationConfig {
+        pii: ruleset.pii,
+        ..SanitizationConfig::default()
+    };
+    let sanitized = Sanitizer::new(config)
+        .expect("sanitizer config should compile")
+        .apply(&chunk("SECRET-1 ops@example.com 10.0.0.1"))
+        .expect("sanitization should succeed");
+    assert_eq!(sanitized.scrubbed_payload, "[REDACTED] [REDACTED] 10.0.0.1");
+    assert!(sanitized.redaction_log[0].ends_with("category=secret"));
+    assert!(sanitized.redaction_log[1].ends_with("category=pii:email"));
+}
@@ -0,0 +1,170 @@
+#![cfg(feature = "native")]
+use std::sync::Arc;
+
+use ingestion_planning::{ChunkPlan, PlannedChunk, RetryPolicy};
+use ingestion_sanitization::commands::{
+    self, APPROVE_COMMAND, PENDING_COMMAND, REJECT_COMMAND, REVIEW_CAPABILITY,
+};
+use ingestion_sanitization::{
+    QuarantineStore, ReviewState, SanitizationConfig, SanitizationError, SanitizedChunk, Sanitizer,
+};
+use runtime_router::{CommandRouter, HandlerRouter, RouterCommand, SessionContext};
+use serde_json::json;
+
+fn sanitized(index: usize, payload: &str) -> SanitizedChunk {
+    let chunk = PlannedChunk::new(
+        ChunkPlan {
+            plan_id: format!("repo-q::scripts/run.sh::{index}"),
+            repo_id: "repo-q".into(),
+            chunker_config: "bytes=1024;max=64".into(),
+            source_span: "scripts/run.sh:0-64".into(),
+            hash: format!("hash-{index}"),
+            retry_policy: RetryPolicy::default(),
+            tenant_id: None,
+        },
+        payload,
+    );
+    Sanitizer::new(SanitizationConfig::default())
+        .expect("sanitizer config should compile")
+        .apply(&chunk)
+        .expect("sanitization should succeed")
+}
+
+#[test]
+fn flagged_chunks_are_held_until_approved() {
+    let store = QuarantineStore::in_memory();
+    let admitted = store
+        .admit(vec![
+            sanitized(0, "#!/bin/sh\nrm -rf build"),
+            sanitized(1, "echo clean"),
+            sanitized(2, "#!/usr/bin/env python\nprint(1)"),
+        ])
+        .expect("admit should succeed");
+    assert_eq!(admitted.len(), 1);
+    assert_eq!(admitted[0].plan_id, "repo-q::scripts/run.sh::1");
+    assert_eq!(store.pending().len(), 2);
+
+    let approved = store
+        .approve(
+            "repo-q::scripts/run.sh::0",
+            "alice",
+            Some("build helper".into()),
+        )
+        .expect("approve pending chunk");
+    assert_eq!(approved.state, ReviewState::Approved);
+    store
+        .reject("repo-q::scripts/run.sh::2", "alice", None)
+        .expect("reject pending chunk");
+    assert!(matches!(
+        store.approve("repo-q::scripts/run.sh::2", "alice", None),
+        Err(SanitizationError::UnknownChunk(_))
+    ));
+
+    let released = store.release_approved().expect("release approved");
+    assert_eq!(released.len(), 1);
+    assert_eq!(released[0].plan_id, "repo-q::scripts/run.sh::0");
+    assert!(store.release_approved().expect("nothing left").is_empty());
+    assert!(store.pending().is_empty());
+}
+
+#[test]
+fn persisted_store_survives_reopen() {
+    let dir = tempfile::tempdir().expect("state dir");
+    let path = dir.path().join("quarantine.json");
+    {
+        let store = QuarantineStore::open(&path).expect("open store");
+        store
+            .admit(vec![sanitized(0, "#!/bin/sh\nexit 0")])
+            .expect("admit should succeed");
+    }
+    let reopened = QuarantineStore::open(&path).expect("reopen store");
+    let pending = reopened.pending();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].chunk.validation_status, "script-reviewed");
+}
+
+#[tokio::test]
+async fn router_commands_review_quarantined_chunks() {
+    let store = Arc::new(QuarantineStore::in_memory());
+    store
+        .admit(vec![
+            sanitized(0, "#!/bin/sh\nexit 0"),
+            sanitized(1, "#!/bin/sh\nexit 1"),
+        ])
+        .expect("admit should succeed");
+    let mut router = HandlerRouter::new();
+    commands::register_commands(&mut router, Arc::clone(&store));
+
+    let reviewer = SessionContext::new("bob", vec![REVIEW_CAPABILITY.into()]);
+    let reader = SessionContext::new("reader", vec!["search".into()]);
+    let err = router
+        .dispatch(reader, RouterCommand::new(PENDING_COMMAND, json!({})))
+        .await
+        .expect_err("review capability required");
+    assert_eq!(err.status_code(), 401);
+
+    let pending = router
+        .dispatch(
+            reviewer.clone(),
+            RouterCommand::new(PENDING_COMMAND, json!({})),
+        )
+        .await
+        .expect("list pending");
+    assert_eq!(pending.payload["pending"].as_array().map(Vec::len), Some(2));
+
+    let approved = router
+        .dispatch(
+            reviewer.clone(),
+            RouterCommand::new(
+                APPROVE_COMMAND,
+                json!({ "plan_id": "repo-q::scripts/run.sh::0", "note": "safe" }),
+            ),
+        )
+        .await
+        .expect("approve chunk");
+    assert_eq!(approved.payload["state"], json!("approved"));
+    assert_eq!(approved.payload["reviewer"], json!("bob"));
+
+    router
+        .dispatch(
+            reviewer.clone(),
+            RouterCommand::new(
+                REJECT_COMMAND,
+                json!({ "plan_id": "repo-q::scripts/run.sh::1" }),
+            ),
+        )
+        .await
+        .expect("reject chunk");
+    let err = router
+        .dispatch(
+            reviewer,
+            RouterCommand::new(REJECT_COMMAND, json!({ "plan_id": "missing" })),
+        )
+        .await
+        .expect_err("unknown chunk");
+    assert_eq!(err.status_code(), 404);
+    assert_eq!(store.release_approved().expect("release").len(), 1);
+}
+
+#[test]
+fn failed_persist_leaves_reviews_untouched() {
+    let dir = tempfile::tempdir().expect("state dir");
+    let path = dir.path().join("quarantine.json");
+    let store = QuarantineStore::open(&path).expect("open store");
+    store
+        .admit(vec![sanitized(0, "#!/bin/sh\nexit 0")])
+        .expect("admit should succeed");
+
+    std::fs::remove_file(&path).expect("remove quarantine file");
+    std::fs::create_dir(&path).expect("block the quarantine path");
+    assert!(store
+        .approve("repo-q::scripts/run.sh::0", "alice", None)
+        .is_err());
+    assert!(store
+        .admit(vec![sanitized(1, "#!/bin/sh\nexit 1")])
+        .is_err());
+
+    let pending = store.pending();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].reviewer, None);
+}
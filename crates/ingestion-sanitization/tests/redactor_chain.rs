@@ -0,0 +1,93 @@
+use std::sync::Arc;
+
+use ingestion_planning::{ChunkPlan, PlannedChunk, RetryPolicy};
+use ingestion_sanitization::{
+    Finding, Redaction, Redactor, SanitizationConfig, SanitizationError, Sanitizer,
+};
+
+/// Organisation-specific detector for employee ids such as `EMP-00042`.
+struct EmployeeIdRedactor;
+
+impl Redactor for EmployeeIdRedactor {
+    fn name(&self) -> &str {
+        "employee-id"
+    }
+
+    fn redact(&self, _plan: &ChunkPlan, text: &str) -> Result<Redaction, SanitizationError> {
+        let matches: Vec<String> = text
+            .split_whitespace()
+            .filter(|word| word.starts_with("EMP-"))
+            .map(str::to_string)
+            .collect();
+        if matches.is_empty() {
+            return Ok(Redaction::unchanged(text));
+        }
+        let mut scrubbed = text.to_string();
+        for id in &matches {
+            scrubbed = scrubbed.replace(id, "[EMPLOYEE]");
+        }
+        Ok(Redaction {
+            scrubbed,
+            findings: vec![Finding::from_matches("employee-id", "org:hr", &matches)],
+            suppressed: Vec::new(),
+        })
+    }
+}
+
+struct FailingRedactor;
+
+impl Redactor for FailingRedactor {
+    fn name(&self) -> &str {
+        "failing"
+    }
+
+    fn redact(&self, _plan: &ChunkPlan, _text: &str) -> Result<Redaction, SanitizationError> {
+        Err(SanitizationError::Redactor("detector offline".into()))
+    }
+}
+
+fn chunk(payload: &str) -> PlannedChunk {
+    PlannedChunk::new(
+        ChunkPlan {
+            plan_id: "repo-chain::src/hr.rs::0".into(),
+            repo_id: "repo-chain".into(),
+            chunker_config: "bytes=1024;max=64".into(),
+            source_span: "src/hr.rs:0-64".into(),
+            hash: "hash".into(),
+            retry_policy: RetryPolicy::default(),
+            tenant_id: None,
+        },
+        payload,
+    )
+}
+
+#[test]
+fn custom_redactors_run_after_builtin_patterns() {
+    let sanitizer = Sanitizer::new(SanitizationConfig::default())
+        .expect("sanitizer config should compile")
+        .with_redactor(Arc::new(EmployeeIdRedactor));
+    let sanitized = sanitizer
+        .apply(&chunk("owner EMP-00042 uses SECRET-9 and EMP-00077"))
+        .expect("sanitization should succeed");
+
+    assert_eq!(
+        sanitized.scrubbed_payload,
+        "owner [EMPLOYEE] uses [REDACTED] and [EMPLOYEE]"
+    );
+    assert_eq!(sanitized.redaction_log.len(), 2);
+    assert!(sanitized.redaction_log[0].contains("category=secret"));
+    assert!(sanitized.redaction_log[1].starts_with("employee-id => count=2"));
+    assert!(sanitized.redaction_log[1].ends_with("category=org:hr"));
+    assert!(format!("{sanitizer:?}").contains("employee-id"));
+}
+
+#[test]
+fn redactor_errors_abort_sanitization() {
+    let sanitizer = Sanitizer::new(SanitizationConfig::default())
+        .expect("sanitizer config should compile")
+        .with_redactor(Arc::new(FailingRedactor));
+    let err = sanitizer
+        .apply(&chunk("plain text"))
+        .expect_err("failing redactor should surface");
+    assert!(err.to_string().contains("detector offline"));
+}
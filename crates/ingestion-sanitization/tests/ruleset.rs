@@ -0,0 +1,127 @@
+#![cfg(feature = "native")]
+use std::fs::{self, File};
+use std::time::{Duration, SystemTime};
+
+use ingestion_planning::{ChunkPlan, PlannedChunk, RetryPolicy};
+use ingestion_sanitization::{
+    ReloadingSanitizer, Ruleset, SanitizationConfig, SanitizationError, Sanitizer, Severity,
+};
+
+const TOML_RULESET: &str = r##"
+[[rules]]
+name = "internal-token"
+pattern = "INT-[0-9]{4}"
+severity = "high"
+
+[[rules]]
+name = "legacy"
+pattern = "LEGACY"
+enabled = false
+
+[[script_indicators]]
+name = "python"
+indicator = "#!/usr/bin/python"
+"##;
+
+fn chunk(payload: &str) -> PlannedChunk {
+    PlannedChunk::new(
+        ChunkPlan {
+            plan_id: "repo-rules::src/lib.rs::0".into(),
+            repo_id: "repo-rules".into(),
+            chunker_config: "bytes=1024;max=64".into(),
+            source_span: "src/lib.rs:0-64".into(),
+            hash: "hash".into(),
+            retry_policy: RetryPolicy::default(),
+            tenant_id: None,
+        },
+        payload,
+    )
+}
+
+#[test]
+fn toml_ruleset_applies_enabled_rules_with_names_and_severities() {
+    let ruleset = Ruleset::from_toml_str(TOML_RULESET).expect("ruleset should parse");
+    assert_eq!(ruleset.rules[0].severity, Severity::High);
+    assert_eq!(ruleset.rules[1].severity, Severity::Medium);
+
+    let sanitizer = Sanitizer::new(SanitizationConfig::from_ruleset(&ruleset))
+        .expect("sanitizer config should compile");
+    let sanitized = sanitizer
+        .apply(&chunk("#!/usr/bin/python\nkey = INT-1234 # LEGACY"))
+        .expect("sanitization should succeed");
+    assert_eq!(
+        sanitized.scrubbed_payload,
+        "#!/usr/bin/python\nkey = [REDACTED] # LEGACY"
+    );
+    assert_eq!(sanitized.redaction_log.len(), 1);
+    assert!(sanitized.redaction_log[0].starts_with("internal-token [high] INT-[0-9]{4} => count=1"));
+    assert_eq!(sanitized.validation_status, "script-reviewed");
+}
+
+#[test]
+fn yaml_ruleset_loads_from_file_and_rejects_bad_patterns() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("rules.yaml");
+    fs::write(
+        &path,
+        "rules:\n  - name: ticket\n    pattern: 'TICKET-\\d+'\n    severity: low\n",
+    )
+    .expect("write ruleset");
+    let ruleset = Ruleset::load(&path).expect("yaml ruleset should load");
+    assert_eq!(ruleset.rules[0].name, "ticket");
+    assert_eq!(ruleset.rules[0].severity, Severity::Low);
+
+    fs::write(
+        &path,
+        "rules:\n  - name: broken\n    pattern: '(unclosed'\n",
+    )
+    .expect("write");
+    assert!(matches!(
+        Ruleset::load(&path),
+        Err(SanitizationError::InvalidPattern(pattern)) if pattern == "(unclosed"
+    ));
+    assert!(matches!(
+        Ruleset::load(&dir.path().join("rules.ini")),
+        Err(SanitizationError::Ruleset(_))
+    ));
+}
+
+#[test]
+fn reloading_sanitizer_picks_up_changes_and_keeps_last_good_ruleset() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("rules.toml");
+    fs::write(&path, TOML_RULESET).expect("write ruleset");
+    let reloading = ReloadingSanitizer::open(&path).expect("ruleset should load");
+    assert!(!reloading.reload_if_changed().expect("no change"));
+
+    let touch = |offset: u64| {
+        File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now() + Duration::from_secs(offset)))
+            .expect("set mtime");
+    };
+    fs::write(
+        &path,
+        "[[rules]]\nname = \"legacy\"\npattern = \"LEGACY\"\n",
+    )
+    .expect("write");
+    touch(10);
+    assert!(reloading
+        .reload_if_changed()
+        .expect("reload should succeed"));
+    let sanitized = reloading
+        .current()
+        .apply(&chunk("INT-1234 LEGACY"))
+        .expect("sanitization should succeed");
+    assert_eq!(sanitized.scrubbed_payload, "INT-1234 [REDACTED]");
+
+    fs::write(&path, "[[rules]]\nname = \"bad\"\npattern = \"(\"\n").expect("write");
+    touch(20);
+    assert!(reloading.reload_if_changed().is_err());
+    let sanitized = reloading
+        .current()
+        .apply(&chunk("LEGACY"))
+        .expect("previous ruleset stays active");
+    assert_eq!(sanitized.scrubbed_payload, "[REDACTED]");
+}
@@ -1,5 +1,5 @@
 use ingestion_planning::{ChunkPlan, PlannedChunk, RetryPolicy};
-use ingestion_sanitization::{SanitizationConfig, Sanitizer};
+use ingestion_sanitization::{SanitizationConfig, SanitizationError, Sanitizer};
 
 fn build_plan() -> ChunkPlan {
     ChunkPlan {
@@ -9,6 +9,7 @@ fn build_plan() -> ChunkPlan {
         source_span: "src/secret.rs:1-40".into(),
         hash: "deadbeef".into(),
         retry_policy: RetryPolicy::default(),
+        tenant_id: None,
     }
 }
 
@@ -19,7 +20,8 @@ fn sanitizer_redacts_secret_tokens() {
         plan.clone(),
         "let TOKEN = \"SECRET-123\";\nlet api_key = \"API_KEY=XYZ\";",
     );
-    let sanitizer = Sanitizer::new(SanitizationConfig::default());
+    let sanitizer =
+        Sanitizer::new(SanitizationConfig::default()).expect("sanitizer config should compile");
     let sanitized = sanitizer
         .apply(&chunk)
         .expect("sanitization should succeed");
@@ -47,9 +49,39 @@ fn sanitizer_redacts_secret_tokens() {
 fn sanitizer_flags_script_with_shebang() {
     let plan = build_plan();
     let chunk = PlannedChunk::new(plan, "#!/bin/bash\necho SECRET");
-    let sanitizer = Sanitizer::new(SanitizationConfig::default());
+    let sanitizer =
+        Sanitizer::new(SanitizationConfig::default()).expect("sanitizer config should compile");
     let sanitized = sanitizer
         .apply(&chunk)
         .expect("sanitization should succeed");
     assert_eq!(sanitized.validation_status, "script-reviewed");
 }
+
+#[test]
+fn sanitizer_rejects_invalid_patterns_at_construction() {
+    let config = SanitizationConfig {
+        redact_patterns: vec!["SECRET[".into()],
+        ..SanitizationConfig::default()
+    };
+    let err = Sanitizer::new(config).expect_err("invalid pattern should fail eagerly");
+    assert!(matches!(err, SanitizationError::InvalidPattern(pattern) if pattern == "SECRET["));
+}
+
+#[test]
+fn sanitizer_reuses_compiled_patterns_across_chunks() {
+    let sanitizer =
+        Sanitizer::new(SanitizationConfig::default()).expect("sanitizer config should compile");
+    let plan = build_plan();
+    let payloads = ["no secrets here", "SECRET-1 and SECRET-2", "token = 'abc'"];
+    let logs: Vec<usize> = payloads
+        .iter()
+        .map(|payload| {
+            sanitizer
+                .apply(&PlannedChunk::new(plan.clone(), *payload))
+                .expect("sanitization should succeed")
+                .redaction_log
+                .len()
+        })
+        .collect();
+    assert_eq!(logs, vec![0, 1, 1]);
+}
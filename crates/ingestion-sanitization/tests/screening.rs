@@ -0,0 +1,117 @@
+use ingestion_planning::{ChunkPlan, PlannedChunk, RetryPolicy};
+use ingestion_sanitization::{SanitizationConfig, Sanitizer, ScreenVerdict, ScreeningConfig};
+
+fn chunk(index: usize, path: &str, payload: &str) -> PlannedChunk {
+    PlannedChunk::new(
+        ChunkPlan {
+            plan_id: format!("repo-screen::{path}::{index}"),
+            repo_id: "repo-screen".into(),
+            chunker_config: "bytes=4096;max=64".into(),
+            source_span: format!("{path}:0-4096"),
+            hash: format!("hash-{index}"),
+            retry_policy: RetryPolicy::default(),
+            tenant_id: None,
+        },
+        payload,
+    )
+}
+
+fn minified() -> String {
+    let mut line = String::from("!function(e){");
+    while line.len() < 4_000 {
+        line.push_str("var a=e.b(c),d=a&&a.f;if(d){g(d)}");
+    }
+    line.push_str("}();\n");
+    line
+}
+
+#[test]
+fn screening_classifies_binary_base64_and_minified_content() {
+    let config = ScreeningConfig::default();
+    assert_eq!(
+        config.screen("src/main.rs", "fn main() {}\n"),
+        ScreenVerdict::Embeddable
+    );
+    assert_eq!(
+        config.screen("assets/blob", "\u{0}\u{1}\u{2}ELF\u{fffd}\u{fffd}"),
+        ScreenVerdict::Binary
+    );
+    assert_eq!(
+        config.screen("assets/blob", &format!("data: {}\n", "QUJD".repeat(400))),
+        ScreenVerdict::Base64
+    );
+    assert_eq!(
+        config.screen("assets/app.min.js", &minified()),
+        ScreenVerdict::Minified
+    );
+
+    let disabled = ScreeningConfig {
+        enabled: false,
+        ..ScreeningConfig::default()
+    };
+    assert_eq!(
+        disabled.screen("assets/app.min.js", &minified()),
+        ScreenVerdict::Embeddable
+    );
+}
+
+#[test]
+fn skipped_chunks_carry_status_and_no_payload() {
+    let sanitizer =
+        Sanitizer::new(SanitizationConfig::default()).expect("sanitizer config should compile");
+    let blob = format!("SECRET-1 {}", "QUJD".repeat(400));
+    let sanitized = sanitizer
+        .apply(&chunk(0, "assets/blob", &blob))
+        .expect("sanitization should succeed");
+    assert!(sanitized.is_skipped());
+    assert_eq!(sanitized.validation_status, "skipped-base64");
+    assert!(sanitized.scrubbed_payload.is_empty());
+    assert!(sanitized.redaction_log.is_empty());
+
+    let batch = sanitizer
+        .apply_batch(&[
+            chunk(1, "assets/blob", "\u{0}\u{0}\u{0}\u{0}"),
+            chunk(2, "assets/app.min.js", &minified()),
+            chunk(3, "notes.txt", "plain text"),
+        ])
+        .expect("batch should succeed");
+    let statuses: Vec<_> = batch
+        .chunks
+        .iter()
+        .map(|chunk| chunk.validation_status.as_str())
+        .collect();
+    assert_eq!(
+        statuses,
+        vec!["skipped-binary", "skipped-minified", "clean"]
+    );
+    assert_eq!(batch.report.skipped, 2);
+    assert_eq!(batch.report.flagged, 0);
+}
+
+#[test]
+fn long_prose_lines_are_not_treated_as_minified() {
+    let mut paragraph = String::from("# Overview\n\n");
+    while paragraph.len() < 4_000 {
+        paragraph.push_str(
+            "The scheduler hands each batch to the next idle worker and retries on failure. ",
+        );
+    }
+    paragraph.push('\n');
+    let config = ScreeningConfig::default();
+    assert_eq!(
+        config.screen("docs/overview.md", &paragraph),
+        ScreenVerdict::Embeddable
+    );
+    assert_eq!(
+        config.screen("assets/data.json", &minified()),
+        ScreenVerdict::Minified
+    );
+
+    let sanitizer =
+        Sanitizer::new(SanitizationConfig::default()).expect("sanitizer config should compile");
+    let sanitized = sanitizer
+        .apply(&chunk(4, "docs/overview.md", &paragraph))
+        .expect("sanitization should succeed");
+    assert_eq!(sanitized.validation_status, "clean");
+    assert_eq!(sanitized.scrubbed_payload, paragraph);
+}
@@ -0,0 +1,32 @@
+#![cfg(feature = "native")]
+
+use ingestion_sanitization::telemetry::telemetry_redactor;
+use ingestion_sanitization::{AllowlistEntry, PiiConfig, PiiKind, SanitizationConfig};
+
+#[test]
+fn telemetry_messages_lose_secrets_and_configured_pii() {
+    let config = SanitizationConfig {
+        pii: PiiConfig {
+            kinds: vec![PiiKind::Email],
+        },
+        allowlist: vec![
+            AllowlistEntry::new(r"SECRET_PLACEHOLDER", "documented sample value"),
+            AllowlistEntry::new(r"SECRET_REPO_ONLY", "only inside the docs repo").for_repo("docs"),
+        ],
+        ..SanitizationConfig::default()
+    };
+    let redactor = telemetry_redactor(&config).unwrap();
+
+    let message = "invalid payload: token = 'abc123' from ops@example.com";
+    let scrubbed = redactor.redact(message);
+    assert!(!scrubbed.contains("abc123"), "{scrubbed}");
+    assert!(!scrubbed.contains("ops@example.com"), "{scrubbed}");
+    assert!(scrubbed.starts_with("invalid payload: [REDACTED]"));
+
+    // Only allowlist entries that hold everywhere apply outside a chunk.
+    assert_eq!(
+        redactor.redact("SECRET_PLACEHOLDER SECRET_REPO_ONLY"),
+        "SECRET_PLACEHOLDER [REDACTED]"
+    );
+    assert_eq!(redactor.redact("search"), "search");
+}
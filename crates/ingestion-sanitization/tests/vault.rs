@@ -0,0 +1,228 @@
+#![cfg(feature = "vault")]
+use std::sync::Arc;
+
+use ingestion_planning::{ChunkPlan, PlannedChunk, RetryPolicy};
+use ingestion_sanitization::commands::{self, REVEAL_CAPABILITY, REVEAL_COMMAND};
+use ingestion_sanitization::{
+    SanitizationConfig, SanitizationError, Sanitizer, TokenVault, TOKEN_PREFIX, VAULT_VERSION,
+};
+use runtime_router::{CommandRouter, HandlerRouter, RouterCommand, SessionContext};
+use serde_json::json;
+use storage_vector::encryption::aes_gcm::AesGcmEncrypter;
+use storage_vector::kms::{InMemoryKeyManager, KeyScope};
+
+fn chunk(index: usize, payload: &str) -> PlannedChunk {
+    PlannedChunk::new(
+        ChunkPlan {
+            plan_id: format!("repo-vault::src/config.rs::{index}"),
+            repo_id: "repo-vault".into(),
+            chunker_config: "bytes=1024;max=64".into(),
+            source_span: "src/config.rs:0-64".into(),
+            hash: format!("hash-{index}"),
+            retry_policy: RetryPolicy::default(),
+            tenant_id: None,
+        },
+        payload,
+    )
+}
+
+fn vault(keys: Arc<InMemoryKeyManager>) -> TokenVault {
+    TokenVault::in_memory(
+        Arc::new(AesGcmEncrypter::new()),
+        keys,
+        KeyScope {
+            repo_id: "repo-vault".into(),
+        },
+    )
+}
+
+#[test]
+fn redacted_secrets_become_stable_tokens() {
+    let vault = Arc::new(vault(Arc::new(InMemoryKeyManager::new_with_secret(
+        "k1", [7u8; 32],
+    ))));
+    let sanitizer = Sanitizer::new(SanitizationConfig::default())
+        .expect("sanitizer config should compile")
+        .with_vault(Arc::clone(&vault));
+
+    let first = sanitizer
+        .apply(&chunk(0, "let key = SECRET-ALPHA;"))
+        .expect("sanitization should succeed");
+    let second = sanitizer
+        .apply(&chunk(1, "retry with SECRET-ALPHA and SECRET-BETA"))
+        .expect("sanitization should succeed");
+
+    assert!(!first.scrubbed_payload.contains("SECRET-ALPHA"));
+    let token = first
+        .scrubbed_payload
+        .trim_start_matches("let key = ")
+        .trim_end_matches(';')
+        .to_string();
+    assert!(token.starts_with(TOKEN_PREFIX));
+    assert_eq!(token.len(), TOKEN_PREFIX.len() + 32 + 1);
+    assert!(second.scrubbed_payload.contains(&token));
+    assert_eq!(vault.len(), 2);
+
+    let (category, secret) = vault.reveal(&token).expect("token should resolve");
+    assert_eq!(category, "secret");
+    assert_eq!(secret, "SECRET-ALPHA");
+    assert!(matches!(
+        vault.reveal("[TOKEN:00000000000000000000000000000000]"),
+        Err(SanitizationError::UnknownToken(_))
+    ));
+}
+
+#[test]
+fn persisted_vault_reveals_after_key_rotation() {
+    let dir = tempfile::tempdir().expect("state dir");
+    let path = dir.path().join("vault.json");
+    let keys = Arc::new(InMemoryKeyManager::new_with_secret("k1", [1u8; 32]));
+    let token = {
+        let vault = TokenVault::open(
+            &path,
+            Arc::new(AesGcmEncrypter::new()),
+            Arc::clone(&keys) as _,
+            KeyScope {
+                repo_id: "repo-vault".into(),
+            },
+        )
+        .expect("open vault");
+        vault
+            .tokenize(None, "pii:email", "dev@example.com")
+            .expect("tokenize")
+    };
+    let raw = std::fs::read_to_string(&path).expect("vault file");
+    assert!(!raw.contains("dev@example.com"));
+
+    keys.set_current("k2", [2u8; 32]);
+    let reopened = TokenVault::open(
+        &path,
+        Arc::new(AesGcmEncrypter::new()),
+        keys,
+        KeyScope {
+            repo_id: "repo-vault".into(),
+        },
+    )
+    .expect("reopen vault");
+    let (category, secret) = reopened.reveal(&token).expect("old key still resolves");
+    assert_eq!(category, "pii:email");
+    assert_eq!(secret, "dev@example.com");
+}
+
+fn open_vault(path: &std::path::Path, keys: Arc<InMemoryKeyManager>) -> TokenVault {
+    TokenVault::open(
+        path,
+        Arc::new(AesGcmEncrypter::new()),
+        keys,
+        KeyScope {
+            repo_id: "repo-vault".into(),
+        },
+    )
+    .expect("open vault")
+}
+
+#[test]
+fn vault_log_appends_one_line_per_secret() {
+    let dir = tempfile::tempdir().expect("state dir");
+    let path = dir.path().join("vault.json");
+    let keys = Arc::new(InMemoryKeyManager::new_with_secret("k1", [3u8; 32]));
+    let vault = open_vault(&path, Arc::clone(&keys));
+    let first = vault
+        .tokenize(None, "secret", "SECRET-ONE")
+        .expect("tokenize");
+    vault
+        .tokenize(None, "secret", "SECRET-ONE")
+        .expect("tokenize again");
+    let second = vault
+        .tokenize(None, "secret", "SECRET-TWO")
+        .expect("tokenize");
+    let log = std::fs::read_to_string(&path).expect("vault log");
+    assert_eq!(log.lines().count(), 3);
+    assert!(log.starts_with(&format!("{{\"version\":{VAULT_VERSION}}}\n")));
+    let mac_only = blake3::keyed_hash(&[3u8; 32], b"SECRET-ONE").to_hex();
+    assert!(!first.contains(&mac_only[..32]));
+
+    // A record torn by a crash mid-append is dropped on reopen.
+    std::fs::write(&path, format!("{log}{{\"token\":\"[TOKEN:")).expect("tear log");
+    let reopened = open_vault(&path, Arc::clone(&keys));
+    assert_eq!(reopened.len(), 2);
+    assert_eq!(
+        reopened.reveal(&second).expect("second token").1,
+        "SECRET-TWO"
+    );
+    reopened
+        .tokenize(None, "secret", "SECRET-THREE")
+        .expect("tokenize");
+    assert_eq!(open_vault(&path, keys).len(), 3);
+}
+
+#[test]
+fn version_one_vaults_are_converted_to_the_log_layout() {
+    let dir = tempfile::tempdir().expect("state dir");
+    let path = dir.path().join("vault.json");
+    let legacy = json!({
+        "version": 1,
+        "entries": {
+            "[TOKEN:0123456789abcdef]": { "category": "secret", "envelope": "AAAA" }
+        }
+    });
+    std::fs::write(
+        &path,
+        serde_json::to_vec_pretty(&legacy).expect("legacy json"),
+    )
+    .expect("write legacy vault");
+    let vault = open_vault(&path, Arc::new(InMemoryKeyManager::new_random("k1")));
+    assert_eq!(vault.len(), 1);
+    let log = std::fs::read_to_string(&path).expect("vault log");
+    assert_eq!(log.lines().count(), 2);
+    assert!(log.contains("[TOKEN:0123456789abcdef]"));
+
+    std::fs::write(&path, r#"{"version":3}"#.to_owned() + "\n").expect("write future vault");
+    assert!(TokenVault::open(
+        &path,
+        Arc::new(AesGcmEncrypter::new()),
+        Arc::new(InMemoryKeyManager::new_random("k1")),
+        KeyScope {
+            repo_id: "repo-vault".into(),
+        },
+    )
+    .is_err());
+}
+
+#[tokio::test]
+async fn reveal_command_requires_capability() {
+    let vault = Arc::new(vault(Arc::new(InMemoryKeyManager::new_random("k1"))));
+    let token = vault
+        .tokenize(None, "secret", "SECRET-GAMMA")
+        .expect("tokenize");
+    let mut router = HandlerRouter::new();
+    commands::register_vault_commands(&mut router, Arc::clone(&vault));
+
+    let err = router
+        .dispatch(
+            SessionContext::new("reader", vec!["search".into()]),
+            RouterCommand::new(REVEAL_COMMAND, json!({ "token": token })),
+        )
+        .await
+        .expect_err("reveal capability required");
+    assert_eq!(err.status_code(), 401);
+
+    let responder = SessionContext::new("ir-oncall", vec![REVEAL_CAPABILITY.into()]);
+    let revealed = router
+        .dispatch(
+            responder.clone(),
+            RouterCommand::new(REVEAL_COMMAND, json!({ "token": token })),
+        )
+        .await
+        .expect("reveal token");
+    assert_eq!(revealed.payload["secret"], json!("SECRET-GAMMA"));
+
+    let err = router
+        .dispatch(
+            responder,
+            RouterCommand::new(REVEAL_COMMAND, json!({ "token": "[TOKEN:missing]" })),
+        )
+        .await
+        .expect_err("unknown token");
+    assert_eq!(err.status_code(), 404);
+}
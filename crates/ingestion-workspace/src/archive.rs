@@ -0,0 +1,111 @@
+//! Per-entry limits applied while archives are extracted, and the report
+//! naming the entries they rejected.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Nested archive levels extracted by default, matching the default quota profile.
+pub const DEFAULT_NESTING_MAX: u32 = 2;
+/// Longest entry path accepted by default, in bytes (`PATH_MAX` on Linux).
+pub const DEFAULT_PATH_BYTES_MAX: usize = 4096;
+/// Longest path component accepted by default, in bytes (`NAME_MAX`).
+pub const DEFAULT_COMPONENT_BYTES_MAX: usize = 255;
+
+/// Caps checked against every entry before it is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtractionLimits {
+    /// Deepest archive-within-archive level extracted; `0` rejects every
+    /// nested archive without writing or opening it.
+    pub nesting_max: u32,
+    /// Longest entry path relative to the extraction root, in bytes,
+    /// including the paths of the archives it is nested in.
+    pub path_bytes_max: usize,
+    /// Longest single path component, in bytes.
+    pub component_bytes_max: usize,
+}
+
+impl Default for ExtractionLimits {
+    fn default() -> Self {
+        Self {
+            nesting_max: DEFAULT_NESTING_MAX,
+            path_bytes_max: DEFAULT_PATH_BYTES_MAX,
+            component_bytes_max: DEFAULT_COMPONENT_BYTES_MAX,
+        }
+    }
+}
+
+/// Why an archive entry was not extracted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum EntryRejection {
+    /// The path starts at a filesystem root or drive.
+    AbsolutePath,
+    /// The path has a `..` component.
+    ParentTraversal,
+    /// The path is longer than [`ExtractionLimits::path_bytes_max`].
+    PathTooLong { bytes: usize, limit: usize },
+    /// A component is longer than [`ExtractionLimits::component_bytes_max`].
+    ComponentTooLong { bytes: usize, limit: usize },
+    /// A nested archive sits deeper than [`ExtractionLimits::nesting_max`].
+    NestingExceeded { depth: u32, limit: u32 },
+    /// The file is larger than
+    /// [`EnumeratorConfig::max_file_bytes`](crate::EnumeratorConfig::max_file_bytes).
+    FileTooLarge { limit: u64 },
+    /// Writing the file would take the extraction past
+    /// [`EnumeratorConfig::max_total_bytes`](crate::EnumeratorConfig::max_total_bytes).
+    TotalBytesExceeded { limit: u64 },
+    /// Symbolic and hard links are never created.
+    Link { target: String },
+    /// Devices, FIFOs and other special files.
+    UnsupportedType,
+}
+
+impl fmt::Display for EntryRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AbsolutePath => f.write_str("absolute path"),
+            Self::ParentTraversal => f.write_str("parent directory traversal"),
+            Self::PathTooLong { bytes, limit } => {
+                write!(f, "path is {bytes} bytes, limit {limit}")
+            }
+            Self::ComponentTooLong { bytes, limit } => {
+                write!(f, "path component is {bytes} bytes, limit {limit}")
+            }
+            Self::NestingExceeded { depth, limit } => {
+                write!(f, "nested archive at depth {depth}, limit {limit}")
+            }
+            Self::FileTooLarge { limit } => write!(f, "file exceeds {limit} bytes"),
+            Self::TotalBytesExceeded { limit } => {
+                write!(f, "extraction would exceed {limit} bytes")
+            }
+            Self::Link { target } => write!(f, "link to {target}"),
+            Self::UnsupportedType => f.write_str("unsupported entry type"),
+        }
+    }
+}
+
+/// An entry skipped during extraction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RejectedEntry {
+    /// Path of the nested archive holding the entry, relative to the
+    /// extraction root; `None` for entries of the outermost archive.
+    pub archive: Option<String>,
+    /// Entry name exactly as stored in the archive.
+    pub path: String,
+    #[serde(flatten)]
+    pub rejection: EntryRejection,
+}
+
+/// Outcome of extracting one archive.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveExtraction {
+    /// Files written, relative to the extraction root and in archive order.
+    pub extracted: Vec<String>,
+    /// Bytes written across all files.
+    pub bytes: u64,
+    /// Deepest nested archive opened.
+    pub nesting_depth: u32,
+    /// Entries skipped, in archive order.
+    pub rejected: Vec<RejectedEntry>,
+}
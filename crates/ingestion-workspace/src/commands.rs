@@ -0,0 +1,223 @@
+//! Router commands exposing [`WorkspaceRegistry`] membership changes.
+//!
+//! A workspace registered from a tenant session belongs to that tenant;
+//! other tenants neither see it listed nor can deregister it.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use runtime_router::{
+    CommandHandler, HandlerRouter, PageRequest, RouterError, RouterResponse, SessionContext,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    GeneratedCodePolicy, IgnoreRule, RepoType, WorkspaceError, WorkspaceRecord, WorkspaceRegistry,
+};
+
+/// Command registering a workspace
+/// (`{ repo_id, root_path, repo_type?, ignore_rules?, generated_code? }`).
+pub const REGISTER_COMMAND: &str = "workspace.register";
+/// Command deregistering a workspace (`{ repo_id }`).
+pub const DEREGISTER_COMMAND: &str = "workspace.deregister";
+/// Command listing registered workspaces, a page at a time
+/// (`{ cursor?, page_size? }`).
+pub const LIST_COMMAND: &str = "workspace.list";
+
+/// Workspaces listed per page when the request does not say.
+pub const DEFAULT_LIST_PAGE_SIZE: usize = 100;
+/// Most workspaces one `workspace.list` page holds.
+pub const MAX_LIST_PAGE_SIZE: usize = 1_000;
+
+/// Capability required for registry mutations.
+pub const ADMIN_CAPABILITY: &str = "admin";
+
+/// Register the workspace registry commands on `router`.
+pub fn register_commands(router: &mut HandlerRouter, registry: Arc<WorkspaceRegistry>) {
+    router
+        .register_with_capabilities(
+            REGISTER_COMMAND,
+            vec![ADMIN_CAPABILITY.into()],
+            Arc::new(RegisterWorkspaceHandler {
+                registry: Arc::clone(&registry),
+            }),
+        )
+        .register_with_capabilities(
+            DEREGISTER_COMMAND,
+            vec![ADMIN_CAPABILITY.into()],
+            Arc::new(DeregisterWorkspaceHandler {
+                registry: Arc::clone(&registry),
+            }),
+        )
+        .register(LIST_COMMAND, Arc::new(ListWorkspacesHandler { registry }));
+}
+
+#[derive(Debug, Deserialize)]
+struct RegisterWorkspaceRequest {
+    repo_id: String,
+    root_path: PathBuf,
+    #[serde(default = "default_repo_type")]
+    repo_type: RepoType,
+    #[serde(default)]
+    ignore_rules: Vec<IgnoreRule>,
+    #[serde(default)]
+    generated_code: Option<GeneratedCodePolicy>,
+}
+
+const fn default_repo_type() -> RepoType {
+    RepoType::Git
+}
+
+#[derive(Debug, Deserialize)]
+struct DeregisterWorkspaceRequest {
+    repo_id: String,
+}
+
+struct RegisterWorkspaceHandler {
+    registry: Arc<WorkspaceRegistry>,
+}
+
+#[async_trait]
+impl CommandHandler for RegisterWorkspaceHandler {
+    async fn handle(
+        &self,
+        ctx: &SessionContext,
+        payload: Value,
+    ) -> Result<RouterResponse, RouterError> {
+        let request: RegisterWorkspaceRequest = parse_payload(payload)?;
+        if !request.root_path.is_absolute() {
+            return Err(RouterError::InvalidRequest {
+                detail: "root_path must be absolute".into(),
+            });
+        }
+        let repo_id = request.repo_id.clone();
+        self.registry
+            .register_workspace(WorkspaceRecord {
+                repo_id: request.repo_id,
+                root_path: request.root_path,
+                repo_type: request.repo_type,
+                manifest_cursor: None,
+                ignore_rules: request.ignore_rules,
+                archives: Vec::new(),
+                latency_windows: Vec::new(),
+                files: Vec::new(),
+                tenant_id: ctx.tenant_id.clone(),
+                generated_code: request.generated_code,
+            })
+            .map_err(router_error)?;
+        Ok(RouterResponse::ok(json!({ "registered": repo_id })))
+    }
+
+    fn compensable(&self) -> bool {
+        true
+    }
+
+    /// Deregister the workspace again.
+    async fn compensate(
+        &self,
+        _ctx: &SessionContext,
+        payload: Value,
+        _response: &RouterResponse,
+    ) -> Result<(), RouterError> {
+        let request: RegisterWorkspaceRequest = parse_payload(payload)?;
+        self.registry
+            .deregister_workspace(&request.repo_id)
+            .map_err(router_error)?;
+        Ok(())
+    }
+}
+
+struct DeregisterWorkspaceHandler {
+    registry: Arc<WorkspaceRegistry>,
+}
+
+#[async_trait]
+impl CommandHandler for DeregisterWorkspaceHandler {
+    async fn handle(
+        &self,
+        ctx: &SessionContext,
+        payload: Value,
+    ) -> Result<RouterResponse, RouterError> {
+        let request: DeregisterWorkspaceRequest = parse_payload(payload)?;
+        let visible = self.registry.snapshot().workspaces.iter().any(|record| {
+            record.repo_id == request.repo_id && ctx.can_access_tenant(record.tenant_id.as_deref())
+        });
+        if !visible {
+            return Err(router_error(WorkspaceError::UnknownWorkspace(
+                request.repo_id,
+            )));
+        }
+        let removed = self
+            .registry
+            .deregister_workspace(&request.repo_id)
+            .map_err(router_error)?;
+        Ok(RouterResponse::ok(
+            json!({ "deregistered": removed.repo_id }),
+        ))
+    }
+}
+
+struct ListWorkspacesHandler {
+    registry: Arc<WorkspaceRegistry>,
+}
+
+#[async_trait]
+impl CommandHandler for ListWorkspacesHandler {
+    async fn handle(
+        &self,
+        ctx: &SessionContext,
+        payload: Value,
+    ) -> Result<RouterResponse, RouterError> {
+        let request: PageRequest = if payload.is_null() {
+            PageRequest::default()
+        } else {
+            parse_payload(payload)?
+        };
+        let visible = self
+            .registry
+            .snapshot()
+            .workspaces
+            .into_iter()
+            .filter(|record| ctx.can_access_tenant(record.tenant_id.as_deref()))
+            .collect();
+        let (records, page) =
+            request.paginate(visible, DEFAULT_LIST_PAGE_SIZE, MAX_LIST_PAGE_SIZE)?;
+        let workspaces: Vec<Value> = records
+            .iter()
+            .map(|record| {
+                json!({
+                    "repo_id": record.repo_id,
+                    "root_path": record.root_path,
+                    "repo_type": record.repo_type,
+                    "tenant_id": record.tenant_id,
+                })
+            })
+            .collect();
+        Ok(RouterResponse::paged(
+            json!({ "workspaces": workspaces }),
+            page,
+        ))
+    }
+}
+
+fn parse_payload<T: serde::de::DeserializeOwned>(payload: Value) -> Result<T, RouterError> {
+    serde_json::from_value(payload).map_err(|err| RouterError::InvalidRequest {
+        detail: err.to_string(),
+    })
+}
+
+fn router_error(err: WorkspaceError) -> RouterError {
+    match err {
+        WorkspaceError::DuplicateWorkspace(_) => RouterError::InvalidRequest {
+            detail: err.to_string(),
+        },
+        WorkspaceError::UnknownWorkspace(_) => RouterError::NotFound {
+            detail: err.to_string(),
+        },
+        other => RouterError::Internal {
+            detail: other.to_string(),
+        },
+    }
+}
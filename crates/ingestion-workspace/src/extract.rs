@@ -0,0 +1,264 @@
+//! Tar archive extraction that validates every entry before writing it.
+
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use tar::EntryType;
+
+use crate::archive::{ArchiveExtraction, EntryRejection, ExtractionLimits, RejectedEntry};
+use crate::walk::io_error;
+use crate::{TelemetryEvent, WorkspaceEnumerator, WorkspaceError};
+
+/// Name suffixes of nested archives that are opened rather than written.
+const TAR_SUFFIXES: &[&str] = &[".tar"];
+const ZSTD_TAR_SUFFIXES: &[&str] = &[".tar.zst", ".tzst"];
+
+impl WorkspaceEnumerator {
+    /// Extract the tar archive at `archive` (zstd-compressed when named
+    /// `.tar.zst` or `.tzst`) into `dest`, an empty scratch directory.
+    ///
+    /// Each entry is checked against
+    /// [`EnumeratorConfig::archive_limits`](crate::EnumeratorConfig::archive_limits)
+    /// before anything is written: absolute paths, `..` components, links,
+    /// special files, over-long paths and archives nested too deep are
+    /// skipped, as are files whose contents overrun
+    /// [`EnumeratorConfig::max_file_bytes`](crate::EnumeratorConfig::max_file_bytes)
+    /// or the remaining
+    /// [`EnumeratorConfig::max_total_bytes`](crate::EnumeratorConfig::max_total_bytes);
+    /// all are reported in [`ArchiveExtraction::rejected`] and recorded as
+    /// `workspace.archive.rejected` telemetry. Nested tar archives within
+    /// the limit are extracted into a directory named after the entry.
+    pub fn extract_archive(
+        &self,
+        archive: &Path,
+        dest: &Path,
+    ) -> Result<ArchiveExtraction, WorkspaceError> {
+        let file = File::open(archive).map_err(|err| io_error(archive, &err))?;
+        fs::create_dir_all(dest).map_err(|err| io_error(dest, &err))?;
+        let name = archive.to_string_lossy();
+        let mut extraction = Extraction {
+            limits: self.config.archive_limits,
+            archive: &name,
+            dest,
+            enumerator: self,
+            report: ArchiveExtraction::default(),
+        };
+        let mut file = io::BufReader::new(file);
+        if has_suffix(&name, ZSTD_TAR_SUFFIXES) {
+            let mut decoder = zstd::Decoder::new(file).map_err(|err| io_error(archive, &err))?;
+            extraction.entries(&mut decoder, None, 0)?;
+        } else {
+            extraction.entries(&mut file, None, 0)?;
+        }
+        Ok(extraction.report)
+    }
+}
+
+struct Extraction<'a> {
+    limits: ExtractionLimits,
+    /// Name of the outermost archive, for error messages.
+    archive: &'a str,
+    dest: &'a Path,
+    enumerator: &'a WorkspaceEnumerator,
+    report: ArchiveExtraction,
+}
+
+impl Extraction<'_> {
+    /// Extract the entries of one archive level; `within` is the relative
+    /// path of the nested archive being read, `None` at the top.
+    fn entries(
+        &mut self,
+        reader: &mut dyn Read,
+        within: Option<&str>,
+        depth: u32,
+    ) -> Result<(), WorkspaceError> {
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries().map_err(|err| self.error(within, &err))? {
+            let mut entry = entry.map_err(|err| self.error(within, &err))?;
+            let raw = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+            let entry_type = entry.header().entry_type();
+            if entry_type.is_pax_global_extensions() {
+                continue;
+            }
+            let relative = match validate_path(&raw, within, &self.limits) {
+                Ok(Some(relative)) => relative,
+                // Entries naming the archive root itself, such as `./`.
+                Ok(None) => continue,
+                Err(rejection) => {
+                    self.reject(within, raw, rejection);
+                    continue;
+                }
+            };
+            match entry_type {
+                EntryType::Directory => {
+                    let path = self.target(&relative);
+                    fs::create_dir_all(&path).map_err(|err| io_error(&path, &err))?;
+                }
+                EntryType::Regular | EntryType::Continuous => {
+                    if let Some(zstd) = nested_kind(&relative) {
+                        let nested_depth = depth + 1;
+                        if nested_depth > self.limits.nesting_max {
+                            let rejection = EntryRejection::NestingExceeded {
+                                depth: nested_depth,
+                                limit: self.limits.nesting_max,
+                            };
+                            self.reject(within, raw, rejection);
+                            continue;
+                        }
+                        self.report.nesting_depth = self.report.nesting_depth.max(nested_depth);
+                        if zstd {
+                            let mut decoder = zstd::Decoder::new(&mut entry)
+                                .map_err(|err| self.error(Some(&relative), &err))?;
+                            self.entries(&mut decoder, Some(&relative), nested_depth)?;
+                        } else {
+                            self.entries(&mut entry, Some(&relative), nested_depth)?;
+                        }
+                        continue;
+                    }
+                    let path = self.target(&relative);
+                    if let Some(parent) = path.parent() {
+                        fs::create_dir_all(parent).map_err(|err| io_error(parent, &err))?;
+                    }
+                    let (allowed, rejection) = self.quota();
+                    let mut file = File::create(&path).map_err(|err| io_error(&path, &err))?;
+                    // Read one byte past the quota so an overrun is detected
+                    // without trusting the size recorded in the header.
+                    let written =
+                        io::copy(&mut (&mut entry).take(allowed.saturating_add(1)), &mut file)
+                            .map_err(|err| self.error(within, &err))?;
+                    if written > allowed {
+                        drop(file);
+                        fs::remove_file(&path).map_err(|err| io_error(&path, &err))?;
+                        self.reject(within, raw, rejection);
+                        continue;
+                    }
+                    self.report.bytes = self.report.bytes.saturating_add(written);
+                    self.report.extracted.push(relative);
+                }
+                EntryType::Symlink | EntryType::Link => {
+                    let target = entry
+                        .link_name_bytes()
+                        .map(|target| String::from_utf8_lossy(&target).into_owned())
+                        .unwrap_or_default();
+                    self.reject(within, raw, EntryRejection::Link { target });
+                }
+                _ => self.reject(within, raw, EntryRejection::UnsupportedType),
+            }
+        }
+        Ok(())
+    }
+
+    /// Bytes the next file may hold under the workspace byte quotas, and the
+    /// rejection reported when it holds more.
+    fn quota(&self) -> (u64, EntryRejection) {
+        let config = &self.enumerator.config;
+        let file = config
+            .max_file_bytes
+            .map(|limit| (limit, EntryRejection::FileTooLarge { limit }));
+        let total = config.max_total_bytes.map(|limit| {
+            (
+                limit.saturating_sub(self.report.bytes),
+                EntryRejection::TotalBytesExceeded { limit },
+            )
+        });
+        match (file, total) {
+            (Some(file), Some(total)) if total.0 < file.0 => total,
+            (Some(file), _) => file,
+            (None, Some(total)) => total,
+            (None, None) => (u64::MAX, EntryRejection::FileTooLarge { limit: u64::MAX }),
+        }
+    }
+
+    fn error(&self, within: Option<&str>, err: &io::Error) -> WorkspaceError {
+        WorkspaceError::Archive(format!("{}: {err}", within.unwrap_or(self.archive)))
+    }
+
+    fn target(&self, relative: &str) -> PathBuf {
+        let mut path = self.dest.to_path_buf();
+        path.extend(relative.split('/'));
+        path
+    }
+
+    fn reject(&mut self, within: Option<&str>, path: String, rejection: EntryRejection) {
+        tracing::warn!(archive = within, path = %path, %rejection, "archive entry rejected");
+        let location = match within {
+            Some(archive) => format!("{archive}!{path}"),
+            None => path.clone(),
+        };
+        self.enumerator.record(TelemetryEvent {
+            kind: "workspace.archive.rejected".into(),
+            message: format!("{location}: {rejection}"),
+        });
+        self.report.rejected.push(RejectedEntry {
+            archive: within.map(str::to_owned),
+            path,
+            rejection,
+        });
+    }
+}
+
+/// Normalize an entry name into a `/`-separated path relative to the
+/// extraction root, prefixed with the nested archive holding it; `None`
+/// when the entry names the root itself.
+///
+/// Backslashes count as separators so Windows-style names cannot smuggle a
+/// traversal past the checks.
+fn validate_path(
+    raw: &str,
+    within: Option<&str>,
+    limits: &ExtractionLimits,
+) -> Result<Option<String>, EntryRejection> {
+    let bytes = raw.as_bytes();
+    let drive = bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':';
+    if raw.starts_with(['/', '\\']) || drive {
+        return Err(EntryRejection::AbsolutePath);
+    }
+    let mut components = Vec::new();
+    for component in raw.split(['/', '\\']) {
+        match component {
+            "" | "." => {}
+            ".." => return Err(EntryRejection::ParentTraversal),
+            _ if component.len() > limits.component_bytes_max => {
+                return Err(EntryRejection::ComponentTooLong {
+                    bytes: component.len(),
+                    limit: limits.component_bytes_max,
+                });
+            }
+            _ => components.push(component),
+        }
+    }
+    if components.is_empty() {
+        return Ok(None);
+    }
+    let mut relative = within.map(str::to_owned).unwrap_or_default();
+    for component in components {
+        if !relative.is_empty() {
+            relative.push('/');
+        }
+        relative.push_str(component);
+    }
+    if relative.len() > limits.path_bytes_max {
+        return Err(EntryRejection::PathTooLong {
+            bytes: relative.len(),
+            limit: limits.path_bytes_max,
+        });
+    }
+    Ok(Some(relative))
+}
+
+/// `Some(compressed)` when `path` names a nested tar archive.
+fn nested_kind(path: &str) -> Option<bool> {
+    if has_suffix(path, ZSTD_TAR_SUFFIXES) {
+        Some(true)
+    } else if has_suffix(path, TAR_SUFFIXES) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+fn has_suffix(name: &str, suffixes: &[&str]) -> bool {
+    let name = name.to_ascii_lowercase();
+    suffixes.iter().any(|suffix| name.ends_with(suffix))
+}
@@ -0,0 +1,268 @@
+//! Incremental workspace scanning backed by a persisted per-repo file index.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+use crate::limits::LimitTracker;
+use crate::parallel::{parallel_map, resolve_workers};
+use crate::walk::{io_error, walk_files, WalkOptions, WalkedFile};
+use crate::{
+    RegistrySnapshot, WorkspaceDescriptor, WorkspaceEnumerator, WorkspaceError, WorkspaceFile,
+    WorkspaceRecord,
+};
+
+/// Layout version of a saved [`WorkspaceIndex`]; indexes written under any
+/// other version are discarded and rebuilt.
+pub const WORKSPACE_INDEX_VERSION: u32 = 1;
+
+/// Indexed state for a single file from the previous scan.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileIndexEntry {
+    pub path: String,
+    pub mtime_ms: u64,
+    pub size: u64,
+    pub hash: String,
+}
+
+/// Persisted per-repo index used to compute incremental changes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspaceIndex {
+    pub version: u32,
+    pub repo_id: String,
+    pub entries: BTreeMap<String, FileIndexEntry>,
+}
+
+impl WorkspaceIndex {
+    #[must_use]
+    pub fn new(repo_id: impl Into<String>) -> Self {
+        Self {
+            version: WORKSPACE_INDEX_VERSION,
+            repo_id: repo_id.into(),
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Location of the index for `repo_id` inside `state_dir`.
+    #[must_use]
+    pub fn path_for(state_dir: &Path, repo_id: &str) -> PathBuf {
+        state_dir.join(format!("{}.index.json", encode_component(repo_id)))
+    }
+
+    /// Load the index for `repo_id`, returning an empty index when none exists.
+    pub fn load(state_dir: &Path, repo_id: &str) -> Result<Self, WorkspaceError> {
+        let path = Self::path_for(state_dir, repo_id);
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Self::new(repo_id));
+            }
+            Err(err) => return Err(io_error(&path, &err)),
+        };
+        let index: Self = serde_json::from_slice(&bytes).map_err(|err| {
+            WorkspaceError::Enumeration(format!("{}: invalid index: {err}", path.display()))
+        })?;
+        if index.version != WORKSPACE_INDEX_VERSION || index.repo_id != repo_id {
+            // Unknown layouts are discarded so the next scan rebuilds from scratch.
+            return Ok(Self::new(repo_id));
+        }
+        Ok(index)
+    }
+
+    /// Atomically persist the index into `state_dir`.
+    pub fn save(&self, state_dir: &Path) -> Result<(), WorkspaceError> {
+        let path = Self::path_for(state_dir, &self.repo_id);
+        let bytes = serde_json::to_vec_pretty(self)
+            .map_err(|err| WorkspaceError::Enumeration(format!("serializing index: {err}")))?;
+        storage_atomic::write(&path, &bytes).map_err(|err| io_error(&path, &err))
+    }
+}
+
+/// Files that changed since the previous incremental scan.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkspaceChanges {
+    pub added: Vec<String>,
+    pub modified: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl WorkspaceChanges {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.modified.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Result of an incremental scan for one workspace.
+///
+/// `descriptor.files` only contains added and modified files so downstream
+/// planning does not re-read unchanged content. `index` is the state the
+/// next scan diffs against; it is not persisted until [`Self::commit`] runs,
+/// so changes whose processing fails are reported again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncrementalScan {
+    pub descriptor: WorkspaceDescriptor,
+    pub changes: WorkspaceChanges,
+    pub index: WorkspaceIndex,
+}
+
+impl IncrementalScan {
+    /// Persist the scan's index into `state_dir` once its changes have been
+    /// processed.
+    pub fn commit(&self, state_dir: &Path) -> Result<(), WorkspaceError> {
+        self.index.save(state_dir)
+    }
+}
+
+impl WorkspaceEnumerator {
+    /// Scan workspace roots on disk and return only files that changed since
+    /// the index persisted in `state_dir`.
+    ///
+    /// The index is left untouched; call [`IncrementalScan::commit`] after
+    /// the changes have been processed. See [`Self::scan_changes`] for how
+    /// each workspace is diffed.
+    pub fn scan_incremental(
+        &self,
+        snapshot: &RegistrySnapshot,
+        state_dir: &Path,
+    ) -> Result<Vec<IncrementalScan>, WorkspaceError> {
+        snapshot
+            .workspaces
+            .iter()
+            .map(|record| {
+                let previous = WorkspaceIndex::load(state_dir, &record.repo_id)?;
+                self.scan_changes(record, &previous)
+            })
+            .collect()
+    }
+
+    /// Scan the workspace root of `record` on disk and return the files that
+    /// changed since `previous`.
+    ///
+    /// Generated and vendored files are skipped unless the workspace's
+    /// [`GeneratedCodePolicy`](crate::GeneratedCodePolicy) includes them.
+    /// Files whose size and mtime match the index are not re-read; otherwise
+    /// the content hash decides whether the file was actually modified. File
+    /// inspection fans out over the configured worker count while results
+    /// keep the sorted walk order.
+    pub fn scan_changes(
+        &self,
+        record: &WorkspaceRecord,
+        previous: &WorkspaceIndex,
+    ) -> Result<IncrementalScan, WorkspaceError> {
+        let workers = resolve_workers(self.config.workers);
+        let mut descriptor = self.describe(record);
+        let policy = self.generated_code(record);
+        let options = WalkOptions {
+            ignore_stack: &descriptor.ignore_stack,
+            symlinks: self.config.symlinks,
+            generated_code: policy,
+            telemetry: self.telemetry.as_deref(),
+        };
+        let walked = walk_files(&record.root_path, &options)?;
+        let mut limits = LimitTracker::new(&self.config, &record.repo_id);
+        for file in &walked {
+            limits.observe(&file.relative, file.metadata.len());
+        }
+        limits.check()?;
+        let inspected = parallel_map(&walked, workers, |walked| {
+            inspect(walked, previous.entries.get(&walked.relative))
+        });
+
+        let mut index = WorkspaceIndex::new(&record.repo_id);
+        let mut changes = WorkspaceChanges::default();
+        let mut files = Vec::new();
+        for result in inspected {
+            let (entry, change) = result?;
+            if let Some(FileChange::Added(file) | FileChange::Modified(file)) = &change {
+                // Content markers are only seen once the file is read;
+                // leaving it unindexed keeps it out of later diffs too.
+                if policy.excludes(file.origin) {
+                    self.record_excluded(&file.path, file.origin);
+                    continue;
+                }
+            }
+            match change {
+                Some(FileChange::Added(file)) => {
+                    changes.added.push(entry.path.clone());
+                    files.push(file);
+                }
+                Some(FileChange::Modified(file)) => {
+                    changes.modified.push(entry.path.clone());
+                    files.push(file);
+                }
+                None => {}
+            }
+            index.entries.insert(entry.path.clone(), entry);
+        }
+
+        changes.removed = previous
+            .entries
+            .keys()
+            .filter(|path| !index.entries.contains_key(*path))
+            .cloned()
+            .collect();
+        descriptor.files = files;
+        Ok(IncrementalScan {
+            descriptor,
+            changes,
+            index,
+        })
+    }
+}
+
+enum FileChange {
+    Added(WorkspaceFile),
+    Modified(WorkspaceFile),
+}
+
+/// Compare a walked file against its previous index entry.
+fn inspect(
+    walked: &WalkedFile,
+    prior: Option<&FileIndexEntry>,
+) -> Result<(FileIndexEntry, Option<FileChange>), WorkspaceError> {
+    let size = walked.metadata.len();
+    let mtime_ms = walked
+        .metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |elapsed| elapsed.as_millis() as u64);
+    if let Some(prior) = prior.filter(|p| p.size == size && p.mtime_ms == mtime_ms) {
+        return Ok((prior.clone(), None));
+    }
+
+    let bytes = fs::read(&walked.absolute).map_err(|err| io_error(&walked.absolute, &err))?;
+    let hash = blake3::hash(&bytes).to_hex().to_string();
+    let change = match prior {
+        None => Some(FileChange::Added(
+            WorkspaceFile::from_bytes(walked.relative.clone(), &bytes).with_mtime_ms(mtime_ms),
+        )),
+        Some(prior) if prior.hash != hash => Some(FileChange::Modified(
+            WorkspaceFile::from_bytes(walked.relative.clone(), &bytes).with_mtime_ms(mtime_ms),
+        )),
+        Some(_) => None,
+    };
+    let entry = FileIndexEntry {
+        path: walked.relative.clone(),
+        mtime_ms,
+        size,
+        hash,
+    };
+    Ok((entry, change))
+}
+
+fn encode_component(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'0'..=b'9' | b'a'..=b'z' | b'A'..=b'Z' | b'-' | b'_' | b'.' => {
+                char::from(byte).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
@@ -0,0 +1,157 @@
+//! File-type detection used to annotate [`WorkspaceFile`](crate::WorkspaceFile) entries.
+
+use serde::{Deserialize, Serialize};
+
+/// Number of leading bytes inspected when sniffing content.
+const SNIFF_BYTES: usize = 8 * 1024;
+
+/// Lines inspected for generated-code markers.
+const GENERATED_MARKER_LINES: usize = 5;
+
+const GENERATED_MARKERS: &[&str] = &[
+    "@generated",
+    "do not edit",
+    "auto-generated",
+    "autogenerated",
+    "code generated by",
+];
+
+const LOCKFILE_NAMES: &[&str] = &[
+    "Cargo.lock",
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "poetry.lock",
+    "Pipfile.lock",
+    "Gemfile.lock",
+    "composer.lock",
+    "go.sum",
+];
+
+/// Coarse classification attached to every scanned file so downstream stages
+/// can apply per-type policies.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum FileKind {
+    Rust,
+    Markdown,
+    Toml,
+    Json,
+    Yaml,
+    Python,
+    #[serde(rename = "javascript")]
+    JavaScript,
+    #[serde(rename = "typescript")]
+    TypeScript,
+    Shell,
+    Text,
+    Lockfile,
+    Generated,
+    Binary,
+    #[default]
+    Unknown,
+}
+
+impl FileKind {
+    /// Classify a file by name, falling back to content sniffing.
+    ///
+    /// Binary content wins over everything else, followed by lockfile names
+    /// and generated-code markers; the extension (or a shebang for
+    /// extensionless scripts) decides the remaining cases.
+    #[must_use]
+    pub fn detect(path: &str, content: &[u8]) -> Self {
+        let head = &content[..content.len().min(SNIFF_BYTES)];
+        if is_binary(head) {
+            return Self::Binary;
+        }
+        let name = path.rsplit('/').next().unwrap_or(path);
+        if LOCKFILE_NAMES.contains(&name) {
+            return Self::Lockfile;
+        }
+        let text = String::from_utf8_lossy(head);
+        if has_generated_marker(&text) {
+            return Self::Generated;
+        }
+        match name.rsplit_once('.') {
+            Some((stem, extension)) if !stem.is_empty() => Self::from_extension(extension),
+            _ => Self::from_shebang(&text),
+        }
+    }
+
+    /// Stable lowercase label, matching the serialized form.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Rust => "rust",
+            Self::Markdown => "markdown",
+            Self::Toml => "toml",
+            Self::Json => "json",
+            Self::Yaml => "yaml",
+            Self::Python => "python",
+            Self::JavaScript => "javascript",
+            Self::TypeScript => "typescript",
+            Self::Shell => "shell",
+            Self::Text => "text",
+            Self::Lockfile => "lockfile",
+            Self::Generated => "generated",
+            Self::Binary => "binary",
+            Self::Unknown => "unknown",
+        }
+    }
+
+    fn from_extension(extension: &str) -> Self {
+        match extension.to_ascii_lowercase().as_str() {
+            "rs" => Self::Rust,
+            "md" | "markdown" => Self::Markdown,
+            "toml" => Self::Toml,
+            "json" => Self::Json,
+            "yaml" | "yml" => Self::Yaml,
+            "py" | "pyi" => Self::Python,
+            "js" | "mjs" | "cjs" | "jsx" => Self::JavaScript,
+            "ts" | "mts" | "cts" | "tsx" => Self::TypeScript,
+            "sh" | "bash" | "zsh" => Self::Shell,
+            "txt" | "text" => Self::Text,
+            "lock" => Self::Lockfile,
+            _ => Self::Unknown,
+        }
+    }
+
+    fn from_shebang(text: &str) -> Self {
+        let Some(shebang) = text.lines().next().and_then(|line| line.strip_prefix("#!")) else {
+            return Self::Unknown;
+        };
+        if shebang.contains("python") {
+            Self::Python
+        } else if shebang.contains("node") {
+            Self::JavaScript
+        } else if ["sh", "bash", "zsh"]
+            .iter()
+            .any(|shell| shebang.split(['/', ' ']).any(|part| part == *shell))
+        {
+            Self::Shell
+        } else {
+            Self::Unknown
+        }
+    }
+}
+
+fn is_binary(head: &[u8]) -> bool {
+    if head.contains(&0) {
+        return true;
+    }
+    match std::str::from_utf8(head) {
+        Ok(_) => false,
+        // A multi-byte sequence cut off by the sniff window is still text.
+        Err(err) => err.error_len().is_some(),
+    }
+}
+
+fn has_generated_marker(text: &str) -> bool {
+    text.lines().take(GENERATED_MARKER_LINES).any(|line| {
+        let line = line.to_ascii_lowercase();
+        GENERATED_MARKERS.iter().any(|marker| line.contains(marker))
+    })
+}
@@ -2,14 +2,120 @@
 
 use std::collections::HashSet;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::limits::LimitTracker;
+
+pub mod archive;
+#[cfg(feature = "native")]
+pub mod commands;
+#[cfg(feature = "native")]
+mod extract;
+#[cfg(feature = "native")]
+pub mod incremental;
+pub mod kind;
+pub mod limits;
+pub mod origin;
+pub mod parallel;
+#[cfg(feature = "native")]
+pub mod registry;
+#[cfg(feature = "native")]
+mod walk;
+#[cfg(feature = "native")]
+pub mod watcher;
+
+pub use archive::{ArchiveExtraction, EntryRejection, ExtractionLimits, RejectedEntry};
+#[cfg(feature = "native")]
+pub use incremental::{FileIndexEntry, IncrementalScan, WorkspaceChanges, WorkspaceIndex};
+pub use kind::FileKind;
+pub use limits::LimitDiagnostics;
+pub use origin::{FileOrigin, GeneratedCodePolicy};
+#[cfg(feature = "native")]
+pub use registry::{WorkspaceRegistry, REGISTRY_VERSION};
+#[cfg(feature = "native")]
+pub use watcher::{LatencyDebouncer, ReplanRequest, WatcherConfig, WorkspaceWatcher};
+
 #[derive(Debug, Clone, Default)]
 pub struct EnumeratorConfig {
     pub global_ignores: Vec<IgnoreRule>,
     pub sandbox_ignores: Vec<IgnoreRule>,
+    pub symlinks: SymlinkPolicy,
+    /// Upper bound on scan worker threads; `0` uses the available parallelism.
+    pub workers: usize,
+    /// Largest single file accepted, in bytes.
+    pub max_file_bytes: Option<u64>,
+    /// Largest combined size of all files in one workspace, in bytes.
+    pub max_total_bytes: Option<u64>,
+    /// Maximum number of files in one workspace.
+    pub max_files: Option<u64>,
+    /// Whether generated and vendored files are scanned; a workspace's own
+    /// `generated_code` setting takes precedence.
+    pub generated_code: GeneratedCodePolicy,
+    /// Per-entry caps enforced while archives are extracted.
+    pub archive_limits: ExtractionLimits,
+}
+
+/// How on-disk scans treat symbolic links.
+///
+/// Links resolving outside the workspace root are never followed; they are
+/// reported as `workspace.symlink.escape` telemetry under every policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SymlinkPolicy {
+    /// Ignore symbolic links entirely.
+    #[default]
+    Skip,
+    /// Follow links whose target stays inside the workspace root.
+    FollowWithinRoot,
+    /// Fail the scan when a symbolic link is encountered.
+    Error,
+}
+
+/// Telemetry event emitted while scanning workspaces.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TelemetryEvent {
+    /// Event type (e.g., `workspace.symlink.escape`).
+    pub kind: String,
+    /// Additional message for debugging.
+    pub message: String,
+}
+
+impl TelemetryEvent {
+    /// A file left out as generated or vendored.
+    pub(crate) fn generated_excluded(path: &str, origin: FileOrigin) -> Self {
+        Self {
+            kind: "workspace.generated.excluded".into(),
+            message: format!("{path} ({})", origin.as_str()),
+        }
+    }
+
+    /// A directory the walk did not descend into because of its origin.
+    pub(crate) fn generated_skipped(path: &str, origin: FileOrigin) -> Self {
+        Self {
+            kind: "workspace.generated.skipped".into(),
+            message: format!("{path} ({})", origin.as_str()),
+        }
+    }
+}
+
+/// Sink capturing telemetry events for auditing and testing.
+#[derive(Debug, Default)]
+pub struct TelemetrySink {
+    events: Mutex<Vec<TelemetryEvent>>,
+}
+
+impl TelemetrySink {
+    /// Record a telemetry event synchronously.
+    pub fn record(&self, event: TelemetryEvent) {
+        self.events.lock().unwrap().push(event);
+    }
+
+    /// Retrieve recorded events.
+    pub fn events(&self) -> Vec<TelemetryEvent> {
+        self.events.lock().unwrap().clone()
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -52,6 +158,48 @@ impl IgnoreRule {
             pattern: pattern.into(),
         }
     }
+
+    /// Match a workspace-relative path (using `/` separators) against the rule.
+    ///
+    /// Patterns containing `/` are anchored at the workspace root and match the
+    /// path or any of its descendants; other patterns match any single path
+    /// component. `*` matches any run of characters within a component.
+    #[must_use]
+    pub fn matches(&self, relative_path: &str) -> bool {
+        let pattern = self.pattern.trim_matches('/');
+        if pattern.is_empty() {
+            return false;
+        }
+        let path = relative_path.trim_start_matches("./").trim_matches('/');
+        if pattern.contains('/') {
+            let pattern_parts: Vec<&str> = pattern.split('/').collect();
+            let path_parts: Vec<&str> = path.split('/').collect();
+            return path_parts.len() >= pattern_parts.len()
+                && pattern_parts
+                    .iter()
+                    .zip(&path_parts)
+                    .all(|(pattern, part)| wildcard_match(pattern, part));
+        }
+        path.split('/').any(|part| wildcard_match(pattern, part))
+    }
+}
+
+fn wildcard_match(pattern: &str, candidate: &str) -> bool {
+    let Some((prefix, rest)) = pattern.split_once('*') else {
+        return pattern == candidate;
+    };
+    let Some(mut remainder) = candidate.strip_prefix(prefix) else {
+        return false;
+    };
+    let mut segments: Vec<&str> = rest.split('*').collect();
+    let suffix = segments.pop().unwrap_or_default();
+    for segment in segments {
+        match remainder.find(segment) {
+            Some(index) => remainder = &remainder[index + segment.len()..],
+            None => return false,
+        }
+    }
+    remainder.len() >= suffix.len() && remainder.ends_with(suffix)
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -86,15 +234,57 @@ pub struct ArchiveDescriptor {
 pub struct WorkspaceFile {
     pub path: String,
     pub content: String,
+    /// Detected file type; filled in by the enumerator when absent.
+    #[serde(default)]
+    pub file_kind: FileKind,
+    /// Last modification time in milliseconds since the Unix epoch, when known.
+    #[serde(default)]
+    pub mtime_ms: Option<u64>,
+    /// Whether the file was authored, generated or vendored; filled in by the
+    /// enumerator when left as authored.
+    #[serde(default)]
+    pub origin: FileOrigin,
 }
 
 impl WorkspaceFile {
+    /// Build a file entry, detecting its [`FileKind`] and [`FileOrigin`] from
+    /// the path and content.
     pub fn new(path: impl Into<String>, content: impl Into<String>) -> Self {
+        let path = path.into();
+        let content = content.into();
+        let file_kind = FileKind::detect(&path, content.as_bytes());
+        let origin = FileOrigin::detect(&path, file_kind);
+        Self {
+            path,
+            content,
+            file_kind,
+            mtime_ms: None,
+            origin,
+        }
+    }
+
+    /// Build a file entry from raw bytes so binary content is detected before
+    /// lossy UTF-8 conversion.
+    #[must_use]
+    pub fn from_bytes(path: impl Into<String>, bytes: &[u8]) -> Self {
+        let path = path.into();
+        let file_kind = FileKind::detect(&path, bytes);
+        let origin = FileOrigin::detect(&path, file_kind);
         Self {
-            path: path.into(),
-            content: content.into(),
+            path,
+            content: String::from_utf8_lossy(bytes).into_owned(),
+            file_kind,
+            mtime_ms: None,
+            origin,
         }
     }
+
+    /// Record the file's modification time.
+    #[must_use]
+    pub const fn with_mtime_ms(mut self, mtime_ms: u64) -> Self {
+        self.mtime_ms = Some(mtime_ms);
+        self
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -107,6 +297,13 @@ pub struct WorkspaceRecord {
     pub archives: Vec<ArchiveDescriptor>,
     pub latency_windows: Vec<LatencyWindow>,
     pub files: Vec<WorkspaceFile>,
+    /// Tenant owning the workspace; `None` for workspaces shared by every
+    /// tenant-less session.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    /// Per-workspace override of [`EnumeratorConfig::generated_code`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generated_code: Option<GeneratedCodePolicy>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -119,6 +316,10 @@ pub struct WorkspaceDescriptor {
     pub archives: Vec<ArchiveDescriptor>,
     pub latency_windows: Vec<LatencyWindow>,
     pub files: Vec<WorkspaceFile>,
+    /// Tenant owning the workspace; `None` for workspaces shared by every
+    /// tenant-less session.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
 }
 
 impl WorkspaceDescriptor {
@@ -142,43 +343,94 @@ impl WorkspaceDescriptor {
 pub enum WorkspaceError {
     #[error("workspace enumeration failed: {0}")]
     Enumeration(String),
+    #[error("workspace watcher failed: {0}")]
+    Watch(String),
+    #[error("symbolic link rejected: {0}")]
+    Symlink(String),
+    #[error("workspace registry error: {0}")]
+    Registry(String),
+    #[error("workspace '{0}' is already registered")]
+    DuplicateWorkspace(String),
+    #[error("workspace '{0}' is not registered")]
+    UnknownWorkspace(String),
+    #[error("archive extraction failed: {0}")]
+    Archive(String),
+    #[error("workspace limits exceeded for '{}'", diagnostics.repo_id)]
+    LimitExceeded { diagnostics: LimitDiagnostics },
 }
 
 #[derive(Debug, Clone)]
 pub struct WorkspaceEnumerator {
     config: EnumeratorConfig,
+    telemetry: Option<Arc<TelemetrySink>>,
 }
 
 impl WorkspaceEnumerator {
     #[must_use]
     pub const fn new(config: EnumeratorConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            telemetry: None,
+        }
+    }
+
+    /// Record the events of on-disk scans and extractions into `sink`.
+    #[must_use]
+    pub fn with_telemetry(mut self, sink: Arc<TelemetrySink>) -> Self {
+        self.telemetry = Some(sink);
+        self
+    }
+
+    /// The telemetry sink populated by on-disk scans, if one is attached.
+    #[must_use]
+    pub fn telemetry(&self) -> Option<&Arc<TelemetrySink>> {
+        self.telemetry.as_ref()
     }
 
     pub fn scan(
         &self,
         snapshot: &RegistrySnapshot,
     ) -> Result<Vec<WorkspaceDescriptor>, WorkspaceError> {
-        let mut descriptors = Vec::with_capacity(snapshot.workspaces.len());
-        for record in &snapshot.workspaces {
-            let ignore_stack = self.merge_ignore_stack(&record.ignore_rules);
-            let latency_windows = record
-                .latency_windows
-                .iter()
-                .map(Self::normalize_window)
-                .collect();
-            descriptors.push(WorkspaceDescriptor {
-                repo_id: record.repo_id.clone(),
-                root_path: record.root_path.clone(),
-                repo_type: record.repo_type.clone(),
-                manifest_cursor: record.manifest_cursor.clone(),
-                ignore_stack,
-                archives: record.archives.clone(),
-                latency_windows,
-                files: record.files.clone(),
+        let workers = parallel::resolve_workers(self.config.workers);
+        parallel::parallel_map(&snapshot.workspaces, workers, |record| {
+            let mut descriptor = self.describe(record);
+            let policy = self.generated_code(record);
+            descriptor.files.retain(|file| {
+                let excluded = policy.excludes(file.origin);
+                if excluded {
+                    self.record_excluded(&file.path, file.origin);
+                }
+                !excluded
             });
+            let mut limits = LimitTracker::new(&self.config, &record.repo_id);
+            for file in &descriptor.files {
+                limits.observe(&file.path, file.content.len() as u64);
+            }
+            limits.check()?;
+            Ok(descriptor)
+        })
+        .into_iter()
+        .collect()
+    }
+
+    fn describe(&self, record: &WorkspaceRecord) -> WorkspaceDescriptor {
+        let ignore_stack = self.merge_ignore_stack(&record.ignore_rules);
+        let latency_windows = record
+            .latency_windows
+            .iter()
+            .map(Self::normalize_window)
+            .collect();
+        WorkspaceDescriptor {
+            repo_id: record.repo_id.clone(),
+            root_path: record.root_path.clone(),
+            repo_type: record.repo_type.clone(),
+            manifest_cursor: record.manifest_cursor.clone(),
+            ignore_stack,
+            archives: record.archives.clone(),
+            latency_windows,
+            files: record.files.iter().map(Self::annotate_file).collect(),
+            tenant_id: record.tenant_id.clone(),
         }
-        Ok(descriptors)
     }
 
     fn merge_ignore_stack(&self, repo_rules: &[IgnoreRule]) -> Vec<IgnoreRule> {
@@ -199,6 +451,32 @@ impl WorkspaceEnumerator {
         stack
     }
 
+    fn annotate_file(file: &WorkspaceFile) -> WorkspaceFile {
+        let mut annotated = file.clone();
+        if annotated.file_kind == FileKind::Unknown {
+            annotated.file_kind = FileKind::detect(&annotated.path, annotated.content.as_bytes());
+        }
+        if annotated.origin == FileOrigin::Authored {
+            annotated.origin = FileOrigin::detect(&annotated.path, annotated.file_kind);
+        }
+        annotated
+    }
+
+    /// The generated-code policy in force for `record`.
+    fn generated_code(&self, record: &WorkspaceRecord) -> GeneratedCodePolicy {
+        record.generated_code.unwrap_or(self.config.generated_code)
+    }
+
+    fn record(&self, event: TelemetryEvent) {
+        if let Some(sink) = &self.telemetry {
+            sink.record(event);
+        }
+    }
+
+    fn record_excluded(&self, path: &str, origin: FileOrigin) {
+        self.record(TelemetryEvent::generated_excluded(path, origin));
+    }
+
     fn normalize_window(window: &LatencyWindow) -> LatencyWindow {
         let mut normalized = window.clone();
         if normalized.events_observed == 0 {
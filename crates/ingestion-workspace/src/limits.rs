@@ -0,0 +1,82 @@
+//! Per-workspace file size and count caps, modelled on the archive quota tracker.
+
+use crate::{EnumeratorConfig, WorkspaceError};
+
+/// Observed totals and configured limits reported when a cap is exceeded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitDiagnostics {
+    pub repo_id: String,
+    /// Largest file observed, reported when `max_file_bytes` is exceeded.
+    pub largest_path: Option<String>,
+    pub file_bytes_limit: Option<u64>,
+    pub total_bytes_limit: Option<u64>,
+    pub files_limit: Option<u64>,
+    pub largest_file_bytes: u64,
+    pub total_bytes_observed: u64,
+    pub files_observed: u64,
+}
+
+/// Accumulates file sizes for one workspace and checks them against the caps.
+#[derive(Debug)]
+pub(crate) struct LimitTracker<'a> {
+    config: &'a EnumeratorConfig,
+    repo_id: &'a str,
+    largest: Option<(String, u64)>,
+    total_bytes: u64,
+    files: u64,
+}
+
+impl<'a> LimitTracker<'a> {
+    pub(crate) const fn new(config: &'a EnumeratorConfig, repo_id: &'a str) -> Self {
+        Self {
+            config,
+            repo_id,
+            largest: None,
+            total_bytes: 0,
+            files: 0,
+        }
+    }
+
+    pub(crate) fn observe(&mut self, path: &str, bytes: u64) {
+        self.total_bytes = self.total_bytes.saturating_add(bytes);
+        self.files = self.files.saturating_add(1);
+        if self
+            .largest
+            .as_ref()
+            .map_or(true, |(_, size)| bytes > *size)
+        {
+            self.largest = Some((path.to_string(), bytes));
+        }
+    }
+
+    pub(crate) fn check(self) -> Result<(), WorkspaceError> {
+        let largest_file_bytes = self.largest.as_ref().map_or(0, |(_, size)| *size);
+        let file_exceeded = self
+            .config
+            .max_file_bytes
+            .is_some_and(|limit| largest_file_bytes > limit);
+        let total_exceeded = self
+            .config
+            .max_total_bytes
+            .is_some_and(|limit| self.total_bytes > limit);
+        let files_exceeded = self
+            .config
+            .max_files
+            .is_some_and(|limit| self.files > limit);
+        if !(file_exceeded || total_exceeded || files_exceeded) {
+            return Ok(());
+        }
+        Err(WorkspaceError::LimitExceeded {
+            diagnostics: LimitDiagnostics {
+                repo_id: self.repo_id.to_string(),
+                largest_path: self.largest.filter(|_| file_exceeded).map(|(path, _)| path),
+                file_bytes_limit: self.config.max_file_bytes,
+                total_bytes_limit: self.config.max_total_bytes,
+                files_limit: self.config.max_files,
+                largest_file_bytes,
+                total_bytes_observed: self.total_bytes,
+                files_observed: self.files,
+            },
+        })
+    }
+}
@@ -0,0 +1,110 @@
+//! Heuristics tagging [`WorkspaceFile`](crate::WorkspaceFile) entries that
+//! were generated by a tool or vendored from another project.
+
+use serde::{Deserialize, Serialize};
+
+use crate::FileKind;
+
+/// Directories holding third-party code checked out or installed into the tree.
+const VENDORED_DIRS: &[&str] = &[
+    "node_modules",
+    "bower_components",
+    "vendor",
+    "third_party",
+    "site-packages",
+];
+
+/// Directories holding build output. Only matched at the workspace root, as
+/// the same names are common for authored modules further down.
+const GENERATED_DIRS: &[&str] = &["target", "dist", "build", "__generated__"];
+
+/// File name suffixes emitted by minifiers and code generators.
+const GENERATED_SUFFIXES: &[&str] = &[".min.js", ".min.css", ".pb.go", "_pb2.py", ".g.dart"];
+
+/// Where a file's content came from.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum FileOrigin {
+    /// Written by the workspace's own authors.
+    #[default]
+    Authored,
+    /// Produced by a build step or code generator.
+    Generated,
+    /// Copied in from another project.
+    Vendored,
+}
+
+impl FileOrigin {
+    /// Classify a workspace-relative path and its detected kind.
+    ///
+    /// A vendored directory anywhere in the path wins, then a build-output
+    /// directory at the root, generated file name suffixes and
+    /// `@generated`-style content markers (already reflected in
+    /// [`FileKind::Generated`]).
+    #[must_use]
+    pub fn detect(path: &str, kind: FileKind) -> Self {
+        let path = path.trim_start_matches("./");
+        let (dirs, name) = path.rsplit_once('/').unwrap_or(("", path));
+        let dirs = dirs.split('/').filter(|dir| !dir.is_empty());
+        let origin = dirs
+            .enumerate()
+            .filter_map(|(depth, dir)| Self::of_directory(dir, depth == 0))
+            .max();
+        if let Some(origin) = origin {
+            return origin;
+        }
+        if kind == FileKind::Generated
+            || GENERATED_SUFFIXES
+                .iter()
+                .any(|suffix| name.len() > suffix.len() && name.ends_with(suffix))
+        {
+            return Self::Generated;
+        }
+        Self::Authored
+    }
+
+    /// Origin of everything below a directory named `name`, when the name
+    /// alone gives it away. Build-output names only count `at_root`.
+    #[must_use]
+    pub fn of_directory(name: &str, at_root: bool) -> Option<Self> {
+        if VENDORED_DIRS.contains(&name) {
+            Some(Self::Vendored)
+        } else if at_root && GENERATED_DIRS.contains(&name) {
+            Some(Self::Generated)
+        } else {
+            None
+        }
+    }
+
+    /// Stable lowercase label, matching the serialized form.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Authored => "authored",
+            Self::Generated => "generated",
+            Self::Vendored => "vendored",
+        }
+    }
+}
+
+/// Whether scans keep generated and vendored files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GeneratedCodePolicy {
+    /// Drop them: vendored and build-output directories are not descended
+    /// into and marked files are left out of descriptors.
+    #[default]
+    Exclude,
+    /// Keep them, tagged with their [`FileOrigin`].
+    Include,
+}
+
+impl GeneratedCodePolicy {
+    /// Whether a file of `origin` is dropped under this policy.
+    #[must_use]
+    pub fn excludes(self, origin: FileOrigin) -> bool {
+        self == Self::Exclude && origin != FileOrigin::Authored
+    }
+}
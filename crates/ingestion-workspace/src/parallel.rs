@@ -0,0 +1,78 @@
+//! Bounded fan-out helper used by the scanning paths and by batch
+//! sanitization.
+
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Resolve a configured worker count, where `0` means "one per available core".
+pub fn resolve_workers(configured: usize) -> usize {
+    if configured > 0 {
+        return configured;
+    }
+    thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+/// Apply `f` to every item using at most `workers` threads.
+///
+/// Results are returned in input order regardless of which worker produced
+/// them, so callers observe the same output as a sequential map.
+pub fn parallel_map<T, R, F>(items: &[T], workers: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let workers = workers.clamp(1, items.len().max(1));
+    if workers == 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let mut slots: Vec<Option<R>> = std::iter::repeat_with(|| None).take(items.len()).collect();
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut produced = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(index) else {
+                            break;
+                        };
+                        produced.push((index, f(item)));
+                    }
+                    produced
+                })
+            })
+            .collect();
+        for handle in handles {
+            let produced = handle
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+            for (index, result) in produced {
+                slots[index] = Some(result);
+            }
+        }
+    });
+    slots
+        .into_iter()
+        .map(|slot| slot.expect("every index is claimed by exactly one worker"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preserves_input_order_across_workers() {
+        let items: Vec<u32> = (0..257).collect();
+        let doubled = parallel_map(&items, 8, |value| value * 2);
+        assert_eq!(
+            doubled,
+            items.iter().map(|value| value * 2).collect::<Vec<_>>()
+        );
+        assert!(parallel_map(&[] as &[u32], 4, |value| *value).is_empty());
+    }
+}
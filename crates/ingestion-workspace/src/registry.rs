@@ -0,0 +1,204 @@
+//! Versioned on-disk persistence for [`RegistrySnapshot`].
+//!
+//! Registry files ending in `.toml` are read and written as TOML; any other
+//! extension is JSON. Both formats share the schema and its migrations.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::walk::io_error;
+use crate::{RegistrySnapshot, WorkspaceError, WorkspaceRecord};
+
+/// Version written into saved registries; [`RegistrySnapshot::load`]
+/// migrates anything older up to it.
+pub const REGISTRY_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RegistryFile {
+    version: u32,
+    workspaces: Vec<WorkspaceRecord>,
+}
+
+/// Serialization of a registry file, chosen by its extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RegistryFormat {
+    Json,
+    Toml,
+}
+
+impl RegistryFormat {
+    fn of(path: &Path) -> Self {
+        match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => Self::Toml,
+            _ => Self::Json,
+        }
+    }
+
+    fn parse(self, bytes: &[u8]) -> Result<Value, String> {
+        match self {
+            Self::Json => serde_json::from_slice(bytes).map_err(|err| err.to_string()),
+            Self::Toml => std::str::from_utf8(bytes)
+                .map_err(|err| err.to_string())
+                .and_then(|text| toml::from_str(text).map_err(|err| err.to_string())),
+        }
+    }
+
+    fn render(self, file: &RegistryFile) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Json => serde_json::to_vec_pretty(file).map_err(|err| err.to_string()),
+            Self::Toml => toml::to_string_pretty(file)
+                .map(String::into_bytes)
+                .map_err(|err| err.to_string()),
+        }
+    }
+}
+
+impl RegistrySnapshot {
+    /// Load a snapshot from `path`, migrating older layouts to the current
+    /// schema. A missing file yields an empty snapshot.
+    ///
+    /// Version 0 files predate the version field and are either a bare array
+    /// of records or an object holding only `workspaces`.
+    pub fn load(path: &Path) -> Result<Self, WorkspaceError> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Self::default());
+            }
+            Err(err) => return Err(io_error(path, &err)),
+        };
+        let value = RegistryFormat::of(path).parse(&bytes).map_err(|err| {
+            WorkspaceError::Registry(format!("{}: invalid registry: {err}", path.display()))
+        })?;
+        let file = migrate(value)
+            .map_err(|detail| WorkspaceError::Registry(format!("{}: {detail}", path.display())))?;
+        Ok(Self::new(file.workspaces))
+    }
+
+    /// Atomically persist the snapshot to `path` using the current schema.
+    pub fn save(&self, path: &Path) -> Result<(), WorkspaceError> {
+        let file = RegistryFile {
+            version: REGISTRY_VERSION,
+            workspaces: self.workspaces.clone(),
+        };
+        let bytes = RegistryFormat::of(path)
+            .render(&file)
+            .map_err(|err| WorkspaceError::Registry(format!("serializing registry: {err}")))?;
+        storage_atomic::write(path, &bytes).map_err(|err| io_error(path, &err))
+    }
+
+    /// Add `record`, rejecting duplicate repository identifiers.
+    pub fn register_workspace(&mut self, record: WorkspaceRecord) -> Result<(), WorkspaceError> {
+        if self
+            .workspaces
+            .iter()
+            .any(|existing| existing.repo_id == record.repo_id)
+        {
+            return Err(WorkspaceError::DuplicateWorkspace(record.repo_id));
+        }
+        self.workspaces.push(record);
+        Ok(())
+    }
+
+    /// Remove and return the record for `repo_id`.
+    pub fn deregister_workspace(
+        &mut self,
+        repo_id: &str,
+    ) -> Result<WorkspaceRecord, WorkspaceError> {
+        let index = self
+            .workspaces
+            .iter()
+            .position(|record| record.repo_id == repo_id)
+            .ok_or_else(|| WorkspaceError::UnknownWorkspace(repo_id.to_string()))?;
+        Ok(self.workspaces.remove(index))
+    }
+}
+
+fn migrate(value: Value) -> Result<RegistryFile, String> {
+    let version = match &value {
+        Value::Array(_) => 0,
+        Value::Object(map) => match map.get("version") {
+            None => 0,
+            Some(version) => version
+                .as_u64()
+                .and_then(|version| u32::try_from(version).ok())
+                .ok_or_else(|| format!("invalid registry version {version}"))?,
+        },
+        _ => return Err("registry must be an object or array".into()),
+    };
+    match version {
+        0 => {
+            let workspaces = match value {
+                Value::Array(records) => Value::Array(records),
+                Value::Object(mut map) => map.remove("workspaces").unwrap_or(Value::Array(vec![])),
+                _ => unreachable!("checked above"),
+            };
+            let workspaces = serde_json::from_value(workspaces)
+                .map_err(|err| format!("invalid version 0 registry: {err}"))?;
+            Ok(RegistryFile {
+                version: REGISTRY_VERSION,
+                workspaces,
+            })
+        }
+        REGISTRY_VERSION => {
+            serde_json::from_value(value).map_err(|err| format!("invalid registry: {err}"))
+        }
+        other => Err(format!(
+            "unsupported registry version {other} (expected <= {REGISTRY_VERSION})"
+        )),
+    }
+}
+
+/// Registry snapshot bound to a file, persisted after every mutation.
+#[derive(Debug)]
+pub struct WorkspaceRegistry {
+    path: PathBuf,
+    snapshot: Mutex<RegistrySnapshot>,
+}
+
+impl WorkspaceRegistry {
+    /// Open the registry stored at `path`, creating an empty one if absent.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, WorkspaceError> {
+        let path = path.into();
+        let snapshot = RegistrySnapshot::load(&path)?;
+        Ok(Self {
+            path,
+            snapshot: Mutex::new(snapshot),
+        })
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Copy of the current registry contents.
+    #[must_use]
+    pub fn snapshot(&self) -> RegistrySnapshot {
+        self.snapshot.lock().unwrap().clone()
+    }
+
+    /// Register a workspace and persist the registry.
+    pub fn register_workspace(&self, record: WorkspaceRecord) -> Result<(), WorkspaceError> {
+        let mut snapshot = self.snapshot.lock().unwrap();
+        let mut next = snapshot.clone();
+        next.register_workspace(record)?;
+        next.save(&self.path)?;
+        *snapshot = next;
+        Ok(())
+    }
+
+    /// Deregister a workspace and persist the registry.
+    pub fn deregister_workspace(&self, repo_id: &str) -> Result<WorkspaceRecord, WorkspaceError> {
+        let mut snapshot = self.snapshot.lock().unwrap();
+        let mut next = snapshot.clone();
+        let removed = next.deregister_workspace(repo_id)?;
+        next.save(&self.path)?;
+        *snapshot = next;
+        Ok(removed)
+    }
+}
@@ -0,0 +1,259 @@
+//! Filesystem traversal shared by the on-disk scanning paths.
+
+use std::collections::HashSet;
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+
+use crate::{
+    FileKind, FileOrigin, GeneratedCodePolicy, IgnoreRule, SymlinkPolicy, TelemetryEvent,
+    TelemetrySink, WorkspaceError,
+};
+
+/// Regular file discovered beneath a workspace root.
+#[derive(Debug, Clone)]
+pub(crate) struct WalkedFile {
+    /// Workspace-relative path using `/` separators.
+    pub relative: String,
+    pub absolute: PathBuf,
+    pub metadata: Metadata,
+}
+
+/// Traversal settings derived from the enumerator configuration.
+pub(crate) struct WalkOptions<'a> {
+    pub ignore_stack: &'a [IgnoreRule],
+    pub symlinks: SymlinkPolicy,
+    pub generated_code: GeneratedCodePolicy,
+    pub telemetry: Option<&'a TelemetrySink>,
+}
+
+impl WalkOptions<'_> {
+    fn record(&self, event: TelemetryEvent) {
+        if let Some(sink) = self.telemetry {
+            sink.record(event);
+        }
+    }
+}
+
+/// Walk `root` depth-first, returning regular files sorted by relative path.
+///
+/// Entries matching the ignore stack are pruned (directories are not descended
+/// into), as are vendored and build-output directories and generated file
+/// names when the [`GeneratedCodePolicy`] excludes them. Symbolic links are
+/// handled per [`SymlinkPolicy`]; links resolving outside the root are never
+/// followed and are reported as telemetry, and followed links are recorded
+/// and walked by their canonical target rather than the link path. Directories reachable both
+/// directly and through a link are walked once, under whichever path the walk
+/// reaches first. Files reachable through several hard links are returned
+/// once, under the first path in sorted order.
+pub(crate) fn walk_files(
+    root: &Path,
+    options: &WalkOptions<'_>,
+) -> Result<Vec<WalkedFile>, WorkspaceError> {
+    let canonical_root = root.canonicalize().map_err(|err| io_error(root, &err))?;
+    let mut visited_dirs = HashSet::from([canonical_root.clone()]);
+    let mut files = Vec::new();
+    let mut pending = vec![(root.to_path_buf(), String::new())];
+    while let Some((dir, prefix)) = pending.pop() {
+        let mut entries = fs::read_dir(&dir)
+            .and_then(Iterator::collect::<Result<Vec<_>, _>>)
+            .map_err(|err| io_error(&dir, &err))?;
+        // A stable order decides which path wins when a directory is
+        // reachable both directly and through a link.
+        entries.sort_by_key(fs::DirEntry::file_name);
+        for entry in entries {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let relative = if prefix.is_empty() {
+                name
+            } else {
+                format!("{prefix}/{name}")
+            };
+            if options
+                .ignore_stack
+                .iter()
+                .any(|rule| rule.matches(&relative))
+            {
+                continue;
+            }
+            let path = entry.path();
+            let file_type = entry.file_type().map_err(|err| io_error(&path, &err))?;
+            if file_type.is_symlink() {
+                let Some(target) = resolve_link(&path, &relative, &canonical_root, options)? else {
+                    continue;
+                };
+                let metadata = fs::metadata(&target).map_err(|err| io_error(&target, &err))?;
+                if excluded(&relative, metadata.is_dir(), options) {
+                    continue;
+                }
+                // Descend into and read the checked target, never the link
+                // path, so a link swapped after the check cannot redirect
+                // the scan outside the root.
+                if metadata.is_dir() {
+                    if visited_dirs.insert(target.clone()) {
+                        pending.push((target, relative));
+                    } else {
+                        options.record(TelemetryEvent {
+                            kind: "workspace.symlink.cycle".into(),
+                            message: relative,
+                        });
+                    }
+                } else if metadata.is_file() {
+                    files.push(WalkedFile {
+                        relative,
+                        absolute: target,
+                        metadata,
+                    });
+                }
+                continue;
+            }
+            if excluded(&relative, file_type.is_dir(), options) {
+                continue;
+            }
+            if file_type.is_dir() {
+                // The target may already have been walked through a link;
+                // its contents are only ingested under the first path.
+                let first_visit = path
+                    .canonicalize()
+                    .map_or(true, |canonical| visited_dirs.insert(canonical));
+                if first_visit {
+                    pending.push((path, relative));
+                } else {
+                    options.record(TelemetryEvent {
+                        kind: "workspace.symlink.duplicate".into(),
+                        message: relative,
+                    });
+                }
+            } else if file_type.is_file() {
+                let metadata = entry.metadata().map_err(|err| io_error(&path, &err))?;
+                files.push(WalkedFile {
+                    relative,
+                    absolute: path,
+                    metadata,
+                });
+            }
+        }
+    }
+    files.sort_by(|a, b| a.relative.cmp(&b.relative));
+    dedup_hardlinks(&mut files, options);
+    Ok(files)
+}
+
+/// Whether the policy drops `relative` on its path alone, recording it if so.
+fn excluded(relative: &str, is_dir: bool, options: &WalkOptions<'_>) -> bool {
+    let origin = if is_dir {
+        let (parent, name) = relative.rsplit_once('/').unwrap_or(("", relative));
+        FileOrigin::of_directory(name, parent.is_empty()).unwrap_or_default()
+    } else {
+        FileOrigin::detect(relative, FileKind::Unknown)
+    };
+    if !options.generated_code.excludes(origin) {
+        return false;
+    }
+    options.record(if is_dir {
+        TelemetryEvent::generated_skipped(relative, origin)
+    } else {
+        TelemetryEvent::generated_excluded(relative, origin)
+    });
+    true
+}
+
+/// Apply the symlink policy, returning the canonical target when it should be followed.
+fn resolve_link(
+    path: &Path,
+    relative: &str,
+    canonical_root: &Path,
+    options: &WalkOptions<'_>,
+) -> Result<Option<PathBuf>, WorkspaceError> {
+    let Ok(target) = path.canonicalize() else {
+        // Dangling links have nothing to ingest regardless of policy.
+        return Ok(None);
+    };
+    if !target.starts_with(canonical_root) {
+        tracing::warn!(path = relative, target = %target.display(), "symlink escapes workspace root");
+        options.record(TelemetryEvent {
+            kind: "workspace.symlink.escape".into(),
+            message: format!("{relative} -> {}", target.display()),
+        });
+        if options.symlinks == SymlinkPolicy::Error {
+            return Err(WorkspaceError::Symlink(format!(
+                "{relative} resolves outside the workspace root"
+            )));
+        }
+        return Ok(None);
+    }
+    match options.symlinks {
+        SymlinkPolicy::Skip => Ok(None),
+        SymlinkPolicy::FollowWithinRoot => Ok(Some(target)),
+        SymlinkPolicy::Error => Err(WorkspaceError::Symlink(format!(
+            "{relative} is a symbolic link"
+        ))),
+    }
+}
+
+#[cfg(unix)]
+fn dedup_hardlinks(files: &mut Vec<WalkedFile>, options: &WalkOptions<'_>) {
+    use std::os::unix::fs::MetadataExt;
+
+    let mut seen = HashSet::new();
+    files.retain(|file| {
+        if file.metadata.nlink() <= 1 {
+            return true;
+        }
+        let inode = (file.metadata.dev(), file.metadata.ino());
+        if seen.insert(inode) {
+            return true;
+        }
+        options.record(TelemetryEvent {
+            kind: "workspace.hardlink.duplicate".into(),
+            message: file.relative.clone(),
+        });
+        false
+    });
+}
+
+#[cfg(not(unix))]
+fn dedup_hardlinks(_files: &mut Vec<WalkedFile>, _options: &WalkOptions<'_>) {}
+
+pub(crate) fn io_error(path: &Path, err: &std::io::Error) -> WorkspaceError {
+    WorkspaceError::Enumeration(format!("{}: {err}", path.display()))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn followed_links_are_read_through_their_checked_target() {
+        let scratch = tempfile::tempdir().expect("scratch dir");
+        let root = scratch.path().join("root");
+        let outside = scratch.path().join("outside");
+        fs::create_dir_all(root.join("real")).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(root.join("real/lib.rs"), "inside").unwrap();
+        fs::write(root.join("real.rs"), "inside").unwrap();
+        fs::write(outside.join("lib.rs"), "outside").unwrap();
+        fs::write(outside.join("secret.rs"), "outside").unwrap();
+        std::os::unix::fs::symlink(root.join("real"), root.join("dir-link")).unwrap();
+        std::os::unix::fs::symlink(root.join("real.rs"), root.join("file-link.rs")).unwrap();
+
+        let options = WalkOptions {
+            ignore_stack: &[],
+            symlinks: SymlinkPolicy::FollowWithinRoot,
+            generated_code: GeneratedCodePolicy::Include,
+            telemetry: None,
+        };
+        let files = walk_files(&root, &options).expect("walk");
+
+        // Swap both links to point outside the root once they were checked.
+        fs::remove_file(root.join("dir-link")).unwrap();
+        std::os::unix::fs::symlink(&outside, root.join("dir-link")).unwrap();
+        fs::remove_file(root.join("file-link.rs")).unwrap();
+        std::os::unix::fs::symlink(outside.join("secret.rs"), root.join("file-link.rs")).unwrap();
+
+        let canonical_root = root.canonicalize().unwrap();
+        for file in &files {
+            assert!(file.absolute.starts_with(&canonical_root), "{file:?}");
+            assert_eq!(fs::read_to_string(&file.absolute).unwrap(), "inside");
+        }
+        assert!(files.iter().any(|file| file.relative == "file-link.rs"));
+    }
+}
@@ -0,0 +1,416 @@
+//! Filesystem watcher that turns raw notify events into debounced latency windows.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc as std_mpsc;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use notify::event::{EventKind, ModifyKind};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+
+use crate::{IgnoreRule, LatencyEvent, LatencyWindow, WorkspaceDescriptor, WorkspaceError};
+
+/// Timing parameters applied to every watched workspace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatcherConfig {
+    /// Upper bound on how long a window may stay open before it is flushed.
+    pub window_ms: u64,
+    /// Quiet period after the last event before a window is flushed early.
+    pub debounce_ms: u64,
+    /// Capacity of the re-planning channel handed back to the caller.
+    pub channel_capacity: usize,
+}
+
+impl Default for WatcherConfig {
+    fn default() -> Self {
+        Self {
+            window_ms: 100,
+            debounce_ms: 20,
+            channel_capacity: 64,
+        }
+    }
+}
+
+/// Request emitted on the re-planning channel once a window closes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplanRequest {
+    pub repo_id: String,
+    pub root_path: PathBuf,
+    /// Distinct workspace-relative paths touched during the window, sorted.
+    pub changed_paths: Vec<String>,
+    pub window: LatencyWindow,
+}
+
+#[derive(Debug, Clone)]
+struct PendingEvent {
+    path: String,
+    action: String,
+    first_seen: Instant,
+}
+
+/// Debounce state for a single workspace.
+///
+/// Events for the same path arriving within `debounce_ms` of each other are
+/// coalesced (the latest action wins). A window is flushed once it has been
+/// quiet for `debounce_ms` or has been open for `window_ms`.
+#[derive(Debug, Clone)]
+pub struct LatencyDebouncer {
+    window_ms: u64,
+    debounce_ms: u64,
+    window_start: Option<Instant>,
+    last_event: Option<Instant>,
+    pending: Vec<PendingEvent>,
+    last_seen_by_path: HashMap<String, Instant>,
+    observed: u32,
+    queue_depth: u32,
+}
+
+impl LatencyDebouncer {
+    #[must_use]
+    pub fn new(window_ms: u64, debounce_ms: u64) -> Self {
+        Self {
+            window_ms,
+            debounce_ms,
+            window_start: None,
+            last_event: None,
+            pending: Vec::new(),
+            last_seen_by_path: HashMap::new(),
+            observed: 0,
+            queue_depth: 0,
+        }
+    }
+
+    /// Record a raw filesystem event observed at `at`.
+    pub fn observe(&mut self, path: impl Into<String>, action: impl Into<String>, at: Instant) {
+        let path = path.into();
+        let action = action.into();
+        self.window_start.get_or_insert(at);
+        self.last_event = Some(at);
+        self.observed = self.observed.saturating_add(1);
+
+        let debounce = Duration::from_millis(self.debounce_ms);
+        let coalesce = self
+            .last_seen_by_path
+            .get(&path)
+            .is_some_and(|previous| at.saturating_duration_since(*previous) < debounce);
+        self.last_seen_by_path.insert(path.clone(), at);
+        if coalesce {
+            if let Some(existing) = self.pending.iter_mut().rev().find(|p| p.path == path) {
+                existing.action = action;
+                return;
+            }
+        }
+        self.pending.push(PendingEvent {
+            path,
+            action,
+            first_seen: at,
+        });
+        self.queue_depth = self.queue_depth.max(self.pending.len() as u32);
+    }
+
+    /// Whether the current window should be flushed at `now`.
+    #[must_use]
+    pub fn is_ready(&self, now: Instant) -> bool {
+        let (Some(start), Some(last)) = (self.window_start, self.last_event) else {
+            return false;
+        };
+        now.saturating_duration_since(last) >= Duration::from_millis(self.debounce_ms)
+            || now.saturating_duration_since(start) >= Duration::from_millis(self.window_ms)
+    }
+
+    /// Close the current window, returning `None` when no events are pending.
+    pub fn flush(&mut self, now: Instant) -> Option<LatencyWindow> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let events: Vec<LatencyEvent> = self
+            .pending
+            .drain(..)
+            .map(|pending| LatencyEvent {
+                path: pending.path,
+                action: pending.action,
+                latency_ms: now
+                    .saturating_duration_since(pending.first_seen)
+                    .as_millis() as u64,
+            })
+            .collect();
+        let max_latency_ms = events
+            .iter()
+            .map(|event| event.latency_ms)
+            .max()
+            .unwrap_or_default();
+        let window = LatencyWindow {
+            window_ms: self.window_ms,
+            debounce_ms: self.debounce_ms,
+            queue_depth: self.queue_depth,
+            events,
+            events_observed: self.observed,
+            max_latency_ms,
+        };
+        self.window_start = None;
+        self.last_event = None;
+        self.last_seen_by_path.clear();
+        self.observed = 0;
+        self.queue_depth = 0;
+        Some(window)
+    }
+}
+
+struct WatchedRoot {
+    repo_id: String,
+    root_path: PathBuf,
+    canonical_root: PathBuf,
+    ignore_stack: Vec<IgnoreRule>,
+    debouncer: LatencyDebouncer,
+}
+
+/// Watches workspace roots and emits a [`ReplanRequest`] per closed window.
+///
+/// Dropping the watcher stops the underlying notify backend; any pending
+/// window is flushed before the worker thread exits if the channel has room.
+/// Windows the caller is not draining are dropped rather than waited on, so
+/// dropping never blocks on a full channel.
+pub struct WorkspaceWatcher {
+    watcher: Option<RecommendedWatcher>,
+    worker: Option<JoinHandle<()>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl WorkspaceWatcher {
+    /// Start watching the roots of `descriptors` recursively.
+    pub fn spawn(
+        descriptors: &[WorkspaceDescriptor],
+        config: WatcherConfig,
+    ) -> Result<(Self, mpsc::Receiver<ReplanRequest>), WorkspaceError> {
+        let (raw_tx, raw_rx) = std_mpsc::channel::<notify::Result<Event>>();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = raw_tx.send(event);
+        })
+        .map_err(|err| WorkspaceError::Watch(err.to_string()))?;
+
+        let mut roots = Vec::with_capacity(descriptors.len());
+        for descriptor in descriptors {
+            watcher
+                .watch(&descriptor.root_path, RecursiveMode::Recursive)
+                .map_err(|err| {
+                    WorkspaceError::Watch(format!("{}: {err}", descriptor.root_path.display()))
+                })?;
+            let canonical_root = descriptor
+                .root_path
+                .canonicalize()
+                .unwrap_or_else(|_| descriptor.root_path.clone());
+            roots.push(WatchedRoot {
+                repo_id: descriptor.repo_id.clone(),
+                root_path: descriptor.root_path.clone(),
+                canonical_root,
+                ignore_stack: descriptor.ignore_stack.clone(),
+                debouncer: LatencyDebouncer::new(config.window_ms, config.debounce_ms),
+            });
+        }
+
+        let (replan_tx, replan_rx) = mpsc::channel(config.channel_capacity.max(1));
+        let tick = Duration::from_millis(config.debounce_ms.clamp(1, 50));
+        let stop = Arc::new(AtomicBool::new(false));
+        let worker_stop = Arc::clone(&stop);
+        let worker = thread::Builder::new()
+            .name("workspace-watcher".into())
+            .spawn(move || run_worker(&raw_rx, &replan_tx, &worker_stop, roots, tick))
+            .map_err(|err| WorkspaceError::Watch(err.to_string()))?;
+
+        Ok((
+            Self {
+                watcher: Some(watcher),
+                worker: Some(worker),
+                stop,
+            },
+            replan_rx,
+        ))
+    }
+}
+
+impl Drop for WorkspaceWatcher {
+    fn drop(&mut self) {
+        // The flag stops the worker waiting for channel room; dropping the
+        // notify watcher closes the raw channel, which ends the worker loop.
+        self.stop.store(true, Ordering::Release);
+        self.watcher.take();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn run_worker(
+    raw_rx: &std_mpsc::Receiver<notify::Result<Event>>,
+    replan_tx: &mpsc::Sender<ReplanRequest>,
+    stop: &AtomicBool,
+    mut roots: Vec<WatchedRoot>,
+    tick: Duration,
+) {
+    loop {
+        let disconnected = match raw_rx.recv_timeout(tick) {
+            Ok(Ok(event)) => {
+                let now = Instant::now();
+                if let Some(action) = action_label(&event.kind) {
+                    for path in &event.paths {
+                        route_event(&mut roots, path, action, now);
+                    }
+                }
+                false
+            }
+            Ok(Err(err)) => {
+                tracing::warn!(error = %err, "workspace watcher backend error");
+                false
+            }
+            Err(std_mpsc::RecvTimeoutError::Timeout) => false,
+            Err(std_mpsc::RecvTimeoutError::Disconnected) => true,
+        };
+
+        let now = Instant::now();
+        for root in &mut roots {
+            if disconnected || root.debouncer.is_ready(now) {
+                if let Some(window) = root.debouncer.flush(now) {
+                    let mut changed_paths: Vec<String> = window
+                        .events
+                        .iter()
+                        .map(|event| event.path.clone())
+                        .collect();
+                    changed_paths.sort();
+                    changed_paths.dedup();
+                    let request = ReplanRequest {
+                        repo_id: root.repo_id.clone(),
+                        root_path: root.root_path.clone(),
+                        changed_paths,
+                        window,
+                    };
+                    if !deliver(replan_tx, request, stop, tick) {
+                        return;
+                    }
+                }
+            }
+        }
+        if disconnected {
+            return;
+        }
+    }
+}
+
+/// Send `request`, waiting in `tick` steps while the channel is full until
+/// the watcher is dropped; returns `false` once the receiver is gone.
+fn deliver(
+    replan_tx: &mpsc::Sender<ReplanRequest>,
+    mut request: ReplanRequest,
+    stop: &AtomicBool,
+    tick: Duration,
+) -> bool {
+    loop {
+        match replan_tx.try_send(request) {
+            Ok(()) => return true,
+            Err(TrySendError::Closed(_)) => return false,
+            Err(TrySendError::Full(unsent)) => {
+                if stop.load(Ordering::Acquire) {
+                    tracing::warn!(
+                        repo_id = %unsent.repo_id,
+                        "replan channel full while stopping; window dropped"
+                    );
+                    return true;
+                }
+                request = unsent;
+                thread::sleep(tick);
+            }
+        }
+    }
+}
+
+fn route_event(roots: &mut [WatchedRoot], path: &Path, action: &str, now: Instant) {
+    let Some(root) = roots
+        .iter_mut()
+        .filter(|root| path.starts_with(&root.canonical_root) || path.starts_with(&root.root_path))
+        .max_by_key(|root| root.canonical_root.components().count())
+    else {
+        return;
+    };
+    let relative = path
+        .strip_prefix(&root.canonical_root)
+        .or_else(|_| path.strip_prefix(&root.root_path))
+        .unwrap_or(path);
+    let relative = relative_path_string(relative);
+    if relative.is_empty() || root.ignore_stack.iter().any(|rule| rule.matches(&relative)) {
+        return;
+    }
+    root.debouncer.observe(relative, action, now);
+}
+
+fn relative_path_string(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+const fn action_label(kind: &EventKind) -> Option<&'static str> {
+    match kind {
+        EventKind::Create(_) => Some("Create"),
+        EventKind::Modify(ModifyKind::Name(_)) => Some("Rename"),
+        EventKind::Modify(_) => Some("Modify"),
+        EventKind::Remove(_) => Some("Remove"),
+        EventKind::Access(_) | EventKind::Any | EventKind::Other => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coalesces_events_within_debounce_interval() {
+        let start = Instant::now();
+        let mut debouncer = LatencyDebouncer::new(100, 10);
+        debouncer.observe("src/lib.rs", "Create", start);
+        debouncer.observe("src/lib.rs", "Modify", start + Duration::from_millis(4));
+        debouncer.observe("README.md", "Modify", start + Duration::from_millis(5));
+        debouncer.observe("src/lib.rs", "Modify", start + Duration::from_millis(30));
+
+        let window = debouncer
+            .flush(start + Duration::from_millis(48))
+            .expect("window should flush");
+        let summary: Vec<_> = window
+            .events
+            .iter()
+            .map(|event| (event.path.as_str(), event.action.as_str(), event.latency_ms))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("src/lib.rs", "Modify", 48),
+                ("README.md", "Modify", 43),
+                ("src/lib.rs", "Modify", 18),
+            ]
+        );
+        assert_eq!(window.events_observed, 4);
+        assert_eq!(window.queue_depth, 3);
+        assert_eq!(window.max_latency_ms, 48);
+        assert!(debouncer.flush(start + Duration::from_millis(60)).is_none());
+    }
+
+    #[test]
+    fn window_is_ready_after_quiet_period_or_window_elapsed() {
+        let start = Instant::now();
+        let mut debouncer = LatencyDebouncer::new(50, 10);
+        assert!(!debouncer.is_ready(start));
+
+        debouncer.observe("a.rs", "Modify", start);
+        assert!(!debouncer.is_ready(start + Duration::from_millis(5)));
+        assert!(debouncer.is_ready(start + Duration::from_millis(10)));
+
+        for offset in (0..60).step_by(5) {
+            debouncer.observe("b.rs", "Modify", start + Duration::from_millis(offset));
+        }
+        assert!(debouncer.is_ready(start + Duration::from_millis(56)));
+    }
+}
@@ -0,0 +1,197 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use ingestion_workspace::{
+    EntryRejection, EnumeratorConfig, ExtractionLimits, RejectedEntry, TelemetrySink,
+    WorkspaceEnumerator,
+};
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../../tests/fixtures/archives")
+        .join(name)
+}
+
+fn enumerator(archive_limits: ExtractionLimits) -> WorkspaceEnumerator {
+    WorkspaceEnumerator::new(EnumeratorConfig {
+        archive_limits,
+        ..EnumeratorConfig::default()
+    })
+    .with_telemetry(Arc::new(TelemetrySink::default()))
+}
+
+fn rejected(path: &str, rejection: EntryRejection) -> RejectedEntry {
+    RejectedEntry {
+        archive: None,
+        path: path.into(),
+        rejection,
+    }
+}
+
+#[test]
+fn traversal_absolute_and_link_entries_are_rejected_before_writing() {
+    let scratch = tempfile::tempdir().expect("scratch dir");
+    let dest = scratch.path().join("out");
+    let enumerator = enumerator(ExtractionLimits::default());
+
+    let extraction = enumerator
+        .extract_archive(&fixture("zip-slip.tar.zst"), &dest)
+        .expect("extract");
+    assert_eq!(
+        extraction.extracted,
+        [
+            "metadata.json",
+            "safe/README.txt",
+            "safe/link/escape-through-link.txt",
+        ]
+    );
+    assert_eq!(
+        extraction.rejected,
+        [
+            rejected("safe/nested/../inside.txt", EntryRejection::ParentTraversal),
+            rejected("../escape-parent.txt", EntryRejection::ParentTraversal),
+            rejected(
+                "../../../../tmp/escape-deep.txt",
+                EntryRejection::ParentTraversal
+            ),
+            rejected("/tmp/escape-absolute.txt", EntryRejection::AbsolutePath),
+            rejected(
+                "safe/../../escape-normalized.txt",
+                EntryRejection::ParentTraversal
+            ),
+            rejected("..\\escape-windows.txt", EntryRejection::ParentTraversal),
+            rejected(
+                "safe/link",
+                EntryRejection::Link {
+                    target: "../../outside".into()
+                }
+            ),
+        ]
+    );
+    // With the link never created, the file behind it lands inside the root.
+    assert!(dest.join("safe/link/escape-through-link.txt").is_file());
+    assert!(!scratch.path().join("escape-parent.txt").exists());
+    assert!(!scratch.path().join("outside").exists());
+
+    let events = enumerator.telemetry().expect("telemetry attached").events();
+    assert_eq!(
+        events
+            .iter()
+            .filter(|event| event.kind == "workspace.archive.rejected")
+            .count(),
+        7
+    );
+    assert!(events
+        .iter()
+        .any(|event| event.message == "/tmp/escape-absolute.txt: absolute path"));
+}
+
+#[test]
+fn nested_archives_beyond_nesting_max_are_not_opened() {
+    let dest = tempfile::tempdir().expect("dest dir");
+    let extraction = enumerator(ExtractionLimits::default())
+        .extract_archive(&fixture("nested.tar.zst"), dest.path())
+        .expect("extract");
+    assert_eq!(extraction.nesting_depth, 2);
+    assert_eq!(
+        extraction.extracted,
+        [
+            "metadata.json",
+            "README.txt",
+            "level-1.tar/README.txt",
+            "level-1.tar/level-2.tar/README.txt",
+        ]
+    );
+    assert_eq!(
+        extraction.rejected,
+        [RejectedEntry {
+            archive: Some("level-1.tar/level-2.tar".into()),
+            path: "level-3.tar".into(),
+            rejection: EntryRejection::NestingExceeded { depth: 3, limit: 2 },
+        }]
+    );
+    assert!(!dest
+        .path()
+        .join("level-1.tar/level-2.tar/level-3.tar")
+        .exists());
+
+    let flat = tempfile::tempdir().expect("dest dir");
+    let extraction = enumerator(ExtractionLimits {
+        nesting_max: 0,
+        ..ExtractionLimits::default()
+    })
+    .extract_archive(&fixture("nested.tar.zst"), flat.path())
+    .expect("extract");
+    assert_eq!(extraction.extracted, ["metadata.json", "README.txt"]);
+    assert_eq!(
+        extraction.rejected[0].rejection,
+        EntryRejection::NestingExceeded { depth: 1, limit: 0 }
+    );
+}
+
+#[test]
+fn files_over_the_byte_quotas_are_rejected_and_removed() {
+    let dest = tempfile::tempdir().expect("dest dir");
+    let extraction = WorkspaceEnumerator::new(EnumeratorConfig {
+        max_file_bytes: Some(100),
+        ..EnumeratorConfig::default()
+    })
+    .extract_archive(&fixture("nested.tar.zst"), dest.path())
+    .expect("extract");
+    assert_eq!(
+        extraction.rejected[0],
+        rejected("metadata.json", EntryRejection::FileTooLarge { limit: 100 })
+    );
+    assert_eq!(extraction.extracted[0], "README.txt");
+    assert!(!dest.path().join("metadata.json").exists());
+
+    let capped = tempfile::tempdir().expect("dest dir");
+    let extraction = WorkspaceEnumerator::new(EnumeratorConfig {
+        max_total_bytes: Some(250),
+        ..EnumeratorConfig::default()
+    })
+    .extract_archive(&fixture("nested.tar.zst"), capped.path())
+    .expect("extract");
+    assert_eq!(extraction.extracted[0], "metadata.json");
+    assert!(!extraction.extracted.iter().any(|path| path == "README.txt"));
+    assert!(extraction.bytes <= 250);
+    assert_eq!(
+        extraction.rejected[0],
+        rejected(
+            "README.txt",
+            EntryRejection::TotalBytesExceeded { limit: 250 }
+        )
+    );
+    assert!(!capped.path().join("README.txt").exists());
+}
+
+#[test]
+fn over_long_names_and_paths_are_rejected() {
+    let dest = tempfile::tempdir().expect("dest dir");
+    let extraction = enumerator(ExtractionLimits {
+        path_bytes_max: 512,
+        ..ExtractionLimits::default()
+    })
+    .extract_archive(&fixture("long-path.tar.zst"), dest.path())
+    .expect("extract");
+
+    let first = &extraction.rejected[0];
+    assert!(first.path.starts_with("nnnn"));
+    assert!(matches!(
+        first.rejection,
+        EntryRejection::ComponentTooLong { limit: 255, bytes } if bytes > 255
+    ));
+    assert!(extraction.rejected[1..].iter().all(|entry| matches!(
+        entry.rejection,
+        EntryRejection::PathTooLong { limit: 512, bytes } if bytes > 512
+    )));
+    assert_eq!(extraction.extracted, ["metadata.json"]);
+    assert_eq!(extraction.rejected.len(), 4096);
+
+    let roomy = tempfile::tempdir().expect("dest dir");
+    let extraction = enumerator(ExtractionLimits::default())
+        .extract_archive(&fixture("long-path.tar.zst"), roomy.path())
+        .expect("extract");
+    assert_eq!(extraction.rejected.len(), 1);
+    assert_eq!(extraction.extracted.len(), 4096);
+}
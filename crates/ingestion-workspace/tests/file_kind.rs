@@ -0,0 +1,87 @@
+use std::fs;
+
+use ingestion_workspace::{
+    EnumeratorConfig, FileKind, RegistrySnapshot, RepoType, WorkspaceEnumerator, WorkspaceFile,
+    WorkspaceRecord,
+};
+
+#[test]
+fn detects_kinds_from_extension_and_content() {
+    let cases = [
+        ("src/lib.rs", b"pub fn a() {}".as_slice(), FileKind::Rust),
+        ("docs/README.md", b"# Title", FileKind::Markdown),
+        ("Cargo.lock", b"version = 3", FileKind::Lockfile),
+        ("web/package-lock.json", b"{}", FileKind::Lockfile),
+        (
+            "src/schema.rs",
+            b"// @generated by diesel\npub mod schema {}",
+            FileKind::Generated,
+        ),
+        (
+            "assets/logo.png",
+            b"\x89PNG\r\n\x1a\n\0\0",
+            FileKind::Binary,
+        ),
+        (
+            "scripts/build",
+            b"#!/usr/bin/env bash\necho hi",
+            FileKind::Shell,
+        ),
+        (
+            "bin/tool",
+            b"#!/usr/bin/env python3\nprint(1)",
+            FileKind::Python,
+        ),
+        (".gitignore", b"target/", FileKind::Unknown),
+        ("config/app.YML", b"key: value", FileKind::Yaml),
+    ];
+    for (path, content, expected) in cases {
+        assert_eq!(FileKind::detect(path, content), expected, "{path}");
+    }
+    assert_eq!(
+        serde_json::to_string(&FileKind::TypeScript).unwrap(),
+        "\"typescript\""
+    );
+}
+
+#[test]
+fn enumerator_annotates_fixture_and_on_disk_files() {
+    let workspace = tempfile::tempdir().expect("workspace dir");
+    let state = tempfile::tempdir().expect("state dir");
+    let root = workspace.path();
+    fs::write(root.join("main.rs"), "fn main() {}").unwrap();
+    fs::write(root.join("blob.bin"), [0_u8, 159, 146, 150]).unwrap();
+
+    let mut fixture_file = WorkspaceFile::new("notes.md", "# notes");
+    fixture_file.file_kind = FileKind::Unknown;
+    let snapshot = RegistrySnapshot::new(vec![WorkspaceRecord {
+        repo_id: "repo-kinds".into(),
+        root_path: root.to_path_buf(),
+        repo_type: RepoType::Git,
+        manifest_cursor: None,
+        ignore_rules: vec![],
+        archives: vec![],
+        latency_windows: vec![],
+        files: vec![fixture_file],
+        tenant_id: None,
+        generated_code: None,
+    }]);
+    let enumerator = WorkspaceEnumerator::new(EnumeratorConfig::default());
+
+    let described = enumerator.scan(&snapshot).expect("scan");
+    assert_eq!(described[0].files[0].file_kind, FileKind::Markdown);
+
+    let scanned = enumerator
+        .scan_incremental(&snapshot, state.path())
+        .expect("incremental scan");
+    let kinds: Vec<_> = scanned[0]
+        .descriptor
+        .files
+        .iter()
+        .map(|file| (file.path.as_str(), file.file_kind))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![("blob.bin", FileKind::Binary), ("main.rs", FileKind::Rust)]
+    );
+}
@@ -0,0 +1,187 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use ingestion_workspace::{
+    EnumeratorConfig, FileKind, FileOrigin, GeneratedCodePolicy, RegistrySnapshot, RepoType,
+    TelemetrySink, WorkspaceEnumerator, WorkspaceFile, WorkspaceRecord,
+};
+
+fn record(root: &Path, generated_code: Option<GeneratedCodePolicy>) -> WorkspaceRecord {
+    WorkspaceRecord {
+        repo_id: "repo-generated".into(),
+        root_path: root.to_path_buf(),
+        repo_type: RepoType::Git,
+        manifest_cursor: None,
+        ignore_rules: vec![],
+        archives: vec![],
+        latency_windows: vec![],
+        files: vec![],
+        tenant_id: None,
+        generated_code,
+    }
+}
+
+fn write(root: &Path, path: &str, content: &str) {
+    let path = root.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+fn scanned(enumerator: &WorkspaceEnumerator, record: WorkspaceRecord, state: &Path) -> Vec<String> {
+    let scans = enumerator
+        .scan_incremental(&RegistrySnapshot::new(vec![record]), state)
+        .expect("scan");
+    scans[0].commit(state).expect("commit index");
+    scans[0]
+        .descriptor
+        .files
+        .iter()
+        .map(|file| file.path.clone())
+        .collect()
+}
+
+#[test]
+fn detects_origin_from_paths_and_markers() {
+    let cases = [
+        (
+            "src/lib.rs",
+            b"pub fn a() {}".as_slice(),
+            FileOrigin::Authored,
+        ),
+        ("node_modules/left-pad/index.js", b"", FileOrigin::Vendored),
+        ("vendor/github.com/x/y.go", b"", FileOrigin::Vendored),
+        ("target/debug/build.rs", b"", FileOrigin::Generated),
+        ("dist/bundle.js", b"", FileOrigin::Generated),
+        ("src/build/mod.rs", b"", FileOrigin::Authored),
+        ("tools/dist/release.py", b"", FileOrigin::Authored),
+        ("dist/node_modules/a.js", b"", FileOrigin::Vendored),
+        ("static/app.min.js", b"", FileOrigin::Generated),
+        ("api/service.pb.go", b"", FileOrigin::Generated),
+        (
+            "src/schema.rs",
+            b"// @generated by diesel\npub mod schema {}",
+            FileOrigin::Generated,
+        ),
+        ("src/target.rs", b"", FileOrigin::Authored),
+        ("build", b"", FileOrigin::Authored),
+    ];
+    for (path, content, expected) in cases {
+        assert_eq!(
+            FileOrigin::detect(path, FileKind::detect(path, content)),
+            expected,
+            "{path}"
+        );
+        assert_eq!(WorkspaceFile::from_bytes(path, content).origin, expected);
+    }
+    assert_eq!(
+        serde_json::to_string(&GeneratedCodePolicy::Include).unwrap(),
+        "\"include\""
+    );
+}
+
+#[test]
+fn scans_exclude_generated_and_vendored_files_by_default() {
+    let workspace = tempfile::tempdir().expect("workspace dir");
+    let state = tempfile::tempdir().expect("state dir");
+    let root = workspace.path();
+    write(root, "src/lib.rs", "pub fn a() {}");
+    write(root, "src/schema.rs", "// @generated\npub mod schema {}");
+    write(
+        root,
+        "node_modules/left-pad/index.js",
+        "module.exports = 1;",
+    );
+    write(root, "target/debug/out.rs", "fn main() {}");
+    write(root, "src/build/mod.rs", "pub fn build() {}");
+    write(root, "static/app.min.js", "var a=1;");
+
+    let telemetry = Arc::new(TelemetrySink::default());
+    let enumerator = WorkspaceEnumerator::new(EnumeratorConfig::default())
+        .with_telemetry(Arc::clone(&telemetry));
+    assert_eq!(
+        scanned(&enumerator, record(root, None), state.path()),
+        ["src/build/mod.rs", "src/lib.rs"]
+    );
+    let events = |kind: &str| {
+        let mut messages: Vec<_> = telemetry
+            .events()
+            .into_iter()
+            .filter(|event| event.kind == kind)
+            .map(|event| event.message)
+            .collect();
+        messages.sort();
+        messages
+    };
+    assert_eq!(
+        events("workspace.generated.skipped"),
+        ["node_modules (vendored)", "target (generated)"]
+    );
+    assert_eq!(
+        events("workspace.generated.excluded"),
+        ["src/schema.rs (generated)", "static/app.min.js (generated)"]
+    );
+    // Marked files stay out of the index, so a rescan reports no changes.
+    let rescan = enumerator
+        .scan_incremental(
+            &RegistrySnapshot::new(vec![record(root, None)]),
+            state.path(),
+        )
+        .expect("rescan");
+    assert!(rescan[0].changes.is_empty());
+
+    let mut fixture = record(root, None);
+    fixture.files = vec![
+        WorkspaceFile::new("README.md", "# repo"),
+        WorkspaceFile::new("third_party/zlib/zlib.h", "/* zlib */"),
+    ];
+    let described = enumerator
+        .scan(&RegistrySnapshot::new(vec![fixture]))
+        .expect("scan");
+    let paths: Vec<_> = described[0].files.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(paths, ["README.md"]);
+}
+
+#[test]
+fn per_repo_override_wins_over_the_enumerator_default() {
+    let workspace = tempfile::tempdir().expect("workspace dir");
+    let root = workspace.path();
+    write(root, "src/lib.rs", "pub fn a() {}");
+    write(root, "vendor/dep/lib.rs", "pub fn dep() {}");
+
+    let excluding = WorkspaceEnumerator::new(EnumeratorConfig::default());
+    let state = tempfile::tempdir().expect("state dir");
+    let included = excluding
+        .scan_incremental(
+            &RegistrySnapshot::new(vec![record(root, Some(GeneratedCodePolicy::Include))]),
+            state.path(),
+        )
+        .expect("scan");
+    let files: Vec<_> = included[0]
+        .descriptor
+        .files
+        .iter()
+        .map(|file| (file.path.as_str(), file.origin))
+        .collect();
+    assert_eq!(
+        files,
+        [
+            ("src/lib.rs", FileOrigin::Authored),
+            ("vendor/dep/lib.rs", FileOrigin::Vendored),
+        ]
+    );
+
+    let including = WorkspaceEnumerator::new(EnumeratorConfig {
+        generated_code: GeneratedCodePolicy::Include,
+        ..EnumeratorConfig::default()
+    });
+    let state = tempfile::tempdir().expect("state dir");
+    assert_eq!(
+        scanned(
+            &including,
+            record(root, Some(GeneratedCodePolicy::Exclude)),
+            state.path()
+        ),
+        ["src/lib.rs"]
+    );
+}
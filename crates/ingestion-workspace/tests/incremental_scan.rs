@@ -0,0 +1,131 @@
+use std::fs;
+use std::path::Path;
+
+use ingestion_workspace::{
+    EnumeratorConfig, IgnoreRule, IgnoreSource, RegistrySnapshot, RepoType, WorkspaceEnumerator,
+    WorkspaceIndex, WorkspaceRecord,
+};
+
+fn snapshot(root: &Path) -> RegistrySnapshot {
+    RegistrySnapshot::new(vec![WorkspaceRecord {
+        repo_id: "repo-incremental".into(),
+        root_path: root.to_path_buf(),
+        repo_type: RepoType::Git,
+        manifest_cursor: None,
+        ignore_rules: vec![IgnoreRule::new(IgnoreSource::Git, "target")],
+        archives: vec![],
+        latency_windows: vec![],
+        files: vec![],
+        tenant_id: None,
+        generated_code: None,
+    }])
+}
+
+#[test]
+fn incremental_scan_reports_only_changed_files() {
+    let workspace = tempfile::tempdir().expect("workspace dir");
+    let state = tempfile::tempdir().expect("state dir");
+    let root = workspace.path();
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::create_dir_all(root.join("target")).unwrap();
+    fs::write(root.join("src/lib.rs"), "pub fn a() {}").unwrap();
+    fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+    fs::write(root.join("README.md"), "# readme").unwrap();
+    fs::write(root.join("target/out.bin"), "ignored").unwrap();
+
+    let enumerator = WorkspaceEnumerator::new(EnumeratorConfig::default());
+    let first = enumerator
+        .scan_incremental(&snapshot(root), state.path())
+        .expect("first scan");
+    assert_eq!(first.len(), 1);
+    assert_eq!(
+        first[0].changes.added,
+        vec!["README.md", "src/lib.rs", "src/main.rs"]
+    );
+    assert_eq!(first[0].descriptor.files().len(), 3);
+    first[0].commit(state.path()).expect("commit index");
+
+    let unchanged = enumerator
+        .scan_incremental(&snapshot(root), state.path())
+        .expect("second scan");
+    assert!(unchanged[0].changes.is_empty());
+    assert!(unchanged[0].descriptor.files().is_empty());
+    unchanged[0].commit(state.path()).expect("commit index");
+
+    fs::write(root.join("src/lib.rs"), "pub fn a() { /* changed */ }").unwrap();
+    fs::remove_file(root.join("src/main.rs")).unwrap();
+    fs::write(root.join("docs.md"), "docs").unwrap();
+
+    let changed = enumerator
+        .scan_incremental(&snapshot(root), state.path())
+        .expect("third scan");
+    let changes = &changed[0].changes;
+    assert_eq!(changes.added, vec!["docs.md"]);
+    assert_eq!(changes.modified, vec!["src/lib.rs"]);
+    assert_eq!(changes.removed, vec!["src/main.rs"]);
+    let paths: Vec<_> = changed[0]
+        .descriptor
+        .files()
+        .iter()
+        .map(|file| file.path.as_str())
+        .collect();
+    assert_eq!(paths, vec!["docs.md", "src/lib.rs"]);
+    changed[0].commit(state.path()).expect("commit index");
+
+    let index = WorkspaceIndex::load(state.path(), "repo-incremental").expect("index loads");
+    let indexed: Vec<_> = index.entries.keys().map(String::as_str).collect();
+    assert_eq!(indexed, vec!["README.md", "docs.md", "src/lib.rs"]);
+}
+
+#[test]
+fn uncommitted_scan_reports_changes_again() {
+    let workspace = tempfile::tempdir().expect("workspace dir");
+    let state = tempfile::tempdir().expect("state dir");
+    let root = workspace.path();
+    fs::write(root.join("lib.rs"), "pub fn a() {}").unwrap();
+
+    let enumerator = WorkspaceEnumerator::new(EnumeratorConfig::default());
+    let first = enumerator
+        .scan_incremental(&snapshot(root), state.path())
+        .expect("first scan");
+    assert_eq!(first[0].changes.added, vec!["lib.rs"]);
+
+    // Processing failed, so the scan was never committed.
+    let retried = enumerator
+        .scan_incremental(&snapshot(root), state.path())
+        .expect("retried scan");
+    assert_eq!(retried[0].changes.added, vec!["lib.rs"]);
+    assert_eq!(retried[0].descriptor.files().len(), 1);
+}
+
+#[test]
+fn parallel_scan_matches_sequential_ordering() {
+    let workspace = tempfile::tempdir().expect("workspace dir");
+    let root = workspace.path();
+    for dir in 0..8 {
+        fs::create_dir_all(root.join(format!("mod{dir}"))).unwrap();
+        for file in 0..16 {
+            fs::write(
+                root.join(format!("mod{dir}/file{file}.rs")),
+                format!("// {dir}/{file}"),
+            )
+            .unwrap();
+        }
+    }
+
+    let scan_with = |workers| {
+        let state = tempfile::tempdir().expect("state dir");
+        let enumerator = WorkspaceEnumerator::new(EnumeratorConfig {
+            workers,
+            ..EnumeratorConfig::default()
+        });
+        enumerator
+            .scan_incremental(&snapshot(root), state.path())
+            .expect("scan should succeed")
+            .remove(0)
+    };
+    let sequential = scan_with(1);
+    let parallel = scan_with(6);
+    assert_eq!(sequential.descriptor.files.len(), 128);
+    assert_eq!(parallel, sequential);
+}
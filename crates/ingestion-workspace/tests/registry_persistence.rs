@@ -0,0 +1,213 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use ingestion_workspace::commands::{self, DEREGISTER_COMMAND, LIST_COMMAND, REGISTER_COMMAND};
+use ingestion_workspace::{
+    IgnoreRule, IgnoreSource, RegistrySnapshot, RepoType, WorkspaceError, WorkspaceRecord,
+    WorkspaceRegistry, REGISTRY_VERSION,
+};
+use runtime_router::{CommandRouter, HandlerRouter, RouterCommand, SessionContext};
+use serde_json::json;
+
+fn record(repo_id: &str, root: &Path) -> WorkspaceRecord {
+    WorkspaceRecord {
+        repo_id: repo_id.into(),
+        root_path: root.to_path_buf(),
+        repo_type: RepoType::Git,
+        manifest_cursor: None,
+        ignore_rules: vec![],
+        archives: vec![],
+        latency_windows: vec![],
+        files: vec![],
+        tenant_id: None,
+        generated_code: None,
+    }
+}
+
+#[test]
+fn registry_membership_survives_reopen() {
+    let dir = tempfile::tempdir().expect("state dir");
+    let path = dir.path().join("registry.json");
+
+    let registry = WorkspaceRegistry::open(&path).expect("open empty registry");
+    registry
+        .register_workspace(record("repo-a", Path::new("/srv/repo-a")))
+        .expect("register repo-a");
+    registry
+        .register_workspace(record("repo-b", Path::new("/srv/repo-b")))
+        .expect("register repo-b");
+    assert!(matches!(
+        registry.register_workspace(record("repo-a", Path::new("/srv/other"))),
+        Err(WorkspaceError::DuplicateWorkspace(_))
+    ));
+    registry
+        .deregister_workspace("repo-a")
+        .expect("deregister repo-a");
+
+    let reopened = WorkspaceRegistry::open(&path).expect("reopen registry");
+    let ids: Vec<_> = reopened
+        .snapshot()
+        .workspaces
+        .into_iter()
+        .map(|record| record.repo_id)
+        .collect();
+    assert_eq!(ids, vec!["repo-b"]);
+
+    let stored: serde_json::Value =
+        serde_json::from_slice(&fs::read(&path).unwrap()).expect("registry is json");
+    assert_eq!(stored["version"], json!(REGISTRY_VERSION));
+}
+
+#[test]
+fn toml_registries_round_trip() {
+    let dir = tempfile::tempdir().expect("state dir");
+    let path = dir.path().join("registry.toml");
+    let mut tenanted = record("repo-toml", Path::new("/srv/repo-toml"));
+    tenanted.tenant_id = Some("tenant-a".into());
+    tenanted.ignore_rules = vec![IgnoreRule::new(IgnoreSource::Git, "target")];
+
+    let registry = WorkspaceRegistry::open(&path).expect("open empty registry");
+    registry
+        .register_workspace(tenanted.clone())
+        .expect("register workspace");
+
+    let stored: toml::Table = fs::read_to_string(&path)
+        .unwrap()
+        .parse()
+        .expect("registry is toml");
+    assert_eq!(
+        stored["version"].as_integer(),
+        Some(i64::from(REGISTRY_VERSION))
+    );
+    let reopened = WorkspaceRegistry::open(&path).expect("reopen registry");
+    assert_eq!(reopened.snapshot().workspaces, vec![tenanted]);
+}
+
+#[test]
+fn unversioned_registries_are_migrated() {
+    let dir = tempfile::tempdir().expect("state dir");
+    let path = dir.path().join("legacy.json");
+    let legacy = json!([record("repo-legacy", Path::new("/srv/legacy"))]);
+    fs::write(&path, serde_json::to_vec(&legacy).unwrap()).unwrap();
+
+    let snapshot = RegistrySnapshot::load(&path).expect("legacy registry loads");
+    assert_eq!(snapshot.workspaces[0].repo_id, "repo-legacy");
+
+    fs::write(&path, r#"{"version": 99, "workspaces": []}"#).unwrap();
+    assert!(matches!(
+        RegistrySnapshot::load(&path),
+        Err(WorkspaceError::Registry(_))
+    ));
+}
+
+#[tokio::test]
+async fn router_commands_mutate_registry() {
+    let dir = tempfile::tempdir().expect("state dir");
+    let registry = Arc::new(WorkspaceRegistry::open(dir.path().join("registry.json")).unwrap());
+    let mut router = HandlerRouter::new();
+    commands::register_commands(&mut router, Arc::clone(&registry));
+
+    let admin = SessionContext::new("admin", vec!["admin".into()]);
+    let reader = SessionContext::new("reader", vec!["search".into()]);
+    let register = RouterCommand::new(
+        REGISTER_COMMAND,
+        json!({ "repo_id": "repo-cmd", "root_path": "/srv/repo-cmd" }),
+    );
+
+    let err = router
+        .dispatch(reader.clone(), register.clone())
+        .await
+        .expect_err("registration requires admin");
+    assert_eq!(err.status_code(), 401);
+
+    router
+        .dispatch(admin.clone(), register.clone())
+        .await
+        .expect("admin registers workspace");
+    let err = router
+        .dispatch(admin.clone(), register.clone())
+        .await
+        .expect_err("duplicate registration rejected");
+    assert_eq!(err.status_code(), 400);
+
+    // The duplicate fails the atomic batch, so the first registration is
+    // undone.
+    let batch = router
+        .dispatch(
+            admin.clone(),
+            RouterCommand::batch([
+                RouterCommand::new(
+                    REGISTER_COMMAND,
+                    json!({ "repo_id": "repo-tmp", "root_path": "/srv/repo-tmp" }),
+                ),
+                register,
+            ])
+            .with_atomic(),
+        )
+        .await
+        .expect("atomic batch");
+    assert_eq!(batch.payload["committed"], json!(false));
+    assert_eq!(registry.snapshot().workspaces.len(), 1);
+
+    router
+        .dispatch(
+            admin.clone(),
+            RouterCommand::new(
+                REGISTER_COMMAND,
+                json!({ "repo_id": "repo-two", "root_path": "/srv/repo-two" }),
+            ),
+        )
+        .await
+        .expect("admin registers a second workspace");
+    let listed = router
+        .dispatch(
+            reader.clone(),
+            RouterCommand::new(LIST_COMMAND, json!({ "page_size": 1 })),
+        )
+        .await
+        .expect("list workspaces");
+    assert_eq!(
+        listed.payload["workspaces"][0]["repo_id"],
+        json!("repo-cmd")
+    );
+    let page = listed.page.expect("list is paged");
+    assert_eq!(page.total_estimate, Some(2));
+    let listed = router
+        .dispatch(
+            reader,
+            RouterCommand::new(LIST_COMMAND, json!({ "cursor": page.next_cursor })),
+        )
+        .await
+        .expect("list second page");
+    assert_eq!(
+        listed.payload["workspaces"],
+        json!([{
+            "repo_id": "repo-two",
+            "root_path": "/srv/repo-two",
+            "repo_type": "Git",
+            "tenant_id": null,
+        }])
+    );
+    assert_eq!(listed.page.and_then(|page| page.next_cursor), None);
+
+    router
+        .dispatch(
+            admin.clone(),
+            RouterCommand::new(DEREGISTER_COMMAND, json!({ "repo_id": "repo-cmd" })),
+        )
+        .await
+        .expect("deregister workspace");
+    registry
+        .deregister_workspace("repo-two")
+        .expect("deregister second workspace");
+    let err = router
+        .dispatch(
+            admin,
+            RouterCommand::new(DEREGISTER_COMMAND, json!({ "repo_id": "repo-cmd" })),
+        )
+        .await
+        .expect_err("unknown workspace");
+    assert_eq!(err.status_code(), 404);
+    assert!(registry.snapshot().workspaces.is_empty());
+}
@@ -0,0 +1,125 @@
+#![cfg(unix)]
+
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::Path;
+use std::sync::Arc;
+
+use ingestion_workspace::{
+    EnumeratorConfig, RegistrySnapshot, RepoType, SymlinkPolicy, TelemetrySink,
+    WorkspaceEnumerator, WorkspaceError, WorkspaceRecord,
+};
+
+fn snapshot(root: &Path) -> RegistrySnapshot {
+    RegistrySnapshot::new(vec![WorkspaceRecord {
+        repo_id: "repo-links".into(),
+        root_path: root.to_path_buf(),
+        repo_type: RepoType::Git,
+        manifest_cursor: None,
+        ignore_rules: vec![],
+        archives: vec![],
+        latency_windows: vec![],
+        files: vec![],
+        tenant_id: None,
+        generated_code: None,
+    }])
+}
+
+fn enumerator(symlinks: SymlinkPolicy) -> WorkspaceEnumerator {
+    WorkspaceEnumerator::new(EnumeratorConfig {
+        symlinks,
+        ..EnumeratorConfig::default()
+    })
+    .with_telemetry(Arc::new(TelemetrySink::default()))
+}
+
+fn event_kinds(enumerator: &WorkspaceEnumerator) -> Vec<String> {
+    enumerator
+        .telemetry()
+        .expect("telemetry attached")
+        .events()
+        .into_iter()
+        .map(|event| event.kind)
+        .collect()
+}
+
+fn scanned_paths(enumerator: &WorkspaceEnumerator, root: &Path, state: &Path) -> Vec<String> {
+    let scans = enumerator
+        .scan_incremental(&snapshot(root), state)
+        .expect("scan should succeed");
+    scans[0]
+        .descriptor
+        .files
+        .iter()
+        .map(|file| file.path.clone())
+        .collect()
+}
+
+#[test]
+fn follow_within_root_ingests_internal_links_and_reports_escapes() {
+    let workspace = tempfile::tempdir().expect("workspace dir");
+    let outside = tempfile::tempdir().expect("outside dir");
+    let state = tempfile::tempdir().expect("state dir");
+    let root = workspace.path();
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("src/lib.rs"), "pub fn a() {}").unwrap();
+    fs::write(outside.path().join("passwd"), "root:x:0:0").unwrap();
+    symlink(root.join("src/lib.rs"), root.join("alias.rs")).unwrap();
+    symlink(outside.path().join("passwd"), root.join("passwd")).unwrap();
+    symlink(root.join("src"), root.join("src/loop")).unwrap();
+
+    let enumerator = enumerator(SymlinkPolicy::FollowWithinRoot);
+    let paths = scanned_paths(&enumerator, root, state.path());
+    assert_eq!(paths, vec!["alias.rs", "src/lib.rs"]);
+
+    let kinds = event_kinds(&enumerator);
+    assert!(kinds.contains(&"workspace.symlink.escape".to_string()));
+    assert!(kinds.contains(&"workspace.symlink.cycle".to_string()));
+}
+
+#[test]
+fn skip_policy_ignores_links_and_error_policy_rejects_them() {
+    let workspace = tempfile::tempdir().expect("workspace dir");
+    let state = tempfile::tempdir().expect("state dir");
+    let root = workspace.path();
+    fs::write(root.join("lib.rs"), "pub fn a() {}").unwrap();
+    symlink(root.join("lib.rs"), root.join("alias.rs")).unwrap();
+
+    let paths = scanned_paths(&enumerator(SymlinkPolicy::Skip), root, state.path());
+    assert_eq!(paths, vec!["lib.rs"]);
+
+    let err = enumerator(SymlinkPolicy::Error)
+        .scan_incremental(&snapshot(root), state.path())
+        .expect_err("links should be rejected");
+    assert!(matches!(err, WorkspaceError::Symlink(_)));
+}
+
+#[test]
+fn hardlinked_files_are_ingested_once() {
+    let workspace = tempfile::tempdir().expect("workspace dir");
+    let state = tempfile::tempdir().expect("state dir");
+    let root = workspace.path();
+    fs::write(root.join("a.rs"), "pub fn a() {}").unwrap();
+    fs::hard_link(root.join("a.rs"), root.join("b.rs")).unwrap();
+
+    let enumerator = enumerator(SymlinkPolicy::Skip);
+    let paths = scanned_paths(&enumerator, root, state.path());
+    assert_eq!(paths, vec!["a.rs"]);
+    assert_eq!(event_kinds(&enumerator)[0], "workspace.hardlink.duplicate");
+}
+
+#[test]
+fn directories_linked_ahead_of_their_target_are_ingested_once() {
+    let workspace = tempfile::tempdir().expect("workspace dir");
+    let state = tempfile::tempdir().expect("state dir");
+    let root = workspace.path();
+    fs::create_dir_all(root.join("real")).unwrap();
+    fs::write(root.join("real/lib.rs"), "pub fn a() {}").unwrap();
+    // `alias` sorts before `real`, so the walk reaches the link first.
+    symlink(root.join("real"), root.join("alias")).unwrap();
+
+    let enumerator = enumerator(SymlinkPolicy::FollowWithinRoot);
+    let paths = scanned_paths(&enumerator, root, state.path());
+    assert_eq!(paths, vec!["alias/lib.rs"]);
+    assert!(event_kinds(&enumerator).contains(&"workspace.symlink.duplicate".to_string()));
+}
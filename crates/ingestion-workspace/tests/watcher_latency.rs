@@ -98,11 +98,14 @@ fn watcher_latency_budget_matches_golden() {
             .map(LatencyWindowFixture::to_latency_window)
             .collect(),
         files: vec![],
+        tenant_id: None,
+        generated_code: None,
     };
     let snapshot = RegistrySnapshot::new(vec![record]);
     let enumerator = WorkspaceEnumerator::new(EnumeratorConfig {
         global_ignores: vec![IgnoreRule::new(IgnoreSource::Global, "node_modules")],
         sandbox_ignores: vec![IgnoreRule::new(IgnoreSource::Sandbox, "tmp")],
+        ..EnumeratorConfig::default()
     });
 
     let descriptors = enumerator
@@ -140,6 +143,8 @@ fn ignore_stack_merges_deterministically() {
         archives: vec![],
         latency_windows: vec![],
         files: vec![],
+        tenant_id: None,
+        generated_code: None,
     };
     let snapshot = RegistrySnapshot::new(vec![record]);
     let enumerator = WorkspaceEnumerator::new(EnumeratorConfig {
@@ -148,6 +153,7 @@ fn ignore_stack_merges_deterministically() {
             IgnoreRule::new(IgnoreSource::Global, ".git"),
         ],
         sandbox_ignores: vec![IgnoreRule::new(IgnoreSource::Sandbox, "tmp")],
+        ..EnumeratorConfig::default()
     });
 
     let descriptors = enumerator
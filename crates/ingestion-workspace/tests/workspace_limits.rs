@@ -0,0 +1,80 @@
+use std::fs;
+use std::path::PathBuf;
+
+use ingestion_workspace::{
+    EnumeratorConfig, RegistrySnapshot, RepoType, WorkspaceEnumerator, WorkspaceError,
+    WorkspaceFile, WorkspaceRecord,
+};
+
+fn snapshot(root: PathBuf, files: Vec<WorkspaceFile>) -> RegistrySnapshot {
+    RegistrySnapshot::new(vec![WorkspaceRecord {
+        repo_id: "repo-limits".into(),
+        root_path: root,
+        repo_type: RepoType::Git,
+        manifest_cursor: None,
+        ignore_rules: vec![],
+        archives: vec![],
+        latency_windows: vec![],
+        files,
+        tenant_id: None,
+        generated_code: None,
+    }])
+}
+
+#[test]
+fn scan_reports_structured_limit_diagnostics() {
+    let files = vec![
+        WorkspaceFile::new("small.rs", "fn a() {}"),
+        WorkspaceFile::new("large.rs", "x".repeat(64)),
+    ];
+    let snapshot = snapshot(PathBuf::from("/srv/repo-limits"), files);
+
+    let within = WorkspaceEnumerator::new(EnumeratorConfig {
+        max_file_bytes: Some(64),
+        max_total_bytes: Some(128),
+        max_files: Some(2),
+        ..EnumeratorConfig::default()
+    });
+    within.scan(&snapshot).expect("limits are inclusive");
+
+    let err = WorkspaceEnumerator::new(EnumeratorConfig {
+        max_file_bytes: Some(32),
+        max_files: Some(1),
+        ..EnumeratorConfig::default()
+    })
+    .scan(&snapshot)
+    .expect_err("caps should be enforced");
+    let WorkspaceError::LimitExceeded { diagnostics } = err else {
+        panic!("expected LimitExceeded, got {err:?}");
+    };
+    assert_eq!(diagnostics.repo_id, "repo-limits");
+    assert_eq!(diagnostics.largest_path.as_deref(), Some("large.rs"));
+    assert_eq!(diagnostics.largest_file_bytes, 64);
+    assert_eq!(diagnostics.total_bytes_observed, 73);
+    assert_eq!(diagnostics.files_observed, 2);
+    assert_eq!(diagnostics.files_limit, Some(1));
+    assert_eq!(diagnostics.total_bytes_limit, None);
+}
+
+#[test]
+fn incremental_scan_enforces_total_bytes_before_reading() {
+    let workspace = tempfile::tempdir().expect("workspace dir");
+    let state = tempfile::tempdir().expect("state dir");
+    fs::write(workspace.path().join("a.txt"), "a".repeat(40)).unwrap();
+    fs::write(workspace.path().join("b.txt"), "b".repeat(40)).unwrap();
+
+    let err = WorkspaceEnumerator::new(EnumeratorConfig {
+        max_total_bytes: Some(64),
+        ..EnumeratorConfig::default()
+    })
+    .scan_incremental(
+        &snapshot(workspace.path().to_path_buf(), vec![]),
+        state.path(),
+    )
+    .expect_err("total bytes cap should trip");
+    let WorkspaceError::LimitExceeded { diagnostics } = err else {
+        panic!("expected LimitExceeded, got {err:?}");
+    };
+    assert_eq!(diagnostics.total_bytes_observed, 80);
+    assert_eq!(diagnostics.largest_path, None);
+}
@@ -0,0 +1,115 @@
+use std::fs;
+use std::time::Duration;
+
+use ingestion_workspace::{
+    IgnoreRule, IgnoreSource, RepoType, WatcherConfig, WorkspaceDescriptor, WorkspaceWatcher,
+};
+
+fn descriptor(root: &std::path::Path) -> WorkspaceDescriptor {
+    WorkspaceDescriptor {
+        repo_id: "repo-watch".into(),
+        root_path: root.to_path_buf(),
+        repo_type: RepoType::Git,
+        manifest_cursor: None,
+        ignore_stack: vec![IgnoreRule::new(IgnoreSource::Editor, "*.swp")],
+        archives: vec![],
+        latency_windows: vec![],
+        files: vec![],
+        tenant_id: None,
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn watcher_emits_replan_request_for_changed_files() {
+    let tmp = tempfile::tempdir().expect("tmpdir");
+    let root = tmp.path().to_path_buf();
+    fs::create_dir_all(root.join("src")).unwrap();
+
+    let (watcher, mut replans) = WorkspaceWatcher::spawn(
+        &[descriptor(&root)],
+        WatcherConfig {
+            window_ms: 200,
+            debounce_ms: 50,
+            channel_capacity: 8,
+        },
+    )
+    .expect("watcher starts");
+
+    fs::write(root.join("src").join("lib.rs"), "pub fn demo() {}").unwrap();
+    fs::write(root.join(".lib.rs.swp"), "swap").unwrap();
+
+    let request = tokio::time::timeout(Duration::from_secs(5), replans.recv())
+        .await
+        .expect("replan request before timeout")
+        .expect("channel open");
+    assert_eq!(request.repo_id, "repo-watch");
+    assert!(request
+        .changed_paths
+        .iter()
+        .any(|path| path == "src/lib.rs"));
+    assert!(request
+        .changed_paths
+        .iter()
+        .all(|path| !path.ends_with(".swp")));
+    assert_eq!(request.window.debounce_ms, 50);
+    assert!(request.window.events_observed as usize >= request.window.events.len());
+    assert_eq!(
+        request.window.max_latency_ms,
+        request
+            .window
+            .events
+            .iter()
+            .map(|event| event.latency_ms)
+            .max()
+            .unwrap_or_default()
+    );
+
+    drop(watcher);
+}
+
+#[test]
+fn dropping_the_watcher_does_not_wait_on_an_undrained_channel() {
+    let tmp = tempfile::tempdir().expect("tmpdir");
+    let root = tmp.path().to_path_buf();
+    let (watcher, replans) = WorkspaceWatcher::spawn(
+        &[descriptor(&root)],
+        WatcherConfig {
+            window_ms: 20,
+            debounce_ms: 5,
+            channel_capacity: 1,
+        },
+    )
+    .expect("watcher starts");
+
+    // Several windows close while nothing drains the one-slot channel.
+    for round in 0..5 {
+        fs::write(root.join(format!("file-{round}.rs")), "fn demo() {}").unwrap();
+        std::thread::sleep(Duration::from_millis(60));
+    }
+
+    let (done_tx, done_rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        drop(watcher);
+        let _ = done_tx.send(());
+    });
+    done_rx
+        .recv_timeout(Duration::from_secs(5))
+        .expect("drop returns while the receiver is still held");
+    drop(replans);
+}
+
+#[test]
+fn ignore_rules_match_components_and_globs() {
+    let target = IgnoreRule::new(IgnoreSource::Git, "target");
+    assert!(target.matches("target/debug/build.log"));
+    assert!(target.matches("crates/a/target/out"));
+    assert!(!target.matches("src/targets.rs"));
+
+    let swap = IgnoreRule::new(IgnoreSource::Editor, "*.swp");
+    assert!(swap.matches("src/.lib.rs.swp"));
+    assert!(!swap.matches("src/lib.rs"));
+
+    let anchored = IgnoreRule::new(IgnoreSource::Custom("repo".into()), "docs/generated");
+    assert!(anchored.matches("docs/generated/index.md"));
+    assert!(!anchored.matches("src/docs/generated/index.md"));
+}
@@ -0,0 +1,120 @@
+//! Wall-clock source shared by the time-dependent runtime components.
+//!
+//! Retry and replay buffers age entries out and token signers stamp and
+//! check expiry against [`Clock::now`] instead of calling
+//! [`SystemTime::now`] directly. Production code uses [`SystemClock`];
+//! tests hand the same component a [`MockClock`] and move time forward
+//! with [`MockClock::advance`] instead of sleeping.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Source of the current wall-clock time.
+pub trait Clock: fmt::Debug + Send + Sync {
+    fn now(&self) -> SystemTime;
+
+    /// [`Clock::now`] in whole seconds since the Unix epoch, as token
+    /// envelopes record it.
+    fn unix_secs(&self) -> u64 {
+        self.now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+    }
+}
+
+/// Clock handle shared between a component and its owner.
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The operating system clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl SystemClock {
+    /// A [`SharedClock`] over the system clock, the default of every
+    /// component taking one.
+    #[must_use]
+    pub fn shared() -> SharedClock {
+        Arc::new(Self)
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Clock that only moves when told to.
+#[derive(Debug)]
+pub struct MockClock {
+    now: Mutex<SystemTime>,
+}
+
+impl MockClock {
+    #[must_use]
+    pub fn new(start: SystemTime) -> Self {
+        Self {
+            now: Mutex::new(start),
+        }
+    }
+
+    /// A clock reading `secs` seconds after the Unix epoch.
+    #[must_use]
+    pub fn at_unix(secs: u64) -> Self {
+        Self::new(UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    /// Move the clock forward by `by`.
+    pub fn advance(&self, by: Duration) {
+        *self.lock() += by;
+    }
+
+    /// Jump to `now`, which may be earlier than the current reading.
+    pub fn set(&self, now: SystemTime) {
+        *self.lock() = now;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, SystemTime> {
+        self.now.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl Default for MockClock {
+    /// 2023-11-14T22:13:20Z, so timestamps in tests are stable.
+    fn default() -> Self {
+        Self::at_unix(1_700_000_000)
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> SystemTime {
+        *self.lock()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mock_clock_moves_only_when_told() {
+        let clock = MockClock::default();
+        let start = clock.now();
+        assert_eq!(clock.now(), start);
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(clock.unix_secs(), 1_700_000_090);
+        clock.set(start);
+        assert_eq!(clock.now(), start);
+    }
+
+    #[test]
+    fn shared_clocks_observe_the_same_mock() {
+        let mock = Arc::new(MockClock::at_unix(10));
+        let shared: SharedClock = mock.clone();
+        mock.advance(Duration::from_secs(5));
+        assert_eq!(shared.unix_secs(), 15);
+        assert!(SystemClock::shared().unix_secs() > 1_700_000_000);
+    }
+}
@@ -0,0 +1,160 @@
+//! The runtime embedded in another process.
+//!
+//! Hosts that load the runtime as a library (language bindings, editors)
+//! have no transport in front of it. They open an [`EmbeddedRuntime`] from
+//! a [`RuntimeConfig`] and dispatch commands straight to its router, as the
+//! one principal the configuration names. Everything the runtime keeps
+//! lives under the configured state directory: the workspace registry, the
+//! scan indexes and the vector store. Besides those commands the router
+//! answers `status` with the store and ingest runs.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use ingestion_embedding::{EmbeddingConfig, EmbeddingGenerator};
+use ingestion_sanitization::{SanitizationConfig, Sanitizer};
+use ingestion_workspace::commands::ADMIN_CAPABILITY;
+use ingestion_workspace::WorkspaceRegistry;
+use runtime_router::{
+    CommandRouter, HandlerRouter, RouterCommand, RouterError, RouterResponse, SessionContext,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use storage_vector::VectorStore;
+
+use crate::{
+    register_commands, register_status, IngestError, PipelineOrchestrator, INGEST_CAPABILITY,
+    SEARCH_CAPABILITY,
+};
+
+/// Settings of an [`EmbeddedRuntime`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuntimeConfig {
+    /// Directory holding everything the runtime persists.
+    pub state_dir: PathBuf,
+    #[serde(default = "default_encoder_id")]
+    pub encoder_id: String,
+    #[serde(default = "default_dimensions")]
+    pub dimensions: usize,
+    /// Principal every command runs as.
+    #[serde(default = "default_principal")]
+    pub principal: String,
+    /// Capabilities of that principal; all of them by default.
+    #[serde(default = "default_capabilities")]
+    pub capabilities: Vec<String>,
+}
+
+fn default_encoder_id() -> String {
+    "local-hash".into()
+}
+
+const fn default_dimensions() -> usize {
+    384
+}
+
+fn default_principal() -> String {
+    "local".into()
+}
+
+fn default_capabilities() -> Vec<String> {
+    vec![
+        ADMIN_CAPABILITY.into(),
+        INGEST_CAPABILITY.into(),
+        SEARCH_CAPABILITY.into(),
+    ]
+}
+
+impl RuntimeConfig {
+    /// Defaults for everything but the state directory.
+    pub fn new(state_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            state_dir: state_dir.into(),
+            encoder_id: default_encoder_id(),
+            dimensions: default_dimensions(),
+            principal: default_principal(),
+            capabilities: default_capabilities(),
+        }
+    }
+
+    /// Load a configuration, choosing the format from the file extension
+    /// (`.toml` or `.json`). A relative `state_dir` is resolved against the
+    /// directory holding the file.
+    pub fn load(path: &Path) -> Result<Self, IngestError> {
+        let source = fs::read_to_string(path)
+            .map_err(|err| IngestError::Config(format!("{}: {err}", path.display())))?;
+        let parsed = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&source).map_err(|err| err.to_string()),
+            Some("json") => serde_json::from_str(&source).map_err(|err| err.to_string()),
+            _ => Err("unsupported config format".to_string()),
+        };
+        let mut config: Self =
+            parsed.map_err(|err| IngestError::Config(format!("{}: {err}", path.display())))?;
+        if config.state_dir.is_relative() {
+            if let Some(parent) = path.parent() {
+                config.state_dir = parent.join(&config.state_dir);
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// The ingest, search and registry commands over state on local disk.
+pub struct EmbeddedRuntime {
+    config: RuntimeConfig,
+    router: HandlerRouter,
+    pipeline: Arc<PipelineOrchestrator>,
+}
+
+impl EmbeddedRuntime {
+    /// Open the state under `config.state_dir`, creating it on first use.
+    pub fn open(config: RuntimeConfig) -> Result<Self, IngestError> {
+        let state_dir = &config.state_dir;
+        let registry = WorkspaceRegistry::open(state_dir.join("registry.json"))?;
+        let sanitizer = Sanitizer::new(SanitizationConfig::default())?;
+        let embedder = EmbeddingGenerator::new(EmbeddingConfig::new(
+            config.encoder_id.clone(),
+            config.dimensions,
+        ));
+        let store = VectorStore::with_fs_root(state_dir.join("vectors"));
+        let pipeline = Arc::new(PipelineOrchestrator::new(
+            Arc::new(registry),
+            Arc::new(sanitizer),
+            Arc::new(embedder),
+            Arc::new(store),
+            state_dir.join("index"),
+        ));
+        let mut router = HandlerRouter::new();
+        register_commands(&mut router, Arc::clone(&pipeline));
+        register_status(&mut router, &pipeline, env!("CARGO_PKG_VERSION"));
+        Ok(Self {
+            config,
+            router,
+            pipeline,
+        })
+    }
+
+    #[must_use]
+    pub fn config(&self) -> &RuntimeConfig {
+        &self.config
+    }
+
+    #[must_use]
+    pub fn pipeline(&self) -> &Arc<PipelineOrchestrator> {
+        &self.pipeline
+    }
+
+    /// Run `command` as the configured principal. Must be called from
+    /// within a tokio runtime, which keeps ingest runs going between calls.
+    pub async fn dispatch(
+        &self,
+        command: &str,
+        payload: Value,
+    ) -> Result<RouterResponse, RouterError> {
+        let ctx = SessionContext::new(&self.config.principal, self.config.capabilities.clone());
+        self.router
+            .dispatch(ctx, RouterCommand::new(command, payload))
+            .await
+    }
+}
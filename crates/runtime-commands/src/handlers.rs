@@ -0,0 +1,319 @@
+//! Handlers behind the commands registered by
+//! [`register_commands`](crate::register_commands).
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use ingestion_workspace::WorkspaceError;
+use runtime_router::{
+    CommandHandler, HandlerRouter, Page, PageRequest, RouterError, RouterResponse, SessionContext,
+    StatusProvider, StatusRegistry,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use storage_vector::FilterExpr;
+
+use crate::{
+    IngestError, PathBoost, PipelineOrchestrator, Query, QueryEngine, QueryError, RunState,
+    CANCEL_COMMAND, DEFAULT_SEARCH_RESULTS, INGEST_CAPABILITY, MAX_SEARCH_DEPTH,
+    MAX_SEARCH_RESULTS, MAX_STATUS_WAIT_MS, SEARCH_CAPABILITY, SEARCH_COMMAND, START_COMMAND,
+    STATUS_COMMAND,
+};
+
+pub(crate) fn register(router: &mut HandlerRouter, pipeline: Arc<PipelineOrchestrator>) {
+    router
+        .register_with_capabilities(
+            START_COMMAND,
+            vec![INGEST_CAPABILITY.into()],
+            Arc::new(StartHandler {
+                pipeline: Arc::clone(&pipeline),
+            }),
+        )
+        .register_with_capabilities(
+            STATUS_COMMAND,
+            vec![INGEST_CAPABILITY.into()],
+            Arc::new(StatusHandler {
+                pipeline: Arc::clone(&pipeline),
+            }),
+        )
+        .register_with_capabilities(
+            CANCEL_COMMAND,
+            vec![INGEST_CAPABILITY.into()],
+            Arc::new(CancelHandler { pipeline }),
+        );
+}
+
+pub(crate) fn register_search(router: &mut HandlerRouter, engine: Arc<QueryEngine>) {
+    router.register_with_capabilities(
+        SEARCH_COMMAND,
+        vec![SEARCH_CAPABILITY.into()],
+        Arc::new(SearchHandler { engine }),
+    );
+}
+
+pub(crate) fn register_status(
+    router: &mut HandlerRouter,
+    pipeline: &Arc<PipelineOrchestrator>,
+    version: &str,
+) -> Arc<StatusRegistry> {
+    let status = Arc::new(StatusRegistry::new(version));
+    status.register(
+        "store",
+        Arc::clone(pipeline.store()) as Arc<dyn StatusProvider>,
+    );
+    status.register("ingest", pipeline.clone());
+    router.register(runtime_router::STATUS_COMMAND, status.clone());
+    status
+}
+
+/// Reports how many runs were started and how many are in each state.
+#[async_trait]
+impl StatusProvider for PipelineOrchestrator {
+    async fn report(&self) -> Result<Value, RouterError> {
+        let runs = self.runs();
+        let count = |state: RunState| runs.iter().filter(|run| run.state == state).count();
+        Ok(json!({
+            "runs": runs.len(),
+            "running": count(RunState::Running),
+            "completed": count(RunState::Completed),
+            "failed": count(RunState::Failed),
+            "cancelled": count(RunState::Cancelled),
+        }))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct StartRequest {
+    repo_id: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct StatusRequest {
+    #[serde(default)]
+    run_id: Option<String>,
+    #[serde(default)]
+    wait_ms: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CancelRequest {
+    run_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchRequest {
+    repo_id: String,
+    /// Tenant owning `repo_id`; defaults to the caller's tenant.
+    #[serde(default)]
+    tenant_id: Option<String>,
+    query: String,
+    /// Page size; `page_size` takes precedence.
+    #[serde(default)]
+    k: Option<usize>,
+    #[serde(default)]
+    filter: Option<FilterExpr>,
+    #[serde(default)]
+    boosts: Vec<PathBoost>,
+    #[serde(flatten)]
+    page: PageRequest,
+}
+
+struct StartHandler {
+    pipeline: Arc<PipelineOrchestrator>,
+}
+
+#[async_trait]
+impl CommandHandler for StartHandler {
+    async fn handle(
+        &self,
+        ctx: &SessionContext,
+        payload: Value,
+    ) -> Result<RouterResponse, RouterError> {
+        let request: StartRequest = parse_payload(payload)?;
+        let record = self
+            .pipeline
+            .workspace(&request.repo_id)
+            .map_err(router_error)?;
+        if !ctx.can_access_tenant(record.tenant_id.as_deref()) {
+            return Err(router_error(
+                WorkspaceError::UnknownWorkspace(request.repo_id).into(),
+            ));
+        }
+        let status = self
+            .pipeline
+            .start(&request.repo_id)
+            .map_err(router_error)?;
+        tracing::info!(
+            principal = %ctx.principal,
+            run_id = %status.run_id,
+            repo_id = %status.repo_id,
+            "ingest run started"
+        );
+        Ok(RouterResponse::ok(to_value(&status)?))
+    }
+}
+
+struct StatusHandler {
+    pipeline: Arc<PipelineOrchestrator>,
+}
+
+#[async_trait]
+impl CommandHandler for StatusHandler {
+    async fn handle(
+        &self,
+        ctx: &SessionContext,
+        payload: Value,
+    ) -> Result<RouterResponse, RouterError> {
+        let request: StatusRequest = if payload.is_null() {
+            StatusRequest::default()
+        } else {
+            parse_payload(payload)?
+        };
+        match request.run_id {
+            Some(run_id) => {
+                let mut updates = self.pipeline.watch(&run_id).map_err(router_error)?;
+                if !ctx.can_access_tenant(updates.borrow().tenant_id.as_deref()) {
+                    return Err(router_error(IngestError::UnknownRun(run_id)));
+                }
+                if let Some(wait_ms) = request.wait_ms {
+                    if updates.borrow().state == RunState::Running {
+                        let wait = Duration::from_millis(wait_ms.min(MAX_STATUS_WAIT_MS));
+                        // A timeout or a finished run both just return the
+                        // latest status.
+                        let _ = tokio::time::timeout(wait, updates.changed()).await;
+                    }
+                }
+                let status = updates.borrow().clone();
+                Ok(RouterResponse::ok(to_value(&status)?))
+            }
+            None => {
+                let runs: Vec<_> = self
+                    .pipeline
+                    .runs()
+                    .into_iter()
+                    .filter(|run| ctx.can_access_tenant(run.tenant_id.as_deref()))
+                    .collect();
+                Ok(RouterResponse::ok(json!({ "runs": to_value(&runs)? })))
+            }
+        }
+    }
+}
+
+struct CancelHandler {
+    pipeline: Arc<PipelineOrchestrator>,
+}
+
+#[async_trait]
+impl CommandHandler for CancelHandler {
+    async fn handle(
+        &self,
+        ctx: &SessionContext,
+        payload: Value,
+    ) -> Result<RouterResponse, RouterError> {
+        let request: CancelRequest = parse_payload(payload)?;
+        let run = self
+            .pipeline
+            .status(&request.run_id)
+            .map_err(router_error)?;
+        if !ctx.can_access_tenant(run.tenant_id.as_deref()) {
+            return Err(router_error(IngestError::UnknownRun(request.run_id)));
+        }
+        let status = self
+            .pipeline
+            .cancel(&request.run_id)
+            .map_err(router_error)?;
+        tracing::info!(
+            principal = %ctx.principal,
+            run_id = %status.run_id,
+            "ingest run cancellation requested"
+        );
+        Ok(RouterResponse::ok(to_value(&status)?))
+    }
+}
+
+struct SearchHandler {
+    engine: Arc<QueryEngine>,
+}
+
+#[async_trait]
+impl CommandHandler for SearchHandler {
+    async fn handle(
+        &self,
+        ctx: &SessionContext,
+        payload: Value,
+    ) -> Result<RouterResponse, RouterError> {
+        let request: SearchRequest = parse_payload(payload)?;
+        let tenant_id = request.tenant_id.or_else(|| ctx.tenant_id.clone());
+        if !ctx.can_access_tenant(tenant_id.as_deref()) {
+            return Err(router_error(
+                WorkspaceError::UnknownWorkspace(request.repo_id).into(),
+            ));
+        }
+        let offset = request.page.offset()?;
+        if offset >= MAX_SEARCH_DEPTH {
+            return Err(RouterError::InvalidRequest {
+                detail: format!("search pages end after {MAX_SEARCH_DEPTH} results"),
+            });
+        }
+        let size = request
+            .page
+            .size(
+                request.k.unwrap_or(DEFAULT_SEARCH_RESULTS),
+                MAX_SEARCH_RESULTS,
+            )
+            .min(MAX_SEARCH_DEPTH - offset);
+        // Each page re-runs the query; one extra hit tells whether another
+        // page follows.
+        let query = Query {
+            repo_id: request.repo_id,
+            tenant_id,
+            text: request.query,
+            k: offset + size + 1,
+            filter: request.filter,
+            boosts: request.boosts,
+        };
+        let hits = self.engine.search(&query).await.map_err(|err| match err {
+            QueryError::EmptyQuery => RouterError::InvalidRequest {
+                detail: err.to_string(),
+            },
+            other => internal(&other),
+        })?;
+        let end = offset + size;
+        let next = (hits.len() > end && end < MAX_SEARCH_DEPTH).then_some(end);
+        let hits: Vec<_> = hits.into_iter().skip(offset).take(size).collect();
+        Ok(RouterResponse::paged(
+            json!({ "hits": to_value(&hits)? }),
+            Page::at_offset(next, size, None),
+        ))
+    }
+}
+
+fn parse_payload<T: serde::de::DeserializeOwned>(payload: Value) -> Result<T, RouterError> {
+    serde_json::from_value(payload).map_err(|err| RouterError::InvalidRequest {
+        detail: err.to_string(),
+    })
+}
+
+fn to_value<T: serde::Serialize>(value: &T) -> Result<Value, RouterError> {
+    serde_json::to_value(value).map_err(|err| internal(&err))
+}
+
+fn internal(err: &dyn std::fmt::Display) -> RouterError {
+    RouterError::Internal {
+        detail: err.to_string(),
+    }
+}
+
+fn router_error(err: IngestError) -> RouterError {
+    match err {
+        IngestError::UnknownRun(_)
+        | IngestError::Workspace(WorkspaceError::UnknownWorkspace(_)) => RouterError::NotFound {
+            detail: err.to_string(),
+        },
+        IngestError::AlreadyRunning(_) => RouterError::InvalidRequest {
+            detail: err.to_string(),
+        },
+        other => internal(&other),
+    }
+}
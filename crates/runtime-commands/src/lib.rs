@@ -0,0 +1,75 @@
+//! Ready-made router commands binding the ingestion pipeline and the vector
+//! store to the [`CommandRouter`](runtime_router::CommandRouter) contract.
+
+use std::sync::Arc;
+
+use runtime_router::{HandlerRouter, StatusRegistry};
+
+pub mod embedded;
+mod handlers;
+pub mod pipeline;
+pub mod query;
+
+pub use embedded::{EmbeddedRuntime, RuntimeConfig};
+pub use ingestion_embedding::Embedder;
+pub use pipeline::{IngestError, PipelineOrchestrator, RunState, RunStatus};
+pub use query::{PathBoost, Query, QueryEngine, QueryError, QueryHit, RankingConfig};
+
+/// Command starting an ingest run (`{ repo_id }`).
+pub const START_COMMAND: &str = "ingest.start";
+/// Command reporting one run (`{ run_id, wait_ms? }`) or every run (`{}`).
+/// With `wait_ms`, a running run's status is returned after its next
+/// progress update, or once the wait runs out.
+pub const STATUS_COMMAND: &str = "ingest.status";
+/// Command cancelling a run (`{ run_id }`).
+pub const CANCEL_COMMAND: &str = "ingest.cancel";
+/// Command searching a repository (`{ repo_id, query, k?, filter?, boosts?,
+/// cursor?, page_size? }`), a page of hits at a time.
+pub const SEARCH_COMMAND: &str = "search.query";
+/// Command registering a workspace; see
+/// [`ingestion_workspace::commands::REGISTER_COMMAND`].
+pub const REGISTER_COMMAND: &str = ingestion_workspace::commands::REGISTER_COMMAND;
+
+/// Capability required for the `ingest.*` commands.
+pub const INGEST_CAPABILITY: &str = "ingest";
+/// Capability required for `search.query`.
+pub const SEARCH_CAPABILITY: &str = "search";
+
+/// Results `search.query` returns when the request does not say.
+pub const DEFAULT_SEARCH_RESULTS: usize = 10;
+/// Most results one `search.query` page returns.
+pub const MAX_SEARCH_RESULTS: usize = 100;
+/// Deepest result `search.query` pages reach.
+pub const MAX_SEARCH_DEPTH: usize = 1_000;
+/// Longest wait one `ingest.status` long poll may ask for.
+pub const MAX_STATUS_WAIT_MS: u64 = 30_000;
+
+/// Register the ingest, search and workspace registry commands on `router`.
+/// Searches rank with the default [`RankingConfig`].
+pub fn register_commands(router: &mut HandlerRouter, pipeline: Arc<PipelineOrchestrator>) {
+    ingestion_workspace::commands::register_commands(router, Arc::clone(pipeline.registry()));
+    let engine = QueryEngine::new(
+        Arc::clone(pipeline.embedder()),
+        Arc::clone(pipeline.store()),
+    );
+    handlers::register(router, pipeline);
+    register_search(router, Arc::new(engine));
+}
+
+/// Register the runtime-wide [`runtime_router::STATUS_COMMAND`] on `router`,
+/// reporting `version` along with the vector store (`store`: mode, usage
+/// and quotas) and the ingest runs (`ingest`) of `pipeline`. Adapters and
+/// other subsystems add themselves to the returned registry.
+pub fn register_status(
+    router: &mut HandlerRouter,
+    pipeline: &Arc<PipelineOrchestrator>,
+    version: &str,
+) -> Arc<StatusRegistry> {
+    handlers::register_status(router, pipeline, version)
+}
+
+/// Register `search.query` on `router` backed by `engine`, replacing any
+/// earlier registration.
+pub fn register_search(router: &mut HandlerRouter, engine: Arc<QueryEngine>) {
+    handlers::register_search(router, engine);
+}
@@ -0,0 +1,500 @@
+//! Ingestion runs over registered workspaces.
+//!
+//! A run scans the workspace root incrementally, deletes the records of
+//! files that changed or disappeared, then plans, sanitizes, embeds and
+//! stores the changed files batch by batch. Runs can be cancelled between
+//! batches; a run that fails or is cancelled puts the scan index back, so
+//! the next run picks up the same changes.
+//!
+//! Each stage updates the run's [`RunStatus`] as it goes: the scan counts
+//! the files to ingest, planning the files and chunks planned, embedding
+//! the chunks embedded and the store step the vector bytes written. The estimate
+//! of the time left extrapolates from the share of files planned so far.
+//! [`PipelineOrchestrator::watch`] follows those updates as they happen.
+//!
+//! Records of a workspace owned by a tenant are stored under the
+//! [`tenant_namespace`] of its repository, so tenants sharing a store never
+//! read or overwrite each other's records.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use ingestion_embedding::{EmbeddingBatch, EmbeddingError};
+use ingestion_manifest::{persist_batch, record_key};
+use ingestion_planning::{ChunkPlanner, PlannerConfig, PlanningError};
+use ingestion_sanitization::{QuarantineStore, SanitizationError, SanitizedChunk, Sanitizer};
+use ingestion_workspace::{
+    EnumeratorConfig, RegistrySnapshot, WorkspaceEnumerator, WorkspaceError, WorkspaceFile,
+    WorkspaceRecord, WorkspaceRegistry,
+};
+use serde::Serialize;
+use storage_vector::{tenant_namespace, Store, VectorMetadata, VectorStore};
+use thiserror::Error;
+use tokio::sync::watch;
+use uuid::Uuid;
+
+use crate::Embedder;
+
+/// Vector metadata attribute holding the chunk's source span.
+pub const SOURCE_SPAN_ATTRIBUTE: &str = "source_span";
+
+/// Keys listed per page while deleting the records of a file.
+const DELETE_PAGE: usize = 256;
+
+#[derive(Debug, Error)]
+pub enum IngestError {
+    #[error("workspace error: {0}")]
+    Workspace(#[from] WorkspaceError),
+    #[error("planning error: {0}")]
+    Planning(#[from] PlanningError),
+    #[error("sanitization error: {0}")]
+    Sanitization(#[from] SanitizationError),
+    #[error("embedding error: {0}")]
+    Embedding(#[from] EmbeddingError),
+    #[error("vector store error: {0}")]
+    Store(String),
+    #[error("ingest run '{0}' is not known")]
+    UnknownRun(String),
+    #[error("workspace '{0}' already has an ingest run in progress")]
+    AlreadyRunning(String),
+    #[error("ingest run cancelled")]
+    Cancelled,
+    #[error("runtime config error: {0}")]
+    Config(String),
+}
+
+fn store_error(err: impl ToString) -> IngestError {
+    IngestError::Store(err.to_string())
+}
+
+/// Lifecycle of an ingest run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunState {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// What an ingest run has done so far.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RunStatus {
+    pub run_id: String,
+    pub repo_id: String,
+    /// Tenant owning the workspace.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    pub state: RunState,
+    /// Files added or modified since the previous run.
+    pub files_changed: usize,
+    pub files_removed: usize,
+    /// Changed files whose chunks have all been planned.
+    pub files_planned: usize,
+    pub chunks_planned: usize,
+    pub chunks_embedded: usize,
+    /// Chunks withheld by screening or quarantined for review.
+    pub chunks_held: usize,
+    /// Records of changed or removed files deleted before re-embedding.
+    pub records_deleted: usize,
+    /// Bytes of embedding vectors written to the store.
+    pub bytes_stored: u64,
+    /// Estimated milliseconds until the run finishes, once at least one
+    /// file has been planned.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eta_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+struct Run {
+    cancel: AtomicBool,
+    started: Instant,
+    status: watch::Sender<RunStatus>,
+}
+
+impl Run {
+    fn new(record: &WorkspaceRecord) -> Self {
+        let (status, _) = watch::channel(RunStatus {
+            run_id: Uuid::new_v4().to_string(),
+            repo_id: record.repo_id.clone(),
+            tenant_id: record.tenant_id.clone(),
+            state: RunState::Running,
+            files_changed: 0,
+            files_removed: 0,
+            files_planned: 0,
+            chunks_planned: 0,
+            chunks_embedded: 0,
+            chunks_held: 0,
+            records_deleted: 0,
+            bytes_stored: 0,
+            eta_ms: None,
+            error: None,
+        });
+        Self {
+            cancel: AtomicBool::new(false),
+            started: Instant::now(),
+            status,
+        }
+    }
+
+    fn status(&self) -> RunStatus {
+        self.status.borrow().clone()
+    }
+
+    fn update(&self, apply: impl FnOnce(&mut RunStatus)) {
+        self.status.send_modify(apply);
+    }
+
+    /// Extrapolate the time left from the share of changed files planned.
+    fn estimate(&self, status: &mut RunStatus) {
+        if status.files_planned == 0 {
+            return;
+        }
+        let left = status.files_changed.saturating_sub(status.files_planned);
+        let elapsed = self.started.elapsed().as_millis() as u64;
+        status.eta_ms = Some(elapsed * left as u64 / status.files_planned as u64);
+    }
+
+    fn check_cancelled(&self) -> Result<(), IngestError> {
+        if self.cancel.load(Ordering::SeqCst) {
+            return Err(IngestError::Cancelled);
+        }
+        Ok(())
+    }
+
+    fn finish(&self, result: Result<(), IngestError>) -> RunStatus {
+        self.update(|status| {
+            status.eta_ms = None;
+            match result {
+                Ok(()) => status.state = RunState::Completed,
+                Err(IngestError::Cancelled) => status.state = RunState::Cancelled,
+                Err(err) => {
+                    status.state = RunState::Failed;
+                    status.error = Some(err.to_string());
+                }
+            }
+        });
+        let status = self.status();
+        tracing::info!(
+            run_id = %status.run_id,
+            repo_id = %status.repo_id,
+            state = ?status.state,
+            files_changed = status.files_changed,
+            chunks_embedded = status.chunks_embedded,
+            bytes_stored = status.bytes_stored,
+            elapsed_ms = self.started.elapsed().as_millis() as u64,
+            "ingest run finished"
+        );
+        status
+    }
+}
+
+/// Runs the ingestion pipeline for registered workspaces and tracks the
+/// runs it started.
+pub struct PipelineOrchestrator {
+    registry: Arc<WorkspaceRegistry>,
+    enumerator: WorkspaceEnumerator,
+    planner: ChunkPlanner,
+    sanitizer: Arc<Sanitizer>,
+    quarantine: Option<Arc<QuarantineStore>>,
+    embedder: Arc<dyn Embedder>,
+    store: Arc<VectorStore>,
+    /// Directory holding the incremental scan index of each workspace.
+    state_dir: PathBuf,
+    /// Every run started, oldest first.
+    runs: Mutex<Vec<Arc<Run>>>,
+}
+
+impl PipelineOrchestrator {
+    pub fn new(
+        registry: Arc<WorkspaceRegistry>,
+        sanitizer: Arc<Sanitizer>,
+        embedder: Arc<dyn Embedder>,
+        store: Arc<VectorStore>,
+        state_dir: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            registry,
+            enumerator: WorkspaceEnumerator::new(EnumeratorConfig::default()),
+            planner: ChunkPlanner::new(PlannerConfig::default().with_default_profiles()),
+            sanitizer,
+            quarantine: None,
+            embedder,
+            store,
+            state_dir: state_dir.into(),
+            runs: Mutex::new(Vec::new()),
+        }
+    }
+
+    #[must_use]
+    pub fn with_enumerator(mut self, enumerator: WorkspaceEnumerator) -> Self {
+        self.enumerator = enumerator;
+        self
+    }
+
+    #[must_use]
+    pub fn with_planner(mut self, planner: ChunkPlanner) -> Self {
+        self.planner = planner;
+        self
+    }
+
+    /// Hold chunks flagged for review in `quarantine` instead of embedding
+    /// them.
+    #[must_use]
+    pub fn with_quarantine(mut self, quarantine: Arc<QuarantineStore>) -> Self {
+        self.quarantine = Some(quarantine);
+        self
+    }
+
+    #[must_use]
+    pub fn registry(&self) -> &Arc<WorkspaceRegistry> {
+        &self.registry
+    }
+
+    #[must_use]
+    pub fn embedder(&self) -> &Arc<dyn Embedder> {
+        &self.embedder
+    }
+
+    #[must_use]
+    pub fn store(&self) -> &Arc<VectorStore> {
+        &self.store
+    }
+
+    /// Ingest `repo_id` on a tokio task, returning the new run's status.
+    /// Must be called from within a tokio runtime.
+    pub fn start(self: &Arc<Self>, repo_id: &str) -> Result<RunStatus, IngestError> {
+        let run = self.begin(repo_id)?;
+        let status = run.status();
+        let this = Arc::clone(self);
+        tokio::spawn(async move {
+            let result = this.run_pipeline(&run).await;
+            run.finish(result);
+        });
+        Ok(status)
+    }
+
+    /// Ingest `repo_id` and wait for the run to finish.
+    pub async fn execute(&self, repo_id: &str) -> Result<RunStatus, IngestError> {
+        let run = self.begin(repo_id)?;
+        let result = self.run_pipeline(&run).await;
+        Ok(run.finish(result))
+    }
+
+    /// Status of the run `run_id`.
+    pub fn status(&self, run_id: &str) -> Result<RunStatus, IngestError> {
+        self.find(run_id).map(|run| run.status())
+    }
+
+    /// Follow the status of the run `run_id`; the receiver sees every
+    /// update until the run finishes.
+    pub fn watch(&self, run_id: &str) -> Result<watch::Receiver<RunStatus>, IngestError> {
+        self.find(run_id).map(|run| run.status.subscribe())
+    }
+
+    /// Status of every run started, oldest first.
+    pub fn runs(&self) -> Vec<RunStatus> {
+        self.lock_runs().iter().map(|run| run.status()).collect()
+    }
+
+    /// Ask the run `run_id` to stop after the batch in progress. Finished
+    /// runs are left as they are.
+    pub fn cancel(&self, run_id: &str) -> Result<RunStatus, IngestError> {
+        let run = self.find(run_id)?;
+        run.cancel.store(true, Ordering::SeqCst);
+        Ok(run.status())
+    }
+
+    fn lock_runs(&self) -> std::sync::MutexGuard<'_, Vec<Arc<Run>>> {
+        self.runs.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn find(&self, run_id: &str) -> Result<Arc<Run>, IngestError> {
+        self.lock_runs()
+            .iter()
+            .find(|run| run.status().run_id == run_id)
+            .cloned()
+            .ok_or_else(|| IngestError::UnknownRun(run_id.to_string()))
+    }
+
+    /// Register a run for `repo_id`, which must be registered and not
+    /// already being ingested.
+    fn begin(&self, repo_id: &str) -> Result<Arc<Run>, IngestError> {
+        let record = self.workspace(repo_id)?;
+        let mut runs = self.lock_runs();
+        if runs.iter().any(|run| {
+            let status = run.status();
+            status.repo_id == repo_id && status.state == RunState::Running
+        }) {
+            return Err(IngestError::AlreadyRunning(repo_id.to_string()));
+        }
+        let run = Arc::new(Run::new(&record));
+        runs.push(Arc::clone(&run));
+        Ok(run)
+    }
+
+    /// Registry record of `repo_id`.
+    pub fn workspace(&self, repo_id: &str) -> Result<WorkspaceRecord, IngestError> {
+        self.registry
+            .snapshot()
+            .workspaces
+            .into_iter()
+            .find(|record| record.repo_id == repo_id)
+            .ok_or_else(|| WorkspaceError::UnknownWorkspace(repo_id.to_string()).into())
+    }
+
+    async fn run_pipeline(&self, run: &Run) -> Result<(), IngestError> {
+        let repo_id = &run.status().repo_id;
+        let record = self.workspace(repo_id)?;
+        let namespace = tenant_namespace(record.tenant_id.as_deref(), repo_id);
+        let enumerator = self.enumerator.clone();
+        let state_dir = self.state_dir.clone();
+        let scan = tokio::task::spawn_blocking(move || {
+            enumerator.scan_incremental(&RegistrySnapshot::new(vec![record]), &state_dir)
+        })
+        .await
+        .map_err(|err| WorkspaceError::Enumeration(err.to_string()))??
+        .pop()
+        .ok_or_else(|| WorkspaceError::UnknownWorkspace(repo_id.to_string()))?;
+        let changes = &scan.changes;
+        run.update(|status| {
+            status.files_changed = changes.added.len() + changes.modified.len();
+            status.files_removed = changes.removed.len();
+        });
+
+        let encoder_id = self.embedder.encoder_id().to_string();
+        for path in changes.modified.iter().chain(&changes.removed) {
+            run.check_cancelled()?;
+            let deleted = self.delete_file_records(&namespace, repo_id, &encoder_id, path)?;
+            run.update(|status| status.records_deleted += deleted);
+        }
+
+        let files: HashMap<&str, &WorkspaceFile> = scan
+            .descriptor
+            .files
+            .iter()
+            .map(|file| (file.path.as_str(), file))
+            .collect();
+        let mut plans = self.planner.plan_iter(&scan.descriptor)?;
+        while let Some(batch) = plans.next_chunks() {
+            run.check_cancelled()?;
+            let files_planned = batch
+                .next
+                .as_ref()
+                .map_or(scan.descriptor.files.len(), |cursor| cursor.file_index);
+            run.update(|status| {
+                status.files_planned = files_planned;
+                status.chunks_planned += batch.chunks.len();
+            });
+            let sanitized = self.sanitizer.apply_batch(&batch.chunks)?;
+            let total = sanitized.chunks.len();
+            let mut chunks: Vec<SanitizedChunk> = sanitized
+                .chunks
+                .into_iter()
+                .filter(|chunk| !chunk.is_skipped())
+                .collect();
+            if let Some(quarantine) = &self.quarantine {
+                chunks = quarantine.admit(chunks)?;
+            }
+            let mut stored = 0;
+            if !chunks.is_empty() {
+                let embedded = self.embedder.encode_batch(&chunks).await?;
+                stored = self.index_batch(&namespace, repo_id, &embedded, &files)?;
+            }
+            run.update(|status| {
+                status.chunks_embedded += chunks.len();
+                status.chunks_held += total - chunks.len();
+                status.bytes_stored += stored;
+                run.estimate(status);
+            });
+        }
+        self.store.persist_vectors().map_err(store_error)?;
+        // Only a fully processed scan advances the index; a failed run
+        // reports the same changes next time.
+        scan.commit(&self.state_dir)?;
+        Ok(())
+    }
+
+    /// Delete the records `encoder_id` stored in `namespace` for the chunks
+    /// of `path`.
+    fn delete_file_records(
+        &self,
+        namespace: &str,
+        repo_id: &str,
+        encoder_id: &str,
+        path: &str,
+    ) -> Result<usize, IngestError> {
+        let prefix = record_key(encoder_id, &format!("{repo_id}::{path}::"));
+        let mut deleted = 0;
+        let mut cursor = None;
+        loop {
+            let page = self
+                .store
+                .list_keys(namespace, &prefix, cursor.as_deref(), DELETE_PAGE)
+                .map_err(store_error)?;
+            for key in &page.keys {
+                self.store.delete(namespace, key).map_err(store_error)?;
+                deleted += 1;
+            }
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => return Ok(deleted),
+            }
+        }
+    }
+
+    /// Store the records of `batch` in `namespace` and index their vectors
+    /// for search, returning the bytes of vectors written.
+    fn index_batch(
+        &self,
+        namespace: &str,
+        repo_id: &str,
+        batch: &EmbeddingBatch,
+        files: &HashMap<&str, &WorkspaceFile>,
+    ) -> Result<u64, IngestError> {
+        persist_batch(self.store.as_ref(), namespace, batch).map_err(store_error)?;
+        let mut stored = 0;
+        let embedded_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        for (chunk, vector) in batch.chunks.iter().zip(&batch.vectors) {
+            stored += (vector.len() * std::mem::size_of::<f32>()) as u64;
+            let mut metadata = VectorMetadata::new(&chunk.plan_id)
+                .with_repo_id(repo_id)
+                .with_chunk_hash(&chunk.hash)
+                .with_embedded_at(embedded_at)
+                .with_attribute(SOURCE_SPAN_ATTRIBUTE, &chunk.source_span);
+            if let Some(file) = plan_path(repo_id, &chunk.plan_id).and_then(|path| files.get(path))
+            {
+                metadata = metadata
+                    .with_path(&file.path)
+                    .with_language(file.file_kind.as_str());
+                if let Some(mtime_ms) = file.mtime_ms {
+                    metadata = metadata.with_modified_at(mtime_ms / 1000);
+                }
+            }
+            self.store
+                .insert_vector(
+                    namespace,
+                    &record_key(&batch.encoder_id, &chunk.plan_id),
+                    vector.clone(),
+                    metadata,
+                )
+                .map_err(store_error)?;
+        }
+        Ok(stored)
+    }
+}
+
+/// File path of a plan id of the form `<repo_id>::<path>::<index>`.
+fn plan_path<'a>(repo_id: &str, plan_id: &'a str) -> Option<&'a str> {
+    plan_id
+        .strip_prefix(repo_id)?
+        .strip_prefix("::")?
+        .rsplit_once("::")
+        .map(|(path, _)| path)
+}
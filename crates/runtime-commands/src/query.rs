@@ -0,0 +1,236 @@
+//! Code search over the vector store.
+//!
+//! A query embeds its text with the same [`Embedder`] ingestion used,
+//! fetches the nearest chunks from the [`VectorStore`], then re-ranks them:
+//! recently modified files and paths the caller boosts move up. Candidates
+//! are over-fetched so re-ranking can promote hits the plain similarity
+//! order would have cut.
+
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+use ingestion_embedding::EmbeddingError;
+use ingestion_sanitization::SanitizedChunk;
+use serde::{Deserialize, Serialize};
+use storage_vector::{tenant_namespace, FilterExpr, SearchFilter, SearchHit, VectorStore};
+use thiserror::Error;
+
+use crate::pipeline::SOURCE_SPAN_ATTRIBUTE;
+use crate::Embedder;
+
+#[derive(Debug, Error)]
+pub enum QueryError {
+    #[error("query text must not be empty")]
+    EmptyQuery,
+    #[error("embedding error: {0}")]
+    Embedding(#[from] EmbeddingError),
+    #[error("embedder returned no vector for the query")]
+    NoVector,
+    #[error("vector store error: {0}")]
+    Store(String),
+}
+
+/// Weights applied on top of vector similarity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RankingConfig {
+    /// Scale of the recency bonus, which decays from 1.0 for the newest
+    /// file among the candidates.
+    pub recency: f32,
+    /// Age relative to the newest candidate at which the recency bonus
+    /// halves.
+    pub recency_half_life_secs: u64,
+    /// Candidates fetched per requested result before re-ranking.
+    pub oversample: usize,
+}
+
+impl Default for RankingConfig {
+    fn default() -> Self {
+        Self {
+            recency: 0.05,
+            recency_half_life_secs: 7 * 24 * 60 * 60,
+            oversample: 4,
+        }
+    }
+}
+
+/// Bonus added to hits whose path starts with `prefix`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PathBoost {
+    pub prefix: String,
+    pub weight: f32,
+}
+
+/// One search over a repository.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub repo_id: String,
+    /// Tenant owning the repository; selects the store namespace searched.
+    pub tenant_id: Option<String>,
+    pub text: String,
+    /// Results to return.
+    pub k: usize,
+    /// Only hits passing this expression are considered.
+    pub filter: Option<FilterExpr>,
+    pub boosts: Vec<PathBoost>,
+}
+
+impl Query {
+    pub fn new(repo_id: impl Into<String>, text: impl Into<String>, k: usize) -> Self {
+        Self {
+            repo_id: repo_id.into(),
+            tenant_id: None,
+            text: text.into(),
+            k,
+            filter: None,
+            boosts: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_tenant_id(mut self, tenant_id: impl Into<String>) -> Self {
+        self.tenant_id = Some(tenant_id.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_filter(mut self, filter: FilterExpr) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    #[must_use]
+    pub fn with_boost(mut self, prefix: impl Into<String>, weight: f32) -> Self {
+        self.boosts.push(PathBoost {
+            prefix: prefix.into(),
+            weight,
+        });
+        self
+    }
+}
+
+/// A ranked search result.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QueryHit {
+    pub key: String,
+    /// Similarity plus the re-ranking bonuses; results are ordered by it.
+    pub score: f32,
+    /// Vector similarity alone.
+    pub similarity: f32,
+    pub plan_id: String,
+    pub path: Option<String>,
+    pub language: Option<String>,
+    pub source_span: Option<String>,
+    /// Last modification of the source file, in seconds since the Unix epoch.
+    pub modified_at: Option<u64>,
+}
+
+/// Embeds queries and ranks the chunks of a [`VectorStore`] against them.
+pub struct QueryEngine {
+    embedder: Arc<dyn Embedder>,
+    store: Arc<VectorStore>,
+    ranking: RankingConfig,
+}
+
+impl QueryEngine {
+    pub fn new(embedder: Arc<dyn Embedder>, store: Arc<VectorStore>) -> Self {
+        Self {
+            embedder,
+            store,
+            ranking: RankingConfig::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_ranking(mut self, ranking: RankingConfig) -> Self {
+        self.ranking = ranking;
+        self
+    }
+
+    #[must_use]
+    pub fn ranking(&self) -> &RankingConfig {
+        &self.ranking
+    }
+
+    /// Up to `query.k` hits, best first.
+    pub async fn search(&self, query: &Query) -> Result<Vec<QueryHit>, QueryError> {
+        if query.text.trim().is_empty() {
+            return Err(QueryError::EmptyQuery);
+        }
+        let chunk = SanitizedChunk {
+            plan_id: "query".into(),
+            source_span: String::new(),
+            hash: String::new(),
+            tenant_id: query.tenant_id.clone(),
+            scrubbed_payload: query.text.clone(),
+            redaction_log: Vec::new(),
+            findings: Vec::new(),
+            validation_status: "clean".into(),
+            suppressed: Vec::new(),
+        };
+        let vector = self
+            .embedder
+            .encode_batch(&[chunk])
+            .await?
+            .vectors
+            .pop()
+            .ok_or(QueryError::NoVector)?;
+        let filter = query.filter.clone().map(SearchFilter::expr);
+        let candidates = self
+            .store
+            .search(
+                &tenant_namespace(query.tenant_id.as_deref(), &query.repo_id),
+                &vector,
+                query.k.saturating_mul(self.ranking.oversample.max(1)),
+                filter.as_ref(),
+            )
+            .map_err(|err| QueryError::Store(err.to_string()))?;
+        Ok(self.rerank(candidates, &query.boosts, query.k))
+    }
+
+    /// Order `candidates` by similarity plus recency and path bonuses,
+    /// keeping the best `k`.
+    fn rerank(&self, candidates: Vec<SearchHit>, boosts: &[PathBoost], k: usize) -> Vec<QueryHit> {
+        let newest = candidates
+            .iter()
+            .filter_map(|hit| hit.metadata.modified_at)
+            .max();
+        let half_life = self.ranking.recency_half_life_secs.max(1) as f32;
+        let mut hits: Vec<QueryHit> = candidates
+            .into_iter()
+            .map(|hit| {
+                let mut score = hit.score;
+                if let (Some(modified), Some(newest)) = (hit.metadata.modified_at, newest) {
+                    let age = newest.saturating_sub(modified) as f32;
+                    score += self.ranking.recency * 0.5_f32.powf(age / half_life);
+                }
+                if let Some(path) = &hit.metadata.path {
+                    score += boosts
+                        .iter()
+                        .filter(|boost| path.starts_with(&boost.prefix))
+                        .map(|boost| boost.weight)
+                        .sum::<f32>();
+                }
+                let mut metadata = hit.metadata;
+                QueryHit {
+                    key: hit.key,
+                    score,
+                    similarity: hit.score,
+                    source_span: metadata.attributes.remove(SOURCE_SPAN_ATTRIBUTE),
+                    plan_id: metadata.plan_id,
+                    path: metadata.path,
+                    language: metadata.language,
+                    modified_at: metadata.modified_at,
+                }
+            })
+            .collect();
+        hits.sort_by(|left, right| {
+            right
+                .score
+                .partial_cmp(&left.score)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| left.key.cmp(&right.key))
+        });
+        hits.truncate(k);
+        hits
+    }
+}
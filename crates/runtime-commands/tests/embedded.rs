@@ -0,0 +1,99 @@
+use std::fs;
+use std::time::Duration;
+
+use runtime_commands::{
+    EmbeddedRuntime, IngestError, RuntimeConfig, REGISTER_COMMAND, SEARCH_COMMAND, START_COMMAND,
+    STATUS_COMMAND,
+};
+use serde_json::{json, Value};
+
+async fn send(runtime: &EmbeddedRuntime, command: &str, payload: Value) -> Value {
+    runtime
+        .dispatch(command, payload)
+        .await
+        .unwrap_or_else(|err| panic!("{command} failed: {err:?}"))
+        .payload
+}
+
+#[tokio::test]
+async fn embedded_runtime_keeps_its_state_across_reopens() {
+    let state = tempfile::tempdir().expect("state dir");
+    let root = tempfile::tempdir().expect("workspace root");
+    fs::write(root.path().join("lib.rs"), "fn parse() {}\n").unwrap();
+    let mut config = RuntimeConfig::new(state.path());
+    config.dimensions = 8;
+
+    let runtime = EmbeddedRuntime::open(config.clone()).expect("open");
+    send(
+        &runtime,
+        REGISTER_COMMAND,
+        json!({ "repo_id": "repo", "root_path": root.path() }),
+    )
+    .await;
+    let started = send(&runtime, START_COMMAND, json!({ "repo_id": "repo" })).await;
+    let mut status = started;
+    for _ in 0..200 {
+        if status["state"] != "running" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        status = send(
+            &runtime,
+            STATUS_COMMAND,
+            json!({ "run_id": status["run_id"] }),
+        )
+        .await;
+    }
+    assert_eq!(status["state"], "completed", "{status}");
+    let report = send(&runtime, runtime_router::STATUS_COMMAND, Value::Null).await;
+    assert_eq!(report["subsystems"]["ingest"]["completed"], 1, "{report}");
+    assert_eq!(report["subsystems"]["store"]["mode"], "normal", "{report}");
+    assert!(report["subsystems"]["store"]["records"].as_u64() > Some(0));
+    drop(runtime);
+
+    // A new runtime over the same state finds the workspace and its vectors.
+    let runtime = EmbeddedRuntime::open(config).expect("reopen");
+    let hits = send(
+        &runtime,
+        SEARCH_COMMAND,
+        json!({ "repo_id": "repo", "query": "parse" }),
+    )
+    .await;
+    assert_eq!(hits["hits"].as_array().map(Vec::len), Some(1), "{hits}");
+    assert_eq!(hits["hits"][0]["path"], "lib.rs");
+}
+
+#[test]
+fn config_files_fill_in_defaults_and_reject_unknown_fields() {
+    let config: RuntimeConfig =
+        serde_json::from_value(json!({ "state_dir": "/var/lib/embednexus" })).expect("config");
+    assert_eq!(config, RuntimeConfig::new("/var/lib/embednexus"));
+    assert!(config.capabilities.contains(&"search".to_string()));
+    assert!(serde_json::from_value::<RuntimeConfig>(
+        json!({ "state_dir": "/tmp", "dimension": 8 })
+    )
+    .is_err());
+}
+
+#[test]
+fn config_files_load_by_extension_relative_to_their_directory() {
+    let dir = tempfile::tempdir().expect("config dir");
+    let toml_path = dir.path().join("runtime.toml");
+    fs::write(&toml_path, "state_dir = \"state\"\ndimensions = 16\n").unwrap();
+    let config = RuntimeConfig::load(&toml_path).expect("toml config");
+    assert_eq!(config.state_dir, dir.path().join("state"));
+    assert_eq!(config.dimensions, 16);
+
+    let json_path = dir.path().join("runtime.json");
+    fs::write(&json_path, r#"{ "state_dir": "/srv/embednexus" }"#).unwrap();
+    let config = RuntimeConfig::load(&json_path).expect("json config");
+    assert_eq!(config, RuntimeConfig::new("/srv/embednexus"));
+
+    let yaml_path = dir.path().join("runtime.yaml");
+    fs::write(&yaml_path, "state_dir: state\n").unwrap();
+    assert!(matches!(
+        RuntimeConfig::load(&yaml_path),
+        Err(IngestError::Config(_))
+    ));
+    assert!(RuntimeConfig::load(&dir.path().join("missing.toml")).is_err());
+}
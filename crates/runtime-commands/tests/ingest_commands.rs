@@ -0,0 +1,548 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use ingestion_embedding::{EmbeddingBatch, EmbeddingConfig, EmbeddingError, EmbeddingGenerator};
+use ingestion_planning::{ChunkPlan, ChunkPlanner, PlannedChunk, PlannerConfig, RetryPolicy};
+use ingestion_sanitization::commands::{
+    self as sanitization_commands, APPROVE_COMMAND, PENDING_COMMAND, REVEAL_CAPABILITY,
+    REVEAL_COMMAND, REVIEW_CAPABILITY,
+};
+use ingestion_sanitization::{
+    QuarantineStore, SanitizationConfig, SanitizedChunk, Sanitizer, TokenVault,
+};
+use ingestion_workspace::commands::{DEREGISTER_COMMAND, LIST_COMMAND};
+use ingestion_workspace::WorkspaceRegistry;
+use runtime_commands::{
+    register_commands, Embedder, PipelineOrchestrator, RunState, CANCEL_COMMAND, INGEST_CAPABILITY,
+    REGISTER_COMMAND, SEARCH_CAPABILITY, SEARCH_COMMAND, START_COMMAND, STATUS_COMMAND,
+};
+use runtime_router::{CommandRouter, HandlerRouter, RouterCommand, SessionContext};
+use serde_json::{json, Value};
+use storage_vector::encryption::aes_gcm::AesGcmEncrypter;
+use storage_vector::kms::{InMemoryKeyManager, KeyScope};
+use storage_vector::{tenant_namespace, Store, VectorStore};
+use tokio::sync::{Notify, Semaphore};
+
+fn pipeline(state: &Path, embedder: Arc<dyn Embedder>) -> PipelineOrchestrator {
+    let registry = WorkspaceRegistry::open(state.join("registry.json")).expect("registry");
+    let sanitizer = Sanitizer::new(SanitizationConfig::default()).expect("sanitizer");
+    PipelineOrchestrator::new(
+        Arc::new(registry),
+        Arc::new(sanitizer),
+        embedder,
+        Arc::new(VectorStore::new()),
+        state.join("index"),
+    )
+}
+
+fn hash_embedder() -> Arc<dyn Embedder> {
+    Arc::new(EmbeddingGenerator::new(EmbeddingConfig::new(
+        "encoder-a".into(),
+        8,
+    )))
+}
+
+fn operator() -> SessionContext {
+    SessionContext::new(
+        "ops",
+        vec![
+            "admin".into(),
+            INGEST_CAPABILITY.into(),
+            SEARCH_CAPABILITY.into(),
+        ],
+    )
+}
+
+async fn send(router: &HandlerRouter, command: &str, payload: Value) -> Value {
+    router
+        .dispatch(operator(), RouterCommand::new(command, payload))
+        .await
+        .unwrap_or_else(|err| panic!("{command} failed: {err:?}"))
+        .payload
+}
+
+async fn wait_for_run(router: &HandlerRouter, run_id: &str) -> Value {
+    for _ in 0..200 {
+        let status = send(router, STATUS_COMMAND, json!({ "run_id": run_id })).await;
+        if status["state"] != "running" {
+            return status;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("run {run_id} did not finish");
+}
+
+#[tokio::test]
+async fn commands_register_ingest_and_search_a_workspace() {
+    let state = tempfile::tempdir().expect("state dir");
+    let root = tempfile::tempdir().expect("workspace root");
+    fs::write(root.path().join("lib.rs"), "fn parse() {}\n").unwrap();
+    fs::write(root.path().join("README.md"), "# Parser\n").unwrap();
+    let pipeline = Arc::new(pipeline(state.path(), hash_embedder()));
+    let mut router = HandlerRouter::new();
+    register_commands(&mut router, Arc::clone(&pipeline));
+
+    send(
+        &router,
+        REGISTER_COMMAND,
+        json!({ "repo_id": "repo-a", "root_path": root.path() }),
+    )
+    .await;
+    let started = send(&router, START_COMMAND, json!({ "repo_id": "repo-a" })).await;
+    let run_id = started["run_id"].as_str().expect("run id").to_string();
+    let status = wait_for_run(&router, &run_id).await;
+    assert_eq!(status["state"], "completed", "{status}");
+    assert_eq!(status["files_changed"], 2);
+    assert_eq!(status["files_planned"], 2);
+    assert_eq!(status["chunks_planned"], 2);
+    assert_eq!(status["chunks_embedded"], 2);
+    assert_eq!(status["bytes_stored"], 2 * 8 * 4);
+    assert!(status.get("eta_ms").is_none(), "{status}");
+
+    let results = send(
+        &router,
+        SEARCH_COMMAND,
+        json!({ "repo_id": "repo-a", "query": "parse", "k": 5 }),
+    )
+    .await;
+    let hits = results["hits"].as_array().expect("hits");
+    assert_eq!(hits.len(), 2);
+    let lib = hits
+        .iter()
+        .find(|hit| hit["path"] == "lib.rs")
+        .expect("lib.rs hit");
+    assert_eq!(lib["plan_id"], "repo-a::lib.rs::0");
+    assert!(lib["source_span"]
+        .as_str()
+        .is_some_and(|span| span.starts_with("lib.rs:")));
+
+    // The same hits, a page at a time.
+    let search = |cursor: Value| {
+        RouterCommand::new(
+            SEARCH_COMMAND,
+            json!({ "repo_id": "repo-a", "query": "parse", "page_size": 1, "cursor": cursor }),
+        )
+    };
+    let first = router
+        .dispatch(operator(), search(Value::Null))
+        .await
+        .expect("first page");
+    let cursor = first.page.expect("paged").next_cursor.expect("more hits");
+    let second = router
+        .dispatch(operator(), search(json!(cursor)))
+        .await
+        .expect("second page");
+    assert_eq!(second.page.expect("paged").next_cursor, None);
+    let paged = [&first.payload["hits"][0], &second.payload["hits"][0]];
+    assert_eq!(paged, [&hits[0], &hits[1]]);
+
+    // A second run only re-embeds what changed and drops removed files.
+    fs::remove_file(root.path().join("README.md")).unwrap();
+    fs::write(root.path().join("lib.rs"), "fn parse() { todo!() }\n").unwrap();
+    let status = pipeline.execute("repo-a").await.expect("second run");
+    assert_eq!(status.state, RunState::Completed);
+    assert_eq!((status.files_changed, status.files_removed), (1, 1));
+    assert_eq!(status.records_deleted, 2);
+    assert_eq!(status.chunks_embedded, 1);
+    let keys = pipeline
+        .store()
+        .list_keys("repo-a", "", None, 10)
+        .expect("keys")
+        .keys;
+    assert_eq!(keys, vec!["encoder-a::repo-a::lib.rs::0"]);
+
+    let runs = send(&router, STATUS_COMMAND, json!({})).await;
+    assert_eq!(runs["runs"].as_array().map(Vec::len), Some(2));
+}
+
+#[tokio::test]
+async fn commands_reject_unknown_targets_and_missing_capabilities() {
+    let state = tempfile::tempdir().expect("state dir");
+    let mut router = HandlerRouter::new();
+    register_commands(
+        &mut router,
+        Arc::new(pipeline(state.path(), hash_embedder())),
+    );
+
+    let err = router
+        .dispatch(
+            operator(),
+            RouterCommand::new(START_COMMAND, json!({ "repo_id": "missing" })),
+        )
+        .await
+        .expect_err("unregistered workspace");
+    assert_eq!(err.status_code(), 404);
+    let err = router
+        .dispatch(
+            operator(),
+            RouterCommand::new(CANCEL_COMMAND, json!({ "run_id": "nope" })),
+        )
+        .await
+        .expect_err("unknown run");
+    assert_eq!(err.status_code(), 404);
+
+    let searcher = SessionContext::new("reader", vec![SEARCH_CAPABILITY.into()]);
+    let err = router
+        .dispatch(
+            searcher,
+            RouterCommand::new(START_COMMAND, json!({ "repo_id": "repo-a" })),
+        )
+        .await
+        .expect_err("ingest capability required");
+    assert_eq!(err.status_code(), 401);
+}
+
+#[tokio::test]
+async fn tenant_sessions_only_reach_their_own_workspaces_and_records() {
+    let state = tempfile::tempdir().expect("state dir");
+    let root = tempfile::tempdir().expect("workspace root");
+    fs::write(root.path().join("lib.rs"), "fn parse() {}\n").unwrap();
+    let pipeline = Arc::new(pipeline(state.path(), hash_embedder()));
+    let mut router = HandlerRouter::new();
+    register_commands(&mut router, Arc::clone(&pipeline));
+    let tenant = |tenant_id: &str| operator().with_tenant(tenant_id);
+    let dispatch = |ctx: SessionContext, command: &str, payload: Value| {
+        router.dispatch(ctx, RouterCommand::new(command, payload))
+    };
+
+    dispatch(
+        tenant("team-a"),
+        REGISTER_COMMAND,
+        json!({ "repo_id": "repo-a", "root_path": root.path() }),
+    )
+    .await
+    .expect("register");
+    let listed = dispatch(tenant("team-b"), LIST_COMMAND, Value::Null)
+        .await
+        .expect("list");
+    assert_eq!(listed.payload["workspaces"], json!([]));
+    let err = dispatch(
+        tenant("team-b"),
+        START_COMMAND,
+        json!({ "repo_id": "repo-a" }),
+    )
+    .await
+    .expect_err("foreign workspace");
+    assert_eq!(err.status_code(), 404);
+
+    let started = dispatch(
+        tenant("team-a"),
+        START_COMMAND,
+        json!({ "repo_id": "repo-a" }),
+    )
+    .await
+    .expect("start");
+    assert_eq!(started.payload["tenant_id"], "team-a");
+    let run_id = started.payload["run_id"].as_str().expect("run id");
+    let status = wait_for_run(&router, run_id).await;
+    assert_eq!(status["state"], "completed", "{status}");
+    let err = dispatch(
+        tenant("team-b"),
+        STATUS_COMMAND,
+        json!({ "run_id": run_id }),
+    )
+    .await
+    .expect_err("foreign run");
+    assert_eq!(err.status_code(), 404);
+    let runs = dispatch(tenant("team-b"), STATUS_COMMAND, json!({}))
+        .await
+        .expect("runs");
+    assert_eq!(runs.payload["runs"], json!([]));
+
+    // Records live in the tenant's namespace, not the bare repository's.
+    let store = pipeline.store();
+    assert!(store
+        .list_keys("repo-a", "", None, 10)
+        .unwrap()
+        .keys
+        .is_empty());
+    assert_eq!(
+        store
+            .list_keys("repo-a@team-a", "", None, 10)
+            .unwrap()
+            .keys
+            .len(),
+        1
+    );
+    // A bare repository id cannot pose as a tenant's namespace.
+    assert_ne!(
+        tenant_namespace(None, "repo-a@team-a"),
+        tenant_namespace(Some("team-a"), "repo-a")
+    );
+
+    let search = json!({ "repo_id": "repo-a", "query": "parse" });
+    let hits = dispatch(tenant("team-a"), SEARCH_COMMAND, search.clone())
+        .await
+        .expect("own search");
+    assert_eq!(hits.payload["hits"].as_array().map(Vec::len), Some(1));
+    let hits = dispatch(tenant("team-b"), SEARCH_COMMAND, search)
+        .await
+        .expect("foreign search");
+    assert_eq!(hits.payload["hits"], json!([]));
+    let err = dispatch(
+        tenant("team-b"),
+        SEARCH_COMMAND,
+        json!({ "repo_id": "repo-a", "query": "parse", "tenant_id": "team-a" }),
+    )
+    .await
+    .expect_err("naming another tenant");
+    assert_eq!(err.status_code(), 404);
+    let hits = send(
+        &router,
+        SEARCH_COMMAND,
+        json!({ "repo_id": "repo-a", "query": "parse", "tenant_id": "team-a" }),
+    )
+    .await;
+    assert_eq!(hits["hits"].as_array().map(Vec::len), Some(1));
+
+    let err = dispatch(
+        tenant("team-b"),
+        DEREGISTER_COMMAND,
+        json!({ "repo_id": "repo-a" }),
+    )
+    .await
+    .expect_err("foreign deregister");
+    assert_eq!(err.status_code(), 404);
+    assert_eq!(pipeline.registry().snapshot().workspaces.len(), 1);
+}
+
+#[tokio::test]
+async fn tenant_sessions_only_review_and_reveal_their_own_quarantined_chunks() {
+    let vault = Arc::new(TokenVault::in_memory(
+        Arc::new(AesGcmEncrypter::new()),
+        Arc::new(InMemoryKeyManager::new_random("k1")),
+        KeyScope {
+            repo_id: "repo-a".into(),
+        },
+    ));
+    let sanitizer = Sanitizer::new(SanitizationConfig::default())
+        .expect("sanitizer")
+        .with_vault(Arc::clone(&vault));
+    let sanitize = |tenant_id: &str| {
+        let plan = ChunkPlan {
+            plan_id: format!("repo-a::{tenant_id}/run.sh::0"),
+            repo_id: "repo-a".into(),
+            chunker_config: "bytes=1024;max=64".into(),
+            source_span: format!("{tenant_id}/run.sh:0-64"),
+            hash: format!("hash-{tenant_id}"),
+            retry_policy: RetryPolicy::default(),
+            tenant_id: Some(tenant_id.into()),
+        };
+        sanitizer
+            .apply(&PlannedChunk::new(
+                plan,
+                "#!/bin/sh\nexport TOKEN=SECRET-ALPHA",
+            ))
+            .expect("sanitize")
+    };
+    let (own, foreign) = (sanitize("team-a"), sanitize("team-b"));
+    assert_eq!(own.tenant_id.as_deref(), Some("team-a"));
+    // The same secret gets a separate token per tenant.
+    assert_ne!(own.scrubbed_payload, foreign.scrubbed_payload);
+    let token = own
+        .scrubbed_payload
+        .trim_start_matches("#!/bin/sh\nexport TOKEN=")
+        .to_string();
+    let store = Arc::new(QuarantineStore::in_memory());
+    store.admit(vec![own, foreign]).expect("admit");
+
+    let mut router = HandlerRouter::new();
+    sanitization_commands::register_commands(&mut router, Arc::clone(&store));
+    sanitization_commands::register_vault_commands(&mut router, vault);
+    let tenant = |tenant_id: &str| {
+        SessionContext::new(
+            "reviewer",
+            vec![REVIEW_CAPABILITY.into(), REVEAL_CAPABILITY.into()],
+        )
+        .with_tenant(tenant_id)
+    };
+    let dispatch = |ctx: SessionContext, command: &str, payload: Value| {
+        router.dispatch(ctx, RouterCommand::new(command, payload))
+    };
+
+    let pending = dispatch(tenant("team-b"), PENDING_COMMAND, json!({}))
+        .await
+        .expect("pending");
+    assert_eq!(pending.payload["pending"].as_array().map(Vec::len), Some(1));
+    assert_eq!(
+        pending.payload["pending"][0]["plan_id"],
+        "repo-a::team-b/run.sh::0"
+    );
+
+    let review = json!({ "plan_id": "repo-a::team-a/run.sh::0" });
+    let err = dispatch(tenant("team-b"), APPROVE_COMMAND, review.clone())
+        .await
+        .expect_err("foreign chunk");
+    assert_eq!(err.status_code(), 404);
+    let err = dispatch(tenant("team-b"), REVEAL_COMMAND, json!({ "token": token }))
+        .await
+        .expect_err("foreign token");
+    assert_eq!(err.status_code(), 404);
+
+    dispatch(tenant("team-a"), APPROVE_COMMAND, review)
+        .await
+        .expect("approve own chunk");
+    let revealed = dispatch(tenant("team-a"), REVEAL_COMMAND, json!({ "token": token }))
+        .await
+        .expect("reveal own token");
+    assert_eq!(revealed.payload["secret"], "SECRET-ALPHA");
+}
+
+/// Embedder that waits for a permit per batch, so a test can cancel a run
+/// while it is embedding.
+struct GatedEmbedder {
+    inner: EmbeddingGenerator,
+    entered: Arc<Notify>,
+    gate: Arc<Semaphore>,
+}
+
+#[async_trait]
+impl Embedder for GatedEmbedder {
+    fn encoder_id(&self) -> &str {
+        self.inner.encoder_id()
+    }
+
+    fn dimensions(&self) -> usize {
+        self.inner.dimensions()
+    }
+
+    async fn encode_batch(
+        &self,
+        chunks: &[SanitizedChunk],
+    ) -> Result<EmbeddingBatch, EmbeddingError> {
+        self.entered.notify_one();
+        self.gate
+            .acquire()
+            .await
+            .map_err(|err| EmbeddingError::Backend(err.to_string()))?
+            .forget();
+        self.inner.encode(chunks)
+    }
+}
+
+#[tokio::test]
+async fn cancelled_runs_stop_between_batches_and_rescan_next_time() {
+    let state = tempfile::tempdir().expect("state dir");
+    let root = tempfile::tempdir().expect("workspace root");
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        fs::write(root.path().join(name), name).unwrap();
+    }
+    let entered = Arc::new(Notify::new());
+    let gate = Arc::new(Semaphore::new(0));
+    let embedder = Arc::new(GatedEmbedder {
+        inner: EmbeddingGenerator::new(EmbeddingConfig::new("encoder-a".into(), 8)),
+        entered: Arc::clone(&entered),
+        gate: Arc::clone(&gate),
+    });
+    let pipeline = Arc::new(
+        pipeline(state.path(), embedder)
+            .with_planner(ChunkPlanner::new(PlannerConfig::new(1024, 1))),
+    );
+    let mut router = HandlerRouter::new();
+    register_commands(&mut router, Arc::clone(&pipeline));
+    send(
+        &router,
+        REGISTER_COMMAND,
+        json!({ "repo_id": "repo-c", "root_path": root.path() }),
+    )
+    .await;
+
+    let started = send(&router, START_COMMAND, json!({ "repo_id": "repo-c" })).await;
+    let run_id = started["run_id"].as_str().expect("run id").to_string();
+    let err = router
+        .dispatch(
+            operator(),
+            RouterCommand::new(START_COMMAND, json!({ "repo_id": "repo-c" })),
+        )
+        .await
+        .expect_err("one run per workspace");
+    assert_eq!(err.status_code(), 400);
+
+    entered.notified().await;
+    send(&router, CANCEL_COMMAND, json!({ "run_id": run_id })).await;
+    gate.add_permits(1);
+    let status = wait_for_run(&router, &run_id).await;
+    assert_eq!(status["state"], "cancelled", "{status}");
+    assert_eq!(status["chunks_embedded"], 1);
+
+    gate.add_permits(3);
+    let status = pipeline.execute("repo-c").await.expect("rerun");
+    assert_eq!(status.state, RunState::Completed);
+    assert_eq!(status.files_changed, 3);
+    assert_eq!(status.chunks_embedded, 3);
+}
+
+#[tokio::test]
+async fn status_reports_progress_while_a_run_embeds() {
+    let state = tempfile::tempdir().expect("state dir");
+    let root = tempfile::tempdir().expect("workspace root");
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        fs::write(root.path().join(name), name).unwrap();
+    }
+    let entered = Arc::new(Notify::new());
+    let gate = Arc::new(Semaphore::new(0));
+    let embedder = Arc::new(GatedEmbedder {
+        inner: EmbeddingGenerator::new(EmbeddingConfig::new("encoder-a".into(), 8)),
+        entered: Arc::clone(&entered),
+        gate: Arc::clone(&gate),
+    });
+    let pipeline = Arc::new(
+        pipeline(state.path(), embedder)
+            .with_planner(ChunkPlanner::new(PlannerConfig::new(1024, 1))),
+    );
+    let mut router = HandlerRouter::new();
+    register_commands(&mut router, Arc::clone(&pipeline));
+    send(
+        &router,
+        REGISTER_COMMAND,
+        json!({ "repo_id": "repo-p", "root_path": root.path() }),
+    )
+    .await;
+
+    let started = send(&router, START_COMMAND, json!({ "repo_id": "repo-p" })).await;
+    let run_id = started["run_id"].as_str().expect("run id").to_string();
+    entered.notified().await;
+    let status = send(&router, STATUS_COMMAND, json!({ "run_id": run_id })).await;
+    assert_eq!(status["files_changed"], 3);
+    assert_eq!(status["files_planned"], 1);
+    assert_eq!(status["chunks_planned"], 1);
+    assert_eq!(status["chunks_embedded"], 0);
+
+    let mut updates = pipeline.watch(&run_id).expect("watch");
+    gate.add_permits(1);
+    while updates.borrow().chunks_embedded == 0 {
+        updates.changed().await.expect("run still tracked");
+    }
+    let status = updates.borrow().clone();
+    assert_eq!(status.bytes_stored, 8 * 4);
+    assert!(status.eta_ms.is_some(), "{status:?}");
+
+    // A long poll returns once the run moves on.
+    entered.notified().await;
+    let release = {
+        let gate = Arc::clone(&gate);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            gate.add_permits(1);
+        })
+    };
+    let status = send(
+        &router,
+        STATUS_COMMAND,
+        json!({ "run_id": run_id, "wait_ms": 5_000 }),
+    )
+    .await;
+    release.await.unwrap();
+    assert_eq!(status["state"], "running", "{status}");
+    assert_eq!(status["chunks_embedded"], 2);
+
+    gate.add_permits(1);
+    let status = wait_for_run(&router, &run_id).await;
+    assert_eq!(status["state"], "completed", "{status}");
+    assert_eq!(status["files_planned"], 3);
+    assert_eq!(status["bytes_stored"], 3 * 8 * 4);
+    assert!(status.get("eta_ms").is_none(), "{status}");
+}
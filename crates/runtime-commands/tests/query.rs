@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use ingestion_embedding::{EmbeddingBatch, EmbeddingConfig, EmbeddingError, EmbeddingGenerator};
+use ingestion_sanitization::SanitizedChunk;
+use runtime_commands::{Embedder, Query, QueryEngine, QueryError, QueryHit, RankingConfig};
+use storage_vector::{Field, FilterExpr, VectorMetadata, VectorStore};
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// Embedder mapping every query to `[1, 0]`.
+struct FixedEmbedder {
+    inner: EmbeddingGenerator,
+}
+
+#[async_trait]
+impl Embedder for FixedEmbedder {
+    fn encoder_id(&self) -> &str {
+        self.inner.encoder_id()
+    }
+
+    fn dimensions(&self) -> usize {
+        2
+    }
+
+    async fn encode_batch(
+        &self,
+        chunks: &[SanitizedChunk],
+    ) -> Result<EmbeddingBatch, EmbeddingError> {
+        let mut batch = self.inner.encode(chunks)?;
+        batch.vectors = vec![vec![1.0, 0.0]; chunks.len()];
+        Ok(batch)
+    }
+}
+
+/// An exact match in an old file, a close match in a new one and a weak
+/// match in the docs.
+fn engine() -> QueryEngine {
+    let store = VectorStore::new();
+    for (key, vector, path, modified_at) in [
+        ("old", vec![1.0, 0.0], "src/old.rs", Some(0)),
+        ("new", vec![0.96, 0.28], "src/new.rs", Some(10 * DAY)),
+        ("guide", vec![0.6, 0.8], "docs/guide.md", None),
+    ] {
+        let mut metadata = VectorMetadata::new(format!("repo::{path}::0"))
+            .with_path(path)
+            .with_attribute("source_span", format!("{path}:1-3"));
+        if let Some(modified_at) = modified_at {
+            metadata = metadata.with_modified_at(modified_at);
+        }
+        store
+            .insert_vector("repo", key, vector, metadata)
+            .expect("insert");
+    }
+    let embedder = FixedEmbedder {
+        inner: EmbeddingGenerator::new(EmbeddingConfig::new("fixed".into(), 2)),
+    };
+    QueryEngine::new(Arc::new(embedder), Arc::new(store))
+}
+
+fn keys(hits: &[QueryHit]) -> Vec<&str> {
+    hits.iter().map(|hit| hit.key.as_str()).collect()
+}
+
+#[tokio::test]
+async fn recency_reorders_close_matches() {
+    let hits = engine()
+        .search(&Query::new("repo", "parse", 3))
+        .await
+        .expect("search");
+    assert_eq!(keys(&hits), ["old", "new", "guide"]);
+    assert_eq!(hits[0].source_span.as_deref(), Some("src/old.rs:1-3"));
+    assert_eq!(hits[0].path.as_deref(), Some("src/old.rs"));
+    assert!(hits[1].score > hits[1].similarity);
+
+    let eager = engine().with_ranking(RankingConfig {
+        recency: 0.1,
+        ..RankingConfig::default()
+    });
+    let hits = eager
+        .search(&Query::new("repo", "parse", 3))
+        .await
+        .expect("search");
+    assert_eq!(keys(&hits), ["new", "old", "guide"]);
+}
+
+#[tokio::test]
+async fn path_boosts_promote_candidates_beyond_k() {
+    let query = Query::new("repo", "parse", 1).with_boost("docs/", 0.5);
+    let hits = engine().search(&query).await.expect("search");
+    assert_eq!(keys(&hits), ["guide"]);
+
+    // Without over-fetching, the boosted hit is never a candidate.
+    let narrow = engine().with_ranking(RankingConfig {
+        oversample: 1,
+        ..RankingConfig::default()
+    });
+    let hits = narrow.search(&query).await.expect("search");
+    assert_eq!(keys(&hits), ["old"]);
+}
+
+#[tokio::test]
+async fn filters_apply_before_ranking_and_empty_queries_fail() {
+    let query = Query::new("repo", "parse", 3)
+        .with_boost("docs/", 0.5)
+        .with_filter(FilterExpr::Prefix {
+            field: Field::Path,
+            prefix: "src/".into(),
+        });
+    let hits = engine().search(&query).await.expect("search");
+    assert_eq!(keys(&hits), ["old", "new"]);
+
+    let err = engine()
+        .search(&Query::new("repo", "  ", 3))
+        .await
+        .expect_err("empty query");
+    assert!(matches!(err, QueryError::EmptyQuery));
+    let hits = engine()
+        .search(&Query::new("missing", "parse", 3))
+        .await
+        .expect("unknown repositories have no hits");
+    assert!(hits.is_empty());
+}
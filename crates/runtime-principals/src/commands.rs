@@ -0,0 +1,217 @@
+//! Admin router commands managing a [`PrincipalStore`](crate::PrincipalStore).
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use runtime_router::{
+    CommandHandler, HandlerRouter, PageRequest, RouterError, RouterResponse, SessionContext,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{PrincipalError, PrincipalRecord, SharedPrincipalStore};
+
+/// Command adding an enabled principal (`{ principal, tenant_id? }`).
+pub const ADD_COMMAND: &str = "principals.add";
+/// Command removing a principal (`{ principal }`).
+pub const REMOVE_COMMAND: &str = "principals.remove";
+/// Command refusing a principal while keeping its entry (`{ principal }`).
+pub const DISABLE_COMMAND: &str = "principals.disable";
+/// Command accepting a disabled principal again (`{ principal }`).
+pub const ENABLE_COMMAND: &str = "principals.enable";
+/// Command listing principals, a page at a time (`{ cursor?, page_size? }`).
+pub const LIST_COMMAND: &str = "principals.list";
+
+/// Principals listed per page when the request does not say.
+pub const DEFAULT_LIST_PAGE_SIZE: usize = 100;
+/// Most principals one `principals.list` page holds.
+pub const MAX_LIST_PAGE_SIZE: usize = 1_000;
+
+/// Capability required for every principal command, listing included.
+pub const ADMIN_CAPABILITY: &str = "admin";
+
+/// Register the principal commands on `router`.
+pub fn register_commands(router: &mut HandlerRouter, store: SharedPrincipalStore) {
+    let change = |op| {
+        Arc::new(ChangePrincipalHandler {
+            store: store.clone(),
+            op,
+        })
+    };
+    let admin = || vec![ADMIN_CAPABILITY.to_string()];
+    router
+        .register_with_capabilities(ADD_COMMAND, admin(), change(Change::Add))
+        .register_with_capabilities(REMOVE_COMMAND, admin(), change(Change::Remove))
+        .register_with_capabilities(DISABLE_COMMAND, admin(), change(Change::Disable))
+        .register_with_capabilities(ENABLE_COMMAND, admin(), change(Change::Enable))
+        .register_with_capabilities(
+            LIST_COMMAND,
+            admin(),
+            Arc::new(ListPrincipalsHandler {
+                store: store.clone(),
+            }),
+        );
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PrincipalRequest {
+    principal: String,
+    /// Only read by `principals.add`.
+    #[serde(default)]
+    tenant_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Change {
+    Add,
+    Remove,
+    Disable,
+    Enable,
+}
+
+struct ChangePrincipalHandler {
+    store: SharedPrincipalStore,
+    op: Change,
+}
+
+#[async_trait]
+impl CommandHandler for ChangePrincipalHandler {
+    async fn handle(
+        &self,
+        _ctx: &SessionContext,
+        payload: Value,
+    ) -> Result<RouterResponse, RouterError> {
+        let request: PrincipalRequest = parse_payload(payload)?;
+        let principal = request.principal.as_str();
+        let record = match self.op {
+            Change::Add => self.store.add(principal, request.tenant_id.as_deref()),
+            Change::Remove => self.store.remove(principal),
+            Change::Disable => self.store.set_disabled(principal, true),
+            Change::Enable => self.store.set_disabled(principal, false),
+        }
+        .map_err(router_error)?;
+        Ok(RouterResponse::ok(record_json(&record)))
+    }
+}
+
+struct ListPrincipalsHandler {
+    store: SharedPrincipalStore,
+}
+
+#[async_trait]
+impl CommandHandler for ListPrincipalsHandler {
+    async fn handle(
+        &self,
+        _ctx: &SessionContext,
+        payload: Value,
+    ) -> Result<RouterResponse, RouterError> {
+        let request: PageRequest = if payload.is_null() {
+            PageRequest::default()
+        } else {
+            parse_payload(payload)?
+        };
+        let (records, page) = request.paginate(
+            self.store.list().map_err(router_error)?,
+            DEFAULT_LIST_PAGE_SIZE,
+            MAX_LIST_PAGE_SIZE,
+        )?;
+        let principals: Vec<Value> = records.iter().map(record_json).collect();
+        Ok(RouterResponse::paged(
+            json!({ "principals": principals }),
+            page,
+        ))
+    }
+}
+
+fn record_json(record: &PrincipalRecord) -> Value {
+    json!({
+        "principal": record.principal,
+        "disabled": record.disabled,
+        "tenant_id": record.tenant_id,
+    })
+}
+
+fn parse_payload<T: serde::de::DeserializeOwned>(payload: Value) -> Result<T, RouterError> {
+    serde_json::from_value(payload).map_err(|err| RouterError::InvalidRequest {
+        detail: err.to_string(),
+    })
+}
+
+fn router_error(err: PrincipalError) -> RouterError {
+    match err {
+        PrincipalError::Duplicate(_)
+        | PrincipalError::InvalidName(_)
+        | PrincipalError::InvalidTenant(_) => RouterError::InvalidRequest {
+            detail: err.to_string(),
+        },
+        PrincipalError::Unknown(_) => RouterError::NotFound {
+            detail: err.to_string(),
+        },
+        PrincipalError::Storage(_) => RouterError::Internal {
+            detail: err.to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use runtime_router::{CommandRouter, RouterCommand};
+
+    use super::*;
+    use crate::{InMemoryPrincipalStore, PrincipalStore};
+
+    #[tokio::test]
+    async fn admins_manage_principals_through_the_router() {
+        let store = Arc::new(InMemoryPrincipalStore::with_principals(["alice"]));
+        let mut router = HandlerRouter::new();
+        register_commands(&mut router, store.clone());
+        let admin = SessionContext::new("root", vec![ADMIN_CAPABILITY.into()]);
+        let send = |command: &str, payload: Value| {
+            router.dispatch(admin.clone(), RouterCommand::new(command, payload))
+        };
+
+        let added = send(
+            ADD_COMMAND,
+            json!({ "principal": "bob", "tenant_id": "team-b" }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            added.payload,
+            json!({ "principal": "bob", "disabled": false, "tenant_id": "team-b" })
+        );
+        send(DISABLE_COMMAND, json!({ "principal": "alice" }))
+            .await
+            .unwrap();
+        assert!(!store.is_active("alice"));
+        assert!(store.is_active("bob"));
+
+        let listed = send(LIST_COMMAND, json!({ "page_size": 1 })).await.unwrap();
+        assert_eq!(
+            listed.payload,
+            json!({ "principals": [{ "principal": "alice", "disabled": true, "tenant_id": null }] })
+        );
+        assert!(listed.page.unwrap().next_cursor.is_some());
+
+        let err = send(REMOVE_COMMAND, json!({ "principal": "carol" }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status_code(), 404);
+        let err = send(ADD_COMMAND, json!({ "principal": "bob" }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status_code(), 400);
+
+        let user = SessionContext::new("bob", Vec::new());
+        let err = router
+            .dispatch(
+                user,
+                RouterCommand::new(ENABLE_COMMAND, json!({ "principal": "alice" })),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.status_code(), 401);
+        assert!(!store.is_active("alice"));
+    }
+}
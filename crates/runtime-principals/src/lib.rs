@@ -0,0 +1,424 @@
+//! Principals the transport adapters accept, managed at runtime.
+//!
+//! Adapters given a [`PrincipalStore`] check every token's principal
+//! against it when the token is verified, instead of against the static
+//! `allowed_principals` of their config. Principals are added, removed,
+//! disabled and re-enabled through the store or the admin router commands
+//! in [`commands`], and the next request sees the change. A principal
+//! assigned to a tenant gets sessions confined to that tenant's
+//! repositories.
+//! [`InMemoryPrincipalStore`] forgets its principals on restart;
+//! [`FilePrincipalStore`] keeps them in a JSON file.
+
+pub mod commands;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock};
+
+use runtime_router::validate_tenant_id;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+pub use commands::register_commands;
+
+/// Layout version of the principals file; a registry saved by another
+/// version is rejected at open.
+pub const PRINCIPALS_VERSION: u32 = 1;
+
+/// Errors raised by a [`PrincipalStore`].
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum PrincipalError {
+    #[error("principal {0} already exists")]
+    Duplicate(String),
+    #[error("unknown principal {0}")]
+    Unknown(String),
+    #[error("invalid principal name {0:?}")]
+    InvalidName(String),
+    #[error("invalid tenant id {0:?}")]
+    InvalidTenant(String),
+    #[error("principal store: {0}")]
+    Storage(String),
+}
+
+/// One principal known to a store.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrincipalRecord {
+    pub principal: String,
+    /// Disabled principals are refused like unknown ones but keep their
+    /// entry, so they can be enabled again.
+    #[serde(default)]
+    pub disabled: bool,
+    /// Tenant the principal's sessions are confined to; `None` for
+    /// operators.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+}
+
+/// Principals an adapter accepts.
+pub trait PrincipalStore: fmt::Debug + Send + Sync {
+    fn get(&self, principal: &str) -> Result<Option<PrincipalRecord>, PrincipalError>;
+
+    /// Every principal, ordered by name.
+    fn list(&self) -> Result<Vec<PrincipalRecord>, PrincipalError>;
+
+    /// Add an enabled principal, confined to `tenant_id` if given; fails if
+    /// it already exists.
+    fn add(
+        &self,
+        principal: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<PrincipalRecord, PrincipalError>;
+
+    /// Remove a principal, returning its last record.
+    fn remove(&self, principal: &str) -> Result<PrincipalRecord, PrincipalError>;
+
+    fn set_disabled(
+        &self,
+        principal: &str,
+        disabled: bool,
+    ) -> Result<PrincipalRecord, PrincipalError>;
+
+    /// Whether `principal` exists and is enabled. A store that cannot
+    /// answer refuses the principal.
+    fn is_active(&self, principal: &str) -> bool {
+        matches!(self.get(principal), Ok(Some(record)) if !record.disabled)
+    }
+
+    /// Tenant `principal` is confined to, if any.
+    fn tenant_of(&self, principal: &str) -> Option<String> {
+        self.get(principal).ok().flatten()?.tenant_id
+    }
+}
+
+/// Principal store shared between adapters and the admin commands.
+pub type SharedPrincipalStore = Arc<dyn PrincipalStore>;
+
+type Principals = BTreeMap<String, PrincipalRecord>;
+
+fn add_to(
+    principals: &mut Principals,
+    principal: &str,
+    tenant_id: Option<&str>,
+) -> Result<PrincipalRecord, PrincipalError> {
+    if principal.is_empty() || principal.trim() != principal {
+        return Err(PrincipalError::InvalidName(principal.into()));
+    }
+    if let Some(tenant_id) = tenant_id {
+        validate_tenant_id(tenant_id)
+            .map_err(|_| PrincipalError::InvalidTenant(tenant_id.into()))?;
+    }
+    if principals.contains_key(principal) {
+        return Err(PrincipalError::Duplicate(principal.into()));
+    }
+    let record = PrincipalRecord {
+        principal: principal.into(),
+        disabled: false,
+        tenant_id: tenant_id.map(Into::into),
+    };
+    principals.insert(principal.into(), record.clone());
+    Ok(record)
+}
+
+fn remove_from(
+    principals: &mut Principals,
+    principal: &str,
+) -> Result<PrincipalRecord, PrincipalError> {
+    principals
+        .remove(principal)
+        .ok_or_else(|| PrincipalError::Unknown(principal.into()))
+}
+
+fn set_disabled_in(
+    principals: &mut Principals,
+    principal: &str,
+    disabled: bool,
+) -> Result<PrincipalRecord, PrincipalError> {
+    let record = principals
+        .get_mut(principal)
+        .ok_or_else(|| PrincipalError::Unknown(principal.into()))?;
+    record.disabled = disabled;
+    Ok(record.clone())
+}
+
+/// Principals held in memory only.
+#[derive(Debug, Default)]
+pub struct InMemoryPrincipalStore {
+    principals: RwLock<Principals>,
+}
+
+impl InMemoryPrincipalStore {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store holding `principals`, all enabled; typically a config's
+    /// `allowed_principals`.
+    #[must_use]
+    pub fn with_principals<I, S>(principals: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let principals = principals
+            .into_iter()
+            .map(|principal| {
+                let principal = principal.into();
+                let record = PrincipalRecord {
+                    principal: principal.clone(),
+                    disabled: false,
+                    tenant_id: None,
+                };
+                (principal, record)
+            })
+            .collect();
+        Self {
+            principals: RwLock::new(principals),
+        }
+    }
+}
+
+impl PrincipalStore for InMemoryPrincipalStore {
+    fn get(&self, principal: &str) -> Result<Option<PrincipalRecord>, PrincipalError> {
+        let principals = self
+            .principals
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        Ok(principals.get(principal).cloned())
+    }
+
+    fn list(&self) -> Result<Vec<PrincipalRecord>, PrincipalError> {
+        let principals = self
+            .principals
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        Ok(principals.values().cloned().collect())
+    }
+
+    fn add(
+        &self,
+        principal: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<PrincipalRecord, PrincipalError> {
+        let mut principals = self
+            .principals
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        add_to(&mut principals, principal, tenant_id)
+    }
+
+    fn remove(&self, principal: &str) -> Result<PrincipalRecord, PrincipalError> {
+        let mut principals = self
+            .principals
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        remove_from(&mut principals, principal)
+    }
+
+    fn set_disabled(
+        &self,
+        principal: &str,
+        disabled: bool,
+    ) -> Result<PrincipalRecord, PrincipalError> {
+        let mut principals = self
+            .principals
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        set_disabled_in(&mut principals, principal, disabled)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PrincipalsFile {
+    version: u32,
+    principals: Vec<PrincipalRecord>,
+}
+
+/// Principals persisted as JSON at a path, read once on open and rewritten
+/// on every change. Lookups are answered from memory, so edits made to the
+/// file behind a running store's back are not seen until it is reopened.
+#[derive(Debug)]
+pub struct FilePrincipalStore {
+    path: PathBuf,
+    principals: Mutex<Principals>,
+}
+
+impl FilePrincipalStore {
+    /// Open the store persisted at `path`, creating it on first change.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, PrincipalError> {
+        let path = path.into();
+        let storage =
+            |err: &dyn fmt::Display| PrincipalError::Storage(format!("{}: {err}", path.display()));
+        let principals = match fs::read(&path) {
+            Ok(bytes) => {
+                let file: PrincipalsFile =
+                    serde_json::from_slice(&bytes).map_err(|err| storage(&err))?;
+                if file.version != PRINCIPALS_VERSION {
+                    return Err(storage(&format_args!(
+                        "unsupported principals version {}",
+                        file.version
+                    )));
+                }
+                file.principals
+                    .into_iter()
+                    .map(|record| (record.principal.clone(), record))
+                    .collect()
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Principals::new(),
+            Err(err) => return Err(storage(&err)),
+        };
+        Ok(Self {
+            path,
+            principals: Mutex::new(principals),
+        })
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Principals> {
+        self.principals
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Apply `change` to a copy of the principals and keep it only once the
+    /// copy is on disk.
+    fn update(
+        &self,
+        change: impl FnOnce(&mut Principals) -> Result<PrincipalRecord, PrincipalError>,
+    ) -> Result<PrincipalRecord, PrincipalError> {
+        let mut principals = self.lock();
+        let mut next = principals.clone();
+        let record = change(&mut next)?;
+        self.persist(&next)?;
+        *principals = next;
+        Ok(record)
+    }
+
+    fn persist(&self, principals: &Principals) -> Result<(), PrincipalError> {
+        let path = &self.path;
+        let io =
+            |err: std::io::Error| PrincipalError::Storage(format!("{}: {err}", path.display()));
+        let file = PrincipalsFile {
+            version: PRINCIPALS_VERSION,
+            principals: principals.values().cloned().collect(),
+        };
+        let bytes = serde_json::to_vec_pretty(&file)
+            .map_err(|err| PrincipalError::Storage(format!("serializing principals: {err}")))?;
+        storage_atomic::write(path, &bytes).map_err(io)
+    }
+}
+
+impl PrincipalStore for FilePrincipalStore {
+    fn get(&self, principal: &str) -> Result<Option<PrincipalRecord>, PrincipalError> {
+        Ok(self.lock().get(principal).cloned())
+    }
+
+    fn list(&self) -> Result<Vec<PrincipalRecord>, PrincipalError> {
+        Ok(self.lock().values().cloned().collect())
+    }
+
+    fn add(
+        &self,
+        principal: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<PrincipalRecord, PrincipalError> {
+        self.update(|principals| add_to(principals, principal, tenant_id))
+    }
+
+    fn remove(&self, principal: &str) -> Result<PrincipalRecord, PrincipalError> {
+        self.update(|principals| remove_from(principals, principal))
+    }
+
+    fn set_disabled(
+        &self,
+        principal: &str,
+        disabled: bool,
+    ) -> Result<PrincipalRecord, PrincipalError> {
+        self.update(|principals| set_disabled_in(principals, principal, disabled))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disabled_and_removed_principals_are_inactive() {
+        let store = InMemoryPrincipalStore::with_principals(["alice"]);
+        assert!(store.is_active("alice"));
+        assert!(!store.is_active("bob"));
+
+        store.add("bob", Some("team-b")).unwrap();
+        assert_eq!(
+            store.add("bob", None),
+            Err(PrincipalError::Duplicate("bob".into()))
+        );
+        assert_eq!(
+            store.add(" ", None),
+            Err(PrincipalError::InvalidName(" ".into()))
+        );
+        assert_eq!(
+            store.add("carol", Some("team/c")),
+            Err(PrincipalError::InvalidTenant("team/c".into()))
+        );
+        assert_eq!(store.tenant_of("bob").as_deref(), Some("team-b"));
+        assert_eq!(store.tenant_of("alice"), None);
+
+        store.set_disabled("alice", true).unwrap();
+        assert!(!store.is_active("alice"));
+        store.set_disabled("alice", false).unwrap();
+        assert!(store.is_active("alice"));
+
+        store.remove("bob").unwrap();
+        assert!(!store.is_active("bob"));
+        assert_eq!(
+            store.set_disabled("bob", true),
+            Err(PrincipalError::Unknown("bob".into()))
+        );
+    }
+
+    #[test]
+    fn file_store_survives_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state/principals.json");
+        let store = FilePrincipalStore::open(&path).unwrap();
+        assert!(store.list().unwrap().is_empty());
+        store.add("alice", None).unwrap();
+        store.add("bob", Some("team-b")).unwrap();
+        store.set_disabled("bob", true).unwrap();
+        // A failed change leaves both memory and disk as they were.
+        store.add("alice", None).unwrap_err();
+
+        let reopened = FilePrincipalStore::open(&path).unwrap();
+        assert_eq!(
+            reopened.list().unwrap(),
+            vec![
+                PrincipalRecord {
+                    principal: "alice".into(),
+                    disabled: false,
+                    tenant_id: None,
+                },
+                PrincipalRecord {
+                    principal: "bob".into(),
+                    disabled: true,
+                    tenant_id: Some("team-b".into()),
+                },
+            ]
+        );
+        assert!(reopened.is_active("alice"));
+        assert!(!reopened.is_active("bob"));
+
+        fs::write(&path, br#"{"version":9,"principals":[]}"#).unwrap();
+        assert!(matches!(
+            FilePrincipalStore::open(&path),
+            Err(PrincipalError::Storage(_))
+        ));
+    }
+}
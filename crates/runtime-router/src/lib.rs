@@ -1,15 +1,16 @@
 //! Runtime command router contract and lightweight testing utilities.
 
 use std::cmp::Reverse;
-use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
 use std::fs::File;
 use std::io::{self, Read};
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
 use thiserror::Error;
 use tokio::sync::Mutex;
 use uuid::Uuid;
@@ -27,6 +28,13 @@ pub struct SessionContext {
     pub token_id: Option<Uuid>,
     /// Optional peer identity as reported by the transport layer.
     pub peer: Option<String>,
+    /// Unix time, in seconds, the session token expires at.
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+    /// Tenant whose repositories the session is confined to; `None` for
+    /// operator sessions, which may reach every tenant's.
+    #[serde(default)]
+    pub tenant_id: Option<String>,
 }
 
 impl SessionContext {
@@ -38,10 +46,48 @@ impl SessionContext {
             trace_id: Uuid::new_v4(),
             token_id: None,
             peer: None,
+            expires_at: None,
+            tenant_id: None,
+        }
+    }
+
+    /// Confine the context to `tenant_id`'s repositories.
+    #[must_use]
+    pub fn with_tenant(mut self, tenant_id: impl Into<String>) -> Self {
+        self.tenant_id = Some(tenant_id.into());
+        self
+    }
+
+    /// Whether the session may touch data owned by `owner` (`None` for data
+    /// outside any tenant). Operator sessions may touch anything; tenant
+    /// sessions only their own tenant's data.
+    #[must_use]
+    pub fn can_access_tenant(&self, owner: Option<&str>) -> bool {
+        match &self.tenant_id {
+            None => true,
+            Some(tenant_id) => owner == Some(tenant_id.as_str()),
         }
     }
 }
 
+/// Check that `tenant_id` is a usable tenant name: 1 to 64 ASCII letters,
+/// digits, `-` or `_`. Tenant ids become part of store keys, so they may not
+/// contain the separators those use.
+pub fn validate_tenant_id(tenant_id: &str) -> Result<(), RouterError> {
+    let valid = !tenant_id.is_empty()
+        && tenant_id.len() <= 64
+        && tenant_id
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_');
+    if valid {
+        Ok(())
+    } else {
+        Err(RouterError::InvalidRequest {
+            detail: format!("invalid tenant id {tenant_id:?}"),
+        })
+    }
+}
+
 /// Normalized command forwarded from a transport adapter.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct RouterCommand {
@@ -59,6 +105,93 @@ impl RouterCommand {
             payload,
         }
     }
+
+    /// A [`BATCH_COMMAND`] running `commands` in order.
+    pub fn batch(commands: impl IntoIterator<Item = RouterCommand>) -> Self {
+        let commands: Vec<Value> = commands
+            .into_iter()
+            .map(|command| json!({ "command": command.name, "payload": command.payload }))
+            .collect();
+        Self::new(BATCH_COMMAND, json!({ "commands": commands }))
+    }
+
+    /// Mark a [`RouterCommand::batch`] atomic.
+    #[must_use]
+    pub fn with_atomic(mut self) -> Self {
+        if let Value::Object(fields) = &mut self.payload {
+            fields.insert("atomic".into(), Value::Bool(true));
+        }
+        self
+    }
+}
+
+/// Command running several commands in one request
+/// (`{ commands: [{ command, payload? }], atomic? }`).
+///
+/// [`HandlerRouter`] runs the items in order under the batch's session,
+/// checking each one's capabilities, and answers with one result per item
+/// (`{ results: [{ status_code, payload?, page?, error? }] }`); a failing
+/// item does not stop the ones after it. Batches cannot nest.
+///
+/// An `atomic` batch only starts when every item's handler is
+/// [`compensable`](CommandHandler::compensable). The first failing item
+/// stops it, and the items that succeeded are compensated in reverse
+/// order; the answer adds `committed`, marks undone items `compensated`
+/// (with a `compensation_error` when undoing failed) and items that never
+/// ran `skipped`.
+pub const BATCH_COMMAND: &str = "batch";
+
+/// Most commands one batch may carry.
+pub const MAX_BATCH_COMMANDS: usize = 64;
+
+/// Command answering who the caller is, as a [`SessionInfo`].
+///
+/// [`HandlerRouter`] answers it for every session, without capability
+/// checks, ahead of any handler registered under the same name. It may run
+/// in batches, atomic ones included.
+pub const WHOAMI_COMMAND: &str = "auth.whoami";
+
+/// Answer to [`WHOAMI_COMMAND`]: the parts of a [`SessionContext`] the
+/// adapter derived from the caller's token and connection.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub principal: String,
+    pub capabilities: Vec<String>,
+    pub token_id: Option<Uuid>,
+    /// Unix time, in seconds, the session token expires at.
+    pub expires_at: Option<u64>,
+    pub peer: Option<String>,
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+}
+
+impl From<&SessionContext> for SessionInfo {
+    fn from(ctx: &SessionContext) -> Self {
+        Self {
+            principal: ctx.principal.clone(),
+            capabilities: ctx.capabilities.clone(),
+            token_id: ctx.token_id,
+            expires_at: ctx.expires_at,
+            peer: ctx.peer.clone(),
+            tenant_id: ctx.tenant_id.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BatchRequest {
+    commands: Vec<BatchItem>,
+    #[serde(default)]
+    atomic: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BatchItem {
+    command: String,
+    #[serde(default)]
+    payload: Value,
 }
 
 /// Successful response emitted by the router.
@@ -70,6 +203,9 @@ pub struct RouterResponse {
     pub payload: Value,
     /// Optional diagnostics for telemetry correlation.
     pub diagnostics: Vec<String>,
+    /// Set when `payload` holds one page of a larger result.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<Page>,
 }
 
 impl RouterResponse {
@@ -80,8 +216,111 @@ impl RouterResponse {
             status_code: 200,
             payload,
             diagnostics: Vec::new(),
+            page: None,
+        }
+    }
+
+    /// OK response carrying one page of a larger result.
+    #[must_use]
+    pub const fn paged(payload: Value, page: Page) -> Self {
+        Self {
+            status_code: 200,
+            payload,
+            diagnostics: Vec::new(),
+            page: Some(page),
         }
     }
+
+    /// The payload with the pagination envelope, if any, added under a
+    /// `page` key, for transports that only forward the payload. Payloads
+    /// that are not objects are returned unchanged.
+    #[must_use]
+    pub fn into_payload_with_page(self) -> Value {
+        match (self.payload, self.page) {
+            (Value::Object(mut fields), Some(page)) => {
+                fields.insert(
+                    "page".into(),
+                    serde_json::to_value(page).unwrap_or(Value::Null),
+                );
+                Value::Object(fields)
+            }
+            (payload, _) => payload,
+        }
+    }
+}
+
+/// Pagination envelope describing where a page sits in a larger result.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Page {
+    /// Opaque cursor requesting the page after this one; `None` on the last
+    /// page.
+    pub next_cursor: Option<String>,
+    /// Page size the handler applied.
+    pub page_size: usize,
+    /// Size of the whole result, when the handler can tell.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_estimate: Option<u64>,
+}
+
+impl Page {
+    /// Envelope for a page of `page_size` whose successor starts at
+    /// `next_offset`.
+    #[must_use]
+    pub fn at_offset(
+        next_offset: Option<usize>,
+        page_size: usize,
+        total_estimate: Option<u64>,
+    ) -> Self {
+        Self {
+            next_cursor: next_offset.map(|offset| offset.to_string()),
+            page_size,
+            total_estimate,
+        }
+    }
+}
+
+/// Pagination fields of a request payload; flatten it into a handler's
+/// request type.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageRequest {
+    /// `next_cursor` of the previous page; absent for the first page.
+    #[serde(default)]
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub page_size: Option<usize>,
+}
+
+impl PageRequest {
+    /// Requested page size, or `default`, within `1..=max`.
+    #[must_use]
+    pub fn size(&self, default: usize, max: usize) -> usize {
+        self.page_size.unwrap_or(default).clamp(1, max.max(1))
+    }
+
+    /// Position the cursor points at; zero for the first page.
+    pub fn offset(&self) -> Result<usize, RouterError> {
+        self.cursor.as_deref().map_or(Ok(0), |cursor| {
+            cursor.parse().map_err(|_| RouterError::InvalidRequest {
+                detail: format!("invalid page cursor '{cursor}'"),
+            })
+        })
+    }
+
+    /// The page of `items` this request selects, with its envelope.
+    pub fn paginate<T>(
+        &self,
+        items: Vec<T>,
+        default: usize,
+        max: usize,
+    ) -> Result<(Vec<T>, Page), RouterError> {
+        let size = self.size(default, max);
+        let offset = self.offset()?;
+        let total = items.len();
+        let page: Vec<T> = items.into_iter().skip(offset).take(size).collect();
+        let end = offset.saturating_add(size);
+        let next = (end < total).then_some(end);
+        Ok((page, Page::at_offset(next, size, Some(total as u64))))
+    }
 }
 
 /// Router errors mapped back to transport adapters.
@@ -99,6 +338,9 @@ pub enum RouterError {
     /// Any other unexpected failure.
     #[error("internal error: {detail}")]
     Internal { detail: String },
+    /// The request was refused to shed load; retry after `retry_after_ms`.
+    #[error("throttled: {detail}")]
+    Throttled { detail: String, retry_after_ms: u64 },
 }
 
 impl RouterError {
@@ -110,7 +352,75 @@ impl RouterError {
             Self::InvalidRequest { .. } => 400,
             Self::NotFound { .. } => 404,
             Self::Internal { .. } => 500,
+            Self::Throttled { .. } => 429,
+        }
+    }
+
+    /// How long the client should wait before retrying, when the error
+    /// says.
+    #[must_use]
+    pub const fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::Throttled { retry_after_ms, .. } => Some(Duration::from_millis(*retry_after_ms)),
+            _ => None,
+        }
+    }
+}
+
+/// Per-principal request budget enforced by [`HandlerRouter::limit_rate`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimit {
+    /// Requests a principal may send at once after being idle.
+    pub burst: u32,
+    /// Requests per second the budget refills at.
+    pub per_second: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token buckets keyed by principal.
+#[derive(Debug)]
+struct RateLimiter {
+    limit: RateLimit,
+    buckets: std::sync::Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            buckets: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take `cost` requests from `principal`'s budget, or fail with how
+    /// long until the budget covers them. Costs above the burst count as
+    /// the burst so large batches are slowed rather than refused forever.
+    fn acquire(&self, principal: &str, cost: usize) -> Result<(), Duration> {
+        let burst = f64::from(self.limit.burst.max(1));
+        let cost = (cost as f64).clamp(1.0, burst);
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|err| err.into_inner());
+        let bucket = buckets.entry(principal.to_string()).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        let refill = now.duration_since(bucket.updated).as_secs_f64() * self.limit.per_second;
+        bucket.tokens = (bucket.tokens + refill).min(burst);
+        bucket.updated = now;
+        if bucket.tokens >= cost {
+            bucket.tokens -= cost;
+            return Ok(());
         }
+        Err(
+            Duration::try_from_secs_f64((cost - bucket.tokens) / self.limit.per_second)
+                .unwrap_or(Duration::MAX),
+        )
     }
 }
 
@@ -177,6 +487,7 @@ impl CommandRouter for RecordingRouter {
                 status_code: 200,
                 payload: Value::Null,
                 diagnostics: vec!["default-script".into()],
+                page: None,
             });
         }
 
@@ -187,6 +498,571 @@ impl CommandRouter for RecordingRouter {
 /// Shared pointer helper for adapters.
 pub type SharedRouter = Arc<dyn CommandRouter>;
 
+/// Handler for a single named command registered on a [`HandlerRouter`].
+#[async_trait]
+pub trait CommandHandler: Send + Sync {
+    /// Execute the command with its payload.
+    async fn handle(
+        &self,
+        ctx: &SessionContext,
+        payload: Value,
+    ) -> Result<RouterResponse, RouterError>;
+
+    /// Whether [`CommandHandler::compensate`] can undo this command, which
+    /// lets it run in an atomic batch. Read-only handlers may return `true`
+    /// and keep the no-op `compensate`.
+    fn compensable(&self) -> bool {
+        false
+    }
+
+    /// Undo a successful `handle` of `payload` that answered `response`,
+    /// because a later command of its atomic batch failed.
+    async fn compensate(
+        &self,
+        _ctx: &SessionContext,
+        _payload: Value,
+        _response: &RouterResponse,
+    ) -> Result<(), RouterError> {
+        Ok(())
+    }
+}
+
+struct Route {
+    capabilities: Vec<String>,
+    handler: Arc<dyn CommandHandler>,
+}
+
+/// Built-in handler for [`WHOAMI_COMMAND`].
+struct WhoamiHandler;
+
+#[async_trait]
+impl CommandHandler for WhoamiHandler {
+    async fn handle(
+        &self,
+        ctx: &SessionContext,
+        _payload: Value,
+    ) -> Result<RouterResponse, RouterError> {
+        let info =
+            serde_json::to_value(SessionInfo::from(ctx)).map_err(|err| RouterError::Internal {
+                detail: err.to_string(),
+            })?;
+        Ok(RouterResponse::ok(info))
+    }
+
+    fn compensable(&self) -> bool {
+        true
+    }
+}
+
+/// Router dispatching commands to handlers registered by name.
+///
+/// Each route may require capabilities; sessions lacking any of them are
+/// rejected before the handler runs. Unknown commands map to
+/// [`RouterError::NotFound`].
+#[derive(Default)]
+pub struct HandlerRouter {
+    routes: HashMap<String, Route>,
+    limiter: RwLock<Option<RateLimiter>>,
+}
+
+impl HandlerRouter {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `handler` for `name` without capability requirements.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        handler: Arc<dyn CommandHandler>,
+    ) -> &mut Self {
+        self.register_with_capabilities(name, Vec::new(), handler)
+    }
+
+    /// Register `handler` for `name`, requiring every capability in `capabilities`.
+    pub fn register_with_capabilities(
+        &mut self,
+        name: impl Into<String>,
+        capabilities: Vec<String>,
+        handler: Arc<dyn CommandHandler>,
+    ) -> &mut Self {
+        self.routes.insert(
+            name.into(),
+            Route {
+                capabilities,
+                handler,
+            },
+        );
+        self
+    }
+
+    /// Throttle each principal to `limit`; requests over budget fail with
+    /// [`RouterError::Throttled`] saying when the budget will cover them. A
+    /// batch costs one request per item.
+    pub fn limit_rate(&mut self, limit: RateLimit) -> &mut Self {
+        self.set_rate_limit(Some(limit));
+        self
+    }
+
+    /// Replace the request budget of a running router, or lift it with
+    /// `None`. Principals keep the requests left in their budgets, capped
+    /// at the new burst.
+    pub fn set_rate_limit(&self, limit: Option<RateLimit>) {
+        let mut limiter = self.limiter.write().unwrap_or_else(PoisonError::into_inner);
+        match (limiter.as_mut(), limit) {
+            (Some(current), Some(limit)) => current.limit = limit,
+            (_, limit) => *limiter = limit.map(RateLimiter::new),
+        }
+    }
+
+    /// The request budget in force, if any.
+    #[must_use]
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.limiter
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .map(|limiter| limiter.limit)
+    }
+
+    /// Names of the registered commands, sorted.
+    #[must_use]
+    pub fn command_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.routes.keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
+
+impl std::fmt::Debug for HandlerRouter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HandlerRouter")
+            .field("commands", &self.command_names())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl CommandRouter for HandlerRouter {
+    async fn dispatch(
+        &self,
+        ctx: SessionContext,
+        command: RouterCommand,
+    ) -> Result<RouterResponse, RouterError> {
+        let cost = if command.name == BATCH_COMMAND {
+            command.payload["commands"].as_array().map_or(1, Vec::len)
+        } else {
+            1
+        };
+        let acquired = self
+            .limiter
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .map(|limiter| limiter.acquire(&ctx.principal, cost));
+        if let Some(Err(wait)) = acquired {
+            // Round up so clients never retry before the budget refills.
+            let retry_after_ms =
+                u64::try_from(wait.as_nanos().div_ceil(1_000_000)).unwrap_or(u64::MAX);
+            return Err(RouterError::Throttled {
+                detail: format!("principal '{}' exceeded its request budget", ctx.principal),
+                retry_after_ms,
+            });
+        }
+        if command.name == BATCH_COMMAND {
+            return self.dispatch_batch(&ctx, command.payload).await;
+        }
+        self.dispatch_route(&ctx, command).await
+    }
+}
+
+impl HandlerRouter {
+    /// The handler for `name`, if `ctx` holds the capabilities it requires.
+    fn route(&self, ctx: &SessionContext, name: &str) -> Result<&dyn CommandHandler, RouterError> {
+        if name == BATCH_COMMAND {
+            return Err(RouterError::InvalidRequest {
+                detail: "batches cannot nest".into(),
+            });
+        }
+        if name == WHOAMI_COMMAND {
+            return Ok(&WhoamiHandler);
+        }
+        let route = self.routes.get(name).ok_or_else(|| RouterError::NotFound {
+            detail: format!("command '{name}' is not registered"),
+        })?;
+        if let Some(missing) = route
+            .capabilities
+            .iter()
+            .find(|cap| !ctx.capabilities.contains(cap))
+        {
+            return Err(RouterError::Unauthorized {
+                detail: format!(
+                    "command '{name}' requires capability '{missing}' not granted to principal '{}'",
+                    ctx.principal
+                ),
+            });
+        }
+        Ok(route.handler.as_ref())
+    }
+
+    async fn dispatch_route(
+        &self,
+        ctx: &SessionContext,
+        command: RouterCommand,
+    ) -> Result<RouterResponse, RouterError> {
+        self.route(ctx, &command.name)?
+            .handle(ctx, command.payload)
+            .await
+    }
+
+    async fn dispatch_batch(
+        &self,
+        ctx: &SessionContext,
+        payload: Value,
+    ) -> Result<RouterResponse, RouterError> {
+        let request: BatchRequest =
+            serde_json::from_value(payload).map_err(|err| RouterError::InvalidRequest {
+                detail: err.to_string(),
+            })?;
+        if request.commands.len() > MAX_BATCH_COMMANDS {
+            return Err(RouterError::InvalidRequest {
+                detail: format!(
+                    "batch of {} commands exceeds the limit of {MAX_BATCH_COMMANDS}",
+                    request.commands.len()
+                ),
+            });
+        }
+        if request.atomic {
+            return self.dispatch_atomic(ctx, request.commands).await;
+        }
+        let mut results = Vec::with_capacity(request.commands.len());
+        for item in request.commands {
+            let outcome = self
+                .dispatch_route(ctx, RouterCommand::new(item.command, item.payload))
+                .await;
+            results.push(batch_result(outcome));
+        }
+        Ok(RouterResponse::ok(json!({ "results": results })))
+    }
+
+    async fn dispatch_atomic(
+        &self,
+        ctx: &SessionContext,
+        items: Vec<BatchItem>,
+    ) -> Result<RouterResponse, RouterError> {
+        // Resolve every route first so nothing runs unless the whole batch
+        // can be undone.
+        let mut handlers = Vec::with_capacity(items.len());
+        for item in &items {
+            let handler = self.route(ctx, &item.command)?;
+            if !handler.compensable() {
+                return Err(RouterError::InvalidRequest {
+                    detail: format!(
+                        "command '{}' cannot be rolled back and may not run in an atomic batch",
+                        item.command
+                    ),
+                });
+            }
+            handlers.push(handler);
+        }
+
+        let mut results = Vec::with_capacity(items.len());
+        let mut completed = Vec::new();
+        let mut failed = false;
+        for (item, &handler) in items.into_iter().zip(&handlers) {
+            if failed {
+                results.push(json!({ "skipped": true }));
+                continue;
+            }
+            match handler.handle(ctx, item.payload.clone()).await {
+                Ok(response) => {
+                    completed.push((results.len(), handler, item.payload, response.clone()));
+                    results.push(batch_result(Ok(response)));
+                }
+                Err(err) => {
+                    failed = true;
+                    results.push(batch_result(Err(err)));
+                }
+            }
+        }
+        if failed {
+            for (index, handler, payload, response) in completed.into_iter().rev() {
+                let undone = handler.compensate(ctx, payload, &response).await;
+                results[index]["compensated"] = json!(undone.is_ok());
+                if let Err(err) = undone {
+                    results[index]["compensation_error"] = json!(err.to_string());
+                }
+            }
+        }
+        Ok(RouterResponse::ok(
+            json!({ "committed": !failed, "results": results }),
+        ))
+    }
+}
+
+/// One entry of a batch's `results`.
+fn batch_result(outcome: Result<RouterResponse, RouterError>) -> Value {
+    match outcome {
+        Ok(response) => {
+            let mut result = json!({
+                "status_code": response.status_code,
+                "payload": response.payload,
+            });
+            if let Some(page) = response.page {
+                result["page"] = json!(page);
+            }
+            result
+        }
+        Err(err) => json!({
+            "status_code": err.status_code(),
+            "error": err.to_string(),
+        }),
+    }
+}
+
+/// Command reporting runtime diagnostics; see [`StatusRegistry`].
+pub const STATUS_COMMAND: &str = "status";
+
+/// Subsystem contributing a section to the [`STATUS_COMMAND`] answer.
+#[async_trait]
+pub trait StatusProvider: Send + Sync {
+    /// Current state of the subsystem, as a JSON object.
+    async fn report(&self) -> Result<Value, RouterError>;
+}
+
+/// Handler for [`STATUS_COMMAND`], assembling its answer from the
+/// providers registered with it.
+///
+/// The answer is `{ version, uptime_ms, subsystems: { <name>: report } }`,
+/// where `uptime_ms` counts from the registry's creation. A provider that
+/// fails reports `{ error }` in its section rather than failing the
+/// command. Providers may register after the registry itself is registered
+/// on a router, so adapters bound to that router can add themselves.
+pub struct StatusRegistry {
+    version: String,
+    started: Instant,
+    providers: RwLock<BTreeMap<String, Arc<dyn StatusProvider>>>,
+}
+
+impl StatusRegistry {
+    /// An empty registry reporting `version`.
+    pub fn new(version: impl Into<String>) -> Self {
+        Self {
+            version: version.into(),
+            started: Instant::now(),
+            providers: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Report `provider` under `name`, replacing any provider already
+    /// registered there.
+    pub fn register(&self, name: impl Into<String>, provider: Arc<dyn StatusProvider>) {
+        self.providers
+            .write()
+            .expect("status registry lock poisoned")
+            .insert(name.into(), provider);
+    }
+
+    /// Names of the registered providers, sorted.
+    #[must_use]
+    pub fn provider_names(&self) -> Vec<String> {
+        self.providers
+            .read()
+            .expect("status registry lock poisoned")
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    /// The [`STATUS_COMMAND`] answer as of now.
+    pub async fn report(&self) -> Value {
+        let providers: Vec<(String, Arc<dyn StatusProvider>)> = self
+            .providers
+            .read()
+            .expect("status registry lock poisoned")
+            .iter()
+            .map(|(name, provider)| (name.clone(), Arc::clone(provider)))
+            .collect();
+        let mut subsystems = serde_json::Map::new();
+        for (name, provider) in providers {
+            let report = provider
+                .report()
+                .await
+                .unwrap_or_else(|err| json!({ "error": err.to_string() }));
+            subsystems.insert(name, report);
+        }
+        json!({
+            "version": self.version,
+            "uptime_ms": u64::try_from(self.started.elapsed().as_millis()).unwrap_or(u64::MAX),
+            "subsystems": subsystems,
+        })
+    }
+}
+
+impl std::fmt::Debug for StatusRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StatusRegistry")
+            .field("version", &self.version)
+            .field("providers", &self.provider_names())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl CommandHandler for StatusRegistry {
+    async fn handle(
+        &self,
+        _ctx: &SessionContext,
+        _payload: Value,
+    ) -> Result<RouterResponse, RouterError> {
+        Ok(RouterResponse::ok(self.report().await))
+    }
+
+    fn compensable(&self) -> bool {
+        true
+    }
+}
+
+/// Command applying new settings to running components (`{ <name>:
+/// config, ... }`); see [`ReloadRegistry`].
+pub const CONFIG_RELOAD_COMMAND: &str = "config.reload";
+
+/// Component whose settings can change while it runs.
+pub trait Reloadable: Send + Sync {
+    /// Fail unless `config` could be applied by [`Reloadable::reload`].
+    fn check(&self, config: &Value) -> Result<(), RouterError>;
+
+    /// Apply `config` in one step; requests in flight finish under the old
+    /// settings.
+    fn reload(&self, config: Value) -> Result<(), RouterError>;
+}
+
+/// Handler for [`CONFIG_RELOAD_COMMAND`], applying each section of the
+/// payload to the component registered under its name.
+///
+/// Every section is checked before any is applied, so a payload naming an
+/// unknown component or carrying settings one of them rejects changes
+/// nothing. The answer lists the reloaded components
+/// (`{ reloaded: [name] }`). Register it with an administrative capability.
+pub struct ReloadRegistry {
+    targets: RwLock<BTreeMap<String, Arc<dyn Reloadable>>>,
+}
+
+impl ReloadRegistry {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            targets: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Reload `target` from the section named `name`, replacing any
+    /// component already registered there.
+    pub fn register(&self, name: impl Into<String>, target: Arc<dyn Reloadable>) {
+        self.targets
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(name.into(), target);
+    }
+
+    /// Names of the registered components, sorted.
+    #[must_use]
+    pub fn target_names(&self) -> Vec<String> {
+        self.targets
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .keys()
+            .cloned()
+            .collect()
+    }
+}
+
+impl Default for ReloadRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for ReloadRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReloadRegistry")
+            .field("targets", &self.target_names())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl CommandHandler for ReloadRegistry {
+    async fn handle(
+        &self,
+        ctx: &SessionContext,
+        payload: Value,
+    ) -> Result<RouterResponse, RouterError> {
+        let Value::Object(sections) = payload else {
+            return Err(RouterError::InvalidRequest {
+                detail: "expected an object of configuration sections".into(),
+            });
+        };
+        let mut planned = Vec::with_capacity(sections.len());
+        {
+            let targets = self.targets.read().unwrap_or_else(PoisonError::into_inner);
+            for (name, config) in sections {
+                let target = targets
+                    .get(&name)
+                    .ok_or_else(|| RouterError::InvalidRequest {
+                        detail: format!("no reloadable component named '{name}'"),
+                    })?;
+                target.check(&config)?;
+                planned.push((name, Arc::clone(target), config));
+            }
+        }
+        let mut reloaded = Vec::with_capacity(planned.len());
+        for (name, target, config) in planned {
+            target.reload(config)?;
+            tracing::info!(principal = %ctx.principal, component = %name, "configuration reloaded");
+            reloaded.push(name);
+        }
+        Ok(RouterResponse::ok(json!({ "reloaded": reloaded })))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RouterReload {
+    rate_limit: Option<RateLimit>,
+}
+
+impl RouterReload {
+    fn parse(config: Value) -> Result<Self, RouterError> {
+        let reload: Self =
+            serde_json::from_value(config).map_err(|err| RouterError::InvalidRequest {
+                detail: err.to_string(),
+            })?;
+        if let Some(limit) = reload.rate_limit {
+            if !(limit.per_second.is_finite() && limit.per_second > 0.0) {
+                return Err(RouterError::InvalidRequest {
+                    detail: "rate_limit.per_second must be positive".into(),
+                });
+            }
+        }
+        Ok(reload)
+    }
+}
+
+/// Reloads `{ rate_limit: { burst, per_second } | null }`; `null` lifts
+/// the limit.
+impl Reloadable for HandlerRouter {
+    fn check(&self, config: &Value) -> Result<(), RouterError> {
+        RouterReload::parse(config.clone()).map(|_| ())
+    }
+
+    fn reload(&self, config: Value) -> Result<(), RouterError> {
+        self.set_rate_limit(RouterReload::parse(config)?.rate_limit);
+        Ok(())
+    }
+}
+
 /// Routing matrix describing cross-repository adjacency and weights.
 #[derive(Debug, Clone)]
 pub struct RoutingMatrix {
@@ -302,6 +1178,7 @@ mod tests {
     use std::collections::HashMap;
     use std::fs;
     use std::path::PathBuf;
+    use std::sync::atomic::{AtomicI64, Ordering};
 
     struct CapabilityRouter {
         required: HashMap<String, Vec<String>>,
@@ -380,6 +1257,464 @@ mod tests {
         assert_eq!(response_full.payload["executed"], json!("admin.reset"));
     }
 
+    struct EchoHandler;
+
+    #[async_trait]
+    impl CommandHandler for EchoHandler {
+        async fn handle(
+            &self,
+            ctx: &SessionContext,
+            payload: Value,
+        ) -> Result<RouterResponse, RouterError> {
+            Ok(RouterResponse::ok(
+                json!({ "principal": ctx.principal, "payload": payload }),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn handler_router_dispatches_registered_commands() {
+        let mut router = HandlerRouter::new();
+        router
+            .register("echo", Arc::new(EchoHandler))
+            .register_with_capabilities("admin.echo", vec!["admin".into()], Arc::new(EchoHandler));
+        assert_eq!(router.command_names(), vec!["admin.echo", "echo"]);
+
+        let ctx = SessionContext::new("alice", vec!["read".into()]);
+        let response = router
+            .dispatch(ctx.clone(), RouterCommand::new("echo", json!({ "n": 1 })))
+            .await
+            .expect("registered command dispatches");
+        assert_eq!(response.payload["principal"], json!("alice"));
+        assert_eq!(response.payload["payload"]["n"], json!(1));
+
+        let err = router
+            .dispatch(ctx.clone(), RouterCommand::new("admin.echo", json!({})))
+            .await
+            .expect_err("missing capability should fail");
+        assert_eq!(err.status_code(), 401);
+
+        let err = router
+            .dispatch(ctx, RouterCommand::new("missing", json!({})))
+            .await
+            .expect_err("unknown command should fail");
+        assert_eq!(err.status_code(), 404);
+    }
+
+    #[tokio::test]
+    async fn batches_run_each_command_and_report_per_item_results() {
+        let mut router = HandlerRouter::new();
+        router
+            .register("echo", Arc::new(EchoHandler))
+            .register_with_capabilities("admin.echo", vec!["admin".into()], Arc::new(EchoHandler));
+        let ctx = SessionContext::new("alice", vec!["read".into()]);
+
+        let response = router
+            .dispatch(
+                ctx.clone(),
+                RouterCommand::batch([
+                    RouterCommand::new("echo", json!({ "n": 1 })),
+                    RouterCommand::new("admin.echo", json!({})),
+                    RouterCommand::new("missing", json!({})),
+                    RouterCommand::new(BATCH_COMMAND, json!({ "commands": [] })),
+                    RouterCommand::new("echo", json!({ "n": 2 })),
+                ]),
+            )
+            .await
+            .expect("batch dispatches");
+        let results = response.payload["results"].as_array().expect("results");
+        let codes: Vec<&Value> = results.iter().map(|r| &r["status_code"]).collect();
+        assert_eq!(
+            codes,
+            [
+                &json!(200),
+                &json!(401),
+                &json!(404),
+                &json!(400),
+                &json!(200)
+            ]
+        );
+        assert_eq!(results[0]["payload"]["payload"]["n"], json!(1));
+        assert_eq!(results[4]["payload"]["payload"]["n"], json!(2));
+        assert!(results[1]["error"]
+            .as_str()
+            .is_some_and(|error| error.contains("admin")));
+
+        let oversized = RouterCommand::batch(
+            (0..=MAX_BATCH_COMMANDS).map(|_| RouterCommand::new("echo", Value::Null)),
+        );
+        let err = router
+            .dispatch(ctx, oversized)
+            .await
+            .expect_err("oversized batch");
+        assert_eq!(err.status_code(), 400);
+    }
+
+    /// Adds `payload.n` to a shared total; compensation subtracts it again
+    /// unless `payload.stuck` is set. `n` of zero fails.
+    struct CounterHandler {
+        total: Arc<AtomicI64>,
+    }
+
+    #[async_trait]
+    impl CommandHandler for CounterHandler {
+        async fn handle(
+            &self,
+            _ctx: &SessionContext,
+            payload: Value,
+        ) -> Result<RouterResponse, RouterError> {
+            let n = payload["n"].as_i64().unwrap_or_default();
+            if n == 0 {
+                return Err(RouterError::InvalidRequest {
+                    detail: "n must not be zero".into(),
+                });
+            }
+            let total = self.total.fetch_add(n, Ordering::SeqCst);
+            Ok(RouterResponse::ok(json!({ "total": total + n })))
+        }
+
+        fn compensable(&self) -> bool {
+            true
+        }
+
+        async fn compensate(
+            &self,
+            _ctx: &SessionContext,
+            payload: Value,
+            _response: &RouterResponse,
+        ) -> Result<(), RouterError> {
+            if payload["stuck"] == json!(true) {
+                return Err(RouterError::Internal {
+                    detail: "stuck".into(),
+                });
+            }
+            let n = payload["n"].as_i64().unwrap_or_default();
+            self.total.fetch_sub(n, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn atomic_batches_roll_back_completed_commands() {
+        let total = Arc::new(AtomicI64::new(0));
+        let mut router = HandlerRouter::new();
+        router
+            .register(
+                "add",
+                Arc::new(CounterHandler {
+                    total: Arc::clone(&total),
+                }),
+            )
+            .register("echo", Arc::new(EchoHandler));
+        let ctx = SessionContext::new("alice", Vec::new());
+        let atomic = |commands: Value| {
+            RouterCommand::new(
+                BATCH_COMMAND,
+                json!({ "atomic": true, "commands": commands }),
+            )
+        };
+        let load = || total.load(Ordering::SeqCst);
+
+        let response = router
+            .dispatch(
+                ctx.clone(),
+                atomic(json!([
+                    { "command": "add", "payload": { "n": 2 } },
+                    { "command": "add", "payload": { "n": 3 } },
+                ])),
+            )
+            .await
+            .expect("atomic batch");
+        assert_eq!(response.payload["committed"], json!(true));
+        assert_eq!(load(), 5);
+
+        let response = router
+            .dispatch(
+                ctx.clone(),
+                atomic(json!([
+                    { "command": "add", "payload": { "n": 1 } },
+                    { "command": "add", "payload": { "n": 4 } },
+                    { "command": "add", "payload": { "n": 0 } },
+                    { "command": "add", "payload": { "n": 8 } },
+                ])),
+            )
+            .await
+            .expect("atomic batch");
+        let results = &response.payload["results"];
+        assert_eq!(response.payload["committed"], json!(false));
+        assert_eq!(load(), 5);
+        assert_eq!(results[0]["compensated"], json!(true));
+        assert_eq!(results[1]["compensated"], json!(true));
+        assert_eq!(results[2]["status_code"], json!(400));
+        assert_eq!(results[3], json!({ "skipped": true }));
+
+        // Undo failures are reported rather than hidden.
+        let response = router
+            .dispatch(
+                ctx.clone(),
+                atomic(json!([
+                    { "command": "add", "payload": { "n": 1, "stuck": true } },
+                    { "command": "add", "payload": { "n": 0 } },
+                ])),
+            )
+            .await
+            .expect("atomic batch");
+        let first = &response.payload["results"][0];
+        assert_eq!(first["compensated"], json!(false));
+        assert_eq!(first["compensation_error"], json!("internal error: stuck"));
+        assert_eq!(load(), 6);
+
+        let err = router
+            .dispatch(
+                ctx,
+                atomic(json!([
+                    { "command": "add", "payload": { "n": 1 } },
+                    { "command": "echo" },
+                ])),
+            )
+            .await
+            .expect_err("echo cannot be rolled back");
+        assert_eq!(err.status_code(), 400);
+        assert_eq!(load(), 6);
+    }
+
+    #[tokio::test]
+    async fn whoami_reports_the_session_without_capability_checks() {
+        let mut router = HandlerRouter::new();
+        router.register_with_capabilities(
+            WHOAMI_COMMAND,
+            vec!["admin".into()],
+            Arc::new(EchoHandler),
+        );
+        let ctx = SessionContext {
+            token_id: Some(Uuid::nil()),
+            peer: Some("uds://cli".into()),
+            expires_at: Some(1_700_000_600),
+            ..SessionContext::new("alice", vec!["search".into()])
+        };
+
+        let response = router
+            .dispatch(ctx.clone(), RouterCommand::new(WHOAMI_COMMAND, Value::Null))
+            .await
+            .expect("whoami");
+        let info: SessionInfo = serde_json::from_value(response.payload).expect("session info");
+        assert_eq!(info, SessionInfo::from(&ctx));
+        assert_eq!(info.expires_at, Some(1_700_000_600));
+
+        // Read-only, so it may join atomic batches.
+        let response = router
+            .dispatch(
+                ctx,
+                RouterCommand::batch([RouterCommand::new(WHOAMI_COMMAND, Value::Null)])
+                    .with_atomic(),
+            )
+            .await
+            .expect("atomic batch");
+        assert_eq!(response.payload["committed"], json!(true));
+        assert_eq!(
+            response.payload["results"][0]["payload"]["principal"],
+            json!("alice")
+        );
+    }
+
+    #[test]
+    fn tenant_sessions_reach_only_their_own_tenant() {
+        let operator = SessionContext::new("ops", Vec::new());
+        assert!(operator.can_access_tenant(None));
+        assert!(operator.can_access_tenant(Some("team-a")));
+        let tenant = SessionContext::new("alice", Vec::new()).with_tenant("team-a");
+        assert!(tenant.can_access_tenant(Some("team-a")));
+        assert!(!tenant.can_access_tenant(Some("team-b")));
+        assert!(!tenant.can_access_tenant(None));
+
+        assert!(validate_tenant_id("team_a-1").is_ok());
+        for invalid in ["", "team@a", "team/a", &"t".repeat(65)] {
+            assert!(validate_tenant_id(invalid).is_err(), "{invalid:?}");
+        }
+    }
+
+    struct FixedStatus(Result<Value, RouterError>);
+
+    #[async_trait]
+    impl StatusProvider for FixedStatus {
+        async fn report(&self) -> Result<Value, RouterError> {
+            self.0.clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn status_collects_every_registered_provider() {
+        let status = Arc::new(StatusRegistry::new("1.2.3"));
+        let mut router = HandlerRouter::new();
+        router.register(STATUS_COMMAND, status.clone());
+        let ctx = SessionContext::new("alice", Vec::new());
+        let dispatch =
+            || router.dispatch(ctx.clone(), RouterCommand::new(STATUS_COMMAND, Value::Null));
+
+        let payload = dispatch().await.expect("status").payload;
+        assert_eq!(payload["version"], json!("1.2.3"));
+        assert!(payload["uptime_ms"].is_u64());
+        assert_eq!(payload["subsystems"], json!({}));
+
+        // Providers added after the route still show up.
+        status.register("store", Arc::new(FixedStatus(Ok(json!({ "records": 3 })))));
+        status.register(
+            "ledger",
+            Arc::new(FixedStatus(Err(RouterError::Internal {
+                detail: "unreadable".into(),
+            }))),
+        );
+        assert_eq!(status.provider_names(), vec!["ledger", "store"]);
+        let payload = dispatch().await.expect("status").payload;
+        assert_eq!(payload["subsystems"]["store"], json!({ "records": 3 }));
+        assert_eq!(
+            payload["subsystems"]["ledger"],
+            json!({ "error": "internal error: unreadable" })
+        );
+    }
+
+    #[tokio::test]
+    async fn config_reload_checks_every_section_before_applying_any() {
+        let reloads = Arc::new(ReloadRegistry::new());
+        let mut router = HandlerRouter::new();
+        router
+            .register("echo", Arc::new(EchoHandler))
+            .register_with_capabilities(
+                CONFIG_RELOAD_COMMAND,
+                vec!["admin".into()],
+                reloads.clone(),
+            );
+        let router = Arc::new(router);
+        reloads.register("router", router.clone());
+        let admin = SessionContext::new("root", vec!["admin".into()]);
+        let reload = |payload: Value| {
+            router.dispatch(
+                admin.clone(),
+                RouterCommand::new(CONFIG_RELOAD_COMMAND, payload),
+            )
+        };
+
+        let response =
+            reload(json!({ "router": { "rate_limit": { "burst": 8, "per_second": 1.0 } } }))
+                .await
+                .expect("reload");
+        assert_eq!(response.payload, json!({ "reloaded": ["router"] }));
+        let limit = RateLimit {
+            burst: 8,
+            per_second: 1.0,
+        };
+        assert_eq!(router.rate_limit(), Some(limit));
+
+        // The new budget applies to the next request.
+        let alice = SessionContext::new("alice", Vec::new());
+        let echo = || RouterCommand::new("echo", json!({}));
+        for _ in 0..8 {
+            router
+                .dispatch(alice.clone(), echo())
+                .await
+                .expect("within budget");
+        }
+        let err = router
+            .dispatch(alice.clone(), echo())
+            .await
+            .expect_err("over the reloaded budget");
+        assert_eq!(err.status_code(), 429);
+
+        // One bad section leaves every component as it was.
+        for payload in [
+            json!({ "router": { "rate_limit": null }, "store": {} }),
+            json!({ "router": { "rate_limit": { "burst": 5, "per_second": 0.0 } } }),
+            json!({ "router": { "rate_limit": null, "unknown": true } }),
+            json!("router"),
+        ] {
+            let err = reload(payload).await.expect_err("rejected reload");
+            assert_eq!(err.status_code(), 400);
+            assert_eq!(router.rate_limit(), Some(limit));
+        }
+
+        reload(json!({ "router": { "rate_limit": null } }))
+            .await
+            .expect("lift the limit");
+        assert_eq!(router.rate_limit(), None);
+        router.dispatch(alice, echo()).await.expect("unthrottled");
+    }
+
+    #[tokio::test]
+    async fn rate_limits_throttle_each_principal_with_a_retry_hint() {
+        let mut router = HandlerRouter::new();
+        router
+            .register("echo", Arc::new(EchoHandler))
+            .limit_rate(RateLimit {
+                burst: 2,
+                per_second: 1.0,
+            });
+        let alice = SessionContext::new("alice", Vec::new());
+        let echo = || RouterCommand::new("echo", json!({}));
+
+        router.dispatch(alice.clone(), echo()).await.expect("first");
+        router
+            .dispatch(alice.clone(), echo())
+            .await
+            .expect("second");
+        let err = router
+            .dispatch(alice.clone(), echo())
+            .await
+            .expect_err("over budget");
+        assert_eq!(err.status_code(), 429);
+        let wait = err.retry_after().expect("retry hint");
+        assert!(wait > Duration::from_millis(900) && wait <= Duration::from_secs(1));
+
+        // Budgets are per principal; a batch pays per item, capped at the
+        // burst so it can still run.
+        let bob = SessionContext::new("bob", Vec::new());
+        router
+            .dispatch(bob.clone(), RouterCommand::batch([echo(), echo(), echo()]))
+            .await
+            .expect("batch within burst");
+        let err = router
+            .dispatch(bob, echo())
+            .await
+            .expect_err("batch drained the budget");
+        assert!(err.retry_after().is_some());
+    }
+
+    #[test]
+    fn page_requests_walk_a_result_with_cursors() {
+        let request: PageRequest =
+            serde_json::from_value(json!({ "page_size": 2 })).expect("request");
+        let (first, page) = request
+            .paginate(vec![1, 2, 3, 4, 5], 10, 100)
+            .expect("first page");
+        assert_eq!(first, vec![1, 2]);
+        assert_eq!(page.page_size, 2);
+        assert_eq!(page.total_estimate, Some(5));
+
+        let request = PageRequest {
+            cursor: page.next_cursor,
+            page_size: Some(50),
+        };
+        let (rest, page) = request
+            .paginate(vec![1, 2, 3, 4, 5], 10, 3)
+            .expect("last page");
+        assert_eq!(rest, vec![3, 4, 5]);
+        assert_eq!(page.next_cursor, None);
+
+        let bad = PageRequest {
+            cursor: Some("zz".into()),
+            page_size: None,
+        };
+        assert_eq!(bad.offset().expect_err("bad cursor").status_code(), 400);
+
+        let response =
+            RouterResponse::paged(json!({ "items": [1] }), Page::at_offset(Some(1), 1, None));
+        let payload = response.into_payload_with_page();
+        assert_eq!(
+            payload["page"],
+            json!({ "next_cursor": "1", "page_size": 1 })
+        );
+        assert_eq!(payload["items"], json!([1]));
+    }
+
     #[test]
     fn routing_matrix_merges_latency_fixture() {
         let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
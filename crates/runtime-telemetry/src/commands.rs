@@ -0,0 +1,121 @@
+//! Admin router command reading a [`PeerTelemetry`](crate::PeerTelemetry).
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use runtime_router::{
+    CommandHandler, HandlerRouter, PageRequest, RouterError, RouterResponse, SessionContext,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::SharedPeerTelemetry;
+
+/// Command listing peer counters, most recently seen first, a page at a
+/// time (`{ prefix?, cursor?, page_size? }`).
+pub const PEERS_COMMAND: &str = "telemetry.peers";
+
+/// Peers listed per page when the request does not say.
+pub const DEFAULT_PEERS_PAGE_SIZE: usize = 50;
+/// Most peers one `telemetry.peers` page holds.
+pub const MAX_PEERS_PAGE_SIZE: usize = 500;
+
+/// Capability required for `telemetry.peers`.
+pub const ADMIN_CAPABILITY: &str = "admin";
+
+/// Register the telemetry commands on `router`.
+pub fn register_commands(router: &mut HandlerRouter, telemetry: SharedPeerTelemetry) {
+    router.register_with_capabilities(
+        PEERS_COMMAND,
+        vec![ADMIN_CAPABILITY.to_string()],
+        Arc::new(PeersHandler { telemetry }),
+    );
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PeersRequest {
+    /// Only peers whose name starts with this, such as `uds://`.
+    #[serde(default)]
+    prefix: Option<String>,
+    #[serde(flatten)]
+    page: PageRequest,
+}
+
+struct PeersHandler {
+    telemetry: SharedPeerTelemetry,
+}
+
+#[async_trait]
+impl CommandHandler for PeersHandler {
+    async fn handle(
+        &self,
+        _ctx: &SessionContext,
+        payload: Value,
+    ) -> Result<RouterResponse, RouterError> {
+        let request: PeersRequest = if payload.is_null() {
+            PeersRequest::default()
+        } else {
+            serde_json::from_value(payload).map_err(|err| RouterError::InvalidRequest {
+                detail: err.to_string(),
+            })?
+        };
+        let peers = self
+            .telemetry
+            .peers()
+            .into_iter()
+            .filter(|stats| {
+                request
+                    .prefix
+                    .as_deref()
+                    .map_or(true, |prefix| stats.peer.starts_with(prefix))
+            })
+            .collect();
+        let (peers, page) =
+            request
+                .page
+                .paginate(peers, DEFAULT_PEERS_PAGE_SIZE, MAX_PEERS_PAGE_SIZE)?;
+        Ok(RouterResponse::paged(
+            json!({ "peers": peers, "evicted": self.telemetry.evicted() }),
+            page,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use runtime_router::{CommandRouter, RouterCommand};
+
+    use super::*;
+    use crate::PeerTelemetry;
+
+    #[tokio::test]
+    async fn admins_list_peers_by_prefix() {
+        let telemetry = Arc::new(PeerTelemetry::default());
+        telemetry.frame_in("uds://cli/42", 64);
+        telemetry.auth_failure("uds://cli/42", "uid 1001 not permitted");
+        telemetry.frame_in("stdio", 10);
+        let mut router = HandlerRouter::new();
+        register_commands(&mut router, telemetry);
+
+        let admin = SessionContext::new("root", vec![ADMIN_CAPABILITY.into()]);
+        let reply = router
+            .dispatch(
+                admin,
+                RouterCommand::new(PEERS_COMMAND, json!({ "prefix": "uds://" })),
+            )
+            .await
+            .unwrap();
+        let peers = reply.payload["peers"].as_array().unwrap();
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0]["peer"], json!("uds://cli/42"));
+        assert_eq!(peers[0]["auth_failures"], json!(1));
+        assert_eq!(reply.page.unwrap().total_estimate, Some(1));
+
+        let user = SessionContext::new("bob", Vec::new());
+        let err = router
+            .dispatch(user, RouterCommand::new(PEERS_COMMAND, Value::Null))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status_code(), 401);
+    }
+}
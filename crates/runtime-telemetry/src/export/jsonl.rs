@@ -0,0 +1,38 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+
+use super::{ExportError, ExportRecord, TelemetryExporter};
+
+/// Appends each record to a file as one line of JSON.
+#[derive(Debug)]
+pub struct JsonlExporter {
+    file: Mutex<File>,
+}
+
+impl JsonlExporter {
+    /// Append to `path`, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ExportError> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path.as_ref())?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl TelemetryExporter for JsonlExporter {
+    fn export(&self, batch: &[ExportRecord]) -> Result<(), ExportError> {
+        let mut file = self.file.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut out = BufWriter::new(&mut *file);
+        for record in batch {
+            serde_json::to_writer(&mut out, record).map_err(std::io::Error::from)?;
+            out.write_all(b"\n")?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+}
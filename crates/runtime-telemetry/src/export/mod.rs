@@ -0,0 +1,520 @@
+//! Shipping adapter telemetry events out of the process.
+//!
+//! An adapter built with an [`ExportPipeline`] hands every event it records
+//! to the pipeline as an [`ExportRecord`] besides keeping it in memory. The
+//! pipeline queues records on a bounded channel and a worker thread hands
+//! them to a [`TelemetryExporter`] in batches of `batch_size`, or whatever
+//! has queued once `flush_interval_ms` passes. When the exporter falls
+//! behind and the queue fills, [`Overflow`] decides whether new records are
+//! dropped (and counted) or whether recording waits for room. Failed
+//! batches are counted and dropped rather than retried.
+//!
+//! Before a record is queued, records below `min_severity` are dropped and
+//! kinds listed in `sampling` keep only that share of their records, so
+//! per-request events can be thinned out. Auth failures are exported
+//! regardless.
+//!
+//! [`JsonlExporter`] appends records to a file, [`SyslogExporter`] sends
+//! RFC 5424 messages to a syslog daemon and [`OtlpExporter`] posts OTLP
+//! log batches to a collector. [`ExportConfig`] names one of them plus the
+//! batching settings, so each adapter can be given its own.
+
+mod jsonl;
+mod otlp;
+mod sampling;
+mod syslog;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::Severity;
+use sampling::Sampler;
+
+pub use jsonl::JsonlExporter;
+pub use otlp::OtlpExporter;
+pub use syslog::SyslogExporter;
+
+/// Records per batch unless the config says otherwise.
+pub const DEFAULT_BATCH_SIZE: usize = 128;
+/// How long a partial batch waits before it is exported anyway.
+pub const DEFAULT_FLUSH_INTERVAL_MS: u64 = 1_000;
+/// Records queued for the worker before [`Overflow`] applies.
+pub const DEFAULT_QUEUE_CAPACITY: usize = 4_096;
+
+/// One telemetry event as exporters see it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportRecord {
+    /// Milliseconds since the Unix epoch when the event was recorded.
+    pub timestamp_ms: u64,
+    /// Event type, prefixed by the adapter (`http.request`, `stdio.frame`).
+    pub kind: String,
+    #[serde(default)]
+    pub severity: Severity,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub principal: Option<String>,
+    pub message: String,
+}
+
+impl ExportRecord {
+    /// Record stamped with the current time, with the severity of its kind.
+    #[must_use]
+    pub fn now(kind: &str, principal: Option<&str>, message: &str) -> Self {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| {
+                u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX)
+            });
+        Self {
+            timestamp_ms,
+            kind: kind.to_string(),
+            severity: Severity::of_kind(kind),
+            principal: principal.map(str::to_string),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Errors raised while building or running an exporter.
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error("telemetry export i/o failed: {0}")]
+    Io(#[from] io::Error),
+    /// The destination answered but refused the batch.
+    #[error("telemetry export rejected: {0}")]
+    Rejected(String),
+    #[error("invalid telemetry export config: {0}")]
+    Config(String),
+}
+
+/// Destination of exported telemetry.
+pub trait TelemetryExporter: Send + Sync {
+    /// Deliver `batch`, oldest record first.
+    fn export(&self, batch: &[ExportRecord]) -> Result<(), ExportError>;
+}
+
+/// What recording does once the queue is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Overflow {
+    /// Drop the new record and count it; recording never waits.
+    #[default]
+    Drop,
+    /// Wait until the worker makes room, slowing the adapter down.
+    Block,
+}
+
+/// Where an [`ExportConfig`] sends records.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ExporterConfig {
+    /// Append one JSON object per line to `path`.
+    Jsonl { path: PathBuf },
+    /// Send to a syslog daemon at `address`: `host:port` over UDP, or a
+    /// Unix datagram socket path such as `/dev/log`.
+    Syslog {
+        address: String,
+        #[serde(default = "default_app_name")]
+        app_name: String,
+    },
+    /// POST OTLP/HTTP JSON log batches to `endpoint`, usually
+    /// `http://127.0.0.1:4318/v1/logs`.
+    Otlp {
+        endpoint: String,
+        #[serde(default = "default_app_name")]
+        service_name: String,
+    },
+}
+
+fn default_app_name() -> String {
+    "embednexus".into()
+}
+
+/// Exporter plus batching and sampling settings for one adapter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExportConfig {
+    pub exporter: ExporterConfig,
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    #[serde(default = "default_flush_interval_ms")]
+    pub flush_interval_ms: u64,
+    #[serde(default = "default_queue_capacity")]
+    pub queue_capacity: usize,
+    #[serde(default)]
+    pub overflow: Overflow,
+    /// Records less severe than this are not exported.
+    #[serde(default)]
+    pub min_severity: Severity,
+    /// Share of records kept, from 0 to 1, by event kind; kinds not listed
+    /// keep every record.
+    #[serde(default)]
+    pub sampling: BTreeMap<String, f64>,
+}
+
+const fn default_batch_size() -> usize {
+    DEFAULT_BATCH_SIZE
+}
+
+const fn default_flush_interval_ms() -> u64 {
+    DEFAULT_FLUSH_INTERVAL_MS
+}
+
+const fn default_queue_capacity() -> usize {
+    DEFAULT_QUEUE_CAPACITY
+}
+
+impl ExportConfig {
+    /// Default batching around `exporter`.
+    #[must_use]
+    pub fn new(exporter: ExporterConfig) -> Self {
+        Self {
+            exporter,
+            batch_size: DEFAULT_BATCH_SIZE,
+            flush_interval_ms: DEFAULT_FLUSH_INTERVAL_MS,
+            queue_capacity: DEFAULT_QUEUE_CAPACITY,
+            overflow: Overflow::default(),
+            min_severity: Severity::default(),
+            sampling: BTreeMap::new(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ExportError> {
+        if self.batch_size == 0 {
+            return Err(ExportError::Config("batch_size must be non-zero".into()));
+        }
+        if self.queue_capacity == 0 {
+            return Err(ExportError::Config(
+                "queue_capacity must be non-zero".into(),
+            ));
+        }
+        if self.flush_interval_ms == 0 {
+            return Err(ExportError::Config(
+                "flush_interval_ms must be non-zero".into(),
+            ));
+        }
+        if let Some((kind, rate)) = self
+            .sampling
+            .iter()
+            .find(|(_, rate)| !(0.0..=1.0).contains(*rate))
+        {
+            return Err(ExportError::Config(format!(
+                "sampling rate {rate} of {kind} must be between 0 and 1"
+            )));
+        }
+        Ok(())
+    }
+
+    fn exporter(&self) -> Result<Arc<dyn TelemetryExporter>, ExportError> {
+        Ok(match &self.exporter {
+            ExporterConfig::Jsonl { path } => Arc::new(JsonlExporter::open(path)?),
+            ExporterConfig::Syslog { address, app_name } => {
+                Arc::new(SyslogExporter::connect(address, app_name)?)
+            }
+            ExporterConfig::Otlp {
+                endpoint,
+                service_name,
+            } => Arc::new(OtlpExporter::new(endpoint, service_name)?),
+        })
+    }
+}
+
+/// Counters of an [`ExportPipeline`] since it started.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportStats {
+    /// Records the exporter accepted.
+    pub exported: u64,
+    /// Records dropped because the queue was full.
+    pub dropped: u64,
+    /// Records lost to batches the exporter failed.
+    pub failed: u64,
+    /// Records left out by the severity threshold or sampling.
+    pub sampled_out: u64,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    exported: AtomicU64,
+    dropped: AtomicU64,
+    failed: AtomicU64,
+    sampled_out: AtomicU64,
+}
+
+enum Message {
+    Record(ExportRecord),
+    /// Export everything queued before this, then answer.
+    Flush(mpsc::Sender<()>),
+}
+
+/// Bounded queue and worker thread feeding one [`TelemetryExporter`].
+///
+/// Dropping the pipeline exports what is still queued and stops the worker.
+pub struct ExportPipeline {
+    sender: Option<SyncSender<Message>>,
+    worker: Mutex<Option<JoinHandle<()>>>,
+    overflow: Overflow,
+    sampler: Sampler,
+    counters: Arc<Counters>,
+}
+
+/// Pipeline shared by the sinks of the adapters that export through it.
+pub type SharedExportPipeline = Arc<ExportPipeline>;
+
+impl fmt::Debug for ExportPipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExportPipeline")
+            .field("overflow", &self.overflow)
+            .field("stats", &self.stats())
+            .finish_non_exhaustive()
+    }
+}
+
+impl ExportPipeline {
+    /// Build the exporter `config` names and start its worker.
+    pub fn from_config(config: &ExportConfig) -> Result<Self, ExportError> {
+        config.validate()?;
+        Ok(Self::start(config.exporter()?, config))
+    }
+
+    /// Start a worker feeding `exporter` with the batching and sampling
+    /// settings of `config`; its `exporter` field is ignored.
+    #[must_use]
+    pub fn start(exporter: Arc<dyn TelemetryExporter>, config: &ExportConfig) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(config.queue_capacity.max(1));
+        let counters = Arc::new(Counters::default());
+        let worker = Worker {
+            exporter,
+            receiver,
+            batch_size: config.batch_size.max(1),
+            flush_interval: Duration::from_millis(config.flush_interval_ms.max(1)),
+            counters: Arc::clone(&counters),
+        };
+        let worker = std::thread::Builder::new()
+            .name("telemetry-export".into())
+            .spawn(move || worker.run())
+            .expect("spawn telemetry export worker");
+        Self {
+            sender: Some(sender),
+            worker: Mutex::new(Some(worker)),
+            overflow: config.overflow,
+            sampler: Sampler::new(config.min_severity, config.sampling.clone()),
+            counters,
+        }
+    }
+
+    /// Queue `record` for export, unless sampling leaves it out.
+    pub fn record(&self, record: ExportRecord) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+        if !self.sampler.keep(&record) {
+            self.counters.sampled_out.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        let sent = match self.overflow {
+            Overflow::Drop => match sender.try_send(Message::Record(record)) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_) | TrySendError::Disconnected(_)) => false,
+            },
+            Overflow::Block => sender.send(Message::Record(record)).is_ok(),
+        };
+        if !sent {
+            self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Export everything queued so far and wait until it has been.
+    pub fn flush(&self) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+        let (done, wait) = mpsc::channel();
+        if sender.send(Message::Flush(done)).is_ok() {
+            let _ = wait.recv();
+        }
+    }
+
+    #[must_use]
+    pub fn stats(&self) -> ExportStats {
+        ExportStats {
+            exported: self.counters.exported.load(Ordering::Relaxed),
+            dropped: self.counters.dropped.load(Ordering::Relaxed),
+            failed: self.counters.failed.load(Ordering::Relaxed),
+            sampled_out: self.counters.sampled_out.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Drop for ExportPipeline {
+    fn drop(&mut self) {
+        self.sender = None;
+        let worker = self
+            .worker
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        if let Some(worker) = worker {
+            let _ = worker.join();
+        }
+    }
+}
+
+struct Worker {
+    exporter: Arc<dyn TelemetryExporter>,
+    receiver: Receiver<Message>,
+    batch_size: usize,
+    flush_interval: Duration,
+    counters: Arc<Counters>,
+}
+
+impl Worker {
+    fn run(self) {
+        let mut batch = Vec::with_capacity(self.batch_size);
+        let mut deadline = Instant::now() + self.flush_interval;
+        loop {
+            let wait = deadline.saturating_duration_since(Instant::now());
+            match self.receiver.recv_timeout(wait) {
+                Ok(Message::Record(record)) => {
+                    batch.push(record);
+                    if batch.len() >= self.batch_size {
+                        self.export(&mut batch);
+                        deadline = Instant::now() + self.flush_interval;
+                    }
+                }
+                Ok(Message::Flush(done)) => {
+                    self.export(&mut batch);
+                    let _ = done.send(());
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    self.export(&mut batch);
+                    deadline = Instant::now() + self.flush_interval;
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.export(&mut batch);
+                    return;
+                }
+            }
+        }
+    }
+
+    fn export(&self, batch: &mut Vec<ExportRecord>) {
+        if batch.is_empty() {
+            return;
+        }
+        let count = batch.len() as u64;
+        match self.exporter.export(batch) {
+            Ok(()) => self.counters.exported.fetch_add(count, Ordering::Relaxed),
+            Err(err) => {
+                tracing::warn!(error = %err, records = count, "telemetry export failed");
+                self.counters.failed.fetch_add(count, Ordering::Relaxed)
+            }
+        };
+        batch.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Condvar;
+
+    use super::*;
+
+    /// Exporter holding batches until released, recording what it got.
+    #[derive(Default)]
+    struct Gate {
+        /// Set once the worker has a batch in hand.
+        busy: AtomicBool,
+        open: Mutex<bool>,
+        opened: Condvar,
+        batches: Mutex<Vec<Vec<String>>>,
+    }
+
+    impl Gate {
+        fn release(&self) {
+            *self.open.lock().unwrap() = true;
+            self.opened.notify_all();
+        }
+    }
+
+    impl TelemetryExporter for Gate {
+        fn export(&self, batch: &[ExportRecord]) -> Result<(), ExportError> {
+            self.busy.store(true, Ordering::SeqCst);
+            let open = self.open.lock().unwrap();
+            drop(self.opened.wait_while(open, |open| !*open).unwrap());
+            self.batches
+                .lock()
+                .unwrap()
+                .push(batch.iter().map(|record| record.message.clone()).collect());
+            Ok(())
+        }
+    }
+
+    fn config(batch_size: usize, queue_capacity: usize) -> ExportConfig {
+        ExportConfig {
+            batch_size,
+            queue_capacity,
+            flush_interval_ms: 60_000,
+            ..ExportConfig::new(ExporterConfig::Jsonl {
+                path: PathBuf::new(),
+            })
+        }
+    }
+
+    #[test]
+    fn exports_full_batches_and_drops_records_the_queue_has_no_room_for() {
+        let gate = Arc::new(Gate::default());
+        let pipeline = ExportPipeline::start(gate.clone(), &config(2, 2));
+        for n in 0..2 {
+            pipeline.record(ExportRecord::now("stdio.frame", None, &n.to_string()));
+        }
+        while !gate.busy.load(Ordering::SeqCst) {
+            std::thread::yield_now();
+        }
+        // The worker is stuck on the first batch: two records fit the queue.
+        for n in 2..10 {
+            pipeline.record(ExportRecord::now("stdio.frame", None, &n.to_string()));
+        }
+        gate.release();
+        pipeline.flush();
+
+        let stats = pipeline.stats();
+        assert_eq!((stats.exported, stats.dropped, stats.failed), (4, 6, 0));
+        assert_eq!(
+            *gate.batches.lock().unwrap(),
+            vec![vec!["0", "1"], vec!["2", "3"]]
+        );
+    }
+
+    #[test]
+    fn dropping_the_pipeline_exports_what_is_queued() {
+        let gate = Arc::new(Gate::default());
+        gate.release();
+        let pipeline = ExportPipeline::start(
+            gate.clone(),
+            &ExportConfig {
+                overflow: Overflow::Block,
+                ..config(100, 1)
+            },
+        );
+        for n in 0..5 {
+            pipeline.record(ExportRecord::now(
+                "uds.request",
+                Some("alice"),
+                &n.to_string(),
+            ));
+        }
+        drop(pipeline);
+        let batches = gate.batches.lock().unwrap();
+        assert_eq!(batches.concat(), vec!["0", "1", "2", "3", "4"]);
+    }
+}
@@ -0,0 +1,130 @@
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use super::{ExportError, ExportRecord, TelemetryExporter};
+
+/// How long connecting to, writing to or reading from the collector may take.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Posts batches to an OpenTelemetry collector as OTLP/HTTP JSON
+/// (`ExportLogsServiceRequest`), one request per batch.
+///
+/// Only plain `http://` endpoints are supported: the collector is expected
+/// to run beside the runtime, as the offline-first deployment has no
+/// other place to send to, and forwards over TLS itself if it needs to.
+#[derive(Debug)]
+pub struct OtlpExporter {
+    /// `host:port` to connect to.
+    authority: String,
+    path: String,
+    service_name: String,
+}
+
+impl OtlpExporter {
+    /// Exporter posting to `endpoint`, such as
+    /// `http://127.0.0.1:4318/v1/logs`, as service `service_name`.
+    pub fn new(endpoint: &str, service_name: &str) -> Result<Self, ExportError> {
+        let rest = endpoint.strip_prefix("http://").ok_or_else(|| {
+            ExportError::Config(format!("otlp endpoint {endpoint} must be http://"))
+        })?;
+        let (authority, path) = rest
+            .find('/')
+            .map_or((rest, "/v1/logs"), |at| rest.split_at(at));
+        if authority.is_empty() {
+            return Err(ExportError::Config(format!(
+                "otlp endpoint {endpoint} has no host"
+            )));
+        }
+        let authority = if authority.contains(':') {
+            authority.to_string()
+        } else {
+            format!("{authority}:80")
+        };
+        Ok(Self {
+            authority,
+            path: path.to_string(),
+            service_name: service_name.to_string(),
+        })
+    }
+
+    fn body(&self, batch: &[ExportRecord]) -> Value {
+        let records: Vec<Value> = batch
+            .iter()
+            .map(|record| {
+                let mut attributes = vec![attribute("event.kind", &record.kind)];
+                if let Some(principal) = &record.principal {
+                    attributes.push(attribute("enduser.id", principal));
+                }
+                let nanos = u128::from(record.timestamp_ms) * 1_000_000;
+                let (severity_number, severity_text) = record.severity.otel();
+                json!({
+                    "timeUnixNano": nanos.to_string(),
+                    "severityNumber": severity_number,
+                    "severityText": severity_text,
+                    "body": { "stringValue": record.message },
+                    "attributes": attributes,
+                })
+            })
+            .collect();
+        json!({
+            "resourceLogs": [{
+                "resource": { "attributes": [attribute("service.name", &self.service_name)] },
+                "scopeLogs": [{
+                    "scope": { "name": "embednexus.transport" },
+                    "logRecords": records,
+                }],
+            }],
+        })
+    }
+
+    fn post(&self, body: &[u8]) -> Result<(), ExportError> {
+        let addr =
+            self.authority.to_socket_addrs()?.next().ok_or_else(|| {
+                ExportError::Config(format!("{} did not resolve", self.authority))
+            })?;
+        let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n",
+            self.path,
+            self.authority,
+            body.len()
+        )?;
+        stream.write_all(body)?;
+        stream.flush()?;
+        // `Connection: close` ends the reply at EOF; only its status matters.
+        let mut reply = Vec::new();
+        stream.read_to_end(&mut reply)?;
+        let status_line = reply.split(|b| *b == b'\n').next().unwrap_or_default();
+        let status_line = String::from_utf8_lossy(status_line);
+        let status = status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|code| code.parse::<u16>().ok())
+            .ok_or_else(|| ExportError::Rejected(format!("malformed reply: {status_line}")))?;
+        if (200..300).contains(&status) {
+            Ok(())
+        } else {
+            Err(ExportError::Rejected(format!(
+                "collector answered HTTP {status}"
+            )))
+        }
+    }
+}
+
+fn attribute(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+impl TelemetryExporter for OtlpExporter {
+    fn export(&self, batch: &[ExportRecord]) -> Result<(), ExportError> {
+        let body = serde_json::to_vec(&self.body(batch)).map_err(std::io::Error::from)?;
+        self.post(&body)
+    }
+}
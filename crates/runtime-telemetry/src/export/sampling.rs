@@ -0,0 +1,77 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, PoisonError};
+
+use super::ExportRecord;
+use crate::severity::{is_auth_failure, Severity};
+
+/// Decides which records reach the exporter.
+///
+/// Records below `min_severity` are dropped. A kind with a sampling rate
+/// keeps that share of its records, evenly spread and starting with the
+/// first: at `0.25`, records 1, 5, 9 and so on. Auth failures are always
+/// kept.
+#[derive(Debug)]
+pub(super) struct Sampler {
+    min_severity: Severity,
+    rates: BTreeMap<String, f64>,
+    /// Records seen so far of each sampled kind.
+    seen: Mutex<HashMap<String, u64>>,
+}
+
+impl Sampler {
+    pub(super) fn new(min_severity: Severity, rates: BTreeMap<String, f64>) -> Self {
+        Self {
+            min_severity,
+            rates,
+            seen: Mutex::default(),
+        }
+    }
+
+    pub(super) fn keep(&self, record: &ExportRecord) -> bool {
+        if is_auth_failure(&record.kind) {
+            return true;
+        }
+        if record.severity < self.min_severity {
+            return false;
+        }
+        let Some(&rate) = self.rates.get(&record.kind) else {
+            return true;
+        };
+        let mut seen = self.seen.lock().unwrap_or_else(PoisonError::into_inner);
+        let n = seen.entry(record.kind.clone()).or_default();
+        // Keep the record whenever the running count of kept records,
+        // rounded up, steps forward.
+        let keep = ((*n + 1) as f64 * rate).ceil() > (*n as f64 * rate).ceil();
+        *n += 1;
+        keep
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_per_kind_and_always_keeps_auth_failures() {
+        let sampler = Sampler::new(
+            Severity::Info,
+            BTreeMap::from([
+                ("stdio.session.issued".to_string(), 0.25),
+                ("http.auth.failure".to_string(), 0.0),
+            ]),
+        );
+        let kept: Vec<bool> = (0..9)
+            .map(|_| sampler.keep(&ExportRecord::now("stdio.session.issued", None, "")))
+            .collect();
+        assert_eq!(
+            kept,
+            [true, false, false, false, true, false, false, false, true]
+        );
+
+        assert!(!sampler.keep(&ExportRecord::now("stdio.request", None, "search")));
+        assert!(sampler.keep(&ExportRecord::now("uds.router.error", None, "boom")));
+        for _ in 0..3 {
+            assert!(sampler.keep(&ExportRecord::now("http.auth.failure", None, "expired")));
+        }
+    }
+}
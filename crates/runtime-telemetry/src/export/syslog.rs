@@ -0,0 +1,181 @@
+use std::fmt::Write as _;
+use std::net::UdpSocket;
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+
+use super::{ExportError, ExportRecord, TelemetryExporter};
+
+/// `local0`: the first facility set aside for applications.
+const FACILITY: u8 = 16;
+/// Private enterprise number the structured data is tagged with, the
+/// example number RFC 5424 reserves for documentation.
+const SD_ID: &str = "embednexus@32473";
+/// RFC 5424 caps `MSGID` at 32 characters.
+const MAX_MSGID: usize = 32;
+
+enum Socket {
+    Udp(UdpSocket),
+    #[cfg(unix)]
+    Unix(UnixDatagram),
+}
+
+/// Sends each record as an RFC 5424 message, one datagram apiece.
+///
+/// The event kind is the `MSGID`, the principal goes in structured data
+/// and the message is the body.
+pub struct SyslogExporter {
+    socket: Socket,
+    app_name: String,
+    procid: u32,
+}
+
+impl SyslogExporter {
+    /// Send to `address`: a path (starting with `/`) is a Unix datagram
+    /// socket, anything else `host:port` over UDP.
+    pub fn connect(address: &str, app_name: &str) -> Result<Self, ExportError> {
+        let socket = if address.starts_with('/') {
+            #[cfg(unix)]
+            {
+                let socket = UnixDatagram::unbound()?;
+                socket.connect(address)?;
+                Socket::Unix(socket)
+            }
+            #[cfg(not(unix))]
+            {
+                return Err(ExportError::Config(format!(
+                    "unix syslog socket {address} is not supported on this platform"
+                )));
+            }
+        } else {
+            let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+            socket.connect(address)?;
+            Socket::Udp(socket)
+        };
+        Ok(Self {
+            socket,
+            app_name: header_field(app_name, 48),
+            procid: std::process::id(),
+        })
+    }
+
+    fn format(&self, record: &ExportRecord) -> String {
+        let mut line = format!(
+            "<{}>1 {} - {} {} {} ",
+            FACILITY * 8 + record.severity.syslog_code(),
+            rfc3339(record.timestamp_ms),
+            self.app_name,
+            self.procid,
+            header_field(&record.kind, MAX_MSGID),
+        );
+        match &record.principal {
+            Some(principal) => {
+                let _ = write!(line, "[{SD_ID} principal=\"{}\"]", sd_escape(principal));
+            }
+            None => line.push('-'),
+        }
+        if !record.message.is_empty() {
+            line.push(' ');
+            line.push_str(&record.message);
+        }
+        line
+    }
+}
+
+impl TelemetryExporter for SyslogExporter {
+    fn export(&self, batch: &[ExportRecord]) -> Result<(), ExportError> {
+        for record in batch {
+            let line = self.format(record);
+            match &self.socket {
+                Socket::Udp(socket) => socket.send(line.as_bytes())?,
+                #[cfg(unix)]
+                Socket::Unix(socket) => socket.send(line.as_bytes())?,
+            };
+        }
+        Ok(())
+    }
+}
+
+/// Printable ASCII without spaces, at most `max` characters, or `-`.
+fn header_field(value: &str, max: usize) -> String {
+    let field: String = value
+        .chars()
+        .filter(|c| c.is_ascii_graphic())
+        .take(max)
+        .collect();
+    if field.is_empty() {
+        "-".into()
+    } else {
+        field
+    }
+}
+
+/// Escape the characters RFC 5424 reserves inside parameter values.
+fn sd_escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | ']') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// `YYYY-MM-DDThh:mm:ss.sssZ` for milliseconds since the Unix epoch.
+fn rfc3339(timestamp_ms: u64) -> String {
+    let secs = timestamp_ms / 1000;
+    let days = i64::try_from(secs / 86_400).unwrap_or(i64::MAX);
+    let rem = secs % 86_400;
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60,
+        timestamp_ms % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Severity;
+
+    #[test]
+    fn formats_rfc5424_messages() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let exporter =
+            SyslogExporter::connect(&receiver.local_addr().unwrap().to_string(), "enx test")
+                .unwrap();
+        let record = ExportRecord {
+            timestamp_ms: 1_700_000_000_123,
+            kind: "http.auth.failure".into(),
+            severity: Severity::Warn,
+            principal: Some("al\"ice".into()),
+            message: "token expired".into(),
+        };
+        assert_eq!(
+            exporter.format(&record),
+            format!(
+                "<132>1 2023-11-14T22:13:20.123Z - enxtest {} http.auth.failure \
+                 [embednexus@32473 principal=\"al\\\"ice\"] token expired",
+                std::process::id()
+            )
+        );
+        assert_eq!(rfc3339(951_782_400_000), "2000-02-29T00:00:00.000Z");
+
+        exporter.export(&[record]).unwrap();
+        let mut buf = [0u8; 512];
+        let len = receiver.recv(&mut buf).unwrap();
+        assert!(String::from_utf8_lossy(&buf[..len]).ends_with("] token expired"));
+    }
+}
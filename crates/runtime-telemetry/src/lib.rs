@@ -0,0 +1,215 @@
+//! Connection-level counters of the transport adapters, per peer.
+//!
+//! Every adapter given a [`PeerTelemetry`] counts, for each peer it talks
+//! to, the frames and bytes it received and sent, the frames it could not
+//! decode and the requests it refused for authentication, along with the
+//! last such failure. Peers are named by the adapter (`stdio`,
+//! `uds://<process>/<pid>`, `http://<remote address>`). Only the most
+//! recently seen peers are kept: once `capacity` peers are tracked, a new
+//! one evicts the peer seen longest ago. The `telemetry.peers` command in
+//! [`commands`] lists them for debugging misbehaving clients.
+//!
+//! [`export`] ships the adapters' telemetry events out of the process, to
+//! a JSONL file, syslog or an OTLP collector, filtered by [`Severity`] and
+//! sampled per event kind. A [`Redact`] keeps secrets out of what the
+//! adapters record.
+
+pub mod commands;
+pub mod export;
+pub mod redact;
+pub mod severity;
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use runtime_clock::{SharedClock, SystemClock};
+use serde::{Deserialize, Serialize};
+
+pub use commands::register_commands;
+pub use export::{
+    ExportConfig, ExportError, ExportPipeline, ExportRecord, ExporterConfig, SharedExportPipeline,
+    TelemetryExporter,
+};
+pub use redact::{Redact, SharedRedactor};
+pub use severity::Severity;
+
+/// Peers tracked unless [`PeerTelemetry::new`] says otherwise.
+pub const DEFAULT_PEER_CAPACITY: usize = 256;
+
+/// Counters of one peer since it was first seen.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerStats {
+    pub peer: String,
+    pub frames_in: u64,
+    pub frames_out: u64,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    /// Frames or requests that could not be decoded.
+    pub decode_errors: u64,
+    /// Requests refused because the caller could not be authenticated or
+    /// is not permitted.
+    pub auth_failures: u64,
+    /// Unix seconds.
+    pub first_seen: u64,
+    /// Unix seconds.
+    pub last_seen: u64,
+    /// The latest decode or authentication failure.
+    pub last_error: Option<String>,
+}
+
+/// Per-peer counters, most recently seen peer last.
+#[derive(Debug)]
+pub struct PeerTelemetry {
+    capacity: usize,
+    peers: Mutex<VecDeque<PeerStats>>,
+    evicted: AtomicU64,
+    clock: SharedClock,
+}
+
+/// Counters shared by the adapters and the `telemetry.peers` command.
+pub type SharedPeerTelemetry = Arc<PeerTelemetry>;
+
+impl Default for PeerTelemetry {
+    fn default() -> Self {
+        Self::new(DEFAULT_PEER_CAPACITY)
+    }
+}
+
+impl PeerTelemetry {
+    /// Track at most `capacity` peers, at least one.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            peers: Mutex::new(VecDeque::new()),
+            evicted: AtomicU64::new(0),
+            clock: SystemClock::shared(),
+        }
+    }
+
+    /// Stamp `first_seen` and `last_seen` from `clock` instead of the
+    /// system clock.
+    #[must_use]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Count a frame of `bytes` received from `peer`.
+    pub fn frame_in(&self, peer: &str, bytes: usize) {
+        self.update(peer, |stats| {
+            stats.frames_in += 1;
+            stats.bytes_in += bytes as u64;
+        });
+    }
+
+    /// Count a frame of `bytes` sent to `peer`.
+    pub fn frame_out(&self, peer: &str, bytes: usize) {
+        self.update(peer, |stats| {
+            stats.frames_out += 1;
+            stats.bytes_out += bytes as u64;
+        });
+    }
+
+    /// Count a frame from `peer` that could not be decoded.
+    pub fn decode_error(&self, peer: &str, detail: &str) {
+        self.update(peer, |stats| {
+            stats.decode_errors += 1;
+            stats.last_error = Some(detail.to_string());
+        });
+    }
+
+    /// Count a request from `peer` refused for authentication.
+    pub fn auth_failure(&self, peer: &str, detail: &str) {
+        self.update(peer, |stats| {
+            stats.auth_failures += 1;
+            stats.last_error = Some(detail.to_string());
+        });
+    }
+
+    /// Tracked peers, most recently seen first.
+    #[must_use]
+    pub fn peers(&self) -> Vec<PeerStats> {
+        self.lock().iter().rev().cloned().collect()
+    }
+
+    #[must_use]
+    pub fn peer(&self, peer: &str) -> Option<PeerStats> {
+        self.lock().iter().find(|stats| stats.peer == peer).cloned()
+    }
+
+    /// Peers dropped to make room for newer ones since creation.
+    #[must_use]
+    pub fn evicted(&self) -> u64 {
+        self.evicted.load(Ordering::Relaxed)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, VecDeque<PeerStats>> {
+        self.peers.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn update(&self, peer: &str, apply: impl FnOnce(&mut PeerStats)) {
+        let now = self.clock.unix_secs();
+        let mut peers = self.lock();
+        let mut stats = match peers.iter().position(|stats| stats.peer == peer) {
+            Some(index) => peers.remove(index).expect("position is in range"),
+            None => {
+                if peers.len() >= self.capacity {
+                    peers.pop_front();
+                    self.evicted.fetch_add(1, Ordering::Relaxed);
+                }
+                PeerStats {
+                    peer: peer.to_string(),
+                    first_seen: now,
+                    ..PeerStats::default()
+                }
+            }
+        };
+        apply(&mut stats);
+        stats.last_seen = now;
+        peers.push_back(stats);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use runtime_clock::MockClock;
+
+    use super::*;
+
+    #[test]
+    fn counts_per_peer_and_evicts_the_peer_seen_longest_ago() {
+        let clock = Arc::new(MockClock::at_unix(1_000));
+        let telemetry = PeerTelemetry::new(2).with_clock(clock.clone());
+        telemetry.frame_in("stdio", 120);
+        telemetry.frame_out("stdio", 300);
+        clock.advance(Duration::from_secs(5));
+        telemetry.frame_in("uds://cli/42", 64);
+        telemetry.decode_error("uds://cli/42", "frame too short");
+        telemetry.auth_failure("stdio", "token expired");
+
+        let stdio = telemetry.peer("stdio").unwrap();
+        assert_eq!(
+            (
+                stdio.frames_in,
+                stdio.frames_out,
+                stdio.bytes_in,
+                stdio.bytes_out
+            ),
+            (1, 1, 120, 300)
+        );
+        assert_eq!(stdio.auth_failures, 1);
+        assert_eq!(stdio.last_error.as_deref(), Some("token expired"));
+        assert_eq!((stdio.first_seen, stdio.last_seen), (1_000, 1_005));
+        let names: Vec<String> = telemetry.peers().into_iter().map(|s| s.peer).collect();
+        assert_eq!(names, vec!["stdio", "uds://cli/42"]);
+
+        telemetry.frame_in("http://10.0.0.7:50122", 10);
+        assert_eq!(telemetry.peer("uds://cli/42"), None);
+        assert_eq!(telemetry.evicted(), 1);
+        assert_eq!(telemetry.peers().len(), 2);
+    }
+}
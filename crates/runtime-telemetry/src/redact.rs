@@ -0,0 +1,21 @@
+//! Scrubbing secrets out of telemetry before it is recorded.
+//!
+//! Event messages can echo what a client sent (router errors quote the
+//! offending field, framing errors the bad JSON), and ingest payloads carry
+//! the very secrets the sanitizer removes from chunks. An adapter given a
+//! [`Redact`] passes every event message and peer failure through it
+//! before recording them; `ingestion-sanitization` implements it over its
+//! configured redaction patterns.
+
+use std::fmt;
+use std::sync::Arc;
+
+/// Removes secrets from text bound for telemetry.
+pub trait Redact: fmt::Debug + Send + Sync {
+    /// `text` with every secret replaced. Implementations that cannot
+    /// scrub it should return a placeholder rather than the input.
+    fn redact(&self, text: &str) -> String;
+}
+
+/// Redactor shared by the sinks of the adapters using it.
+pub type SharedRedactor = Arc<dyn Redact>;
@@ -0,0 +1,68 @@
+//! How much an operator should care about a telemetry event.
+
+use serde::{Deserialize, Serialize};
+
+/// Severity of a telemetry event, lowest first.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Per-request traffic: requests, responses and streamed frames.
+    #[default]
+    Debug,
+    /// Sessions issued, peers accepted and configuration reloads.
+    Info,
+    /// Authentication failures and throttled requests.
+    Warn,
+    /// Requests the router failed.
+    Error,
+}
+
+impl Severity {
+    /// Severity the adapters' event `kind`s carry, from their suffix.
+    #[must_use]
+    pub fn of_kind(kind: &str) -> Self {
+        if is_auth_failure(kind) || kind.ends_with(".throttled") {
+            Self::Warn
+        } else if kind.ends_with(".error") {
+            Self::Error
+        } else if kind.ends_with(".request")
+            || kind.ends_with(".response")
+            || kind.ends_with(".response.streamed")
+        {
+            Self::Debug
+        } else {
+            Self::Info
+        }
+    }
+
+    /// RFC 5424 severity code.
+    #[must_use]
+    pub const fn syslog_code(self) -> u8 {
+        match self {
+            Self::Debug => 7,
+            Self::Info => 6,
+            Self::Warn => 4,
+            Self::Error => 3,
+        }
+    }
+
+    /// OpenTelemetry `SeverityNumber` and `SeverityText`.
+    #[must_use]
+    pub const fn otel(self) -> (u8, &'static str) {
+        match self {
+            Self::Debug => (5, "DEBUG"),
+            Self::Info => (9, "INFO"),
+            Self::Warn => (13, "WARN"),
+            Self::Error => (17, "ERROR"),
+        }
+    }
+}
+
+/// Whether `kind` records a refused authentication, which is exported
+/// whatever the severity threshold and sampling say.
+#[must_use]
+pub fn is_auth_failure(kind: &str) -> bool {
+    kind.ends_with(".auth.failure")
+}
@@ -0,0 +1,111 @@
+use std::io::{Read, Write};
+use std::net::TcpListener;
+
+use runtime_telemetry::{ExportConfig, ExportPipeline, ExportRecord, ExporterConfig};
+use serde_json::Value;
+
+#[test]
+fn jsonl_exporter_appends_one_line_per_record() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("telemetry.jsonl");
+    let config: ExportConfig = serde_json::from_value(serde_json::json!({
+        "exporter": { "kind": "jsonl", "path": path },
+        "batch_size": 2,
+    }))
+    .unwrap();
+    let pipeline = ExportPipeline::from_config(&config).unwrap();
+    pipeline.record(ExportRecord::now("http.request", Some("alice"), "search"));
+    pipeline.record(ExportRecord::now("http.response", Some("alice"), "200"));
+    pipeline.record(ExportRecord::now("stdio.frame", None, "ping"));
+    pipeline.flush();
+    assert_eq!(pipeline.stats().exported, 3);
+
+    let lines: Vec<ExportRecord> = std::fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let kinds: Vec<&str> = lines.iter().map(|record| record.kind.as_str()).collect();
+    assert_eq!(kinds, ["http.request", "http.response", "stdio.frame"]);
+    assert_eq!(lines[0].principal.as_deref(), Some("alice"));
+    assert_eq!(lines[2].principal, None);
+}
+
+#[test]
+fn otlp_exporter_posts_log_batches_to_the_collector() {
+    let collector = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}/v1/logs", collector.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        let (mut conn, _) = collector.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0u8; 4096];
+        // Read until the whole body named by Content-Length has arrived.
+        loop {
+            let read = conn.read(&mut buf).unwrap();
+            request.extend_from_slice(&buf[..read]);
+            let text = String::from_utf8_lossy(&request);
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let length: usize = head
+                    .lines()
+                    .find_map(|line| line.strip_prefix("Content-Length: "))
+                    .unwrap()
+                    .parse()
+                    .unwrap();
+                if body.len() >= length {
+                    conn.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}")
+                        .unwrap();
+                    return (head.to_string(), body.to_string());
+                }
+            }
+        }
+    });
+
+    let pipeline = ExportPipeline::from_config(&ExportConfig::new(ExporterConfig::Otlp {
+        endpoint,
+        service_name: "enx-test".into(),
+    }))
+    .unwrap();
+    pipeline.record(ExportRecord::now("uds.auth.failure", Some("bob"), "uid 77"));
+    pipeline.flush();
+    assert_eq!(pipeline.stats().exported, 1);
+
+    let (head, body) = server.join().unwrap();
+    assert!(head.starts_with("POST /v1/logs HTTP/1.1"));
+    let body: Value = serde_json::from_str(&body).unwrap();
+    let resource = &body["resourceLogs"][0];
+    assert_eq!(
+        resource["resource"]["attributes"][0]["value"]["stringValue"],
+        "enx-test"
+    );
+    let record = &resource["scopeLogs"][0]["logRecords"][0];
+    assert_eq!(record["body"]["stringValue"], "uid 77");
+    assert_eq!(
+        record["attributes"][0]["value"]["stringValue"],
+        "uds.auth.failure"
+    );
+    assert_eq!(record["attributes"][1]["key"], "enduser.id");
+}
+
+#[test]
+fn rejects_unusable_configs() {
+    let https = ExportConfig::new(ExporterConfig::Otlp {
+        endpoint: "https://collector.example/v1/logs".into(),
+        service_name: "enx".into(),
+    });
+    assert!(ExportPipeline::from_config(&https).is_err());
+    let empty_batches = ExportConfig {
+        batch_size: 0,
+        ..ExportConfig::new(ExporterConfig::Syslog {
+            address: "127.0.0.1:514".into(),
+            app_name: "enx".into(),
+        })
+    };
+    assert!(ExportPipeline::from_config(&empty_batches).is_err());
+    let bad_rate: ExportConfig = serde_json::from_value(serde_json::json!({
+        "exporter": { "kind": "syslog", "address": "127.0.0.1:514" },
+        "min_severity": "warn",
+        "sampling": { "stdio.request": 1.5 },
+    }))
+    .unwrap();
+    assert!(ExportPipeline::from_config(&bad_rate).is_err());
+}
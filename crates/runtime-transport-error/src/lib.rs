@@ -0,0 +1,178 @@
+//! Error taxonomy shared by the transport adapters.
+//!
+//! Every adapter fails in the same handful of ways: bad configuration, a
+//! caller that cannot be authenticated, a malformed request, or an error
+//! from the router. [`TransportError`] names those once, with one status
+//! code mapping, and leaves an [`Adapter`](TransportError::Adapter) variant
+//! for the failures only one adapter has (CSRF for HTTP, framing for STDIO).
+
+use std::convert::Infallible;
+use std::time::Duration;
+
+use runtime_router::RouterError;
+use thiserror::Error;
+
+/// Failures specific to one adapter, carried by
+/// [`TransportError::Adapter`].
+pub trait AdapterError: std::error::Error {
+    /// HTTP-like status code for the failure.
+    fn status_code(&self) -> u16;
+
+    /// Stable machine-readable name of the failure.
+    fn kind(&self) -> &'static str;
+}
+
+/// Adapters without failures of their own use `Infallible`.
+impl AdapterError for Infallible {
+    fn status_code(&self) -> u16 {
+        match *self {}
+    }
+
+    fn kind(&self) -> &'static str {
+        match *self {}
+    }
+}
+
+/// Errors surfaced by a transport adapter.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TransportError<A = Infallible> {
+    /// Configuration validation failure.
+    #[error("configuration error: {0}")]
+    Configuration(String),
+    /// The caller could not be authenticated or is not permitted.
+    #[error("unauthorized: {0}")]
+    Unauthorized(String),
+    /// The request is malformed or missing fields.
+    #[error("invalid request: {0}")]
+    InvalidRequest(String),
+    /// Router surfaced an error.
+    #[error("router error: {0}")]
+    Router(RouterError),
+    /// Failure specific to the adapter.
+    #[error(transparent)]
+    Adapter(A),
+}
+
+impl<A: AdapterError> TransportError<A> {
+    /// HTTP-like status code for the error; router errors keep theirs.
+    #[must_use]
+    pub fn status_code(&self) -> u16 {
+        match self {
+            Self::Configuration(_) => 500,
+            Self::Unauthorized(_) => 401,
+            Self::InvalidRequest(_) => 400,
+            Self::Router(err) => err.status_code(),
+            Self::Adapter(err) => err.status_code(),
+        }
+    }
+
+    /// Stable machine-readable name of the error.
+    #[must_use]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Configuration(_) => "configuration",
+            Self::Unauthorized(_) => "unauthorized",
+            Self::InvalidRequest(_) => "invalid_request",
+            Self::Router(_) => "router",
+            Self::Adapter(err) => err.kind(),
+        }
+    }
+}
+
+impl<A> TransportError<A> {
+    /// Return the status code associated with a router error, if available.
+    #[must_use]
+    pub const fn router_status_code(&self) -> Option<u16> {
+        match self {
+            Self::Router(err) => Some(err.status_code()),
+            _ => None,
+        }
+    }
+
+    /// How long the client should wait before retrying, when the router
+    /// throttled the request.
+    #[must_use]
+    pub const fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::Router(err) => err.retry_after(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Error, PartialEq, Eq)]
+    enum TestError {
+        #[error("csrf violation: {0}")]
+        Csrf(String),
+    }
+
+    impl AdapterError for TestError {
+        fn status_code(&self) -> u16 {
+            403
+        }
+
+        fn kind(&self) -> &'static str {
+            "csrf"
+        }
+    }
+
+    #[test]
+    fn status_codes_and_kinds_are_uniform() {
+        let cases: Vec<(TransportError<TestError>, u16, &str)> = vec![
+            (
+                TransportError::Configuration("port".into()),
+                500,
+                "configuration",
+            ),
+            (
+                TransportError::Unauthorized("expired".into()),
+                401,
+                "unauthorized",
+            ),
+            (
+                TransportError::InvalidRequest("command".into()),
+                400,
+                "invalid_request",
+            ),
+            (
+                TransportError::Router(RouterError::NotFound {
+                    detail: "run".into(),
+                }),
+                404,
+                "router",
+            ),
+            (
+                TransportError::Adapter(TestError::Csrf("nonce".into())),
+                403,
+                "csrf",
+            ),
+        ];
+        for (err, status, kind) in cases {
+            assert_eq!(err.status_code(), status, "{err}");
+            assert_eq!(err.kind(), kind, "{err}");
+        }
+
+        let csrf: TransportError<TestError> = TransportError::Adapter(TestError::Csrf("x".into()));
+        assert_eq!(csrf.to_string(), "csrf violation: x");
+        assert_eq!(csrf.router_status_code(), None);
+    }
+
+    #[test]
+    fn throttled_router_errors_carry_retry_hints() {
+        let err: TransportError = TransportError::Router(RouterError::Throttled {
+            detail: "slow down".into(),
+            retry_after_ms: 250,
+        });
+        assert_eq!(err.status_code(), 429);
+        assert_eq!(err.router_status_code(), Some(429));
+        assert_eq!(err.retry_after(), Some(Duration::from_millis(250)));
+        assert_eq!(
+            TransportError::<Infallible>::Unauthorized("x".into()).retry_after(),
+            None
+        );
+    }
+}
@@ -0,0 +1,310 @@
+//! Long-lived API keys for clients that cannot ask for a session token,
+//! such as CI jobs and scheduled ingestion.
+//!
+//! A key reads `enx_<id>_<secret>`. The id is kept in clear so a key seen
+//! in a log or a leaked config can be looked up and revoked; the secret is
+//! only kept as its BLAKE3 hash and is shown once, when the key is issued.
+//! Each key carries the principal it acts for and the capabilities its
+//! requests get, and stays valid until revoked.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine as _;
+use runtime_clock::{SharedClock, SystemClock};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
+
+/// Prefix of every API key, telling it apart from a session token.
+pub const API_KEY_PREFIX: &str = "enx_";
+
+/// Layout version of the API key file; files written under another version
+/// are refused rather than guessed at.
+pub const API_KEYS_VERSION: u32 = 1;
+
+/// Errors raised by an [`ApiKeyStore`].
+///
+/// The variants name why a key failed for logs and audits; the HTTP adapter
+/// answers every verification failure with the same unauthorized error.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ApiKeyError {
+    #[error("malformed api key")]
+    Malformed,
+    #[error("unknown api key {0}")]
+    Unknown(String),
+    #[error("api key {0} was revoked")]
+    Revoked(String),
+    #[error("api key {0} does not match")]
+    Mismatch(String),
+    #[error("api key store: {0}")]
+    Storage(String),
+}
+
+/// One issued key, without its secret.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiKeyRecord {
+    /// Identifier embedded in the key after [`API_KEY_PREFIX`].
+    pub id: String,
+    pub principal: String,
+    pub capabilities: Vec<String>,
+    /// Free-form note naming the job the key was issued for.
+    pub label: String,
+    /// Unix seconds.
+    pub created_at: u64,
+    pub revoked: bool,
+    secret_hash: String,
+}
+
+/// A freshly issued key. `key` is the only copy of the secret.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IssuedApiKey {
+    pub key: String,
+    pub record: ApiKeyRecord,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ApiKeysFile {
+    version: u32,
+    keys: Vec<ApiKeyRecord>,
+}
+
+type Keys = BTreeMap<String, ApiKeyRecord>;
+
+/// Issued API keys, in memory or persisted as JSON and rewritten on every
+/// change.
+pub struct ApiKeyStore {
+    path: Option<PathBuf>,
+    keys: Mutex<Keys>,
+    clock: SharedClock,
+}
+
+impl fmt::Debug for ApiKeyStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApiKeyStore")
+            .field("path", &self.path)
+            .field("keys", &self.lock().len())
+            .finish()
+    }
+}
+
+impl Default for ApiKeyStore {
+    fn default() -> Self {
+        Self::in_memory()
+    }
+}
+
+impl ApiKeyStore {
+    /// Store kept in memory only.
+    #[must_use]
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            keys: Mutex::new(Keys::new()),
+            clock: SystemClock::shared(),
+        }
+    }
+
+    /// Open the store persisted at `path`, creating it on first change.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, ApiKeyError> {
+        let path = path.into();
+        let storage =
+            |err: &dyn fmt::Display| ApiKeyError::Storage(format!("{}: {err}", path.display()));
+        let keys = match fs::read(&path) {
+            Ok(bytes) => {
+                let file: ApiKeysFile =
+                    serde_json::from_slice(&bytes).map_err(|err| storage(&err))?;
+                if file.version != API_KEYS_VERSION {
+                    return Err(storage(&format_args!(
+                        "unsupported api key version {}",
+                        file.version
+                    )));
+                }
+                file.keys
+                    .into_iter()
+                    .map(|record| (record.id.clone(), record))
+                    .collect()
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Keys::new(),
+            Err(err) => return Err(storage(&err)),
+        };
+        Ok(Self {
+            path: Some(path),
+            keys: Mutex::new(keys),
+            ..Self::in_memory()
+        })
+    }
+
+    /// Stamp `created_at` from `clock` instead of the system clock.
+    #[must_use]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    #[must_use]
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Issue a key acting for `principal` with `capabilities`.
+    pub fn issue(
+        &self,
+        principal: &str,
+        capabilities: &[String],
+        label: &str,
+    ) -> Result<IssuedApiKey, ApiKeyError> {
+        let mut keys = self.lock();
+        let id = loop {
+            let id = Uuid::new_v4().simple().to_string()[..12].to_string();
+            if !keys.contains_key(&id) {
+                break id;
+            }
+        };
+        let mut secret = Vec::with_capacity(32);
+        secret.extend_from_slice(Uuid::new_v4().as_bytes());
+        secret.extend_from_slice(Uuid::new_v4().as_bytes());
+        let secret = URL_SAFE_NO_PAD.encode(secret);
+        let record = ApiKeyRecord {
+            id: id.clone(),
+            principal: principal.into(),
+            capabilities: capabilities.to_vec(),
+            label: label.into(),
+            created_at: self.clock.unix_secs(),
+            revoked: false,
+            secret_hash: blake3::hash(secret.as_bytes()).to_hex().to_string(),
+        };
+        let mut next = keys.clone();
+        next.insert(id.clone(), record.clone());
+        self.persist(&next)?;
+        *keys = next;
+        Ok(IssuedApiKey {
+            key: format!("{API_KEY_PREFIX}{id}_{secret}"),
+            record,
+        })
+    }
+
+    /// Refuse the key with `id` from now on. The record stays so the id
+    /// keeps resolving in audits.
+    pub fn revoke(&self, id: &str) -> Result<ApiKeyRecord, ApiKeyError> {
+        let mut keys = self.lock();
+        let mut next = keys.clone();
+        let record = next
+            .get_mut(id)
+            .ok_or_else(|| ApiKeyError::Unknown(id.into()))?;
+        record.revoked = true;
+        let record = record.clone();
+        self.persist(&next)?;
+        *keys = next;
+        Ok(record)
+    }
+
+    /// Every issued key, revoked ones included, ordered by id.
+    #[must_use]
+    pub fn list(&self) -> Vec<ApiKeyRecord> {
+        self.lock().values().cloned().collect()
+    }
+
+    /// The id of a key-shaped string, without checking the secret.
+    #[must_use]
+    pub fn key_id(key: &str) -> Option<&str> {
+        key.strip_prefix(API_KEY_PREFIX)?
+            .split_once('_')
+            .map(|(id, _)| id)
+    }
+
+    /// The record of `key` if its secret matches and it is not revoked.
+    pub fn verify(&self, key: &str) -> Result<ApiKeyRecord, ApiKeyError> {
+        let (id, secret) = key
+            .strip_prefix(API_KEY_PREFIX)
+            .and_then(|rest| rest.split_once('_'))
+            .ok_or(ApiKeyError::Malformed)?;
+        // Hashed before the lookup so unknown ids take as long as known ones.
+        let digest = blake3::hash(secret.as_bytes());
+        let keys = self.lock();
+        let record = keys
+            .get(id)
+            .ok_or_else(|| ApiKeyError::Unknown(id.into()))?;
+        let expected =
+            blake3::Hash::from_hex(&record.secret_hash).map_err(|_| ApiKeyError::Malformed)?;
+        // `blake3::Hash` compares in constant time.
+        if digest != expected {
+            return Err(ApiKeyError::Mismatch(id.into()));
+        }
+        if record.revoked {
+            return Err(ApiKeyError::Revoked(id.into()));
+        }
+        Ok(record.clone())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Keys> {
+        self.keys.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn persist(&self, keys: &Keys) -> Result<(), ApiKeyError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let io = |err: std::io::Error| ApiKeyError::Storage(format!("{}: {err}", path.display()));
+        let file = ApiKeysFile {
+            version: API_KEYS_VERSION,
+            keys: keys.values().cloned().collect(),
+        };
+        let bytes = serde_json::to_vec_pretty(&file)
+            .map_err(|err| ApiKeyError::Storage(format!("serializing api keys: {err}")))?;
+        storage_atomic::write(path, &bytes).map_err(io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_verify_until_revoked_and_only_hashes_reach_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("api-keys.json");
+        let store = ApiKeyStore::open(&path).unwrap();
+        let issued = store
+            .issue("ci", &["ingest".into()], "nightly ingest")
+            .unwrap();
+        assert!(issued.key.starts_with(API_KEY_PREFIX));
+        assert_eq!(
+            ApiKeyStore::key_id(&issued.key),
+            Some(issued.record.id.as_str())
+        );
+
+        let on_disk = fs::read_to_string(&path).unwrap();
+        let secret = &issued.key[API_KEY_PREFIX.len() + issued.record.id.len() + 1..];
+        assert!(!on_disk.contains(secret));
+
+        let reopened = ApiKeyStore::open(&path).unwrap();
+        let record = reopened.verify(&issued.key).unwrap();
+        assert_eq!(record.principal, "ci");
+        assert_eq!(record.capabilities, vec!["ingest".to_string()]);
+
+        let forged = format!("{API_KEY_PREFIX}{}_{}", issued.record.id, "x".repeat(43));
+        assert_eq!(
+            reopened.verify(&forged),
+            Err(ApiKeyError::Mismatch(issued.record.id.clone()))
+        );
+        assert_eq!(
+            reopened.verify("enx_nounderscore"),
+            Err(ApiKeyError::Malformed)
+        );
+
+        reopened.revoke(&issued.record.id).unwrap();
+        assert_eq!(
+            ApiKeyStore::open(&path).unwrap().verify(&issued.key),
+            Err(ApiKeyError::Revoked(issued.record.id.clone()))
+        );
+        assert_eq!(
+            reopened.revoke("missing"),
+            Err(ApiKeyError::Unknown("missing".into()))
+        );
+    }
+}
@@ -1,18 +1,30 @@
 //! HTTP transport adapter implementation surface.
 
+pub mod api_keys;
+
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::sync::{Arc, Mutex, PoisonError, RwLock, RwLockReadGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use base64::Engine as _;
 use blake3::Hasher;
-use runtime_router::{RouterCommand, RouterError, SessionContext, SharedRouter};
+use runtime_clock::{SharedClock, SystemClock};
+use runtime_principals::SharedPrincipalStore;
+use runtime_router::{
+    Reloadable, RouterCommand, RouterError, SessionContext, SharedRouter, StatusProvider,
+};
+use runtime_telemetry::{
+    ExportRecord, PeerTelemetry, SharedExportPipeline, SharedPeerTelemetry, SharedRedactor,
+};
+use runtime_transport_error::AdapterError;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
 use thiserror::Error;
 use uuid::Uuid;
 
+pub use api_keys::{ApiKeyError, ApiKeyRecord, ApiKeyStore, IssuedApiKey, API_KEY_PREFIX};
+
 /// HTTP binding and security policy configuration.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct HttpConfig {
@@ -76,6 +88,9 @@ pub struct HttpRequest {
     pub headers: HashMap<String, String>,
     /// Parsed JSON body.
     pub body: Value,
+    /// Address of the connection the request arrived on, if the server
+    /// knows it; names the peer in [`PeerTelemetry`].
+    pub remote_addr: Option<String>,
 }
 
 impl HttpRequest {
@@ -86,6 +101,7 @@ impl HttpRequest {
             path: path.into(),
             headers: HashMap::new(),
             body,
+            remote_addr: None,
         }
     }
 
@@ -94,6 +110,22 @@ impl HttpRequest {
         self.headers.insert(key.into(), value.into());
         self
     }
+
+    /// Record the address the request arrived from.
+    #[must_use]
+    pub fn with_remote_addr(mut self, addr: impl Into<String>) -> Self {
+        self.remote_addr = Some(addr.into());
+        self
+    }
+
+    /// Name the request's peer is counted under in [`PeerTelemetry`].
+    #[must_use]
+    pub fn peer(&self) -> String {
+        format!(
+            "http://{}",
+            self.remote_addr.as_deref().unwrap_or("unknown")
+        )
+    }
 }
 
 /// Canonical HTTP response returned by the adapter.
@@ -122,11 +154,54 @@ pub struct TelemetryEvent {
 #[derive(Debug, Default)]
 pub struct TelemetrySink {
     events: Mutex<Vec<TelemetryEvent>>,
+    export: Option<SharedExportPipeline>,
+    redactor: Option<SharedRedactor>,
 }
 
 impl TelemetrySink {
+    /// Also hand every event to `pipeline`.
+    #[must_use]
+    pub fn with_exporter(mut self, pipeline: SharedExportPipeline) -> Self {
+        self.export = Some(pipeline);
+        self
+    }
+
+    /// Pass every event message through `redactor` before recording it.
+    #[must_use]
+    pub fn with_redactor(mut self, redactor: SharedRedactor) -> Self {
+        self.redactor = Some(redactor);
+        self
+    }
+
+    /// `text` as the sink would record it.
+    #[must_use]
+    pub fn redacted(&self, text: &str) -> String {
+        self.redactor
+            .as_ref()
+            .map_or_else(|| text.to_string(), |redactor| redactor.redact(text))
+    }
+
+    /// Empty sink with the same exporter and redactor.
+    fn settings(&self) -> Self {
+        Self {
+            events: Mutex::default(),
+            export: self.export.clone(),
+            redactor: self.redactor.clone(),
+        }
+    }
+
     /// Record a telemetry event synchronously.
-    pub fn record(&self, event: TelemetryEvent) {
+    pub fn record(&self, mut event: TelemetryEvent) {
+        if self.redactor.is_some() {
+            event.message = self.redacted(&event.message);
+        }
+        if let Some(export) = &self.export {
+            export.record(ExportRecord::now(
+                &event.kind,
+                event.principal.as_deref(),
+                &event.message,
+            ));
+        }
         self.events.lock().unwrap().push(event);
     }
 
@@ -136,43 +211,75 @@ impl TelemetrySink {
     }
 }
 
-/// Errors surfaced by the HTTP adapter.
+/// Failures only the HTTP adapter has.
 #[derive(Debug, Error, PartialEq, Eq)]
-pub enum TransportError {
-    /// Configuration validation failure.
-    #[error("configuration error: {0}")]
-    Configuration(String),
-    /// Authentication or authorization failure.
-    #[error("unauthorized: {0}")]
-    Unauthorized(String),
+pub enum HttpError {
     /// CSRF guard failure.
     #[error("csrf violation: {0}")]
     Csrf(String),
-    /// Invalid request payload or method.
-    #[error("invalid request: {0}")]
-    InvalidRequest(String),
-    /// Router surfaced an error.
-    #[error("router error: {0}")]
-    Router(RouterError),
 }
 
-impl TransportError {
-    /// Return the status code associated with a router error, if available.
-    #[must_use]
-    pub const fn router_status_code(&self) -> Option<u16> {
-        match self {
-            Self::Router(err) => Some(err.status_code()),
-            _ => None,
-        }
+impl AdapterError for HttpError {
+    fn status_code(&self) -> u16 {
+        403
+    }
+
+    fn kind(&self) -> &'static str {
+        "csrf"
     }
 }
 
+/// Errors surfaced by the HTTP adapter.
+pub type TransportError = runtime_transport_error::TransportError<HttpError>;
+
+/// 429 response telling the client when to retry. `Retry-After` only
+/// carries whole seconds, so it rounds up; the body keeps the exact delay.
+fn throttled_response(err: &RouterError, wait: Duration) -> HttpResponse {
+    let retry_after_ms = u64::try_from(wait.as_millis()).unwrap_or(u64::MAX);
+    let mut headers = HashMap::new();
+    headers.insert("content-type".into(), "application/json".into());
+    headers.insert(
+        "retry-after".into(),
+        retry_after_ms.div_ceil(1000).max(1).to_string(),
+    );
+    HttpResponse {
+        status: err.status_code(),
+        headers,
+        body: json!({ "error": err.to_string(), "retry_after_ms": retry_after_ms }),
+    }
+}
+
+/// Bytes of `body` once serialized, as sent on the wire.
+fn body_len(body: &Value) -> usize {
+    serde_json::to_vec(body).map_or(0, |bytes| bytes.len())
+}
+
+/// Session token lifetime used unless [`HttpAdapter::with_session_ttl`]
+/// overrides it.
+pub const DEFAULT_SESSION_TTL: Duration = Duration::from_secs(3600);
+
 /// HTTP adapter bridging requests into the runtime router.
 pub struct HttpAdapter {
-    config: HttpConfig,
+    config: RwLock<HttpConfig>,
     router: SharedRouter,
     telemetry: Arc<TelemetrySink>,
     signer: TokenSigner,
+    session_ttl: Duration,
+    started: Instant,
+    principals: Option<SharedPrincipalStore>,
+    api_keys: Option<Arc<ApiKeyStore>>,
+    peers: SharedPeerTelemetry,
+}
+
+/// Who a request acts for, from its session token or API key.
+struct Caller {
+    principal: String,
+    capabilities: Vec<String>,
+    token_id: Option<Uuid>,
+    expires_at: Option<u64>,
+    /// Only session tokens have one; API keys are not sent by browsers,
+    /// so there is no cross-site request to guard against.
+    csrf_nonce: Option<String>,
 }
 
 impl HttpAdapter {
@@ -182,32 +289,143 @@ impl HttpAdapter {
         let telemetry = Arc::new(TelemetrySink::default());
         let signer = TokenSigner::new(config.token_secret.clone());
         Ok(Self {
-            config,
+            config: RwLock::new(config),
             router,
             telemetry,
             signer,
+            session_ttl: DEFAULT_SESSION_TTL,
+            started: Instant::now(),
+            principals: None,
+            api_keys: None,
+            peers: Arc::new(PeerTelemetry::default()),
         })
     }
 
+    /// Lifetime of the session tokens issued from now on; an hour unless
+    /// overridden. A zero TTL issues tokens that are already expired.
+    #[must_use]
+    pub fn with_session_ttl(mut self, ttl: Duration) -> Self {
+        self.session_ttl = ttl;
+        self
+    }
+
+    /// Issue and check session tokens against `clock` instead of the system
+    /// clock.
+    #[must_use]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        let secret = self.config().token_secret.clone();
+        self.signer = TokenSigner::with_clock(secret, clock);
+        self
+    }
+
+    /// Check principals against `store` instead of the config's
+    /// `allowed_principals`, when issuing tokens and on every request, so
+    /// changes made through the store apply to sessions already issued.
+    #[must_use]
+    pub fn with_principals(mut self, store: SharedPrincipalStore) -> Self {
+        self.principals = Some(store);
+        self
+    }
+
+    /// Also export every telemetry event through `pipeline`, which other
+    /// adapters may share.
+    #[must_use]
+    pub fn with_exporter(mut self, pipeline: SharedExportPipeline) -> Self {
+        self.telemetry = Arc::new(self.telemetry.settings().with_exporter(pipeline));
+        self
+    }
+
+    /// Scrub telemetry event messages and peer failure details with
+    /// `redactor`, such as the sanitizer's redaction patterns, before they
+    /// are recorded or exported.
+    #[must_use]
+    pub fn with_redactor(mut self, redactor: SharedRedactor) -> Self {
+        self.telemetry = Arc::new(self.telemetry.settings().with_redactor(redactor));
+        self
+    }
+
+    /// Count requests, bytes and failures in `telemetry`, which other
+    /// adapters may share, instead of in counters of the adapter's own.
+    #[must_use]
+    pub fn with_peer_telemetry(mut self, telemetry: SharedPeerTelemetry) -> Self {
+        self.peers = telemetry;
+        self
+    }
+
+    /// Also accept `Authorization: Bearer enx_...` API keys from `store`.
+    /// Key requests act for the key's principal with its capabilities, still
+    /// subject to the principal check, and skip the CSRF check.
+    #[must_use]
+    pub fn with_api_keys(mut self, store: Arc<ApiKeyStore>) -> Self {
+        self.api_keys = Some(store);
+        self
+    }
+
+    fn config(&self) -> RwLockReadGuard<'_, HttpConfig> {
+        self.config.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn permits(&self, principal: &str) -> bool {
+        match &self.principals {
+            Some(store) => store.is_active(principal),
+            None => self
+                .config()
+                .allowed_principals
+                .iter()
+                .any(|p| p == principal),
+        }
+    }
+
+    /// Tenant `principal`'s sessions are confined to, per the principal
+    /// store. Without a store every session is unconfined.
+    fn tenant_of(&self, principal: &str) -> Option<String> {
+        self.principals
+            .as_ref()
+            .and_then(|store| store.tenant_of(principal))
+    }
+
+    /// Principals [`Self::permits`] accepts; a store that cannot list its
+    /// principals counts as none.
+    fn allowed_principal_count(&self) -> usize {
+        match &self.principals {
+            Some(store) => store.list().map_or(0, |records| {
+                records.iter().filter(|record| !record.disabled).count()
+            }),
+            None => self.config().allowed_principals.len(),
+        }
+    }
+
+    /// Apply `config` to the running adapter in one step. The allowed
+    /// principals and `require_csrf` take effect from the next request;
+    /// sessions of principals no longer allowed are refused from then on.
+    /// `host`, `port`, `tls_required` and `token_secret` only change with a
+    /// restart, so a `config` changing them is rejected.
+    pub fn reload(&self, config: HttpConfig) -> Result<(), TransportError> {
+        config.validate()?;
+        let mut current = self.config.write().unwrap_or_else(PoisonError::into_inner);
+        restart_only_changes(&current, &config)?;
+        *current = config;
+        drop(current);
+        self.telemetry.record(TelemetryEvent {
+            kind: "http.config.reloaded".into(),
+            principal: None,
+            message: String::new(),
+        });
+        Ok(())
+    }
+
     /// Issue a session token for the provided principal and capabilities.
     pub fn issue_session_token(
         &self,
         principal: &str,
         capabilities: &[String],
     ) -> Result<SessionToken, TransportError> {
-        if !self
-            .config
-            .allowed_principals
-            .iter()
-            .any(|p| p == principal)
-        {
+        if !self.permits(principal) {
             return Err(TransportError::Unauthorized(format!(
                 "principal {principal} is not permitted",
             )));
         }
-        let token = self
-            .signer
-            .issue(principal, capabilities, Duration::from_secs(3600));
+        let token = self.signer.issue(principal, capabilities, self.session_ttl);
         self.telemetry.record(TelemetryEvent {
             kind: "http.session.issued".into(),
             principal: Some(principal.into()),
@@ -218,44 +436,55 @@ impl HttpAdapter {
 
     /// Dispatch a normalized request to the router.
     pub async fn dispatch(&self, request: HttpRequest) -> Result<HttpResponse, TransportError> {
+        let peer = request.peer();
+        self.peers.frame_in(&peer, body_len(&request.body));
+        let result = self.route(request).await;
+        match &result {
+            Ok(response) => self.peers.frame_out(&peer, body_len(&response.body)),
+            Err(
+                TransportError::Unauthorized(detail)
+                | TransportError::Adapter(HttpError::Csrf(detail)),
+            ) => self
+                .peers
+                .auth_failure(&peer, &self.telemetry.redacted(detail)),
+            Err(TransportError::InvalidRequest(detail)) => self
+                .peers
+                .decode_error(&peer, &self.telemetry.redacted(detail)),
+            Err(_) => {}
+        }
+        result
+    }
+
+    async fn route(&self, request: HttpRequest) -> Result<HttpResponse, TransportError> {
         let token_str = self
             .header(&request, "authorization")
             .ok_or_else(|| TransportError::Unauthorized("missing authorization header".into()))?
             .strip_prefix("Bearer ")
             .ok_or_else(|| TransportError::Unauthorized("expected bearer token".into()))?
             .to_string();
-        let principal_hint = self.decode_principal(&token_str);
-        let envelope = match self.signer.verify(&token_str) {
-            Ok(envelope) => envelope,
-            Err(err @ TransportError::Unauthorized(_)) => {
-                self.telemetry.record(TelemetryEvent {
-                    kind: "http.auth.failure".into(),
-                    principal: principal_hint,
-                    message: err.to_string(),
-                });
-                return Err(err);
-            }
-            Err(err) => return Err(err),
+        let caller = if token_str.starts_with(API_KEY_PREFIX) {
+            self.verify_api_key(&token_str)?
+        } else {
+            self.verify_session(&token_str)?
         };
 
-        if !self
-            .config
-            .allowed_principals
-            .iter()
-            .any(|p| p == &envelope.principal)
-        {
+        if !self.permits(&caller.principal) {
             return Err(TransportError::Unauthorized(format!(
                 "principal {} is not permitted",
-                envelope.principal
+                caller.principal
             )));
         }
 
-        if self.config.require_csrf {
-            let csrf = self
-                .header(&request, "x-csrf-token")
-                .ok_or_else(|| TransportError::Csrf("missing csrf token".into()))?;
-            if csrf != &envelope.csrf_nonce {
-                return Err(TransportError::Csrf("csrf token mismatch".into()));
+        if self.config().require_csrf {
+            if let Some(nonce) = &caller.csrf_nonce {
+                let csrf = self.header(&request, "x-csrf-token").ok_or_else(|| {
+                    TransportError::Adapter(HttpError::Csrf("missing csrf token".into()))
+                })?;
+                if csrf != nonce {
+                    return Err(TransportError::Adapter(HttpError::Csrf(
+                        "csrf token mismatch".into(),
+                    )));
+                }
             }
         }
 
@@ -266,50 +495,74 @@ impl HttpAdapter {
             .ok_or_else(|| TransportError::InvalidRequest("command field missing".into()))?;
         let payload = request.body.get("payload").cloned().unwrap_or(Value::Null);
 
-        let host = if self.config.host.contains(':') {
-            format!("[{}]", self.config.host)
+        let host = if self.config().host.contains(':') {
+            format!("[{}]", self.config().host)
         } else {
-            self.config.host.clone()
+            self.config().host.clone()
         };
 
         let context = SessionContext {
-            principal: envelope.principal.clone(),
-            capabilities: envelope.capabilities.clone(),
+            principal: caller.principal.clone(),
+            capabilities: caller.capabilities,
             trace_id: Uuid::new_v4(),
-            token_id: Some(envelope.token_id),
+            token_id: caller.token_id,
             peer: Some(format!(
                 "http://{}:{}{}",
-                host, self.config.port, request.path
+                host,
+                self.config().port,
+                request.path
             )),
+            tenant_id: self.tenant_of(&caller.principal),
+            expires_at: caller.expires_at,
         };
 
         self.telemetry.record(TelemetryEvent {
             kind: "http.request".into(),
-            principal: Some(envelope.principal.clone()),
+            principal: Some(caller.principal.clone()),
             message: command_name.to_string(),
         });
 
-        let response = self
+        let response = match self
             .router
             .dispatch(context, RouterCommand::new(command_name, payload))
             .await
-            .map_err(|err| {
+        {
+            Ok(response) => response,
+            Err(err) => {
+                let throttled = err.retry_after();
                 self.telemetry.record(TelemetryEvent {
-                    kind: "http.router.error".into(),
-                    principal: Some(envelope.principal.clone()),
+                    kind: if throttled.is_some() {
+                        "http.throttled".into()
+                    } else {
+                        "http.router.error".into()
+                    },
+                    principal: Some(caller.principal.clone()),
                     message: err.to_string(),
                 });
-                TransportError::Router(err)
-            })?;
+                return match throttled {
+                    Some(wait) => Ok(throttled_response(&err, wait)),
+                    None => Err(TransportError::Router(err)),
+                };
+            }
+        };
 
         self.telemetry.record(TelemetryEvent {
             kind: "http.response".into(),
-            principal: Some(envelope.principal.clone()),
+            principal: Some(caller.principal.clone()),
             message: response.status_code.to_string(),
         });
 
         let mut headers = HashMap::new();
         headers.insert("content-type".into(), "application/json".into());
+        if let Some(page) = &response.page {
+            headers.insert("x-page-size".into(), page.page_size.to_string());
+            if let Some(cursor) = &page.next_cursor {
+                headers.insert("x-next-cursor".into(), cursor.clone());
+            }
+            if let Some(total) = page.total_estimate {
+                headers.insert("x-total-estimate".into(), total.to_string());
+            }
+        }
 
         Ok(HttpResponse {
             status: response.status_code,
@@ -324,6 +577,11 @@ impl HttpAdapter {
         Arc::clone(&self.telemetry)
     }
 
+    #[must_use]
+    pub fn peer_telemetry(&self) -> SharedPeerTelemetry {
+        Arc::clone(&self.peers)
+    }
+
     fn header<'a>(&self, request: &'a HttpRequest, key: &str) -> Option<&'a String> {
         request
             .headers
@@ -332,6 +590,56 @@ impl HttpAdapter {
             .map(|(_, v)| v)
     }
 
+    fn verify_session(&self, token: &str) -> Result<Caller, TransportError> {
+        let envelope = match self.signer.verify(token) {
+            Ok(envelope) => envelope,
+            Err(err @ TransportError::Unauthorized(_)) => {
+                self.telemetry.record(TelemetryEvent {
+                    kind: "http.auth.failure".into(),
+                    principal: self.decode_principal(token),
+                    message: err.to_string(),
+                });
+                return Err(err);
+            }
+            Err(err) => return Err(err),
+        };
+        Ok(Caller {
+            principal: envelope.principal,
+            capabilities: envelope.capabilities,
+            token_id: Some(envelope.token_id),
+            expires_at: Some(envelope.expires_at),
+            csrf_nonce: Some(envelope.csrf_nonce),
+        })
+    }
+
+    fn verify_api_key(&self, key: &str) -> Result<Caller, TransportError> {
+        let Some(store) = &self.api_keys else {
+            return Err(TransportError::Unauthorized(
+                "api keys are not accepted".into(),
+            ));
+        };
+        match store.verify(key) {
+            Ok(record) => Ok(Caller {
+                principal: record.principal,
+                capabilities: record.capabilities,
+                token_id: None,
+                expires_at: None,
+                csrf_nonce: None,
+            }),
+            Err(err) => {
+                // Why the key failed stays server-side: telling unknown,
+                // revoked and mismatched keys apart would let a caller probe
+                // which key ids exist.
+                self.telemetry.record(TelemetryEvent {
+                    kind: "http.auth.failure".into(),
+                    principal: None,
+                    message: err.to_string(),
+                });
+                Err(TransportError::Unauthorized("invalid api key".into()))
+            }
+        }
+    }
+
     fn decode_principal(&self, token: &str) -> Option<String> {
         let bytes = URL_SAFE_NO_PAD.decode(token).ok()?;
         let envelope: TokenEnvelope = serde_json::from_slice(&bytes).ok()?;
@@ -339,6 +647,66 @@ impl HttpAdapter {
     }
 }
 
+/// Fail when `next` changes settings that need a restart.
+fn restart_only_changes(current: &HttpConfig, next: &HttpConfig) -> Result<(), TransportError> {
+    let changed: Vec<&str> = [
+        ("host", current.host != next.host),
+        ("port", current.port != next.port),
+        ("tls_required", current.tls_required != next.tls_required),
+        ("token_secret", current.token_secret != next.token_secret),
+    ]
+    .into_iter()
+    .filter_map(|(field, changed)| changed.then_some(field))
+    .collect();
+    if changed.is_empty() {
+        return Ok(());
+    }
+    Err(TransportError::Configuration(format!(
+        "{} cannot change without a restart",
+        changed.join(", ")
+    )))
+}
+
+/// Reloads an [`HttpConfig`] through [`HttpAdapter::reload`] for the
+/// `config.reload` command.
+impl Reloadable for HttpAdapter {
+    fn check(&self, config: &Value) -> Result<(), RouterError> {
+        let config: HttpConfig = parse_reload(config.clone())?;
+        config.validate().map_err(reload_error)?;
+        restart_only_changes(&self.config(), &config).map_err(reload_error)
+    }
+
+    fn reload(&self, config: Value) -> Result<(), RouterError> {
+        Self::reload(self, parse_reload(config)?).map_err(reload_error)
+    }
+}
+
+fn parse_reload(config: Value) -> Result<HttpConfig, RouterError> {
+    serde_json::from_value(config).map_err(|err| RouterError::InvalidRequest {
+        detail: err.to_string(),
+    })
+}
+
+fn reload_error(err: TransportError) -> RouterError {
+    RouterError::InvalidRequest {
+        detail: err.to_string(),
+    }
+}
+
+/// Reports `{ uptime_ms, session_ttl_secs, allowed_principals,
+/// telemetry_events }` for the `status` command.
+#[async_trait::async_trait]
+impl StatusProvider for HttpAdapter {
+    async fn report(&self) -> Result<Value, RouterError> {
+        Ok(json!({
+            "uptime_ms": u64::try_from(self.started.elapsed().as_millis()).unwrap_or(u64::MAX),
+            "session_ttl_secs": self.session_ttl.as_secs(),
+            "allowed_principals": self.allowed_principal_count(),
+            "telemetry_events": self.telemetry.events().len(),
+        }))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct TokenEnvelope {
     token_id: Uuid,
@@ -365,15 +733,20 @@ impl TokenEnvelope {
 #[derive(Debug, Clone)]
 struct TokenSigner {
     secret: String,
+    clock: SharedClock,
 }
 
 impl TokenSigner {
-    const fn new(secret: String) -> Self {
-        Self { secret }
+    fn new(secret: String) -> Self {
+        Self::with_clock(secret, SystemClock::shared())
+    }
+
+    const fn with_clock(secret: String, clock: SharedClock) -> Self {
+        Self { secret, clock }
     }
 
     fn issue(&self, principal: &str, capabilities: &[String], ttl: Duration) -> SessionToken {
-        let expires_at = SystemTime::now() + ttl;
+        let expires_at = self.clock.now() + ttl;
         let expires_unix = expires_at
             .duration_since(UNIX_EPOCH)
             .unwrap_or_default()
@@ -408,11 +781,7 @@ impl TokenSigner {
                 "token signature mismatch".into(),
             ));
         }
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        if envelope.expires_at <= now {
+        if envelope.expires_at <= self.clock.unix_secs() {
             return Err(TransportError::Unauthorized("token expired".into()));
         }
         Ok(envelope)
@@ -432,10 +801,16 @@ impl TokenSigner {
     }
 }
 
+#[cfg(feature = "test-support")]
+pub mod test_support;
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use runtime_router::{RecordingRouter, RouterResponse};
+    use runtime_clock::{Clock, MockClock};
+    use runtime_router::{
+        CommandHandler, HandlerRouter, Page, RecordingRouter, RouterResponse, BATCH_COMMAND,
+    };
     use serde_json::json;
 
     fn config() -> HttpConfig {
@@ -512,6 +887,179 @@ mod tests {
         assert_eq!(calls[0].command.name, "ingest");
     }
 
+    struct EchoHandler;
+
+    #[async_trait::async_trait]
+    impl CommandHandler for EchoHandler {
+        async fn handle(
+            &self,
+            _ctx: &SessionContext,
+            payload: Value,
+        ) -> Result<RouterResponse, RouterError> {
+            Ok(RouterResponse::ok(payload))
+        }
+    }
+
+    fn echo_router() -> SharedRouter {
+        let mut router = HandlerRouter::new();
+        router.register("echo", Arc::new(EchoHandler));
+        Arc::new(router)
+    }
+
+    fn batch_body() -> Value {
+        json!({
+            "command": BATCH_COMMAND,
+            "payload": { "commands": [
+                { "command": "echo", "payload": { "n": 1 } },
+                { "command": "missing" },
+                { "command": "echo", "payload": { "n": 2 } },
+            ] },
+        })
+    }
+
+    fn assert_batch_results(payload: &Value) {
+        let results = payload["results"].as_array().expect("results");
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0]["payload"], json!({ "n": 1 }));
+        assert_eq!(results[1]["status_code"], json!(404));
+        assert_eq!(results[2]["payload"], json!({ "n": 2 }));
+    }
+
+    #[tokio::test]
+    async fn api_keys_authenticate_without_session_or_csrf() {
+        let router = Arc::new(RecordingRouter::default());
+        let keys = Arc::new(ApiKeyStore::in_memory());
+        let adapter = HttpAdapter::bind(config(), router.clone() as SharedRouter)
+            .unwrap()
+            .with_api_keys(keys.clone());
+        let issued = keys
+            .issue("alice", &["ingest".into()], "nightly ingest")
+            .unwrap();
+        let request = |key: &str| {
+            HttpRequest::new("POST", "/commands/ingest", json!({ "command": "ingest" }))
+                .with_header("Authorization", format!("Bearer {key}"))
+        };
+
+        let response = adapter.dispatch(request(&issued.key)).await.unwrap();
+        assert_eq!(response.status, 200);
+        let calls = router.calls().await;
+        assert_eq!(calls[0].context.principal, "alice");
+        assert_eq!(calls[0].context.capabilities, vec!["ingest".to_string()]);
+        assert_eq!(calls[0].context.token_id, None);
+
+        // Revoked, forged and unknown keys are refused alike.
+        let rejected = TransportError::Unauthorized("invalid api key".into());
+        let forged = format!("{API_KEY_PREFIX}{}_{}", issued.record.id, "x".repeat(43));
+        let unknown = format!("{API_KEY_PREFIX}000000000000_{}", "x".repeat(43));
+        for key in [&forged, &unknown, &"enx_nounderscore".to_string()] {
+            let err = adapter.dispatch(request(key)).await.unwrap_err();
+            assert_eq!(err, rejected);
+        }
+        keys.revoke(&issued.record.id).unwrap();
+        let err = adapter.dispatch(request(&issued.key)).await.unwrap_err();
+        assert_eq!(err, rejected);
+
+        // Keys act for their principal, so the principal check still applies.
+        let bob = keys.issue("bob", &[], "").unwrap();
+        let err = adapter.dispatch(request(&bob.key)).await.unwrap_err();
+        assert_eq!(
+            err,
+            TransportError::Unauthorized("principal bob is not permitted".into())
+        );
+
+        // Without a key store, key-shaped credentials are refused.
+        let plain = HttpAdapter::bind(config(), router as SharedRouter).unwrap();
+        let other = keys.issue("alice", &[], "").unwrap();
+        let err = plain.dispatch(request(&other.key)).await.unwrap_err();
+        assert!(matches!(err, TransportError::Unauthorized(_)));
+    }
+
+    #[tokio::test]
+    async fn dispatches_command_batches() {
+        let adapter = HttpAdapter::bind(config(), echo_router()).unwrap();
+        let token = adapter
+            .issue_session_token("alice", &[])
+            .expect("token issuance should work");
+        let request = HttpRequest::new("POST", "/commands/batch", batch_body())
+            .with_header("Authorization", format!("Bearer {}", token.token))
+            .with_header("X-Csrf-Token", token.csrf_nonce.clone());
+
+        let response = adapter
+            .dispatch(request)
+            .await
+            .expect("dispatch should succeed");
+        assert_eq!(response.status, 200);
+        assert_batch_results(&response.body);
+    }
+
+    #[tokio::test]
+    async fn paged_responses_carry_cursor_headers() {
+        let router = Arc::new(RecordingRouter::default());
+        router
+            .script_response(Ok(RouterResponse::paged(
+                json!({ "hits": [] }),
+                Page::at_offset(Some(20), 20, Some(45)),
+            )))
+            .await;
+
+        let adapter = HttpAdapter::bind(config(), router as SharedRouter).unwrap();
+        let token = adapter
+            .issue_session_token("alice", &["search".into()])
+            .expect("token issuance should work");
+        let request = HttpRequest::new(
+            "POST",
+            "/commands/search",
+            json!({ "command": "search.query", "payload": {"page_size": 20} }),
+        )
+        .with_header("Authorization", format!("Bearer {}", token.token))
+        .with_header("X-Csrf-Token", token.csrf_nonce.clone());
+
+        let response = adapter
+            .dispatch(request)
+            .await
+            .expect("dispatch should succeed");
+        assert_eq!(response.body, json!({ "hits": [] }));
+        assert_eq!(response.headers["x-next-cursor"], "20");
+        assert_eq!(response.headers["x-page-size"], "20");
+        assert_eq!(response.headers["x-total-estimate"], "45");
+    }
+
+    #[tokio::test]
+    async fn throttled_requests_carry_retry_after() {
+        let router = Arc::new(RecordingRouter::default());
+        router
+            .script_response(Err(RouterError::Throttled {
+                detail: "slow down".into(),
+                retry_after_ms: 1_500,
+            }))
+            .await;
+
+        let adapter = HttpAdapter::bind(config(), router as SharedRouter).unwrap();
+        let token = adapter
+            .issue_session_token("alice", &["search".into()])
+            .expect("token issuance should work");
+        let request = HttpRequest::new(
+            "POST",
+            "/commands/search",
+            json!({ "command": "search.query", "payload": {} }),
+        )
+        .with_header("Authorization", format!("Bearer {}", token.token))
+        .with_header("X-Csrf-Token", token.csrf_nonce.clone());
+
+        let response = adapter
+            .dispatch(request)
+            .await
+            .expect("throttling is a response");
+        assert_eq!(response.status, 429);
+        assert_eq!(response.headers["retry-after"], "2");
+        assert_eq!(response.body["retry_after_ms"], json!(1_500));
+        assert!(adapter
+            .telemetry()
+            .events()
+            .iter()
+            .any(|event| event.kind == "http.throttled"));
+    }
+
     #[tokio::test]
     async fn dispatch_rejects_expired_token() {
         let router = Arc::new(RecordingRouter::default());
@@ -556,6 +1104,46 @@ mod tests {
         }));
     }
 
+    #[tokio::test]
+    async fn session_tokens_expire_on_the_injected_clock() {
+        let clock = Arc::new(MockClock::default());
+        let router = Arc::new(RecordingRouter::default());
+        let adapter = HttpAdapter::bind(config(), router as SharedRouter)
+            .unwrap()
+            .with_session_ttl(Duration::from_secs(60))
+            .with_clock(clock.clone());
+        let token = adapter
+            .issue_session_token("alice", &["ingest".into()])
+            .expect("token issuance should work");
+        assert_eq!(
+            token.expires_at,
+            clock.now() + Duration::from_secs(60),
+            "expiry is stamped from the injected clock"
+        );
+        clock.advance(Duration::from_secs(60));
+
+        let request = HttpRequest::new(
+            "POST",
+            "/commands/ingest",
+            json!({ "command": "ingest", "payload": {"doc": 1} }),
+        )
+        .with_header("Authorization", format!("Bearer {}", token.token))
+        .with_header("X-Csrf-Token", token.csrf_nonce.clone());
+
+        let err = adapter
+            .dispatch(request)
+            .await
+            .expect_err("expired token must be rejected");
+        assert!(
+            matches!(err, TransportError::Unauthorized(message) if message.contains("expired"))
+        );
+
+        let events = adapter.telemetry().events();
+        assert!(events.iter().any(|event| {
+            event.kind == "http.auth.failure" && event.principal.as_deref() == Some("alice")
+        }));
+    }
+
     #[tokio::test]
     async fn rejects_missing_csrf() {
         let router = Arc::new(RecordingRouter::default());
@@ -568,7 +1156,8 @@ mod tests {
             .with_header("Authorization", format!("Bearer {}", token.token));
 
         let err = adapter.dispatch(request).await.expect_err("csrf required");
-        assert!(matches!(err, TransportError::Csrf(_)));
+        assert!(matches!(err, TransportError::Adapter(HttpError::Csrf(_))));
+        assert_eq!(err.status_code(), 403);
     }
 
     #[tokio::test]
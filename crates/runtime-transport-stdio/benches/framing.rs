@@ -0,0 +1,52 @@
+//! `FramingCodec::encode` and `decode` across payload sizes, including token
+//! verification and the checksum.
+//!
+//! Run with `cargo bench -p runtime-transport-stdio --bench framing`.
+
+use std::hint::black_box;
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use runtime_router::RecordingRouter;
+use runtime_transport_stdio::{StdioAdapter, StdioConfig};
+use serde_json::{json, Value};
+
+const PAYLOAD_SIZES: [usize; 3] = [256, 16 * 1024, 256 * 1024];
+
+fn payload(size: usize) -> Value {
+    json!({
+        "command": "ingest",
+        "payload": { "chunk": "x".repeat(size) },
+    })
+}
+
+fn framing(c: &mut Criterion) {
+    let adapter = StdioAdapter::bind(
+        StdioConfig {
+            max_frame_length: 1024 * 1024,
+            allowed_principals: vec!["bench".into()],
+            token_secret: "bench-secret".into(),
+        },
+        Arc::new(RecordingRouter::default()),
+    )
+    .expect("valid config");
+    let token = adapter.issue_session_token("bench").expect("token");
+    let codec = adapter.codec();
+
+    let mut group = c.benchmark_group("stdio_framing");
+    for size in PAYLOAD_SIZES {
+        let value = payload(size);
+        let frame = codec.encode(&value, &token).expect("encode");
+        group.throughput(Throughput::Bytes(frame.payload.len() as u64));
+        group.bench_with_input(BenchmarkId::new("encode", size), &value, |b, value| {
+            b.iter(|| codec.encode(black_box(value), &token).expect("encode"));
+        });
+        group.bench_with_input(BenchmarkId::new("decode", size), &frame, |b, frame| {
+            b.iter(|| codec.decode(black_box(frame)).expect("decode"));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, framing);
+criterion_main!(benches);
@@ -1,18 +1,32 @@
 //! STDIO transport adapter framing and dispatch scaffolding.
 
 use std::collections::VecDeque;
-use std::sync::{Arc, Mutex};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock, RwLockReadGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use base64::Engine as _;
 use blake3::Hasher;
-use runtime_router::{RouterCommand, RouterError, SessionContext, SharedRouter};
+use runtime_clock::{SharedClock, SystemClock};
+use runtime_principals::SharedPrincipalStore;
+use runtime_router::{
+    Reloadable, RouterCommand, RouterError, SessionContext, SharedRouter, StatusProvider,
+};
+use runtime_telemetry::{
+    ExportRecord, PeerTelemetry, SharedExportPipeline, SharedPeerTelemetry, SharedRedactor,
+};
+use runtime_transport_error::AdapterError;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use storage_ledger::{AgeDistribution, BufferStats, EvictionReason};
 use thiserror::Error;
 use uuid::Uuid;
 
+mod stream;
+
+pub use stream::{StreamReader, StreamWriter, DEFAULT_MAX_STREAM_BYTES};
+
 /// STDIO adapter configuration.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StdioConfig {
@@ -70,14 +84,12 @@ impl FramingCodec {
     pub fn encode(&self, json: &Value, token: &SessionToken) -> Result<StdioFrame, TransportError> {
         self.signer.verify(&token.token)?;
         let payload_bytes = serde_json::to_vec(json)
-            .map_err(|err| TransportError::Framing(format!("json serialization failed: {err}")))?;
+            .map_err(|err| framing_error(format!("json serialization failed: {err}")))?;
         let token_bytes = token.token.as_bytes();
 
         let frame_len = 4 + 2 + payload_bytes.len() + token_bytes.len() + 16;
         if frame_len > self.max_frame_length {
-            return Err(TransportError::Framing(
-                "frame exceeds maximum length".into(),
-            ));
+            return Err(framing_error("frame exceeds maximum length".into()));
         }
 
         let mut buffer = Vec::with_capacity(frame_len);
@@ -101,12 +113,10 @@ impl FramingCodec {
         frame: &StdioFrame,
     ) -> Result<(Value, TokenEnvelope), TransportError> {
         if frame.payload.len() < 4 + 2 + 16 {
-            return Err(TransportError::Framing("frame too short".into()));
+            return Err(framing_error("frame too short".into()));
         }
         if frame.payload.len() > self.max_frame_length {
-            return Err(TransportError::Framing(
-                "frame exceeds maximum length".into(),
-            ));
+            return Err(framing_error("frame exceeds maximum length".into()));
         }
 
         let mut cursor = &frame.payload[..];
@@ -116,20 +126,20 @@ impl FramingCodec {
         cursor = &cursor[2..];
 
         if payload_len + token_len + 16 != cursor.len() {
-            return Err(TransportError::Framing("frame length mismatch".into()));
+            return Err(framing_error("frame length mismatch".into()));
         }
 
         let (payload_bytes, remainder) = cursor.split_at(payload_len);
         let (token_bytes, checksum) = remainder.split_at(token_len);
         let expected_checksum = self.checksum(&frame.payload[..frame.payload.len() - 16]);
         if checksum != expected_checksum {
-            return Err(TransportError::Framing("checksum mismatch".into()));
+            return Err(framing_error("checksum mismatch".into()));
         }
 
         let payload: Value = serde_json::from_slice(payload_bytes)
-            .map_err(|err| TransportError::Framing(format!("invalid json: {err}")))?;
-        let token = std::str::from_utf8(token_bytes)
-            .map_err(|_| TransportError::Framing("token not utf8".into()))?;
+            .map_err(|err| framing_error(format!("invalid json: {err}")))?;
+        let token =
+            std::str::from_utf8(token_bytes).map_err(|_| framing_error("token not utf8".into()))?;
         let envelope = self.signer.verify(token)?;
         Ok((payload, envelope))
     }
@@ -152,10 +162,49 @@ pub struct TelemetryEvent {
 #[derive(Debug, Default)]
 pub struct TelemetrySink {
     events: Mutex<Vec<TelemetryEvent>>,
+    export: Option<SharedExportPipeline>,
+    redactor: Option<SharedRedactor>,
 }
 
 impl TelemetrySink {
-    pub fn record(&self, event: TelemetryEvent) {
+    /// Also hand every event to `pipeline`.
+    #[must_use]
+    pub fn with_exporter(mut self, pipeline: SharedExportPipeline) -> Self {
+        self.export = Some(pipeline);
+        self
+    }
+
+    /// Pass every event message through `redactor` before recording it.
+    #[must_use]
+    pub fn with_redactor(mut self, redactor: SharedRedactor) -> Self {
+        self.redactor = Some(redactor);
+        self
+    }
+
+    /// `text` as the sink would record it.
+    #[must_use]
+    pub fn redacted(&self, text: &str) -> String {
+        self.redactor
+            .as_ref()
+            .map_or_else(|| text.to_string(), |redactor| redactor.redact(text))
+    }
+
+    /// Empty sink with the same exporter and redactor.
+    fn settings(&self) -> Self {
+        Self {
+            events: Mutex::default(),
+            export: self.export.clone(),
+            redactor: self.redactor.clone(),
+        }
+    }
+
+    pub fn record(&self, mut event: TelemetryEvent) {
+        if self.redactor.is_some() {
+            event.message = self.redacted(&event.message);
+        }
+        if let Some(export) = &self.export {
+            export.record(ExportRecord::now(&event.kind, None, &event.message));
+        }
         self.events.lock().unwrap().push(event);
     }
 
@@ -164,36 +213,49 @@ impl TelemetrySink {
     }
 }
 
-/// Errors exposed by the STDIO adapter.
+/// Failures only the STDIO adapter has.
 #[derive(Debug, Error, PartialEq, Eq)]
-pub enum TransportError {
-    #[error("configuration error: {0}")]
-    Configuration(String),
-    #[error("authentication failure: {0}")]
-    Unauthorized(String),
+pub enum StdioError {
     #[error("framing error: {0}")]
     Framing(String),
-    #[error("router error: {0}")]
-    Router(RouterError),
 }
 
-impl TransportError {
-    #[must_use]
-    pub const fn router_status_code(&self) -> Option<u16> {
-        match self {
-            Self::Router(err) => Some(err.status_code()),
-            _ => None,
-        }
+impl AdapterError for StdioError {
+    fn status_code(&self) -> u16 {
+        400
+    }
+
+    fn kind(&self) -> &'static str {
+        "framing"
     }
 }
 
+/// Errors exposed by the STDIO adapter.
+pub type TransportError = runtime_transport_error::TransportError<StdioError>;
+
+fn framing_error(detail: String) -> TransportError {
+    TransportError::Adapter(StdioError::Framing(detail))
+}
+
+/// Name the adapter's single peer is counted under in its
+/// [`PeerTelemetry`].
+pub const STDIO_PEER: &str = "stdio";
+
+/// Session token lifetime used unless [`StdioAdapter::with_session_ttl`]
+/// overrides it.
+pub const DEFAULT_SESSION_TTL: Duration = Duration::from_secs(3600);
+
 /// STDIO adapter entry point.
 pub struct StdioAdapter {
-    config: StdioConfig,
+    config: RwLock<StdioConfig>,
     router: SharedRouter,
     telemetry: Arc<TelemetrySink>,
     signer: Arc<TokenSigner>,
     codec: FramingCodec,
+    session_ttl: Duration,
+    started: Instant,
+    principals: Option<SharedPrincipalStore>,
+    peers: SharedPeerTelemetry,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -233,29 +295,66 @@ impl RetryEntry {
     }
 }
 
+/// Callback registered with [`RetryBuffer::with_on_evict`].
+struct EvictionHook(Box<dyn Fn(RetryEntry, EvictionReason) + Send + Sync>);
+
+impl std::fmt::Debug for EvictionHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EvictionHook")
+    }
+}
+
 #[derive(Debug)]
 pub struct RetryBuffer {
     max_entries: usize,
     max_age: Duration,
     inner: Mutex<VecDeque<RetryEntry>>,
     max_sequence_seen: Mutex<Option<u64>>,
+    evicted: AtomicU64,
+    expired: AtomicU64,
+    requeued: AtomicU64,
+    on_evict: Option<EvictionHook>,
+    clock: SharedClock,
 }
 
 impl RetryBuffer {
     #[must_use]
-    pub const fn new(max_entries: usize, max_age: Duration) -> Self {
+    pub fn new(max_entries: usize, max_age: Duration) -> Self {
         Self {
             max_entries,
             max_age,
             inner: Mutex::new(VecDeque::new()),
             max_sequence_seen: Mutex::new(None),
+            evicted: AtomicU64::new(0),
+            expired: AtomicU64::new(0),
+            requeued: AtomicU64::new(0),
+            on_evict: None,
+            clock: SystemClock::shared(),
         }
     }
 
+    /// Stamp and age entries against `clock` instead of the system clock.
+    #[must_use]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Call `hook` with every entry dropped for capacity or age instead of
+    /// losing it silently. The hook runs after the buffer's lock is released.
+    #[must_use]
+    pub fn with_on_evict(
+        mut self,
+        hook: impl Fn(RetryEntry, EvictionReason) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_evict = Some(EvictionHook(Box::new(hook)));
+        self
+    }
+
     pub fn enqueue(&self, payload: RetryPayload) -> Result<(), RetryError> {
         self.push_entry(RetryEntry {
             payload,
-            enqueued_at: SystemTime::now(),
+            enqueued_at: self.clock.now(),
             attempts: 0,
         })
     }
@@ -273,17 +372,57 @@ impl RetryBuffer {
     }
 
     pub fn requeue(&self, entry: RetryEntry) -> Result<(), RetryError> {
-        self.push_entry(entry)
+        self.push_entry(entry)?;
+        self.requeued.fetch_add(1, Ordering::Relaxed);
+        Ok(())
     }
 
     pub fn drain_ready(&self) -> Vec<RetryEntry> {
         let mut guard = self.inner.lock().expect("retry buffer mutex poisoned");
-        let now = SystemTime::now();
-        guard.retain(|entry| match now.duration_since(entry.enqueued_at) {
-            Ok(age) => age <= self.max_age,
-            Err(_) => true,
-        });
-        guard.drain(..).collect()
+        let now = self.clock.now();
+        let (expired, ready): (Vec<_>, Vec<_>) =
+            guard
+                .drain(..)
+                .partition(|entry| match now.duration_since(entry.enqueued_at) {
+                    Ok(age) => age > self.max_age,
+                    Err(_) => false,
+                });
+        drop(guard);
+        self.expired
+            .fetch_add(expired.len() as u64, Ordering::Relaxed);
+        self.notify_evicted(expired, EvictionReason::Expired);
+        ready
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().map(|guard| guard.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Fill level, entry ages and eviction/requeue counts since creation.
+    /// Expired entries are only counted once a drain purges them.
+    pub fn stats(&self) -> BufferStats {
+        let now = self.clock.now();
+        let (len, ages) = {
+            let guard = self.inner.lock().expect("retry buffer mutex poisoned");
+            let ages = guard
+                .iter()
+                .map(|entry| now.duration_since(entry.enqueued_at).unwrap_or_default())
+                .collect();
+            (guard.len(), ages)
+        };
+        BufferStats {
+            len,
+            capacity: self.max_entries,
+            max_age: self.max_age,
+            ages: AgeDistribution::from_ages(ages),
+            evicted: self.evicted.load(Ordering::Relaxed),
+            expired: self.expired.load(Ordering::Relaxed),
+            requeued: self.requeued.load(Ordering::Relaxed),
+        }
     }
 
     pub fn max_sequence(&self) -> Option<u64> {
@@ -299,8 +438,10 @@ impl RetryBuffer {
         // preserve original enqueue time on requeue
         entry.attempts = entry.attempts.saturating_add(1);
         let mut guard = self.inner.lock().expect("retry buffer mutex poisoned");
+        let mut evicted = Vec::new();
         while guard.len() >= self.max_entries {
-            guard.pop_front();
+            evicted.extend(guard.pop_front());
+            self.evicted.fetch_add(1, Ordering::Relaxed);
         }
         {
             let mut max_seen = self.max_sequence_seen.lock().unwrap();
@@ -310,8 +451,18 @@ impl RetryBuffer {
             }
         }
         guard.push_back(entry);
+        drop(guard);
+        self.notify_evicted(evicted, EvictionReason::Capacity);
         Ok(())
     }
+
+    fn notify_evicted(&self, evicted: Vec<RetryEntry>, reason: EvictionReason) {
+        if let Some(EvictionHook(hook)) = &self.on_evict {
+            for entry in evicted {
+                hook(entry, reason);
+            }
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -326,28 +477,123 @@ impl StdioAdapter {
         let signer = Arc::new(TokenSigner::new(config.token_secret.clone()));
         let codec = FramingCodec::new(config.max_frame_length, signer.clone());
         Ok(Self {
-            config,
+            config: RwLock::new(config),
             router,
             telemetry: Arc::new(TelemetrySink::default()),
             signer,
             codec,
+            session_ttl: DEFAULT_SESSION_TTL,
+            started: Instant::now(),
+            principals: None,
+            peers: Arc::new(PeerTelemetry::default()),
         })
     }
 
+    /// Lifetime of the session tokens issued from now on; an hour unless
+    /// overridden. A zero TTL issues tokens that are already expired.
+    #[must_use]
+    pub fn with_session_ttl(mut self, ttl: Duration) -> Self {
+        self.session_ttl = ttl;
+        self
+    }
+
+    /// Issue and check session tokens against `clock` instead of the system
+    /// clock. Tokens issued before the switch stay valid if they verify.
+    #[must_use]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        let (secret, max_frame_length) = {
+            let config = self.config();
+            (config.token_secret.clone(), config.max_frame_length)
+        };
+        self.signer = Arc::new(TokenSigner::with_clock(secret, clock));
+        self.codec = FramingCodec::new(max_frame_length, self.signer.clone());
+        self
+    }
+
+    /// Check principals against `store` instead of the config's
+    /// `allowed_principals`, when issuing tokens and on every request, so
+    /// changes made through the store apply to sessions already issued.
+    #[must_use]
+    pub fn with_principals(mut self, store: SharedPrincipalStore) -> Self {
+        self.principals = Some(store);
+        self
+    }
+
+    /// Also export every telemetry event through `pipeline`, which other
+    /// adapters may share.
+    #[must_use]
+    pub fn with_exporter(mut self, pipeline: SharedExportPipeline) -> Self {
+        self.telemetry = Arc::new(self.telemetry.settings().with_exporter(pipeline));
+        self
+    }
+
+    /// Scrub telemetry event messages and peer failure details with
+    /// `redactor`, such as the sanitizer's redaction patterns, before they
+    /// are recorded or exported.
+    #[must_use]
+    pub fn with_redactor(mut self, redactor: SharedRedactor) -> Self {
+        self.telemetry = Arc::new(self.telemetry.settings().with_redactor(redactor));
+        self
+    }
+
+    /// Count frames, bytes and failures in `telemetry`, which other
+    /// adapters may share, instead of in counters of the adapter's own.
+    #[must_use]
+    pub fn with_peer_telemetry(mut self, telemetry: SharedPeerTelemetry) -> Self {
+        self.peers = telemetry;
+        self
+    }
+
+    fn config(&self) -> RwLockReadGuard<'_, StdioConfig> {
+        self.config.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn permits(&self, principal: &str) -> bool {
+        match &self.principals {
+            Some(store) => store.is_active(principal),
+            None => self
+                .config()
+                .allowed_principals
+                .iter()
+                .any(|p| p == principal),
+        }
+    }
+
+    /// Tenant `principal`'s sessions are confined to, per the principal
+    /// store. Without a store every session is unconfined.
+    fn tenant_of(&self, principal: &str) -> Option<String> {
+        self.principals
+            .as_ref()
+            .and_then(|store| store.tenant_of(principal))
+    }
+
+    /// Apply `config` to the running adapter in one step. The allowed
+    /// principals take effect from the next frame; sessions of principals
+    /// no longer allowed are refused from then on, while the stream itself
+    /// stays open. `max_frame_length` and `token_secret` only change with a
+    /// restart, so a `config` changing them is rejected.
+    pub fn reload(&self, config: StdioConfig) -> Result<(), TransportError> {
+        config.validate()?;
+        let mut current = self.config.write().unwrap_or_else(PoisonError::into_inner);
+        restart_only_changes(&current, &config)?;
+        *current = config;
+        drop(current);
+        self.telemetry.record(TelemetryEvent {
+            kind: "stdio.config.reloaded".into(),
+            message: String::new(),
+        });
+        Ok(())
+    }
+
     pub fn issue_session_token(&self, principal: &str) -> Result<SessionToken, TransportError> {
-        if !self
-            .config
-            .allowed_principals
-            .iter()
-            .any(|p| p == principal)
-        {
+        if !self.permits(principal) {
             return Err(TransportError::Unauthorized(format!(
                 "principal {principal} is not permitted",
             )));
         }
         let IssuedToken { token, token_id } =
             self.signer
-                .issue(principal, &["stdio".into()], Duration::from_secs(3600));
+                .issue(principal, &["stdio".into()], self.session_ttl);
         self.telemetry.record(TelemetryEvent {
             kind: "stdio.session.issued".into(),
             message: token_id.to_string(),
@@ -356,19 +602,65 @@ impl StdioAdapter {
     }
 
     pub async fn dispatch_frame(&self, frame: StdioFrame) -> Result<StdioFrame, TransportError> {
+        let (body, token) = self.respond(frame).await?;
+        let response = self.codec.encode(&body, &token)?;
+        self.peers.frame_out(STDIO_PEER, response.payload.len());
+        Ok(response)
+    }
+
+    /// [`dispatch_frame`](Self::dispatch_frame) for clients that reassemble
+    /// streams with a [`StreamReader`]: a response too large for one frame
+    /// comes back as partial frames and a terminal frame instead of failing.
+    pub async fn dispatch_frame_streamed(
+        &self,
+        frame: StdioFrame,
+    ) -> Result<StreamWriter, TransportError> {
+        let (body, token) = self.respond(frame).await?;
+        let frames = self
+            .codec
+            .encode_stream(&body, &token)?
+            .counted(Arc::clone(&self.peers), STDIO_PEER);
+        if frames.is_streamed() {
+            self.telemetry.record(TelemetryEvent {
+                kind: "stdio.response.streamed".into(),
+                message: frames.len().to_string(),
+            });
+        }
+        Ok(frames)
+    }
+
+    /// Route the request in `frame`; returns the response body and the
+    /// token to sign it with.
+    async fn respond(&self, frame: StdioFrame) -> Result<(Value, SessionToken), TransportError> {
+        self.peers.frame_in(STDIO_PEER, frame.payload.len());
+        let result = self.route(frame).await;
+        match &result {
+            Err(TransportError::Unauthorized(detail)) => {
+                self.peers
+                    .auth_failure(STDIO_PEER, &self.telemetry.redacted(detail));
+                self.telemetry.record(TelemetryEvent {
+                    kind: "stdio.auth.failure".into(),
+                    message: detail.clone(),
+                });
+            }
+            Err(TransportError::Adapter(StdioError::Framing(detail))) => {
+                self.peers
+                    .decode_error(STDIO_PEER, &self.telemetry.redacted(detail));
+            }
+            _ => {}
+        }
+        result
+    }
+
+    async fn route(&self, frame: StdioFrame) -> Result<(Value, SessionToken), TransportError> {
         let (payload, envelope) = self.codec.decode_with_envelope(&frame)?;
         let command = payload
             .get("command")
             .and_then(|v| v.as_str())
-            .ok_or_else(|| TransportError::Framing("command missing".into()))?;
+            .ok_or_else(|| framing_error("command missing".into()))?;
         let body = payload.get("payload").cloned().unwrap_or(Value::Null);
 
-        if !self
-            .config
-            .allowed_principals
-            .iter()
-            .any(|p| p == &envelope.principal)
-        {
+        if !self.permits(&envelope.principal) {
             return Err(TransportError::Unauthorized(format!(
                 "principal {} is not permitted",
                 envelope.principal
@@ -381,6 +673,8 @@ impl StdioAdapter {
             trace_id: Uuid::new_v4(),
             token_id: Some(envelope.token_id),
             peer: Some("stdio".into()),
+            tenant_id: self.tenant_of(&envelope.principal),
+            expires_at: Some(envelope.expires_at),
         };
 
         self.telemetry.record(TelemetryEvent {
@@ -388,37 +682,59 @@ impl StdioAdapter {
             message: command.to_string(),
         });
 
-        let response = self
+        let token = SessionToken {
+            token: envelope.raw_token,
+        };
+        let response = match self
             .router
             .dispatch(context, RouterCommand::new(command, body))
             .await
-            .map_err(|err| {
+        {
+            Ok(response) => response,
+            Err(err) => {
+                let Some(wait) = err.retry_after() else {
+                    self.telemetry.record(TelemetryEvent {
+                        kind: "stdio.router.error".into(),
+                        message: err.to_string(),
+                    });
+                    return Err(TransportError::Router(err));
+                };
+                // Throttling is answered in-band so the client can pace
+                // itself without tearing down the session.
                 self.telemetry.record(TelemetryEvent {
-                    kind: "stdio.router.error".into(),
+                    kind: "stdio.throttled".into(),
                     message: err.to_string(),
                 });
-                TransportError::Router(err)
-            })?;
+                let retry_after_ms = u64::try_from(wait.as_millis()).unwrap_or(u64::MAX);
+                return Ok((
+                    json!({
+                        "status": "throttled",
+                        "error": err.to_string(),
+                        "retry_after_ms": retry_after_ms,
+                    }),
+                    token,
+                ));
+            }
+        };
 
         let status = if (200..=299).contains(&response.status_code) {
             "ok"
         } else {
             "error"
         };
-        let response_body = json!({
+        let mut response_body = json!({
             "status": status,
+            "status_code": response.status_code,
             "payload": response.payload,
         });
+        if let Some(page) = &response.page {
+            response_body["page"] = json!(page);
+        }
         self.telemetry.record(TelemetryEvent {
             kind: "stdio.response".into(),
             message: response.status_code.to_string(),
         });
-        self.codec.encode(
-            &response_body,
-            &SessionToken {
-                token: envelope.raw_token,
-            },
-        )
+        Ok((response_body, token))
     }
 
     #[must_use]
@@ -430,6 +746,80 @@ impl StdioAdapter {
     pub fn telemetry(&self) -> Arc<TelemetrySink> {
         Arc::clone(&self.telemetry)
     }
+
+    #[must_use]
+    pub fn peer_telemetry(&self) -> SharedPeerTelemetry {
+        Arc::clone(&self.peers)
+    }
+}
+
+/// Fail when `next` changes settings that need a restart.
+fn restart_only_changes(current: &StdioConfig, next: &StdioConfig) -> Result<(), TransportError> {
+    let changed: Vec<&str> = [
+        (
+            "max_frame_length",
+            current.max_frame_length != next.max_frame_length,
+        ),
+        ("token_secret", current.token_secret != next.token_secret),
+    ]
+    .into_iter()
+    .filter_map(|(field, changed)| changed.then_some(field))
+    .collect();
+    if changed.is_empty() {
+        return Ok(());
+    }
+    Err(TransportError::Configuration(format!(
+        "{} cannot change without a restart",
+        changed.join(", ")
+    )))
+}
+
+/// Reloads a [`StdioConfig`] through [`StdioAdapter::reload`] for the
+/// `config.reload` command.
+impl Reloadable for StdioAdapter {
+    fn check(&self, config: &Value) -> Result<(), RouterError> {
+        let config: StdioConfig = parse_reload(config.clone())?;
+        config.validate().map_err(reload_error)?;
+        restart_only_changes(&self.config(), &config).map_err(reload_error)
+    }
+
+    fn reload(&self, config: Value) -> Result<(), RouterError> {
+        Self::reload(self, parse_reload(config)?).map_err(reload_error)
+    }
+}
+
+fn parse_reload(config: Value) -> Result<StdioConfig, RouterError> {
+    serde_json::from_value(config).map_err(|err| RouterError::InvalidRequest {
+        detail: err.to_string(),
+    })
+}
+
+fn reload_error(err: TransportError) -> RouterError {
+    RouterError::InvalidRequest {
+        detail: err.to_string(),
+    }
+}
+
+/// Reports `{ uptime_ms, session_ttl_secs, max_frame_length,
+/// telemetry_events }` for the `status` command.
+#[async_trait::async_trait]
+impl StatusProvider for StdioAdapter {
+    async fn report(&self) -> Result<Value, RouterError> {
+        Ok(json!({
+            "uptime_ms": u64::try_from(self.started.elapsed().as_millis()).unwrap_or(u64::MAX),
+            "session_ttl_secs": self.session_ttl.as_secs(),
+            "max_frame_length": self.config().max_frame_length,
+            "telemetry_events": self.telemetry.events().len(),
+        }))
+    }
+}
+
+/// Reports [`BufferStats::to_json`] for the `status` command.
+#[async_trait::async_trait]
+impl StatusProvider for RetryBuffer {
+    async fn report(&self) -> Result<Value, RouterError> {
+        Ok(self.stats().to_json())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -456,15 +846,20 @@ impl TokenEnvelope {
 #[derive(Debug)]
 struct TokenSigner {
     secret: String,
+    clock: SharedClock,
 }
 
 impl TokenSigner {
-    const fn new(secret: String) -> Self {
-        Self { secret }
+    fn new(secret: String) -> Self {
+        Self::with_clock(secret, SystemClock::shared())
+    }
+
+    const fn with_clock(secret: String, clock: SharedClock) -> Self {
+        Self { secret, clock }
     }
 
     fn issue(&self, principal: &str, capabilities: &[String], ttl: Duration) -> IssuedToken {
-        let expires_at = SystemTime::now() + ttl;
+        let expires_at = self.clock.now() + ttl;
         let expires_unix = expires_at
             .duration_since(UNIX_EPOCH)
             .unwrap_or_default()
@@ -498,11 +893,7 @@ impl TokenSigner {
                 "token signature mismatch".into(),
             ));
         }
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        if signed.envelope.expires_at <= now {
+        if signed.envelope.expires_at <= self.clock.unix_secs() {
             return Err(TransportError::Unauthorized("token expired".into()));
         }
         Ok(TokenEnvelope {
@@ -536,10 +927,16 @@ struct IssuedToken {
     token_id: Uuid,
 }
 
+#[cfg(feature = "test-support")]
+pub mod test_support;
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use runtime_router::{RecordingRouter, RouterResponse};
+    use runtime_clock::MockClock;
+    use runtime_router::{
+        CommandHandler, HandlerRouter, RecordingRouter, RouterError, RouterResponse, BATCH_COMMAND,
+    };
     use serde_json::json;
     use std::sync::Arc;
     use std::time::{Duration, SystemTime};
@@ -589,6 +986,159 @@ mod tests {
             .expect("dispatch should succeed");
         let (body, _) = adapter.codec().decode(&response).expect("decode response");
         assert_eq!(body["status"], json!("ok"));
+        assert_eq!(body["status_code"], json!(200));
+    }
+
+    struct EchoHandler;
+
+    #[async_trait::async_trait]
+    impl CommandHandler for EchoHandler {
+        async fn handle(
+            &self,
+            _ctx: &SessionContext,
+            payload: Value,
+        ) -> Result<RouterResponse, RouterError> {
+            Ok(RouterResponse::ok(payload))
+        }
+    }
+
+    fn echo_router() -> SharedRouter {
+        let mut router = HandlerRouter::new();
+        router.register("echo", Arc::new(EchoHandler));
+        Arc::new(router)
+    }
+
+    fn batch_body() -> Value {
+        json!({
+            "command": BATCH_COMMAND,
+            "payload": { "commands": [
+                { "command": "echo", "payload": { "n": 1 } },
+                { "command": "missing" },
+                { "command": "echo", "payload": { "n": 2 } },
+            ] },
+        })
+    }
+
+    fn assert_batch_results(payload: &Value) {
+        let results = payload["results"].as_array().expect("results");
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0]["payload"], json!({ "n": 1 }));
+        assert_eq!(results[1]["status_code"], json!(404));
+        assert_eq!(results[2]["payload"], json!({ "n": 2 }));
+    }
+
+    #[tokio::test]
+    async fn dispatches_command_batches() {
+        let adapter = StdioAdapter::bind(config(), echo_router()).unwrap();
+        let token = adapter
+            .issue_session_token("alice")
+            .expect("token issuance should succeed");
+        let frame = adapter
+            .codec()
+            .encode(&batch_body(), &token)
+            .expect("encode should work");
+
+        let response = adapter
+            .dispatch_frame(frame)
+            .await
+            .expect("dispatch should succeed");
+        let (body, _) = adapter.codec().decode(&response).expect("decode response");
+        assert_eq!(body["status"], json!("ok"));
+        assert_batch_results(&body["payload"]);
+    }
+
+    #[tokio::test]
+    async fn streams_responses_larger_than_one_frame() {
+        let hits: Vec<Value> = (0..100)
+            .map(|n| json!({ "key": format!("src/module_{n}.rs"), "score": 0.5 }))
+            .collect();
+        let router = Arc::new(RecordingRouter::default());
+        for _ in 0..2 {
+            router
+                .script_response(Ok(RouterResponse::ok(json!({ "hits": hits }))))
+                .await;
+        }
+        let adapter = StdioAdapter::bind(config(), router as SharedRouter).unwrap();
+        let token = adapter
+            .issue_session_token("alice")
+            .expect("token issuance should succeed");
+        let request = || {
+            adapter
+                .codec()
+                .encode(&json!({ "command": "search.query" }), &token)
+                .expect("encode should work")
+        };
+
+        let err = adapter
+            .dispatch_frame(request())
+            .await
+            .expect_err("the response does not fit one frame");
+        assert!(matches!(
+            err,
+            TransportError::Adapter(StdioError::Framing(_))
+        ));
+
+        let frames: Vec<StdioFrame> = adapter
+            .dispatch_frame_streamed(request())
+            .await
+            .expect("dispatch should succeed")
+            .collect::<Result<_, _>>()
+            .expect("frames encode");
+        assert!(frames.len() > 1);
+        assert!(frames
+            .iter()
+            .all(|frame| frame.payload.len() <= config().max_frame_length));
+        let bodies: Vec<Value> = frames
+            .iter()
+            .map(|frame| adapter.codec().decode(frame).expect("decode frame").0)
+            .collect();
+
+        let mut reader = StreamReader::default();
+        let (last, partial) = bodies.split_last().unwrap();
+        for body in partial {
+            assert_eq!(reader.push(body.clone()).unwrap(), None);
+        }
+        let body = reader.push(last.clone()).unwrap().expect("terminal frame");
+        assert_eq!(body["status"], json!("ok"));
+        assert_eq!(body["payload"]["hits"], json!(hits));
+
+        let mut reader = StreamReader::default();
+        assert!(reader.push(bodies[1].clone()).is_err());
+        let mut reader = StreamReader::new(16);
+        assert!(reader.push(bodies[0].clone()).is_err());
+        assert!(adapter
+            .telemetry()
+            .events()
+            .iter()
+            .any(|event| event.kind == "stdio.response.streamed"));
+    }
+
+    #[tokio::test]
+    async fn throttled_frames_carry_retry_hint() {
+        let router = Arc::new(RecordingRouter::default());
+        router
+            .script_response(Err(RouterError::Throttled {
+                detail: "slow down".into(),
+                retry_after_ms: 250,
+            }))
+            .await;
+
+        let adapter = StdioAdapter::bind(config(), router as SharedRouter).unwrap();
+        let token = adapter
+            .issue_session_token("alice")
+            .expect("token issuance should succeed");
+        let frame = adapter
+            .codec()
+            .encode(&json!({ "command": "status" }), &token)
+            .expect("encode should work");
+
+        let response = adapter
+            .dispatch_frame(frame)
+            .await
+            .expect("throttling is answered in-band");
+        let (body, _) = adapter.codec().decode(&response).expect("decode response");
+        assert_eq!(body["status"], json!("throttled"));
+        assert_eq!(body["retry_after_ms"], json!(250));
     }
 
     #[tokio::test]
@@ -602,7 +1152,10 @@ mod tests {
             .dispatch_frame(frame)
             .await
             .expect_err("checksum must be validated");
-        assert!(matches!(err, TransportError::Framing(_)));
+        assert!(matches!(
+            err,
+            TransportError::Adapter(StdioError::Framing(_))
+        ));
     }
 
     #[tokio::test]
@@ -622,12 +1175,12 @@ mod tests {
     #[tokio::test]
     async fn rejects_revoked_principal_on_dispatch() {
         let router = Arc::new(RecordingRouter::default());
-        let mut adapter = StdioAdapter::bind(config(), router.clone() as SharedRouter).unwrap();
+        let adapter = StdioAdapter::bind(config(), router.clone() as SharedRouter).unwrap();
         let token = adapter
             .issue_session_token("alice")
             .expect("token issuance should succeed");
 
-        adapter.config.allowed_principals.clear();
+        adapter.config.write().unwrap().allowed_principals.clear();
 
         let frame = adapter
             .codec()
@@ -649,6 +1202,7 @@ mod tests {
                 status_code: 204,
                 payload: json!({ "ok": true }),
                 diagnostics: Vec::new(),
+                page: None,
             }))
             .await;
 
@@ -725,6 +1279,100 @@ mod tests {
         assert_eq!(sequences, vec![11, 13]);
     }
 
+    #[test]
+    fn retry_buffer_reports_evicted_entries() {
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let buffer = RetryBuffer::new(2, Duration::from_secs(60)).with_on_evict({
+            let evicted = Arc::clone(&evicted);
+            move |entry, reason| {
+                evicted
+                    .lock()
+                    .unwrap()
+                    .push((entry.payload.sequence, reason));
+            }
+        });
+        let payload = |seq: u64| RetryPayload {
+            sequence: seq,
+            command: "ingest".into(),
+            payload: json!({ "id": seq }),
+            token_id: format!("tok-{seq}"),
+        };
+        buffer
+            .enqueue_at(payload(1), SystemTime::now() - Duration::from_secs(120))
+            .unwrap();
+        buffer
+            .enqueue_at(payload(2), SystemTime::now() - Duration::from_secs(90))
+            .unwrap();
+        buffer.enqueue(payload(3)).unwrap();
+        let drained = buffer.drain_ready();
+        assert_eq!(drained.len(), 1);
+        assert_eq!(
+            *evicted.lock().unwrap(),
+            vec![(1, EvictionReason::Capacity), (2, EvictionReason::Expired)]
+        );
+    }
+
+    #[test]
+    fn retry_buffer_ages_entries_on_the_injected_clock() {
+        let clock = Arc::new(MockClock::default());
+        let buffer = RetryBuffer::new(4, Duration::from_secs(60)).with_clock(clock.clone());
+        let payload = |seq: u64| RetryPayload {
+            sequence: seq,
+            command: "ingest".into(),
+            payload: json!({ "id": seq }),
+            token_id: format!("tok-{seq}"),
+        };
+        buffer.enqueue(payload(1)).unwrap();
+        clock.advance(Duration::from_secs(45));
+        buffer.enqueue(payload(2)).unwrap();
+        assert_eq!(
+            buffer.stats().ages.map(|ages| ages.oldest),
+            Some(Duration::from_secs(45))
+        );
+
+        clock.advance(Duration::from_secs(30));
+        let ready = buffer.drain_ready();
+        assert_eq!(ready.len(), 1);
+        assert_eq!(ready[0].payload.sequence, 2);
+        assert_eq!(buffer.stats().expired, 1);
+    }
+
+    #[test]
+    fn retry_buffer_stats_report_occupancy_and_counters() {
+        let buffer = RetryBuffer::new(2, Duration::from_secs(60));
+        let payload = |seq: u64| RetryPayload {
+            sequence: seq,
+            command: "ingest".into(),
+            payload: json!({ "id": seq }),
+            token_id: format!("tok-{seq}"),
+        };
+        buffer
+            .enqueue_at(payload(1), SystemTime::now() - Duration::from_secs(120))
+            .unwrap();
+        buffer
+            .enqueue_at(payload(2), SystemTime::now() - Duration::from_secs(30))
+            .unwrap();
+        let stats = buffer.stats();
+        assert_eq!(stats.len, 2);
+        assert!(stats.near_capacity(1.0));
+        assert!(stats
+            .ages
+            .is_some_and(|ages| ages.oldest >= Duration::from_secs(120)));
+
+        buffer.enqueue(payload(3)).unwrap();
+        let drained = buffer.drain_ready();
+        assert_eq!(drained.len(), 2);
+        for entry in drained {
+            buffer.requeue(entry).unwrap();
+        }
+        let stats = buffer.stats();
+        assert_eq!((stats.len, stats.capacity), (2, 2));
+        assert_eq!(stats.evicted, 1);
+        assert_eq!(stats.expired, 0);
+        assert_eq!(stats.requeued, 2);
+        assert_eq!(buffer.len(), 2);
+    }
+
     #[allow(dead_code)]
     fn issue_session_token_records_telemetry() {
         let router = Arc::new(RecordingRouter::default());
@@ -739,6 +1387,26 @@ mod tests {
             .any(|event| event.kind == "stdio.session.issued" && event.message.len() == 36));
     }
 
+    #[test]
+    fn session_tokens_expire_on_the_injected_clock() {
+        let clock = Arc::new(MockClock::default());
+        let adapter = StdioAdapter::bind(config(), Arc::new(RecordingRouter::default()) as _)
+            .unwrap()
+            .with_session_ttl(Duration::from_secs(60))
+            .with_clock(clock.clone());
+        let token = adapter.issue_session_token("alice").unwrap();
+        let frame = adapter
+            .codec()
+            .encode(&json!({ "command": "status" }), &token)
+            .unwrap();
+
+        clock.advance(Duration::from_secs(59));
+        assert!(adapter.codec().decode(&frame).is_ok());
+        clock.advance(Duration::from_secs(1));
+        let err = adapter.codec().decode(&frame).unwrap_err();
+        assert!(matches!(err, TransportError::Unauthorized(ref msg) if msg == "token expired"));
+    }
+
     #[tokio::test]
     async fn dispatch_rejects_expired_token() {
         let router = Arc::new(RecordingRouter::default());
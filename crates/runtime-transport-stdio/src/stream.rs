@@ -0,0 +1,260 @@
+//! Responses too large for one frame, sent as a stream of frames.
+//!
+//! [`FramingCodec::encode_stream`] cuts a serialized response into chunks
+//! sized so every frame stays within `max_frame_length`, and wraps each as
+//! `{ stream: { id, seq, last }, chunk }` with the chunk in base64. The
+//! frames of a stream share an id and are numbered from zero; only the
+//! terminal frame has `last` set. A [`StreamReader`] joins the chunks back
+//! into the response and passes a frame without a `stream` object through
+//! as a whole response, so a client handles both the same way.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine as _;
+use runtime_telemetry::SharedPeerTelemetry;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{framing_error, FramingCodec, SessionToken, StdioFrame, TransportError};
+
+/// Largest response a [`StreamReader`] reassembles unless told otherwise.
+pub const DEFAULT_MAX_STREAM_BYTES: usize = 64 << 20;
+
+/// Length, token length and checksum around a frame's payload.
+const FRAME_OVERHEAD: usize = 4 + 2 + 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct StreamHeader {
+    id: Uuid,
+    seq: u32,
+    last: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StreamFrame {
+    stream: StreamHeader,
+    chunk: String,
+}
+
+impl FramingCodec {
+    /// Frames carrying `json`: the single frame [`encode`](Self::encode)
+    /// makes when it fits, otherwise partial frames and a terminal frame.
+    pub fn encode_stream(
+        &self,
+        json: &Value,
+        token: &SessionToken,
+    ) -> Result<StreamWriter, TransportError> {
+        self.signer.verify(&token.token)?;
+        let bytes = serde_json::to_vec(json)
+            .map_err(|err| framing_error(format!("json serialization failed: {err}")))?;
+        let fixed = FRAME_OVERHEAD + token.token.len();
+        if fixed + bytes.len() <= self.max_frame_length {
+            return Ok(StreamWriter::new(Frames::Whole(Some(
+                self.encode(json, token)?,
+            ))));
+        }
+        let id = Uuid::new_v4();
+        // The widest envelope: the largest sequence number and `false`.
+        let envelope = serde_json::to_vec(&StreamFrame {
+            stream: StreamHeader {
+                id,
+                seq: u32::MAX,
+                last: false,
+            },
+            chunk: String::new(),
+        })
+        .map_err(|err| framing_error(format!("json serialization failed: {err}")))?;
+        // Unpadded base64 of a multiple of three bytes fills the budget exactly.
+        let chunk_len = self.max_frame_length.saturating_sub(fixed + envelope.len()) / 4 * 3;
+        if chunk_len == 0 {
+            return Err(framing_error("frame too short to stream a response".into()));
+        }
+        let frames = u32::try_from(bytes.len().div_ceil(chunk_len))
+            .map_err(|_| framing_error("response too large to stream".into()))?;
+        Ok(StreamWriter::new(Frames::Chunks {
+            codec: self.clone(),
+            token: token.clone(),
+            id,
+            bytes,
+            chunk_len,
+            frames,
+            next: 0,
+        }))
+    }
+}
+
+/// Frames of one response, each encoded when it is taken.
+#[derive(Debug)]
+pub struct StreamWriter {
+    frames: Frames,
+    /// Peer the frames are counted as sent to.
+    counted: Option<(SharedPeerTelemetry, &'static str)>,
+}
+
+#[derive(Debug)]
+enum Frames {
+    /// The response fit in one frame.
+    Whole(Option<StdioFrame>),
+    Chunks {
+        codec: FramingCodec,
+        token: SessionToken,
+        id: Uuid,
+        bytes: Vec<u8>,
+        chunk_len: usize,
+        frames: u32,
+        next: u32,
+    },
+}
+
+impl StreamWriter {
+    const fn new(frames: Frames) -> Self {
+        Self {
+            frames,
+            counted: None,
+        }
+    }
+
+    /// Count every frame taken as sent to `peer`.
+    pub(crate) fn counted(mut self, telemetry: SharedPeerTelemetry, peer: &'static str) -> Self {
+        self.counted = Some((telemetry, peer));
+        self
+    }
+
+    /// Whether the response is split over several frames.
+    #[must_use]
+    pub const fn is_streamed(&self) -> bool {
+        matches!(self.frames, Frames::Chunks { .. })
+    }
+
+    fn encode_next(&mut self) -> Option<Result<StdioFrame, TransportError>> {
+        match &mut self.frames {
+            Frames::Whole(frame) => frame.take().map(Ok),
+            Frames::Chunks {
+                codec,
+                token,
+                id,
+                bytes,
+                chunk_len,
+                frames,
+                next,
+            } => {
+                if *next == *frames {
+                    return None;
+                }
+                let start = *next as usize * *chunk_len;
+                let end = bytes.len().min(start + *chunk_len);
+                let frame = StreamFrame {
+                    stream: StreamHeader {
+                        id: *id,
+                        seq: *next,
+                        last: *next + 1 == *frames,
+                    },
+                    chunk: URL_SAFE_NO_PAD.encode(&bytes[start..end]),
+                };
+                *next += 1;
+                Some(
+                    serde_json::to_value(frame)
+                        .map_err(|err| framing_error(format!("json serialization failed: {err}")))
+                        .and_then(|json| codec.encode(&json, token)),
+                )
+            }
+        }
+    }
+}
+
+impl Iterator for StreamWriter {
+    type Item = Result<StdioFrame, TransportError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = self.encode_next()?;
+        if let (Ok(frame), Some((telemetry, peer))) = (&frame, &self.counted) {
+            telemetry.frame_out(peer, frame.payload.len());
+        }
+        Some(frame)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = match &self.frames {
+            Frames::Whole(frame) => usize::from(frame.is_some()),
+            Frames::Chunks { frames, next, .. } => (frames - next) as usize,
+        };
+        (left, Some(left))
+    }
+}
+
+impl ExactSizeIterator for StreamWriter {}
+
+/// Joins the decoded bodies of a response's frames back into the response.
+#[derive(Debug)]
+pub struct StreamReader {
+    max_bytes: usize,
+    /// Id and next sequence number of the stream being read.
+    open: Option<(Uuid, u32)>,
+    bytes: Vec<u8>,
+}
+
+impl Default for StreamReader {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_STREAM_BYTES)
+    }
+}
+
+impl StreamReader {
+    /// Reader refusing responses over `max_bytes` once serialized.
+    #[must_use]
+    pub const fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            open: None,
+            bytes: Vec::new(),
+        }
+    }
+
+    /// Take the decoded body of the next frame; returns the response once
+    /// its terminal frame arrives. Frames out of order, from another
+    /// stream or past the size limit fail the stream, and the reader
+    /// starts over with the next one.
+    pub fn push(&mut self, body: Value) -> Result<Option<Value>, TransportError> {
+        let result = self.read(body);
+        if !matches!(result, Ok(None)) {
+            self.open = None;
+            self.bytes = Vec::new();
+        }
+        result
+    }
+
+    fn read(&mut self, body: Value) -> Result<Option<Value>, TransportError> {
+        if body.get("stream").is_none() {
+            if self.open.is_some() {
+                return Err(framing_error("stream interrupted by a whole frame".into()));
+            }
+            return Ok(Some(body));
+        }
+        let frame: StreamFrame = serde_json::from_value(body)
+            .map_err(|err| framing_error(format!("invalid stream frame: {err}")))?;
+        let StreamHeader { id, seq, last } = frame.stream;
+        let expected = self.open.map_or(0, |(_, next)| next);
+        if seq != expected || self.open.is_some_and(|(open, _)| open != id) {
+            return Err(framing_error(format!(
+                "stream frame {seq} of {id} out of order"
+            )));
+        }
+        let chunk = URL_SAFE_NO_PAD
+            .decode(frame.chunk)
+            .map_err(|_| framing_error("stream chunk not base64".into()))?;
+        if self.bytes.len() + chunk.len() > self.max_bytes {
+            return Err(framing_error(format!(
+                "streamed response exceeds {} bytes",
+                self.max_bytes
+            )));
+        }
+        self.bytes.extend_from_slice(&chunk);
+        if !last {
+            self.open = Some((id, seq + 1));
+            return Ok(None);
+        }
+        serde_json::from_slice(&self.bytes)
+            .map(Some)
+            .map_err(|err| framing_error(format!("invalid json: {err}")))
+    }
+}
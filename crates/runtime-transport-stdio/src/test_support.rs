@@ -0,0 +1,169 @@
+//! Helpers for exercising the adapter's untrusted-input surfaces, frame
+//! decoding and token verification, from property tests and fuzz targets.
+//!
+//! The `arb_*` strategies lean towards inputs that get past the cheap
+//! checks: frames with consistent length prefixes and checksums around
+//! junk, and tokens whose base64 and JSON decode but whose fields do not.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine as _;
+use proptest::prelude::*;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::{
+    FramingCodec, SessionToken, SignedToken, StdioFrame, TokenEnvelope, TokenSigner, TransportError,
+};
+
+/// Secret the strategies sign valid tokens with.
+pub const TEST_SECRET: &str = "test-support-secret";
+
+/// A codec verifying tokens signed with `secret`.
+pub fn codec(max_frame_length: usize, secret: &str) -> FramingCodec {
+    FramingCodec::new(
+        max_frame_length,
+        std::sync::Arc::new(TokenSigner::new(secret.into())),
+    )
+}
+
+/// A token for `principal` signed with `secret`, with the `stdio`
+/// capability.
+pub fn issue_token(secret: &str, principal: &str, ttl: Duration) -> SessionToken {
+    let issued = TokenSigner::new(secret.into()).issue(principal, &["stdio".into()], ttl);
+    SessionToken {
+        token: issued.token,
+    }
+}
+
+/// Verify `token` the way the adapter does before dispatching a frame.
+pub fn verify_token(secret: &str, token: &str) -> Result<(), TransportError> {
+    TokenSigner::new(secret.into()).verify(token).map(|_| ())
+}
+
+/// A frame around raw `payload` and `token` bytes with correct length
+/// prefixes and checksum, so decoding reaches the JSON and token checks.
+pub fn assemble_frame(payload: &[u8], token: &[u8]) -> StdioFrame {
+    let mut buffer = Vec::with_capacity(4 + 2 + payload.len() + token.len() + 16);
+    buffer.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    buffer.extend_from_slice(&(token.len() as u16).to_be_bytes());
+    buffer.extend_from_slice(payload);
+    buffer.extend_from_slice(token);
+    let checksum = codec(usize::MAX, TEST_SECRET).checksum(&buffer);
+    buffer.extend_from_slice(&checksum);
+    StdioFrame { payload: buffer }
+}
+
+/// Arbitrary JSON values, nested a few levels deep.
+pub fn arb_json() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::from),
+        any::<i64>().prop_map(Value::from),
+        any::<f64>().prop_map(|n| json!(n)),
+        ".{0,24}".prop_map(Value::from),
+    ];
+    leaf.prop_recursive(3, 32, 6, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..6).prop_map(Value::Array),
+            prop::collection::btree_map(".{0,12}", inner, 0..6)
+                .prop_map(|map| Value::Object(map.into_iter().collect())),
+        ]
+    })
+}
+
+/// JSON shaped like a signed token, with each field either plausible or
+/// of the wrong type.
+pub fn arb_envelope() -> impl Strategy<Value = Value> {
+    let field = |plausible: BoxedStrategy<Value>| prop_oneof![3 => plausible, 1 => arb_json()];
+    (
+        field(Just(json!(Uuid::nil())).boxed()),
+        field(".{0,16}".prop_map(Value::from).boxed()),
+        field(
+            prop::collection::vec("[a-z.]{0,8}", 0..4)
+                .prop_map(Value::from)
+                .boxed(),
+        ),
+        field(any::<u64>().prop_map(Value::from).boxed()),
+        field("[A-Za-z0-9_-]{0,43}".prop_map(Value::from).boxed()),
+    )
+        .prop_map(
+            |(token_id, principal, capabilities, expires_at, signature)| {
+                json!({
+                    "envelope": {
+                        "raw_token": "",
+                        "token_id": token_id,
+                        "principal": principal,
+                        "capabilities": capabilities,
+                        "expires_at": expires_at,
+                    },
+                    "signature": signature,
+                })
+            },
+        )
+}
+
+/// Token strings that should all be rejected: arbitrary text, base64 of
+/// arbitrary bytes, and base64 of unsigned or mis-signed envelopes.
+pub fn arb_token() -> impl Strategy<Value = String> {
+    prop_oneof![
+        ".{0,64}",
+        prop::collection::vec(any::<u8>(), 0..96).prop_map(|bytes| URL_SAFE_NO_PAD.encode(bytes)),
+        arb_envelope().prop_map(|envelope| URL_SAFE_NO_PAD.encode(envelope.to_string())),
+    ]
+}
+
+/// Tokens correctly signed with `secret` over arbitrary principals and
+/// capabilities; `live` picks an expiry a minute or more in the future
+/// rather than in the past.
+pub fn arb_signed_token(secret: &str, live: bool) -> impl Strategy<Value = String> {
+    let signer = TokenSigner::new(secret.into());
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let expires_at = if live {
+        (now + 60..u64::MAX).boxed()
+    } else {
+        (0..now.saturating_sub(60)).boxed()
+    };
+    (
+        ".{0,32}",
+        prop::collection::vec(".{0,12}", 0..4),
+        expires_at,
+    )
+        .prop_map(move |(principal, capabilities, expires_at)| {
+            let envelope = TokenEnvelope {
+                raw_token: String::new(),
+                token_id: Uuid::nil(),
+                principal,
+                capabilities,
+                expires_at,
+            };
+            let signature = signer.sign(&envelope.canonical());
+            let signed = SignedToken {
+                envelope,
+                signature,
+            };
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(&signed).expect("envelope serializes"))
+        })
+}
+
+/// Frames the codec should reject or decode cleanly, never panic on: raw
+/// bytes, well-formed frames around arbitrary bytes, and well-formed frames
+/// around arbitrary JSON and rejected tokens.
+pub fn arb_frame() -> impl Strategy<Value = StdioFrame> {
+    prop_oneof![
+        prop::collection::vec(any::<u8>(), 0..128).prop_map(|payload| StdioFrame { payload }),
+        (
+            prop::collection::vec(any::<u8>(), 0..96),
+            prop::collection::vec(any::<u8>(), 0..96),
+        )
+            .prop_map(|(payload, token)| assemble_frame(&payload, &token)),
+        (arb_json(), arb_token()).prop_map(|(payload, token)| assemble_frame(
+            payload.to_string().as_bytes(),
+            token.as_bytes()
+        )),
+    ]
+}
@@ -1,16 +1,22 @@
 //! Unix domain socket transport adapter implementation.
 
 use std::collections::HashSet;
-use std::sync::{Arc, Mutex};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::sync::{Arc, Mutex, PoisonError, RwLock, RwLockReadGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use base64::Engine as _;
 use blake3::Hasher;
-use runtime_router::{RouterCommand, RouterError, SessionContext, SharedRouter};
+use runtime_clock::{SharedClock, SystemClock};
+use runtime_principals::SharedPrincipalStore;
+use runtime_router::{
+    Reloadable, RouterCommand, RouterError, SessionContext, SharedRouter, StatusProvider,
+};
+use runtime_telemetry::{
+    ExportRecord, PeerTelemetry, SharedExportPipeline, SharedPeerTelemetry, SharedRedactor,
+};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use thiserror::Error;
+use serde_json::{json, Value};
 use uuid::Uuid;
 
 /// UDS adapter configuration.
@@ -51,6 +57,14 @@ pub struct PeerCredentials {
     pub process_name: String,
 }
 
+impl PeerCredentials {
+    /// Name the peer is counted under in [`PeerTelemetry`].
+    #[must_use]
+    pub fn peer(&self) -> String {
+        format!("uds://{}/{}", self.process_name, self.pid)
+    }
+}
+
 /// Issued session token.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SessionToken {
@@ -89,10 +103,53 @@ pub struct TelemetryEvent {
 #[derive(Debug, Default)]
 pub struct TelemetrySink {
     events: Mutex<Vec<TelemetryEvent>>,
+    export: Option<SharedExportPipeline>,
+    redactor: Option<SharedRedactor>,
 }
 
 impl TelemetrySink {
-    pub fn record(&self, event: TelemetryEvent) {
+    /// Also hand every event to `pipeline`.
+    #[must_use]
+    pub fn with_exporter(mut self, pipeline: SharedExportPipeline) -> Self {
+        self.export = Some(pipeline);
+        self
+    }
+
+    /// Pass every event message through `redactor` before recording it.
+    #[must_use]
+    pub fn with_redactor(mut self, redactor: SharedRedactor) -> Self {
+        self.redactor = Some(redactor);
+        self
+    }
+
+    /// `text` as the sink would record it.
+    #[must_use]
+    pub fn redacted(&self, text: &str) -> String {
+        self.redactor
+            .as_ref()
+            .map_or_else(|| text.to_string(), |redactor| redactor.redact(text))
+    }
+
+    /// Empty sink with the same exporter and redactor.
+    fn settings(&self) -> Self {
+        Self {
+            events: Mutex::default(),
+            export: self.export.clone(),
+            redactor: self.redactor.clone(),
+        }
+    }
+
+    pub fn record(&self, mut event: TelemetryEvent) {
+        if self.redactor.is_some() {
+            event.message = self.redacted(&event.message);
+        }
+        if let Some(export) = &self.export {
+            export.record(ExportRecord::now(
+                &event.kind,
+                event.principal.as_deref(),
+                &event.message,
+            ));
+        }
         self.events.lock().unwrap().push(event);
     }
 
@@ -101,34 +158,24 @@ impl TelemetrySink {
     }
 }
 
-/// Errors produced by the adapter.
-#[derive(Debug, Error, PartialEq, Eq)]
-pub enum TransportError {
-    #[error("configuration error: {0}")]
-    Configuration(String),
-    #[error("unauthorized peer: {0}")]
-    Unauthorized(String),
-    #[error("router error: {0}")]
-    Router(RouterError),
-}
+/// Errors produced by the adapter, which has no failures of its own.
+pub type TransportError = runtime_transport_error::TransportError;
 
-impl TransportError {
-    #[must_use]
-    pub const fn router_status_code(&self) -> Option<u16> {
-        match self {
-            Self::Router(err) => Some(err.status_code()),
-            _ => None,
-        }
-    }
-}
+/// Session token lifetime used unless [`UdsAdapter::with_session_ttl`]
+/// overrides it.
+pub const DEFAULT_SESSION_TTL: Duration = Duration::from_secs(3600);
 
 /// UDS adapter bridging IPC requests into the router.
 pub struct UdsAdapter {
-    config: UdsConfig,
+    config: RwLock<UdsConfig>,
     router: SharedRouter,
     telemetry: Arc<TelemetrySink>,
     signer: Arc<TokenSigner>,
     negotiated_uids: Mutex<HashSet<u32>>,
+    session_ttl: Duration,
+    started: Instant,
+    principals: Option<SharedPrincipalStore>,
+    peers: SharedPeerTelemetry,
 }
 
 impl UdsAdapter {
@@ -136,19 +183,132 @@ impl UdsAdapter {
         config.validate()?;
         Ok(Self {
             signer: Arc::new(TokenSigner::new(config.token_secret.clone())),
-            config,
+            config: RwLock::new(config),
             router,
             telemetry: Arc::new(TelemetrySink::default()),
             negotiated_uids: Mutex::new(HashSet::new()),
+            session_ttl: DEFAULT_SESSION_TTL,
+            started: Instant::now(),
+            principals: None,
+            peers: Arc::new(PeerTelemetry::default()),
         })
     }
 
+    /// Lifetime of the session tokens issued from now on; an hour unless
+    /// overridden. A zero TTL issues tokens that are already expired.
+    #[must_use]
+    pub fn with_session_ttl(mut self, ttl: Duration) -> Self {
+        self.session_ttl = ttl;
+        self
+    }
+
+    /// Issue and check session tokens against `clock` instead of the system
+    /// clock.
+    #[must_use]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        let secret = self.config().token_secret.clone();
+        self.signer = Arc::new(TokenSigner::with_clock(secret, clock));
+        self
+    }
+
+    /// Check principals against `store` instead of the config's
+    /// `allowed_principals`, when issuing tokens and on every request, so
+    /// changes made through the store apply to sessions already issued.
+    #[must_use]
+    pub fn with_principals(mut self, store: SharedPrincipalStore) -> Self {
+        self.principals = Some(store);
+        self
+    }
+
+    /// Also export every telemetry event through `pipeline`, which other
+    /// adapters may share.
+    #[must_use]
+    pub fn with_exporter(mut self, pipeline: SharedExportPipeline) -> Self {
+        self.telemetry = Arc::new(self.telemetry.settings().with_exporter(pipeline));
+        self
+    }
+
+    /// Scrub telemetry event messages and peer failure details with
+    /// `redactor`, such as the sanitizer's redaction patterns, before they
+    /// are recorded or exported.
+    #[must_use]
+    pub fn with_redactor(mut self, redactor: SharedRedactor) -> Self {
+        self.telemetry = Arc::new(self.telemetry.settings().with_redactor(redactor));
+        self
+    }
+
+    /// Count requests, bytes and failures in `telemetry`, which other
+    /// adapters may share, instead of in counters of the adapter's own.
+    #[must_use]
+    pub fn with_peer_telemetry(mut self, telemetry: SharedPeerTelemetry) -> Self {
+        self.peers = telemetry;
+        self
+    }
+
+    fn config(&self) -> RwLockReadGuard<'_, UdsConfig> {
+        self.config.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn permits(&self, principal: &str) -> bool {
+        match &self.principals {
+            Some(store) => store.is_active(principal),
+            None => self
+                .config()
+                .allowed_principals
+                .iter()
+                .any(|p| p == principal),
+        }
+    }
+
+    /// Tenant `principal`'s sessions are confined to, per the principal
+    /// store. Without a store every session is unconfined.
+    fn tenant_of(&self, principal: &str) -> Option<String> {
+        self.principals
+            .as_ref()
+            .and_then(|store| store.tenant_of(principal))
+    }
+
+    /// Principals [`Self::permits`] accepts; a store that cannot list its
+    /// principals counts as none.
+    fn allowed_principal_count(&self) -> usize {
+        match &self.principals {
+            Some(store) => store.list().map_or(0, |records| {
+                records.iter().filter(|record| !record.disabled).count()
+            }),
+            None => self.config().allowed_principals.len(),
+        }
+    }
+
+    /// Apply `config` to the running adapter in one step. The allowed
+    /// principals and UIDs take effect from the next request: negotiated
+    /// peers whose UID is still allowed keep their connection, the others
+    /// are forgotten and refused until they negotiate again, and sessions of
+    /// principals no longer allowed are refused. `socket_path` and
+    /// `token_secret` only change with a restart, so a `config` changing
+    /// them is rejected.
+    pub fn reload(&self, config: UdsConfig) -> Result<(), TransportError> {
+        config.validate()?;
+        let mut current = self.config.write().unwrap_or_else(PoisonError::into_inner);
+        restart_only_changes(&current, &config)?;
+        self.negotiated_uids
+            .lock()
+            .unwrap()
+            .retain(|uid| config.allowed_uids.contains(uid));
+        *current = config;
+        drop(current);
+        self.telemetry.record(TelemetryEvent {
+            kind: "uds.config.reloaded".into(),
+            message: String::new(),
+            principal: None,
+        });
+        Ok(())
+    }
+
     pub fn negotiate_peer(&self, peer: &PeerCredentials) -> Result<(), TransportError> {
-        if !self.config.allowed_uids.contains(&peer.uid) {
-            return Err(TransportError::Unauthorized(format!(
-                "uid {} not permitted",
-                peer.uid
-            )));
+        if !self.config().allowed_uids.contains(&peer.uid) {
+            let detail = format!("uid {} not permitted", peer.uid);
+            self.auth_failure(&peer.peer(), &detail);
+            return Err(TransportError::Unauthorized(detail));
         }
         self.telemetry.record(TelemetryEvent {
             kind: "uds.peer.accepted".into(),
@@ -164,19 +324,12 @@ impl UdsAdapter {
         principal: &str,
         capabilities: &[String],
     ) -> Result<SessionToken, TransportError> {
-        if !self
-            .config
-            .allowed_principals
-            .iter()
-            .any(|p| p == principal)
-        {
+        if !self.permits(principal) {
             return Err(TransportError::Unauthorized(format!(
                 "principal {principal} is not permitted",
             )));
         }
-        let issued = self
-            .signer
-            .issue(principal, capabilities, Duration::from_secs(3600));
+        let issued = self.signer.issue(principal, capabilities, self.session_ttl);
         self.telemetry.record(TelemetryEvent {
             kind: "uds.session.issued".into(),
             message: issued.token_id.to_string(),
@@ -190,6 +343,21 @@ impl UdsAdapter {
     }
 
     pub async fn dispatch(&self, request: UdsRequest) -> Result<Value, TransportError> {
+        let peer = request.peer.peer();
+        self.peers.frame_in(&peer, payload_len(&request.payload));
+        let result = self.route(request).await;
+        match &result {
+            Ok(response) => self.peers.frame_out(&peer, payload_len(response)),
+            Err(TransportError::Unauthorized(detail)) => self.auth_failure(&peer, detail),
+            Err(TransportError::InvalidRequest(detail)) => self
+                .peers
+                .decode_error(&peer, &self.telemetry.redacted(detail)),
+            Err(_) => {}
+        }
+        result
+    }
+
+    async fn route(&self, request: UdsRequest) -> Result<Value, TransportError> {
         if !self
             .negotiated_uids
             .lock()
@@ -202,12 +370,7 @@ impl UdsAdapter {
             )));
         }
         let envelope = self.signer.verify(&request.token)?;
-        if !self
-            .config
-            .allowed_principals
-            .iter()
-            .any(|p| p == &envelope.principal)
-        {
+        if !self.permits(&envelope.principal) {
             return Err(TransportError::Unauthorized(format!(
                 "principal {} is not permitted",
                 envelope.principal
@@ -218,7 +381,7 @@ impl UdsAdapter {
             .payload
             .get("command")
             .and_then(|v| v.as_str())
-            .ok_or_else(|| TransportError::Unauthorized("command missing".into()))?;
+            .ok_or_else(|| TransportError::InvalidRequest("command missing".into()))?;
         let body = request
             .payload
             .get("payload")
@@ -231,6 +394,8 @@ impl UdsAdapter {
             trace_id: Uuid::new_v4(),
             token_id: Some(envelope.token_id),
             peer: Some(format!("uds://{}", request.peer.process_name)),
+            tenant_id: self.tenant_of(&envelope.principal),
+            expires_at: Some(envelope.expires_at),
         };
 
         self.telemetry.record(TelemetryEvent {
@@ -258,12 +423,97 @@ impl UdsAdapter {
             principal: Some(envelope.principal.clone()),
         });
 
-        Ok(response.payload)
+        Ok(response.into_payload_with_page())
     }
 
     pub fn telemetry(&self) -> Arc<TelemetrySink> {
         Arc::clone(&self.telemetry)
     }
+
+    pub fn peer_telemetry(&self) -> SharedPeerTelemetry {
+        Arc::clone(&self.peers)
+    }
+
+    fn auth_failure(&self, peer: &str, detail: &str) {
+        self.peers
+            .auth_failure(peer, &self.telemetry.redacted(detail));
+        self.telemetry.record(TelemetryEvent {
+            kind: "uds.auth.failure".into(),
+            message: format!("{peer}: {detail}"),
+            principal: None,
+        });
+    }
+}
+
+/// Bytes of `payload` once serialized, as sent on the wire.
+fn payload_len(payload: &Value) -> usize {
+    serde_json::to_vec(payload).map_or(0, |bytes| bytes.len())
+}
+
+/// Fail when `next` changes settings that need a restart.
+fn restart_only_changes(current: &UdsConfig, next: &UdsConfig) -> Result<(), TransportError> {
+    let changed: Vec<&str> = [
+        ("socket_path", current.socket_path != next.socket_path),
+        ("token_secret", current.token_secret != next.token_secret),
+    ]
+    .into_iter()
+    .filter_map(|(field, changed)| changed.then_some(field))
+    .collect();
+    if changed.is_empty() {
+        return Ok(());
+    }
+    Err(TransportError::Configuration(format!(
+        "{} cannot change without a restart",
+        changed.join(", ")
+    )))
+}
+
+/// Reloads a [`UdsConfig`] through [`UdsAdapter::reload`] for the
+/// `config.reload` command.
+impl Reloadable for UdsAdapter {
+    fn check(&self, config: &Value) -> Result<(), RouterError> {
+        let config: UdsConfig = parse_reload(config.clone())?;
+        config.validate().map_err(reload_error)?;
+        restart_only_changes(&self.config(), &config).map_err(reload_error)
+    }
+
+    fn reload(&self, config: Value) -> Result<(), RouterError> {
+        Self::reload(self, parse_reload(config)?).map_err(reload_error)
+    }
+}
+
+fn parse_reload(config: Value) -> Result<UdsConfig, RouterError> {
+    serde_json::from_value(config).map_err(|err| RouterError::InvalidRequest {
+        detail: err.to_string(),
+    })
+}
+
+fn reload_error(err: TransportError) -> RouterError {
+    RouterError::InvalidRequest {
+        detail: err.to_string(),
+    }
+}
+
+/// Reports `{ uptime_ms, session_ttl_secs, allowed_principals,
+/// negotiated_peers, telemetry_events }` for the `status` command.
+#[async_trait::async_trait]
+impl StatusProvider for UdsAdapter {
+    async fn report(&self) -> Result<Value, RouterError> {
+        let negotiated_peers = self
+            .negotiated_uids
+            .lock()
+            .map_err(|err| RouterError::Internal {
+                detail: err.to_string(),
+            })?
+            .len();
+        Ok(json!({
+            "uptime_ms": u64::try_from(self.started.elapsed().as_millis()).unwrap_or(u64::MAX),
+            "session_ttl_secs": self.session_ttl.as_secs(),
+            "allowed_principals": self.allowed_principal_count(),
+            "negotiated_peers": negotiated_peers,
+            "telemetry_events": self.telemetry.events().len(),
+        }))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -290,15 +540,20 @@ impl TokenEnvelope {
 #[derive(Debug)]
 struct TokenSigner {
     secret: String,
+    clock: SharedClock,
 }
 
 impl TokenSigner {
-    const fn new(secret: String) -> Self {
-        Self { secret }
+    fn new(secret: String) -> Self {
+        Self::with_clock(secret, SystemClock::shared())
+    }
+
+    const fn with_clock(secret: String, clock: SharedClock) -> Self {
+        Self { secret, clock }
     }
 
     fn issue(&self, principal: &str, capabilities: &[String], ttl: Duration) -> IssuedToken {
-        let expires_at = SystemTime::now() + ttl;
+        let expires_at = self.clock.now() + ttl;
         let expires_unix = expires_at
             .duration_since(UNIX_EPOCH)
             .unwrap_or_default()
@@ -331,11 +586,7 @@ impl TokenSigner {
                 "token signature mismatch".into(),
             ));
         }
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        if envelope.expires_at <= now {
+        if envelope.expires_at <= self.clock.unix_secs() {
             return Err(TransportError::Unauthorized("token expired".into()));
         }
         Ok(envelope)
@@ -361,10 +612,16 @@ struct IssuedToken {
     expires_at: SystemTime,
 }
 
+#[cfg(feature = "test-support")]
+pub mod test_support;
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use runtime_router::{RecordingRouter, RouterError, RouterResponse};
+    use runtime_clock::MockClock;
+    use runtime_router::{
+        CommandHandler, HandlerRouter, RecordingRouter, RouterError, RouterResponse, BATCH_COMMAND,
+    };
     use serde_json::json;
     use std::sync::Arc;
 
@@ -414,6 +671,86 @@ mod tests {
         assert_eq!(calls[0].context.principal, "alice");
     }
 
+    struct EchoHandler;
+
+    #[async_trait::async_trait]
+    impl CommandHandler for EchoHandler {
+        async fn handle(
+            &self,
+            _ctx: &SessionContext,
+            payload: Value,
+        ) -> Result<RouterResponse, RouterError> {
+            Ok(RouterResponse::ok(payload))
+        }
+    }
+
+    fn echo_router() -> SharedRouter {
+        let mut router = HandlerRouter::new();
+        router.register("echo", Arc::new(EchoHandler));
+        Arc::new(router)
+    }
+
+    fn batch_body() -> Value {
+        json!({
+            "command": BATCH_COMMAND,
+            "payload": { "commands": [
+                { "command": "echo", "payload": { "n": 1 } },
+                { "command": "missing" },
+                { "command": "echo", "payload": { "n": 2 } },
+            ] },
+        })
+    }
+
+    fn assert_batch_results(payload: &Value) {
+        let results = payload["results"].as_array().expect("results");
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0]["payload"], json!({ "n": 1 }));
+        assert_eq!(results[1]["status_code"], json!(404));
+        assert_eq!(results[2]["payload"], json!({ "n": 2 }));
+    }
+
+    #[tokio::test]
+    async fn dispatches_command_batches() {
+        let adapter = UdsAdapter::bind(config(), echo_router()).unwrap();
+        adapter
+            .negotiate_peer(&peer())
+            .expect("peer negotiation succeeds");
+        let token = adapter
+            .issue_session_token("alice", &[])
+            .expect("token issuance works");
+        let request = UdsRequest::new(peer(), token.token.clone(), batch_body());
+        let response = adapter.dispatch(request).await.expect("dispatch succeeds");
+        assert_batch_results(&response);
+    }
+
+    #[tokio::test]
+    async fn session_tokens_expire_on_the_injected_clock() {
+        let clock = Arc::new(MockClock::default());
+        let router = Arc::new(RecordingRouter::default());
+        router
+            .script_response(Ok(RouterResponse::ok(json!({ "status": "ok" }))))
+            .await;
+        let adapter = UdsAdapter::bind(config(), router as SharedRouter)
+            .unwrap()
+            .with_session_ttl(Duration::from_secs(60))
+            .with_clock(clock.clone());
+        adapter.negotiate_peer(&peer()).unwrap();
+        let token = adapter
+            .issue_session_token("alice", &["search".into()])
+            .unwrap();
+        let request =
+            || UdsRequest::new(peer(), token.token.clone(), json!({ "command": "search" }));
+
+        clock.advance(Duration::from_secs(59));
+        adapter
+            .dispatch(request())
+            .await
+            .expect("token still valid");
+        clock.advance(Duration::from_secs(1));
+        let err = adapter.dispatch(request()).await.unwrap_err();
+        assert!(matches!(err, TransportError::Unauthorized(ref msg) if msg == "token expired"));
+    }
+
     #[tokio::test]
     async fn rejects_unapproved_uid() {
         let router = Arc::new(RecordingRouter::default());
@@ -455,6 +792,37 @@ mod tests {
             .await
             .expect_err("router error surfaces");
         assert!(matches!(err, TransportError::Router(_)));
+        assert_eq!(err.retry_after(), None);
+    }
+
+    #[tokio::test]
+    async fn throttled_errors_carry_retry_hint() {
+        let router = Arc::new(RecordingRouter::default());
+        router
+            .script_response(Err(RouterError::Throttled {
+                detail: "slow down".into(),
+                retry_after_ms: 250,
+            }))
+            .await;
+
+        let adapter = UdsAdapter::bind(config(), router as SharedRouter).unwrap();
+        adapter
+            .negotiate_peer(&peer())
+            .expect("peer negotiation succeeds");
+        let token = adapter
+            .issue_session_token("alice", &["search".into()])
+            .expect("token issuance works");
+        let request = UdsRequest::new(
+            peer(),
+            token.token.clone(),
+            json!({ "command": "search", "payload": {} }),
+        );
+        let err = adapter
+            .dispatch(request)
+            .await
+            .expect_err("throttling surfaces");
+        assert_eq!(err.router_status_code(), Some(429));
+        assert_eq!(err.retry_after(), Some(Duration::from_millis(250)));
     }
 
     #[tokio::test]
@@ -480,6 +848,19 @@ mod tests {
         assert!(negotiated.contains(&1000), "uid 1000 should be tracked");
     }
 
+    #[tokio::test]
+    async fn status_reports_negotiated_peers() {
+        let adapter = UdsAdapter::bind(config(), echo_router()).unwrap();
+        let report = adapter.report().await.expect("status report");
+        assert_eq!(report["negotiated_peers"], json!(0));
+        assert_eq!(report["session_ttl_secs"], json!(3600));
+
+        adapter.negotiate_peer(&peer()).expect("peer accepted");
+        let report = adapter.report().await.expect("status report");
+        assert_eq!(report["negotiated_peers"], json!(1));
+        assert!(report["uptime_ms"].is_u64());
+    }
+
     #[tokio::test]
     async fn dispatch_rejects_unnegotiated_peer() {
         let router = Arc::new(RecordingRouter::default());
@@ -0,0 +1,96 @@
+//! Crash-safe file replacement shared by the components that persist state.
+//!
+//! [`write`] stages the new contents in a temporary sibling, syncs it,
+//! renames it over the target and then syncs the parent directory, so after
+//! a crash the target holds either the old or the new bytes and a completed
+//! write survives power loss. [`stage`] and [`sync_dir`] are the pieces for
+//! callers that rename several files before one directory sync.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Prefix of the temporary files [`stage`] creates next to their target.
+pub const TMP_PREFIX: &str = "%tmp-";
+
+/// Atomically replace the contents of `path` with `bytes`, creating its
+/// parent directories as needed.
+pub fn write(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let parent = parent_dir(path);
+    fs::create_dir_all(parent)?;
+    let tmp = tmp_path(path);
+    let replaced = write_synced(&tmp, bytes).and_then(|()| fs::rename(&tmp, path));
+    if let Err(err) = replaced {
+        let _ = fs::remove_file(&tmp);
+        return Err(err);
+    }
+    sync_dir(parent)
+}
+
+/// Write `bytes` to a temporary sibling of `path` (whose parent must exist)
+/// without syncing it, and return the sibling, ready to be renamed into
+/// place.
+pub fn stage(path: &Path, bytes: &[u8]) -> io::Result<PathBuf> {
+    let tmp = tmp_path(path);
+    File::create(&tmp)?.write_all(bytes)?;
+    Ok(tmp)
+}
+
+/// Persist renames within `dir`. Directories cannot be synced on Windows.
+pub fn sync_dir(dir: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+fn write_synced(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(bytes)?;
+    file.sync_all()
+}
+
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = std::ffi::OsString::from(TMP_PREFIX);
+    name.push(path.file_name().unwrap_or_default());
+    path.with_file_name(name)
+}
+
+/// Directory holding `path`; a bare file name lives in the working
+/// directory.
+fn parent_dir(path: &Path) -> &Path {
+    path.parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_replaces_contents_and_leaves_no_staging_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested/state.json");
+        write(&path, b"first").unwrap();
+        write(&path, b"second").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+        let names: Vec<_> = fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(names, ["state.json"]);
+    }
+
+    #[test]
+    fn failed_write_keeps_the_previous_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        write(&path, b"kept").unwrap();
+        // A directory in the staging slot makes the temporary file uncreatable.
+        fs::create_dir(tmp_path(&path)).unwrap();
+        assert!(write(&path, b"lost").is_err());
+        assert_eq!(fs::read(&path).unwrap(), b"kept");
+    }
+}
@@ -1,20 +1,49 @@
 //! Ledger persistence and offline replay buffer utilities.
 
 use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use runtime_clock::{SharedClock, SystemClock};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::cipher::{Cipher, JOURNAL_CONTEXT};
+
+pub mod cipher;
+pub mod sequence;
+pub mod stats;
+pub mod wal;
+
+pub use cipher::RecordCipher;
+pub use sequence::{find_gaps, ReplayMode, RepoSequences, SequenceGap};
+pub use stats::{AgeDistribution, BufferStats, EvictionReason};
+pub use wal::{FsyncPolicy, Ledger, LedgerConfig, LedgerError, LedgerIter, LedgerSubscription};
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ReplayEntry {
     pub sequence: u64,
     pub repo_id: String,
+    /// Tenant owning `repo_id`; absent for tenant-less repositories.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
     pub delayed_ms: u64,
     pub payload_checksum_before: String,
     pub payload_checksum_after: String,
     pub status: String,
+    /// Base64 envelope of the encrypted manifest payload, when sealed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sealed_payload: Option<String>,
+    /// Origin signature over the entry; `status` is excluded because it
+    /// changes in transit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -23,18 +52,211 @@ struct ReplayEnvelope {
     inserted_at: SystemTime,
 }
 
+impl ReplayEnvelope {
+    fn evicted(self, reason: EvictionReason) -> EvictedReplayEntry {
+        EvictedReplayEntry {
+            entry: self.entry,
+            inserted_at: self.inserted_at,
+            reason,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ReadyReplayEntry {
     pub entry: ReplayEntry,
     pub inserted_at: SystemTime,
 }
 
+/// Entry an [`OfflineReplayBuffer`] dropped before it was drained.
+#[derive(Debug, Clone)]
+pub struct EvictedReplayEntry {
+    pub entry: ReplayEntry,
+    pub inserted_at: SystemTime,
+    pub reason: EvictionReason,
+}
+
+/// Callback registered with [`OfflineReplayBuffer::with_on_evict`].
+#[derive(Clone)]
+struct EvictionHook(Arc<dyn Fn(EvictedReplayEntry) + Send + Sync>);
+
+impl std::fmt::Debug for EvictionHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EvictionHook")
+    }
+}
+
+/// Line of the append-only journal behind a durable buffer.
+#[derive(Debug, Serialize, Deserialize)]
+struct JournalRecord {
+    entry: ReplayEntry,
+    inserted_at_ms: u64,
+}
+
+impl JournalRecord {
+    fn from_envelope(envelope: &ReplayEnvelope) -> Self {
+        Self {
+            entry: envelope.entry.clone(),
+            inserted_at_ms: envelope
+                .inserted_at
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_millis() as u64),
+        }
+    }
+
+    fn into_envelope(self) -> ReplayEnvelope {
+        ReplayEnvelope {
+            entry: self.entry,
+            inserted_at: UNIX_EPOCH + Duration::from_millis(self.inserted_at_ms),
+        }
+    }
+}
+
+/// Journal line of a buffer opened with a [`RecordCipher`]: the base64 of
+/// the sealed JSON [`JournalRecord`].
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SealedJournalRecord {
+    sealed: String,
+}
+
+#[derive(Debug)]
+struct Journal {
+    path: PathBuf,
+    file: File,
+    /// Lines currently in the file, including evicted entries.
+    records: usize,
+    cipher: Option<Cipher>,
+}
+
+impl Journal {
+    /// One journal line, sealed when the journal has a cipher.
+    fn encode(&self, envelope: &ReplayEnvelope) -> Result<Vec<u8>, ReplayError> {
+        let mut line = serde_json::to_vec(&JournalRecord::from_envelope(envelope))
+            .map_err(|err| ReplayError::Journal(err.to_string()))?;
+        if let Some(Cipher(cipher)) = &self.cipher {
+            let sealed = cipher
+                .seal(&line, JOURNAL_CONTEXT)
+                .map_err(|err| ReplayError::Journal(format!("sealing record: {err}")))?;
+            line = serde_json::to_vec(&SealedJournalRecord {
+                sealed: BASE64.encode(sealed),
+            })
+            .map_err(|err| ReplayError::Journal(err.to_string()))?;
+        }
+        line.push(b'\n');
+        Ok(line)
+    }
+
+    fn append(&mut self, envelope: &ReplayEnvelope) -> Result<(), ReplayError> {
+        let line = self.encode(envelope)?;
+        self.file
+            .write_all(&line)
+            .and_then(|()| self.file.sync_data())
+            .map_err(|err| self.io_error(&err))?;
+        self.records += 1;
+        Ok(())
+    }
+
+    /// Replace the journal with exactly `envelopes`.
+    fn rewrite<'a>(
+        &mut self,
+        envelopes: impl IntoIterator<Item = &'a ReplayEnvelope>,
+    ) -> Result<(), ReplayError> {
+        let mut bytes = Vec::new();
+        let mut records = 0;
+        for envelope in envelopes {
+            bytes.extend(self.encode(envelope)?);
+            records += 1;
+        }
+        storage_atomic::write(&self.path, &bytes).map_err(|err| self.io_error(&err))?;
+        self.file = open_append(&self.path).map_err(|err| self.io_error(&err))?;
+        self.records = records;
+        Ok(())
+    }
+
+    fn io_error(&self, err: &std::io::Error) -> ReplayError {
+        ReplayError::Journal(format!("{}: {err}", self.path.display()))
+    }
+}
+
+/// Why a journal line could not be read back.
+enum LineError {
+    /// Not a record; a crash may have cut the final line short.
+    Malformed(serde_json::Error),
+    /// A sealed record that could not be opened.
+    Cipher(String),
+}
+
+impl std::fmt::Display for LineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Malformed(err) => err.fmt(f),
+            Self::Cipher(detail) => f.write_str(detail),
+        }
+    }
+}
+
+/// Decode one journal line. Plaintext records are accepted with a cipher
+/// too, so encryption can be enabled on an existing journal.
+fn decode_line(line: &str, cipher: Option<&Cipher>) -> Result<JournalRecord, LineError> {
+    let Ok(SealedJournalRecord { sealed }) = serde_json::from_str(line) else {
+        return serde_json::from_str(line).map_err(LineError::Malformed);
+    };
+    let Some(Cipher(cipher)) = cipher else {
+        return Err(LineError::Cipher("sealed record but no cipher".into()));
+    };
+    let sealed = BASE64
+        .decode(sealed)
+        .map_err(|err| LineError::Cipher(format!("sealed record is not base64: {err}")))?;
+    let plain = cipher
+        .open(&sealed, JOURNAL_CONTEXT)
+        .map_err(LineError::Cipher)?;
+    serde_json::from_slice(&plain).map_err(LineError::Malformed)
+}
+
+/// `command` of every record in an exported snapshot.
+pub const SNAPSHOT_COMMAND: &str = "manifest.replay";
+
+/// Line of a snapshot file, in the offline transport queue snapshot format.
+#[derive(Debug, Serialize, Deserialize)]
+struct SnapshotRecord {
+    sequence: u64,
+    command: String,
+    payload: ReplayEntry,
+    token_id: String,
+    /// Unix seconds.
+    enqueued_at: i64,
+}
+
+fn open_append(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Lifetime counters reported through [`BufferStats`].
+#[derive(Debug, Default)]
+struct BufferCounters {
+    evicted: AtomicU64,
+    expired: AtomicU64,
+    requeued: AtomicU64,
+}
+
+/// Bounded FIFO of replay entries held while the downstream queue is offline.
+///
+/// Buffers created with [`OfflineReplayBuffer::open`] also append every
+/// entry to a JSONL journal and rebuild from it on startup, so buffered
+/// entries survive a crash. Draining truncates the journal; entries drained
+/// but not yet delivered are only preserved if the caller requeues them.
 #[derive(Debug, Clone)]
 pub struct OfflineReplayBuffer {
     max_entries: usize,
     max_age: Duration,
     inner: Arc<Mutex<VecDeque<ReplayEnvelope>>>,
     max_sequence_seen: Arc<Mutex<Option<u64>>>,
+    journal: Option<Arc<Mutex<Journal>>>,
+    mode: ReplayMode,
+    counters: Arc<BufferCounters>,
+    on_evict: Option<EvictionHook>,
+    clock: SharedClock,
 }
 
 impl OfflineReplayBuffer {
@@ -45,30 +267,267 @@ impl OfflineReplayBuffer {
             max_age,
             inner: Arc::new(Mutex::new(VecDeque::new())),
             max_sequence_seen: Arc::new(Mutex::new(None)),
+            journal: None,
+            mode: ReplayMode::Lenient,
+            counters: Arc::default(),
+            on_evict: None,
+            clock: SystemClock::shared(),
+        }
+    }
+
+    /// Open a durable buffer journaled at `path`, recovering any entries a
+    /// previous process left behind.
+    ///
+    /// A truncated final line, as left by a crash mid-append, is discarded.
+    pub fn open(
+        path: impl Into<PathBuf>,
+        max_entries: usize,
+        max_age: Duration,
+    ) -> Result<Self, ReplayError> {
+        Self::open_inner(path.into(), max_entries, max_age, None)
+    }
+
+    /// Like [`OfflineReplayBuffer::open`], but seal every journal record
+    /// with `cipher`. A plaintext journal is resealed on open; a sealed one
+    /// fails to open without the cipher that wrote it.
+    pub fn open_with_cipher(
+        path: impl Into<PathBuf>,
+        max_entries: usize,
+        max_age: Duration,
+        cipher: Arc<dyn RecordCipher>,
+    ) -> Result<Self, ReplayError> {
+        Self::open_inner(path.into(), max_entries, max_age, Some(Cipher(cipher)))
+    }
+
+    fn open_inner(
+        path: PathBuf,
+        max_entries: usize,
+        max_age: Duration,
+        cipher: Option<Cipher>,
+    ) -> Result<Self, ReplayError> {
+        let io = |err: std::io::Error| ReplayError::Journal(format!("{}: {err}", path.display()));
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent).map_err(io)?;
+        }
+        let buffer = Self::new(max_entries, max_age);
+        match File::open(&path) {
+            Ok(file) => {
+                let lines: Vec<String> = BufReader::new(file)
+                    .lines()
+                    .collect::<Result<_, _>>()
+                    .map_err(io)?;
+                let last = lines.len().saturating_sub(1);
+                for (index, line) in lines.iter().enumerate() {
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    match decode_line(line, cipher.as_ref()) {
+                        Ok(record) => {
+                            let envelope = record.into_envelope();
+                            buffer.push_envelope(envelope.entry, envelope.inserted_at)?;
+                        }
+                        Err(LineError::Malformed(err)) if index == last => {
+                            tracing::warn!(
+                                path = %path.display(),
+                                error = %err,
+                                "discarding truncated replay journal record"
+                            );
+                        }
+                        Err(err) => {
+                            return Err(ReplayError::Journal(format!(
+                                "{} line {}: {err}",
+                                path.display(),
+                                index + 1
+                            )));
+                        }
+                    }
+                }
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(io(err)),
+        }
+        let file = open_append(&path).map_err(io)?;
+        let mut journal = Journal {
+            path,
+            file,
+            records: 0,
+            cipher,
+        };
+        journal.rewrite(buffer.inner.lock().expect("buffer mutex poisoned").iter())?;
+        Ok(Self {
+            journal: Some(Arc::new(Mutex::new(journal))),
+            ..buffer
+        })
+    }
+
+    /// In [`ReplayMode::Strict`], pushing or requeueing an entry whose
+    /// repository and sequence are already buffered fails with
+    /// [`ReplayError::Duplicate`].
+    #[must_use]
+    pub fn with_replay_mode(mut self, mode: ReplayMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Call `hook` with every entry dropped for capacity or age, so callers
+    /// can log, dead-letter or persist it. The hook runs after the buffer's
+    /// lock is released and may use the buffer.
+    #[must_use]
+    pub fn with_on_evict(
+        mut self,
+        hook: impl Fn(EvictedReplayEntry) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_evict = Some(EvictionHook(Arc::new(hook)));
+        self
+    }
+
+    /// Stamp and age entries against `clock` instead of the system clock.
+    /// Entries recovered by [`OfflineReplayBuffer::open`] were already
+    /// checked against the system clock.
+    #[must_use]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    #[must_use]
+    pub fn replay_mode(&self) -> ReplayMode {
+        self.mode
+    }
+
+    /// Sequence ranges missing between the buffered entries of each
+    /// repository.
+    #[must_use]
+    pub fn gaps(&self) -> Vec<SequenceGap> {
+        let guard = self.inner.lock().expect("buffer mutex poisoned");
+        find_gaps(guard.iter().map(|envelope| &envelope.entry))
+    }
+
+    /// Journal file backing a durable buffer.
+    #[must_use]
+    pub fn path(&self) -> Option<PathBuf> {
+        self.journal
+            .as_ref()
+            .map(|journal| journal.lock().expect("journal mutex poisoned").path.clone())
+    }
+
+    /// Write the buffered entries to `path` as a JSONL snapshot, oldest
+    /// first, without draining them. Returns the number of entries written.
+    ///
+    /// The format matches the offline transport queue snapshots: one
+    /// `{sequence, command, payload, token_id, enqueued_at}` object per line,
+    /// with the replay entry as `payload` and `enqueued_at` in Unix seconds.
+    pub fn export_snapshot(&self, path: impl AsRef<Path>) -> Result<usize, ReplayError> {
+        let path = path.as_ref();
+        let mut bytes = Vec::new();
+        let (count, evicted) = {
+            let mut guard = self.inner.lock().expect("buffer mutex poisoned");
+            let evicted = self.purge_locked(&mut guard, self.clock.now());
+            for envelope in guard.iter() {
+                let record = SnapshotRecord {
+                    sequence: envelope.entry.sequence,
+                    command: SNAPSHOT_COMMAND.into(),
+                    payload: envelope.entry.clone(),
+                    token_id: format!("offline-{}", envelope.entry.sequence),
+                    enqueued_at: envelope
+                        .inserted_at
+                        .duration_since(UNIX_EPOCH)
+                        .map_or(0, |since| since.as_secs() as i64),
+                };
+                serde_json::to_writer(&mut bytes, &record)
+                    .map_err(|err| ReplayError::Snapshot(err.to_string()))?;
+                bytes.push(b'\n');
+            }
+            (guard.len(), evicted)
+        };
+        self.notify_evicted(evicted);
+        let io = |err: std::io::Error| ReplayError::Snapshot(format!("{}: {err}", path.display()));
+        storage_atomic::write(path, &bytes).map_err(io)?;
+        Ok(count)
+    }
+
+    /// Append the entries of a snapshot written by
+    /// [`OfflineReplayBuffer::export_snapshot`], keeping their original
+    /// enqueue time so retention still applies. Returns the number of
+    /// entries read; entries already past `max_age` are dropped as usual.
+    ///
+    /// The whole file is validated before anything is pushed.
+    pub fn import_snapshot(&self, path: impl AsRef<Path>) -> Result<usize, ReplayError> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .map_err(|err| ReplayError::Snapshot(format!("{}: {err}", path.display())))?;
+        let mut envelopes = Vec::new();
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line =
+                line.map_err(|err| ReplayError::Snapshot(format!("{}: {err}", path.display())))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let invalid = |detail: String| {
+                ReplayError::Snapshot(format!("{} line {}: {detail}", path.display(), index + 1))
+            };
+            let record: SnapshotRecord =
+                serde_json::from_str(&line).map_err(|err| invalid(err.to_string()))?;
+            if record.command != SNAPSHOT_COMMAND {
+                return Err(invalid(format!("unexpected command `{}`", record.command)));
+            }
+            if record.payload.sequence != record.sequence {
+                return Err(invalid(format!(
+                    "sequence {} does not match payload sequence {}",
+                    record.sequence, record.payload.sequence
+                )));
+            }
+            let inserted_at = UNIX_EPOCH + Duration::from_secs(record.enqueued_at.max(0) as u64);
+            envelopes.push((record.payload, inserted_at));
+        }
+        let count = envelopes.len();
+        for (entry, inserted_at) in envelopes {
+            self.push_envelope(entry, inserted_at)?;
         }
+        Ok(count)
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.inner.lock().map(|guard| guard.len()).unwrap_or(0)
     }
 
     pub fn push(&self, entry: ReplayEntry) -> Result<(), ReplayError> {
-        let now = SystemTime::now();
+        let now = self.clock.now();
         self.push_envelope(entry, now)
     }
 
     pub fn requeue(&self, ready: ReadyReplayEntry) -> Result<(), ReplayError> {
-        self.push_envelope(ready.entry, ready.inserted_at)
+        self.push_envelope(ready.entry, ready.inserted_at)?;
+        self.counters.requeued.fetch_add(1, Ordering::Relaxed);
+        Ok(())
     }
 
     #[must_use]
     pub fn drain_ready(&self) -> Vec<ReadyReplayEntry> {
         let mut guard = self.inner.lock().expect("buffer mutex poisoned");
-        let now = SystemTime::now();
-        self.purge_locked(&mut guard, now);
-        guard
+        let now = self.clock.now();
+        let evicted = self.purge_locked(&mut guard, now);
+        if let Some(journal) = &self.journal {
+            let mut journal = journal.lock().expect("journal mutex poisoned");
+            if let Err(err) = journal.rewrite([]) {
+                // Entries stay journaled and are replayed again after a restart.
+                tracing::warn!(error = %err, "failed to truncate replay journal");
+            }
+        }
+        let ready = guard
             .drain(..)
             .map(|env| ReadyReplayEntry {
                 entry: env.entry,
                 inserted_at: env.inserted_at,
             })
-            .collect()
+            .collect();
+        drop(guard);
+        self.notify_evicted(evicted);
+        ready
     }
 
     #[must_use]
@@ -84,11 +543,58 @@ impl OfflineReplayBuffer {
         *self.max_sequence_seen.lock().unwrap()
     }
 
-    fn purge_locked(&self, guard: &mut VecDeque<ReplayEnvelope>, now: SystemTime) {
-        guard.retain(|envelope| match now.duration_since(envelope.inserted_at) {
-            Ok(age) => age <= self.max_age,
-            Err(_) => true,
-        });
+    /// Fill level, entry ages and eviction/requeue counts since creation.
+    #[must_use]
+    pub fn stats(&self) -> BufferStats {
+        let now = self.clock.now();
+        let ages = {
+            let guard = self.inner.lock().expect("buffer mutex poisoned");
+            guard
+                .iter()
+                .map(|envelope| now.duration_since(envelope.inserted_at).unwrap_or_default())
+                .collect()
+        };
+        BufferStats {
+            len: self.len(),
+            capacity: self.max_entries,
+            max_age: self.max_age,
+            ages: AgeDistribution::from_ages(ages),
+            evicted: self.counters.evicted.load(Ordering::Relaxed),
+            expired: self.counters.expired.load(Ordering::Relaxed),
+            requeued: self.counters.requeued.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Drop expired entries, returning them for [`Self::notify_evicted`].
+    fn purge_locked(
+        &self,
+        guard: &mut VecDeque<ReplayEnvelope>,
+        now: SystemTime,
+    ) -> Vec<EvictedReplayEntry> {
+        let expired = |envelope: &ReplayEnvelope| match now.duration_since(envelope.inserted_at) {
+            Ok(age) => age > self.max_age,
+            Err(_) => false,
+        };
+        if !guard.iter().any(expired) {
+            return Vec::new();
+        }
+        let (dropped, kept): (VecDeque<_>, VecDeque<_>) = guard.drain(..).partition(expired);
+        *guard = kept;
+        self.counters
+            .expired
+            .fetch_add(dropped.len() as u64, Ordering::Relaxed);
+        dropped
+            .into_iter()
+            .map(|envelope| envelope.evicted(EvictionReason::Expired))
+            .collect()
+    }
+
+    fn notify_evicted(&self, evicted: Vec<EvictedReplayEntry>) {
+        if let Some(EvictionHook(hook)) = &self.on_evict {
+            for entry in evicted {
+                hook(entry);
+            }
+        }
     }
 
     fn push_envelope(
@@ -102,7 +608,26 @@ impl OfflineReplayBuffer {
             ));
         }
         let mut guard = self.inner.lock().expect("buffer mutex poisoned");
-        let now = SystemTime::now();
+        let now = self.clock.now();
+        if self.mode == ReplayMode::Strict
+            && guard.iter().any(|envelope| {
+                envelope.entry.sequence == entry.sequence && envelope.entry.repo_id == entry.repo_id
+            })
+        {
+            return Err(ReplayError::Duplicate {
+                repo_id: entry.repo_id,
+                sequence: entry.sequence,
+            });
+        }
+        if let Some(journal) = &self.journal {
+            journal
+                .lock()
+                .expect("journal mutex poisoned")
+                .append(&ReplayEnvelope {
+                    entry: entry.clone(),
+                    inserted_at,
+                })?;
+        }
         {
             let mut max_seen = self.max_sequence_seen.lock().unwrap();
             match *max_seen {
@@ -111,10 +636,22 @@ impl OfflineReplayBuffer {
             }
         }
         guard.push_back(ReplayEnvelope { entry, inserted_at });
-        self.purge_locked(&mut guard, now);
+        let mut evicted = self.purge_locked(&mut guard, now);
         while guard.len() > self.max_entries {
-            guard.pop_front();
+            if let Some(envelope) = guard.pop_front() {
+                evicted.push(envelope.evicted(EvictionReason::Capacity));
+            }
+            self.counters.evicted.fetch_add(1, Ordering::Relaxed);
+        }
+        if let Some(journal) = &self.journal {
+            let mut journal = journal.lock().expect("journal mutex poisoned");
+            // Keep evicted entries from accumulating in the journal.
+            if journal.records > self.max_entries.saturating_mul(2) {
+                journal.rewrite(guard.iter())?;
+            }
         }
+        drop(guard);
+        self.notify_evicted(evicted);
         Ok(())
     }
 }
@@ -123,12 +660,24 @@ impl OfflineReplayBuffer {
 pub enum ReplayError {
     #[error("offline replay buffer misconfigured: {0}")]
     Misconfigured(String),
+    #[error("offline replay journal error: {0}")]
+    Journal(String),
+    #[error("offline replay snapshot error: {0}")]
+    Snapshot(String),
+    #[error("replay entry {sequence} for repository {repo_id} does not follow {last}")]
+    OutOfOrder {
+        repo_id: String,
+        last: u64,
+        sequence: u64,
+    },
+    #[error("duplicate replay entry {sequence} for repository {repo_id}")]
+    Duplicate { repo_id: String, sequence: u64 },
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::thread;
+    use runtime_clock::{Clock, MockClock};
 
     fn entry_with_sequence(sequence: u64) -> ReplayEntry {
         ReplayEntry {
@@ -138,14 +687,19 @@ mod tests {
             payload_checksum_before: format!("before-{sequence}"),
             payload_checksum_after: format!("after-{sequence}"),
             status: "buffered".into(),
+            sealed_payload: None,
+            signature: None,
+            tenant_id: None,
         }
     }
 
     #[test]
     fn requeue_preserves_original_age_for_expiration() {
-        let buffer = OfflineReplayBuffer::new(16, Duration::from_millis(100));
+        let clock = Arc::new(MockClock::default());
+        let buffer =
+            OfflineReplayBuffer::new(16, Duration::from_secs(100)).with_clock(clock.clone());
         buffer.push(entry_with_sequence(1)).unwrap();
-        thread::sleep(Duration::from_millis(40));
+        clock.advance(Duration::from_secs(40));
 
         let mut ready = buffer.drain_ready();
         assert_eq!(ready.len(), 1);
@@ -154,7 +708,7 @@ mod tests {
         buffer.requeue(ready_entry).unwrap();
         assert!(!buffer.is_empty());
 
-        thread::sleep(Duration::from_millis(80));
+        clock.advance(Duration::from_secs(80));
         let drained_after_wait = buffer.drain_ready();
         assert!(drained_after_wait.is_empty());
         assert!(buffer.is_empty());
@@ -177,10 +731,13 @@ mod tests {
 
     #[test]
     fn purges_entries_exceeding_max_age() {
-        let buffer = OfflineReplayBuffer::new(4, Duration::from_millis(50));
+        let clock = Arc::new(MockClock::default());
+        let buffer = OfflineReplayBuffer::new(4, Duration::from_secs(50)).with_clock(clock.clone());
         buffer.push(entry_with_sequence(10)).unwrap();
 
-        thread::sleep(Duration::from_millis(65));
+        clock.advance(Duration::from_secs(50));
+        assert_eq!(buffer.stats().len, 1, "entries live for exactly max_age");
+        clock.advance(Duration::from_secs(1));
 
         let drained = buffer.drain_ready();
         assert!(drained.is_empty(), "expired entry should be purged");
@@ -216,6 +773,282 @@ mod tests {
         assert!(buffer.is_empty());
     }
 
+    #[test]
+    fn durable_buffer_recovers_entries_after_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledger").join("offline.jsonl");
+        {
+            let buffer = OfflineReplayBuffer::open(&path, 8, Duration::from_secs(60)).unwrap();
+            for seq in 1..=3 {
+                buffer.push(entry_with_sequence(seq)).unwrap();
+            }
+            assert_eq!(buffer.path().as_deref(), Some(path.as_path()));
+        }
+        // Simulate a crash midway through appending another record.
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"entry":{"sequence":4,"#).unwrap();
+        drop(file);
+
+        let recovered = OfflineReplayBuffer::open(&path, 8, Duration::from_secs(60)).unwrap();
+        assert_eq!(recovered.len(), 3);
+        assert_eq!(recovered.max_sequence(), Some(3));
+        let sequences: Vec<u64> = recovered
+            .drain_ready()
+            .iter()
+            .map(|ready| ready.entry.sequence)
+            .collect();
+        assert_eq!(sequences, vec![1, 2, 3]);
+
+        let reopened = OfflineReplayBuffer::open(&path, 8, Duration::from_secs(60)).unwrap();
+        assert!(reopened.is_empty(), "drained entries are not replayed");
+    }
+
+    #[test]
+    fn sealed_journal_hides_entries_and_needs_the_cipher() {
+        use crate::cipher::XorCipher;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("offline.jsonl");
+        let max_age = Duration::from_secs(60);
+        {
+            let plain = OfflineReplayBuffer::open(&path, 8, max_age).unwrap();
+            plain.push(entry_with_sequence(1)).unwrap();
+        }
+        {
+            // The plaintext journal is resealed on open.
+            let sealed =
+                OfflineReplayBuffer::open_with_cipher(&path, 8, max_age, Arc::new(XorCipher(7)))
+                    .unwrap();
+            sealed.push(entry_with_sequence(2)).unwrap();
+        }
+        let journal = fs::read_to_string(&path).unwrap();
+        assert_eq!(journal.lines().count(), 2);
+        assert!(
+            !journal.contains("repo-"),
+            "journal leaks metadata: {journal}"
+        );
+
+        assert!(matches!(
+            OfflineReplayBuffer::open(&path, 8, max_age),
+            Err(ReplayError::Journal(_))
+        ));
+        assert!(matches!(
+            OfflineReplayBuffer::open_with_cipher(&path, 8, max_age, Arc::new(XorCipher(8))),
+            Err(ReplayError::Journal(_))
+        ));
+        let reopened =
+            OfflineReplayBuffer::open_with_cipher(&path, 8, max_age, Arc::new(XorCipher(7)))
+                .unwrap();
+        let sequences: Vec<u64> = reopened
+            .drain_ready()
+            .iter()
+            .map(|ready| ready.entry.sequence)
+            .collect();
+        assert_eq!(sequences, vec![1, 2]);
+    }
+
+    #[test]
+    fn snapshot_round_trips_between_buffers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export").join("snapshot.jsonl");
+        let source = OfflineReplayBuffer::new(8, Duration::from_secs(600));
+        for seq in [3, 1, 2] {
+            source.push(entry_with_sequence(seq)).unwrap();
+        }
+        assert_eq!(source.export_snapshot(&path).unwrap(), 3);
+        assert_eq!(source.len(), 3, "export does not drain");
+
+        let first_line = fs::read_to_string(&path).unwrap();
+        let first: serde_json::Value =
+            serde_json::from_str(first_line.lines().next().unwrap()).unwrap();
+        assert_eq!(first["sequence"], 3);
+        assert_eq!(first["command"], SNAPSHOT_COMMAND);
+        assert_eq!(first["token_id"], "offline-3");
+        assert!(first["enqueued_at"].as_i64().unwrap() > 0);
+
+        let target = OfflineReplayBuffer::new(8, Duration::from_secs(600));
+        assert_eq!(target.import_snapshot(&path).unwrap(), 3);
+        assert_eq!(target.max_sequence(), Some(3));
+        let imported: Vec<ReplayEntry> = target
+            .drain_ready()
+            .into_iter()
+            .map(|ready| ready.entry)
+            .collect();
+        assert_eq!(
+            imported,
+            vec![
+                entry_with_sequence(3),
+                entry_with_sequence(1),
+                entry_with_sequence(2)
+            ]
+        );
+    }
+
+    #[test]
+    fn import_snapshot_rejects_foreign_records_without_partial_import() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshot.jsonl");
+        let valid = serde_json::to_string(&SnapshotRecord {
+            sequence: 1,
+            command: SNAPSHOT_COMMAND.into(),
+            payload: entry_with_sequence(1),
+            token_id: "offline-1".into(),
+            enqueued_at: 1_700_000_000,
+        })
+        .unwrap();
+        let foreign = r#"{"sequence": 2, "command": "ingest", "payload": {"doc": 1}, "token_id": "offline-2", "enqueued_at": 1700000000}"#;
+        fs::write(&path, format!("{valid}\n{foreign}\n")).unwrap();
+
+        let buffer = OfflineReplayBuffer::new(8, Duration::from_secs(600));
+        let err = buffer.import_snapshot(&path).unwrap_err();
+        assert!(err.to_string().contains("line 2"), "{err}");
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn strict_buffer_rejects_duplicates_and_reports_gaps() {
+        let in_repo = |repo_id: &str, sequence| ReplayEntry {
+            repo_id: repo_id.into(),
+            ..entry_with_sequence(sequence)
+        };
+        let buffer = OfflineReplayBuffer::new(8, Duration::from_secs(60))
+            .with_replay_mode(ReplayMode::Strict);
+        for (repo_id, seq) in [("repo-a", 1), ("repo-b", 2), ("repo-a", 2), ("repo-a", 6)] {
+            buffer.push(in_repo(repo_id, seq)).unwrap();
+        }
+
+        let err = buffer.push(in_repo("repo-a", 2)).unwrap_err();
+        assert!(matches!(err, ReplayError::Duplicate { sequence: 2, .. }));
+        assert_eq!(buffer.len(), 4);
+        assert_eq!(
+            buffer.gaps(),
+            vec![SequenceGap {
+                repo_id: "repo-a".into(),
+                first_missing: 3,
+                last_missing: 5,
+            }]
+        );
+
+        let lenient = OfflineReplayBuffer::new(8, Duration::from_secs(60));
+        lenient.push(entry_with_sequence(1)).unwrap();
+        lenient.push(entry_with_sequence(1)).unwrap();
+        assert_eq!(lenient.len(), 2);
+    }
+
+    #[test]
+    fn stats_track_occupancy_evictions_and_requeues() {
+        let clock = Arc::new(MockClock::default());
+        let buffer =
+            OfflineReplayBuffer::new(2, Duration::from_secs(200)).with_clock(clock.clone());
+        let past = clock.now() - Duration::from_secs(201);
+        buffer
+            .requeue(ReadyReplayEntry {
+                entry: entry_with_sequence(1),
+                inserted_at: past,
+            })
+            .unwrap();
+        for seq in 2..=4 {
+            buffer.push(entry_with_sequence(seq)).unwrap();
+        }
+        let ready = buffer.drain_ready();
+        for entry in ready {
+            buffer.requeue(entry).unwrap();
+        }
+
+        let stats = buffer.stats();
+        assert_eq!(stats.len, 2);
+        assert_eq!(stats.capacity, 2);
+        assert_eq!(stats.expired, 1);
+        assert_eq!(stats.evicted, 1);
+        assert_eq!(stats.requeued, 3);
+        clock.advance(Duration::from_secs(30));
+        let stats = buffer.stats();
+        assert_eq!(
+            stats.ages.map(|ages| ages.oldest),
+            Some(Duration::from_secs(30))
+        );
+        assert!(stats.near_capacity(0.9));
+    }
+
+    #[test]
+    fn on_evict_reports_capacity_and_age_drops() {
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let clock = Arc::new(MockClock::default());
+        let buffer = OfflineReplayBuffer::new(2, Duration::from_secs(100))
+            .with_clock(clock.clone())
+            .with_on_evict({
+                let evicted = Arc::clone(&evicted);
+                move |dropped: EvictedReplayEntry| {
+                    evicted
+                        .lock()
+                        .unwrap()
+                        .push((dropped.entry.sequence, dropped.reason));
+                }
+            });
+        for seq in 1..=3 {
+            buffer.push(entry_with_sequence(seq)).unwrap();
+        }
+        clock.advance(Duration::from_secs(150));
+        assert!(buffer.drain_ready().is_empty());
+
+        assert_eq!(
+            *evicted.lock().unwrap(),
+            vec![
+                (1, EvictionReason::Capacity),
+                (2, EvictionReason::Expired),
+                (3, EvictionReason::Expired),
+            ]
+        );
+    }
+
+    #[test]
+    fn on_evict_hook_may_reenter_the_buffer() {
+        let slot = Arc::new(std::sync::OnceLock::<OfflineReplayBuffer>::new());
+        let seen_len = Arc::new(Mutex::new(None));
+        let buffer = OfflineReplayBuffer::new(1, Duration::from_secs(60)).with_on_evict({
+            let (slot, seen_len) = (Arc::clone(&slot), Arc::clone(&seen_len));
+            move |_| *seen_len.lock().unwrap() = slot.get().map(OfflineReplayBuffer::len)
+        });
+        slot.set(buffer.clone()).unwrap();
+        buffer.push(entry_with_sequence(1)).unwrap();
+        buffer.push(entry_with_sequence(2)).unwrap();
+        assert_eq!(*seen_len.lock().unwrap(), Some(1));
+    }
+
+    #[test]
+    fn durable_buffer_compacts_evicted_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("offline.jsonl");
+        let buffer = OfflineReplayBuffer::open(&path, 2, Duration::from_secs(60)).unwrap();
+        for seq in 1..=10 {
+            buffer.push(entry_with_sequence(seq)).unwrap();
+        }
+        let lines = fs::read_to_string(&path).unwrap().lines().count();
+        assert!(
+            lines <= 5,
+            "journal should be compacted, found {lines} lines"
+        );
+
+        let recovered = OfflineReplayBuffer::open(&path, 2, Duration::from_secs(60)).unwrap();
+        let sequences: Vec<u64> = recovered
+            .drain_ready()
+            .iter()
+            .map(|ready| ready.entry.sequence)
+            .collect();
+        assert_eq!(sequences, vec![9, 10]);
+    }
+
+    #[test]
+    fn corrupt_journal_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("offline.jsonl");
+        fs::write(&path, "not json\n{}\n").unwrap();
+        assert!(matches!(
+            OfflineReplayBuffer::open(&path, 2, Duration::from_secs(60)),
+            Err(ReplayError::Journal(_))
+        ));
+    }
+
     #[test]
     fn drain_ready_concurrent_push_preserves_order() {
         let buffer = OfflineReplayBuffer::new(8, Duration::from_secs(60));
@@ -0,0 +1,209 @@
+//! Per-repository sequence domains.
+//!
+//! Replay entries from several repositories share one stream, so ordering is
+//! only meaningful within a repository: an entry must have a higher sequence
+//! than every earlier entry for the same `repo_id`, while entries of
+//! different repositories may interleave freely. Gap detection assumes each
+//! repository's producer numbers its entries contiguously.
+
+use std::collections::HashMap;
+
+use crate::{ReplayEntry, ReplayError};
+
+/// Sequences skipped within one repository's domain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SequenceGap {
+    pub repo_id: String,
+    pub first_missing: u64,
+    pub last_missing: u64,
+}
+
+impl SequenceGap {
+    /// Number of sequences missing.
+    #[must_use]
+    pub fn missing(&self) -> u64 {
+        self.last_missing - self.first_missing + 1
+    }
+}
+
+/// Missing ranges between the sequences of each repository in `entries`,
+/// ordered by repository and sequence. Repeated sequences are ignored.
+pub fn find_gaps<'a>(entries: impl IntoIterator<Item = &'a ReplayEntry>) -> Vec<SequenceGap> {
+    let mut by_repo: Vec<(&str, u64)> = entries
+        .into_iter()
+        .map(|entry| (entry.repo_id.as_str(), entry.sequence))
+        .collect();
+    by_repo.sort_unstable();
+    by_repo
+        .windows(2)
+        .filter(|pair| pair[0].0 == pair[1].0 && pair[1].1 > pair[0].1 + 1)
+        .map(|pair| SequenceGap {
+            repo_id: pair[0].0.to_string(),
+            first_missing: pair[0].1 + 1,
+            last_missing: pair[1].1 - 1,
+        })
+        .collect()
+}
+
+/// How replay treats repeated and skipped sequences.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReplayMode {
+    /// Accept entries as they come.
+    #[default]
+    Lenient,
+    /// Reject duplicate sequences within a repository and report gaps.
+    Strict,
+}
+
+/// Highest sequence seen for each repository.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepoSequences {
+    last: HashMap<String, u64>,
+}
+
+impl RepoSequences {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Highest sequence recorded for `repo_id`.
+    #[must_use]
+    pub fn max_sequence(&self, repo_id: &str) -> Option<u64> {
+        self.last.get(repo_id).copied()
+    }
+
+    /// Repositories and their highest sequence, in no particular order.
+    pub fn repos(&self) -> impl Iterator<Item = (&str, u64)> {
+        self.last
+            .iter()
+            .map(|(repo_id, sequence)| (repo_id.as_str(), *sequence))
+    }
+
+    /// Raise the high-water mark of `entry`'s repository without validating
+    /// order; lower sequences are ignored.
+    pub fn record(&mut self, entry: &ReplayEntry) {
+        let last = self.last.entry(entry.repo_id.clone()).or_insert(0);
+        *last = (*last).max(entry.sequence);
+    }
+
+    /// Record `entry` if it follows its repository's previous entry.
+    ///
+    /// Returns the skipped range when the sequence jumps ahead,
+    /// [`ReplayError::Duplicate`] for a repeated sequence and
+    /// [`ReplayError::OutOfOrder`] for a regressing one.
+    /// The first entry of a repository never reports a gap.
+    pub fn observe(&mut self, entry: &ReplayEntry) -> Result<Option<SequenceGap>, ReplayError> {
+        let Some(last) = self.max_sequence(&entry.repo_id) else {
+            self.record(entry);
+            return Ok(None);
+        };
+        if entry.sequence == last {
+            return Err(ReplayError::Duplicate {
+                repo_id: entry.repo_id.clone(),
+                sequence: entry.sequence,
+            });
+        }
+        if entry.sequence < last {
+            return Err(ReplayError::OutOfOrder {
+                repo_id: entry.repo_id.clone(),
+                last,
+                sequence: entry.sequence,
+            });
+        }
+        self.record(entry);
+        Ok((entry.sequence > last + 1).then(|| SequenceGap {
+            repo_id: entry.repo_id.clone(),
+            first_missing: last + 1,
+            last_missing: entry.sequence - 1,
+        }))
+    }
+
+    /// Validate a replay batch in order and return the gaps it contains.
+    ///
+    /// Nothing is recorded unless the whole batch is in order.
+    pub fn validate<'a>(
+        &mut self,
+        entries: impl IntoIterator<Item = &'a ReplayEntry>,
+    ) -> Result<Vec<SequenceGap>, ReplayError> {
+        let mut next = self.clone();
+        let mut gaps = Vec::new();
+        for entry in entries {
+            gaps.extend(next.observe(entry)?);
+        }
+        *self = next;
+        Ok(gaps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(repo_id: &str, sequence: u64) -> ReplayEntry {
+        ReplayEntry {
+            sequence,
+            repo_id: repo_id.into(),
+            delayed_ms: 0,
+            payload_checksum_before: format!("before-{sequence}"),
+            payload_checksum_after: format!("after-{sequence}"),
+            status: "emitted".into(),
+            sealed_payload: None,
+            signature: None,
+            tenant_id: None,
+        }
+    }
+
+    #[test]
+    fn repositories_are_ordered_independently() {
+        let mut sequences = RepoSequences::new();
+        let batch = [
+            entry("repo-a", 1),
+            entry("repo-b", 1),
+            entry("repo-a", 2),
+            entry("repo-b", 2),
+        ];
+        assert!(sequences.validate(&batch).expect("in order").is_empty());
+        assert_eq!(sequences.max_sequence("repo-a"), Some(2));
+        assert_eq!(sequences.max_sequence("repo-b"), Some(2));
+        assert_eq!(sequences.max_sequence("repo-c"), None);
+    }
+
+    #[test]
+    fn skipped_sequences_are_reported_per_repository() {
+        let mut sequences = RepoSequences::new();
+        let batch = [entry("repo-a", 1), entry("repo-b", 7), entry("repo-a", 4)];
+        let gaps = sequences.validate(&batch).expect("in order");
+        assert_eq!(
+            gaps,
+            vec![SequenceGap {
+                repo_id: "repo-a".into(),
+                first_missing: 2,
+                last_missing: 3,
+            }]
+        );
+        assert_eq!(gaps[0].missing(), 2);
+    }
+
+    #[test]
+    fn regressions_reject_the_whole_batch() {
+        let mut sequences = RepoSequences::new();
+        sequences.record(&entry("repo-a", 3));
+        let batch = [entry("repo-b", 1), entry("repo-a", 2)];
+        let err = sequences.validate(&batch).expect_err("regression");
+        assert!(matches!(
+            err,
+            ReplayError::OutOfOrder {
+                last: 3,
+                sequence: 2,
+                ..
+            }
+        ));
+        assert_eq!(sequences.max_sequence("repo-b"), None);
+
+        let err = sequences
+            .validate(&[entry("repo-a", 3)])
+            .expect_err("duplicate");
+        assert!(matches!(err, ReplayError::Duplicate { sequence: 3, .. }));
+    }
+}
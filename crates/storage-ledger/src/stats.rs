@@ -0,0 +1,151 @@
+//! Occupancy statistics shared by the replay and transport retry buffers.
+
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+/// Why a buffer dropped an entry that was never drained.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionReason {
+    /// Made room for a newer entry once the buffer was full.
+    Capacity,
+    /// Exceeded the buffer's `max_age`.
+    Expired,
+}
+
+/// Spread of entry ages in a buffer at the time of the snapshot.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AgeDistribution {
+    pub newest: Duration,
+    pub median: Duration,
+    pub p90: Duration,
+    pub oldest: Duration,
+}
+
+impl AgeDistribution {
+    /// Summarise `ages`; `None` when there are no entries.
+    #[must_use]
+    pub fn from_ages(mut ages: Vec<Duration>) -> Option<Self> {
+        if ages.is_empty() {
+            return None;
+        }
+        ages.sort_unstable();
+        let at = |quantile: usize| ages[(ages.len() - 1) * quantile / 100];
+        Some(Self {
+            newest: ages[0],
+            median: at(50),
+            p90: at(90),
+            oldest: ages[ages.len() - 1],
+        })
+    }
+}
+
+/// Snapshot of a bounded buffer's fill level and lifetime counters.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BufferStats {
+    pub len: usize,
+    /// Entries beyond this evict the oldest.
+    pub capacity: usize,
+    /// Entries older than this are purged.
+    pub max_age: Duration,
+    pub ages: Option<AgeDistribution>,
+    /// Entries dropped to make room for newer ones.
+    pub evicted: u64,
+    /// Entries purged for exceeding `max_age`.
+    pub expired: u64,
+    pub requeued: u64,
+}
+
+impl BufferStats {
+    /// Fraction of the capacity in use.
+    #[must_use]
+    pub fn utilization(&self) -> f64 {
+        if self.capacity == 0 {
+            return 1.0;
+        }
+        self.len as f64 / self.capacity as f64
+    }
+
+    /// Whether the buffer is at least `threshold` (0.0-1.0) full, or its
+    /// oldest entry has used up that share of `max_age`.
+    #[must_use]
+    pub fn near_capacity(&self, threshold: f64) -> bool {
+        let aging = self.ages.is_some_and(|ages| {
+            !self.max_age.is_zero()
+                && ages.oldest.as_secs_f64() / self.max_age.as_secs_f64() >= threshold
+        });
+        self.utilization() >= threshold || aging
+    }
+
+    /// The stats as JSON for diagnostics, with durations in milliseconds
+    /// and `oldest_age_ms` null while the buffer is empty.
+    #[must_use]
+    pub fn to_json(&self) -> Value {
+        let millis = |age: Duration| u64::try_from(age.as_millis()).unwrap_or(u64::MAX);
+        json!({
+            "len": self.len,
+            "capacity": self.capacity,
+            "utilization": self.utilization(),
+            "max_age_ms": millis(self.max_age),
+            "oldest_age_ms": self.ages.map(|ages| millis(ages.oldest)),
+            "evicted": self.evicted,
+            "expired": self.expired,
+            "requeued": self.requeued,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn age_distribution_uses_nearest_rank() {
+        assert_eq!(AgeDistribution::from_ages(Vec::new()), None);
+        let ages = (1..=10).rev().map(Duration::from_secs).collect();
+        let distribution = AgeDistribution::from_ages(ages).unwrap();
+        assert_eq!(distribution.newest, Duration::from_secs(1));
+        assert_eq!(distribution.median, Duration::from_secs(5));
+        assert_eq!(distribution.p90, Duration::from_secs(9));
+        assert_eq!(distribution.oldest, Duration::from_secs(10));
+    }
+
+    #[test]
+    fn near_capacity_considers_fill_level_and_age() {
+        let stats = BufferStats {
+            len: 9,
+            capacity: 10,
+            max_age: Duration::from_secs(100),
+            ..BufferStats::default()
+        };
+        assert!(stats.near_capacity(0.9));
+        let aging = BufferStats {
+            len: 1,
+            ages: AgeDistribution::from_ages(vec![Duration::from_secs(95)]),
+            ..stats
+        };
+        assert!(aging.near_capacity(0.9));
+        assert!(!aging.near_capacity(0.99));
+    }
+
+    #[test]
+    fn to_json_reports_durations_in_milliseconds() {
+        let stats = BufferStats {
+            len: 1,
+            capacity: 4,
+            max_age: Duration::from_secs(60),
+            ages: AgeDistribution::from_ages(vec![Duration::from_millis(1_500)]),
+            evicted: 2,
+            ..BufferStats::default()
+        };
+        let json = stats.to_json();
+        assert_eq!(json["utilization"], json!(0.25));
+        assert_eq!(json["max_age_ms"], json!(60_000));
+        assert_eq!(json["oldest_age_ms"], json!(1_500));
+        assert_eq!(json["evicted"], json!(2));
+        assert_eq!(
+            BufferStats::default().to_json()["oldest_age_ms"],
+            Value::Null
+        );
+    }
+}
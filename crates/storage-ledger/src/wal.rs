@@ -0,0 +1,922 @@
+//! Durable, segmented write-ahead ledger of replay entries.
+//!
+//! Each segment file is named after the first sequence it holds and contains
+//! frames of `len: u32 LE | checksum: u64 LE | payload`, where the payload is
+//! the JSON-encoded [`ReplayEntry`] and the checksum is the first eight bytes
+//! of its BLAKE3 hash. Ledgers opened with [`Ledger::open_with_cipher`] seal
+//! each payload with a [`RecordCipher`] first and set the top bit of `len`;
+//! the checksum then covers the sealed bytes.
+
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+use tokio::sync::watch;
+
+use crate::cipher::{Cipher, RecordCipher, LEDGER_CONTEXT};
+use crate::{ReplayEntry, RepoSequences};
+
+/// Extension of ledger segment files.
+pub const SEGMENT_EXTENSION: &str = "wal";
+
+const FRAME_HEADER_LEN: usize = 4 + 8;
+/// Frames claiming to be larger than this are treated as corruption.
+const MAX_FRAME_LEN: u32 = 16 * 1024 * 1024;
+/// Set in a frame's length when its payload is sealed.
+const SEALED_FRAME: u32 = 1 << 31;
+
+/// When appended frames are flushed to stable storage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsyncPolicy {
+    /// After every append.
+    Always,
+    /// After every `n` appends.
+    EveryN(u32),
+    /// On the first append once this long has passed since the last sync.
+    Interval(Duration),
+    /// Leave flushing to the operating system; [`Ledger::sync`] still works.
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LedgerConfig {
+    /// Size at which the active segment is closed and a new one started.
+    pub segment_max_bytes: u64,
+    pub fsync: FsyncPolicy,
+}
+
+impl Default for LedgerConfig {
+    fn default() -> Self {
+        Self {
+            segment_max_bytes: 64 * 1024 * 1024,
+            fsync: FsyncPolicy::Always,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum LedgerError {
+    #[error("ledger io error: {0}")]
+    Io(String),
+    #[error("ledger segment {} corrupt at offset {offset}: {detail}", segment.display())]
+    Corrupt {
+        segment: PathBuf,
+        offset: u64,
+        detail: String,
+    },
+    #[error("sequence {sequence} does not follow last appended sequence {last}")]
+    OutOfOrder { last: u64, sequence: u64 },
+    /// A sealed frame could not be opened: no cipher, or the wrong key.
+    /// Unlike corruption, this never truncates the ledger.
+    #[error("ledger cipher error: {0}")]
+    Cipher(String),
+}
+
+#[derive(Debug)]
+struct Segment {
+    first_sequence: u64,
+    path: PathBuf,
+}
+
+#[derive(Debug)]
+struct Active {
+    file: File,
+    bytes: u64,
+}
+
+#[derive(Debug)]
+struct Inner {
+    segments: Vec<Segment>,
+    active: Option<Active>,
+    last_sequence: Option<u64>,
+    repos: RepoSequences,
+    unsynced: u32,
+    last_sync: Instant,
+}
+
+/// Append-only on-disk log of [`ReplayEntry`] records.
+///
+/// Sequences must strictly increase. [`Ledger::open`] validates every frame;
+/// a torn or corrupt tail in the newest segment, as left by a crash
+/// mid-append, is truncated, while damage anywhere else is reported as
+/// [`LedgerError::Corrupt`].
+#[derive(Debug)]
+pub struct Ledger {
+    dir: PathBuf,
+    config: LedgerConfig,
+    inner: Mutex<Inner>,
+    /// Last appended sequence, for subscribers waiting on new entries.
+    tip: watch::Sender<Option<u64>>,
+    cipher: Option<Cipher>,
+}
+
+impl Ledger {
+    /// Open or create the ledger in `dir`, recovering from a crash if needed.
+    pub fn open(dir: impl Into<PathBuf>, config: LedgerConfig) -> Result<Self, LedgerError> {
+        Self::open_inner(dir.into(), config, None)
+    }
+
+    /// Like [`Ledger::open`], but seal every appended entry with `cipher`.
+    ///
+    /// Frames written without a cipher stay readable, so encryption can be
+    /// enabled on an existing ledger; only new appends are sealed.
+    pub fn open_with_cipher(
+        dir: impl Into<PathBuf>,
+        config: LedgerConfig,
+        cipher: Arc<dyn RecordCipher>,
+    ) -> Result<Self, LedgerError> {
+        Self::open_inner(dir.into(), config, Some(Cipher(cipher)))
+    }
+
+    fn open_inner(
+        dir: PathBuf,
+        config: LedgerConfig,
+        cipher: Option<Cipher>,
+    ) -> Result<Self, LedgerError> {
+        fs::create_dir_all(&dir).map_err(|err| io_error(&dir, &err))?;
+        let segments = list_segments(&dir)?;
+
+        let mut last_sequence = None;
+        let mut repos = RepoSequences::new();
+        let segment_count = segments.len();
+        for (index, segment) in segments.iter().enumerate() {
+            let newest = index + 1 == segment_count;
+            let scan = scan_segment(&segment.path, last_sequence, &mut repos, cipher.as_ref())?;
+            if let Some(damage) = scan.damage {
+                if !newest {
+                    return Err(damage);
+                }
+                tracing::warn!(
+                    segment = %segment.path.display(),
+                    offset = scan.valid_bytes,
+                    error = %damage,
+                    "truncating damaged ledger tail"
+                );
+                OpenOptions::new()
+                    .write(true)
+                    .open(&segment.path)
+                    .and_then(|file| {
+                        file.set_len(scan.valid_bytes)?;
+                        file.sync_all()
+                    })
+                    .map_err(|err| io_error(&segment.path, &err))?;
+            }
+            last_sequence = scan.last_sequence.or(last_sequence);
+        }
+
+        let active = match segments.last() {
+            Some(segment) => {
+                let file = open_append(&segment.path)?;
+                let bytes = file
+                    .metadata()
+                    .map_err(|err| io_error(&segment.path, &err))?
+                    .len();
+                Some(Active { file, bytes })
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            dir,
+            config,
+            tip: watch::Sender::new(last_sequence),
+            inner: Mutex::new(Inner {
+                segments,
+                active,
+                last_sequence,
+                repos,
+                unsynced: 0,
+                last_sync: Instant::now(),
+            }),
+            cipher,
+        })
+    }
+
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    #[must_use]
+    pub fn last_sequence(&self) -> Option<u64> {
+        self.lock().last_sequence
+    }
+
+    /// Highest sequence appended for `repo_id`.
+    #[must_use]
+    pub fn max_sequence(&self, repo_id: &str) -> Option<u64> {
+        self.lock().repos.max_sequence(repo_id)
+    }
+
+    /// Highest sequence of every repository in the ledger.
+    #[must_use]
+    pub fn repo_sequences(&self) -> RepoSequences {
+        self.lock().repos.clone()
+    }
+
+    /// Segment files, oldest first.
+    #[must_use]
+    pub fn segments(&self) -> Vec<PathBuf> {
+        self.lock()
+            .segments
+            .iter()
+            .map(|segment| segment.path.clone())
+            .collect()
+    }
+
+    /// Append `entry`, whose sequence must exceed every earlier one.
+    pub fn append(&self, entry: &ReplayEntry) -> Result<(), LedgerError> {
+        let mut inner = self.lock();
+        if let Some(last) = inner.last_sequence {
+            if entry.sequence <= last {
+                return Err(LedgerError::OutOfOrder {
+                    last,
+                    sequence: entry.sequence,
+                });
+            }
+        }
+        let frame = encode_frame(entry, self.cipher.as_ref())?;
+
+        let roll = inner.active.as_ref().map_or(true, |active| {
+            active.bytes > 0 && active.bytes + frame.len() as u64 > self.config.segment_max_bytes
+        });
+        if roll {
+            if let Some(active) = &inner.active {
+                active
+                    .file
+                    .sync_data()
+                    .map_err(|err| io_error(&self.dir, &err))?;
+            }
+            let path = self.dir.join(segment_name(entry.sequence));
+            let file = open_append(&path)?;
+            sync_dir(&self.dir)?;
+            inner.segments.push(Segment {
+                first_sequence: entry.sequence,
+                path,
+            });
+            inner.active = Some(Active { file, bytes: 0 });
+            inner.unsynced = 0;
+        }
+
+        let segment = inner
+            .segments
+            .last()
+            .map(|segment| segment.path.clone())
+            .unwrap_or_default();
+        let active = inner.active.as_mut().expect("active segment after roll");
+        if let Err(err) = active.file.write_all(&frame) {
+            // Drop any partial frame so later appends stay readable.
+            let _ = active.file.set_len(active.bytes);
+            return Err(io_error(&segment, &err));
+        }
+        active.bytes += frame.len() as u64;
+        inner.last_sequence = Some(entry.sequence);
+        inner.repos.record(entry);
+        inner.unsynced += 1;
+        self.tip.send_replace(Some(entry.sequence));
+
+        let due = match self.config.fsync {
+            FsyncPolicy::Always => true,
+            FsyncPolicy::EveryN(n) => inner.unsynced >= n.max(1),
+            FsyncPolicy::Interval(period) => inner.last_sync.elapsed() >= period,
+            FsyncPolicy::Never => false,
+        };
+        if due {
+            sync_active(&mut inner, &segment)?;
+        }
+        Ok(())
+    }
+
+    /// Flush the active segment to stable storage regardless of policy.
+    pub fn sync(&self) -> Result<(), LedgerError> {
+        let mut inner = self.lock();
+        let segment = inner
+            .segments
+            .last()
+            .map(|segment| segment.path.clone())
+            .unwrap_or_default();
+        sync_active(&mut inner, &segment)
+    }
+
+    /// Entries with `sequence >= from`, read from disk in order.
+    pub fn iter_from(&self, from: u64) -> LedgerIter {
+        LedgerIter::new(&self.lock().segments, from, self.cipher.clone())
+    }
+
+    /// Entries with `sequence >= from`, followed by every entry appended
+    /// later. The subscription ends once the ledger is dropped and the
+    /// subscriber has caught up.
+    #[must_use]
+    pub fn subscribe(&self, from: u64) -> LedgerSubscription {
+        LedgerSubscription {
+            dir: self.dir.clone(),
+            next: from,
+            tip: self.tip.subscribe(),
+            iter: Some(self.iter_from(from)),
+            rescanned: true,
+            cipher: self.cipher.clone(),
+        }
+    }
+
+    /// Delete whole segments whose entries all precede `sequence`; returns
+    /// how many were removed. The active segment is always kept.
+    pub fn remove_segments_before(&self, sequence: u64) -> Result<usize, LedgerError> {
+        let mut inner = self.lock();
+        let keep_from = inner
+            .segments
+            .partition_point(|segment| segment.first_sequence <= sequence)
+            .saturating_sub(1)
+            .min(inner.segments.len().saturating_sub(1));
+        let removed: Vec<Segment> = inner.segments.drain(..keep_from).collect();
+        for segment in &removed {
+            fs::remove_file(&segment.path).map_err(|err| io_error(&segment.path, &err))?;
+        }
+        if !removed.is_empty() {
+            sync_dir(&self.dir)?;
+        }
+        Ok(removed.len())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Iterator returned by [`Ledger::iter_from`].
+///
+/// Reads segments lazily, so entries appended after the iterator was created
+/// to a segment it has not finished are also returned.
+#[derive(Debug)]
+pub struct LedgerIter {
+    from: u64,
+    segments: VecDeque<PathBuf>,
+    reader: Option<(PathBuf, BufReader<File>, u64)>,
+    cipher: Option<Cipher>,
+}
+
+impl LedgerIter {
+    fn new(segments: &[Segment], from: u64, cipher: Option<Cipher>) -> Self {
+        // The last segment starting at or before `from` may hold it.
+        let start = segments
+            .partition_point(|segment| segment.first_sequence <= from)
+            .saturating_sub(1);
+        Self {
+            from,
+            segments: segments[start..]
+                .iter()
+                .map(|segment| segment.path.clone())
+                .collect(),
+            reader: None,
+            cipher,
+        }
+    }
+}
+
+impl Iterator for LedgerIter {
+    type Item = Result<ReplayEntry, LedgerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.reader.is_none() {
+                let path = self.segments.pop_front()?;
+                match File::open(&path) {
+                    Ok(file) => self.reader = Some((path, BufReader::new(file), 0)),
+                    // Removed by `remove_segments_before` since iteration began.
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                    Err(err) => return Some(Err(io_error(&path, &err))),
+                }
+            }
+            let (path, reader, offset) = self.reader.as_mut()?;
+            match read_frame(reader, self.cipher.as_ref()) {
+                Ok(Some((entry, len))) => {
+                    *offset += len;
+                    if entry.sequence >= self.from {
+                        return Some(Ok(entry));
+                    }
+                }
+                // End of segment; an in-flight append may leave a partial frame.
+                Ok(None) | Err(FrameError::Torn) => self.reader = None,
+                Err(FrameError::Corrupt(detail)) => {
+                    let err = LedgerError::Corrupt {
+                        segment: path.clone(),
+                        offset: *offset,
+                        detail,
+                    };
+                    self.reader = None;
+                    self.segments.clear();
+                    return Some(Err(err));
+                }
+                Err(FrameError::Cipher(detail)) => {
+                    let err = cipher_error(path, *offset, &detail);
+                    self.reader = None;
+                    self.segments.clear();
+                    return Some(Err(err));
+                }
+                Err(FrameError::Io(err)) => {
+                    let err = io_error(path, &err);
+                    self.reader = None;
+                    self.segments.clear();
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+/// Live tail of a [`Ledger`], returned by [`Ledger::subscribe`].
+///
+/// Entries are read back from the segment files, so a subscriber that falls
+/// behind never loses entries short of segments being removed under it.
+#[derive(Debug)]
+pub struct LedgerSubscription {
+    dir: PathBuf,
+    /// Lowest sequence not yet returned.
+    next: u64,
+    tip: watch::Receiver<Option<u64>>,
+    iter: Option<LedgerIter>,
+    /// Whether `iter` has read everything on disk since the last entry.
+    rescanned: bool,
+    cipher: Option<Cipher>,
+}
+
+impl LedgerSubscription {
+    /// The next entry, waiting for it to be appended if necessary. Returns
+    /// `None` once the ledger is dropped and every entry has been returned.
+    pub async fn next(&mut self) -> Option<Result<ReplayEntry, LedgerError>> {
+        loop {
+            if let Some(iter) = &mut self.iter {
+                match iter.next() {
+                    Some(Ok(entry)) => {
+                        self.next = entry.sequence.saturating_add(1);
+                        self.rescanned = false;
+                        return Some(Ok(entry));
+                    }
+                    Some(Err(err)) => {
+                        self.iter = None;
+                        return Some(Err(err));
+                    }
+                    None => self.iter = None,
+                }
+            }
+            let behind = |tip: &Option<u64>| tip.is_some_and(|tip| tip >= self.next);
+            // A rescan that found nothing new waits for the next append
+            // instead of spinning on entries that are no longer on disk.
+            if self.rescanned || !behind(&self.tip.borrow_and_update()) {
+                let closed = self.tip.changed().await.is_err();
+                if closed && (self.rescanned || !behind(&self.tip.borrow())) {
+                    return None;
+                }
+            }
+            match list_segments(&self.dir) {
+                Ok(segments) => {
+                    self.iter = Some(LedgerIter::new(&segments, self.next, self.cipher.clone()));
+                }
+                Err(err) => return Some(Err(err)),
+            }
+            self.rescanned = true;
+        }
+    }
+}
+
+#[derive(Debug)]
+enum FrameError {
+    /// The file ends part-way through a frame.
+    Torn,
+    Corrupt(String),
+    /// A sealed payload that could not be opened.
+    Cipher(String),
+    Io(io::Error),
+}
+
+fn encode_frame(entry: &ReplayEntry, cipher: Option<&Cipher>) -> Result<Vec<u8>, LedgerError> {
+    let mut payload =
+        serde_json::to_vec(entry).map_err(|err| LedgerError::Io(format!("encoding: {err}")))?;
+    if let Some(Cipher(cipher)) = cipher {
+        payload = cipher.seal(&payload, LEDGER_CONTEXT).map_err(|err| {
+            LedgerError::Cipher(format!("sealing entry {}: {err}", entry.sequence))
+        })?;
+    }
+    let len = u32::try_from(payload.len())
+        .ok()
+        .filter(|len| *len <= MAX_FRAME_LEN)
+        .ok_or_else(|| LedgerError::Io(format!("entry {} too large", entry.sequence)))?;
+    let flags = if cipher.is_some() { SEALED_FRAME } else { 0 };
+    let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + payload.len());
+    frame.extend_from_slice(&(len | flags).to_le_bytes());
+    frame.extend_from_slice(&checksum(&payload).to_le_bytes());
+    frame.extend_from_slice(&payload);
+    Ok(frame)
+}
+
+/// Read one frame, returning the entry and the frame's size in bytes, or
+/// `None` at a clean end of file.
+fn read_frame(
+    reader: &mut impl Read,
+    cipher: Option<&Cipher>,
+) -> Result<Option<(ReplayEntry, u64)>, FrameError> {
+    let mut header = [0u8; FRAME_HEADER_LEN];
+    match read_full(reader, &mut header)? {
+        0 => return Ok(None),
+        n if n < FRAME_HEADER_LEN => return Err(FrameError::Torn),
+        _ => {}
+    }
+    let raw_len = u32::from_le_bytes(header[..4].try_into().expect("4-byte length"));
+    let sealed = raw_len & SEALED_FRAME != 0;
+    let len = raw_len & !SEALED_FRAME;
+    if len > MAX_FRAME_LEN {
+        return Err(FrameError::Corrupt(format!("frame length {len} too large")));
+    }
+    let expected = u64::from_le_bytes(header[4..].try_into().expect("8-byte checksum"));
+    let mut payload = vec![0u8; len as usize];
+    if read_full(reader, &mut payload)? < payload.len() {
+        return Err(FrameError::Torn);
+    }
+    if checksum(&payload) != expected {
+        return Err(FrameError::Corrupt("checksum mismatch".into()));
+    }
+    let frame_len = (FRAME_HEADER_LEN + payload.len()) as u64;
+    if sealed {
+        let Some(Cipher(cipher)) = cipher else {
+            return Err(FrameError::Cipher("sealed frame but no cipher".into()));
+        };
+        payload = cipher
+            .open(&payload, LEDGER_CONTEXT)
+            .map_err(FrameError::Cipher)?;
+    }
+    let entry = serde_json::from_slice(&payload)
+        .map_err(|err| FrameError::Corrupt(format!("undecodable entry: {err}")))?;
+    Ok(Some((entry, frame_len)))
+}
+
+/// Fill `buf` as far as the reader allows, returning the bytes read.
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> Result<usize, FrameError> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(FrameError::Io(err)),
+        }
+    }
+    Ok(filled)
+}
+
+fn checksum(payload: &[u8]) -> u64 {
+    let hash = blake3::hash(payload);
+    u64::from_le_bytes(hash.as_bytes()[..8].try_into().expect("8-byte prefix"))
+}
+
+struct Scan {
+    valid_bytes: u64,
+    last_sequence: Option<u64>,
+    damage: Option<LedgerError>,
+}
+
+/// Validate a segment's frames, stopping at the first damaged one.
+fn scan_segment(
+    path: &Path,
+    mut last_sequence: Option<u64>,
+    repos: &mut RepoSequences,
+    cipher: Option<&Cipher>,
+) -> Result<Scan, LedgerError> {
+    let file = File::open(path).map_err(|err| io_error(path, &err))?;
+    let mut reader = BufReader::new(file);
+    let mut valid_bytes = 0;
+    let damage = loop {
+        let detail = match read_frame(&mut reader, cipher) {
+            Ok(None) => break None,
+            Ok(Some((entry, len))) => {
+                if last_sequence.is_some_and(|last| entry.sequence <= last) {
+                    format!("sequence {} is not increasing", entry.sequence)
+                } else {
+                    last_sequence = Some(entry.sequence);
+                    repos.record(&entry);
+                    valid_bytes += len;
+                    continue;
+                }
+            }
+            Err(FrameError::Torn) => "torn frame".into(),
+            Err(FrameError::Corrupt(detail)) => detail,
+            Err(FrameError::Cipher(detail)) => {
+                return Err(cipher_error(path, valid_bytes, &detail))
+            }
+            Err(FrameError::Io(err)) => return Err(io_error(path, &err)),
+        };
+        break Some(LedgerError::Corrupt {
+            segment: path.to_path_buf(),
+            offset: valid_bytes,
+            detail,
+        });
+    };
+    Ok(Scan {
+        valid_bytes,
+        last_sequence,
+        damage,
+    })
+}
+
+fn list_segments(dir: &Path) -> Result<Vec<Segment>, LedgerError> {
+    let mut segments = Vec::new();
+    for dirent in fs::read_dir(dir).map_err(|err| io_error(dir, &err))? {
+        let path = dirent.map_err(|err| io_error(dir, &err))?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(SEGMENT_EXTENSION) {
+            continue;
+        }
+        let first_sequence = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse().ok())
+            .ok_or_else(|| LedgerError::Corrupt {
+                segment: path.clone(),
+                offset: 0,
+                detail: "segment name is not a sequence number".into(),
+            })?;
+        segments.push(Segment {
+            first_sequence,
+            path,
+        });
+    }
+    segments.sort_by_key(|segment| segment.first_sequence);
+    Ok(segments)
+}
+
+fn segment_name(first_sequence: u64) -> String {
+    format!("{first_sequence:020}.{SEGMENT_EXTENSION}")
+}
+
+fn open_append(path: &Path) -> Result<File, LedgerError> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|err| io_error(path, &err))
+}
+
+fn sync_active(inner: &mut Inner, segment: &Path) -> Result<(), LedgerError> {
+    if let Some(active) = &inner.active {
+        active
+            .file
+            .sync_data()
+            .map_err(|err| io_error(segment, &err))?;
+    }
+    inner.unsynced = 0;
+    inner.last_sync = Instant::now();
+    Ok(())
+}
+
+/// Persist directory entries so new or removed segments survive a crash.
+fn sync_dir(dir: &Path) -> Result<(), LedgerError> {
+    storage_atomic::sync_dir(dir).map_err(|err| io_error(dir, &err))
+}
+
+fn io_error(path: &Path, err: &io::Error) -> LedgerError {
+    LedgerError::Io(format!("{}: {err}", path.display()))
+}
+
+fn cipher_error(path: &Path, offset: u64, detail: &str) -> LedgerError {
+    LedgerError::Cipher(format!("{} at offset {offset}: {detail}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cipher::XorCipher;
+
+    fn entry(sequence: u64) -> ReplayEntry {
+        ReplayEntry {
+            sequence,
+            repo_id: "repo-wal".into(),
+            delayed_ms: 0,
+            payload_checksum_before: format!("before-{sequence}"),
+            payload_checksum_after: format!("after-{sequence}"),
+            status: "emitted".into(),
+            sealed_payload: None,
+            signature: None,
+            tenant_id: None,
+        }
+    }
+
+    fn small_segments() -> LedgerConfig {
+        LedgerConfig {
+            segment_max_bytes: 512,
+            fsync: FsyncPolicy::EveryN(4),
+        }
+    }
+
+    fn sequences(iter: LedgerIter) -> Vec<u64> {
+        iter.map(|entry| entry.expect("valid frame").sequence)
+            .collect()
+    }
+
+    #[test]
+    fn entries_survive_reopen_and_iterate_from_any_sequence() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        {
+            let ledger = Ledger::open(dir.path(), small_segments()).expect("open");
+            for sequence in 1..=20 {
+                ledger.append(&entry(sequence)).expect("append");
+            }
+            assert!(ledger.segments().len() > 2, "small segments roll over");
+        }
+
+        let ledger = Ledger::open(dir.path(), small_segments()).expect("reopen");
+        assert_eq!(ledger.last_sequence(), Some(20));
+        assert_eq!(sequences(ledger.iter_from(0)), (1..=20).collect::<Vec<_>>());
+        assert_eq!(
+            sequences(ledger.iter_from(13)),
+            (13..=20).collect::<Vec<_>>()
+        );
+        assert!(sequences(ledger.iter_from(21)).is_empty());
+
+        ledger.append(&entry(21)).expect("append after reopen");
+        assert_eq!(sequences(ledger.iter_from(20)), vec![20, 21]);
+    }
+
+    #[test]
+    fn per_repo_sequences_are_rebuilt_on_open() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        {
+            let ledger = Ledger::open(dir.path(), small_segments()).expect("open");
+            for sequence in 1..=6 {
+                let mut next = entry(sequence);
+                next.repo_id = format!("repo-{}", sequence % 2);
+                ledger.append(&next).expect("append");
+            }
+            assert_eq!(ledger.max_sequence("repo-1"), Some(5));
+        }
+        let ledger = Ledger::open(dir.path(), small_segments()).expect("reopen");
+        assert_eq!(ledger.max_sequence("repo-0"), Some(6));
+        assert_eq!(ledger.max_sequence("repo-1"), Some(5));
+        assert_eq!(ledger.max_sequence("repo-2"), None);
+        assert_eq!(ledger.repo_sequences().repos().count(), 2);
+    }
+
+    #[test]
+    fn torn_tail_is_truncated_on_open() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let last_segment = {
+            let ledger = Ledger::open(dir.path(), LedgerConfig::default()).expect("open");
+            for sequence in 1..=3 {
+                ledger.append(&entry(sequence)).expect("append");
+            }
+            ledger.segments().pop().expect("segment")
+        };
+        // Simulate a crash half-way through writing the fourth frame.
+        let frame = encode_frame(&entry(4), None).expect("frame");
+        OpenOptions::new()
+            .append(true)
+            .open(&last_segment)
+            .and_then(|mut file| file.write_all(&frame[..frame.len() / 2]))
+            .expect("write torn frame");
+
+        let ledger = Ledger::open(dir.path(), LedgerConfig::default()).expect("recover");
+        assert_eq!(ledger.last_sequence(), Some(3));
+        ledger.append(&entry(4)).expect("append after recovery");
+        assert_eq!(sequences(ledger.iter_from(1)), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn corruption_in_a_sealed_segment_is_an_error() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let first_segment = {
+            let ledger = Ledger::open(dir.path(), small_segments()).expect("open");
+            for sequence in 1..=10 {
+                ledger.append(&entry(sequence)).expect("append");
+            }
+            ledger.segments()[0].clone()
+        };
+        let mut bytes = fs::read(&first_segment).expect("read");
+        let last = bytes.len() - 2;
+        bytes[last] ^= 0xff;
+        fs::write(&first_segment, bytes).expect("write");
+
+        match Ledger::open(dir.path(), small_segments()) {
+            Err(LedgerError::Corrupt { segment, .. }) => assert_eq!(segment, first_segment),
+            other => panic!("expected corruption, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn sequences_must_increase() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let ledger = Ledger::open(
+            dir.path(),
+            LedgerConfig {
+                fsync: FsyncPolicy::Never,
+                ..LedgerConfig::default()
+            },
+        )
+        .expect("open");
+        ledger.append(&entry(5)).expect("append");
+        assert!(matches!(
+            ledger.append(&entry(5)),
+            Err(LedgerError::OutOfOrder {
+                last: 5,
+                sequence: 5
+            })
+        ));
+        ledger.sync().expect("sync");
+    }
+
+    #[test]
+    fn old_segments_can_be_removed() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let ledger = Ledger::open(dir.path(), small_segments()).expect("open");
+        for sequence in 1..=20 {
+            ledger.append(&entry(sequence)).expect("append");
+        }
+        let before = ledger.segments().len();
+        let removed = ledger.remove_segments_before(15).expect("remove");
+        assert!(removed > 0);
+        assert_eq!(ledger.segments().len(), before - removed);
+        let remaining = sequences(ledger.iter_from(0));
+        assert!(remaining.first().is_some_and(|first| *first <= 15));
+        assert_eq!(remaining.last(), Some(&20));
+        assert_eq!(
+            ledger.remove_segments_before(u64::MAX).expect("remove"),
+            before - removed - 1
+        );
+    }
+
+    #[test]
+    fn sealed_frames_need_the_cipher_and_are_never_truncated() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let config = LedgerConfig::default();
+        {
+            let plain = Ledger::open(dir.path(), config).expect("open");
+            plain.append(&entry(1)).expect("plaintext append");
+        }
+        let segment = {
+            let ledger = Ledger::open_with_cipher(dir.path(), config, Arc::new(XorCipher(0x5a)))
+                .expect("open sealed");
+            ledger.append(&entry(2)).expect("sealed append");
+            // Frames written before encryption was enabled stay readable.
+            assert_eq!(sequences(ledger.iter_from(0)), vec![1, 2]);
+            ledger.segments().pop().expect("segment")
+        };
+        let bytes = fs::read(&segment).expect("read");
+        let needle = b"after-2";
+        assert!(!bytes.windows(needle.len()).any(|window| window == needle));
+
+        for cipher in [None, Some(XorCipher(0x11))] {
+            let opened = match cipher {
+                None => Ledger::open(dir.path(), config),
+                Some(cipher) => Ledger::open_with_cipher(dir.path(), config, Arc::new(cipher)),
+            };
+            assert!(matches!(opened, Err(LedgerError::Cipher(_))));
+            assert_eq!(fs::read(&segment).expect("read").len(), bytes.len());
+        }
+        let ledger = Ledger::open_with_cipher(dir.path(), config, Arc::new(XorCipher(0x5a)))
+            .expect("reopen");
+        assert_eq!(ledger.last_sequence(), Some(2));
+    }
+
+    #[tokio::test]
+    async fn subscription_tails_new_appends_across_segments() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let ledger = Arc::new(Ledger::open(dir.path(), small_segments()).expect("open"));
+        for sequence in 1..=3 {
+            ledger.append(&entry(sequence)).expect("append");
+        }
+        let mut subscription = ledger.subscribe(2);
+        for expected in [2, 3] {
+            let next = subscription.next().await.expect("entry").expect("read");
+            assert_eq!(next.sequence, expected);
+        }
+
+        let writer = tokio::spawn({
+            let ledger = Arc::clone(&ledger);
+            async move {
+                for sequence in 4..=12 {
+                    tokio::task::yield_now().await;
+                    ledger.append(&entry(sequence)).expect("append");
+                }
+            }
+        });
+        let mut tailed = Vec::new();
+        while tailed.len() < 9 {
+            let next = subscription.next().await.expect("entry").expect("read");
+            tailed.push(next.sequence);
+        }
+        writer.await.expect("writer");
+        assert_eq!(tailed, (4..=12).collect::<Vec<_>>());
+        assert!(ledger.segments().len() > 1);
+    }
+
+    #[tokio::test]
+    async fn subscription_ends_after_ledger_is_dropped() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let ledger = Ledger::open(dir.path(), LedgerConfig::default()).expect("open");
+        let mut subscription = ledger.subscribe(0);
+        ledger.append(&entry(1)).expect("append");
+        drop(ledger);
+        let first = subscription.next().await.expect("entry").expect("read");
+        assert_eq!(first.sequence, 1);
+        assert!(subscription.next().await.is_none());
+    }
+}
@@ -0,0 +1,60 @@
+//! Per-record `upsert` against `upsert_batch` for an embedding-sized batch,
+//! in memory and on the filesystem.
+//!
+//! Run with `cargo bench -p storage-vector --bench batch_upsert`.
+
+use std::time::{Duration, Instant};
+
+use storage_vector::store::{Store, VectorStore};
+
+const RECORDS: usize = 2_000;
+const BATCH: usize = 256;
+
+fn records() -> Vec<(String, Vec<u8>)> {
+    (0..RECORDS)
+        .map(|i| (format!("chunk-{i}"), vec![(i % 251) as u8; 1024]))
+        .collect()
+}
+
+fn per_record(store: &VectorStore, records: &[(String, Vec<u8>)]) -> Duration {
+    let started = Instant::now();
+    for (key, payload) in records {
+        store.upsert("bench", key, payload).expect("upsert");
+    }
+    started.elapsed()
+}
+
+fn batched(store: &VectorStore, records: &[(String, Vec<u8>)]) -> Duration {
+    let started = Instant::now();
+    for batch in records.chunks(BATCH) {
+        store.upsert_batch("bench", batch).expect("upsert_batch");
+    }
+    started.elapsed()
+}
+
+fn report(label: &str, single: Duration, batch: Duration) {
+    println!(
+        "{label}: {RECORDS} records per-record {single:?}, batches of {BATCH} {batch:?} ({:.1}x)",
+        single.as_secs_f64() / batch.as_secs_f64()
+    );
+}
+
+fn main() {
+    let records = records();
+    report(
+        "memory",
+        per_record(&VectorStore::new(), &records),
+        batched(&VectorStore::new(), &records),
+    );
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    let single = per_record(
+        &VectorStore::with_fs_root(dir.path().join("single")),
+        &records,
+    );
+    let batch = batched(
+        &VectorStore::with_fs_root(dir.path().join("batch")),
+        &records,
+    );
+    report("filesystem", single, batch);
+}
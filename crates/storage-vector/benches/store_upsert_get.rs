@@ -0,0 +1,74 @@
+//! Single-record `upsert` and `get` against an in-memory store, plain and,
+//! with the `encryption` feature, sealed with AES-GCM.
+//!
+//! Run with `cargo bench -p storage-vector --features encryption --bench store_upsert_get`.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use storage_vector::store::{Store, VectorStore};
+
+const PAYLOAD_SIZES: [usize; 2] = [1024, 64 * 1024];
+const KEYS: usize = 1_000;
+
+fn stores() -> Vec<(&'static str, VectorStore)> {
+    #[cfg_attr(not(feature = "encryption"), allow(unused_mut))]
+    let mut stores = vec![("plain", VectorStore::new())];
+    #[cfg(feature = "encryption")]
+    {
+        use std::sync::Arc;
+
+        use storage_vector::encryption::aes_gcm::AesGcmEncrypter;
+        use storage_vector::kms::InMemoryKeyManager;
+
+        let store = VectorStore::builder()
+            .with_encrypter(Arc::new(AesGcmEncrypter::new()))
+            .with_key_manager(Arc::new(InMemoryKeyManager::new_with_secret(
+                "bench", [7u8; 32],
+            )))
+            .build();
+        stores.push(("encrypted", store));
+    }
+    stores
+}
+
+fn store_upsert_get(c: &mut Criterion) {
+    let mut group = c.benchmark_group("store");
+    for (label, store) in stores() {
+        for size in PAYLOAD_SIZES {
+            let payload = vec![0x5a; size];
+            let keys: Vec<String> = (0..KEYS).map(|i| format!("chunk-{i}")).collect();
+            for key in &keys {
+                store.upsert("bench", key, &payload).expect("seed");
+            }
+            group.throughput(Throughput::Bytes(size as u64));
+
+            let mut next = 0;
+            group.bench_with_input(
+                BenchmarkId::new(format!("upsert/{label}"), size),
+                &payload,
+                |b, payload| {
+                    b.iter(|| {
+                        next = (next + 1) % KEYS;
+                        store
+                            .upsert("bench", &keys[next], black_box(payload))
+                            .expect("upsert")
+                    });
+                },
+            );
+            group.bench_function(BenchmarkId::new(format!("get/{label}"), size), |b| {
+                b.iter(|| {
+                    next = (next + 1) % KEYS;
+                    store
+                        .get("bench", black_box(&keys[next]))
+                        .expect("get")
+                        .expect("present")
+                });
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, store_upsert_get);
+criterion_main!(benches);
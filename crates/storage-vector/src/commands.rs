@@ -0,0 +1,185 @@
+//! Router commands for switching a store between normal operation and
+//! modes that refuse writes, and the store's sections of the `status` and
+//! `config.reload` commands.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use runtime_router::{
+    CommandHandler, HandlerRouter, Reloadable, RouterError, RouterResponse, SessionContext,
+    StatusProvider,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{QuotaLimits, StoreMode, VectorStore};
+
+/// Command reporting the store mode, or switching it (`{ mode }`, one of
+/// `normal`, `read_only` or `maintenance`).
+pub const MODE_COMMAND: &str = "storage.mode";
+
+/// Capability required to switch the store mode.
+pub const STORAGE_ADMIN_CAPABILITY: &str = "storage.admin";
+
+/// Register the storage commands for `store` on `router`.
+pub fn register_commands(router: &mut HandlerRouter, store: Arc<VectorStore>) {
+    router.register_with_capabilities(
+        MODE_COMMAND,
+        vec![STORAGE_ADMIN_CAPABILITY.into()],
+        Arc::new(ModeHandler { store }),
+    );
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ModeRequest {
+    #[serde(default)]
+    mode: Option<StoreMode>,
+}
+
+struct ModeHandler {
+    store: Arc<VectorStore>,
+}
+
+#[async_trait]
+impl CommandHandler for ModeHandler {
+    async fn handle(
+        &self,
+        ctx: &SessionContext,
+        payload: Value,
+    ) -> Result<RouterResponse, RouterError> {
+        let request: ModeRequest = if payload.is_null() {
+            ModeRequest::default()
+        } else {
+            serde_json::from_value(payload).map_err(|err| RouterError::InvalidRequest {
+                detail: err.to_string(),
+            })?
+        };
+        let Some(mode) = request.mode else {
+            return Ok(RouterResponse::ok(json!({ "mode": self.store.mode() })));
+        };
+        let previous = self
+            .store
+            .set_mode(mode)
+            .map_err(|err| RouterError::Internal {
+                detail: err.to_string(),
+            })?;
+        tracing::info!(
+            principal = %ctx.principal,
+            from = %previous,
+            to = %mode,
+            "store mode switched"
+        );
+        Ok(RouterResponse::ok(
+            json!({ "mode": mode, "previous": previous }),
+        ))
+    }
+
+    fn compensable(&self) -> bool {
+        true
+    }
+
+    /// Switch back to the mode a switch replaced; a query needs no undo.
+    async fn compensate(
+        &self,
+        ctx: &SessionContext,
+        _payload: Value,
+        response: &RouterResponse,
+    ) -> Result<(), RouterError> {
+        let Some(previous) = response.payload.get("previous") else {
+            return Ok(());
+        };
+        let previous: StoreMode =
+            serde_json::from_value(previous.clone()).map_err(|err| RouterError::Internal {
+                detail: err.to_string(),
+            })?;
+        self.store
+            .set_mode(previous)
+            .map_err(|err| RouterError::Internal {
+                detail: err.to_string(),
+            })?;
+        tracing::info!(
+            principal = %ctx.principal,
+            to = %previous,
+            "store mode switch rolled back"
+        );
+        Ok(())
+    }
+}
+
+/// Reports `{ mode, records, bytes, repos: [StorageUsage] }`, the latter
+/// carrying each repository's quota, for the `status` command.
+#[async_trait]
+impl StatusProvider for VectorStore {
+    async fn report(&self) -> Result<Value, RouterError> {
+        let repos = self.usage_all().map_err(|err| RouterError::Internal {
+            detail: err.to_string(),
+        })?;
+        Ok(json!({
+            "mode": self.mode(),
+            "records": repos.iter().map(|usage| usage.records).sum::<u64>(),
+            "bytes": repos.iter().map(|usage| usage.bytes).sum::<u64>(),
+            "repos": repos,
+        }))
+    }
+}
+
+/// Byte and record limits as `config.reload` carries them.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct QuotaReload {
+    #[serde(default)]
+    bytes_max: Option<u64>,
+    #[serde(default)]
+    entries_max: Option<u64>,
+}
+
+impl From<QuotaReload> for QuotaLimits {
+    fn from(quota: QuotaReload) -> Self {
+        Self {
+            bytes_max: quota.bytes_max,
+            entries_max: quota.entries_max,
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StoreReload {
+    #[serde(default)]
+    quota: Option<QuotaReload>,
+    #[serde(default)]
+    repo_quotas: HashMap<String, QuotaReload>,
+}
+
+impl StoreReload {
+    fn parse(config: Value) -> Result<Self, RouterError> {
+        serde_json::from_value(config).map_err(|err| RouterError::InvalidRequest {
+            detail: err.to_string(),
+        })
+    }
+}
+
+/// Reloads `{ quota?: { bytes_max?, entries_max? }, repo_quotas?: { <repo>:
+/// { bytes_max?, entries_max? } } }` through [`VectorStore::set_quotas`];
+/// an absent field lifts those quotas.
+impl Reloadable for VectorStore {
+    fn check(&self, config: &Value) -> Result<(), RouterError> {
+        StoreReload::parse(config.clone()).map(|_| ())
+    }
+
+    fn reload(&self, config: Value) -> Result<(), RouterError> {
+        let reload = StoreReload::parse(config)?;
+        let repo_quotas = reload
+            .repo_quotas
+            .into_iter()
+            .map(|(repo_id, quota)| (repo_id, quota.into()))
+            .collect();
+        self.set_quotas(reload.quota.map(Into::into), repo_quotas)
+            .map_err(|err| RouterError::Internal {
+                detail: err.to_string(),
+            })
+    }
+}
@@ -1,13 +1,113 @@
 //! Minimal configuration placeholders for the vector store skeleton.
 
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::QuotaLimits;
+
 #[derive(Debug, Clone, Default)]
 pub struct StoreConfig {
     pub repo_scope: Option<String>,
+    /// Byte and record limits (`bytes_max`, `entries_max`) applied to every
+    /// repository without an entry in `repo_quotas`.
+    pub quota: Option<QuotaLimits>,
+    pub repo_quotas: HashMap<String, QuotaLimits>,
+    /// Lifetime of records written without a TTL of their own to every
+    /// repository without an entry in `repo_ttls`; `None` keeps them.
+    pub default_ttl: Option<Duration>,
+    pub repo_ttls: HashMap<String, Duration>,
+    /// Store each distinct payload once, shared by every key holding it.
+    /// Ignored when encryption is configured.
+    pub dedup: bool,
+    /// Rotation thresholds checked before each payload is sealed.
+    /// Ignored without encryption.
+    pub rotation: RotationPolicy,
+    /// Name repository directories and payload files by a keyed hash of
+    /// their ids instead of the ids themselves. Ignored without encryption
+    /// or a filesystem root. Cannot be changed for an existing root: a store
+    /// whose entries are named the other way fails with
+    /// [`StoreError::Integrity`](crate::error::StoreError::Integrity).
+    pub hashed_paths: bool,
+    /// Index repository vectors in an HNSW graph instead of scanning them all.
+    #[cfg(feature = "hnsw")]
+    pub hnsw: Option<crate::search::HnswConfig>,
+}
+
+/// Layout of a [`crate::store::segment::SegmentStore`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SegmentConfig {
+    /// Size at which the active segment is sealed and a new one started.
+    pub max_segment_bytes: u64,
+    /// Sealed segments whose live share drops below this fraction are
+    /// rewritten by compaction.
+    pub compact_below: f64,
+}
+
+impl Default for SegmentConfig {
+    fn default() -> Self {
+        Self {
+            max_segment_bytes: 64 * 1024 * 1024,
+            compact_below: 0.5,
+        }
+    }
+}
+
+/// Schedule and IO budget of background maintenance, started with
+/// `spawn_maintenance` on a [`crate::store::VectorStore`] or
+/// [`crate::store::segment::SegmentStore`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaintenanceConfig {
+    /// Pause between the end of one pass and the start of the next.
+    pub every: Duration,
+    /// Staging files untouched for this long were left by an interrupted
+    /// write and are removed.
+    pub tmp_max_age: Duration,
+    /// Bytes a pass may delete or rewrite per second; `None` runs
+    /// unthrottled.
+    pub max_bytes_per_second: Option<u64>,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            every: Duration::from_secs(10 * 60),
+            tmp_max_age: Duration::from_secs(60 * 60),
+            max_bytes_per_second: None,
+        }
+    }
 }
 
-/// Minimal rotation policy placeholder for feature-gated encryption flows.
+/// When the write-ahead log of a [`crate::store::VectorStore`] is synced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FsyncPolicy {
+    /// Sync the log and the payload files of every batch: an acknowledged
+    /// batch survives a crash, and an interrupted one is redone whole.
+    #[default]
+    Always,
+    /// Sync the log at most once per interval, on the first write after it
+    /// elapses. A crash can lose the batches written since, including
+    /// payload files of a batch whose log record did not reach disk.
+    Interval(Duration),
+    /// Leave flushing to the operating system.
+    Never,
+}
+
+/// When the key sealing payloads is replaced; no threshold means never.
 #[derive(Debug, Clone, Default)]
 pub struct RotationPolicy {
+    /// Payloads a key may seal before it is rotated.
     pub max_uses: Option<u64>,
+    /// Seconds a key may seal payloads for before it is rotated.
     pub rotate_after_seconds: Option<u64>,
 }
+
+impl RotationPolicy {
+    /// Whether a key that sealed `uses` payloads and was created `age` ago
+    /// is due for rotation.
+    pub fn is_due(&self, uses: u64, age: Duration) -> bool {
+        self.max_uses.is_some_and(|max| uses >= max)
+            || self
+                .rotate_after_seconds
+                .is_some_and(|seconds| age >= Duration::from_secs(seconds))
+    }
+}
@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use crate::store::StoreMode;
+
 #[derive(Debug, Error)]
 pub enum StoreError {
     #[error("I/O error: {0}")]
@@ -16,6 +18,11 @@ pub enum StoreError {
     #[cfg(feature = "encryption")]
     #[error("key manager error: {0}")]
     Key(String),
+    #[error("vector dimension mismatch: expected {expected}, found {found}")]
+    DimensionMismatch { expected: usize, found: usize },
     #[error("unsupported operation: {0}")]
     Unsupported(String),
+    /// A write was refused because the store is not in [`StoreMode::Normal`].
+    #[error("store is in {0} mode; writes are refused")]
+    ReadOnly(StoreMode),
 }
@@ -0,0 +1,273 @@
+//! Key manager over a passphrase-protected keystore file.
+//!
+//! The keystore is JSON holding every key sealed with AES-256-GCM under a
+//! key-encryption key derived from a passphrase with Argon2id, so the file
+//! alone reveals no key material. Each sealed key is bound to its id as
+//! associated data.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use super::{created_instant, new_key_id, random_key, unix_now, KeyManager, KeyRing, KeyScope};
+use super::{KeyUsage, RotationPolicy};
+use crate::encryption::KeyHandle;
+
+const KEYSTORE_VERSION: u32 = 1;
+
+/// Argon2id cost of deriving the key-encryption key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeystoreKdf {
+    /// Memory in KiB.
+    pub m_cost: u32,
+    /// Passes over memory.
+    pub t_cost: u32,
+    /// Lanes.
+    pub p_cost: u32,
+}
+
+impl Default for KeystoreKdf {
+    /// The Argon2 crate's defaults: 19 MiB, two passes, one lane.
+    fn default() -> Self {
+        Self {
+            m_cost: argon2::Params::DEFAULT_M_COST,
+            t_cost: argon2::Params::DEFAULT_T_COST,
+            p_cost: argon2::Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Keystore {
+    version: u32,
+    kdf: KeystoreKdf,
+    /// Base64 salt of the key derivation.
+    salt: String,
+    current: String,
+    keys: Vec<SealedKey>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SealedKey {
+    key_id: String,
+    /// Unix seconds, so rotation by age survives a restart.
+    created: u64,
+    /// Base64 nonce and ciphertext with its tag.
+    nonce: String,
+    sealed: String,
+}
+
+/// Key manager whose keys live sealed in a keystore file. Rotations are
+/// written back to the file; use counts start at zero each time it is
+/// opened.
+pub struct FileKeyManager {
+    path: PathBuf,
+    kek: Zeroizing<[u8; 32]>,
+    state: Mutex<(Keystore, KeyRing)>,
+}
+
+fn derive_kek(
+    passphrase: &[u8],
+    salt: &[u8],
+    kdf: KeystoreKdf,
+) -> Result<Zeroizing<[u8; 32]>, String> {
+    let params = argon2::Params::new(kdf.m_cost, kdf.t_cost, kdf.p_cost, Some(32))
+        .map_err(|e| e.to_string())?;
+    let argon = argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+    let mut kek = Zeroizing::new([0u8; 32]);
+    argon
+        .hash_password_into(passphrase, salt, kek.as_mut())
+        .map_err(|e| e.to_string())?;
+    Ok(kek)
+}
+
+fn seal_key(kek: &[u8; 32], key_id: &str, key: &[u8; 32]) -> Result<SealedKey, String> {
+    let cipher = Aes256Gcm::new_from_slice(kek).map_err(|e| e.to_string())?;
+    let mut nonce = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce);
+    #[allow(deprecated)]
+    let sealed = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: key,
+                aad: key_id.as_bytes(),
+            },
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(SealedKey {
+        key_id: key_id.to_string(),
+        created: unix_now(),
+        nonce: BASE64.encode(nonce),
+        sealed: BASE64.encode(sealed),
+    })
+}
+
+fn open_key(kek: &[u8; 32], sealed: &SealedKey) -> Result<Zeroizing<[u8; 32]>, String> {
+    let cipher = Aes256Gcm::new_from_slice(kek).map_err(|e| e.to_string())?;
+    let nonce = BASE64.decode(&sealed.nonce).map_err(|e| e.to_string())?;
+    let ciphertext = BASE64.decode(&sealed.sealed).map_err(|e| e.to_string())?;
+    if nonce.len() != 12 {
+        return Err(format!("key {} has a malformed nonce", sealed.key_id));
+    }
+    #[allow(deprecated)]
+    let plain = Zeroizing::new(
+        cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: sealed.key_id.as_bytes(),
+                },
+            )
+            .map_err(|_| {
+                format!(
+                    "cannot unseal key {}: wrong passphrase or damaged keystore",
+                    sealed.key_id
+                )
+            })?,
+    );
+    let key: [u8; 32] = plain
+        .as_slice()
+        .try_into()
+        .map_err(|_| format!("key {} is not 32 bytes", sealed.key_id))?;
+    Ok(Zeroizing::new(key))
+}
+
+fn save(path: &Path, keystore: &Keystore) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(keystore).map_err(|e| e.to_string())?;
+    crate::store::fs::atomic_write(path, &json).map_err(|e| e.to_string())?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+impl FileKeyManager {
+    /// Create a keystore at `path` holding one random key, `first_id`,
+    /// sealed under `passphrase`. Fails if the file exists.
+    pub fn create(
+        path: impl Into<PathBuf>,
+        passphrase: impl AsRef<[u8]>,
+        first_id: impl Into<String>,
+        kdf: KeystoreKdf,
+    ) -> Result<Self, String> {
+        let path = path.into();
+        if path.exists() {
+            return Err(format!("keystore {} already exists", path.display()));
+        }
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let kek = derive_kek(passphrase.as_ref(), &salt, kdf)?;
+        let first_id = first_id.into();
+        let key = random_key();
+        let keystore = Keystore {
+            version: KEYSTORE_VERSION,
+            kdf,
+            salt: BASE64.encode(salt),
+            current: first_id.clone(),
+            keys: vec![seal_key(&kek, &first_id, &key)?],
+        };
+        save(&path, &keystore)?;
+        Ok(Self {
+            path,
+            kek,
+            state: Mutex::new((keystore, KeyRing::new(first_id, key))),
+        })
+    }
+
+    /// Open the keystore at `path`, unsealing every key with `passphrase`.
+    pub fn open(path: impl Into<PathBuf>, passphrase: impl AsRef<[u8]>) -> Result<Self, String> {
+        let path = path.into();
+        let json = std::fs::read(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let keystore: Keystore = serde_json::from_slice(&json).map_err(|e| e.to_string())?;
+        if keystore.version != KEYSTORE_VERSION {
+            return Err(format!("keystore version {}", keystore.version));
+        }
+        let salt = BASE64.decode(&keystore.salt).map_err(|e| e.to_string())?;
+        let kek = derive_kek(passphrase.as_ref(), &salt, keystore.kdf)?;
+        let mut ring = KeyRing::empty(keystore.current.clone());
+        for sealed in &keystore.keys {
+            ring.insert(
+                sealed.key_id.clone(),
+                open_key(&kek, sealed)?,
+                created_instant(sealed.created),
+            );
+        }
+        ring.current()?;
+        Ok(Self {
+            path,
+            kek,
+            state: Mutex::new((keystore, ring)),
+        })
+    }
+
+    /// Add a random key and make it current, writing it to the keystore
+    /// before it is used.
+    pub fn rotate(&self) -> Result<KeyHandle, String> {
+        let mut state = self.state.lock().map_err(|e| e.to_string())?;
+        self.rotate_locked(&mut state)
+    }
+
+    fn rotate_locked(&self, state: &mut (Keystore, KeyRing)) -> Result<KeyHandle, String> {
+        let (keystore, ring) = state;
+        let id = new_key_id();
+        let key = random_key();
+        keystore.keys.push(seal_key(&self.kek, &id, &key)?);
+        keystore.current = id.clone();
+        if let Err(e) = save(&self.path, keystore) {
+            keystore.keys.pop();
+            keystore.current = ring.current.clone();
+            return Err(e);
+        }
+        ring.insert(id.clone(), key, std::time::Instant::now());
+        ring.current = id;
+        ring.current()
+    }
+}
+
+impl KeyManager for FileKeyManager {
+    fn current(&self, _scope: &KeyScope) -> Result<KeyHandle, String> {
+        self.state.lock().map_err(|e| e.to_string())?.1.current()
+    }
+
+    fn rotate_if_needed(
+        &self,
+        _scope: &KeyScope,
+        policy: &RotationPolicy,
+    ) -> Result<Option<KeyHandle>, String> {
+        KeyRing::rotate_when_due(
+            &self.state,
+            |state| Ok(state.1.is_due(policy)),
+            |state| self.rotate_locked(state),
+        )
+    }
+
+    fn get(&self, key_id: &str) -> Result<KeyHandle, String> {
+        self.state
+            .lock()
+            .map_err(|e| e.to_string())?
+            .1
+            .handle(key_id)
+    }
+
+    fn record_use(&self, key_id: &str) {
+        if let Ok(mut state) = self.state.lock() {
+            state.1.record_use(key_id);
+        }
+    }
+
+    fn usage(&self, key_id: &str) -> Option<KeyUsage> {
+        self.state.lock().ok()?.1.usage.get(key_id).copied()
+    }
+}
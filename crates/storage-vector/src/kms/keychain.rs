@@ -0,0 +1,188 @@
+//! Key manager over the operating system's credential store.
+//!
+//! Keys never touch configuration or the filesystem: each is a secret of
+//! its own in the credential store (macOS Keychain, Windows Credential
+//! Manager, the Linux kernel keyring), and a further secret names the
+//! current key. Keys are loaded on first use and cached in memory.
+
+use std::sync::Mutex;
+use std::time::Instant;
+
+use zeroize::Zeroizing;
+
+use super::{
+    created_instant, new_key_id, random_key, unix_now, KeyManager, KeyRing, KeyScope, KeyUsage,
+};
+use crate::config::RotationPolicy;
+use crate::encryption::KeyHandle;
+
+/// Secret naming the current key.
+const CURRENT: &str = "current";
+
+/// Named secrets a [`KeychainKeyManager`] keeps its keys in.
+pub trait SecretStore: Send + Sync {
+    /// The secret stored under `name`, or `None` if there is none.
+    fn load(&self, name: &str) -> Result<Option<Vec<u8>>, String>;
+    fn store(&self, name: &str, secret: &[u8]) -> Result<(), String>;
+}
+
+/// The platform credential store, with every secret under `service`.
+pub struct OsKeychain {
+    service: String,
+}
+
+impl OsKeychain {
+    pub fn new(service: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+        }
+    }
+
+    fn entry(&self, name: &str) -> Result<keyring::Entry, String> {
+        keyring::Entry::new(&self.service, name).map_err(|e| e.to_string())
+    }
+}
+
+impl SecretStore for OsKeychain {
+    fn load(&self, name: &str) -> Result<Option<Vec<u8>>, String> {
+        match self.entry(name)?.get_secret() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    fn store(&self, name: &str, secret: &[u8]) -> Result<(), String> {
+        self.entry(name)?
+            .set_secret(secret)
+            .map_err(|e| e.to_string())
+    }
+}
+
+fn key_name(key_id: &str) -> String {
+    format!("key:{key_id}")
+}
+
+/// A stored key: its 32 bytes, then its creation time as little-endian
+/// Unix seconds.
+fn encode_key(key: &[u8; 32], created: u64) -> Zeroizing<Vec<u8>> {
+    let mut secret = Zeroizing::new(Vec::with_capacity(40));
+    secret.extend_from_slice(key);
+    secret.extend_from_slice(&created.to_le_bytes());
+    secret
+}
+
+fn decode_key(key_id: &str, secret: &[u8]) -> Result<(Zeroizing<[u8; 32]>, Instant), String> {
+    if secret.len() != 40 {
+        return Err(format!("stored key {key_id} is malformed"));
+    }
+    let mut key = Zeroizing::new([0u8; 32]);
+    key.copy_from_slice(&secret[..32]);
+    let mut created = [0u8; 8];
+    created.copy_from_slice(&secret[32..]);
+    Ok((key, created_instant(u64::from_le_bytes(created))))
+}
+
+/// Key manager whose keys live in a [`SecretStore`], by default the
+/// platform credential store.
+pub struct KeychainKeyManager<S: SecretStore = OsKeychain> {
+    secrets: S,
+    ring: Mutex<KeyRing>,
+}
+
+impl KeychainKeyManager<OsKeychain> {
+    /// Keys of `service` in the platform credential store; see
+    /// [`KeychainKeyManager::with_store`].
+    pub fn new(service: impl Into<String>) -> Result<Self, String> {
+        Self::with_store(OsKeychain::new(service))
+    }
+}
+
+impl<S: SecretStore> KeychainKeyManager<S> {
+    /// Load the current key of `secrets`, creating a random one under a
+    /// `k-<hex>` id when the store holds none yet.
+    pub fn with_store(secrets: S) -> Result<Self, String> {
+        let current = match secrets.load(CURRENT)? {
+            Some(id) => String::from_utf8(id).map_err(|_| "current key id not utf8")?,
+            None => {
+                let id = new_key_id();
+                secrets.store(&key_name(&id), &encode_key(&random_key(), unix_now()))?;
+                secrets.store(CURRENT, id.as_bytes())?;
+                id
+            }
+        };
+        let secret = secrets
+            .load(&key_name(&current))?
+            .map(Zeroizing::new)
+            .ok_or_else(|| format!("current key {current} is missing"))?;
+        let (key, created) = decode_key(&current, &secret)?;
+        let mut ring = KeyRing::empty(current.clone());
+        ring.insert(current, key, created);
+        Ok(Self {
+            secrets,
+            ring: Mutex::new(ring),
+        })
+    }
+
+    /// Store a random key and make it current.
+    pub fn rotate(&self) -> Result<KeyHandle, String> {
+        let mut ring = self.ring.lock().map_err(|e| e.to_string())?;
+        self.rotate_locked(&mut ring)
+    }
+
+    fn rotate_locked(&self, ring: &mut KeyRing) -> Result<KeyHandle, String> {
+        let id = new_key_id();
+        let key = random_key();
+        self.secrets
+            .store(&key_name(&id), &encode_key(&key, unix_now()))?;
+        // The key is stored before it is named current, so a failure here
+        // leaves the previous key current.
+        self.secrets.store(CURRENT, id.as_bytes())?;
+        ring.insert(id.clone(), key, Instant::now());
+        ring.current = id;
+        ring.current()
+    }
+}
+
+impl<S: SecretStore> KeyManager for KeychainKeyManager<S> {
+    fn current(&self, _scope: &KeyScope) -> Result<KeyHandle, String> {
+        self.ring.lock().map_err(|e| e.to_string())?.current()
+    }
+
+    fn rotate_if_needed(
+        &self,
+        _scope: &KeyScope,
+        policy: &RotationPolicy,
+    ) -> Result<Option<KeyHandle>, String> {
+        KeyRing::rotate_when_due(
+            &self.ring,
+            |ring| Ok(ring.is_due(policy)),
+            |ring| self.rotate_locked(ring),
+        )
+    }
+
+    /// Keys other than the current one are loaded on first use.
+    fn get(&self, key_id: &str) -> Result<KeyHandle, String> {
+        let mut ring = self.ring.lock().map_err(|e| e.to_string())?;
+        if !ring.keys.contains_key(key_id) {
+            let secret = self
+                .secrets
+                .load(&key_name(key_id))?
+                .map(Zeroizing::new)
+                .ok_or_else(|| "unknown key id".to_string())?;
+            let (key, created) = decode_key(key_id, &secret)?;
+            ring.insert(key_id.to_string(), key, created);
+        }
+        ring.handle(key_id)
+    }
+
+    fn record_use(&self, key_id: &str) {
+        if let Ok(mut ring) = self.ring.lock() {
+            ring.record_use(key_id);
+        }
+    }
+
+    fn usage(&self, key_id: &str) -> Option<KeyUsage> {
+        self.ring.lock().ok()?.usage.get(key_id).copied()
+    }
+}
@@ -5,14 +5,64 @@ use crate::encryption::KeyHandle;
 use rand::{rngs::OsRng, RngCore};
 use std::collections::HashMap;
 use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use zeroize::Zeroizing;
+
+#[cfg(feature = "external-kms")]
+mod external;
+#[cfg(feature = "file-keystore")]
+mod file;
+#[cfg(feature = "keychain")]
+mod keychain;
+mod repo;
+
+#[cfg(feature = "kms-http")]
+pub use external::ReqwestPost;
+#[cfg(feature = "external-kms")]
+pub use external::{
+    AwsCredentials, AwsKms, DataKey, ExternalKms, HttpPost, HttpReply, KmsKeyManager, VaultTransit,
+};
+#[cfg(feature = "file-keystore")]
+pub use file::{FileKeyManager, KeystoreKdf};
+#[cfg(feature = "keychain")]
+pub use keychain::{KeychainKeyManager, OsKeychain, SecretStore};
+pub use repo::RepoKeyManager;
 
 #[derive(Debug, Clone)]
 pub struct KeyScope {
     pub repo_id: String,
 }
 
+/// How much a key has been used since it was created.
+#[derive(Debug, Clone, Copy)]
+pub struct KeyUsage {
+    /// Payloads sealed under the key.
+    pub uses: u64,
+    pub created: Instant,
+}
+
+impl KeyUsage {
+    fn new() -> Self {
+        Self::created_at(Instant::now())
+    }
+
+    fn created_at(created: Instant) -> Self {
+        Self { uses: 0, created }
+    }
+
+    /// Whether a key used as `usage` says is due under `policy`. Keys with
+    /// no usage on record are due.
+    fn is_due(usage: Option<&Self>, policy: &RotationPolicy) -> bool {
+        usage.map_or(true, |used| {
+            policy.is_due(used.uses, used.created.elapsed())
+        })
+    }
+}
+
 pub trait KeyManager: Send + Sync {
     fn current(&self, scope: &KeyScope) -> Result<KeyHandle, String>;
+    /// Replace the current key of `scope` when `policy` says it is due,
+    /// returning the new one. Replaced keys stay available to `get`.
     fn rotate_if_needed(
         &self,
         _scope: &KeyScope,
@@ -21,44 +71,163 @@ pub trait KeyManager: Send + Sync {
         Ok(None)
     }
     fn get(&self, key_id: &str) -> Result<KeyHandle, String>;
+    /// Count one payload sealed under `key_id`.
+    fn record_use(&self, _key_id: &str) {}
+    /// Usage of `key_id`, if the manager tracks it.
+    fn usage(&self, _key_id: &str) -> Option<KeyUsage> {
+        None
+    }
+}
+
+/// Random `k-<hex>` id for a key created by rotation.
+fn new_key_id() -> String {
+    format!("k-{:016x}", OsRng.next_u64())
+}
+
+fn random_key() -> Zeroizing<[u8; 32]> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    OsRng.fill_bytes(key.as_mut());
+    key
+}
+
+/// Whether `key_id` can name a file in the key directory; ids read from
+/// envelopes are untrusted.
+fn is_plain_id(key_id: &str) -> bool {
+    !key_id.is_empty()
+        && key_id
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_')
+}
+
+/// Seconds since the Unix epoch, as persisted key creation times.
+#[cfg_attr(
+    not(any(feature = "file-keystore", feature = "keychain")),
+    allow(dead_code)
+)]
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs())
 }
 
-/// Minimal in-memory key manager for tests.
+/// The instant a key persisted as created at `unix_secs` was created, so
+/// its age survives a restart.
+#[cfg_attr(
+    not(any(feature = "file-keystore", feature = "keychain")),
+    allow(dead_code)
+)]
+fn created_instant(unix_secs: u64) -> Instant {
+    let age = Duration::from_secs(unix_now().saturating_sub(unix_secs));
+    Instant::now().checked_sub(age).unwrap_or_else(Instant::now)
+}
+
+/// Key material and usage of a manager, with the key currently sealing.
+struct KeyRing {
+    current: String,
+    keys: HashMap<String, Zeroizing<[u8; 32]>>,
+    usage: HashMap<String, KeyUsage>,
+}
+
+impl KeyRing {
+    fn new(current: String, key: Zeroizing<[u8; 32]>) -> Self {
+        let mut ring = Self::empty(current.clone());
+        ring.insert(current, key, Instant::now());
+        ring
+    }
+
+    /// A ring naming `current` before its key is inserted.
+    #[cfg_attr(
+        not(any(feature = "file-keystore", feature = "keychain")),
+        allow(dead_code)
+    )]
+    fn empty(current: String) -> Self {
+        Self {
+            current,
+            keys: HashMap::new(),
+            usage: HashMap::new(),
+        }
+    }
+
+    fn insert(&mut self, id: String, key: Zeroizing<[u8; 32]>, created: Instant) {
+        self.usage.insert(id.clone(), KeyUsage::created_at(created));
+        self.keys.insert(id, key);
+    }
+
+    fn handle(&self, key_id: &str) -> Result<KeyHandle, String> {
+        let key = self
+            .keys
+            .get(key_id)
+            .ok_or_else(|| "unknown key id".to_string())?
+            .clone();
+        Ok(KeyHandle {
+            key_id: key_id.to_string(),
+            key_bytes: key,
+        })
+    }
+
+    fn current(&self) -> Result<KeyHandle, String> {
+        self.handle(&self.current)
+            .map_err(|_| "missing key bytes for current id".to_string())
+    }
+
+    fn is_due(&self, policy: &RotationPolicy) -> bool {
+        KeyUsage::is_due(self.usage.get(&self.current), policy)
+    }
+
+    /// Lock `state` and replace its current key with `rotate` when `due`
+    /// says so, returning the new key.
+    ///
+    /// The lock is held from the check through the rotation so concurrent
+    /// writers rotate once. Every manager's `rotate_if_needed` goes through
+    /// here.
+    fn rotate_when_due<S>(
+        state: &Mutex<S>,
+        due: impl FnOnce(&mut S) -> Result<bool, String>,
+        rotate: impl FnOnce(&mut S) -> Result<KeyHandle, String>,
+    ) -> Result<Option<KeyHandle>, String> {
+        let mut state = state.lock().map_err(|e| e.to_string())?;
+        if !due(&mut state)? {
+            return Ok(None);
+        }
+        rotate(&mut state).map(Some)
+    }
+
+    fn record_use(&mut self, key_id: &str) {
+        self.usage
+            .entry(key_id.to_string())
+            .or_insert_with(KeyUsage::new)
+            .uses += 1;
+    }
+}
+
+/// Minimal in-memory key manager for tests. One key serves every
+/// repository; wrap it in a [`RepoKeyManager`] for keys per repository.
 pub struct InMemoryKeyManager {
-    current_id: Mutex<String>,
-    keys: Mutex<HashMap<String, zeroize::Zeroizing<[u8; 32]>>>,
+    ring: Mutex<KeyRing>,
 }
 
 impl InMemoryKeyManager {
     pub fn new_with_secret(current_id: impl Into<String>, key: [u8; 32]) -> Self {
-        let id = current_id.into();
-        let mut map = HashMap::new();
-        map.insert(id.clone(), zeroize::Zeroizing::new(key));
         Self {
-            current_id: Mutex::new(id),
-            keys: Mutex::new(map),
+            ring: Mutex::new(KeyRing::new(current_id.into(), Zeroizing::new(key))),
         }
     }
 
     pub fn new_random(current_id: impl Into<String>) -> Self {
-        let id = current_id.into();
-        let mut key = [0u8; 32];
-        OsRng.fill_bytes(&mut key);
-        Self::new_with_secret(id, key)
+        Self {
+            ring: Mutex::new(KeyRing::new(current_id.into(), random_key())),
+        }
     }
 
     pub fn set_current(&self, id: impl Into<String>, key: [u8; 32]) {
         let id = id.into();
-        {
-            let mut keys = self.keys.lock().expect("key map mutex");
-            keys.insert(id.clone(), zeroize::Zeroizing::new(key));
-        }
-        *self.current_id.lock().expect("key mutex") = id;
+        let mut ring = self.ring.lock().expect("key ring mutex");
+        ring.insert(id.clone(), Zeroizing::new(key), Instant::now());
+        ring.current = id;
     }
 
     // Back-compat for tests: rotate id with a new random key
     pub fn set_current_id(&self, id: impl Into<String>) {
-        let id = id.into();
         let mut key = [0u8; 32];
         OsRng.fill_bytes(&mut key);
         self.set_current(id, key);
@@ -67,27 +236,38 @@ impl InMemoryKeyManager {
 
 impl KeyManager for InMemoryKeyManager {
     fn current(&self, _scope: &KeyScope) -> Result<KeyHandle, String> {
-        let id = self.current_id.lock().map_err(|e| e.to_string())?.clone();
-        let keys = self.keys.lock().map_err(|e| e.to_string())?;
-        let key = keys
-            .get(&id)
-            .ok_or_else(|| "missing key bytes for current id".to_string())?
-            .clone();
-        Ok(KeyHandle {
-            key_id: id,
-            key_bytes: key,
-        })
+        self.ring.lock().map_err(|e| e.to_string())?.current()
+    }
+
+    /// Rotates to a random key under a random `k-<hex>` id.
+    fn rotate_if_needed(
+        &self,
+        _scope: &KeyScope,
+        policy: &RotationPolicy,
+    ) -> Result<Option<KeyHandle>, String> {
+        KeyRing::rotate_when_due(
+            &self.ring,
+            |ring| Ok(ring.is_due(policy)),
+            |ring| {
+                let id = new_key_id();
+                ring.insert(id.clone(), random_key(), Instant::now());
+                ring.current = id;
+                ring.current()
+            },
+        )
     }
 
     fn get(&self, key_id: &str) -> Result<KeyHandle, String> {
-        let keys = self.keys.lock().map_err(|e| e.to_string())?;
-        let key = keys
-            .get(key_id)
-            .ok_or_else(|| "unknown key id".to_string())?
-            .clone();
-        Ok(KeyHandle {
-            key_id: key_id.to_string(),
-            key_bytes: key,
-        })
+        self.ring.lock().map_err(|e| e.to_string())?.handle(key_id)
+    }
+
+    fn record_use(&self, key_id: &str) {
+        if let Ok(mut ring) = self.ring.lock() {
+            ring.record_use(key_id);
+        }
+    }
+
+    fn usage(&self, key_id: &str) -> Option<KeyUsage> {
+        self.ring.lock().ok()?.usage.get(key_id).copied()
     }
 }
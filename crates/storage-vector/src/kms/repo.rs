@@ -0,0 +1,256 @@
+//! Independent keys per repository under any [`KeyManager`].
+//!
+//! Each repository gets random data keys of its own, sealed under the
+//! master manager's current key and kept as `<dir>/<repo>/<key_id>`, with
+//! `<dir>/<repo>/current` naming the key new payloads of the repository
+//! are sealed under. Repository names are path-encoded like the
+//! filesystem store. Destroying a repository's keys makes its payloads
+//! unrecoverable, even with the master key, without touching any other
+//! repository.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use zeroize::Zeroizing;
+
+use super::{is_plain_id, new_key_id, random_key, KeyManager, KeyRing, KeyScope, KeyUsage};
+use crate::config::RotationPolicy;
+use crate::encryption::aes_gcm::AesGcmEncrypter;
+use crate::encryption::{peek_key_id, Encrypter, KeyHandle};
+use crate::store::{build_aad, fs};
+
+const CURRENT_FILE: &str = "current";
+
+#[derive(Default)]
+struct State {
+    /// Unsealed keys, loaded on first use.
+    keys: HashMap<String, Zeroizing<[u8; 32]>>,
+    usage: HashMap<String, KeyUsage>,
+    /// Current key per repository.
+    current: HashMap<String, String>,
+    /// Repository of every stored key.
+    owners: HashMap<String, String>,
+}
+
+/// Key manager giving every repository its own keys, sealed under a
+/// master [`KeyManager`].
+pub struct RepoKeyManager<M: KeyManager> {
+    master: M,
+    dir: PathBuf,
+    sealer: AesGcmEncrypter,
+    state: Mutex<State>,
+}
+
+fn io(path: &Path, e: std::io::Error) -> String {
+    format!("{}: {e}", path.display())
+}
+
+impl<M: KeyManager> RepoKeyManager<M> {
+    /// Keep repository keys in `dir`, sealed under `master`.
+    pub fn open(master: M, dir: impl Into<PathBuf>) -> Result<Self, String> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir).map_err(|e| io(&dir, e))?;
+        let mut state = State::default();
+        for repo_id in fs::list_decoded(&dir, true).map_err(|e| io(&dir, e))? {
+            let repo_dir = dir.join(fs::encode_component(&repo_id));
+            for entry in std::fs::read_dir(&repo_dir).map_err(|e| io(&repo_dir, e))? {
+                let name = entry
+                    .map_err(|e| io(&repo_dir, e))?
+                    .file_name()
+                    .to_string_lossy()
+                    .into_owned();
+                if name == CURRENT_FILE {
+                    let path = repo_dir.join(CURRENT_FILE);
+                    let current = std::fs::read_to_string(&path).map_err(|e| io(&path, e))?;
+                    state
+                        .current
+                        .insert(repo_id.clone(), current.trim().to_string());
+                } else if is_plain_id(&name) {
+                    state.owners.insert(name, repo_id.clone());
+                }
+            }
+        }
+        Ok(Self {
+            master,
+            dir,
+            sealer: AesGcmEncrypter::new(),
+            state: Mutex::new(state),
+        })
+    }
+
+    /// The master manager repository keys are sealed under.
+    pub fn master(&self) -> &M {
+        &self.master
+    }
+
+    fn repo_dir(&self, repo_id: &str) -> PathBuf {
+        self.dir.join(fs::encode_component(repo_id))
+    }
+
+    /// Create a random key for `repo_id`, seal it under the master key and
+    /// make it the repository's current key.
+    fn create_key(&self, state: &mut State, repo_id: &str) -> Result<KeyHandle, String> {
+        let scope = KeyScope {
+            repo_id: repo_id.to_string(),
+        };
+        let master = self.master.current(&scope)?;
+        let id = new_key_id();
+        let key = random_key();
+        let sealed = self.sealer.seal(
+            &master,
+            key.as_ref(),
+            &build_aad(repo_id, &master.key_id, &id),
+        )?;
+        self.master.record_use(&master.key_id);
+        let repo_dir = self.repo_dir(repo_id);
+        let write = |name: &str, bytes: &[u8]| {
+            let path = repo_dir.join(name);
+            fs::atomic_write(&path, bytes).map_err(|e| io(&path, e))
+        };
+        write(&id, &sealed)?;
+        write(CURRENT_FILE, id.as_bytes())?;
+        state.owners.insert(id.clone(), repo_id.to_string());
+        state.current.insert(repo_id.to_string(), id.clone());
+        state.usage.insert(
+            id.clone(),
+            KeyUsage {
+                uses: 0,
+                created: Instant::now(),
+            },
+        );
+        state.keys.insert(id.clone(), key.clone());
+        Ok(KeyHandle {
+            key_id: id,
+            key_bytes: key,
+        })
+    }
+
+    /// Unseal `key_id` unless it is loaded already.
+    fn load(&self, state: &mut State, key_id: &str) -> Result<KeyHandle, String> {
+        if let Some(key) = state.keys.get(key_id) {
+            return Ok(KeyHandle {
+                key_id: key_id.to_string(),
+                key_bytes: key.clone(),
+            });
+        }
+        let repo_id = state
+            .owners
+            .get(key_id)
+            .cloned()
+            .ok_or_else(|| "unknown key id".to_string())?;
+        let path = self.repo_dir(&repo_id).join(key_id);
+        let sealed = std::fs::read(&path).map_err(|e| io(&path, e))?;
+        let master_id =
+            peek_key_id(&sealed).ok_or_else(|| format!("sealed key {key_id} is malformed"))?;
+        let master = self.master.get(&master_id)?;
+        let plain = Zeroizing::new(self.sealer.open(
+            &master,
+            &sealed,
+            &build_aad(&repo_id, &master_id, key_id),
+        )?);
+        let key: [u8; 32] = plain
+            .as_slice()
+            .try_into()
+            .map_err(|_| format!("key {key_id} is not 32 bytes"))?;
+        let key = Zeroizing::new(key);
+        // Age from the file, which is written once when the key is created.
+        let age = std::fs::metadata(&path)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .unwrap_or_default();
+        state.usage.entry(key_id.to_string()).or_insert(KeyUsage {
+            uses: 0,
+            created: Instant::now().checked_sub(age).unwrap_or_else(Instant::now),
+        });
+        state.keys.insert(key_id.to_string(), key.clone());
+        Ok(KeyHandle {
+            key_id: key_id.to_string(),
+            key_bytes: key,
+        })
+    }
+
+    /// Give `repo_id` a fresh current key. Its older keys stay readable.
+    pub fn rotate_repo(&self, repo_id: &str) -> Result<KeyHandle, String> {
+        let mut state = self.state.lock().map_err(|e| e.to_string())?;
+        self.create_key(&mut state, repo_id)
+    }
+
+    /// Destroy every key of `repo_id`, returning how many there were. Its
+    /// payloads can no longer be opened, so reading, overwriting or
+    /// deleting them fails with a key error; writes of other keys get a new
+    /// key. Copies of the key directory can undo this, so keep backups of
+    /// it apart from the data.
+    pub fn revoke_repo(&self, repo_id: &str) -> Result<usize, String> {
+        let mut state = self.state.lock().map_err(|e| e.to_string())?;
+        let repo_dir = self.repo_dir(repo_id);
+        match std::fs::remove_dir_all(&repo_dir) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(io(&repo_dir, e)),
+        }
+        let revoked: Vec<String> = state
+            .owners
+            .iter()
+            .filter(|(_, owner)| owner.as_str() == repo_id)
+            .map(|(key_id, _)| key_id.clone())
+            .collect();
+        for key_id in &revoked {
+            state.owners.remove(key_id);
+            state.keys.remove(key_id);
+            state.usage.remove(key_id);
+        }
+        state.current.remove(repo_id);
+        tracing::info!(repo_id, keys = revoked.len(), "revoked repository keys");
+        Ok(revoked.len())
+    }
+}
+
+impl<M: KeyManager> KeyManager for RepoKeyManager<M> {
+    /// The repository's current key, created on its first write.
+    fn current(&self, scope: &KeyScope) -> Result<KeyHandle, String> {
+        let mut state = self.state.lock().map_err(|e| e.to_string())?;
+        match state.current.get(&scope.repo_id).cloned() {
+            Some(key_id) => self.load(&mut state, &key_id),
+            None => self.create_key(&mut state, &scope.repo_id),
+        }
+    }
+
+    fn rotate_if_needed(
+        &self,
+        scope: &KeyScope,
+        policy: &RotationPolicy,
+    ) -> Result<Option<KeyHandle>, String> {
+        // A repository without a key yet gets one on its first write.
+        KeyRing::rotate_when_due(
+            &self.state,
+            |state| {
+                let Some(key_id) = state.current.get(&scope.repo_id).cloned() else {
+                    return Ok(false);
+                };
+                self.load(state, &key_id)?;
+                Ok(KeyUsage::is_due(state.usage.get(&key_id), policy))
+            },
+            |state| self.create_key(state, &scope.repo_id),
+        )
+    }
+
+    fn get(&self, key_id: &str) -> Result<KeyHandle, String> {
+        let mut state = self.state.lock().map_err(|e| e.to_string())?;
+        self.load(&mut state, key_id)
+    }
+
+    fn record_use(&self, key_id: &str) {
+        if let Ok(mut state) = self.state.lock() {
+            if let Some(used) = state.usage.get_mut(key_id) {
+                used.uses += 1;
+            }
+        }
+    }
+
+    fn usage(&self, key_id: &str) -> Option<KeyUsage> {
+        self.state.lock().ok()?.usage.get(key_id).copied()
+    }
+}
@@ -1,7 +1,21 @@
 //! Ledger helpers for replay integration.
 
+#[cfg(feature = "encryption")]
+use std::sync::Arc;
+
+#[cfg(feature = "encryption")]
+use storage_ledger::RecordCipher;
 use storage_ledger::ReplayEntry;
 
+#[cfg(feature = "encryption")]
+use crate::config::RotationPolicy;
+#[cfg(feature = "encryption")]
+use crate::encryption::{peek_key_id, Encrypter};
+#[cfg(feature = "encryption")]
+use crate::kms::{KeyManager, KeyScope};
+#[cfg(feature = "encryption")]
+use crate::store::build_aad;
+
 /// Build a minimal replay entry for a write. Checksums are placeholders; the
 /// integration tests assert ordering rather than specific checksum values.
 pub fn build_replay_entry(
@@ -18,5 +32,82 @@ pub fn build_replay_entry(
         payload_checksum_before: checksum_before.to_string(),
         payload_checksum_after: checksum_after.to_string(),
         status: status.to_string(),
+        sealed_payload: None,
+        signature: None,
+        tenant_id: None,
+    }
+}
+
+/// [`RecordCipher`] sealing ledger frames and replay journal records with
+/// the vector store's [`Encrypter`] and [`KeyManager`].
+///
+/// Records are sealed under the current key of `scope`, rotated by the
+/// configured [`RotationPolicy`] like store payloads, and bound to the key id
+/// and record context through [`build_aad`]. Opening looks the key up by the
+/// id in the envelope, so rotated keys must stay available to the manager.
+#[cfg(feature = "encryption")]
+pub struct LedgerCipher {
+    encrypter: Arc<dyn Encrypter>,
+    keys: Arc<dyn KeyManager>,
+    scope: KeyScope,
+    rotation: RotationPolicy,
+}
+
+#[cfg(feature = "encryption")]
+impl std::fmt::Debug for LedgerCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LedgerCipher")
+            .field("scope", &self.scope)
+            .field("rotation", &self.rotation)
+            .finish()
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl LedgerCipher {
+    pub fn new(encrypter: Arc<dyn Encrypter>, keys: Arc<dyn KeyManager>, scope: KeyScope) -> Self {
+        Self {
+            encrypter,
+            keys,
+            scope,
+            rotation: RotationPolicy::default(),
+        }
+    }
+
+    /// Rotate the scope's key by `policy` before sealing.
+    #[must_use]
+    pub fn with_rotation(mut self, policy: RotationPolicy) -> Self {
+        self.rotation = policy;
+        self
+    }
+
+    fn aad(&self, key_id: &str, context: &[u8]) -> Vec<u8> {
+        build_aad(
+            &self.scope.repo_id,
+            key_id,
+            &String::from_utf8_lossy(context),
+        )
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl RecordCipher for LedgerCipher {
+    fn seal(&self, plaintext: &[u8], context: &[u8]) -> Result<Vec<u8>, String> {
+        let key = match self.keys.rotate_if_needed(&self.scope, &self.rotation)? {
+            Some(key) => key,
+            None => self.keys.current(&self.scope)?,
+        };
+        let sealed = self
+            .encrypter
+            .seal(&key, plaintext, &self.aad(&key.key_id, context))?;
+        self.keys.record_use(&key.key_id);
+        Ok(sealed)
+    }
+
+    fn open(&self, sealed: &[u8], context: &[u8]) -> Result<Vec<u8>, String> {
+        let key_id = peek_key_id(sealed).ok_or("missing or invalid envelope")?;
+        let key = self.keys.get(&key_id)?;
+        self.encrypter
+            .open(&key, sealed, &self.aad(&key_id, context))
     }
 }
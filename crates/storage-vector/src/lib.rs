@@ -126,9 +126,17 @@ impl QuotaDiagnostics {
 }
 
 // Public API surface for Milestone 3 skeleton
+#[cfg(feature = "native")]
+pub mod commands;
+#[cfg(feature = "native")]
 pub mod config;
+#[cfg(feature = "native")]
 pub mod error;
+#[cfg(feature = "native")]
 pub mod ledger;
+#[cfg(feature = "native")]
+pub mod search;
+#[cfg(feature = "native")]
 pub mod store;
 
 #[cfg(feature = "encryption")]
@@ -137,5 +145,25 @@ pub mod encryption;
 #[cfg(feature = "encryption")]
 pub mod kms;
 
+#[cfg(feature = "native")]
+pub use crate::config::{FsyncPolicy, MaintenanceConfig, SegmentConfig, StoreConfig};
+#[cfg(feature = "native")]
 pub use crate::error::StoreError;
-pub use crate::store::{ReplayStats, Store, VectorStore};
+#[cfg(feature = "encryption")]
+pub use crate::ledger::LedgerCipher;
+#[cfg(feature = "native")]
+pub use crate::search::{
+    Field, FilterExpr, Metric, SearchFilter, SearchHit, VectorIndex, VectorMetadata,
+};
+#[cfg(feature = "hnsw")]
+pub use crate::search::{HnswConfig, HnswIndex};
+#[cfg(feature = "native")]
+pub use crate::store::segment::{SegmentCompactor, SegmentStore};
+#[cfg(feature = "native")]
+pub use crate::store::{
+    tenant_namespace, CompactionReport, CorruptRecord, IntegrityReport, KeyPage, MaintenanceReport,
+    MaintenanceTask, ReplayOp, ReplayRecord, ReplayStats, Scan, SnapshotReport, StorageUsage,
+    Store, StoreMode, VectorStore, ABSENT_CHECKSUM, EXPIRED_STATUS, TOMBSTONE_STATUS,
+};
+#[cfg(feature = "encryption")]
+pub use crate::store::{ReencryptJob, ReencryptProgress};
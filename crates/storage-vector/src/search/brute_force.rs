@@ -0,0 +1,62 @@
+//! Exact search by scoring every stored vector.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{rank, Metric, SearchFilter, SearchHit, VectorIndex, VectorMetadata};
+
+/// Baseline [`VectorIndex`]: exact results at O(n) cost per query.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BruteForceIndex {
+    entries: HashMap<String, (Vec<f32>, VectorMetadata)>,
+}
+
+impl BruteForceIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stored vectors with their metadata, consuming the index.
+    pub(crate) fn into_entries(self) -> Vec<(String, Vec<f32>, VectorMetadata)> {
+        self.entries
+            .into_iter()
+            .map(|(key, (vector, metadata))| (key, vector, metadata))
+            .collect()
+    }
+}
+
+impl VectorIndex for BruteForceIndex {
+    fn insert(&mut self, key: &str, vector: Vec<f32>, metadata: VectorMetadata) {
+        self.entries.insert(key.to_string(), (vector, metadata));
+    }
+
+    fn remove(&mut self, key: &str) -> bool {
+        self.entries.remove(key).is_some()
+    }
+
+    fn search(
+        &self,
+        query: &[f32],
+        k: usize,
+        metric: Metric,
+        filter: Option<&SearchFilter>,
+    ) -> Vec<SearchHit> {
+        let mut hits: Vec<SearchHit> = self
+            .entries
+            .iter()
+            .filter(|(_, (_, metadata))| filter.map_or(true, |filter| filter.matches(metadata)))
+            .map(|(key, (vector, metadata))| SearchHit {
+                key: key.clone(),
+                score: metric.score(query, vector),
+                metadata: metadata.clone(),
+            })
+            .collect();
+        rank(&mut hits, k);
+        hits
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
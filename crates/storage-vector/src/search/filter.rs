@@ -0,0 +1,153 @@
+//! Filter expressions over [`VectorMetadata`].
+
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+use super::VectorMetadata;
+
+/// Metadata field a [`FilterExpr`] compares against.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Field {
+    PlanId,
+    RepoId,
+    Path,
+    Language,
+    ChunkHash,
+    EmbeddedAt,
+    ModifiedAt,
+    /// Free-form entry of [`VectorMetadata::attributes`].
+    Attribute(String),
+}
+
+impl Field {
+    /// Value of the field in `metadata`, timestamps rendered in decimal.
+    fn text<'a>(&self, metadata: &'a VectorMetadata) -> Option<Cow<'a, str>> {
+        let borrowed = |value: &'a Option<String>| value.as_deref().map(Cow::Borrowed);
+        match self {
+            Self::PlanId => Some(Cow::Borrowed(metadata.plan_id.as_str())),
+            Self::RepoId => borrowed(&metadata.repo_id),
+            Self::Path => borrowed(&metadata.path),
+            Self::Language => borrowed(&metadata.language),
+            Self::ChunkHash => borrowed(&metadata.chunk_hash),
+            Self::EmbeddedAt => metadata.embedded_at.map(|at| Cow::Owned(at.to_string())),
+            Self::ModifiedAt => metadata.modified_at.map(|at| Cow::Owned(at.to_string())),
+            Self::Attribute(name) => metadata
+                .attributes
+                .get(name)
+                .map(|value| Cow::Borrowed(value.as_str())),
+        }
+    }
+
+    /// Value of the field as a number; text fields must parse as `u64`.
+    fn number(&self, metadata: &VectorMetadata) -> Option<u64> {
+        match self {
+            Self::EmbeddedAt => metadata.embedded_at,
+            Self::ModifiedAt => metadata.modified_at,
+            _ => self.text(metadata)?.parse().ok(),
+        }
+    }
+}
+
+/// Predicate over vector metadata, evaluated during search.
+///
+/// A comparison against a field the metadata does not carry is false.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum FilterExpr {
+    Eq {
+        field: Field,
+        value: String,
+    },
+    Prefix {
+        field: Field,
+        prefix: String,
+    },
+    /// Numeric value within `min..=max`; an open bound is unlimited.
+    Range {
+        field: Field,
+        #[serde(default)]
+        min: Option<u64>,
+        #[serde(default)]
+        max: Option<u64>,
+    },
+    /// Carries every one of these tags.
+    AllTags {
+        tags: Vec<String>,
+    },
+    /// Carries at least one of these tags.
+    AnyTag {
+        tags: Vec<String>,
+    },
+    And {
+        exprs: Vec<FilterExpr>,
+    },
+    Or {
+        exprs: Vec<FilterExpr>,
+    },
+    Not {
+        expr: Box<FilterExpr>,
+    },
+}
+
+impl FilterExpr {
+    pub fn eq(field: Field, value: impl Into<String>) -> Self {
+        Self::Eq {
+            field,
+            value: value.into(),
+        }
+    }
+
+    pub fn prefix(field: Field, prefix: impl Into<String>) -> Self {
+        Self::Prefix {
+            field,
+            prefix: prefix.into(),
+        }
+    }
+
+    pub fn range(field: Field, min: Option<u64>, max: Option<u64>) -> Self {
+        Self::Range { field, min, max }
+    }
+
+    /// Vectors of `repo_id` whose source path starts with `path_prefix`.
+    pub fn repo_path(repo_id: impl Into<String>, path_prefix: impl Into<String>) -> Self {
+        Self::and(vec![
+            Self::eq(Field::RepoId, repo_id),
+            Self::prefix(Field::Path, path_prefix),
+        ])
+    }
+
+    pub fn and(exprs: Vec<FilterExpr>) -> Self {
+        Self::And { exprs }
+    }
+
+    pub fn or(exprs: Vec<FilterExpr>) -> Self {
+        Self::Or { exprs }
+    }
+
+    pub fn negate(expr: FilterExpr) -> Self {
+        Self::Not {
+            expr: Box::new(expr),
+        }
+    }
+
+    pub fn matches(&self, metadata: &VectorMetadata) -> bool {
+        match self {
+            Self::Eq { field, value } => field
+                .text(metadata)
+                .is_some_and(|found| found == value.as_str()),
+            Self::Prefix { field, prefix } => field
+                .text(metadata)
+                .is_some_and(|found| found.starts_with(prefix.as_str())),
+            Self::Range { field, min, max } => field.number(metadata).is_some_and(|found| {
+                min.map_or(true, |min| found >= min) && max.map_or(true, |max| found <= max)
+            }),
+            Self::AllTags { tags } => tags.iter().all(|tag| metadata.tags.contains(tag)),
+            Self::AnyTag { tags } => tags.iter().any(|tag| metadata.tags.contains(tag)),
+            Self::And { exprs } => exprs.iter().all(|expr| expr.matches(metadata)),
+            Self::Or { exprs } => exprs.iter().any(|expr| expr.matches(metadata)),
+            Self::Not { expr } => !expr.matches(metadata),
+        }
+    }
+}
@@ -0,0 +1,386 @@
+//! Approximate search over a hierarchical navigable small world graph.
+//!
+//! Every vector is a node on layer 0 and, with exponentially decreasing
+//! probability, on the layers above it. Searches descend greedily from the
+//! sparse top layer and widen to `ef_search` candidates on layer 0. Nodes are
+//! linked as they are inserted, so the graph never needs a bulk build.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use super::{rank, Metric, SearchFilter, SearchHit, VectorIndex, VectorMetadata};
+
+/// Highest layer a node may be placed on.
+const MAX_LEVEL: usize = 16;
+
+/// Build and query parameters of an [`HnswIndex`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HnswConfig {
+    /// Links kept per node on the upper layers; layer 0 keeps twice as many.
+    pub m: usize,
+    /// Candidates considered when linking a new node.
+    pub ef_construction: usize,
+    /// Candidates considered per query; raised to `k` when smaller.
+    pub ef_search: usize,
+}
+
+impl Default for HnswConfig {
+    fn default() -> Self {
+        Self {
+            m: 16,
+            ef_construction: 200,
+            ef_search: 64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Node {
+    key: String,
+    vector: Vec<f32>,
+    metadata: VectorMetadata,
+    /// Neighbours on each layer the node is part of, layer 0 first.
+    links: Vec<Vec<usize>>,
+    /// Removed or replaced; kept as a waypoint until the graph is rebuilt.
+    removed: bool,
+}
+
+/// Node score while walking the graph, ordered by score then node id.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Scored {
+    score: f32,
+    node: usize,
+}
+
+impl Eq for Scored {}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .total_cmp(&other.score)
+            .then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+/// [`VectorIndex`] answering queries from an HNSW graph in roughly
+/// logarithmic time, at the cost of occasionally missing a true neighbour.
+///
+/// The graph is linked under the metric it was created with; queries using
+/// another metric, and filtered queries the graph walk cannot satisfy, fall
+/// back to an exact scan. Removed vectors stay in the graph as waypoints
+/// until they outnumber the live ones, when the graph is rebuilt.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HnswIndex {
+    config: HnswConfig,
+    metric: Metric,
+    nodes: Vec<Node>,
+    /// Node of each live key.
+    live: HashMap<String, usize>,
+    /// Node on the top layer where every search starts.
+    entry: Option<usize>,
+}
+
+impl HnswIndex {
+    pub fn new(config: HnswConfig, metric: Metric) -> Self {
+        Self {
+            config,
+            metric,
+            nodes: Vec::new(),
+            live: HashMap::new(),
+            entry: None,
+        }
+    }
+
+    pub fn config(&self) -> HnswConfig {
+        self.config
+    }
+
+    /// Metric the graph is linked under.
+    pub fn metric(&self) -> Metric {
+        self.metric
+    }
+
+    /// Live vectors with their metadata, consuming the index.
+    pub(crate) fn into_entries(self) -> Vec<(String, Vec<f32>, VectorMetadata)> {
+        self.nodes
+            .into_iter()
+            .filter(|node| !node.removed)
+            .map(|node| (node.key, node.vector, node.metadata))
+            .collect()
+    }
+
+    /// Whether every link, live key and the entry point refer to a node, as
+    /// checked after loading a persisted graph.
+    pub(crate) fn is_consistent(&self) -> bool {
+        let in_range = |node: &usize| *node < self.nodes.len();
+        self.entry
+            .map_or(self.live.is_empty(), |entry| in_range(&entry))
+            && self.live.values().all(in_range)
+            && self
+                .nodes
+                .iter()
+                .all(|node| node.links.iter().flatten().all(in_range))
+    }
+
+    fn max_links(&self, layer: usize) -> usize {
+        if layer == 0 {
+            self.config.m * 2
+        } else {
+            self.config.m
+        }
+    }
+
+    /// Layer to place a new node on, drawn from an exponential distribution
+    /// seeded by the key so rebuilding a graph is deterministic.
+    fn level(&self, key: &str) -> usize {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(key.as_bytes());
+        hasher.update(&(self.nodes.len() as u64).to_le_bytes());
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&hasher.finalize().as_bytes()[..8]);
+        // Uniform in (0, 1]: 53 random bits, shifted away from zero.
+        let uniform = ((u64::from_le_bytes(bytes) >> 11) + 1) as f64 / (1u64 << 53) as f64;
+        let scale = 1.0 / (self.config.m.max(2) as f64).ln();
+        ((-uniform.ln() * scale) as usize).min(MAX_LEVEL)
+    }
+
+    fn links(&self, node: usize, layer: usize) -> &[usize] {
+        self.nodes[node]
+            .links
+            .get(layer)
+            .map_or(&[], |links| links.as_slice())
+    }
+
+    fn scored(&self, query: &[f32], node: usize) -> Scored {
+        Scored {
+            score: self.metric.score(query, &self.nodes[node].vector),
+            node,
+        }
+    }
+
+    /// The `ef` nodes closest to `query` on `layer` reachable from
+    /// `entries`, best first.
+    fn search_layer(
+        &self,
+        query: &[f32],
+        entries: &[usize],
+        ef: usize,
+        layer: usize,
+    ) -> Vec<Scored> {
+        let mut visited: HashSet<usize> = entries.iter().copied().collect();
+        let mut candidates: BinaryHeap<Scored> = BinaryHeap::new();
+        let mut found: BinaryHeap<Reverse<Scored>> = BinaryHeap::new();
+        for &node in entries {
+            let scored = self.scored(query, node);
+            candidates.push(scored);
+            found.push(Reverse(scored));
+        }
+        while found.len() > ef {
+            found.pop();
+        }
+        while let Some(current) = candidates.pop() {
+            let worst = found
+                .peek()
+                .map_or(f32::NEG_INFINITY, |worst| worst.0.score);
+            if found.len() >= ef && current.score < worst {
+                break;
+            }
+            for &next in self.links(current.node, layer) {
+                if !visited.insert(next) {
+                    continue;
+                }
+                let scored = self.scored(query, next);
+                let worst = found
+                    .peek()
+                    .map_or(f32::NEG_INFINITY, |worst| worst.0.score);
+                if found.len() < ef || scored.score > worst {
+                    candidates.push(scored);
+                    found.push(Reverse(scored));
+                    if found.len() > ef {
+                        found.pop();
+                    }
+                }
+            }
+        }
+        let mut found: Vec<Scored> = found.into_iter().map(|scored| scored.0).collect();
+        found.sort_unstable_by(|a, b| b.cmp(a));
+        found
+    }
+
+    /// Greedy descent from the entry point to the closest node on each
+    /// layer above `floor`.
+    fn descend(&self, query: &[f32], floor: usize) -> Vec<usize> {
+        let Some(entry) = self.entry else {
+            return Vec::new();
+        };
+        let top = self.nodes[entry].links.len() - 1;
+        let mut nearest = vec![entry];
+        for layer in (floor + 1..=top).rev() {
+            nearest = self
+                .search_layer(query, &nearest, 1, layer)
+                .into_iter()
+                .map(|scored| scored.node)
+                .collect();
+        }
+        nearest
+    }
+
+    /// Keep only the `max` links of `node` on `layer` closest to it.
+    fn prune(&mut self, node: usize, layer: usize, max: usize) {
+        let vector = &self.nodes[node].vector;
+        let mut links: Vec<Scored> = self.nodes[node].links[layer]
+            .iter()
+            .map(|&link| Scored {
+                score: self.metric.score(vector, &self.nodes[link].vector),
+                node: link,
+            })
+            .collect();
+        links.sort_unstable_by(|a, b| b.cmp(a));
+        links.truncate(max);
+        self.nodes[node].links[layer] = links.into_iter().map(|scored| scored.node).collect();
+    }
+
+    fn tombstone(&mut self, key: &str) -> bool {
+        match self.live.remove(key) {
+            Some(node) => {
+                self.nodes[node].removed = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Relink the live nodes once removed ones outnumber them.
+    fn rebuild_if_sparse(&mut self) {
+        let removed = self.nodes.len() - self.live.len();
+        if removed <= self.live.len().max(self.config.m) {
+            return;
+        }
+        let nodes = std::mem::take(&mut self.nodes);
+        self.live.clear();
+        self.entry = None;
+        for node in nodes.into_iter().filter(|node| !node.removed) {
+            self.insert(&node.key, node.vector, node.metadata);
+        }
+    }
+
+    fn exact_search(
+        &self,
+        query: &[f32],
+        k: usize,
+        metric: Metric,
+        filter: Option<&SearchFilter>,
+    ) -> Vec<SearchHit> {
+        let mut hits: Vec<SearchHit> = self
+            .live
+            .values()
+            .map(|&node| &self.nodes[node])
+            .filter(|node| filter.map_or(true, |filter| filter.matches(&node.metadata)))
+            .map(|node| SearchHit {
+                key: node.key.clone(),
+                score: metric.score(query, &node.vector),
+                metadata: node.metadata.clone(),
+            })
+            .collect();
+        rank(&mut hits, k);
+        hits
+    }
+}
+
+impl VectorIndex for HnswIndex {
+    fn insert(&mut self, key: &str, vector: Vec<f32>, metadata: VectorMetadata) {
+        self.tombstone(key);
+        let level = self.level(key);
+        let id = self.nodes.len();
+        self.nodes.push(Node {
+            key: key.to_string(),
+            vector,
+            metadata,
+            links: vec![Vec::new(); level + 1],
+            removed: false,
+        });
+        self.live.insert(key.to_string(), id);
+        let Some(entry) = self.entry else {
+            self.entry = Some(id);
+            return;
+        };
+        let top = self.nodes[entry].links.len() - 1;
+        let query = self.nodes[id].vector.clone();
+        let mut nearest = self.descend(&query, level);
+        for layer in (0..=level.min(top)).rev() {
+            let found = self.search_layer(&query, &nearest, self.config.ef_construction, layer);
+            let max = self.max_links(layer);
+            let neighbours: Vec<usize> = found
+                .iter()
+                .take(self.config.m)
+                .map(|scored| scored.node)
+                .collect();
+            for &neighbour in &neighbours {
+                self.nodes[neighbour].links[layer].push(id);
+                if self.nodes[neighbour].links[layer].len() > max {
+                    self.prune(neighbour, layer, max);
+                }
+            }
+            self.nodes[id].links[layer] = neighbours;
+            nearest = found.into_iter().map(|scored| scored.node).collect();
+        }
+        if level > top {
+            self.entry = Some(id);
+        }
+        self.rebuild_if_sparse();
+    }
+
+    fn remove(&mut self, key: &str) -> bool {
+        let removed = self.tombstone(key);
+        if removed {
+            self.rebuild_if_sparse();
+        }
+        removed
+    }
+
+    fn search(
+        &self,
+        query: &[f32],
+        k: usize,
+        metric: Metric,
+        filter: Option<&SearchFilter>,
+    ) -> Vec<SearchHit> {
+        if k == 0 || self.live.is_empty() {
+            return Vec::new();
+        }
+        if metric != self.metric {
+            return self.exact_search(query, k, metric, filter);
+        }
+        let nearest = self.descend(query, 0);
+        let ef = self.config.ef_search.max(k);
+        let mut hits: Vec<SearchHit> = self
+            .search_layer(query, &nearest, ef, 0)
+            .into_iter()
+            .map(|scored| (scored.score, &self.nodes[scored.node]))
+            .filter(|(_, node)| !node.removed)
+            .filter(|(_, node)| filter.map_or(true, |filter| filter.matches(&node.metadata)))
+            .map(|(score, node)| SearchHit {
+                key: node.key.clone(),
+                score,
+                metadata: node.metadata.clone(),
+            })
+            .collect();
+        if hits.len() < k.min(self.live.len()) {
+            return self.exact_search(query, k, metric, filter);
+        }
+        rank(&mut hits, k);
+        hits
+    }
+
+    fn len(&self) -> usize {
+        self.live.len()
+    }
+}
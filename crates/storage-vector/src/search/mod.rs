@@ -0,0 +1,250 @@
+//! Similarity search over embedding vectors stored alongside payloads.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::StoreError;
+
+mod brute_force;
+mod filter;
+#[cfg(feature = "hnsw")]
+mod hnsw;
+
+pub use brute_force::BruteForceIndex;
+pub use filter::{Field, FilterExpr};
+#[cfg(feature = "hnsw")]
+pub use hnsw::{HnswConfig, HnswIndex};
+
+/// How closeness between two vectors is scored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    #[default]
+    Cosine,
+    Dot,
+    /// Scored as the negated distance, so higher is still closer.
+    Euclidean,
+}
+
+impl Metric {
+    /// Similarity of `a` and `b`; higher is closer for every metric.
+    pub fn score(self, a: &[f32], b: &[f32]) -> f32 {
+        match self {
+            Self::Dot => dot(a, b),
+            Self::Cosine => {
+                let norms = dot(a, a).sqrt() * dot(b, b).sqrt();
+                if norms == 0.0 {
+                    0.0
+                } else {
+                    dot(a, b) / norms
+                }
+            }
+            Self::Euclidean => -a
+                .iter()
+                .zip(b)
+                .map(|(x, y)| (x - y) * (x - y))
+                .sum::<f32>()
+                .sqrt(),
+        }
+    }
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Metadata stored with each vector and returned with search hits.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VectorMetadata {
+    /// Chunk plan the vector was embedded from.
+    pub plan_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo_id: Option<String>,
+    /// Source file path relative to the repository root.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Hash of the sanitized chunk text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk_hash: Option<String>,
+    /// Seconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedded_at: Option<u64>,
+    /// Last modification of the source file, in seconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub tags: BTreeSet<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl VectorMetadata {
+    pub fn new(plan_id: impl Into<String>) -> Self {
+        Self {
+            plan_id: plan_id.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_repo_id(mut self, repo_id: impl Into<String>) -> Self {
+        self.repo_id = Some(repo_id.into());
+        self
+    }
+
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn with_language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    pub fn with_chunk_hash(mut self, chunk_hash: impl Into<String>) -> Self {
+        self.chunk_hash = Some(chunk_hash.into());
+        self
+    }
+
+    pub fn with_embedded_at(mut self, seconds: u64) -> Self {
+        self.embedded_at = Some(seconds);
+        self
+    }
+
+    pub fn with_modified_at(mut self, seconds: u64) -> Self {
+        self.modified_at = Some(seconds);
+        self
+    }
+
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.insert(tag.into());
+        self
+    }
+
+    pub fn with_attribute(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.attributes.insert(name.into(), value.into());
+        self
+    }
+}
+
+/// Restricts which vectors a search may return; every part must match.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchFilter {
+    pub plan_id_prefix: Option<String>,
+    /// Attributes that must be present with exactly these values.
+    pub attributes: BTreeMap<String, String>,
+    pub expr: Option<FilterExpr>,
+}
+
+impl SearchFilter {
+    /// Filter on `expr` alone.
+    pub fn expr(expr: FilterExpr) -> Self {
+        Self {
+            expr: Some(expr),
+            ..Self::default()
+        }
+    }
+
+    pub fn matches(&self, metadata: &VectorMetadata) -> bool {
+        self.plan_id_prefix
+            .as_deref()
+            .map_or(true, |prefix| metadata.plan_id.starts_with(prefix))
+            && self
+                .attributes
+                .iter()
+                .all(|(name, value)| metadata.attributes.get(name) == Some(value))
+            && self
+                .expr
+                .as_ref()
+                .map_or(true, |expr| expr.matches(metadata))
+    }
+}
+
+/// One search result, best first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub key: String,
+    pub score: f32,
+    pub metadata: VectorMetadata,
+}
+
+/// Nearest-neighbour index over the vectors of one repository.
+pub trait VectorIndex: Send + Sync {
+    /// Add or replace the vector stored under `key`.
+    fn insert(&mut self, key: &str, vector: Vec<f32>, metadata: VectorMetadata);
+    /// Remove `key`; returns whether it was present.
+    fn remove(&mut self, key: &str) -> bool;
+    /// Up to `k` hits passing `filter`, best first.
+    fn search(
+        &self,
+        query: &[f32],
+        k: usize,
+        metric: Metric,
+        filter: Option<&SearchFilter>,
+    ) -> Vec<SearchHit>;
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Index implementations a store can build and persist.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub(crate) enum IndexBackend {
+    BruteForce(BruteForceIndex),
+    #[cfg(feature = "hnsw")]
+    Hnsw(HnswIndex),
+}
+
+impl IndexBackend {
+    pub(crate) fn index(&self) -> &dyn VectorIndex {
+        match self {
+            Self::BruteForce(index) => index,
+            #[cfg(feature = "hnsw")]
+            Self::Hnsw(index) => index,
+        }
+    }
+
+    pub(crate) fn index_mut(&mut self) -> &mut dyn VectorIndex {
+        match self {
+            Self::BruteForce(index) => index,
+            #[cfg(feature = "hnsw")]
+            Self::Hnsw(index) => index,
+        }
+    }
+
+    pub(crate) fn into_entries(self) -> Vec<(String, Vec<f32>, VectorMetadata)> {
+        match self {
+            Self::BruteForce(index) => index.into_entries(),
+            #[cfg(feature = "hnsw")]
+            Self::Hnsw(index) => index.into_entries(),
+        }
+    }
+}
+
+/// Reject vectors that cannot be scored meaningfully.
+pub(crate) fn check_vector(vector: &[f32], dimension: Option<usize>) -> Result<(), StoreError> {
+    if vector.is_empty() {
+        return Err(StoreError::Integrity("vector is empty".into()));
+    }
+    if let Some(expected) = dimension.filter(|expected| *expected != vector.len()) {
+        return Err(StoreError::DimensionMismatch {
+            expected,
+            found: vector.len(),
+        });
+    }
+    if vector.iter().any(|value| !value.is_finite()) {
+        return Err(StoreError::Integrity("vector has non-finite values".into()));
+    }
+    Ok(())
+}
+
+/// Order hits best first, breaking ties by key so results are stable.
+pub(crate) fn rank(hits: &mut Vec<SearchHit>, k: usize) {
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.key.cmp(&b.key)));
+    hits.truncate(k);
+}
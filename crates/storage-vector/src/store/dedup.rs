@@ -0,0 +1,198 @@
+//! Content-addressed payload storage for a [`VectorStore`].
+//!
+//! With [`crate::StoreConfig::dedup`] on, each payload is stored once as a
+//! blob named by its blake3 digest, and a key holds a reference to it, so
+//! identical chunks across keys and repositories share one blob. Live
+//! references per blob are counted from the stored references on first
+//! use and kept current as keys are written and deleted; compaction removes
+//! blobs nothing references.
+//!
+//! Stores with encryption configured keep sealing payloads per key: shared
+//! blobs would reveal which repositories hold equal content.
+
+use std::collections::HashMap;
+use std::sync::MutexGuard;
+
+use super::{fs, Store, VectorStore};
+use crate::error::StoreError;
+
+/// Prefix of a stored reference; the blob's hex digest follows.
+const REF_MAGIC: &[u8; 8] = b"ENXREF01";
+const DIGEST_HEX_LEN: usize = 64;
+
+/// Live references per blob digest; `None` until counted.
+pub(crate) type RefCounts = Option<HashMap<String, u64>>;
+
+fn reference(digest: &str) -> Vec<u8> {
+    let mut bytes = REF_MAGIC.to_vec();
+    bytes.extend_from_slice(digest.as_bytes());
+    bytes
+}
+
+/// Digest a stored value refers to, if it is a reference.
+fn referenced(bytes: &[u8]) -> Option<&str> {
+    let digest = bytes.strip_prefix(REF_MAGIC)?;
+    if digest.len() != DIGEST_HEX_LEN || !digest.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+    std::str::from_utf8(digest).ok()
+}
+
+impl VectorStore {
+    pub(super) fn dedup_enabled(&self) -> bool {
+        #[cfg(feature = "encryption")]
+        if self.encrypter.is_some() && self.kms.is_some() {
+            return false;
+        }
+        self.config.dedup
+    }
+
+    /// Lock and, on first use, count blob references; `None` when dedup is
+    /// off. Writers hold it from storing a blob until the reference lands,
+    /// so compaction never removes a blob about to be referenced.
+    pub(super) fn lock_refs(&self) -> Result<Option<MutexGuard<'_, RefCounts>>, StoreError> {
+        if !self.dedup_enabled() {
+            return Ok(None);
+        }
+        let mut refs = self
+            .refs
+            .lock()
+            .map_err(|e| StoreError::Io(e.to_string()))?;
+        if refs.is_none() {
+            let mut counts: HashMap<String, u64> = HashMap::new();
+            for repo_id in self.list_repos()? {
+                for key in self.live_keys(&repo_id)? {
+                    if let Some(digest) = self
+                        .read_raw(&repo_id, &key)?
+                        .as_deref()
+                        .and_then(referenced)
+                    {
+                        *counts.entry(digest.to_string()).or_default() += 1;
+                    }
+                }
+            }
+            *refs = Some(counts);
+        }
+        Ok(Some(refs))
+    }
+
+    /// Recount references after writes that bypassed [`VectorStore::lock_refs`].
+    pub(super) fn forget_refs(&self) -> Result<(), StoreError> {
+        *self
+            .refs
+            .lock()
+            .map_err(|e| StoreError::Io(e.to_string()))? = None;
+        Ok(())
+    }
+
+    /// Store `payload` as a blob unless it already exists and return the
+    /// reference to keep under the key.
+    pub(super) fn intern(&self, payload: &[u8]) -> Result<Vec<u8>, StoreError> {
+        let digest = blake3::hash(payload).to_hex();
+        if let Some(root) = &self.fs_root {
+            let path = fs::blob_path(root, &digest);
+            if !path.exists() {
+                fs::atomic_write(&path, payload).map_err(|e| StoreError::Io(e.to_string()))?;
+            }
+        } else {
+            self.blobs
+                .lock()
+                .map_err(|e| StoreError::Io(e.to_string()))?
+                .entry(digest.to_string())
+                .or_insert_with(|| payload.to_vec());
+        }
+        Ok(reference(&digest))
+    }
+
+    /// Move one reference per key from its `old` stored value to its `new`
+    /// one; values that are not references are ignored.
+    pub(super) fn retarget(counts: &mut RefCounts, old: &[u8], new: Option<&[u8]>) {
+        let Some(counts) = counts.as_mut() else {
+            return;
+        };
+        if let Some(digest) = referenced(old) {
+            if let Some(count) = counts.get_mut(digest) {
+                *count = count.saturating_sub(1);
+            }
+        }
+        if let Some(digest) = new.and_then(referenced) {
+            *counts.entry(digest.to_string()).or_default() += 1;
+        }
+    }
+
+    /// Stored bytes of `key` with a reference replaced by its blob, which
+    /// must exist and match its digest.
+    pub(super) fn resolve(&self, bytes: Vec<u8>) -> Result<Vec<u8>, StoreError> {
+        if !self.dedup_enabled() {
+            return Ok(bytes);
+        }
+        let Some(digest) = referenced(&bytes) else {
+            return Ok(bytes);
+        };
+        let blob = match &self.fs_root {
+            Some(root) => match std::fs::read(fs::blob_path(root, digest)) {
+                Ok(blob) => Some(blob),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => return Err(StoreError::Io(e.to_string())),
+            },
+            None => self
+                .blobs
+                .lock()
+                .map_err(|e| StoreError::Io(e.to_string()))?
+                .get(digest)
+                .cloned(),
+        };
+        match blob {
+            Some(blob) if blake3::hash(&blob).to_hex().as_str() == digest => Ok(blob),
+            Some(_) => Err(StoreError::Integrity(format!(
+                "blob {digest} does not match its digest"
+            ))),
+            None => Err(StoreError::Integrity(format!("blob {digest} is missing"))),
+        }
+    }
+
+    /// Remove blobs no live key references, returning how many and their
+    /// bytes.
+    pub(super) fn collect_blobs(&self) -> Result<(usize, u64), StoreError> {
+        let Some(mut refs) = self.lock_refs()? else {
+            return Ok((0, 0));
+        };
+        let counts = refs.as_mut().expect("counted by lock_refs");
+        let live = |digest: &str| counts.get(digest).is_some_and(|count| *count > 0);
+        let (mut removed, mut bytes) = (0, 0);
+        match &self.fs_root {
+            Some(root) => {
+                let io = |e: std::io::Error| StoreError::Io(e.to_string());
+                let dir = root.join(fs::BLOB_DIR);
+                let entries = match std::fs::read_dir(&dir) {
+                    Ok(entries) => entries,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((0, 0)),
+                    Err(e) => return Err(io(e)),
+                };
+                for entry in entries {
+                    let entry = entry.map_err(io)?;
+                    if live(&entry.file_name().to_string_lossy()) {
+                        continue;
+                    }
+                    bytes += entry.metadata().map_err(io)?.len();
+                    std::fs::remove_file(entry.path()).map_err(io)?;
+                    removed += 1;
+                }
+            }
+            None => self
+                .blobs
+                .lock()
+                .map_err(|e| StoreError::Io(e.to_string()))?
+                .retain(|digest, blob| {
+                    let keep = live(digest);
+                    if !keep {
+                        removed += 1;
+                        bytes += blob.len() as u64;
+                    }
+                    keep
+                }),
+        }
+        counts.retain(|_, count| *count > 0);
+        Ok((removed, bytes))
+    }
+}
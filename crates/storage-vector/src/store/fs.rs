@@ -1,8 +1,8 @@
 use std::fs::{self, File};
-use std::io::{Read, Write};
+use std::io::Read;
 use std::path::{Path, PathBuf};
 
-fn encode_component(s: &str) -> String {
+pub fn encode_component(s: &str) -> String {
     // simple percent-encoding for path safety
     s.bytes()
         .flat_map(|b| match b {
@@ -13,9 +13,90 @@ fn encode_component(s: &str) -> String {
         .collect()
 }
 
-pub fn make_path(root: &Path, repo_id: &str, key: &str) -> PathBuf {
+/// File in a repository's directory holding its persisted vector index.
+/// Encoded keys only use `%` before two hex digits, so no payload can
+/// collide with it.
+pub const VECTOR_INDEX_FILE: &str = "%vectors";
+
+/// File in a repository's directory holding the deadlines of its records.
+pub const EXPIRY_FILE: &str = "%expiry";
+
+pub fn expiry_path(root: &Path, repo_id: &str) -> PathBuf {
+    repo_dir(root, repo_id).join(EXPIRY_FILE)
+}
+
+/// Write-ahead log in the store root, next to the repository directories.
+pub const WAL_FILE: &str = "%wal";
+
+/// Directory in the store root holding deduplicated payloads by digest.
+pub const BLOB_DIR: &str = "%blobs";
+
+pub fn blob_path(root: &Path, digest: &str) -> PathBuf {
+    root.join(BLOB_DIR).join(digest)
+}
+
+/// Prefix of a deleted payload kept until compaction.
+pub const TOMBSTONE_PREFIX: &str = "%tomb-";
+
+pub fn tombstone_path(root: &Path, repo_id: &str, key: &str) -> PathBuf {
+    repo_dir(root, repo_id).join(format!("{TOMBSTONE_PREFIX}{}", encode_component(key)))
+}
+
+pub fn vector_index_path(root: &Path, repo_id: &str) -> PathBuf {
+    root.join(encode_component(repo_id)).join(VECTOR_INDEX_FILE)
+}
+
+/// Reverse of the path encoding; `None` for names no key encodes to, such
+/// as the reserved `%`-prefixed files.
+pub fn decode_component(name: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(name.len());
+    let mut rest = name.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        match b {
+            b'%' => {
+                let hex = tail.get(..2)?;
+                if !hex.iter().all(u8::is_ascii_hexdigit) {
+                    return None;
+                }
+                bytes.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(b);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Decoded names of the entries in `dir` that are directories (`dirs`) or
+/// files; an absent directory lists as empty.
+pub fn list_decoded(dir: &Path, dirs: bool) -> std::io::Result<Vec<String>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut names = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() != dirs {
+            continue;
+        }
+        if let Some(name) = entry.file_name().to_str().and_then(decode_component) {
+            names.push(name);
+        }
+    }
+    Ok(names)
+}
+
+pub fn repo_dir(root: &Path, repo_id: &str) -> PathBuf {
     root.join(encode_component(repo_id))
-        .join(encode_component(key))
+}
+
+pub fn make_path(root: &Path, repo_id: &str, key: &str) -> PathBuf {
+    repo_dir(root, repo_id).join(encode_component(key))
 }
 
 pub fn atomic_write_bytes(
@@ -24,20 +105,13 @@ pub fn atomic_write_bytes(
     key: &str,
     bytes: &[u8],
 ) -> std::io::Result<()> {
-    let path = make_path(root, repo_id, key);
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)?;
-    }
-    let tmp = path.with_extension("tmp");
-    {
-        let mut f = File::create(&tmp)?;
-        f.write_all(bytes)?;
-        f.sync_all()?;
-    }
-    fs::rename(tmp, &path)?;
-    Ok(())
+    atomic_write(&make_path(root, repo_id, key), bytes)
 }
 
+// `TMP_PREFIX` cannot occur in an encoded key, so staging never clobbers a
+// stored payload.
+pub use storage_atomic::{stage, sync_dir, write as atomic_write, TMP_PREFIX};
+
 pub fn read_bytes(root: &Path, repo_id: &str, key: &str) -> std::io::Result<Vec<u8>> {
     let path = make_path(root, repo_id, key);
     let mut buf = Vec::new();
@@ -0,0 +1,250 @@
+//! Opt-in background maintenance of a store.
+//!
+//! A pass garbage-collects tombstones and unreferenced blobs, rewrites
+//! sparse segments, and removes staging files an interrupted write left
+//! behind. Passes pace their deletes and rewrites to
+//! [`MaintenanceConfig::max_bytes_per_second`], releasing store locks while
+//! they wait, and report what they did as a `storage.maintenance` tracing
+//! event.
+
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use super::segment::SegmentStore;
+use super::{fs, CompactionReport, VectorStore};
+use crate::config::MaintenanceConfig;
+use crate::error::StoreError;
+
+/// Outcome of one maintenance pass.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MaintenanceReport {
+    /// Records deleted because their time-to-live ran out.
+    pub records_expired: usize,
+    /// Tombstones and blobs collected, segments rewritten.
+    pub compaction: CompactionReport,
+    /// Orphaned staging files removed.
+    pub tmp_files_removed: usize,
+    pub tmp_bytes_removed: u64,
+    /// Wall time of the pass, including `throttled`.
+    pub elapsed: Duration,
+    /// Time spent waiting to stay within the IO budget.
+    pub throttled: Duration,
+}
+
+/// Paces the IO of one pass to a byte budget.
+pub(crate) struct Throttle {
+    max_bytes_per_second: Option<u64>,
+    started: Instant,
+    bytes: u64,
+    slept: Duration,
+}
+
+impl Throttle {
+    pub(crate) fn new(max_bytes_per_second: Option<u64>) -> Self {
+        Self {
+            max_bytes_per_second: max_bytes_per_second.filter(|limit| *limit > 0),
+            started: Instant::now(),
+            bytes: 0,
+            slept: Duration::ZERO,
+        }
+    }
+
+    /// Count `bytes` of IO, sleeping until the pass is back within budget.
+    /// Callers must not hold a store lock.
+    pub(crate) fn consume(&mut self, bytes: u64) {
+        self.bytes += bytes;
+        let Some(limit) = self.max_bytes_per_second else {
+            return;
+        };
+        let due = Duration::from_secs_f64(self.bytes as f64 / limit as f64);
+        if let Some(wait) = due.checked_sub(self.started.elapsed()) {
+            std::thread::sleep(wait);
+            self.slept += wait;
+        }
+    }
+}
+
+/// Remove staging files under `dir` last modified `max_age` or longer ago.
+fn remove_orphaned_tmp(
+    dir: &Path,
+    max_age: Duration,
+    throttle: &mut Throttle,
+    report: &mut MaintenanceReport,
+) -> std::io::Result<()> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    for entry in entries {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        if meta.is_dir() {
+            remove_orphaned_tmp(&entry.path(), max_age, throttle, report)?;
+            continue;
+        }
+        if !entry
+            .file_name()
+            .to_string_lossy()
+            .starts_with(fs::TMP_PREFIX)
+        {
+            continue;
+        }
+        // Unknown ages count as fresh: the file may belong to a write in
+        // progress.
+        let age = meta
+            .modified()
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .unwrap_or_default();
+        if age < max_age {
+            continue;
+        }
+        match std::fs::remove_file(entry.path()) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        }
+        report.tmp_files_removed += 1;
+        report.tmp_bytes_removed += meta.len();
+        throttle.consume(meta.len());
+    }
+    Ok(())
+}
+
+/// Background maintenance started by `spawn_maintenance`; dropping it
+/// stops the thread after any pass in progress.
+pub struct MaintenanceTask {
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+    last: Arc<Mutex<Option<MaintenanceReport>>>,
+}
+
+impl MaintenanceTask {
+    /// Report of the latest successful pass, if one has finished.
+    pub fn last_report(&self) -> Option<MaintenanceReport> {
+        self.last.lock().ok()?.clone()
+    }
+}
+
+impl Drop for MaintenanceTask {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Run `pass` every `every` on a background thread.
+fn spawn(
+    store: &'static str,
+    every: Duration,
+    mut pass: impl FnMut() -> Result<MaintenanceReport, StoreError> + Send + 'static,
+) -> MaintenanceTask {
+    let (stop, stopped) = mpsc::channel::<()>();
+    let last = Arc::new(Mutex::new(None));
+    let shared = Arc::clone(&last);
+    let thread = std::thread::spawn(move || {
+        while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(every) {
+            match pass() {
+                Ok(report) => {
+                    tracing::info!(
+                        store,
+                        records_expired = report.records_expired,
+                        tombstones_removed = report.compaction.tombstones_removed,
+                        blobs_removed = report.compaction.blobs_removed,
+                        segments_merged = report.compaction.segments_merged,
+                        reclaimed_bytes = report.compaction.reclaimed_bytes,
+                        tmp_files_removed = report.tmp_files_removed,
+                        tmp_bytes_removed = report.tmp_bytes_removed,
+                        elapsed_ms = report.elapsed.as_millis() as u64,
+                        throttled_ms = report.throttled.as_millis() as u64,
+                        "storage.maintenance"
+                    );
+                    if let Ok(mut last) = shared.lock() {
+                        *last = Some(report);
+                    }
+                }
+                Err(err) => tracing::warn!(store, error = %err, "storage.maintenance failed"),
+            }
+        }
+    });
+    MaintenanceTask {
+        stop: Some(stop),
+        thread: Some(thread),
+        last,
+    }
+}
+
+/// Run `collect` with a fresh throttle, then sweep `dir` for orphaned
+/// staging files.
+fn run(
+    dir: Option<&Path>,
+    config: &MaintenanceConfig,
+    collect: impl FnOnce(&mut Throttle, &mut MaintenanceReport) -> Result<(), StoreError>,
+) -> Result<MaintenanceReport, StoreError> {
+    let started = Instant::now();
+    let mut throttle = Throttle::new(config.max_bytes_per_second);
+    let mut report = MaintenanceReport::default();
+    collect(&mut throttle, &mut report)?;
+    if let Some(dir) = dir {
+        remove_orphaned_tmp(dir, config.tmp_max_age, &mut throttle, &mut report)
+            .map_err(|e| StoreError::Io(format!("{}: {e}", dir.display())))?;
+    }
+    report.throttled = throttle.slept;
+    report.elapsed = started.elapsed();
+    Ok(report)
+}
+
+impl VectorStore {
+    /// One maintenance pass: delete expired records as
+    /// [`VectorStore::expire`] does, collect tombstones and unreferenced
+    /// blobs as [`VectorStore::compact`] does, then remove orphaned staging
+    /// files.
+    pub fn run_maintenance(
+        &self,
+        config: &MaintenanceConfig,
+    ) -> Result<MaintenanceReport, StoreError> {
+        run(self.fs_root.as_deref(), config, |throttle, report| {
+            report.records_expired = self.expire()?.len();
+            report.compaction = self.compact_throttled(throttle)?;
+            Ok(())
+        })
+    }
+
+    /// Run [`VectorStore::run_maintenance`] every `config.every` on a
+    /// background thread until the returned handle is dropped.
+    pub fn spawn_maintenance(self: &Arc<Self>, config: MaintenanceConfig) -> MaintenanceTask {
+        let store = Arc::clone(self);
+        spawn("vector", config.every, move || {
+            store.run_maintenance(&config)
+        })
+    }
+}
+
+impl SegmentStore {
+    /// One maintenance pass: rewrite sparse segments as
+    /// [`SegmentStore::compact`] does, then remove orphaned staging files.
+    pub fn run_maintenance(
+        &self,
+        config: &MaintenanceConfig,
+    ) -> Result<MaintenanceReport, StoreError> {
+        run(Some(self.dir()), config, |throttle, report| {
+            report.compaction = self.compact_throttled(throttle)?;
+            Ok(())
+        })
+    }
+
+    /// Run [`SegmentStore::run_maintenance`] every `config.every` on a
+    /// background thread until the returned handle is dropped.
+    pub fn spawn_maintenance(self: &Arc<Self>, config: MaintenanceConfig) -> MaintenanceTask {
+        let store = Arc::clone(self);
+        spawn("segment", config.every, move || {
+            store.run_maintenance(&config)
+        })
+    }
+}
@@ -1,15 +1,43 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use std::time::Duration;
 
+use crate::config::{FsyncPolicy, StoreConfig};
 use crate::error::StoreError;
 use crate::ledger::build_replay_entry;
-use storage_ledger::ReplayEntry;
+use crate::search::{
+    check_vector, BruteForceIndex, IndexBackend, Metric, SearchFilter, SearchHit, VectorMetadata,
+};
+use serde::{Deserialize, Serialize};
+use storage_ledger::{Ledger, ReplayEntry, ReplayMode, RepoSequences, SequenceGap};
 // Aliases to reduce clippy::type_complexity noise without changing behavior
 type RepoKey = (String, String);
 type Blob = Vec<u8>;
+mod dedup;
 pub mod fs;
+mod maintenance;
+mod mode;
+mod names;
+mod quota;
+#[cfg(feature = "encryption")]
+mod rotation;
+pub mod segment;
+mod snapshot;
+mod ttl;
+mod wal;
+
+pub use maintenance::{MaintenanceReport, MaintenanceTask};
+pub use mode::StoreMode;
+pub use quota::StorageUsage;
+#[cfg(feature = "encryption")]
+pub use rotation::{ReencryptJob, ReencryptProgress};
+pub use snapshot::SnapshotReport;
+pub use ttl::EXPIRED_STATUS;
+
+use maintenance::Throttle;
+use wal::{LoggedBatch, LoggedRecord, Wal};
 /// Build AEAD associated data binding: (repo_id, key_id, record_key).
 /// Encoding: u16 be repo_len | repo_bytes | u16 be key_id_len | key_id_bytes | u16 be record_key_len | record_key_bytes.
 pub fn build_aad(repo_id: &str, key_id: &str, record_key: &str) -> Vec<u8> {
@@ -26,11 +54,192 @@ pub fn build_aad(repo_id: &str, key_id: &str, record_key: &str) -> Vec<u8> {
     out
 }
 
+/// Store namespace holding the records of `repo_id` for `tenant_id`:
+/// `<repo_id>@<tenant_id>`, or the bare `repo_id` without a tenant. The
+/// tenant thus ends up in every record path and in the AEAD binding, so a
+/// record cannot be read back under another tenant.
+///
+/// `%` and `@` inside either id are percent-escaped, so a namespace holds an
+/// `@` only when it names a tenant and no repository id can pose as another
+/// repository's tenant namespace.
+#[must_use]
+pub fn tenant_namespace(tenant_id: Option<&str>, repo_id: &str) -> String {
+    let escape = |id: &str| id.replace('%', "%25").replace('@', "%40");
+    match tenant_id {
+        Some(tenant_id) => format!("{}@{}", escape(repo_id), escape(tenant_id)),
+        None => escape(repo_id),
+    }
+}
+
+/// Checksum recorded for a key that has no value.
+pub const ABSENT_CHECKSUM: &str = "absent";
+
+/// Status of the replay entry [`Store::delete`] returns.
+pub const TOMBSTONE_STATUS: &str = "tombstone";
+
+/// Change described by a [`ReplayRecord`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum ReplayOp {
+    Put {
+        key: String,
+        payload: Vec<u8>,
+    },
+    /// Tombstone: the key is removed.
+    Delete {
+        key: String,
+    },
+}
+
+impl ReplayOp {
+    pub fn key(&self) -> &str {
+        match self {
+            Self::Put { key, .. } | Self::Delete { key } => key,
+        }
+    }
+}
+
+/// Replay entry carrying the plaintext change it describes, so
+/// [`Store::replay_records`] can restore the store's contents.
+///
+/// The entry's checksums are of the key's value before and after the change
+/// ([`ABSENT_CHECKSUM`] when there is none).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayRecord {
+    pub entry: ReplayEntry,
+    pub op: ReplayOp,
+}
+
+impl ReplayRecord {
+    /// Record for a write that returned `entry` from [`Store::upsert`].
+    pub fn put(entry: ReplayEntry, key: impl Into<String>, payload: impl Into<Vec<u8>>) -> Self {
+        Self {
+            entry,
+            op: ReplayOp::Put {
+                key: key.into(),
+                payload: payload.into(),
+            },
+        }
+    }
+
+    pub fn delete(entry: ReplayEntry, key: impl Into<String>) -> Self {
+        Self {
+            entry,
+            op: ReplayOp::Delete { key: key.into() },
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct ReplayStats {
     pub applied: usize,
     pub skipped: usize,
     pub max_sequence: Option<u64>,
+    /// Per-repository sequence ranges the replay skipped over; only
+    /// populated in [`ReplayMode::Strict`].
+    pub gaps: Vec<SequenceGap>,
+}
+
+/// Outcome of [`VectorStore::compact`] and [`segment::SegmentStore::compact`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompactionReport {
+    pub tombstones_removed: usize,
+    /// Bytes of deleted payloads released.
+    pub reclaimed_bytes: u64,
+    /// Segment files rewritten and removed.
+    pub segments_merged: usize,
+    /// Deduplicated payload blobs no key referenced any more.
+    pub blobs_removed: usize,
+}
+
+/// One page of [`Store::list_keys`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyPage {
+    /// Keys in ascending order.
+    pub keys: Vec<String>,
+    /// Pass back as the cursor to fetch the next page; `None` on the last.
+    pub next_cursor: Option<String>,
+}
+
+/// Record [`Store::verify`] found damaged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorruptRecord {
+    pub key: String,
+    /// Why the record cannot be trusted, as the failed check reported it.
+    pub reason: String,
+}
+
+/// Outcome of [`Store::verify`] for one repository.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IntegrityReport {
+    pub repo_id: String,
+    /// Live records examined.
+    pub checked: usize,
+    pub corrupt: Vec<CorruptRecord>,
+}
+
+impl IntegrityReport {
+    pub fn is_clean(&self) -> bool {
+        self.corrupt.is_empty()
+    }
+}
+
+/// Keys [`Scan`] fetches per [`Store::list_keys`] call.
+const SCAN_PAGE: usize = 256;
+
+/// Iterator over the records of one repository in key order; see
+/// [`Store::scan`]. Keys deleted while the scan runs are skipped.
+pub struct Scan<'a, S> {
+    store: &'a S,
+    repo_id: String,
+    page: std::vec::IntoIter<String>,
+    cursor: Option<String>,
+    exhausted: bool,
+}
+
+impl<'a, S: Store> Scan<'a, S> {
+    pub fn new(store: &'a S, repo_id: &str) -> Self {
+        Self {
+            store,
+            repo_id: repo_id.to_string(),
+            page: Vec::new().into_iter(),
+            cursor: None,
+            exhausted: false,
+        }
+    }
+}
+
+impl<S: Store> Iterator for Scan<'_, S> {
+    type Item = Result<(String, Vec<u8>), StoreError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(key) = self.page.next() {
+                match self.store.get(&self.repo_id, &key) {
+                    Ok(Some(payload)) => return Some(Ok((key, payload))),
+                    Ok(None) => continue,
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+            if self.exhausted {
+                return None;
+            }
+            match self
+                .store
+                .list_keys(&self.repo_id, "", self.cursor.as_deref(), SCAN_PAGE)
+            {
+                Ok(page) => {
+                    self.exhausted = page.next_cursor.is_none();
+                    self.cursor = page.next_cursor;
+                    self.page = page.keys.into_iter();
+                }
+                Err(e) => {
+                    self.exhausted = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
 }
 
 /// Minimal store abstraction for Milestone 3.
@@ -44,6 +253,111 @@ pub trait Store: Send + Sync {
         &self,
         entries: I,
     ) -> Result<ReplayStats, StoreError>;
+    /// Restore payloads and tombstones in sequence order. Records the store
+    /// already reflects are counted as skipped, so replay is idempotent; a
+    /// record whose checksums do not match the payload or the current value
+    /// fails with [`StoreError::Integrity`].
+    fn replay_records<I: IntoIterator<Item = ReplayRecord>>(
+        &self,
+        records: I,
+    ) -> Result<ReplayStats, StoreError> {
+        let _ = records.into_iter();
+        Err(StoreError::Unsupported("payload replay".into()))
+    }
+    /// Remove a key, returning a [`TOMBSTONE_STATUS`] replay entry whose
+    /// after checksum is [`ABSENT_CHECKSUM`]. Deleting a missing key still
+    /// records a tombstone.
+    fn delete(&self, repo_id: &str, key: &str) -> Result<ReplayEntry, StoreError> {
+        let _ = (repo_id, key);
+        Err(StoreError::Unsupported("delete".into()))
+    }
+    /// Repositories holding at least one live key, in ascending order.
+    fn list_repos(&self) -> Result<Vec<String>, StoreError> {
+        Err(StoreError::Unsupported("listing".into()))
+    }
+    /// Up to `limit` live keys of `repo_id` starting with `prefix`, in
+    /// ascending order after `cursor` (a [`KeyPage::next_cursor`]). A
+    /// `limit` of zero is treated as one.
+    fn list_keys(
+        &self,
+        repo_id: &str,
+        prefix: &str,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<KeyPage, StoreError> {
+        let _ = (repo_id, prefix, cursor, limit);
+        Err(StoreError::Unsupported("listing".into()))
+    }
+    /// Stream the records of `repo_id`, fetching keys a page at a time.
+    fn scan(&self, repo_id: &str) -> Scan<'_, Self>
+    where
+        Self: Sized,
+    {
+        Scan::new(self, repo_id)
+    }
+    /// Read every live record of `repo_id` and report those that fail to
+    /// decode, authenticate or match their checksum, rather than leaving
+    /// corruption to be found by the next read. I/O errors abort the walk.
+    fn verify(&self, repo_id: &str) -> Result<IntegrityReport, StoreError> {
+        let mut report = IntegrityReport {
+            repo_id: repo_id.to_string(),
+            ..IntegrityReport::default()
+        };
+        let mut cursor = None;
+        loop {
+            let page = self.list_keys(repo_id, "", cursor.as_deref(), SCAN_PAGE)?;
+            for key in page.keys {
+                match self.get(repo_id, &key) {
+                    // Deleted since the page was listed.
+                    Ok(None) => continue,
+                    Ok(Some(_)) => {}
+                    Err(e @ StoreError::Io(_)) => return Err(e),
+                    Err(e) => report.corrupt.push(CorruptRecord {
+                        key,
+                        reason: e.to_string(),
+                    }),
+                }
+                report.checked += 1;
+            }
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => return Ok(report),
+            }
+        }
+    }
+    /// Upsert several `(key, payload)` records of one repository, returning
+    /// one replay entry per record in input order. A key repeated in the
+    /// batch ends up with its last payload.
+    fn upsert_batch<K: AsRef<str>, P: AsRef<[u8]>>(
+        &self,
+        repo_id: &str,
+        records: &[(K, P)],
+    ) -> Result<Vec<ReplayEntry>, StoreError> {
+        records
+            .iter()
+            .map(|(key, payload)| self.upsert(repo_id, key.as_ref(), payload.as_ref()))
+            .collect()
+    }
+    /// Fetch the latest payloads for `keys`, in the same order.
+    fn get_many<K: AsRef<str>>(
+        &self,
+        repo_id: &str,
+        keys: &[K],
+    ) -> Result<Vec<Option<Vec<u8>>>, StoreError> {
+        keys.iter()
+            .map(|key| self.get(repo_id, key.as_ref()))
+            .collect()
+    }
+}
+
+/// Vectors of one repository; the first insert fixes the dimension.
+#[derive(Serialize, Deserialize)]
+struct RepoIndex {
+    dimension: usize,
+    index: IndexBackend,
+    /// Changed since it was last persisted.
+    #[serde(skip)]
+    dirty: bool,
 }
 
 /// An in-memory store stub to enable TDD. Future work may add FS-backed shards.
@@ -51,10 +365,39 @@ pub struct VectorStore {
     inner: Arc<Mutex<HashMap<RepoKey, Blob>>>,
     next_sequence: AtomicU64,
     fs_root: Option<PathBuf>,
+    replay_mode: ReplayMode,
+    /// Highest sequence written or replayed per repository.
+    sequences: Mutex<RepoSequences>,
+    metric: Metric,
+    config: StoreConfig,
+    vectors: Mutex<HashMap<String, RepoIndex>>,
+    /// Deleted in-memory payloads awaiting compaction.
+    tombstones: Mutex<HashMap<RepoKey, Blob>>,
+    wal: Option<Mutex<Wal>>,
+    /// Blob references per digest when payloads are deduplicated.
+    refs: Mutex<dedup::RefCounts>,
+    /// In-memory blobs by digest when payloads are deduplicated.
+    blobs: Mutex<HashMap<String, Blob>>,
+    /// Quotas in force: those of `config` until `set_quotas` replaces them.
+    quotas: RwLock<quota::Quotas>,
+    /// Usage of repositories with a quota, measured when first needed.
+    usage: Mutex<HashMap<String, quota::RepoUsage>>,
+    /// Entries of logged batches redone since the last `take_recovered`.
+    recovered: Mutex<Vec<ReplayEntry>>,
+    /// Record deadlines per repository, loaded when first needed.
+    deadlines: Mutex<HashMap<String, ttl::Deadlines>>,
+    /// Ledger receiving the entries of expired records.
+    expiry_ledger: Option<Arc<Ledger>>,
+    mode: mode::ModeFlag,
     #[cfg(feature = "encryption")]
     encrypter: Option<Arc<dyn crate::encryption::Encrypter + Send + Sync>>,
     #[cfg(feature = "encryption")]
     kms: Option<Arc<dyn crate::kms::KeyManager + Send + Sync>>,
+    /// Hash key and plain names when `config.hashed_paths` is set.
+    #[cfg(feature = "encryption")]
+    names: names::HashedNames,
+    /// Whether the on-disk naming was found to match `config.hashed_paths`.
+    layout_checked: AtomicBool,
 }
 
 impl Default for VectorStore {
@@ -69,153 +412,1028 @@ impl VectorStore {
             inner: Arc::new(Mutex::new(HashMap::new())),
             next_sequence: AtomicU64::new(1),
             fs_root: None,
+            replay_mode: ReplayMode::Lenient,
+            sequences: Mutex::new(RepoSequences::new()),
+            metric: Metric::default(),
+            config: StoreConfig::default(),
+            quotas: RwLock::default(),
+            vectors: Mutex::new(HashMap::new()),
+            tombstones: Mutex::new(HashMap::new()),
+            wal: None,
+            refs: Mutex::new(None),
+            blobs: Mutex::new(HashMap::new()),
+            usage: Mutex::new(HashMap::new()),
+            recovered: Mutex::new(Vec::new()),
+            deadlines: Mutex::new(HashMap::new()),
+            expiry_ledger: None,
+            mode: mode::ModeFlag::default(),
             #[cfg(feature = "encryption")]
             encrypter: None,
             #[cfg(feature = "encryption")]
             kms: None,
+            #[cfg(feature = "encryption")]
+            names: names::HashedNames::default(),
+            layout_checked: AtomicBool::new(false),
         }
     }
 
-    fn checksum_placeholder(bytes: &[u8]) -> String {
-        // Keep cheap and deterministic to avoid pulling hashing deps in the skeleton.
-        format!("len:{}", bytes.len())
+    /// Checksum of a plaintext value as recorded in replay entries, or
+    /// [`ABSENT_CHECKSUM`] when the key has no value.
+    fn checksum(bytes: Option<&[u8]>) -> String {
+        match bytes {
+            Some(bytes) => format!("blake3:{}", blake3::hash(bytes).to_hex()),
+            None => ABSENT_CHECKSUM.to_string(),
+        }
     }
 
-    /// Create a filesystem-backed store using the given root directory.
-    pub fn with_fs_root(root: impl Into<PathBuf>) -> Self {
-        let mut s = Self::new();
-        s.fs_root = Some(root.into());
-        s
+    /// [`Store::upsert`] giving the record a deadline `ttl` from now, or
+    /// none when `ttl` is `None`.
+    fn upsert_expiring(
+        &self,
+        repo_id: &str,
+        key: &str,
+        payload: &[u8],
+        ttl: Option<Duration>,
+    ) -> Result<ReplayEntry, StoreError> {
+        let _wal = self.lock_wal()?;
+        self.check_writable()?;
+        let before = Self::checksum(self.get(repo_id, key)?.as_deref());
+        self.write_payload(repo_id, key, payload)?;
+        self.set_deadlines(repo_id, &[key], ttl)?;
+        let after = Self::checksum(Some(payload));
+        let seq = self.next_sequence.fetch_add(1, Ordering::SeqCst);
+        let entry = build_replay_entry(seq, repo_id, &before, &after, "emitted");
+        self.record_sequence(&entry)?;
+        Ok(entry)
     }
 
-    #[cfg(feature = "encryption")]
-    pub fn builder() -> VectorStoreBuilder {
-        VectorStoreBuilder::default()
+    /// [`Store::upsert`] with a time-to-live of its own, overriding the
+    /// repository default.
+    pub fn upsert_with_ttl(
+        &self,
+        repo_id: &str,
+        key: &str,
+        payload: &[u8],
+        ttl: Duration,
+    ) -> Result<ReplayEntry, StoreError> {
+        self.upsert_expiring(repo_id, key, payload, Some(ttl))
     }
-}
 
-impl Store for VectorStore {
-    fn upsert(&self, repo_id: &str, key: &str, payload: &[u8]) -> Result<ReplayEntry, StoreError> {
-        let before = Self::checksum_placeholder(payload);
+    /// The stored value of `key`, whether or not it has expired.
+    fn read_value(&self, repo_id: &str, key: &str) -> Result<Option<Vec<u8>>, StoreError> {
+        match self.read_stored(repo_id, key)? {
+            Some(bytes) => self.open(repo_id, key, bytes).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Store `payload`, sealing it first when encryption is configured.
+    fn write_payload(&self, repo_id: &str, key: &str, payload: &[u8]) -> Result<(), StoreError> {
+        let Some(mut refs) = self.lock_refs()? else {
+            let bytes = self.seal(repo_id, key, payload)?;
+            return self.within_quota(repo_id, &[(key, bytes.len() as u64)], || {
+                self.write_bytes(repo_id, key, bytes)
+            });
+        };
+        let old = self.read_raw(repo_id, key)?.unwrap_or_default();
+        let bytes = self.intern(payload)?;
+        self.within_quota(repo_id, &[(key, payload.len() as u64)], || {
+            self.write_bytes(repo_id, key, bytes.clone())
+        })?;
+        Self::retarget(&mut refs, &old, Some(&bytes));
+        Ok(())
+    }
+
+    /// Bytes to persist for `payload`: an envelope bound to `repo_id` and
+    /// `key` when encryption is configured, the payload itself otherwise.
+    #[cfg_attr(not(feature = "encryption"), allow(unused_variables))]
+    fn seal(&self, repo_id: &str, key: &str, payload: &[u8]) -> Result<Vec<u8>, StoreError> {
         #[cfg(feature = "encryption")]
         if let (Some(enc), Some(kms)) = (&self.encrypter, &self.kms) {
             let scope = crate::kms::KeyScope {
                 repo_id: repo_id.to_string(),
             };
-            let kh = kms.current(&scope).map_err(StoreError::Key)?;
+            let kh = match kms
+                .rotate_if_needed(&scope, &self.config.rotation)
+                .map_err(StoreError::Key)?
+            {
+                Some(kh) => {
+                    tracing::info!(repo_id, key_id = %kh.key_id, "rotated payload key");
+                    kh
+                }
+                None => kms.current(&scope).map_err(StoreError::Key)?,
+            };
             let aad = build_aad(repo_id, &kh.key_id, key);
             let sealed = enc
                 .seal(&kh, payload, &aad)
                 .map_err(StoreError::Encryption)?;
-            if let Some(root) = &self.fs_root {
-                fs::atomic_write_bytes(root, repo_id, key, &sealed)
-                    .map_err(|e| StoreError::Io(e.to_string()))?;
-            } else {
-                let mut guard = self
-                    .inner
-                    .lock()
-                    .map_err(|e| StoreError::Io(e.to_string()))?;
-                guard.insert((repo_id.to_string(), key.to_string()), sealed);
+            kms.record_use(&kh.key_id);
+            return Ok(sealed);
+        }
+        Ok(payload.to_vec())
+    }
+
+    /// Reverse of [`VectorStore::seal`].
+    #[cfg_attr(not(feature = "encryption"), allow(unused_variables))]
+    fn open(&self, repo_id: &str, key: &str, bytes: Vec<u8>) -> Result<Vec<u8>, StoreError> {
+        #[cfg(feature = "encryption")]
+        if let (Some(enc), Some(kms)) = (&self.encrypter, &self.kms) {
+            if let Some(kid) = crate::encryption::peek_key_id(&bytes) {
+                let kh = kms.get(&kid).map_err(StoreError::Key)?;
+                let aad = build_aad(repo_id, &kh.key_id, key);
+                return enc.open(&kh, &bytes, &aad).map_err(StoreError::Encryption);
             }
-            let after = Self::checksum_placeholder(payload);
-            let seq = self.next_sequence.fetch_add(1, Ordering::SeqCst);
-            return Ok(build_replay_entry(seq, repo_id, &before, &after, "emitted"));
+            // Encryption is configured but the stored bytes are not a valid envelope.
+            // Treat as corruption/tampering rather than passing plaintext through.
+            return Err(StoreError::Encryption(
+                "missing or invalid envelope".to_string(),
+            ));
         }
-        // Plaintext path
+        Ok(bytes)
+    }
+
+    /// Bytes stored for `key`, still sealed, with a blob reference
+    /// resolved.
+    fn read_stored(&self, repo_id: &str, key: &str) -> Result<Option<Vec<u8>>, StoreError> {
+        self.read_raw(repo_id, key)?
+            .map(|bytes| self.resolve(bytes))
+            .transpose()
+    }
+
+    /// Bytes stored under `key` itself.
+    fn read_raw(&self, repo_id: &str, key: &str) -> Result<Option<Vec<u8>>, StoreError> {
+        // Prefer filesystem when configured, otherwise in-memory map.
+        if let Some(root) = &self.fs_root {
+            match std::fs::read(self.record_path(root, repo_id, key)?) {
+                Ok(b) => return Ok(Some(b)),
+                // fallback to memory if present
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(StoreError::Io(e.to_string())),
+            }
+        }
+        let guard = self
+            .inner
+            .lock()
+            .map_err(|e| StoreError::Io(e.to_string()))?;
+        Ok(guard.get(&(repo_id.to_string(), key.to_string())).cloned())
+    }
+
+    fn write_bytes(&self, repo_id: &str, key: &str, bytes: Vec<u8>) -> Result<(), StoreError> {
         if let Some(root) = &self.fs_root {
-            fs::atomic_write_bytes(root, repo_id, key, payload)
+            self.name_record(root, repo_id, key)?;
+            fs::atomic_write(&self.record_path(root, repo_id, key)?, &bytes)
                 .map_err(|e| StoreError::Io(e.to_string()))?;
         } else {
             let mut guard = self
                 .inner
                 .lock()
                 .map_err(|e| StoreError::Io(e.to_string()))?;
-            guard.insert((repo_id.to_string(), key.to_string()), payload.to_vec());
+            guard.insert((repo_id.to_string(), key.to_string()), bytes);
         }
-        let seq = self.next_sequence.fetch_add(1, Ordering::SeqCst);
-        Ok(build_replay_entry(
-            seq, repo_id, &before, &before, "emitted",
-        ))
+        Ok(())
     }
 
-    fn get(&self, repo_id: &str, key: &str) -> Result<Option<Vec<u8>>, StoreError> {
-        // Prefer filesystem when configured, otherwise in-memory map.
-        let bytes = if let Some(root) = &self.fs_root {
-            match fs::read_bytes(root, repo_id, key) {
-                Ok(b) => b,
-                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-                    // fallback to memory if present
-                    let guard = self
-                        .inner
-                        .lock()
-                        .map_err(|e| StoreError::Io(e.to_string()))?;
-                    match guard.get(&(repo_id.to_string(), key.to_string())) {
-                        Some(b) => b.clone(),
-                        None => return Ok(None),
+    /// Store several sealed payloads of `repo_id` under one lock or, on the
+    /// filesystem, in one pass: every file is staged before any is renamed
+    /// into place, so a failed write leaves the previous values visible.
+    /// Without `sync` the files are left to the page cache.
+    fn write_batch(
+        &self,
+        repo_id: &str,
+        writes: HashMap<&str, Vec<u8>>,
+        sync: bool,
+    ) -> Result<(), StoreError> {
+        let Some(root) = &self.fs_root else {
+            let mut guard = self
+                .inner
+                .lock()
+                .map_err(|e| StoreError::Io(e.to_string()))?;
+            for (key, bytes) in writes {
+                guard.insert((repo_id.to_string(), key.to_string()), bytes);
+            }
+            return Ok(());
+        };
+        let io = |e: std::io::Error| StoreError::Io(e.to_string());
+        let dir = self.repo_path(root, repo_id)?;
+        std::fs::create_dir_all(&dir).map_err(io)?;
+        for key in writes.keys() {
+            self.name_record(root, repo_id, key)?;
+        }
+        let mut staged = Vec::with_capacity(writes.len());
+        for (key, bytes) in &writes {
+            let path = self.record_path(root, repo_id, key)?;
+            match fs::stage(&path, bytes) {
+                Ok(tmp) => staged.push((tmp, path)),
+                Err(e) => {
+                    for (tmp, _) in staged {
+                        let _ = std::fs::remove_file(tmp);
                     }
+                    return Err(io(e));
                 }
+            }
+        }
+        if !sync {
+            for (tmp, path) in staged {
+                std::fs::rename(tmp, path).map_err(io)?;
+            }
+            return Ok(());
+        }
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = staged
+                .chunks(staged.len().div_ceil(8))
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .try_for_each(|(tmp, _)| std::fs::File::open(tmp)?.sync_all())
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .try_for_each(|worker| worker.join().expect("sync worker panicked"))
+        })
+        .map_err(io)?;
+        for (tmp, path) in staged {
+            std::fs::rename(tmp, path).map_err(io)?;
+        }
+        fs::sync_dir(&dir).map_err(io)
+    }
+
+    /// Move the payload of `key` aside until [`VectorStore::compact`] and
+    /// drop its vector, so neither `get` nor `search` returns it.
+    fn tombstone(&self, repo_id: &str, key: &str) -> Result<(), StoreError> {
+        let mut refs = self.lock_refs()?;
+        let old = match refs {
+            Some(_) => self.read_raw(repo_id, key)?,
+            None => None,
+        };
+        self.release(repo_id, key, || self.set_aside(repo_id, key))?;
+        if let (Some(refs), Some(old)) = (refs.as_mut(), old) {
+            Self::retarget(refs, &old, None);
+        }
+        self.unindex(repo_id, key)?;
+        Ok(())
+    }
+
+    /// Move the stored payload of `key` to its tombstone.
+    fn set_aside(&self, repo_id: &str, key: &str) -> Result<(), StoreError> {
+        if let Some(root) = &self.fs_root {
+            let live = self.record_path(root, repo_id, key)?;
+            match std::fs::rename(live, self.tombstone_file(root, repo_id, key)?) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
                 Err(e) => return Err(StoreError::Io(e.to_string())),
             }
-        } else {
-            let guard = self
-                .inner
+        }
+        let id = (repo_id.to_string(), key.to_string());
+        let removed = self
+            .inner
+            .lock()
+            .map_err(|e| StoreError::Io(e.to_string()))?
+            .remove(&id);
+        if let Some(bytes) = removed {
+            self.tombstones
                 .lock()
-                .map_err(|e| StoreError::Io(e.to_string()))?;
-            match guard.get(&(repo_id.to_string(), key.to_string())) {
-                Some(b) => b.clone(),
-                None => return Ok(None),
+                .map_err(|e| StoreError::Io(e.to_string()))?
+                .insert(id, bytes);
+        }
+        Ok(())
+    }
+
+    /// Lock the write-ahead log, first redoing a batch a failed write left
+    /// in it, so writes stay ordered behind logged batches.
+    fn lock_wal(&self) -> Result<Option<MutexGuard<'_, Wal>>, StoreError> {
+        let Some(wal) = &self.wal else {
+            return Ok(None);
+        };
+        let mut wal = wal.lock().map_err(|e| StoreError::Io(e.to_string()))?;
+        if wal.is_pending() {
+            self.redo_logged(&mut wal)?;
+        }
+        Ok(Some(wal))
+    }
+
+    /// Apply every complete batch in `wal` whole, keep its entries for
+    /// [`VectorStore::take_recovered`] and empty the log.
+    fn redo_logged(&self, wal: &mut Wal) -> Result<(), StoreError> {
+        let io = |e: std::io::Error| StoreError::Io(e.to_string());
+        for batch in wal.read().map_err(io)? {
+            let writes: HashMap<&str, Vec<u8>> = batch
+                .records
+                .iter()
+                .map(|record| (record.key.as_str(), record.bytes.clone()))
+                .collect();
+            self.write_batch(&batch.repo_id, writes, true)?;
+            self.forget_usage(&batch.repo_id)?;
+            self.forget_refs()?;
+            let entries: Vec<ReplayEntry> = batch
+                .records
+                .iter()
+                .map(|record| {
+                    build_replay_entry(
+                        record.sequence,
+                        &batch.repo_id,
+                        &record.before,
+                        &record.after,
+                        "emitted",
+                    )
+                })
+                .collect();
+            for entry in &entries {
+                self.record_sequence(entry)?;
+                self.advance_sequence_floor(entry.sequence);
             }
+            tracing::info!(
+                repo_id = %batch.repo_id,
+                records = entries.len(),
+                "redid batch from write-ahead log"
+            );
+            self.recovered
+                .lock()
+                .map_err(|e| StoreError::Io(e.to_string()))?
+                .extend(entries);
+        }
+        wal.clear(true).map_err(io)
+    }
+
+    /// Live keys of `repo_id` across the filesystem and the in-memory map.
+    fn live_keys(&self, repo_id: &str) -> Result<BTreeSet<String>, StoreError> {
+        let mut keys: BTreeSet<String> = self
+            .inner
+            .lock()
+            .map_err(|e| StoreError::Io(e.to_string()))?
+            .keys()
+            .filter(|(repo, _)| repo == repo_id)
+            .map(|(_, key)| key.clone())
+            .collect();
+        if let Some(root) = &self.fs_root {
+            keys.extend(self.stored_keys(root, repo_id)?);
+        }
+        Ok(keys)
+    }
+
+    /// Garbage-collect the payloads of deleted keys.
+    pub fn compact(&self) -> Result<CompactionReport, StoreError> {
+        self.compact_throttled(&mut Throttle::new(None))
+    }
+
+    /// [`VectorStore::compact`], pacing tombstone file removal with
+    /// `throttle`.
+    fn compact_throttled(&self, throttle: &mut Throttle) -> Result<CompactionReport, StoreError> {
+        self.check_maintainable()?;
+        let io = |e: std::io::Error| StoreError::Io(e.to_string());
+        let (blobs_removed, blob_bytes) = self.collect_blobs()?;
+        let mut report = CompactionReport {
+            blobs_removed,
+            reclaimed_bytes: blob_bytes,
+            ..CompactionReport::default()
         };
-        #[cfg(feature = "encryption")]
-        if let (Some(enc), Some(kms)) = (&self.encrypter, &self.kms) {
-            if let Some(kid) = crate::encryption::peek_key_id(&bytes) {
-                let kh = kms.get(&kid).map_err(StoreError::Key)?;
-                let aad = build_aad(repo_id, &kh.key_id, key);
-                let pt = enc
-                    .open(&kh, &bytes, &aad)
-                    .map_err(StoreError::Encryption)?;
-                return Ok(Some(pt));
+        for (_, bytes) in self
+            .tombstones
+            .lock()
+            .map_err(|e| StoreError::Io(e.to_string()))?
+            .drain()
+        {
+            report.tombstones_removed += 1;
+            report.reclaimed_bytes += bytes.len() as u64;
+        }
+        let Some(root) = &self.fs_root else {
+            return Ok(report);
+        };
+        let repos = match std::fs::read_dir(root) {
+            Ok(repos) => repos,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(report),
+            Err(e) => return Err(io(e)),
+        };
+        for repo in repos {
+            let repo = repo.map_err(io)?;
+            if !repo.file_type().map_err(io)?.is_dir() {
+                continue;
             }
-            // Encryption is configured but the stored bytes are not a valid envelope.
-            // Treat as corruption/tampering rather than passing plaintext through.
-            return Err(StoreError::Encryption(
-                "missing or invalid envelope".to_string(),
+            for file in std::fs::read_dir(repo.path()).map_err(io)? {
+                let file = file.map_err(io)?;
+                if !file
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with(fs::TOMBSTONE_PREFIX)
+                {
+                    continue;
+                }
+                let len = file.metadata().map_err(io)?.len();
+                std::fs::remove_file(file.path()).map_err(io)?;
+                report.tombstones_removed += 1;
+                report.reclaimed_bytes += len;
+                throttle.consume(len);
+            }
+        }
+        Ok(report)
+    }
+
+    /// Checksum of the value `record` leaves behind, after checking the
+    /// payload it carries matches the entry.
+    fn record_target(record: &ReplayRecord) -> Result<String, StoreError> {
+        let target = match &record.op {
+            ReplayOp::Put { payload, .. } => Self::checksum(Some(payload)),
+            ReplayOp::Delete { .. } => ABSENT_CHECKSUM.to_string(),
+        };
+        if target != record.entry.payload_checksum_after {
+            return Err(StoreError::Integrity(format!(
+                "replay entry {} for {}/{} does not match its after checksum",
+                record.entry.sequence,
+                record.entry.repo_id,
+                record.op.key()
+            )));
+        }
+        Ok(target)
+    }
+
+    /// Record replayed sequences, validating them in [`ReplayMode::Strict`].
+    fn check_sequences<'a>(
+        &self,
+        entries: impl IntoIterator<Item = &'a ReplayEntry>,
+        stats: &mut ReplayStats,
+    ) -> Result<(), StoreError> {
+        let mut sequences = self
+            .sequences
+            .lock()
+            .map_err(|e| StoreError::Io(e.to_string()))?;
+        match self.replay_mode {
+            ReplayMode::Lenient => entries
+                .into_iter()
+                .for_each(|entry| sequences.record(entry)),
+            ReplayMode::Strict => {
+                let mut ordered: Vec<&ReplayEntry> = entries.into_iter().collect();
+                ordered.sort_by_key(|entry| entry.sequence);
+                stats.gaps = sequences
+                    .validate(ordered)
+                    .map_err(|e| StoreError::Ledger(e.to_string()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Make sure later writes are numbered after `max_sequence`.
+    fn advance_sequence_floor(&self, max_sequence: u64) {
+        let mut current = self.next_sequence.load(Ordering::SeqCst);
+        while max_sequence + 1 > current {
+            match self.next_sequence.compare_exchange(
+                current,
+                max_sequence + 1,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => break,
+                Err(now) => current = now,
+            }
+        }
+    }
+
+    /// Create a filesystem-backed store using the given root directory.
+    pub fn with_fs_root(root: impl Into<PathBuf>) -> Self {
+        let mut s = Self::new();
+        s.fs_root = Some(root.into());
+        s
+    }
+
+    /// Log batch upserts to `%wal` in the store root before applying them,
+    /// so a crash leaves either none or all of a batch in place. Batches a
+    /// previous process logged but may not have finished are redone now;
+    /// their entries never reached the caller and are handed out by
+    /// [`VectorStore::take_recovered`]. Fails without a filesystem root.
+    pub fn with_wal(mut self, policy: FsyncPolicy) -> Result<Self, StoreError> {
+        let io = |e: std::io::Error| StoreError::Io(e.to_string());
+        let Some(root) = &self.fs_root else {
+            return Err(StoreError::Unsupported(
+                "write-ahead log without a filesystem root".into(),
+            ));
+        };
+        std::fs::create_dir_all(root).map_err(io)?;
+        let mut wal = Wal::open(&root.join(fs::WAL_FILE), policy).map_err(io)?;
+        if wal.is_pending() {
+            self.redo_logged(&mut wal)?;
+        }
+        self.wal = Some(Mutex::new(wal));
+        Ok(self)
+    }
+
+    /// Replay entries of batches redone from the write-ahead log, for the
+    /// caller to append to its ledger; each is returned once.
+    pub fn take_recovered(&self) -> Vec<ReplayEntry> {
+        self.recovered
+            .lock()
+            .map(|mut recovered| std::mem::take(&mut *recovered))
+            .unwrap_or_default()
+    }
+
+    /// In [`ReplayMode::Strict`], `replay` applies entries in sequence order,
+    /// rejects a sequence already written or replayed for the same repository,
+    /// and reports skipped ranges in [`ReplayStats::gaps`].
+    pub fn with_replay_mode(mut self, mode: ReplayMode) -> Self {
+        self.replay_mode = mode;
+        self
+    }
+
+    pub fn replay_mode(&self) -> ReplayMode {
+        self.replay_mode
+    }
+
+    /// Highest sequence written or replayed for `repo_id`.
+    pub fn max_sequence(&self, repo_id: &str) -> Option<u64> {
+        self.sequences
+            .lock()
+            .map(|sequences| sequences.max_sequence(repo_id))
+            .unwrap_or(None)
+    }
+
+    /// Score search results with `metric` (cosine by default).
+    pub fn with_metric(mut self, metric: Metric) -> Self {
+        self.metric = metric;
+        self
+    }
+
+    pub fn metric(&self) -> Metric {
+        self.metric
+    }
+
+    /// Apply `config`; with the `hnsw` feature, `config.hnsw` selects the
+    /// approximate index for repositories indexed from now on.
+    pub fn with_config(mut self, config: StoreConfig) -> Self {
+        self.quotas = RwLock::new(quota::Quotas::of(&config));
+        self.config = config;
+        self
+    }
+
+    /// The configuration the store was built with; quotas replaced by
+    /// [`VectorStore::set_quotas`] are not reflected here.
+    pub fn config(&self) -> &StoreConfig {
+        &self.config
+    }
+
+    /// Empty index of the configured backend.
+    fn new_index(&self) -> IndexBackend {
+        #[cfg(feature = "hnsw")]
+        if let Some(config) = self.config.hnsw {
+            return IndexBackend::Hnsw(crate::search::HnswIndex::new(config, self.metric));
+        }
+        IndexBackend::BruteForce(BruteForceIndex::new())
+    }
+
+    /// Whether `index` was built the way this store would build it now.
+    fn index_matches_config(&self, index: &IndexBackend) -> bool {
+        match index {
+            IndexBackend::BruteForce(_) => {
+                #[cfg(feature = "hnsw")]
+                if self.config.hnsw.is_some() {
+                    return false;
+                }
+                true
+            }
+            #[cfg(feature = "hnsw")]
+            IndexBackend::Hnsw(index) => {
+                self.config.hnsw == Some(index.config()) && index.metric() == self.metric
+            }
+        }
+    }
+
+    /// Load the persisted index of `repo_id` into `vectors` unless it is
+    /// already there, rebuilding it when the configuration changed.
+    fn load_vectors(
+        &self,
+        vectors: &mut HashMap<String, RepoIndex>,
+        repo_id: &str,
+    ) -> Result<(), StoreError> {
+        let Some(root) = &self.fs_root else {
+            return Ok(());
+        };
+        if vectors.contains_key(repo_id) {
+            return Ok(());
+        }
+        let path = self.repo_path(root, repo_id)?.join(fs::VECTOR_INDEX_FILE);
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(StoreError::Io(e.to_string())),
+        };
+        let bytes = self.open(repo_id, fs::VECTOR_INDEX_FILE, bytes)?;
+        let mut repo: RepoIndex = serde_json::from_slice(&bytes).map_err(|e| {
+            StoreError::Integrity(format!("vector index of {repo_id} is unreadable: {e}"))
+        })?;
+        #[cfg(feature = "hnsw")]
+        if let IndexBackend::Hnsw(index) = &repo.index {
+            if !index.is_consistent() {
+                return Err(StoreError::Integrity(format!(
+                    "vector index of {repo_id} links to missing nodes"
+                )));
+            }
+        }
+        if !self.index_matches_config(&repo.index) {
+            let entries = std::mem::replace(&mut repo.index, self.new_index()).into_entries();
+            for (key, vector, metadata) in entries {
+                repo.index.index_mut().insert(&key, vector, metadata);
+            }
+            repo.dirty = true;
+        }
+        vectors.insert(repo_id.to_string(), repo);
+        Ok(())
+    }
+
+    /// Write every vector index changed since it was last persisted next to
+    /// the repository's payloads, sealed like a payload when encryption is
+    /// configured. Returns how many were written; in-memory stores write
+    /// nothing. Indexes are loaded back when a repository is first used.
+    pub fn persist_vectors(&self) -> Result<usize, StoreError> {
+        let Some(root) = &self.fs_root else {
+            return Ok(0);
+        };
+        self.check_maintainable()?;
+        let mut vectors = self
+            .vectors
+            .lock()
+            .map_err(|e| StoreError::Io(e.to_string()))?;
+        let mut written = 0;
+        for (repo_id, repo) in vectors.iter_mut().filter(|(_, repo)| repo.dirty) {
+            let json = serde_json::to_vec(repo).map_err(|e| StoreError::Io(e.to_string()))?;
+            let bytes = self.seal(repo_id, fs::VECTOR_INDEX_FILE, &json)?;
+            let path = self.repo_path(root, repo_id)?.join(fs::VECTOR_INDEX_FILE);
+            fs::atomic_write(&path, &bytes).map_err(|e| StoreError::Io(e.to_string()))?;
+            repo.dirty = false;
+            written += 1;
+        }
+        Ok(written)
+    }
+
+    /// Index the embedding of `key` for [`VectorStore::search`], replacing
+    /// any earlier vector for the key. All vectors of a repository must
+    /// have the same dimension.
+    pub fn insert_vector(
+        &self,
+        repo_id: &str,
+        key: &str,
+        vector: Vec<f32>,
+        metadata: VectorMetadata,
+    ) -> Result<(), StoreError> {
+        self.check_writable()?;
+        let mut vectors = self
+            .vectors
+            .lock()
+            .map_err(|e| StoreError::Io(e.to_string()))?;
+        self.load_vectors(&mut vectors, repo_id)?;
+        let dimension = vectors.get(repo_id).map(|repo| repo.dimension);
+        check_vector(&vector, dimension)?;
+        let repo = vectors
+            .entry(repo_id.to_string())
+            .or_insert_with(|| RepoIndex {
+                dimension: vector.len(),
+                index: self.new_index(),
+                dirty: true,
+            });
+        repo.index.index_mut().insert(key, vector, metadata);
+        repo.dirty = true;
+        Ok(())
+    }
+
+    /// Drop the vector of `key`; returns whether one was indexed.
+    pub fn remove_vector(&self, repo_id: &str, key: &str) -> Result<bool, StoreError> {
+        self.check_writable()?;
+        self.unindex(repo_id, key)
+    }
+
+    fn unindex(&self, repo_id: &str, key: &str) -> Result<bool, StoreError> {
+        let mut vectors = self
+            .vectors
+            .lock()
+            .map_err(|e| StoreError::Io(e.to_string()))?;
+        self.load_vectors(&mut vectors, repo_id)?;
+        let Some(repo) = vectors.get_mut(repo_id) else {
+            return Ok(false);
+        };
+        let removed = repo.index.index_mut().remove(key);
+        repo.dirty |= removed;
+        Ok(removed)
+    }
+
+    /// The `k` vectors of `repo_id` closest to `query` that pass `filter`,
+    /// best first.
+    pub fn search(
+        &self,
+        repo_id: &str,
+        query: &[f32],
+        k: usize,
+        filter: Option<&SearchFilter>,
+    ) -> Result<Vec<SearchHit>, StoreError> {
+        let mut vectors = self
+            .vectors
+            .lock()
+            .map_err(|e| StoreError::Io(e.to_string()))?;
+        self.load_vectors(&mut vectors, repo_id)?;
+        let Some(repo) = vectors.get(repo_id) else {
+            return Ok(Vec::new());
+        };
+        check_vector(query, Some(repo.dimension))?;
+        Ok(repo.index.index().search(query, k, self.metric, filter))
+    }
+
+    fn record_sequence(&self, entry: &ReplayEntry) -> Result<(), StoreError> {
+        self.sequences
+            .lock()
+            .map_err(|e| StoreError::Io(e.to_string()))?
+            .record(entry);
+        Ok(())
+    }
+
+    #[cfg(feature = "encryption")]
+    pub fn builder() -> VectorStoreBuilder {
+        VectorStoreBuilder::default()
+    }
+}
+
+impl Store for VectorStore {
+    fn upsert(&self, repo_id: &str, key: &str, payload: &[u8]) -> Result<ReplayEntry, StoreError> {
+        self.upsert_expiring(repo_id, key, payload, self.default_ttl(repo_id))
+    }
+
+    /// Expired records read as missing until [`VectorStore::expire`]
+    /// deletes them.
+    fn get(&self, repo_id: &str, key: &str) -> Result<Option<Vec<u8>>, StoreError> {
+        if self.is_expired(repo_id, key)? {
+            return Ok(None);
+        }
+        self.read_value(repo_id, key)
+    }
+
+    fn list_repos(&self) -> Result<Vec<String>, StoreError> {
+        let mut repos: BTreeSet<String> = self
+            .inner
+            .lock()
+            .map_err(|e| StoreError::Io(e.to_string()))?
+            .keys()
+            .map(|(repo, _)| repo.clone())
+            .collect();
+        if let Some(root) = &self.fs_root {
+            for repo in self.stored_repos(root)? {
+                if !repos.contains(&repo) && !self.live_keys(&repo)?.is_empty() {
+                    repos.insert(repo);
+                }
+            }
+        }
+        Ok(repos.into_iter().collect())
+    }
+
+    fn list_keys(
+        &self,
+        repo_id: &str,
+        prefix: &str,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<KeyPage, StoreError> {
+        let limit = limit.max(1);
+        let expired = self.expired_keys(repo_id)?;
+        let mut keys: Vec<String> = self
+            .live_keys(repo_id)?
+            .into_iter()
+            .filter(|key| !expired.contains(key))
+            .filter(|key| key.starts_with(prefix))
+            .filter(|key| cursor.map_or(true, |cursor| key.as_str() > cursor))
+            .take(limit.saturating_add(1))
+            .collect();
+        let next_cursor = if keys.len() > limit {
+            keys.truncate(limit);
+            keys.last().cloned()
+        } else {
+            None
+        };
+        Ok(KeyPage { keys, next_cursor })
+    }
+
+    fn delete(&self, repo_id: &str, key: &str) -> Result<ReplayEntry, StoreError> {
+        let _wal = self.lock_wal()?;
+        self.check_writable()?;
+        let before = Self::checksum(self.get(repo_id, key)?.as_deref());
+        self.tombstone(repo_id, key)?;
+        self.set_deadlines(repo_id, &[key], None)?;
+        let seq = self.next_sequence.fetch_add(1, Ordering::SeqCst);
+        let entry = build_replay_entry(seq, repo_id, &before, ABSENT_CHECKSUM, TOMBSTONE_STATUS);
+        self.record_sequence(&entry)?;
+        Ok(entry)
+    }
+
+    fn upsert_batch<K: AsRef<str>, P: AsRef<[u8]>>(
+        &self,
+        repo_id: &str,
+        records: &[(K, P)],
+    ) -> Result<Vec<ReplayEntry>, StoreError> {
+        if records.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut wal = self.lock_wal()?;
+        self.check_writable()?;
+        let mut refs = self.lock_refs()?;
+        let keys: Vec<&str> = records.iter().map(|(key, _)| key.as_ref()).collect();
+        let current = self.get_many(repo_id, &keys)?;
+        // Checksum each key holds once the earlier records of the batch land.
+        let mut pending: HashMap<&str, String> = HashMap::new();
+        let first = self
+            .next_sequence
+            .fetch_add(records.len() as u64, Ordering::SeqCst);
+        let mut logged = Vec::with_capacity(records.len());
+        // Bytes each record counts against the quota: the payload itself
+        // when it is stored as a shared blob.
+        let mut charged = Vec::with_capacity(records.len());
+        for (((key, payload), current), sequence) in records.iter().zip(current).zip(first..) {
+            let (key, payload) = (key.as_ref(), payload.as_ref());
+            let before = pending
+                .remove(key)
+                .unwrap_or_else(|| Self::checksum(current.as_deref()));
+            let after = Self::checksum(Some(payload));
+            pending.insert(key, after.clone());
+            let bytes = match refs {
+                Some(_) => self.intern(payload)?,
+                None => self.seal(repo_id, key, payload)?,
+            };
+            charged.push((
+                key,
+                if refs.is_some() {
+                    payload.len()
+                } else {
+                    bytes.len()
+                } as u64,
             ));
+            logged.push(LoggedRecord {
+                sequence,
+                key: key.to_string(),
+                before,
+                after,
+                bytes,
+            });
         }
-        Ok(Some(bytes))
+        let batch = LoggedBatch {
+            repo_id: repo_id.to_string(),
+            records: logged,
+        };
+        let old: HashMap<&str, Vec<u8>> = match refs {
+            Some(_) => charged
+                .iter()
+                .map(|(key, _)| Ok((*key, self.read_raw(repo_id, key)?.unwrap_or_default())))
+                .collect::<Result<_, StoreError>>()?,
+            None => HashMap::new(),
+        };
+        self.within_quota(repo_id, &charged, || {
+            let sync = wal.as_ref().map_or(true, |wal| wal.is_durable());
+            if let Some(wal) = wal.as_mut() {
+                wal.append(&batch)
+                    .map_err(|e| StoreError::Io(e.to_string()))?;
+            }
+            let writes: HashMap<&str, Vec<u8>> = batch
+                .records
+                .iter()
+                .map(|record| (record.key.as_str(), record.bytes.clone()))
+                .collect();
+            self.write_batch(repo_id, writes, sync)?;
+            if let Some(wal) = wal.as_mut() {
+                wal.clear(sync).map_err(|e| StoreError::Io(e.to_string()))?;
+            }
+            Ok(())
+        })?;
+        self.set_deadlines(repo_id, &keys, self.default_ttl(repo_id))?;
+        if let Some(refs) = refs.as_mut() {
+            let last: HashMap<&str, &[u8]> = batch
+                .records
+                .iter()
+                .map(|record| (record.key.as_str(), record.bytes.as_slice()))
+                .collect();
+            for (key, new) in last {
+                Self::retarget(refs, &old[key], Some(new));
+            }
+        }
+        let entries: Vec<ReplayEntry> = batch
+            .records
+            .iter()
+            .map(|record| {
+                build_replay_entry(
+                    record.sequence,
+                    repo_id,
+                    &record.before,
+                    &record.after,
+                    "emitted",
+                )
+            })
+            .collect();
+        let mut sequences = self
+            .sequences
+            .lock()
+            .map_err(|e| StoreError::Io(e.to_string()))?;
+        entries.iter().for_each(|entry| sequences.record(entry));
+        Ok(entries)
+    }
+
+    fn get_many<K: AsRef<str>>(
+        &self,
+        repo_id: &str,
+        keys: &[K],
+    ) -> Result<Vec<Option<Vec<u8>>>, StoreError> {
+        let stored: Vec<Option<Vec<u8>>> = {
+            let guard = self
+                .inner
+                .lock()
+                .map_err(|e| StoreError::Io(e.to_string()))?;
+            let memory = |key: &str| guard.get(&(repo_id.to_string(), key.to_string())).cloned();
+            keys.iter()
+                .map(|key| {
+                    let key = key.as_ref();
+                    let Some(root) = &self.fs_root else {
+                        return Ok(memory(key));
+                    };
+                    match std::fs::read(self.record_path(root, repo_id, key)?) {
+                        Ok(bytes) => Ok(Some(bytes)),
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(memory(key)),
+                        Err(e) => Err(StoreError::Io(e.to_string())),
+                    }
+                })
+                .collect::<Result<_, _>>()?
+        };
+        let expired = self.expired_keys(repo_id)?;
+        keys.iter()
+            .zip(stored)
+            .map(|(key, bytes)| {
+                bytes
+                    .filter(|_| !expired.contains(key.as_ref()))
+                    .map(|bytes| self.open(repo_id, key.as_ref(), bytes))
+                    .transpose()
+            })
+            .collect()
     }
 
     fn replay<I: IntoIterator<Item = ReplayEntry>>(
         &self,
         entries: I,
     ) -> Result<ReplayStats, StoreError> {
-        // Minimal semantics: update max_sequence and count entries; payload restoration to be added later.
+        // Bare entries carry no payload: only sequences are restored.
         let mut stats = ReplayStats::default();
-        let mut max_seq: Option<u64> = None;
-        for entry in entries {
-            stats.applied += 1;
-            max_seq = Some(
-                max_seq
-                    .map(|m| m.max(entry.sequence))
-                    .unwrap_or(entry.sequence),
-            );
+        let entries: Vec<ReplayEntry> = entries.into_iter().collect();
+        self.check_sequences(&entries, &mut stats)?;
+        stats.applied = entries.len();
+        stats.max_sequence = entries.iter().map(|entry| entry.sequence).max();
+        if let Some(max) = stats.max_sequence {
+            self.advance_sequence_floor(max);
         }
-        if let Some(m) = max_seq {
-            // Advance floor if replay moved sequence forward
-            let mut current = self.next_sequence.load(Ordering::SeqCst);
-            while m + 1 > current {
-                match self.next_sequence.compare_exchange(
-                    current,
-                    m + 1,
-                    Ordering::SeqCst,
-                    Ordering::SeqCst,
-                ) {
-                    Ok(_) => break,
-                    Err(now) => current = now,
-                }
+        Ok(stats)
+    }
+
+    fn replay_records<I: IntoIterator<Item = ReplayRecord>>(
+        &self,
+        records: I,
+    ) -> Result<ReplayStats, StoreError> {
+        self.check_maintainable()?;
+        let mut stats = ReplayStats::default();
+        let mut records: Vec<ReplayRecord> = records.into_iter().collect();
+        records.sort_by_key(|record| record.entry.sequence);
+        self.check_sequences(records.iter().map(|record| &record.entry), &mut stats)?;
+
+        // The store may already reflect part of each key's history: resume
+        // after the latest record whose result matches the current value.
+        let mut current: HashMap<RepoKey, String> = HashMap::new();
+        let mut resume_after: HashMap<RepoKey, u64> = HashMap::new();
+        for record in &records {
+            let target = Self::record_target(record)?;
+            let id = (record.entry.repo_id.clone(), record.op.key().to_string());
+            if !current.contains_key(&id) {
+                let value = self.get(&id.0, &id.1)?;
+                current.insert(id.clone(), Self::checksum(value.as_deref()));
+            }
+            if current[&id] == target {
+                resume_after.insert(id, record.entry.sequence);
+            }
+        }
+
+        for record in &records {
+            let entry = &record.entry;
+            let id = (entry.repo_id.clone(), record.op.key().to_string());
+            stats.max_sequence = stats.max_sequence.max(Some(entry.sequence));
+            // Advance as we go so a failure part-way still protects the
+            // sequences already applied.
+            self.advance_sequence_floor(entry.sequence);
+            if resume_after
+                .get(&id)
+                .is_some_and(|resume| entry.sequence <= *resume)
+            {
+                stats.skipped += 1;
+                continue;
             }
+            let value = current.get_mut(&id).expect("every key was read above");
+            if *value != entry.payload_checksum_before {
+                return Err(StoreError::Integrity(format!(
+                    "replay entry {} expects {}/{} at {}, found {value}",
+                    entry.sequence, id.0, id.1, entry.payload_checksum_before
+                )));
+            }
+            match &record.op {
+                ReplayOp::Put { payload, .. } => self.write_payload(&id.0, &id.1, payload)?,
+                ReplayOp::Delete { .. } => self.tombstone(&id.0, &id.1)?,
+            }
+            value.clone_from(&entry.payload_checksum_after);
+            stats.applied += 1;
         }
-        stats.max_sequence = max_seq;
         Ok(stats)
     }
 }
@@ -239,7 +1457,10 @@ impl VectorStore {
     /// Test-only helper: return raw stored bytes for (repo_id, key), from FS or memory.
     pub fn test_dump_raw(&self, repo_id: &str, key: &str) -> Option<Vec<u8>> {
         if let Some(root) = &self.fs_root {
-            if let Ok(b) = fs::read_bytes(root, repo_id, key) {
+            if let Ok(b) = self
+                .record_path(root, repo_id, key)
+                .and_then(|path| std::fs::read(path).map_err(|e| StoreError::Io(e.to_string())))
+            {
                 return Some(b);
             }
         }
@@ -256,6 +1477,7 @@ pub struct VectorStoreBuilder {
     encrypter: Option<Arc<dyn crate::encryption::Encrypter + Send + Sync>>,
     kms: Option<Arc<dyn crate::kms::KeyManager + Send + Sync>>,
     fs_root: Option<PathBuf>,
+    config: StoreConfig,
 }
 
 #[cfg(feature = "encryption")]
@@ -275,13 +1497,34 @@ impl VectorStoreBuilder {
         self.fs_root = Some(root.into());
         self
     }
+    pub fn with_config(mut self, config: StoreConfig) -> Self {
+        self.config = config;
+        self
+    }
     pub fn build(self) -> VectorStore {
         VectorStore {
             inner: Arc::new(Mutex::new(HashMap::new())),
             next_sequence: AtomicU64::new(1),
             fs_root: self.fs_root,
+            replay_mode: ReplayMode::Lenient,
+            sequences: Mutex::new(RepoSequences::new()),
+            metric: Metric::default(),
+            quotas: RwLock::new(quota::Quotas::of(&self.config)),
+            config: self.config,
+            vectors: Mutex::new(HashMap::new()),
+            tombstones: Mutex::new(HashMap::new()),
+            wal: None,
+            refs: Mutex::new(None),
+            blobs: Mutex::new(HashMap::new()),
+            usage: Mutex::new(HashMap::new()),
+            recovered: Mutex::new(Vec::new()),
+            deadlines: Mutex::new(HashMap::new()),
+            expiry_ledger: None,
+            mode: mode::ModeFlag::default(),
             encrypter: self.encrypter,
             kms: self.kms,
+            names: names::HashedNames::default(),
+            layout_checked: AtomicBool::new(false),
         }
     }
 }
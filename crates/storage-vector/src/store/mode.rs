@@ -0,0 +1,103 @@
+//! Runtime switch between normal operation and modes that refuse writes.
+//!
+//! [`StoreMode::ReadOnly`] freezes everything on disk, for backups.
+//! [`StoreMode::Maintenance`] refuses client writes but lets operator
+//! tasks such as compaction, expiry, snapshot restores and re-encryption
+//! run, for migrations. Reads and searches work in every mode.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use super::VectorStore;
+use crate::error::StoreError;
+
+/// Which writes a [`VectorStore`] accepts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StoreMode {
+    /// Every write is accepted.
+    #[default]
+    Normal,
+    /// No write is accepted, including compaction and expiry.
+    ReadOnly,
+    /// Client writes are refused; maintenance and restores still run.
+    Maintenance,
+}
+
+impl StoreMode {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::ReadOnly,
+            2 => Self::Maintenance,
+            _ => Self::Normal,
+        }
+    }
+
+    fn as_u8(self) -> u8 {
+        match self {
+            Self::Normal => 0,
+            Self::ReadOnly => 1,
+            Self::Maintenance => 2,
+        }
+    }
+}
+
+impl fmt::Display for StoreMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Normal => "normal",
+            Self::ReadOnly => "read-only",
+            Self::Maintenance => "maintenance",
+        })
+    }
+}
+
+/// Current mode, shared without a lock so reads never wait on it.
+#[derive(Debug, Default)]
+pub(crate) struct ModeFlag(AtomicU8);
+
+impl ModeFlag {
+    fn get(&self) -> StoreMode {
+        StoreMode::from_u8(self.0.load(Ordering::SeqCst))
+    }
+
+    fn swap(&self, mode: StoreMode) -> StoreMode {
+        StoreMode::from_u8(self.0.swap(mode.as_u8(), Ordering::SeqCst))
+    }
+}
+
+impl VectorStore {
+    /// The mode the store is in.
+    pub fn mode(&self) -> StoreMode {
+        self.mode.get()
+    }
+
+    /// Switch to `mode`, returning the previous one. With a write-ahead
+    /// log this waits for writes in progress, so none lands after it
+    /// returns; without one, a write already past its check may still
+    /// finish.
+    pub fn set_mode(&self, mode: StoreMode) -> Result<StoreMode, StoreError> {
+        let _wal = self.lock_wal()?;
+        Ok(self.mode.swap(mode))
+    }
+
+    /// Fail with [`StoreError::ReadOnly`] unless client writes are
+    /// accepted.
+    pub(super) fn check_writable(&self) -> Result<(), StoreError> {
+        match self.mode() {
+            StoreMode::Normal => Ok(()),
+            mode => Err(StoreError::ReadOnly(mode)),
+        }
+    }
+
+    /// Fail with [`StoreError::ReadOnly`] when the store is read-only;
+    /// maintenance mode lets operator tasks through.
+    pub(super) fn check_maintainable(&self) -> Result<(), StoreError> {
+        match self.mode() {
+            StoreMode::ReadOnly => Err(StoreError::ReadOnly(StoreMode::ReadOnly)),
+            _ => Ok(()),
+        }
+    }
+}
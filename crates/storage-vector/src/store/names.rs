@@ -0,0 +1,420 @@
+//! Names of repository directories and payload files under the store root.
+//!
+//! Directories and files are normally named by the path-encoded repository
+//! id and record key, so listing the root shows the shape of every stored
+//! repository. With [`StoreConfig::hashed_paths`](crate::config::StoreConfig)
+//! and encryption configured they are named by a keyed BLAKE3 hash of the
+//! id and key instead. The hash key is random and kept sealed in `%pathkey`
+//! in the root; the `%names` file of the root and of each repository
+//! directory holds the sealed plain names of its entries, so repositories
+//! and keys can still be listed. A store is refused when its on-disk naming
+//! does not match the setting, rather than hiding the records named the
+//! other way.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+
+use super::{fs, VectorStore};
+use crate::error::StoreError;
+
+#[cfg(feature = "encryption")]
+pub(super) use hashed::HashedNames;
+
+/// Sealed key of the name hash, in the store root.
+const PATH_KEY_FILE: &str = "%pathkey";
+
+impl VectorStore {
+    /// Directory holding the payloads of `repo_id`.
+    pub(super) fn repo_path(&self, root: &Path, repo_id: &str) -> Result<PathBuf, StoreError> {
+        self.check_layout(root)?;
+        #[cfg(feature = "encryption")]
+        if let Some(names) = self.hashed_names() {
+            return Ok(root.join(names.repo_name(root, repo_id)?));
+        }
+        Ok(fs::repo_dir(root, repo_id))
+    }
+
+    /// File holding the payload of `key`.
+    pub(super) fn record_path(
+        &self,
+        root: &Path,
+        repo_id: &str,
+        key: &str,
+    ) -> Result<PathBuf, StoreError> {
+        self.check_layout(root)?;
+        #[cfg(feature = "encryption")]
+        if let Some(names) = self.hashed_names() {
+            let dir = root.join(names.repo_name(root, repo_id)?);
+            return Ok(dir.join(names.key_name(root, repo_id, key)?));
+        }
+        Ok(fs::make_path(root, repo_id, key))
+    }
+
+    /// File the payload of a deleted `key` is kept in until compaction.
+    pub(super) fn tombstone_file(
+        &self,
+        root: &Path,
+        repo_id: &str,
+        key: &str,
+    ) -> Result<PathBuf, StoreError> {
+        self.check_layout(root)?;
+        #[cfg(feature = "encryption")]
+        if let Some(names) = self.hashed_names() {
+            let dir = root.join(names.repo_name(root, repo_id)?);
+            let name = names.key_name(root, repo_id, key)?;
+            return Ok(dir.join(format!("{}{name}", fs::TOMBSTONE_PREFIX)));
+        }
+        Ok(fs::tombstone_path(root, repo_id, key))
+    }
+
+    /// Make the file of `key` listable under its plain name; call before
+    /// the file is first written. Plain names need no record.
+    #[cfg_attr(not(feature = "encryption"), allow(unused_variables))]
+    pub(super) fn name_record(
+        &self,
+        root: &Path,
+        repo_id: &str,
+        key: &str,
+    ) -> Result<(), StoreError> {
+        self.check_layout(root)?;
+        #[cfg(feature = "encryption")]
+        if let Some(names) = self.hashed_names() {
+            return names.remember(root, repo_id, key);
+        }
+        Ok(())
+    }
+
+    /// Repositories with a directory under `root`.
+    pub(super) fn stored_repos(&self, root: &Path) -> Result<Vec<String>, StoreError> {
+        self.check_layout(root)?;
+        #[cfg(feature = "encryption")]
+        if let Some(names) = self.hashed_names() {
+            return names.repos(root);
+        }
+        fs::list_decoded(root, true).map_err(|e| StoreError::Io(e.to_string()))
+    }
+
+    /// Keys with a payload file in the directory of `repo_id`.
+    pub(super) fn stored_keys(
+        &self,
+        root: &Path,
+        repo_id: &str,
+    ) -> Result<Vec<String>, StoreError> {
+        self.check_layout(root)?;
+        #[cfg(feature = "encryption")]
+        if let Some(names) = self.hashed_names() {
+            return names.keys(root, repo_id);
+        }
+        fs::list_decoded(&fs::repo_dir(root, repo_id), false)
+            .map_err(|e| StoreError::Io(e.to_string()))
+    }
+
+    /// Fail unless the entries under `root` are named the way the store is
+    /// configured to name them: a `%pathkey` means hashed names, plain
+    /// repository directories without one mean plain names.
+    fn check_layout(&self, root: &Path) -> Result<(), StoreError> {
+        if self.layout_checked.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        #[cfg(feature = "encryption")]
+        let hashed = self.hashed_names().is_some();
+        #[cfg(not(feature = "encryption"))]
+        let hashed = false;
+        let has_key = root.join(PATH_KEY_FILE).exists();
+        if has_key && !hashed {
+            return Err(StoreError::Integrity(format!(
+                "{} names its entries by hash; open it with hashed_paths and encryption configured",
+                root.display()
+            )));
+        }
+        if hashed
+            && !has_key
+            && !fs::list_decoded(root, true)
+                .map_err(|e| StoreError::Io(e.to_string()))?
+                .is_empty()
+        {
+            return Err(StoreError::Integrity(format!(
+                "{} names its entries in plain; open it without hashed_paths",
+                root.display()
+            )));
+        }
+        self.layout_checked.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    /// Hashed naming, when configured and usable.
+    #[cfg(feature = "encryption")]
+    fn hashed_names(&self) -> Option<hashed::Hashing<'_>> {
+        if !self.config.hashed_paths {
+            return None;
+        }
+        let (Some(enc), Some(kms)) = (&self.encrypter, &self.kms) else {
+            return None;
+        };
+        Some(hashed::Hashing {
+            names: &self.names,
+            enc: enc.as_ref(),
+            kms: kms.as_ref(),
+        })
+    }
+}
+
+#[cfg(feature = "encryption")]
+mod hashed {
+    use std::collections::HashMap;
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+    use std::sync::Mutex;
+
+    use rand::{rngs::OsRng, RngCore};
+    use zeroize::Zeroizing;
+
+    use super::super::{build_aad, fs};
+    use super::PATH_KEY_FILE;
+    use crate::encryption::{peek_key_id, Encrypter};
+    use crate::error::StoreError;
+    use crate::kms::{KeyManager, KeyScope};
+
+    /// Sealed plain names of the entries of a directory.
+    const NAMES_FILE: &str = "%names";
+    /// Key scope sealing the hash key and the plain names.
+    const PATHS_SCOPE: &str = "%paths";
+
+    /// Plain name by hashed name, for one directory.
+    type Index = HashMap<String, String>;
+
+    /// Hash key and reverse indexes, loaded when first needed.
+    #[derive(Default)]
+    pub struct HashedNames {
+        key: Mutex<Option<Zeroizing<[u8; 32]>>>,
+        indexes: Mutex<HashMap<PathBuf, Index>>,
+    }
+
+    pub(in crate::store) struct Hashing<'a> {
+        pub names: &'a HashedNames,
+        pub enc: &'a (dyn Encrypter + Send + Sync),
+        pub kms: &'a (dyn KeyManager + Send + Sync),
+    }
+
+    fn io(e: std::io::Error) -> StoreError {
+        StoreError::Io(e.to_string())
+    }
+
+    fn lock_err<T>(e: std::sync::PoisonError<T>) -> StoreError {
+        StoreError::Io(e.to_string())
+    }
+
+    /// Hex keyed hash of length-prefixed `parts`.
+    fn digest(key: &[u8; 32], parts: &[&str]) -> String {
+        let mut hasher = blake3::Hasher::new_keyed(key);
+        for part in parts {
+            hasher.update(&(part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        hasher.finalize().to_hex().to_string()
+    }
+
+    impl Hashing<'_> {
+        pub fn repo_name(&self, root: &Path, repo_id: &str) -> Result<String, StoreError> {
+            let hash_key = self.key(root)?;
+            Ok(digest(&hash_key, &[repo_id]))
+        }
+
+        pub fn key_name(
+            &self,
+            root: &Path,
+            repo_id: &str,
+            key: &str,
+        ) -> Result<String, StoreError> {
+            let hash_key = self.key(root)?;
+            Ok(digest(&hash_key, &[repo_id, key]))
+        }
+
+        /// Record the plain names of `repo_id` and `key` in the indexes of
+        /// the root and the repository directory, unless already there.
+        pub fn remember(&self, root: &Path, repo_id: &str, key: &str) -> Result<(), StoreError> {
+            let hash_key = self.key(root)?;
+            let repo_name = digest(&hash_key, &[repo_id]);
+            let mut indexes = self.names.indexes.lock().map_err(lock_err)?;
+            self.add(&mut indexes, root, "", repo_id, &repo_name, |plain| {
+                digest(&hash_key, &[plain])
+            })?;
+            let dir = root.join(&repo_name);
+            let key_name = digest(&hash_key, &[repo_id, key]);
+            self.add(&mut indexes, &dir, &repo_name, key, &key_name, |plain| {
+                digest(&hash_key, &[repo_id, plain])
+            })
+        }
+
+        pub fn repos(&self, root: &Path) -> Result<Vec<String>, StoreError> {
+            let hash_key = self.key(root)?;
+            let mut indexes = self.names.indexes.lock().map_err(lock_err)?;
+            self.listed(&mut indexes, root, "", true, |plain| {
+                digest(&hash_key, &[plain])
+            })
+        }
+
+        pub fn keys(&self, root: &Path, repo_id: &str) -> Result<Vec<String>, StoreError> {
+            let hash_key = self.key(root)?;
+            let repo_name = digest(&hash_key, &[repo_id]);
+            let mut indexes = self.names.indexes.lock().map_err(lock_err)?;
+            self.listed(
+                &mut indexes,
+                &root.join(&repo_name),
+                &repo_name,
+                false,
+                |plain| digest(&hash_key, &[repo_id, plain]),
+            )
+        }
+
+        /// Plain names of the directories (`dirs`) or files in `dir` that
+        /// its index knows; reserved and unknown entries are skipped.
+        fn listed(
+            &self,
+            indexes: &mut HashMap<PathBuf, Index>,
+            dir: &Path,
+            label: &str,
+            dirs: bool,
+            hash: impl Fn(&str) -> String,
+        ) -> Result<Vec<String>, StoreError> {
+            let entries = match std::fs::read_dir(dir) {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+                Err(e) => return Err(io(e)),
+            };
+            let index = self.index(indexes, dir, label, hash)?;
+            let mut names = Vec::new();
+            for entry in entries {
+                let entry = entry.map_err(io)?;
+                if entry.file_type().map_err(io)?.is_dir() != dirs {
+                    continue;
+                }
+                if let Some(plain) = entry.file_name().to_str().and_then(|name| index.get(name)) {
+                    names.push(plain.clone());
+                }
+            }
+            Ok(names)
+        }
+
+        /// Append the sealed `plain` name to the index of `dir` unless
+        /// `hashed` is already in it.
+        fn add(
+            &self,
+            indexes: &mut HashMap<PathBuf, Index>,
+            dir: &Path,
+            label: &str,
+            plain: &str,
+            hashed: &str,
+            hash: impl Fn(&str) -> String,
+        ) -> Result<(), StoreError> {
+            if self.index(indexes, dir, label, hash)?.contains_key(hashed) {
+                return Ok(());
+            }
+            let sealed = self.seal(plain.as_bytes(), label)?;
+            let mut record = (sealed.len() as u32).to_le_bytes().to_vec();
+            record.extend_from_slice(&sealed);
+            std::fs::create_dir_all(dir).map_err(io)?;
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(dir.join(NAMES_FILE))
+                .map_err(io)?;
+            file.write_all(&record)
+                .and_then(|()| file.sync_data())
+                .map_err(io)?;
+            indexes
+                .get_mut(dir)
+                .expect("loaded by index")
+                .insert(hashed.to_string(), plain.to_string());
+            Ok(())
+        }
+
+        /// The index of `dir`, read from its `%names` file on first use. A
+        /// crash mid-append leaves a torn final record, which is ignored.
+        fn index<'m>(
+            &self,
+            indexes: &'m mut HashMap<PathBuf, Index>,
+            dir: &Path,
+            label: &str,
+            hash: impl Fn(&str) -> String,
+        ) -> Result<&'m Index, StoreError> {
+            if !indexes.contains_key(dir) {
+                let bytes = match std::fs::read(dir.join(NAMES_FILE)) {
+                    Ok(bytes) => bytes,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+                    Err(e) => return Err(io(e)),
+                };
+                let mut index = Index::new();
+                let mut rest = bytes.as_slice();
+                while rest.len() >= 4 {
+                    let (len, tail) = rest.split_at(4);
+                    let len = u32::from_le_bytes(len.try_into().expect("4 bytes")) as usize;
+                    let Some(sealed) = tail.get(..len) else {
+                        break;
+                    };
+                    let plain = String::from_utf8(self.open(sealed, label)?).map_err(|_| {
+                        StoreError::Integrity(format!(
+                            "{} holds a name that is not UTF-8",
+                            dir.join(NAMES_FILE).display()
+                        ))
+                    })?;
+                    index.insert(hash(&plain), plain);
+                    rest = &tail[len..];
+                }
+                indexes.insert(dir.to_path_buf(), index);
+            }
+            Ok(&indexes[dir])
+        }
+
+        /// The hash key, created and sealed into the root on first use.
+        fn key(&self, root: &Path) -> Result<Zeroizing<[u8; 32]>, StoreError> {
+            let mut cached = self.names.key.lock().map_err(lock_err)?;
+            if let Some(key) = cached.as_ref() {
+                return Ok(key.clone());
+            }
+            let path = root.join(PATH_KEY_FILE);
+            let key = match std::fs::read(&path) {
+                Ok(sealed) => {
+                    let plain = Zeroizing::new(self.open(&sealed, PATH_KEY_FILE)?);
+                    let bytes: [u8; 32] = plain.as_slice().try_into().map_err(|_| {
+                        StoreError::Integrity(format!("{} is not a 32-byte key", path.display()))
+                    })?;
+                    Zeroizing::new(bytes)
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    let mut key = Zeroizing::new([0u8; 32]);
+                    OsRng.fill_bytes(key.as_mut());
+                    let sealed = self.seal(key.as_ref(), PATH_KEY_FILE)?;
+                    fs::atomic_write(&path, &sealed).map_err(io)?;
+                    key
+                }
+                Err(e) => return Err(io(e)),
+            };
+            *cached = Some(key.clone());
+            Ok(key)
+        }
+
+        /// Seal `plain` under the paths scope, bound to `label`: the hashed
+        /// name of the directory an index entry belongs to, or the file.
+        fn seal(&self, plain: &[u8], label: &str) -> Result<Vec<u8>, StoreError> {
+            let scope = KeyScope {
+                repo_id: PATHS_SCOPE.to_string(),
+            };
+            let kh = self.kms.current(&scope).map_err(StoreError::Key)?;
+            let aad = build_aad(PATHS_SCOPE, &kh.key_id, label);
+            self.enc
+                .seal(&kh, plain, &aad)
+                .map_err(StoreError::Encryption)
+        }
+
+        fn open(&self, sealed: &[u8], label: &str) -> Result<Vec<u8>, StoreError> {
+            let kid = peek_key_id(sealed)
+                .ok_or_else(|| StoreError::Encryption("missing or invalid envelope".to_string()))?;
+            let kh = self.kms.get(&kid).map_err(StoreError::Key)?;
+            let aad = build_aad(PATHS_SCOPE, &kh.key_id, label);
+            self.enc
+                .open(&kh, sealed, &aad)
+                .map_err(StoreError::Encryption)
+        }
+    }
+}
@@ -0,0 +1,264 @@
+//! Per-repository storage quotas of a [`VectorStore`].
+//!
+//! Usage counts the bytes stored for each live record (envelopes included
+//! when encryption is on, the whole payload when it is a shared blob) and
+//! the number of live records. It is measured once per repository with a
+//! quota and kept current as writes land; repositories without one are
+//! measured on demand.
+
+use std::collections::HashMap;
+use std::sync::PoisonError;
+
+use serde::{Deserialize, Serialize};
+
+use super::VectorStore;
+use crate::config::StoreConfig;
+use crate::error::StoreError;
+use crate::QuotaLimits;
+
+/// Store-wide and per-repository quotas in force.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Quotas {
+    default: Option<QuotaLimits>,
+    repos: HashMap<String, QuotaLimits>,
+}
+
+impl Quotas {
+    pub(crate) fn of(config: &StoreConfig) -> Self {
+        Self {
+            default: config.quota,
+            repos: config.repo_quotas.clone(),
+        }
+    }
+}
+
+/// Stored bytes and live records of one repository.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct RepoUsage {
+    bytes: u64,
+    records: u64,
+}
+
+/// Usage of one repository against its quota, as the `storage.usage`
+/// command reports it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageUsage {
+    pub repo_id: String,
+    pub bytes: u64,
+    pub records: u64,
+    /// `None` when the repository has no byte quota.
+    pub bytes_max: Option<u64>,
+    /// `None` when the repository has no record quota.
+    pub records_max: Option<u64>,
+}
+
+fn render(limit: Option<u64>) -> String {
+    limit.map_or_else(|| "unlimited".to_string(), |limit| limit.to_string())
+}
+
+impl VectorStore {
+    /// Limits of `repo_id`: its own quota, else the store-wide one.
+    fn quota(&self, repo_id: &str) -> Option<QuotaLimits> {
+        let quotas = self.quotas.read().unwrap_or_else(PoisonError::into_inner);
+        quotas
+            .repos
+            .get(repo_id)
+            .or(quotas.default.as_ref())
+            .copied()
+    }
+
+    /// Replace the store-wide quota and the per-repository ones of a
+    /// running store, as `StoreConfig::quota` and `repo_quotas` would set
+    /// them. Writes starting from now are checked against the new limits;
+    /// data already over them stays until deleted.
+    pub fn set_quotas(
+        &self,
+        quota: Option<QuotaLimits>,
+        repo_quotas: HashMap<String, QuotaLimits>,
+    ) -> Result<(), StoreError> {
+        // Usage is only kept current for repositories that had a quota, so
+        // measure every repository afresh under the new ones.
+        let mut usage = self
+            .usage
+            .lock()
+            .map_err(|e| StoreError::Io(e.to_string()))?;
+        *self.quotas.write().unwrap_or_else(PoisonError::into_inner) = Quotas {
+            default: quota,
+            repos: repo_quotas,
+        };
+        usage.clear();
+        Ok(())
+    }
+
+    /// Length of the bytes stored for `key`, if it is live.
+    fn stored_len(&self, repo_id: &str, key: &str) -> Result<Option<u64>, StoreError> {
+        if self.dedup_enabled() {
+            // A shared blob counts for every key referencing it.
+            return Ok(self
+                .read_stored(repo_id, key)?
+                .map(|bytes| bytes.len() as u64));
+        }
+        if let Some(root) = &self.fs_root {
+            match std::fs::metadata(self.record_path(root, repo_id, key)?) {
+                Ok(meta) => return Ok(Some(meta.len())),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(StoreError::Io(e.to_string())),
+            }
+        }
+        Ok(self
+            .inner
+            .lock()
+            .map_err(|e| StoreError::Io(e.to_string()))?
+            .get(&(repo_id.to_string(), key.to_string()))
+            .map(|bytes| bytes.len() as u64))
+    }
+
+    fn measure(&self, repo_id: &str) -> Result<RepoUsage, StoreError> {
+        let mut usage = RepoUsage::default();
+        for key in self.live_keys(repo_id)? {
+            if let Some(len) = self.stored_len(repo_id, &key)? {
+                usage.bytes += len;
+                usage.records += 1;
+            }
+        }
+        Ok(usage)
+    }
+
+    /// Run `apply`, which stores `writes` as `(key, stored length)`, if the
+    /// result fits the quota of `repo_id`; fails with [`StoreError::Quota`]
+    /// before anything is written otherwise. A write that shrinks usage is
+    /// allowed even while the repository is over its quota.
+    pub(super) fn within_quota<T>(
+        &self,
+        repo_id: &str,
+        writes: &[(&str, u64)],
+        apply: impl FnOnce() -> Result<T, StoreError>,
+    ) -> Result<T, StoreError> {
+        let Some(limits) = self.quota(repo_id) else {
+            return apply();
+        };
+        let mut usage = self
+            .usage
+            .lock()
+            .map_err(|e| StoreError::Io(e.to_string()))?;
+        let current = match usage.get(repo_id) {
+            Some(current) => *current,
+            None => self.measure(repo_id)?,
+        };
+        // A key written twice only counts its last length.
+        let last: HashMap<&str, u64> = writes.iter().copied().collect();
+        let mut next = current;
+        for (key, len) in last {
+            match self.stored_len(repo_id, key)? {
+                Some(old) => next.bytes = next.bytes - old.min(next.bytes) + len,
+                None => {
+                    next.bytes += len;
+                    next.records += 1;
+                }
+            }
+        }
+        let over = |limit: Option<u64>, next: u64, current: u64| {
+            limit.is_some_and(|limit| next > limit && next > current)
+        };
+        if over(limits.bytes_max, next.bytes, current.bytes)
+            || over(limits.entries_max, next.records, current.records)
+        {
+            usage.insert(repo_id.to_string(), current);
+            return Err(StoreError::Quota(format!(
+                "repository {repo_id} would hold {} of {} bytes and {} of {} records",
+                next.bytes,
+                render(limits.bytes_max),
+                next.records,
+                render(limits.entries_max),
+            )));
+        }
+        match apply() {
+            Ok(value) => {
+                usage.insert(repo_id.to_string(), next);
+                Ok(value)
+            }
+            Err(e) => {
+                // Part of the write may have landed; measure again later.
+                usage.remove(repo_id);
+                Err(e)
+            }
+        }
+    }
+
+    /// Stop counting `key` once it is deleted.
+    pub(super) fn release(
+        &self,
+        repo_id: &str,
+        key: &str,
+        remove: impl FnOnce() -> Result<(), StoreError>,
+    ) -> Result<(), StoreError> {
+        if self.quota(repo_id).is_none() {
+            return remove();
+        }
+        let mut usage = self
+            .usage
+            .lock()
+            .map_err(|e| StoreError::Io(e.to_string()))?;
+        let old = self.stored_len(repo_id, key)?;
+        let removed = remove();
+        match (&removed, usage.get_mut(repo_id), old) {
+            (Ok(()), Some(current), Some(old)) => {
+                current.bytes = current.bytes.saturating_sub(old);
+                current.records = current.records.saturating_sub(1);
+            }
+            (Ok(()), _, _) => {}
+            (Err(_), _, _) => {
+                usage.remove(repo_id);
+            }
+        }
+        removed
+    }
+
+    /// Drop the kept usage of `repo_id` after a write that bypassed
+    /// [`VectorStore::within_quota`].
+    pub(super) fn forget_usage(&self, repo_id: &str) -> Result<(), StoreError> {
+        self.usage
+            .lock()
+            .map_err(|e| StoreError::Io(e.to_string()))?
+            .remove(repo_id);
+        Ok(())
+    }
+
+    /// Stored bytes and live records of `repo_id` with its quota.
+    pub fn usage(&self, repo_id: &str) -> Result<StorageUsage, StoreError> {
+        let limits = self.quota(repo_id);
+        let used = match limits {
+            Some(_) => {
+                let mut usage = self
+                    .usage
+                    .lock()
+                    .map_err(|e| StoreError::Io(e.to_string()))?;
+                match usage.get(repo_id) {
+                    Some(used) => *used,
+                    None => {
+                        let used = self.measure(repo_id)?;
+                        usage.insert(repo_id.to_string(), used);
+                        used
+                    }
+                }
+            }
+            None => self.measure(repo_id)?,
+        };
+        Ok(StorageUsage {
+            repo_id: repo_id.to_string(),
+            bytes: used.bytes,
+            records: used.records,
+            bytes_max: limits.and_then(|limits| limits.bytes_max),
+            records_max: limits.and_then(|limits| limits.entries_max),
+        })
+    }
+
+    /// [`VectorStore::usage`] of every repository holding a live record.
+    pub fn usage_all(&self) -> Result<Vec<StorageUsage>, StoreError> {
+        use super::Store;
+        self.list_repos()?
+            .iter()
+            .map(|repo_id| self.usage(repo_id))
+            .collect()
+    }
+}
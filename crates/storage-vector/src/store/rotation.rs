@@ -0,0 +1,125 @@
+//! Re-encryption of a repository's payloads under the current key.
+//!
+//! A rotation only changes the key new payloads are sealed under; records
+//! sealed earlier keep naming their key and stay readable through it.
+//! Re-encrypting a repository opens every such envelope and seals it again
+//! under the current key, after which the old key is no longer needed for
+//! that repository.
+
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use super::VectorStore;
+use crate::encryption::peek_key_id;
+use crate::error::StoreError;
+
+/// How far a re-encryption of one repository has got.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReencryptProgress {
+    pub repo_id: String,
+    /// Live records when the job started.
+    pub total: usize,
+    /// Records looked at so far.
+    pub checked: usize,
+    /// Records sealed again because they named an older key.
+    pub rewritten: usize,
+}
+
+/// Re-encryption started by [`VectorStore::reencrypt_repo`]. Dropping it
+/// leaves the job running to completion.
+pub struct ReencryptJob {
+    progress: Arc<Mutex<ReencryptProgress>>,
+    thread: JoinHandle<Result<ReencryptProgress, StoreError>>,
+}
+
+impl ReencryptJob {
+    /// Progress as of the last record the job finished.
+    pub fn progress(&self) -> ReencryptProgress {
+        self.progress
+            .lock()
+            .map(|progress| progress.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Wait for the job and return its final progress.
+    pub fn join(self) -> Result<ReencryptProgress, StoreError> {
+        self.thread
+            .join()
+            .unwrap_or_else(|_| Err(StoreError::Io("re-encryption thread panicked".into())))
+    }
+}
+
+impl VectorStore {
+    /// Seal every record of `repo_id` that names an older key again under
+    /// the current one, calling `progress` after each record. Each record
+    /// is rewritten under the write-ahead log lock; without a log, quiesce
+    /// writers of the repository first. Persisted vector indexes are sealed
+    /// under the current key on their next [`VectorStore::persist_vectors`].
+    pub fn reencrypt(
+        &self,
+        repo_id: &str,
+        mut progress: impl FnMut(&ReencryptProgress),
+    ) -> Result<ReencryptProgress, StoreError> {
+        let (Some(_), Some(kms)) = (&self.encrypter, &self.kms) else {
+            return Err(StoreError::Unsupported(
+                "re-encryption needs an encrypter and a key manager".into(),
+            ));
+        };
+        let scope = crate::kms::KeyScope {
+            repo_id: repo_id.to_string(),
+        };
+        let keys = self.live_keys(repo_id)?;
+        let mut report = ReencryptProgress {
+            repo_id: repo_id.to_string(),
+            total: keys.len(),
+            ..ReencryptProgress::default()
+        };
+        for key in keys {
+            {
+                let _wal = self.lock_wal()?;
+                self.check_maintainable()?;
+                let current = kms.current(&scope).map_err(StoreError::Key)?.key_id;
+                // Deleted since the keys were listed.
+                if let Some(bytes) = self.read_raw(repo_id, &key)? {
+                    if peek_key_id(&bytes).as_deref() != Some(current.as_str()) {
+                        let payload = self.open(repo_id, &key, bytes)?;
+                        self.write_payload(repo_id, &key, &payload)?;
+                        report.rewritten += 1;
+                    }
+                }
+            }
+            report.checked += 1;
+            progress(&report);
+        }
+        tracing::info!(
+            repo_id,
+            checked = report.checked,
+            rewritten = report.rewritten,
+            "re-encrypted repository"
+        );
+        Ok(report)
+    }
+
+    /// Run [`VectorStore::reencrypt`] for `repo_id` on a background thread.
+    pub fn reencrypt_repo(self: &Arc<Self>, repo_id: &str) -> ReencryptJob {
+        let progress = Arc::new(Mutex::new(ReencryptProgress {
+            repo_id: repo_id.to_string(),
+            ..ReencryptProgress::default()
+        }));
+        let store = Arc::clone(self);
+        let repo_id = repo_id.to_string();
+        let shared = Arc::clone(&progress);
+        let thread = std::thread::spawn(move || {
+            store.reencrypt(&repo_id, |now| {
+                if let Ok(mut progress) = shared.lock() {
+                    progress.clone_from(now);
+                }
+            })
+        });
+        ReencryptJob { progress, thread }
+    }
+}
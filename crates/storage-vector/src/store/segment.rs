@@ -0,0 +1,845 @@
+//! Append-only segment storage.
+//!
+//! Records are appended to the active segment file and an in-memory index
+//! maps every key to its latest record; reads copy the payload out of a
+//! memory map of the segment. The active segment rotates once it reaches
+//! `max_segment_bytes`. Overwritten and deleted records stay in their
+//! segment until [`SegmentStore::compact`] copies the live records of sparse
+//! segments forward and removes the files.
+//!
+//! A segment starts with the magic `ENXSEG01`, followed by frames of
+//! `u32 body_len | first 8 bytes of BLAKE3(body) | body`, where the body is
+//! `u8 op | u64 sequence | u16 repo_len | repo | u16 key_len | key | payload`,
+//! all little-endian. A torn frame at the tail of the newest segment is
+//! truncated on open; anywhere else it is reported as corruption.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use memmap2::Mmap;
+use storage_ledger::ReplayEntry;
+
+use super::maintenance::Throttle;
+use super::{
+    CompactionReport, CorruptRecord, IntegrityReport, KeyPage, ReplayStats, Store, VectorStore,
+    ABSENT_CHECKSUM, TOMBSTONE_STATUS,
+};
+use crate::config::SegmentConfig;
+use crate::error::StoreError;
+use crate::ledger::build_replay_entry;
+
+const MAGIC: &[u8; 8] = b"ENXSEG01";
+const OP_PUT: u8 = 1;
+const OP_DELETE: u8 = 2;
+/// `u32 body_len` plus the checksum prefix.
+const FRAME_HEADER: usize = 12;
+
+fn io(e: std::io::Error) -> StoreError {
+    StoreError::Io(e.to_string())
+}
+
+fn segment_path(dir: &Path, id: u32) -> PathBuf {
+    dir.join(format!("seg-{id:08}.log"))
+}
+
+/// Create segment `id` holding only the magic, returning an append handle.
+fn create_segment(dir: &Path, id: u32) -> Result<File, StoreError> {
+    let mut writer = OpenOptions::new()
+        .create_new(true)
+        .append(true)
+        .open(segment_path(dir, id))
+        .map_err(io)?;
+    writer.write_all(MAGIC).map_err(io)?;
+    writer.sync_all().map_err(io)?;
+    Ok(writer)
+}
+
+fn frame_checksum(body: &[u8]) -> [u8; 8] {
+    let mut prefix = [0u8; 8];
+    prefix.copy_from_slice(&blake3::hash(body).as_bytes()[..8]);
+    prefix
+}
+
+/// One record to append.
+struct Record<'a> {
+    op: u8,
+    sequence: u64,
+    repo_id: &'a str,
+    key: &'a str,
+    payload: &'a [u8],
+}
+
+impl Record<'_> {
+    /// Append the framed record to `out`, returning the offset of the
+    /// payload within the frame.
+    fn encode(&self, out: &mut Vec<u8>) -> Result<usize, StoreError> {
+        let too_long = |what: &str| StoreError::Integrity(format!("{what} exceeds 65535 bytes"));
+        let repo_len = u16::try_from(self.repo_id.len()).map_err(|_| too_long("repo id"))?;
+        let key_len = u16::try_from(self.key.len()).map_err(|_| too_long("key"))?;
+        let mut body =
+            Vec::with_capacity(13 + self.repo_id.len() + self.key.len() + self.payload.len());
+        body.push(self.op);
+        body.extend_from_slice(&self.sequence.to_le_bytes());
+        body.extend_from_slice(&repo_len.to_le_bytes());
+        body.extend_from_slice(self.repo_id.as_bytes());
+        body.extend_from_slice(&key_len.to_le_bytes());
+        body.extend_from_slice(self.key.as_bytes());
+        let payload_at = FRAME_HEADER + body.len();
+        body.extend_from_slice(self.payload);
+        let body_len = u32::try_from(body.len())
+            .map_err(|_| StoreError::Integrity("record exceeds 4 GiB".into()))?;
+        out.extend_from_slice(&body_len.to_le_bytes());
+        out.extend_from_slice(&frame_checksum(&body));
+        out.extend_from_slice(&body);
+        Ok(payload_at)
+    }
+}
+
+/// A record decoded while scanning a segment.
+struct Decoded {
+    op: u8,
+    sequence: u64,
+    repo_id: String,
+    key: String,
+    /// Payload offset within the frame.
+    payload_at: usize,
+    payload_len: usize,
+    frame_len: usize,
+}
+
+/// Decode the frame at the start of `bytes`; `None` when it is truncated
+/// or fails its checksum.
+fn decode(bytes: &[u8]) -> Option<Decoded> {
+    let body_len = u32::from_le_bytes(bytes.get(..4)?.try_into().ok()?) as usize;
+    let body = bytes.get(FRAME_HEADER..FRAME_HEADER + body_len)?;
+    if bytes[4..FRAME_HEADER] != frame_checksum(body) {
+        return None;
+    }
+    let op = *body.first()?;
+    let sequence = u64::from_le_bytes(body.get(1..9)?.try_into().ok()?);
+    let repo_len = u16::from_le_bytes(body.get(9..11)?.try_into().ok()?) as usize;
+    let repo_id = std::str::from_utf8(body.get(11..11 + repo_len)?).ok()?;
+    let key_at = 11 + repo_len;
+    let key_len = u16::from_le_bytes(body.get(key_at..key_at + 2)?.try_into().ok()?) as usize;
+    let key = std::str::from_utf8(body.get(key_at + 2..key_at + 2 + key_len)?).ok()?;
+    let payload_at = key_at + 2 + key_len;
+    Some(Decoded {
+        op,
+        sequence,
+        repo_id: repo_id.to_string(),
+        key: key.to_string(),
+        payload_at: FRAME_HEADER + payload_at,
+        payload_len: body_len - payload_at,
+        frame_len: FRAME_HEADER + body_len,
+    })
+}
+
+/// Where the latest record of a key lives.
+#[derive(Debug, Clone, Copy)]
+struct Location {
+    segment: u32,
+    /// File offset of the payload.
+    offset: u64,
+    len: u64,
+    frame_len: u64,
+    sequence: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Slot {
+    Live(Location),
+    /// Kept while an older segment may still hold a value for the key.
+    Deleted(Location),
+}
+
+impl Slot {
+    fn location(&self) -> &Location {
+        match self {
+            Self::Live(location) | Self::Deleted(location) => location,
+        }
+    }
+}
+
+struct Segment {
+    path: PathBuf,
+    file: File,
+    map: Option<Mmap>,
+    len: u64,
+    /// Frame bytes still referenced by the index.
+    live: u64,
+}
+
+impl Segment {
+    fn open(path: PathBuf) -> Result<Self, StoreError> {
+        let file = File::open(&path).map_err(io)?;
+        let len = file.metadata().map_err(io)?.len();
+        Ok(Self {
+            path,
+            file,
+            map: None,
+            len,
+            live: 0,
+        })
+    }
+
+    /// Map the segment, remapping when it grew past the current mapping.
+    fn bytes(&mut self, end: u64) -> Result<&[u8], StoreError> {
+        if self
+            .map
+            .as_ref()
+            .map_or(true, |map| (map.len() as u64) < end)
+        {
+            // SAFETY: segment files are only ever appended to by this store
+            // and are unmapped before compaction removes them; the mapping
+            // is never exposed beyond a copy made under the store lock.
+            self.map = Some(unsafe { Mmap::map(&self.file) }.map_err(io)?);
+        }
+        Ok(self.map.as_deref().unwrap_or_default())
+    }
+}
+
+struct State {
+    segments: BTreeMap<u32, Segment>,
+    active: u32,
+    writer: File,
+    index: BTreeMap<(String, String), Slot>,
+    next_sequence: u64,
+    /// Set when a failed append could not be cut back off the active
+    /// segment; its tail no longer matches the index, so writes are refused
+    /// until a reopen recovers it.
+    poisoned: bool,
+    /// Test hook: write this many bytes of the next append, then fail it.
+    #[cfg(test)]
+    torn_write: Option<usize>,
+}
+
+impl State {
+    fn read(&mut self, location: &Location) -> Result<Vec<u8>, StoreError> {
+        let segment = self.segments.get_mut(&location.segment).ok_or_else(|| {
+            StoreError::Integrity(format!("segment {} is missing", location.segment))
+        })?;
+        let (start, end) = (
+            location.offset as usize,
+            (location.offset + location.len) as usize,
+        );
+        segment
+            .bytes(end as u64)?
+            .get(start..end)
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| {
+                StoreError::Integrity(format!("segment {} is truncated", location.segment))
+            })
+    }
+
+    fn current(&mut self, repo_id: &str, key: &str) -> Result<Option<Vec<u8>>, StoreError> {
+        match self
+            .index
+            .get(&(repo_id.to_string(), key.to_string()))
+            .copied()
+        {
+            Some(Slot::Live(location)) => self.read(&location).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Point the index at a newly written record.
+    fn apply(&mut self, op: u8, repo_id: &str, key: &str, location: Location) {
+        let slot = if op == OP_PUT {
+            Slot::Live(location)
+        } else {
+            Slot::Deleted(location)
+        };
+        if let Some(previous) = self
+            .index
+            .insert((repo_id.to_string(), key.to_string()), slot)
+        {
+            let previous = previous.location();
+            if let Some(segment) = self.segments.get_mut(&previous.segment) {
+                segment.live = segment.live.saturating_sub(previous.frame_len);
+            }
+        }
+        if let Some(segment) = self.segments.get_mut(&location.segment) {
+            segment.live += location.frame_len;
+        }
+        self.next_sequence = self.next_sequence.max(location.sequence + 1);
+    }
+
+    fn rotate(&mut self, dir: &Path) -> Result<(), StoreError> {
+        let id = self.active + 1;
+        self.writer = create_segment(dir, id)?;
+        self.segments
+            .insert(id, Segment::open(segment_path(dir, id))?);
+        self.active = id;
+        Ok(())
+    }
+
+    fn write_frames(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        #[cfg(test)]
+        if let Some(len) = self.torn_write.take() {
+            self.writer.write_all(&buf[..len.min(buf.len())])?;
+            return Err(std::io::Error::other("injected torn write"));
+        }
+        self.writer.write_all(buf)?;
+        self.writer.sync_data()
+    }
+
+    /// Append `records` in one write and index them, returning the number
+    /// of bytes written.
+    fn append(
+        &mut self,
+        dir: &Path,
+        config: &SegmentConfig,
+        records: &[Record<'_>],
+    ) -> Result<u64, StoreError> {
+        if self.poisoned {
+            return Err(StoreError::Integrity(format!(
+                "segment {} has a torn tail; reopen the store to recover it",
+                self.active
+            )));
+        }
+        if self.segments[&self.active].len >= config.max_segment_bytes {
+            self.rotate(dir)?;
+        }
+        let base = self.segments[&self.active].len;
+        let mut buf = Vec::new();
+        let mut placed = Vec::with_capacity(records.len());
+        for record in records {
+            let frame_at = buf.len() as u64;
+            let payload_at = record.encode(&mut buf)? as u64;
+            placed.push(Location {
+                segment: self.active,
+                offset: base + frame_at + payload_at,
+                len: record.payload.len() as u64,
+                frame_len: buf.len() as u64 - frame_at,
+                sequence: record.sequence,
+            });
+        }
+        if let Err(err) = self.write_frames(&buf) {
+            // Cut a partially written batch off so the next append lands
+            // where the index expects it.
+            if self
+                .writer
+                .set_len(base)
+                .and_then(|()| self.writer.sync_data())
+                .is_err()
+            {
+                self.poisoned = true;
+            }
+            return Err(io(err));
+        }
+        if let Some(segment) = self.segments.get_mut(&self.active) {
+            segment.len += buf.len() as u64;
+        }
+        for (record, location) in records.iter().zip(placed) {
+            self.apply(record.op, record.repo_id, record.key, location);
+        }
+        Ok(buf.len() as u64)
+    }
+}
+
+/// [`Store`] keeping payloads in append-only segment files.
+///
+/// Unlike the one-file-per-key layout of [`VectorStore::with_fs_root`], the
+/// number of files grows with the data volume rather than the key count.
+/// Payloads are stored as given; sealing them is left to the caller.
+pub struct SegmentStore {
+    dir: PathBuf,
+    config: SegmentConfig,
+    state: Mutex<State>,
+}
+
+impl SegmentStore {
+    /// Open the segments in `dir`, creating it when missing, and rebuild
+    /// the index from them.
+    pub fn open(dir: impl Into<PathBuf>, config: SegmentConfig) -> Result<Self, StoreError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).map_err(io)?;
+        let mut ids: Vec<u32> = fs::read_dir(&dir)
+            .map_err(io)?
+            .filter_map(|entry| {
+                let name = entry.ok()?.file_name();
+                let id = name.to_str()?.strip_prefix("seg-")?.strip_suffix(".log")?;
+                id.parse().ok()
+            })
+            .collect();
+        ids.sort_unstable();
+        if ids.is_empty() {
+            create_segment(&dir, 1)?;
+            ids.push(1);
+        }
+        let active = ids[ids.len() - 1];
+        // Append mode writes at the end even after recovery truncates a
+        // torn tail.
+        let writer = OpenOptions::new()
+            .append(true)
+            .open(segment_path(&dir, active))
+            .map_err(io)?;
+        let mut state = State {
+            segments: BTreeMap::new(),
+            active,
+            writer,
+            index: BTreeMap::new(),
+            next_sequence: 1,
+            poisoned: false,
+            #[cfg(test)]
+            torn_write: None,
+        };
+        for id in ids {
+            recover_segment(&mut state, &segment_path(&dir, id), id, id == active)?;
+        }
+        Ok(Self {
+            dir,
+            config,
+            state: Mutex::new(state),
+        })
+    }
+
+    pub fn config(&self) -> &SegmentConfig {
+        &self.config
+    }
+
+    /// Directory holding the segment files.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Number of segment files, including the active one.
+    pub fn segment_count(&self) -> usize {
+        self.lock().map_or(0, |state| state.segments.len())
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, State>, StoreError> {
+        self.state.lock().map_err(|e| StoreError::Io(e.to_string()))
+    }
+
+    /// Rewrite every sealed segment whose live share has dropped below
+    /// [`SegmentConfig::compact_below`]: live records are copied to the
+    /// active segment and the file is removed. Tombstones are dropped once
+    /// no older segment remains that could hold the deleted value.
+    pub fn compact(&self) -> Result<CompactionReport, StoreError> {
+        self.compact_throttled(&mut Throttle::new(None))
+    }
+
+    /// [`SegmentStore::compact`], taking the lock once per segment so
+    /// `throttle` can pace the rewrites without stalling writers.
+    pub(super) fn compact_throttled(
+        &self,
+        throttle: &mut Throttle,
+    ) -> Result<CompactionReport, StoreError> {
+        let mut report = CompactionReport::default();
+        let sparse: Vec<u32> = {
+            let state = self.lock()?;
+            state
+                .segments
+                .iter()
+                .filter(|(id, _)| **id != state.active)
+                .filter(|(_, segment)| {
+                    (segment.live as f64) < segment.len as f64 * self.config.compact_below
+                })
+                .map(|(id, _)| *id)
+                .collect()
+        };
+        for id in sparse {
+            let rewritten = self.compact_segment(&mut *self.lock()?, id, &mut report)?;
+            throttle.consume(rewritten);
+        }
+        Ok(report)
+    }
+
+    /// Copy the live records of sealed segment `id` to the active one and
+    /// remove its file, returning the bytes read and written.
+    fn compact_segment(
+        &self,
+        state: &mut State,
+        id: u32,
+        report: &mut CompactionReport,
+    ) -> Result<u64, StoreError> {
+        // Sealed segments stay sparse, but another pass may have removed it.
+        if !state.segments.contains_key(&id) {
+            return Ok(0);
+        }
+        let has_older = state.segments.range(..id).next().is_some();
+        let slots: Vec<((String, String), Slot)> = state
+            .index
+            .iter()
+            .filter(|(_, slot)| slot.location().segment == id)
+            .map(|(name, slot)| (name.clone(), *slot))
+            .collect();
+        let mut copies = Vec::new();
+        for ((repo_id, key), slot) in slots {
+            match slot {
+                Slot::Live(location) => {
+                    let payload = state.read(&location)?;
+                    copies.push((OP_PUT, location.sequence, repo_id, key, payload));
+                }
+                Slot::Deleted(location) if has_older => {
+                    copies.push((OP_DELETE, location.sequence, repo_id, key, Vec::new()));
+                }
+                Slot::Deleted(_) => {
+                    state.index.remove(&(repo_id, key));
+                    report.tombstones_removed += 1;
+                }
+            }
+        }
+        let records: Vec<Record<'_>> = copies
+            .iter()
+            .map(|(op, sequence, repo_id, key, payload)| Record {
+                op: *op,
+                sequence: *sequence,
+                repo_id,
+                key,
+                payload,
+            })
+            .collect();
+        let copied = if records.is_empty() {
+            0
+        } else {
+            state.append(&self.dir, &self.config, &records)?
+        };
+        let Segment {
+            path,
+            file,
+            map,
+            len,
+            ..
+        } = state.segments.remove(&id).expect("segment checked above");
+        drop(map);
+        drop(file);
+        fs::remove_file(&path).map_err(io)?;
+        report.reclaimed_bytes += len.saturating_sub(copied);
+        report.segments_merged += 1;
+        Ok(len + copied)
+    }
+
+    /// Run [`SegmentStore::compact`] every `every` on a background thread
+    /// until the returned handle is dropped.
+    pub fn spawn_compactor(self: &Arc<Self>, every: Duration) -> SegmentCompactor {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let store = Arc::clone(self);
+        let thread = std::thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(every) {
+                match store.compact() {
+                    Ok(report) if report.segments_merged > 0 => tracing::debug!(
+                        segments = report.segments_merged,
+                        reclaimed_bytes = report.reclaimed_bytes,
+                        "segment compaction finished"
+                    ),
+                    Ok(_) => {}
+                    Err(err) => tracing::warn!(error = %err, "segment compaction failed"),
+                }
+            }
+        });
+        SegmentCompactor {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+}
+
+/// Background compaction started by [`SegmentStore::spawn_compactor`];
+/// dropping it stops the thread after any pass in progress.
+pub struct SegmentCompactor {
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for SegmentCompactor {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Index the records of segment `id`, truncating a torn tail when it is
+/// the newest segment.
+fn recover_segment(
+    state: &mut State,
+    path: &Path,
+    id: u32,
+    newest: bool,
+) -> Result<(), StoreError> {
+    let mut segment = Segment::open(path.to_path_buf())?;
+    if segment.len < MAGIC.len() as u64 && newest {
+        // Crashed while creating the segment: start it afresh.
+        let mut file = OpenOptions::new().write(true).open(path).map_err(io)?;
+        file.set_len(0).map_err(io)?;
+        file.write_all(MAGIC).map_err(io)?;
+        file.sync_all().map_err(io)?;
+        segment = Segment::open(path.to_path_buf())?;
+    }
+    let len = segment.len;
+    let (records, valid) = {
+        let bytes = segment.bytes(len)?;
+        if bytes.get(..MAGIC.len()) != Some(MAGIC.as_slice()) {
+            return Err(StoreError::Integrity(format!(
+                "{} is not a segment file",
+                path.display()
+            )));
+        }
+        let mut records = Vec::new();
+        let mut offset = MAGIC.len();
+        while offset < bytes.len() {
+            let Some(record) = decode(&bytes[offset..]) else {
+                break;
+            };
+            let frame_len = record.frame_len;
+            records.push((offset, record));
+            offset += frame_len;
+        }
+        (records, offset as u64)
+    };
+    state.segments.insert(id, segment);
+    if valid < len {
+        if !newest {
+            return Err(StoreError::Integrity(format!(
+                "{} is corrupt at offset {valid}",
+                path.display()
+            )));
+        }
+        tracing::warn!(
+            segment = %path.display(),
+            discarded = len - valid,
+            "truncating torn segment tail"
+        );
+        let segment = state.segments.get_mut(&id).expect("just inserted");
+        segment.map = None;
+        OpenOptions::new()
+            .write(true)
+            .open(path)
+            .and_then(|file| {
+                file.set_len(valid)?;
+                file.sync_all()
+            })
+            .map_err(io)?;
+        segment.len = valid;
+    }
+    for (frame_at, record) in records {
+        let location = Location {
+            segment: id,
+            offset: (frame_at + record.payload_at) as u64,
+            len: record.payload_len as u64,
+            frame_len: record.frame_len as u64,
+            sequence: record.sequence,
+        };
+        state.apply(record.op, &record.repo_id, &record.key, location);
+    }
+    Ok(())
+}
+
+impl Store for SegmentStore {
+    fn upsert(&self, repo_id: &str, key: &str, payload: &[u8]) -> Result<ReplayEntry, StoreError> {
+        Ok(self
+            .upsert_batch(repo_id, &[(key, payload)])?
+            .pop()
+            .expect("one entry per record"))
+    }
+
+    fn get(&self, repo_id: &str, key: &str) -> Result<Option<Vec<u8>>, StoreError> {
+        self.lock()?.current(repo_id, key)
+    }
+
+    fn replay<I: IntoIterator<Item = ReplayEntry>>(
+        &self,
+        entries: I,
+    ) -> Result<ReplayStats, StoreError> {
+        let mut stats = ReplayStats::default();
+        for entry in entries {
+            stats.applied += 1;
+            stats.max_sequence = stats.max_sequence.max(Some(entry.sequence));
+        }
+        if let Some(max) = stats.max_sequence {
+            let mut state = self.lock()?;
+            state.next_sequence = state.next_sequence.max(max + 1);
+        }
+        Ok(stats)
+    }
+
+    fn delete(&self, repo_id: &str, key: &str) -> Result<ReplayEntry, StoreError> {
+        let mut state = self.lock()?;
+        let before = VectorStore::checksum(state.current(repo_id, key)?.as_deref());
+        let sequence = state.next_sequence;
+        state.append(
+            &self.dir,
+            &self.config,
+            &[Record {
+                op: OP_DELETE,
+                sequence,
+                repo_id,
+                key,
+                payload: &[],
+            }],
+        )?;
+        Ok(build_replay_entry(
+            sequence,
+            repo_id,
+            &before,
+            ABSENT_CHECKSUM,
+            TOMBSTONE_STATUS,
+        ))
+    }
+
+    fn list_repos(&self) -> Result<Vec<String>, StoreError> {
+        let state = self.lock()?;
+        let mut repos: Vec<String> = Vec::new();
+        for ((repo_id, _), slot) in &state.index {
+            if matches!(slot, Slot::Live(_)) && repos.last() != Some(repo_id) {
+                repos.push(repo_id.clone());
+            }
+        }
+        Ok(repos)
+    }
+
+    fn list_keys(
+        &self,
+        repo_id: &str,
+        prefix: &str,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<KeyPage, StoreError> {
+        let limit = limit.max(1);
+        let state = self.lock()?;
+        let start = match cursor.filter(|cursor| *cursor >= prefix) {
+            Some(cursor) => Bound::Excluded((repo_id.to_string(), cursor.to_string())),
+            None => Bound::Included((repo_id.to_string(), prefix.to_string())),
+        };
+        let mut keys: Vec<String> = state
+            .index
+            .range((start, Bound::Unbounded))
+            .take_while(|((repo, key), _)| repo == repo_id && key.starts_with(prefix))
+            .filter(|(_, slot)| matches!(slot, Slot::Live(_)))
+            .map(|((_, key), _)| key.clone())
+            .take(limit.saturating_add(1))
+            .collect();
+        let next_cursor = if keys.len() > limit {
+            keys.truncate(limit);
+            keys.last().cloned()
+        } else {
+            None
+        };
+        Ok(KeyPage { keys, next_cursor })
+    }
+
+    /// Also re-checks the frame checksum of every live record, which reads
+    /// skip once a segment has been opened.
+    fn verify(&self, repo_id: &str) -> Result<IntegrityReport, StoreError> {
+        let mut state = self.lock()?;
+        let live: Vec<(String, Location)> = state
+            .index
+            .range((repo_id.to_string(), String::new())..)
+            .take_while(|((repo, _), _)| repo == repo_id)
+            .filter_map(|((_, key), slot)| match slot {
+                Slot::Live(location) => Some((key.clone(), *location)),
+                Slot::Deleted(_) => None,
+            })
+            .collect();
+        let mut report = IntegrityReport {
+            repo_id: repo_id.to_string(),
+            checked: live.len(),
+            corrupt: Vec::new(),
+        };
+        for (key, location) in live {
+            // The payload ends the frame.
+            let frame = Location {
+                offset: location.offset + location.len - location.frame_len,
+                len: location.frame_len,
+                ..location
+            };
+            let reason = match state.read(&frame) {
+                Ok(bytes) => match decode(&bytes) {
+                    None => Some(format!(
+                        "frame in segment {} fails its checksum",
+                        location.segment
+                    )),
+                    Some(decoded) if decoded.repo_id != repo_id || decoded.key != key => Some(
+                        format!("frame in segment {} holds another key", location.segment),
+                    ),
+                    Some(_) => None,
+                },
+                Err(e @ StoreError::Io(_)) => return Err(e),
+                Err(e) => Some(e.to_string()),
+            };
+            if let Some(reason) = reason {
+                report.corrupt.push(CorruptRecord { key, reason });
+            }
+        }
+        Ok(report)
+    }
+
+    fn upsert_batch<K: AsRef<str>, P: AsRef<[u8]>>(
+        &self,
+        repo_id: &str,
+        records: &[(K, P)],
+    ) -> Result<Vec<ReplayEntry>, StoreError> {
+        let mut state = self.lock()?;
+        let first = state.next_sequence;
+        let mut pending: BTreeMap<&str, String> = BTreeMap::new();
+        let mut entries = Vec::with_capacity(records.len());
+        for ((key, payload), sequence) in records.iter().zip(first..) {
+            let (key, payload) = (key.as_ref(), payload.as_ref());
+            let before = match pending.remove(key) {
+                Some(before) => before,
+                None => VectorStore::checksum(state.current(repo_id, key)?.as_deref()),
+            };
+            let after = VectorStore::checksum(Some(payload));
+            entries.push(build_replay_entry(
+                sequence, repo_id, &before, &after, "emitted",
+            ));
+            pending.insert(key, after);
+        }
+        let framed: Vec<Record<'_>> = records
+            .iter()
+            .zip(first..)
+            .map(|((key, payload), sequence)| Record {
+                op: OP_PUT,
+                sequence,
+                repo_id,
+                key: key.as_ref(),
+                payload: payload.as_ref(),
+            })
+            .collect();
+        if !framed.is_empty() {
+            state.append(&self.dir, &self.config, &framed)?;
+        }
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::Store;
+
+    #[test]
+    fn failed_append_is_truncated_before_the_next_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SegmentStore::open(dir.path(), SegmentConfig::default()).unwrap();
+        store.upsert("repo", "a", b"kept").unwrap();
+        let len = fs::metadata(segment_path(dir.path(), 1)).unwrap().len();
+
+        store.lock().unwrap().torn_write = Some(7);
+        assert!(store.upsert("repo", "b", b"torn").is_err());
+        assert_eq!(
+            fs::metadata(segment_path(dir.path(), 1)).unwrap().len(),
+            len
+        );
+        assert_eq!(store.get("repo", "b").unwrap(), None);
+
+        store.upsert("repo", "c", b"after").unwrap();
+        assert_eq!(store.get("repo", "a").unwrap(), Some(b"kept".to_vec()));
+        assert_eq!(store.get("repo", "c").unwrap(), Some(b"after".to_vec()));
+        drop(store);
+
+        let reopened = SegmentStore::open(dir.path(), SegmentConfig::default()).unwrap();
+        assert_eq!(reopened.get("repo", "b").unwrap(), None);
+        assert_eq!(reopened.get("repo", "c").unwrap(), Some(b"after".to_vec()));
+    }
+}
@@ -0,0 +1,306 @@
+//! Offline backups of a [`VectorStore`].
+//!
+//! A snapshot holds every live record as stored, so sealed payloads stay
+//! sealed and restoring needs the same keys, plus the sequence state. Its
+//! layout is the same in a directory and in an archive:
+//!
+//! - `records/<repo>/<key>`: stored bytes, names path-encoded like the
+//!   filesystem store.
+//! - `manifest.json`: format version, sequence state, and the path, length
+//!   and blake3 checksum of every record.
+//! - `manifest.blake3`: blake3 hex digest of `manifest.json`.
+//!
+//! Vector indexes are not included; re-insert vectors after a restore.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+use super::{fs, Store, VectorStore, ABSENT_CHECKSUM};
+use crate::error::StoreError;
+use crate::ledger::build_replay_entry;
+
+const SNAPSHOT_VERSION: u32 = 1;
+const MANIFEST: &str = "manifest.json";
+const MANIFEST_CHECKSUM: &str = "manifest.blake3";
+
+/// Records and payload bytes a snapshot was written or restored with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SnapshotReport {
+    pub records: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    /// Sequence the next write of the snapshotted store would have taken.
+    next_sequence: u64,
+    /// Highest sequence written or replayed per repository.
+    sequences: BTreeMap<String, u64>,
+    records: Vec<ManifestRecord>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ManifestRecord {
+    repo_id: String,
+    key: String,
+    path: String,
+    len: u64,
+    checksum: String,
+}
+
+fn record_path(repo_id: &str, key: &str) -> String {
+    format!(
+        "records/{}/{}",
+        fs::encode_component(repo_id),
+        fs::encode_component(key)
+    )
+}
+
+/// Where a snapshot is written.
+trait Sink {
+    fn put(&mut self, path: &str, bytes: &[u8]) -> io::Result<()>;
+}
+
+/// Where a snapshot is read from.
+trait Source {
+    fn take(&mut self, path: &str) -> io::Result<Vec<u8>>;
+}
+
+struct DirSink(PathBuf);
+
+impl Sink for DirSink {
+    fn put(&mut self, path: &str, bytes: &[u8]) -> io::Result<()> {
+        fs::atomic_write(&self.0.join(path), bytes)
+    }
+}
+
+struct DirSource(PathBuf);
+
+impl Source for DirSource {
+    fn take(&mut self, path: &str) -> io::Result<Vec<u8>> {
+        std::fs::read(self.0.join(path))
+    }
+}
+
+impl VectorStore {
+    /// Write every live record and the sequence state to `dir`, which must
+    /// be empty or absent. The manifest is written last, so an interrupted
+    /// snapshot is never restorable. Writes through the write-ahead log wait
+    /// for the snapshot; without one, quiesce writers first.
+    pub fn create_snapshot(&self, dir: impl AsRef<Path>) -> Result<SnapshotReport, StoreError> {
+        let dir = dir.as_ref();
+        let io = |e: io::Error| StoreError::Io(e.to_string());
+        if std::fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_some()) {
+            return Err(StoreError::Io(format!(
+                "snapshot directory {} is not empty",
+                dir.display()
+            )));
+        }
+        std::fs::create_dir_all(dir).map_err(io)?;
+        let report = self.write_snapshot(&mut DirSink(dir.to_path_buf()))?;
+        fs::sync_dir(dir).map_err(io)?;
+        Ok(report)
+    }
+
+    /// Restore the records and sequence state of the snapshot in `dir`.
+    /// Every record is checked against the manifest before any is written.
+    /// Keys the snapshot lacks are left as they are; restore into an empty
+    /// store for an exact copy.
+    pub fn restore_snapshot(&self, dir: impl AsRef<Path>) -> Result<SnapshotReport, StoreError> {
+        self.read_snapshot(&mut DirSource(dir.as_ref().to_path_buf()))
+    }
+
+    fn write_snapshot(&self, sink: &mut dyn Sink) -> Result<SnapshotReport, StoreError> {
+        let io = |e: io::Error| StoreError::Io(e.to_string());
+        let _wal = self.lock_wal()?;
+        let mut report = SnapshotReport::default();
+        let mut records = Vec::new();
+        for repo_id in self.list_repos()? {
+            for key in self.live_keys(&repo_id)? {
+                // Deleted between listing and reading.
+                let Some(bytes) = self.read_stored(&repo_id, &key)? else {
+                    continue;
+                };
+                let path = record_path(&repo_id, &key);
+                sink.put(&path, &bytes).map_err(io)?;
+                report.records += 1;
+                report.bytes += bytes.len() as u64;
+                records.push(ManifestRecord {
+                    checksum: Self::checksum(Some(&bytes)),
+                    len: bytes.len() as u64,
+                    repo_id: repo_id.clone(),
+                    key,
+                    path,
+                });
+            }
+        }
+        let sequences = self
+            .sequences
+            .lock()
+            .map_err(|e| StoreError::Io(e.to_string()))?
+            .repos()
+            .map(|(repo_id, sequence)| (repo_id.to_string(), sequence))
+            .collect();
+        let manifest = Manifest {
+            version: SNAPSHOT_VERSION,
+            next_sequence: self.next_sequence.load(Ordering::SeqCst),
+            sequences,
+            records,
+        };
+        let manifest =
+            serde_json::to_vec_pretty(&manifest).map_err(|e| StoreError::Io(e.to_string()))?;
+        let digest = blake3::hash(&manifest).to_hex();
+        sink.put(MANIFEST_CHECKSUM, digest.as_bytes()).map_err(io)?;
+        sink.put(MANIFEST, &manifest).map_err(io)?;
+        Ok(report)
+    }
+
+    fn read_snapshot(&self, source: &mut dyn Source) -> Result<SnapshotReport, StoreError> {
+        self.check_maintainable()?;
+        let io = |e: io::Error| StoreError::Io(e.to_string());
+        let manifest = source.take(MANIFEST).map_err(io)?;
+        let digest = source.take(MANIFEST_CHECKSUM).map_err(io)?;
+        let digest = String::from_utf8_lossy(&digest);
+        if blake3::hash(&manifest).to_hex().as_str() != digest.trim() {
+            return Err(StoreError::Integrity(
+                "snapshot manifest does not match its checksum".into(),
+            ));
+        }
+        let manifest: Manifest =
+            serde_json::from_slice(&manifest).map_err(|e| StoreError::Integrity(e.to_string()))?;
+        if manifest.version != SNAPSHOT_VERSION {
+            return Err(StoreError::Unsupported(format!(
+                "snapshot version {}",
+                manifest.version
+            )));
+        }
+
+        let mut report = SnapshotReport::default();
+        let mut by_repo: BTreeMap<&str, HashMap<&str, Vec<u8>>> = BTreeMap::new();
+        for record in &manifest.records {
+            let corrupt = || {
+                StoreError::Integrity(format!(
+                    "snapshot record {}/{} does not match the manifest",
+                    record.repo_id, record.key
+                ))
+            };
+            if record.path != record_path(&record.repo_id, &record.key) {
+                return Err(corrupt());
+            }
+            let bytes = source.take(&record.path).map_err(io)?;
+            if bytes.len() as u64 != record.len || Self::checksum(Some(&bytes)) != record.checksum {
+                return Err(corrupt());
+            }
+            report.records += 1;
+            report.bytes += record.len;
+            by_repo
+                .entry(&record.repo_id)
+                .or_default()
+                .insert(&record.key, bytes);
+        }
+
+        let _wal = self.lock_wal()?;
+        for (repo_id, writes) in by_repo {
+            self.write_batch(repo_id, writes, true)?;
+            self.forget_usage(repo_id)?;
+            self.forget_refs()?;
+        }
+        for (repo_id, &sequence) in &manifest.sequences {
+            let entry = build_replay_entry(
+                sequence,
+                repo_id,
+                ABSENT_CHECKSUM,
+                ABSENT_CHECKSUM,
+                "snapshot",
+            );
+            self.record_sequence(&entry)?;
+        }
+        if let Some(last) = manifest.next_sequence.checked_sub(1) {
+            self.advance_sequence_floor(last);
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(feature = "snapshot-archive")]
+mod archive {
+    use std::collections::HashMap;
+    use std::fs::File;
+    use std::io::{self, Read, Write};
+    use std::path::Path;
+
+    use super::{Sink, Source};
+    use crate::error::StoreError;
+    use crate::store::VectorStore;
+
+    struct TarSink<W: Write>(tar::Builder<W>);
+
+    impl<W: Write> Sink for TarSink<W> {
+        /// Fixed owner, mode and mtime, like the `archive_builder` fixtures,
+        /// so equal stores produce equal archives.
+        fn put(&mut self, path: &str, bytes: &[u8]) -> io::Result<()> {
+            let mut header = tar::Header::new_gnu();
+            header.set_path(path)?;
+            header.set_mode(0o644);
+            header.set_uid(0);
+            header.set_gid(0);
+            header.set_size(bytes.len() as u64);
+            header.set_mtime(0);
+            header.set_cksum();
+            self.0.append(&header, bytes)
+        }
+    }
+
+    /// Entries of an archive, read up front since tar is sequential.
+    struct Entries(HashMap<String, Vec<u8>>);
+
+    impl Source for Entries {
+        fn take(&mut self, path: &str) -> io::Result<Vec<u8>> {
+            self.0.remove(path).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, format!("{path} not in snapshot"))
+            })
+        }
+    }
+
+    impl VectorStore {
+        /// [`VectorStore::create_snapshot`] into a zstd-compressed tar
+        /// archive at `path`.
+        pub fn create_snapshot_archive(
+            &self,
+            path: impl AsRef<Path>,
+        ) -> Result<super::SnapshotReport, StoreError> {
+            let io = |e: io::Error| StoreError::Io(e.to_string());
+            let file = File::create(path).map_err(io)?;
+            let mut sink = TarSink(tar::Builder::new(zstd::Encoder::new(file, 0).map_err(io)?));
+            let report = self.write_snapshot(&mut sink)?;
+            let file = sink.0.into_inner().map_err(io)?.finish().map_err(io)?;
+            file.sync_all().map_err(io)?;
+            Ok(report)
+        }
+
+        /// [`VectorStore::restore_snapshot`] from an archive written by
+        /// [`VectorStore::create_snapshot_archive`].
+        pub fn restore_snapshot_archive(
+            &self,
+            path: impl AsRef<Path>,
+        ) -> Result<super::SnapshotReport, StoreError> {
+            let io = |e: io::Error| StoreError::Io(e.to_string());
+            let file = File::open(path).map_err(io)?;
+            let mut archive = tar::Archive::new(zstd::Decoder::new(file).map_err(io)?);
+            let mut entries = HashMap::new();
+            for entry in archive.entries().map_err(io)? {
+                let mut entry = entry.map_err(io)?;
+                let path = entry.path().map_err(io)?.to_string_lossy().into_owned();
+                let mut bytes = Vec::new();
+                entry.read_to_end(&mut bytes).map_err(io)?;
+                entries.insert(path, bytes);
+            }
+            self.read_snapshot(&mut Entries(entries))
+        }
+    }
+}
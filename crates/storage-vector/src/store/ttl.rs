@@ -0,0 +1,253 @@
+//! Expiry of records written with a time-to-live.
+//!
+//! A write sets the record's deadline from its own TTL, else from
+//! [`StoreConfig::repo_ttls`](crate::StoreConfig::repo_ttls) or
+//! [`StoreConfig::default_ttl`](crate::StoreConfig::default_ttl); a write
+//! without any clears it. Records past their deadline read as missing at
+//! once and are deleted by [`VectorStore::expire`]. Deadlines of a
+//! filesystem-backed repository are appended to its `%expiry` file, one
+//! JSON line per change, which each sweep rewrites.
+
+use std::collections::{BTreeSet, HashMap};
+use std::io::Write;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use storage_ledger::{Ledger, ReplayEntry};
+
+use super::{fs, Store, VectorStore, ABSENT_CHECKSUM};
+use crate::error::StoreError;
+use crate::ledger::build_replay_entry;
+
+/// Status of the replay entry recorded when a record expires.
+pub const EXPIRED_STATUS: &str = "expired";
+
+/// Deadline per key of one repository.
+pub(crate) type Deadlines = HashMap<String, SystemTime>;
+
+/// Line of a repository's `%expiry` file; no deadline clears the key's.
+#[derive(Debug, Serialize, Deserialize)]
+struct ExpiryLine {
+    key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at_ms: Option<u64>,
+}
+
+fn unix_ms(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis() as u64)
+}
+
+fn io(e: std::io::Error) -> StoreError {
+    StoreError::Io(e.to_string())
+}
+
+impl VectorStore {
+    /// Append the TTL-expired deletions swept by [`VectorStore::expire`] to
+    /// `ledger`.
+    pub fn with_expiry_ledger(mut self, ledger: Arc<Ledger>) -> Self {
+        self.expiry_ledger = Some(ledger);
+        self
+    }
+
+    /// TTL of writes to `repo_id` that do not set their own.
+    pub(super) fn default_ttl(&self, repo_id: &str) -> Option<Duration> {
+        self.config
+            .repo_ttls
+            .get(repo_id)
+            .copied()
+            .or(self.config.default_ttl)
+    }
+
+    /// Deadlines of every repository read so far, with those of `repo_id`
+    /// loaded from disk when first needed.
+    fn lock_deadlines(
+        &self,
+        repo_id: &str,
+    ) -> Result<MutexGuard<'_, HashMap<String, Deadlines>>, StoreError> {
+        let mut all = self
+            .deadlines
+            .lock()
+            .map_err(|e| StoreError::Io(e.to_string()))?;
+        if !all.contains_key(repo_id) {
+            let loaded = self.load_deadlines(repo_id)?;
+            all.insert(repo_id.to_string(), loaded);
+        }
+        Ok(all)
+    }
+
+    fn load_deadlines(&self, repo_id: &str) -> Result<Deadlines, StoreError> {
+        let mut deadlines = Deadlines::new();
+        let Some(root) = &self.fs_root else {
+            return Ok(deadlines);
+        };
+        let path = self.repo_path(root, repo_id)?.join(fs::EXPIRY_FILE);
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(deadlines),
+            Err(e) => return Err(io(e)),
+        };
+        let lines: Vec<&str> = text.lines().filter(|line| !line.is_empty()).collect();
+        for (index, line) in lines.iter().enumerate() {
+            match serde_json::from_str::<ExpiryLine>(line) {
+                Ok(ExpiryLine {
+                    key,
+                    expires_at_ms: Some(ms),
+                }) => {
+                    deadlines.insert(key, UNIX_EPOCH + Duration::from_millis(ms));
+                }
+                Ok(ExpiryLine { key, .. }) => {
+                    deadlines.remove(&key);
+                }
+                // A crash mid-append leaves a torn final line.
+                Err(_) if index + 1 == lines.len() => {}
+                Err(e) => {
+                    return Err(StoreError::Integrity(format!(
+                        "{} line {}: {e}",
+                        path.display(),
+                        index + 1
+                    )))
+                }
+            }
+        }
+        Ok(deadlines)
+    }
+
+    /// Give `keys` of `repo_id` a deadline `ttl` from now, or clear theirs
+    /// when `ttl` is `None`.
+    pub(super) fn set_deadlines(
+        &self,
+        repo_id: &str,
+        keys: &[&str],
+        ttl: Option<Duration>,
+    ) -> Result<(), StoreError> {
+        let deadline = ttl.map(|ttl| SystemTime::now() + ttl);
+        let mut all = self.lock_deadlines(repo_id)?;
+        let deadlines = all.get_mut(repo_id).expect("loaded by lock_deadlines");
+        let mut lines = Vec::new();
+        for key in keys {
+            let changed = match deadline {
+                Some(deadline) => deadlines.insert(key.to_string(), deadline) != Some(deadline),
+                None => deadlines.remove(*key).is_some(),
+            };
+            if changed {
+                let line = ExpiryLine {
+                    key: key.to_string(),
+                    expires_at_ms: deadline.map(unix_ms),
+                };
+                serde_json::to_writer(&mut lines, &line)
+                    .map_err(|e| StoreError::Io(e.to_string()))?;
+                lines.push(b'\n');
+            }
+        }
+        let Some(root) = self.fs_root.as_ref().filter(|_| !lines.is_empty()) else {
+            return Ok(());
+        };
+        let path = self.repo_path(root, repo_id)?.join(fs::EXPIRY_FILE);
+        std::fs::create_dir_all(path.parent().expect("inside the repository directory"))
+            .map_err(io)?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(io)?;
+        file.write_all(&lines)
+            .and_then(|()| file.sync_data())
+            .map_err(io)
+    }
+
+    /// When `key` of `repo_id` expires, if it has a deadline.
+    pub fn expires_at(&self, repo_id: &str, key: &str) -> Result<Option<SystemTime>, StoreError> {
+        Ok(self.lock_deadlines(repo_id)?[repo_id].get(key).copied())
+    }
+
+    pub(super) fn is_expired(&self, repo_id: &str, key: &str) -> Result<bool, StoreError> {
+        Ok(self
+            .expires_at(repo_id, key)?
+            .is_some_and(|deadline| deadline <= SystemTime::now()))
+    }
+
+    /// Keys of `repo_id` whose deadline has passed.
+    pub(super) fn expired_keys(&self, repo_id: &str) -> Result<BTreeSet<String>, StoreError> {
+        let now = SystemTime::now();
+        Ok(self.lock_deadlines(repo_id)?[repo_id]
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(key, _)| key.clone())
+            .collect())
+    }
+
+    /// Delete every record past its deadline, returning one
+    /// [`EXPIRED_STATUS`] entry per record in sequence order. The entries
+    /// are appended to the expiry ledger when one is set.
+    ///
+    /// With a write-ahead log the sweep holds its lock, so writes wait for
+    /// it. Without one, a record rewritten while it is swept may be
+    /// deleted anyway.
+    pub fn expire(&self) -> Result<Vec<ReplayEntry>, StoreError> {
+        let _wal = self.lock_wal()?;
+        self.check_maintainable()?;
+        let mut repos: BTreeSet<String> = self.list_repos()?.into_iter().collect();
+        repos.extend(
+            self.deadlines
+                .lock()
+                .map_err(|e| StoreError::Io(e.to_string()))?
+                .keys()
+                .cloned(),
+        );
+        let mut entries = Vec::new();
+        for repo_id in repos {
+            for key in self.expired_keys(&repo_id)? {
+                let before = Self::checksum(self.read_value(&repo_id, &key)?.as_deref());
+                self.tombstone(&repo_id, &key)?;
+                let seq = self.next_sequence.fetch_add(1, Ordering::SeqCst);
+                let entry =
+                    build_replay_entry(seq, &repo_id, &before, ABSENT_CHECKSUM, EXPIRED_STATUS);
+                self.record_sequence(&entry)?;
+                if let Some(ledger) = &self.expiry_ledger {
+                    ledger
+                        .append(&entry)
+                        .map_err(|e| StoreError::Ledger(e.to_string()))?;
+                }
+                self.lock_deadlines(&repo_id)?
+                    .get_mut(&repo_id)
+                    .expect("loaded by lock_deadlines")
+                    .remove(&key);
+                entries.push(entry);
+            }
+            self.compact_deadlines(&repo_id)?;
+        }
+        if !entries.is_empty() {
+            tracing::info!(records = entries.len(), "expired records");
+        }
+        Ok(entries)
+    }
+
+    /// Rewrite the `%expiry` file of `repo_id` with its live deadlines.
+    fn compact_deadlines(&self, repo_id: &str) -> Result<(), StoreError> {
+        let Some(root) = &self.fs_root else {
+            return Ok(());
+        };
+        let all = self.lock_deadlines(repo_id)?;
+        let path = self.repo_path(root, repo_id)?.join(fs::EXPIRY_FILE);
+        let deadlines = &all[repo_id];
+        if deadlines.is_empty() {
+            return match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(io(e)),
+                _ => Ok(()),
+            };
+        }
+        let mut bytes = Vec::new();
+        for (key, deadline) in deadlines {
+            let line = ExpiryLine {
+                key: key.clone(),
+                expires_at_ms: Some(unix_ms(*deadline)),
+            };
+            serde_json::to_writer(&mut bytes, &line).map_err(|e| StoreError::Io(e.to_string()))?;
+            bytes.push(b'\n');
+        }
+        fs::atomic_write(&path, &bytes).map_err(io)
+    }
+}
@@ -0,0 +1,245 @@
+//! Write-ahead log making multi-record batches of the filesystem store
+//! atomic across crashes.
+//!
+//! A batch is appended as one frame before any payload file is touched and
+//! the log is emptied once every file is in place, so a frame found on open
+//! belongs to a batch that may be partially applied and is redone. A frame
+//! cut short by a crash was never applied and is dropped.
+//!
+//! Frame: `u32 body_len | 8-byte blake3 prefix of body | body`, where the
+//! body is `u16 repo_len | repo | u32 count` followed by `count` records of
+//! `u64 sequence | u16 key_len | key | u16 before_len | before |
+//! u16 after_len | after | u32 bytes_len | bytes`, all little-endian.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, Write};
+use std::path::Path;
+use std::time::Instant;
+
+use crate::config::FsyncPolicy;
+
+const HEADER_LEN: usize = 12;
+
+/// One upsert of a logged batch, payload sealed as it is stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LoggedRecord {
+    pub sequence: u64,
+    pub key: String,
+    pub before: String,
+    pub after: String,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LoggedBatch {
+    pub repo_id: String,
+    pub records: Vec<LoggedRecord>,
+}
+
+pub(crate) struct Wal {
+    file: File,
+    policy: FsyncPolicy,
+    last_sync: Instant,
+    /// Holds a batch that has not been fully applied.
+    pending: bool,
+}
+
+impl Wal {
+    /// Open or create the log at `path`; [`Wal::read`] returns what a
+    /// previous process left behind.
+    pub fn open(path: &Path, policy: FsyncPolicy) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+        let pending = file.metadata()?.len() > 0;
+        Ok(Self {
+            file,
+            policy,
+            last_sync: Instant::now(),
+            pending,
+        })
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.pending
+    }
+
+    /// Whether payload files must be synced before the log is emptied.
+    pub fn is_durable(&self) -> bool {
+        self.policy == FsyncPolicy::Always
+    }
+
+    /// Complete batches in the log, oldest first; a torn or corrupt frame
+    /// ends the log.
+    pub fn read(&mut self) -> io::Result<Vec<LoggedBatch>> {
+        let mut bytes = Vec::new();
+        // Appends leave the offset at the end; writes ignore it.
+        self.file.rewind()?;
+        self.file.read_to_end(&mut bytes)?;
+        let mut batches = Vec::new();
+        let mut rest = bytes.as_slice();
+        while rest.len() >= HEADER_LEN {
+            let len = u32::from_le_bytes(rest[..4].try_into().expect("4 bytes")) as usize;
+            let Some(body) = rest.get(HEADER_LEN..HEADER_LEN + len) else {
+                break;
+            };
+            if blake3::hash(body).as_bytes()[..8] != rest[4..HEADER_LEN] {
+                break;
+            }
+            let Some(batch) = decode(body) else {
+                break;
+            };
+            batches.push(batch);
+            rest = &rest[HEADER_LEN + len..];
+        }
+        Ok(batches)
+    }
+
+    /// Append `batch`, syncing as the policy asks, before it is applied.
+    pub fn append(&mut self, batch: &LoggedBatch) -> io::Result<()> {
+        let body = encode(batch)?;
+        let body_len = u32::try_from(body.len()).map_err(|_| too_long("batch", u32::MAX))?;
+        let mut frame = Vec::with_capacity(HEADER_LEN + body.len());
+        frame.extend_from_slice(&body_len.to_le_bytes());
+        frame.extend_from_slice(&blake3::hash(&body).as_bytes()[..8]);
+        frame.extend_from_slice(&body);
+        self.pending = true;
+        self.file.write_all(&frame)?;
+        let due = match self.policy {
+            FsyncPolicy::Always => true,
+            FsyncPolicy::Interval(every) => self.last_sync.elapsed() >= every,
+            FsyncPolicy::Never => false,
+        };
+        if due {
+            self.file.sync_data()?;
+            self.last_sync = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// Empty the log once its batches are applied. `sync` persists that, so
+    /// recovery does not redo them again.
+    pub fn clear(&mut self, sync: bool) -> io::Result<()> {
+        self.file.set_len(0)?;
+        if sync {
+            self.file.sync_data()?;
+        }
+        self.pending = false;
+        Ok(())
+    }
+}
+
+fn too_long(what: &str, max: u32) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("{what} exceeds {max} bytes"),
+    )
+}
+
+fn put_str(out: &mut Vec<u8>, what: &str, value: &str) -> io::Result<()> {
+    let len = u16::try_from(value.len()).map_err(|_| too_long(what, u16::MAX.into()))?;
+    out.extend_from_slice(&len.to_le_bytes());
+    out.extend_from_slice(value.as_bytes());
+    Ok(())
+}
+
+fn encode(batch: &LoggedBatch) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    put_str(&mut out, "repo id", &batch.repo_id)?;
+    let count = u32::try_from(batch.records.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many records in batch"))?;
+    out.extend_from_slice(&count.to_le_bytes());
+    for record in &batch.records {
+        out.extend_from_slice(&record.sequence.to_le_bytes());
+        put_str(&mut out, "key", &record.key)?;
+        put_str(&mut out, "checksum", &record.before)?;
+        put_str(&mut out, "checksum", &record.after)?;
+        let bytes_len =
+            u32::try_from(record.bytes.len()).map_err(|_| too_long("payload", u32::MAX))?;
+        out.extend_from_slice(&bytes_len.to_le_bytes());
+        out.extend_from_slice(&record.bytes);
+    }
+    Ok(out)
+}
+
+/// Reads the fields of a frame body in order.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let (head, tail) = (self.0.get(..len)?, self.0.get(len..)?);
+        self.0 = tail;
+        Some(head)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.take(2)?.try_into().ok()?))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn str(&mut self) -> Option<String> {
+        let len = self.u16()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).ok()
+    }
+}
+
+fn decode(body: &[u8]) -> Option<LoggedBatch> {
+    let mut reader = Reader(body);
+    let repo_id = reader.str()?;
+    let count = reader.u32()?;
+    let mut records = Vec::new();
+    for _ in 0..count {
+        let sequence = reader.u64()?;
+        let key = reader.str()?;
+        let before = reader.str()?;
+        let after = reader.str()?;
+        let len = reader.u32()? as usize;
+        let bytes = reader.take(len)?.to_vec();
+        records.push(LoggedRecord {
+            sequence,
+            key,
+            before,
+            after,
+            bytes,
+        });
+    }
+    reader
+        .0
+        .is_empty()
+        .then_some(LoggedBatch { repo_id, records })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oversized_fields_are_rejected_instead_of_truncated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("%wal");
+        let mut wal = Wal::open(&path, FsyncPolicy::Always).unwrap();
+        let batch = LoggedBatch {
+            repo_id: "repo".into(),
+            records: vec![LoggedRecord {
+                sequence: 1,
+                key: "k".repeat(usize::from(u16::MAX) + 1),
+                before: String::new(),
+                after: String::new(),
+                bytes: b"payload".to_vec(),
+            }],
+        };
+        let err = wal.append(&batch).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(!wal.is_pending());
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 0);
+    }
+}
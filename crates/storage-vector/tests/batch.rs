@@ -0,0 +1,78 @@
+use storage_vector::store::{Store, VectorStore};
+use storage_vector::ABSENT_CHECKSUM;
+
+fn checksum(payload: &[u8]) -> String {
+    format!("blake3:{}", blake3::hash(payload).to_hex())
+}
+
+#[test]
+fn batch_entries_follow_input_order() {
+    let store = VectorStore::new();
+    store.upsert("repo", "a", b"old").unwrap();
+    let entries = store
+        .upsert_batch(
+            "repo",
+            &[
+                ("a", &b"new"[..]),
+                ("b", &b"first"[..]),
+                ("b", &b"second"[..]),
+            ],
+        )
+        .unwrap();
+
+    let sequences: Vec<u64> = entries.iter().map(|entry| entry.sequence).collect();
+    assert_eq!(sequences, vec![2, 3, 4]);
+    assert_eq!(entries[0].payload_checksum_before, checksum(b"old"));
+    assert_eq!(entries[1].payload_checksum_before, ABSENT_CHECKSUM);
+    // A key repeated in the batch chains from its earlier record.
+    assert_eq!(entries[2].payload_checksum_before, checksum(b"first"));
+    assert_eq!(entries[2].payload_checksum_after, checksum(b"second"));
+    assert_eq!(store.max_sequence("repo"), Some(4));
+    assert_eq!(store.upsert("repo", "c", b"next").unwrap().sequence, 5);
+
+    let values = store.get_many("repo", &["b", "missing", "a"]).unwrap();
+    assert_eq!(
+        values,
+        vec![Some(b"second".to_vec()), None, Some(b"new".to_vec())]
+    );
+    assert!(store
+        .upsert_batch::<&str, &[u8]>("repo", &[])
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn filesystem_batches_are_readable_after_reopen() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("vs");
+    let records: Vec<(String, Vec<u8>)> = (0..20)
+        .map(|i| {
+            (
+                format!("dir/chunk-{i}.rs"),
+                format!("payload-{i}").into_bytes(),
+            )
+        })
+        .collect();
+    VectorStore::with_fs_root(&root)
+        .upsert_batch("repo", &records)
+        .unwrap();
+
+    let reopened = VectorStore::with_fs_root(&root);
+    let keys: Vec<&str> = records.iter().map(|(key, _)| key.as_str()).collect();
+    let values = reopened.get_many("repo", &keys).unwrap();
+    for ((_, payload), value) in records.iter().zip(values) {
+        assert_eq!(value.as_ref(), Some(payload));
+    }
+    let leftovers = std::fs::read_dir(root.join("repo"))
+        .unwrap()
+        .filter(|entry| {
+            entry
+                .as_ref()
+                .unwrap()
+                .file_name()
+                .to_string_lossy()
+                .starts_with("%tmp-")
+        })
+        .count();
+    assert_eq!(leftovers, 0);
+}
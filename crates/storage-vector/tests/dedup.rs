@@ -0,0 +1,73 @@
+use std::path::Path;
+
+use storage_vector::store::{Store, VectorStore};
+use storage_vector::StoreConfig;
+
+fn dedup() -> StoreConfig {
+    StoreConfig {
+        dedup: true,
+        ..StoreConfig::default()
+    }
+}
+
+fn blob_count(root: &Path) -> usize {
+    std::fs::read_dir(root.join("%blobs"))
+        .map(|entries| entries.count())
+        .unwrap_or(0)
+}
+
+#[test]
+fn identical_payloads_share_one_blob_until_unreferenced() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = VectorStore::with_fs_root(dir.path()).with_config(dedup());
+    let chunk = vec![7u8; 4096];
+    store.upsert("repo-a", "one", &chunk).unwrap();
+    store.upsert("repo-b", "two", &chunk).unwrap();
+    store
+        .upsert_batch("repo-b", &[("three", &chunk[..]), ("other", &b"x"[..])])
+        .unwrap();
+    assert_eq!(blob_count(dir.path()), 2);
+    assert_eq!(store.get("repo-b", "two").unwrap(), Some(chunk.clone()));
+    // Keys hold a short reference, not the payload.
+    let stored = std::fs::metadata(dir.path().join("repo-a").join("one")).unwrap();
+    assert!(stored.len() < 100);
+
+    // Overwriting drops the old blob's last reference.
+    store.upsert("repo-b", "other", b"y").unwrap();
+    let report = store.compact().unwrap();
+    assert_eq!(report.blobs_removed, 1);
+    assert_eq!(blob_count(dir.path()), 2);
+
+    // References are recounted from disk by a new handle.
+    let reopened = VectorStore::with_fs_root(dir.path()).with_config(dedup());
+    reopened.delete("repo-a", "one").unwrap();
+    reopened.delete("repo-b", "two").unwrap();
+    assert_eq!(reopened.compact().unwrap().blobs_removed, 0);
+    reopened.delete("repo-b", "three").unwrap();
+    let report = reopened.compact().unwrap();
+    assert_eq!(report.blobs_removed, 1);
+    assert!(report.reclaimed_bytes >= 4096);
+    assert_eq!(
+        reopened.get("repo-b", "other").unwrap(),
+        Some(b"y".to_vec())
+    );
+}
+
+#[test]
+fn in_memory_dedup_and_snapshots_carry_payloads() {
+    let store = VectorStore::new().with_config(dedup());
+    store.upsert("repo", "a", b"shared").unwrap();
+    store.upsert("repo", "b", b"shared").unwrap();
+    store.delete("repo", "a").unwrap();
+    assert_eq!(store.compact().unwrap().blobs_removed, 0);
+    assert_eq!(store.get("repo", "b").unwrap(), Some(b"shared".to_vec()));
+
+    let backup = tempfile::tempdir().unwrap();
+    store.create_snapshot(backup.path().join("snap")).unwrap();
+    let plain = VectorStore::new();
+    plain.restore_snapshot(backup.path().join("snap")).unwrap();
+    assert_eq!(plain.get("repo", "b").unwrap(), Some(b"shared".to_vec()));
+
+    store.delete("repo", "b").unwrap();
+    assert_eq!(store.compact().unwrap().blobs_removed, 1);
+}
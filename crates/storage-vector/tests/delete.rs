@@ -0,0 +1,82 @@
+use storage_vector::store::{Store, VectorStore};
+use storage_vector::{ReplayRecord, VectorMetadata, ABSENT_CHECKSUM, TOMBSTONE_STATUS};
+
+#[test]
+fn delete_hides_payload_and_vector_until_compaction() {
+    let store = VectorStore::new();
+    let put = store
+        .upsert("repo", "src/gone.rs", b"fn gone() {}")
+        .unwrap();
+    store
+        .upsert("repo", "src/kept.rs", b"fn kept() {}")
+        .unwrap();
+    for (key, vector) in [
+        ("src/gone.rs", vec![1.0, 0.0]),
+        ("src/kept.rs", vec![0.8, 0.2]),
+    ] {
+        store
+            .insert_vector("repo", key, vector, VectorMetadata::new(key))
+            .unwrap();
+    }
+
+    let tombstone = store.delete("repo", "src/gone.rs").unwrap();
+    assert_eq!(tombstone.status, TOMBSTONE_STATUS);
+    assert_eq!(tombstone.sequence, 3);
+    assert_eq!(
+        tombstone.payload_checksum_before,
+        put.payload_checksum_after
+    );
+    assert_eq!(tombstone.payload_checksum_after, ABSENT_CHECKSUM);
+    assert_eq!(store.get("repo", "src/gone.rs").unwrap(), None);
+    let hits = store.search("repo", &[1.0, 0.0], 5, None).unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].key, "src/kept.rs");
+
+    // Deleting again is recorded but has nothing left to remove.
+    let again = store.delete("repo", "src/gone.rs").unwrap();
+    assert_eq!(again.payload_checksum_before, ABSENT_CHECKSUM);
+
+    let report = store.compact().unwrap();
+    assert_eq!(report.tombstones_removed, 1);
+    assert_eq!(report.reclaimed_bytes, b"fn gone() {}".len() as u64);
+    assert_eq!(store.compact().unwrap().tombstones_removed, 0);
+    assert!(store.get("repo", "src/kept.rs").unwrap().is_some());
+
+    // The tombstone entry replays as a delete.
+    let replica = VectorStore::new();
+    let put = ReplayRecord::put(put, "src/gone.rs", b"fn gone() {}".to_vec());
+    replica.replay_records([put.clone()]).unwrap();
+    let stats = replica
+        .replay_records([put, ReplayRecord::delete(tombstone, "src/gone.rs")])
+        .unwrap();
+    assert_eq!((stats.applied, stats.skipped), (1, 1));
+    assert_eq!(replica.get("repo", "src/gone.rs").unwrap(), None);
+}
+
+#[test]
+fn filesystem_tombstones_survive_reopen_until_compacted() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("vs");
+    let store = VectorStore::with_fs_root(&root);
+    store.upsert("repo", "a.rs", b"payload").unwrap();
+    store.delete("repo", "a.rs").unwrap();
+    assert!(root.join("repo").join("%tomb-a.rs").exists());
+    assert!(!root.join("repo").join("a.rs").exists());
+
+    let reopened = VectorStore::with_fs_root(&root);
+    assert_eq!(reopened.get("repo", "a.rs").unwrap(), None);
+    reopened.upsert("repo", "a.rs", b"back").unwrap();
+    assert_eq!(
+        reopened.get("repo", "a.rs").unwrap(),
+        Some(b"back".to_vec())
+    );
+
+    let report = reopened.compact().unwrap();
+    assert_eq!(report.tombstones_removed, 1);
+    assert_eq!(report.reclaimed_bytes, b"payload".len() as u64);
+    assert!(!root.join("repo").join("%tomb-a.rs").exists());
+    assert_eq!(
+        reopened.get("repo", "a.rs").unwrap(),
+        Some(b"back".to_vec())
+    );
+}
@@ -0,0 +1,179 @@
+#![cfg(feature = "external-kms")]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use serde_json::{json, Value};
+use storage_vector::encryption::aes_gcm::AesGcmEncrypter;
+use storage_vector::kms::{
+    AwsCredentials, AwsKms, HttpPost, HttpReply, KeyManager, KeyScope, KmsKeyManager, VaultTransit,
+};
+use storage_vector::store::{Store, VectorStore};
+use tempfile::tempdir;
+use zeroize::Zeroizing;
+
+/// Transit engine that "wraps" a key by flipping its bits.
+#[derive(Default)]
+struct FakeVault {
+    issued: Mutex<u8>,
+    decrypts: AtomicUsize,
+}
+
+impl HttpPost for FakeVault {
+    fn post(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        body: &[u8],
+    ) -> Result<HttpReply, String> {
+        assert!(headers
+            .iter()
+            .any(|(name, value)| name == "x-vault-token" && value == "s.token"));
+        let body: Value = serde_json::from_slice(body).unwrap();
+        let plaintext = match url {
+            "http://vault:8200/v1/transit/datakey/plaintext/embeddings" => {
+                assert_eq!(body["bits"], 256);
+                let mut issued = self.issued.lock().unwrap();
+                *issued += 1;
+                vec![*issued; 32]
+            }
+            "http://vault:8200/v1/transit/decrypt/embeddings" => {
+                self.decrypts.fetch_add(1, Ordering::SeqCst);
+                let wrapped = body["ciphertext"].as_str().unwrap();
+                let wrapped = BASE64
+                    .decode(wrapped.strip_prefix("vault:v1:").unwrap())
+                    .unwrap();
+                wrapped.iter().map(|byte| !byte).collect()
+            }
+            other => panic!("unexpected url {other}"),
+        };
+        let wrapped: Vec<u8> = plaintext.iter().map(|byte| !byte).collect();
+        let reply = json!({ "data": {
+            "plaintext": BASE64.encode(&plaintext),
+            "ciphertext": format!("vault:v1:{}", BASE64.encode(wrapped)),
+        }});
+        Ok(HttpReply {
+            status: 200,
+            body: reply.to_string().into_bytes(),
+        })
+    }
+}
+
+fn store_with(root: &std::path::Path, kms: Arc<dyn KeyManager + Send + Sync>) -> VectorStore {
+    VectorStore::builder()
+        .with_fs_root(root)
+        .with_encrypter(Arc::new(AesGcmEncrypter::new()))
+        .with_key_manager(kms)
+        .build()
+}
+
+#[test]
+fn vault_wrapped_keys_persist_and_are_unwrapped_again_after_their_ttl() {
+    let tmp = tempdir().unwrap();
+    let root = tmp.path().join("vs");
+    let keys = tmp.path().join("keys");
+    let vault = Arc::new(FakeVault::default());
+    let transit =
+        || VaultTransit::new("http://vault:8200/", "s.token", "embeddings", vault.clone());
+
+    let kms = Arc::new(KmsKeyManager::open(transit(), &keys, Duration::from_secs(3600)).unwrap());
+    let store = store_with(&root, kms.clone());
+    store.upsert("repo", "a", b"alpha").unwrap();
+    kms.rotate().unwrap();
+    store.upsert("repo", "b", b"beta").unwrap();
+    assert_eq!(store.get("repo", "a").unwrap().unwrap(), b"alpha");
+    // Both keys were generated here and are still cached.
+    assert_eq!(vault.decrypts.load(Ordering::SeqCst), 0);
+    drop(store);
+
+    for entry in std::fs::read_dir(&keys).unwrap() {
+        let stored = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+        assert!(!stored.contains(&BASE64.encode([1u8; 32])));
+        assert!(!stored.contains(&BASE64.encode([2u8; 32])));
+    }
+
+    // A zero TTL unwraps on every use.
+    let kms = Arc::new(KmsKeyManager::open(transit(), &keys, Duration::ZERO).unwrap());
+    let store = store_with(&root, kms);
+    let opened = vault.decrypts.load(Ordering::SeqCst);
+    assert_eq!(opened, 1);
+    assert_eq!(store.get("repo", "a").unwrap().unwrap(), b"alpha");
+    assert_eq!(store.get("repo", "b").unwrap().unwrap(), b"beta");
+    assert_eq!(store.get("repo", "a").unwrap().unwrap(), b"alpha");
+    assert_eq!(vault.decrypts.load(Ordering::SeqCst), opened + 3);
+}
+
+#[test]
+fn aws_kms_requests_are_signed_and_unwrap_through_decrypt() {
+    struct FakeAws;
+
+    impl HttpPost for FakeAws {
+        fn post(
+            &self,
+            url: &str,
+            headers: &[(String, String)],
+            body: &[u8],
+        ) -> Result<HttpReply, String> {
+            assert_eq!(url, "https://kms.eu-west-1.amazonaws.com/");
+            let header = |wanted: &str| {
+                headers
+                    .iter()
+                    .find(|(name, _)| name == wanted)
+                    .map(|(_, value)| value.clone())
+                    .unwrap_or_else(|| panic!("missing {wanted}"))
+            };
+            assert_eq!(header("host"), "kms.eu-west-1.amazonaws.com");
+            assert_eq!(header("x-amz-security-token"), "session");
+            assert!(header("authorization").starts_with("AWS4-HMAC-SHA256 Credential=AKID/"));
+            assert!(header("authorization").contains("/eu-west-1/kms/aws4_request"));
+            let body: Value = serde_json::from_slice(body).unwrap();
+            assert_eq!(body["KeyId"], "alias/embeddings");
+            let reply = match header("x-amz-target").as_str() {
+                "TrentService.GenerateDataKey" => {
+                    assert_eq!(body["KeySpec"], "AES_256");
+                    json!({
+                        "Plaintext": BASE64.encode([7u8; 32]),
+                        "CiphertextBlob": BASE64.encode(b"wrapped-7"),
+                    })
+                }
+                "TrentService.Decrypt" => {
+                    assert_eq!(body["CiphertextBlob"], BASE64.encode(b"wrapped-7"));
+                    json!({ "Plaintext": BASE64.encode([7u8; 32]) })
+                }
+                other => panic!("unexpected target {other}"),
+            };
+            Ok(HttpReply {
+                status: 200,
+                body: reply.to_string().into_bytes(),
+            })
+        }
+    }
+
+    let tmp = tempdir().unwrap();
+    let credentials = AwsCredentials {
+        access_key_id: "AKID".into(),
+        secret_access_key: Zeroizing::new("secret".into()),
+        session_token: Some("session".into()),
+    };
+    let aws = || {
+        AwsKms::new(
+            "eu-west-1",
+            "alias/embeddings",
+            credentials.clone(),
+            Arc::new(FakeAws),
+        )
+    };
+    let scope = KeyScope {
+        repo_id: "repo".into(),
+    };
+    let created = KmsKeyManager::open(aws(), tmp.path(), Duration::ZERO).unwrap();
+    let key = created.current(&scope).unwrap();
+    assert_eq!(*key.key_bytes, [7u8; 32]);
+
+    let reopened = KmsKeyManager::open(aws(), tmp.path(), Duration::ZERO).unwrap();
+    assert_eq!(reopened.current(&scope).unwrap().key_id, key.key_id);
+    assert!(reopened.get("../current").is_err());
+}
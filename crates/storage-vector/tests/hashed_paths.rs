@@ -0,0 +1,113 @@
+#![cfg(feature = "encryption")]
+
+use std::path::Path;
+use std::sync::Arc;
+
+use storage_vector::config::StoreConfig;
+use storage_vector::encryption::aes_gcm::AesGcmEncrypter;
+use storage_vector::kms::InMemoryKeyManager;
+use storage_vector::store::{Store, VectorStore};
+
+fn hashed_store(root: &Path) -> VectorStore {
+    encrypted_store(root, true)
+}
+
+fn encrypted_store(root: &Path, hashed_paths: bool) -> VectorStore {
+    VectorStore::builder()
+        .with_encrypter(Arc::new(AesGcmEncrypter::new()))
+        .with_key_manager(Arc::new(InMemoryKeyManager::new_with_secret(
+            "k1", [7u8; 32],
+        )))
+        .with_fs_root(root)
+        .with_config(StoreConfig {
+            hashed_paths,
+            ..StoreConfig::default()
+        })
+        .build()
+}
+
+/// Every file and directory name below `dir`.
+fn names(dir: &Path, out: &mut Vec<String>) {
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let entry = entry.unwrap();
+        out.push(entry.file_name().to_string_lossy().into_owned());
+        if entry.file_type().unwrap().is_dir() {
+            names(&entry.path(), out);
+        }
+    }
+}
+
+#[test]
+fn on_disk_names_hide_repositories_and_keys() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = hashed_store(dir.path());
+    store
+        .upsert("repo-alpha", "src/secret_plan.rs", b"fn main() {}")
+        .unwrap();
+    store
+        .upsert_batch(
+            "repo-alpha",
+            &[("docs/roadmap.md", &b"q3"[..]), ("gone.rs", &b"x"[..])],
+        )
+        .unwrap();
+    store
+        .upsert("repo-beta", "lib.rs", b"pub fn f() {}")
+        .unwrap();
+    store.delete("repo-alpha", "gone.rs").unwrap();
+
+    let mut on_disk = Vec::new();
+    names(dir.path(), &mut on_disk);
+    for plain in ["repo", "alpha", "beta", "secret", "roadmap", "gone", "lib"] {
+        assert!(
+            on_disk.iter().all(|name| !name.contains(plain)),
+            "{plain} appears in {on_disk:?}"
+        );
+    }
+
+    let reopened = hashed_store(dir.path());
+    assert_eq!(
+        reopened.get("repo-alpha", "src/secret_plan.rs").unwrap(),
+        Some(b"fn main() {}".to_vec())
+    );
+    assert_eq!(
+        reopened.list_repos().unwrap(),
+        vec!["repo-alpha", "repo-beta"]
+    );
+    assert_eq!(
+        reopened.list_keys("repo-alpha", "", None, 10).unwrap().keys,
+        vec!["docs/roadmap.md", "src/secret_plan.rs"]
+    );
+    assert_eq!(reopened.get("repo-alpha", "gone.rs").unwrap(), None);
+    assert_eq!(reopened.compact().unwrap().tombstones_removed, 1);
+}
+
+#[test]
+fn reopening_with_hashed_paths_changed_is_refused() {
+    let plain = tempfile::tempdir().unwrap();
+    encrypted_store(plain.path(), false)
+        .upsert("repo-alpha", "lib.rs", b"pub fn f() {}")
+        .unwrap();
+    let err = hashed_store(plain.path())
+        .get("repo-alpha", "lib.rs")
+        .expect_err("plain names under hashed_paths");
+    assert!(err.to_string().contains("without hashed_paths"), "{err}");
+    assert!(hashed_store(plain.path()).list_repos().is_err());
+
+    let hashed = tempfile::tempdir().unwrap();
+    hashed_store(hashed.path())
+        .upsert("repo-alpha", "lib.rs", b"pub fn f() {}")
+        .unwrap();
+    let err = encrypted_store(hashed.path(), false)
+        .list_repos()
+        .expect_err("hashed names without hashed_paths");
+    assert!(
+        err.to_string().contains("names its entries by hash"),
+        "{err}"
+    );
+    assert_eq!(
+        hashed_store(hashed.path())
+            .get("repo-alpha", "lib.rs")
+            .unwrap(),
+        Some(b"pub fn f() {}".to_vec())
+    );
+}
@@ -0,0 +1,144 @@
+#![cfg(feature = "hnsw")]
+
+use std::collections::HashSet;
+
+use storage_vector::store::VectorStore;
+use storage_vector::{HnswConfig, Metric, SearchFilter, StoreConfig, VectorMetadata};
+
+const DIMENSION: usize = 16;
+
+/// Deterministic pseudo-random vectors so failures reproduce.
+fn vectors(count: usize, seed: u64) -> Vec<Vec<f32>> {
+    let mut state = seed;
+    (0..count)
+        .map(|_| {
+            (0..DIMENSION)
+                .map(|_| {
+                    state = state
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+                    (state >> 40) as f32 / (1u64 << 24) as f32 - 0.5
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn hnsw_config() -> StoreConfig {
+    StoreConfig {
+        hnsw: Some(HnswConfig {
+            m: 8,
+            ef_construction: 64,
+            ef_search: 32,
+        }),
+        ..StoreConfig::default()
+    }
+}
+
+fn fill(store: &VectorStore, data: &[Vec<f32>]) {
+    for (i, vector) in data.iter().enumerate() {
+        let metadata = VectorMetadata::new(format!("repo::file-{}.rs::0", i % 4));
+        store
+            .insert_vector("repo", &format!("v{i}"), vector.clone(), metadata)
+            .unwrap();
+    }
+}
+
+fn keys(hits: &[storage_vector::SearchHit]) -> Vec<String> {
+    hits.iter().map(|hit| hit.key.clone()).collect()
+}
+
+#[test]
+fn recall_matches_brute_force_closely() {
+    let data = vectors(600, 7);
+    let exact = VectorStore::new();
+    let approximate = VectorStore::new().with_config(hnsw_config());
+    fill(&exact, &data);
+    fill(&approximate, &data);
+
+    let mut found = 0;
+    let queries = vectors(25, 99);
+    for query in &queries {
+        let truth: HashSet<String> = keys(&exact.search("repo", query, 10, None).unwrap())
+            .into_iter()
+            .collect();
+        let hits = approximate.search("repo", query, 10, None).unwrap();
+        assert_eq!(hits.len(), 10);
+        found += hits.iter().filter(|hit| truth.contains(&hit.key)).count();
+    }
+    let recall = found as f64 / (queries.len() * 10) as f64;
+    assert!(recall >= 0.9, "recall {recall}");
+}
+
+#[test]
+fn incremental_updates_and_filters_are_reflected() {
+    let data = vectors(200, 3);
+    let store = VectorStore::new().with_config(hnsw_config());
+    fill(&store, &data);
+
+    let query = data[42].clone();
+    assert_eq!(store.search("repo", &query, 1, None).unwrap()[0].key, "v42");
+
+    // Replacing moves the key, removing drops it from every result.
+    store
+        .insert_vector("repo", "v42", data[7].clone(), VectorMetadata::new("p"))
+        .unwrap();
+    let hits = store.search("repo", &data[7], 2, None).unwrap();
+    assert_eq!(
+        keys(&hits).into_iter().collect::<HashSet<_>>(),
+        HashSet::from(["v7".to_string(), "v42".to_string()])
+    );
+    assert!(store.remove_vector("repo", "v42").unwrap());
+    assert!(store
+        .search("repo", &data[7], 200, None)
+        .unwrap()
+        .iter()
+        .all(|hit| hit.key != "v42"));
+
+    let filter = SearchFilter {
+        plan_id_prefix: Some("repo::file-1.rs".into()),
+        ..SearchFilter::default()
+    };
+    let hits = store.search("repo", &query, 5, Some(&filter)).unwrap();
+    assert_eq!(hits.len(), 5);
+    assert!(hits
+        .iter()
+        .all(|hit| hit.metadata.plan_id == "repo::file-1.rs::0"));
+
+    // Churn past the live count triggers a rebuild without losing vectors.
+    for i in 0..150 {
+        store.remove_vector("repo", &format!("v{i}")).unwrap();
+    }
+    let hits = store.search("repo", &data[180], 60, None).unwrap();
+    assert_eq!(hits.len(), 50);
+    assert_eq!(hits[0].key, "v180");
+}
+
+#[test]
+fn graph_is_persisted_alongside_the_fs_store() {
+    let dir = tempfile::tempdir().unwrap();
+    let data = vectors(100, 11);
+    let store = VectorStore::with_fs_root(dir.path()).with_config(hnsw_config());
+    fill(&store, &data);
+    let before = store.search("repo", &data[5], 5, None).unwrap();
+    assert_eq!(store.persist_vectors().unwrap(), 1);
+    assert_eq!(store.persist_vectors().unwrap(), 0);
+    assert!(dir.path().join("repo").join("%vectors").exists());
+
+    let reopened = VectorStore::with_fs_root(dir.path()).with_config(hnsw_config());
+    assert_eq!(reopened.search("repo", &data[5], 5, None).unwrap(), before);
+    reopened
+        .insert_vector("repo", "extra", data[5].clone(), VectorMetadata::new("p"))
+        .unwrap();
+    assert_eq!(
+        reopened.search("repo", &data[5], 101, None).unwrap().len(),
+        101
+    );
+
+    // A store configured for exact search rebuilds the persisted graph.
+    let exact = VectorStore::with_fs_root(dir.path()).with_metric(Metric::Cosine);
+    assert_eq!(
+        keys(&exact.search("repo", &data[5], 5, None).unwrap()),
+        keys(&before)
+    );
+}
@@ -0,0 +1,95 @@
+#![cfg(any(feature = "file-keystore", feature = "keychain"))]
+
+use std::sync::Arc;
+use storage_vector::encryption::aes_gcm::AesGcmEncrypter;
+use storage_vector::kms::KeyManager;
+use storage_vector::store::{Store, VectorStore};
+use tempfile::tempdir;
+
+fn store_with(root: &std::path::Path, kms: Arc<dyn KeyManager + Send + Sync>) -> VectorStore {
+    VectorStore::builder()
+        .with_fs_root(root)
+        .with_encrypter(Arc::new(AesGcmEncrypter::new()))
+        .with_key_manager(kms)
+        .build()
+}
+
+#[cfg(feature = "file-keystore")]
+#[test]
+fn file_keystore_survives_reopen_and_rejects_wrong_passphrase() {
+    use storage_vector::kms::{FileKeyManager, KeystoreKdf};
+
+    let tmp = tempdir().unwrap();
+    let root = tmp.path().join("vs");
+    let path = tmp.path().join("keys.json");
+    // Cheap parameters keep the test fast; the defaults are far costlier.
+    let kdf = KeystoreKdf {
+        m_cost: 64,
+        t_cost: 1,
+        p_cost: 1,
+    };
+    let kms = Arc::new(FileKeyManager::create(&path, "hunter2", "k1", kdf).unwrap());
+    assert!(FileKeyManager::create(&path, "hunter2", "k1", kdf).is_err());
+
+    let store = store_with(&root, kms.clone());
+    store.upsert("repo", "a", b"alpha").unwrap();
+    kms.rotate().unwrap();
+    store.upsert("repo", "b", b"beta").unwrap();
+    drop(store);
+
+    let keystore = std::fs::read_to_string(&path).unwrap();
+    assert!(keystore.contains("\"k1\""));
+    assert!(FileKeyManager::open(&path, "wrong").is_err());
+
+    let reopened = Arc::new(FileKeyManager::open(&path, "hunter2").unwrap());
+    let store = store_with(&root, reopened);
+    assert_eq!(store.get("repo", "a").unwrap().unwrap(), b"alpha");
+    assert_eq!(store.get("repo", "b").unwrap().unwrap(), b"beta");
+}
+
+#[cfg(feature = "keychain")]
+#[test]
+fn keychain_manager_creates_rotates_and_reloads_keys() {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use storage_vector::kms::{KeyScope, KeychainKeyManager, SecretStore};
+
+    #[derive(Clone, Default)]
+    struct Secrets(Arc<Mutex<HashMap<String, Vec<u8>>>>);
+
+    impl SecretStore for Secrets {
+        fn load(&self, name: &str) -> Result<Option<Vec<u8>>, String> {
+            Ok(self.0.lock().unwrap().get(name).cloned())
+        }
+
+        fn store(&self, name: &str, secret: &[u8]) -> Result<(), String> {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(name.to_string(), secret.to_vec());
+            Ok(())
+        }
+    }
+
+    let tmp = tempdir().unwrap();
+    let root = tmp.path().join("vs");
+    let secrets = Secrets::default();
+    let scope = KeyScope {
+        repo_id: "repo".into(),
+    };
+    let kms = Arc::new(KeychainKeyManager::with_store(secrets.clone()).unwrap());
+    let first = kms.current(&scope).unwrap().key_id;
+
+    let store = store_with(&root, kms.clone());
+    store.upsert("repo", "a", b"alpha").unwrap();
+    let second = kms.rotate().unwrap().key_id;
+    assert_ne!(first, second);
+    store.upsert("repo", "b", b"beta").unwrap();
+    drop(store);
+
+    let reloaded = Arc::new(KeychainKeyManager::with_store(secrets).unwrap());
+    assert_eq!(reloaded.current(&scope).unwrap().key_id, second);
+    let store = store_with(&root, reloaded);
+    assert_eq!(store.get("repo", "a").unwrap().unwrap(), b"alpha");
+    assert_eq!(store.get("repo", "b").unwrap().unwrap(), b"beta");
+}
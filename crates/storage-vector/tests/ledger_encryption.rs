@@ -0,0 +1,109 @@
+#![cfg(feature = "encryption")]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use storage_ledger::{Ledger, LedgerConfig, LedgerError, OfflineReplayBuffer, ReplayError};
+use storage_vector::config::RotationPolicy;
+use storage_vector::encryption::aes_gcm::AesGcmEncrypter;
+use storage_vector::kms::{InMemoryKeyManager, KeyManager, KeyScope};
+use storage_vector::ledger::build_replay_entry;
+use storage_vector::LedgerCipher;
+use tempfile::tempdir;
+
+fn cipher(keys: Arc<InMemoryKeyManager>, scope: &str) -> Arc<LedgerCipher> {
+    Arc::new(LedgerCipher::new(
+        Arc::new(AesGcmEncrypter::new()),
+        keys,
+        KeyScope {
+            repo_id: scope.into(),
+        },
+    ))
+}
+
+#[test]
+fn ledger_frames_are_sealed_and_survive_key_rotation() {
+    let tmp = tempdir().unwrap();
+    let keys = Arc::new(InMemoryKeyManager::new_with_secret("k1", [9u8; 32]));
+    let rotating = Arc::new(
+        LedgerCipher::new(
+            Arc::new(AesGcmEncrypter::new()),
+            keys.clone(),
+            KeyScope {
+                repo_id: "ledger".into(),
+            },
+        )
+        .with_rotation(RotationPolicy {
+            max_uses: Some(2),
+            rotate_after_seconds: None,
+        }),
+    );
+    {
+        let ledger =
+            Ledger::open_with_cipher(tmp.path(), LedgerConfig::default(), rotating).unwrap();
+        for sequence in 1..=5 {
+            let entry = build_replay_entry(sequence, "secret-repo", "cafe", "f00d", "emitted");
+            ledger.append(&entry).unwrap();
+        }
+    }
+    let scope = KeyScope {
+        repo_id: "ledger".into(),
+    };
+    assert_ne!(keys.current(&scope).unwrap().key_id, "k1");
+    for segment in std::fs::read_dir(tmp.path()).unwrap() {
+        let bytes = std::fs::read(segment.unwrap().path()).unwrap();
+        assert!(!bytes.windows(11).any(|window| window == b"secret-repo"));
+    }
+
+    let ledger = Ledger::open_with_cipher(
+        tmp.path(),
+        LedgerConfig::default(),
+        cipher(keys.clone(), "ledger"),
+    )
+    .unwrap();
+    let entries: Vec<_> = ledger.iter_from(0).map(Result::unwrap).collect();
+    assert_eq!(entries.len(), 5);
+    assert!(entries.iter().all(|entry| entry.repo_id == "secret-repo"));
+    drop(ledger);
+
+    // Records are bound to the cipher's scope.
+    assert!(matches!(
+        Ledger::open_with_cipher(tmp.path(), LedgerConfig::default(), cipher(keys, "other")),
+        Err(LedgerError::Cipher(_))
+    ));
+}
+
+#[test]
+fn replay_journal_is_sealed_with_the_store_key_manager() {
+    let tmp = tempdir().unwrap();
+    let path = tmp.path().join("offline.jsonl");
+    let keys = Arc::new(InMemoryKeyManager::new_with_secret("k1", [4u8; 32]));
+    let max_age = Duration::from_secs(600);
+    {
+        let buffer =
+            OfflineReplayBuffer::open_with_cipher(&path, 16, max_age, cipher(keys.clone(), "q"))
+                .unwrap();
+        buffer
+            .push(build_replay_entry(
+                1,
+                "secret-repo",
+                "cafe",
+                "f00d",
+                "buffered",
+            ))
+            .unwrap();
+    }
+    let journal = std::fs::read_to_string(&path).unwrap();
+    assert!(!journal.contains("secret-repo") && !journal.contains("f00d"));
+
+    let other = Arc::new(InMemoryKeyManager::new_with_secret("k1", [5u8; 32]));
+    assert!(matches!(
+        OfflineReplayBuffer::open_with_cipher(&path, 16, max_age, cipher(other, "q")),
+        Err(ReplayError::Journal(_))
+    ));
+    let buffer =
+        OfflineReplayBuffer::open_with_cipher(&path, 16, max_age, cipher(keys, "q")).unwrap();
+    let drained = buffer.drain_ready();
+    assert_eq!(drained.len(), 1);
+    assert_eq!(drained[0].entry.payload_checksum_after, "f00d");
+}
@@ -0,0 +1,79 @@
+use storage_vector::store::{Store, VectorStore};
+use storage_vector::VectorMetadata;
+
+fn populate(store: &VectorStore) {
+    for key in [
+        "src/a.rs",
+        "src/b.rs",
+        "src/c.rs",
+        "docs/readme.md",
+        "src/zeta/d.rs",
+    ] {
+        store.upsert("repo-a", key, key.as_bytes()).unwrap();
+    }
+    store.upsert("repo b/with space", "k", b"v").unwrap();
+    store.upsert("repo-empty", "gone", b"v").unwrap();
+    store.delete("repo-empty", "gone").unwrap();
+}
+
+fn check_listing(store: &VectorStore) {
+    assert_eq!(
+        store.list_repos().unwrap(),
+        vec!["repo b/with space".to_string(), "repo-a".to_string()]
+    );
+
+    let first = store.list_keys("repo-a", "src/", None, 2).unwrap();
+    assert_eq!(first.keys, vec!["src/a.rs", "src/b.rs"]);
+    let second = store
+        .list_keys("repo-a", "src/", first.next_cursor.as_deref(), 2)
+        .unwrap();
+    assert_eq!(second.keys, vec!["src/c.rs", "src/zeta/d.rs"]);
+    assert_eq!(second.next_cursor, None);
+    assert!(store
+        .list_keys("repo-empty", "", None, 10)
+        .unwrap()
+        .keys
+        .is_empty());
+
+    let scanned: Vec<(String, Vec<u8>)> = store.scan("repo-a").collect::<Result<_, _>>().unwrap();
+    assert_eq!(scanned.len(), 5);
+    assert_eq!(
+        scanned[0],
+        ("docs/readme.md".into(), b"docs/readme.md".to_vec())
+    );
+    assert!(scanned.windows(2).all(|pair| pair[0].0 < pair[1].0));
+}
+
+#[test]
+fn in_memory_listing_pages_and_scans() {
+    let store = VectorStore::new();
+    populate(&store);
+    check_listing(&store);
+}
+
+#[test]
+fn filesystem_listing_skips_reserved_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("vs");
+    let store = VectorStore::with_fs_root(&root);
+    populate(&store);
+    store
+        .insert_vector("repo-a", "src/a.rs", vec![1.0], VectorMetadata::new("p"))
+        .unwrap();
+    store.persist_vectors().unwrap();
+    assert!(root.join("repo-a").join("%vectors").exists());
+    assert!(root.join("repo-empty").join("%tomb-gone").exists());
+
+    check_listing(&VectorStore::with_fs_root(&root));
+}
+
+#[test]
+fn scans_cross_page_boundaries() {
+    let store = VectorStore::new();
+    let records: Vec<(String, Vec<u8>)> =
+        (0..600).map(|i| (format!("key-{i:04}"), vec![1])).collect();
+    store.upsert_batch("repo", &records).unwrap();
+    let keys: Vec<String> = store.scan("repo").map(|record| record.unwrap().0).collect();
+    assert_eq!(keys.len(), 600);
+    assert_eq!(keys.last().map(String::as_str), Some("key-0599"));
+}
@@ -0,0 +1,70 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use storage_vector::store::fs as vs_fs;
+use storage_vector::store::{Store, VectorStore};
+use storage_vector::{MaintenanceConfig, SegmentConfig, SegmentStore};
+
+#[test]
+fn maintenance_collects_tombstones_and_stale_staging_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = VectorStore::with_fs_root(dir.path());
+    store.upsert("repo", "gone", &[7u8; 300]).unwrap();
+    store.upsert("repo", "kept", b"value").unwrap();
+    store.delete("repo", "gone").unwrap();
+    let orphan = vs_fs::repo_dir(dir.path(), "repo").join(format!("{}k", vs_fs::TMP_PREFIX));
+    std::fs::write(&orphan, [0u8; 500]).unwrap();
+
+    // Fresh staging files may belong to a write in progress.
+    let report = store
+        .run_maintenance(&MaintenanceConfig::default())
+        .unwrap();
+    assert_eq!(report.compaction.tombstones_removed, 1);
+    assert_eq!(report.tmp_files_removed, 0);
+    assert!(orphan.exists());
+
+    let throttled = MaintenanceConfig {
+        tmp_max_age: Duration::ZERO,
+        max_bytes_per_second: Some(5_000),
+        ..MaintenanceConfig::default()
+    };
+    let report = store.run_maintenance(&throttled).unwrap();
+    assert_eq!(
+        (report.tmp_files_removed, report.tmp_bytes_removed),
+        (1, 500)
+    );
+    assert!(!orphan.exists());
+    assert!(report.throttled >= Duration::from_millis(50), "{report:?}");
+    assert!(report.elapsed >= report.throttled);
+    assert_eq!(store.get("repo", "kept").unwrap().unwrap(), b"value");
+}
+
+#[test]
+fn scheduled_maintenance_compacts_segments_and_reports() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = SegmentConfig {
+        max_segment_bytes: 512,
+        ..SegmentConfig::default()
+    };
+    let store = Arc::new(SegmentStore::open(dir.path(), config).unwrap());
+    for round in 0..6 {
+        let value = format!("{round}{}", "y".repeat(200));
+        store.upsert("repo", "hot", value.as_bytes()).unwrap();
+    }
+    let before = store.segment_count();
+
+    let task = store.spawn_maintenance(MaintenanceConfig {
+        every: Duration::from_millis(10),
+        max_bytes_per_second: Some(1 << 20),
+        ..MaintenanceConfig::default()
+    });
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while task.last_report().is_none() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let report = task.last_report().expect("a pass finished");
+    drop(task);
+    assert!(report.compaction.segments_merged > 0, "{report:?}");
+    assert!(store.segment_count() < before);
+    assert!(store.get("repo", "hot").unwrap().unwrap().starts_with(b"5"));
+}
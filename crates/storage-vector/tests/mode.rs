@@ -0,0 +1,141 @@
+use std::sync::Arc;
+
+use runtime_router::{CommandRouter, HandlerRouter, RouterCommand, SessionContext, BATCH_COMMAND};
+use serde_json::json;
+use storage_vector::commands::{self, MODE_COMMAND, STORAGE_ADMIN_CAPABILITY};
+use storage_vector::store::{Store, VectorStore};
+use storage_vector::{FsyncPolicy, MaintenanceConfig, StoreError, StoreMode, VectorMetadata};
+
+fn refused(result: Result<impl std::fmt::Debug, StoreError>, mode: StoreMode) {
+    match result {
+        Err(StoreError::ReadOnly(found)) => assert_eq!(found, mode),
+        other => panic!("expected a {mode} refusal, got {other:?}"),
+    }
+}
+
+#[test]
+fn read_only_refuses_every_write_but_keeps_reads_and_search() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = VectorStore::with_fs_root(dir.path())
+        .with_wal(FsyncPolicy::Never)
+        .unwrap();
+    store.upsert("repo", "a", b"alpha").unwrap();
+    store
+        .insert_vector("repo", "a", vec![1.0, 0.0], VectorMetadata::default())
+        .unwrap();
+
+    assert_eq!(
+        store.set_mode(StoreMode::ReadOnly).unwrap(),
+        StoreMode::Normal
+    );
+    refused(store.upsert("repo", "b", b"beta"), StoreMode::ReadOnly);
+    refused(store.delete("repo", "a"), StoreMode::ReadOnly);
+    refused(
+        store.upsert_batch("repo", &[("c", b"c")]),
+        StoreMode::ReadOnly,
+    );
+    refused(
+        store.insert_vector("repo", "b", vec![0.0, 1.0], VectorMetadata::default()),
+        StoreMode::ReadOnly,
+    );
+    refused(store.compact(), StoreMode::ReadOnly);
+    refused(
+        store.run_maintenance(&MaintenanceConfig::default()),
+        StoreMode::ReadOnly,
+    );
+
+    assert_eq!(
+        store.get("repo", "a").unwrap().as_deref(),
+        Some(&b"alpha"[..])
+    );
+    assert_eq!(store.get("repo", "b").unwrap(), None);
+    let hits = store.search("repo", &[1.0, 0.0], 1, None).unwrap();
+    assert_eq!(hits[0].key, "a");
+
+    assert_eq!(
+        store.set_mode(StoreMode::Normal).unwrap(),
+        StoreMode::ReadOnly
+    );
+    store.upsert("repo", "b", b"beta").unwrap();
+}
+
+#[test]
+fn maintenance_mode_refuses_client_writes_but_runs_maintenance() {
+    let store = VectorStore::new();
+    store.upsert("repo", "a", b"alpha").unwrap();
+    store.delete("repo", "a").unwrap();
+
+    store.set_mode(StoreMode::Maintenance).unwrap();
+    refused(store.upsert("repo", "a", b"again"), StoreMode::Maintenance);
+    refused(store.remove_vector("repo", "a"), StoreMode::Maintenance);
+    let report = store
+        .run_maintenance(&MaintenanceConfig::default())
+        .unwrap();
+    assert_eq!(report.compaction.tombstones_removed, 1);
+    assert_eq!(store.mode(), StoreMode::Maintenance);
+}
+
+#[tokio::test]
+async fn mode_command_switches_the_store_for_admins() {
+    let store = Arc::new(VectorStore::new());
+    let mut router = HandlerRouter::new();
+    commands::register_commands(&mut router, Arc::clone(&store));
+    let admin = SessionContext::new("ops", vec![STORAGE_ADMIN_CAPABILITY.into()]);
+    let reader = SessionContext::new("reader", vec!["search".into()]);
+
+    let err = router
+        .dispatch(
+            reader,
+            RouterCommand::new(MODE_COMMAND, json!({ "mode": "read_only" })),
+        )
+        .await
+        .expect_err("admin capability required");
+    assert_eq!(err.status_code(), 401);
+    assert_eq!(store.mode(), StoreMode::Normal);
+
+    let switched = router
+        .dispatch(
+            admin.clone(),
+            RouterCommand::new(MODE_COMMAND, json!({ "mode": "read_only" })),
+        )
+        .await
+        .expect("switch mode");
+    assert_eq!(
+        switched.payload,
+        json!({ "mode": "read_only", "previous": "normal" })
+    );
+    refused(store.upsert("repo", "a", b"alpha"), StoreMode::ReadOnly);
+
+    let current = router
+        .dispatch(admin.clone(), RouterCommand::new(MODE_COMMAND, json!({})))
+        .await
+        .expect("report mode");
+    assert_eq!(current.payload, json!({ "mode": "read_only" }));
+
+    let err = router
+        .dispatch(
+            admin.clone(),
+            RouterCommand::new(MODE_COMMAND, json!({ "mode": "frozen" })),
+        )
+        .await
+        .expect_err("unknown mode");
+    assert_eq!(err.status_code(), 400);
+
+    // A failed atomic batch switches the store back.
+    let batch = router
+        .dispatch(
+            admin,
+            RouterCommand::new(
+                BATCH_COMMAND,
+                json!({ "atomic": true, "commands": [
+                    { "command": MODE_COMMAND, "payload": { "mode": "maintenance" } },
+                    { "command": MODE_COMMAND, "payload": { "mode": "frozen" } },
+                ] }),
+            ),
+        )
+        .await
+        .expect("atomic batch");
+    assert_eq!(batch.payload["committed"], json!(false));
+    assert_eq!(batch.payload["results"][0]["compensated"], json!(true));
+    assert_eq!(store.mode(), StoreMode::ReadOnly);
+}
@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+
+use storage_vector::store::{Store, VectorStore};
+use storage_vector::{QuotaLimits, StorageUsage, StoreConfig, StoreError};
+
+fn limits(bytes_max: u64, entries_max: u64) -> QuotaLimits {
+    QuotaLimits {
+        bytes_max: Some(bytes_max),
+        entries_max: Some(entries_max),
+        ..QuotaLimits::default()
+    }
+}
+
+fn quota_message(result: Result<impl std::fmt::Debug, StoreError>) -> String {
+    match result {
+        Err(StoreError::Quota(message)) => message,
+        other => panic!("expected quota error, got {other:?}"),
+    }
+}
+
+#[test]
+fn writes_past_the_quota_are_rejected() {
+    let store = VectorStore::new().with_config(StoreConfig {
+        quota: Some(limits(10, 2)),
+        ..StoreConfig::default()
+    });
+    store.upsert("repo", "a", b"aaaa").unwrap();
+    store.upsert("repo", "b", b"bbbb").unwrap();
+    let message = quota_message(store.upsert("repo", "c", b"c"));
+    assert_eq!(
+        message,
+        "repository repo would hold 9 of 10 bytes and 3 of 2 records"
+    );
+    assert_eq!(store.get("repo", "c").unwrap(), None);
+
+    // Overwrites only count the difference.
+    store.upsert("repo", "a", b"aaaaaa").unwrap();
+    quota_message(store.upsert("repo", "a", b"aaaaaaa"));
+
+    store.delete("repo", "b").unwrap();
+    store.upsert("repo", "c", b"cc").unwrap();
+    assert_eq!(
+        store.usage("repo").unwrap(),
+        StorageUsage {
+            repo_id: "repo".into(),
+            bytes: 8,
+            records: 2,
+            bytes_max: Some(10),
+            records_max: Some(2),
+        }
+    );
+
+    // A batch that does not fit lands no record at all.
+    quota_message(store.upsert_batch("repo", &[("a", &b"a"[..]), ("d", &b"d"[..])]));
+    assert_eq!(
+        store.get("repo", "a").unwrap().as_deref(),
+        Some(&b"aaaaaa"[..])
+    );
+    assert_eq!(store.usage("repo").unwrap().records, 2);
+}
+
+#[test]
+fn repo_quotas_override_the_default_and_usage_is_measured_from_disk() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = StoreConfig {
+        quota: Some(limits(4, 100)),
+        repo_quotas: HashMap::from([("big".to_string(), limits(1024, 100))]),
+        ..StoreConfig::default()
+    };
+    let store = VectorStore::with_fs_root(dir.path()).with_config(config.clone());
+    store.upsert("big", "k", b"larger than four").unwrap();
+    quota_message(store.upsert("small", "k", b"larger than four"));
+    store.upsert("small", "k", b"tiny").unwrap();
+
+    let unlimited = VectorStore::with_fs_root(dir.path());
+    unlimited.upsert("free", "k", b"anything at all").unwrap();
+
+    let reopened = VectorStore::with_fs_root(dir.path()).with_config(config);
+    let usage = reopened.usage_all().unwrap();
+    let summary: Vec<(&str, u64, Option<u64>)> = usage
+        .iter()
+        .map(|usage| (usage.repo_id.as_str(), usage.bytes, usage.bytes_max))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("big", 16, Some(1024)),
+            ("free", 15, Some(4)),
+            ("small", 4, Some(4))
+        ]
+    );
+    // The default applies to repositories first written without it.
+    quota_message(reopened.upsert("free", "other", b"x"));
+
+    let json = serde_json::to_value(&usage[2]).unwrap();
+    assert_eq!(json["records"], 1);
+    assert_eq!(json["records_max"], 100);
+}
+
+#[test]
+fn quotas_replaced_at_runtime_apply_to_later_writes() {
+    let store = VectorStore::new();
+    store.upsert("repo", "a", b"aaaa").unwrap();
+    store.upsert("repo", "b", b"bbbb").unwrap();
+
+    store
+        .set_quotas(
+            Some(limits(1024, 100)),
+            HashMap::from([("repo".to_string(), limits(10, 2))]),
+        )
+        .unwrap();
+    let message = quota_message(store.upsert("repo", "c", b"c"));
+    assert_eq!(
+        message,
+        "repository repo would hold 9 of 10 bytes and 3 of 2 records"
+    );
+    store.upsert("other", "c", b"c").unwrap();
+    assert_eq!(store.usage("repo").unwrap().records_max, Some(2));
+
+    // Lifting the quotas lets the write through.
+    store.set_quotas(None, HashMap::new()).unwrap();
+    store.upsert("repo", "c", b"c").unwrap();
+    assert_eq!(store.usage("repo").unwrap().records_max, None);
+}
@@ -19,6 +19,9 @@ fn replay_applies_in_order_and_advances_sequence() {
             payload_checksum_before: "x".into(),
             payload_checksum_after: "x".into(),
             status: "emitted".into(),
+            sealed_payload: None,
+            signature: None,
+            tenant_id: None,
         },
         ReplayEntry {
             sequence: 8,
@@ -27,6 +30,9 @@ fn replay_applies_in_order_and_advances_sequence() {
             payload_checksum_before: "y".into(),
             payload_checksum_after: "y".into(),
             status: "emitted".into(),
+            sealed_payload: None,
+            signature: None,
+            tenant_id: None,
         },
         ReplayEntry {
             sequence: 9,
@@ -35,6 +41,9 @@ fn replay_applies_in_order_and_advances_sequence() {
             payload_checksum_before: "z".into(),
             payload_checksum_after: "z".into(),
             status: "emitted".into(),
+            sealed_payload: None,
+            signature: None,
+            tenant_id: None,
         },
     ];
 
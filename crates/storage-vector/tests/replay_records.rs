@@ -0,0 +1,98 @@
+use storage_ledger::ReplayEntry;
+use storage_vector::store::{Store, VectorStore};
+use storage_vector::{ReplayRecord, StoreError, ABSENT_CHECKSUM};
+
+fn tombstone(sequence: u64, repo: &str, before: &str) -> ReplayEntry {
+    ReplayEntry {
+        sequence,
+        repo_id: repo.into(),
+        delayed_ms: 0,
+        payload_checksum_before: before.into(),
+        payload_checksum_after: ABSENT_CHECKSUM.into(),
+        status: "emitted".into(),
+        sealed_payload: None,
+        signature: None,
+        tenant_id: None,
+    }
+}
+
+fn history(repo: &str) -> Vec<ReplayRecord> {
+    let origin = VectorStore::new();
+    let a1 = origin.upsert(repo, "a", b"alpha").unwrap();
+    let b1 = origin.upsert(repo, "b", b"beta").unwrap();
+    let a2 = origin.upsert(repo, "a", b"alpha-2").unwrap();
+    assert_eq!(a2.payload_checksum_before, a1.payload_checksum_after);
+    let b_gone = tombstone(b1.sequence + 2, repo, &b1.payload_checksum_after);
+    vec![
+        ReplayRecord::put(a2, "a", b"alpha-2".to_vec()),
+        ReplayRecord::delete(b_gone, "b"),
+        ReplayRecord::put(b1, "b", b"beta".to_vec()),
+        ReplayRecord::put(a1, "a", b"alpha".to_vec()),
+    ]
+}
+
+#[test]
+fn replay_records_restores_payloads_and_tombstones_in_order() {
+    let tmpdir = tempfile::tempdir().expect("tmpdir");
+    let store = VectorStore::with_fs_root(tmpdir.path().join("vs"));
+    let repo = "repo-records";
+
+    let stats = store.replay_records(history(repo)).expect("replay");
+    // `b` is already absent, which is where its tombstone leaves it.
+    assert_eq!(stats.applied, 2);
+    assert_eq!(stats.skipped, 2);
+    assert_eq!(stats.max_sequence, Some(4));
+    assert_eq!(store.get(repo, "a").unwrap().unwrap(), b"alpha-2");
+    assert_eq!(store.get(repo, "b").unwrap(), None);
+    assert_eq!(store.upsert(repo, "c", b"gamma").unwrap().sequence, 5);
+}
+
+#[test]
+fn replaying_the_same_records_twice_is_idempotent() {
+    let store = VectorStore::new();
+    let repo = "repo-idempotent";
+    let records = history(repo);
+    store.replay_records(records.clone()).expect("first replay");
+
+    let stats = store.replay_records(records).expect("second replay");
+    assert_eq!(stats.applied, 0);
+    assert_eq!(stats.skipped, 4);
+    assert_eq!(store.get(repo, "a").unwrap().unwrap(), b"alpha-2");
+    assert_eq!(store.get(repo, "b").unwrap(), None);
+}
+
+#[test]
+fn replay_resumes_after_the_writes_the_store_already_has() {
+    let repo = "repo-resume";
+    let mut records = history(repo);
+    records.sort_by_key(|record| record.entry.sequence);
+    let store = VectorStore::new();
+    store
+        .replay_records(records[..2].to_vec())
+        .expect("partial");
+
+    let stats = store.replay_records(records).expect("full history");
+    assert_eq!((stats.applied, stats.skipped), (2, 2));
+    assert_eq!(store.get(repo, "a").unwrap().unwrap(), b"alpha-2");
+}
+
+#[test]
+fn replay_records_verifies_checksums() {
+    let repo = "repo-verify";
+    let mut records = history(repo);
+    records.sort_by_key(|record| record.entry.sequence);
+
+    let tampered = ReplayRecord::put(records[0].entry.clone(), "a", b"forged".to_vec());
+    let err = VectorStore::new()
+        .replay_records(vec![tampered])
+        .expect_err("payload does not match the after checksum");
+    assert!(matches!(err, StoreError::Integrity(_)));
+
+    let store = VectorStore::new();
+    store.upsert(repo, "a", b"diverged").unwrap();
+    let err = store
+        .replay_records(vec![records[0].clone()])
+        .expect_err("current value does not match the before checksum");
+    assert!(err.to_string().contains("expects"));
+    assert_eq!(store.get(repo, "a").unwrap().unwrap(), b"diverged");
+}
@@ -0,0 +1,70 @@
+use storage_ledger::{ReplayEntry, ReplayMode, SequenceGap};
+use storage_vector::store::{Store, VectorStore};
+use storage_vector::StoreError;
+
+fn entry(repo: &str, sequence: u64) -> ReplayEntry {
+    ReplayEntry {
+        sequence,
+        repo_id: repo.into(),
+        delayed_ms: 0,
+        payload_checksum_before: "x".into(),
+        payload_checksum_after: "x".into(),
+        status: "emitted".into(),
+        sealed_payload: None,
+        signature: None,
+        tenant_id: None,
+    }
+}
+
+#[test]
+fn strict_replay_reports_gaps_per_repository() {
+    let store = VectorStore::new().with_replay_mode(ReplayMode::Strict);
+    let written = store.upsert("repo-a", "k1", b"a").unwrap();
+    assert_eq!(store.max_sequence("repo-a"), Some(written.sequence));
+
+    let stats = store
+        .replay(vec![
+            entry("repo-a", 5),
+            entry("repo-b", 3),
+            entry("repo-a", 2),
+            entry("repo-b", 4),
+        ])
+        .expect("strict replay");
+    assert_eq!(stats.applied, 4);
+    assert_eq!(stats.max_sequence, Some(5));
+    assert_eq!(
+        stats.gaps,
+        vec![SequenceGap {
+            repo_id: "repo-a".into(),
+            first_missing: 3,
+            last_missing: 4,
+        }]
+    );
+    assert_eq!(store.max_sequence("repo-a"), Some(5));
+    assert_eq!(store.max_sequence("repo-b"), Some(4));
+}
+
+#[test]
+fn strict_replay_rejects_duplicates_without_applying_the_batch() {
+    let store = VectorStore::new().with_replay_mode(ReplayMode::Strict);
+    store
+        .replay(vec![entry("repo-a", 1)])
+        .expect("first replay");
+
+    let err = store
+        .replay(vec![entry("repo-a", 2), entry("repo-a", 2)])
+        .expect_err("duplicate within batch");
+    assert!(matches!(err, StoreError::Ledger(_)));
+    assert!(err.to_string().contains("duplicate"));
+    store
+        .replay(vec![entry("repo-a", 1)])
+        .expect_err("already replayed");
+    assert_eq!(store.max_sequence("repo-a"), Some(1));
+
+    // Lenient stores keep accepting repeats.
+    let lenient = VectorStore::new();
+    lenient.replay(vec![entry("repo-a", 1)]).unwrap();
+    let stats = lenient.replay(vec![entry("repo-a", 1)]).unwrap();
+    assert_eq!(stats.applied, 1);
+    assert!(stats.gaps.is_empty());
+}
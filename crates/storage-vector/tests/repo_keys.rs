@@ -0,0 +1,88 @@
+#![cfg(feature = "encryption")]
+
+use std::sync::Arc;
+use storage_vector::config::RotationPolicy;
+use storage_vector::encryption::aes_gcm::AesGcmEncrypter;
+use storage_vector::encryption::peek_key_id;
+use storage_vector::kms::{InMemoryKeyManager, KeyManager, KeyScope, RepoKeyManager};
+use storage_vector::store::fs as vs_fs;
+use storage_vector::store::{Store, VectorStore};
+use storage_vector::{StoreConfig, StoreError};
+use tempfile::tempdir;
+
+fn store_with(
+    root: &std::path::Path,
+    kms: Arc<dyn KeyManager + Send + Sync>,
+    config: StoreConfig,
+) -> VectorStore {
+    VectorStore::builder()
+        .with_fs_root(root)
+        .with_encrypter(Arc::new(AesGcmEncrypter::new()))
+        .with_key_manager(kms)
+        .with_config(config)
+        .build()
+}
+
+fn master() -> InMemoryKeyManager {
+    InMemoryKeyManager::new_with_secret("master", [3u8; 32])
+}
+
+#[test]
+fn repositories_get_independent_keys_and_revocation_shreds_only_one() {
+    let tmp = tempdir().unwrap();
+    let root = tmp.path().join("vs");
+    let keys = tmp.path().join("keys");
+    let kms = Arc::new(RepoKeyManager::open(master(), &keys).unwrap());
+    let store = store_with(&root, kms.clone(), StoreConfig::default());
+
+    store.upsert("alpha", "a", b"one").unwrap();
+    store.upsert("beta", "b", b"two").unwrap();
+    let key_of =
+        |repo: &str, key: &str| peek_key_id(&vs_fs::read_bytes(&root, repo, key).unwrap()).unwrap();
+    assert_ne!(key_of("alpha", "a"), key_of("beta", "b"));
+
+    assert_eq!(kms.revoke_repo("alpha").unwrap(), 1);
+    assert!(matches!(store.get("alpha", "a"), Err(StoreError::Key(_))));
+    assert_eq!(store.get("beta", "b").unwrap().unwrap(), b"two");
+
+    // Keys reload from the directory; the revoked one stays gone.
+    let reopened = Arc::new(RepoKeyManager::open(master(), &keys).unwrap());
+    let store = store_with(&root, reopened, StoreConfig::default());
+    assert!(matches!(store.get("alpha", "a"), Err(StoreError::Key(_))));
+    assert_eq!(store.get("beta", "b").unwrap().unwrap(), b"two");
+    store.upsert("alpha", "c", b"fresh").unwrap();
+    assert_eq!(store.get("alpha", "c").unwrap().unwrap(), b"fresh");
+}
+
+#[test]
+fn rotation_policy_applies_per_repository() {
+    let tmp = tempdir().unwrap();
+    let kms = Arc::new(RepoKeyManager::open(master(), tmp.path().join("keys")).unwrap());
+    let config = StoreConfig {
+        rotation: RotationPolicy {
+            max_uses: Some(2),
+            rotate_after_seconds: None,
+        },
+        ..StoreConfig::default()
+    };
+    let store = store_with(&tmp.path().join("vs"), kms.clone(), config);
+    let current = |repo: &str| {
+        kms.current(&KeyScope {
+            repo_id: repo.into(),
+        })
+        .unwrap()
+        .key_id
+    };
+
+    store.upsert("busy", "1", b"x").unwrap();
+    store.upsert("quiet", "1", b"x").unwrap();
+    let (busy, quiet) = (current("busy"), current("quiet"));
+    store.upsert("busy", "2", b"x").unwrap();
+    store.upsert("busy", "3", b"x").unwrap();
+
+    assert_ne!(current("busy"), busy);
+    assert_eq!(current("quiet"), quiet);
+    for key in ["1", "2", "3"] {
+        assert_eq!(store.get("busy", key).unwrap().unwrap(), b"x");
+    }
+}
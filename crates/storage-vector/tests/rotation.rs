@@ -1,9 +1,20 @@
 #![cfg(feature = "encryption")]
 
+use std::path::Path;
 use std::sync::Arc;
+use storage_vector::config::RotationPolicy;
 use storage_vector::encryption::aes_gcm::AesGcmEncrypter;
-use storage_vector::kms::InMemoryKeyManager;
+use storage_vector::encryption::peek_key_id;
+use storage_vector::kms::{InMemoryKeyManager, KeyManager};
+use storage_vector::store::fs as vs_fs;
 use storage_vector::store::{Store, VectorStore};
+use storage_vector::StoreConfig;
+use tempfile::tempdir;
+
+fn stored_key_id(root: &Path, repo: &str, key: &str) -> String {
+    let bytes = vs_fs::read_bytes(root, repo, key).expect("stored envelope");
+    peek_key_id(&bytes).expect("envelope key id")
+}
 
 #[test]
 fn rotates_keys_and_reads_old_records() {
@@ -30,3 +41,72 @@ fn rotates_keys_and_reads_old_records() {
     assert_eq!(store.get(repo, "a").unwrap().unwrap(), p1);
     assert_eq!(store.get(repo, "b").unwrap().unwrap(), p2);
 }
+
+#[test]
+fn rotates_once_a_key_reaches_its_use_limit() {
+    let tmp = tempdir().unwrap();
+    let root = tmp.path().join("vs");
+    let kms = Arc::new(InMemoryKeyManager::new_with_secret("k1", [1u8; 32]));
+    let store = VectorStore::builder()
+        .with_fs_root(&root)
+        .with_encrypter(Arc::new(AesGcmEncrypter::new()))
+        .with_key_manager(kms.clone())
+        .with_config(StoreConfig {
+            rotation: RotationPolicy {
+                max_uses: Some(2),
+                rotate_after_seconds: None,
+            },
+            ..StoreConfig::default()
+        })
+        .build();
+
+    let repo = "repo-uses";
+    for key in ["a", "b", "c"] {
+        store.upsert(repo, key, key.as_bytes()).expect("upsert");
+    }
+
+    assert_eq!(stored_key_id(&root, repo, "a"), "k1");
+    assert_eq!(stored_key_id(&root, repo, "b"), "k1");
+    let rotated = stored_key_id(&root, repo, "c");
+    assert_ne!(rotated, "k1");
+    assert_eq!(kms.usage("k1").expect("k1 usage").uses, 2);
+    assert_eq!(kms.usage(&rotated).expect("new key usage").uses, 1);
+    for key in ["a", "b", "c"] {
+        assert_eq!(store.get(repo, key).unwrap().unwrap(), key.as_bytes());
+    }
+}
+
+#[test]
+fn reencrypt_job_rewrites_old_envelopes_under_the_current_key() {
+    let tmp = tempdir().unwrap();
+    let root = tmp.path().join("vs");
+    let kms = Arc::new(InMemoryKeyManager::new_with_secret("k1", [1u8; 32]));
+    let store = Arc::new(
+        VectorStore::builder()
+            .with_fs_root(&root)
+            .with_encrypter(Arc::new(AesGcmEncrypter::new()))
+            .with_key_manager(kms.clone())
+            .build(),
+    );
+
+    let repo = "repo-reencrypt";
+    store.upsert(repo, "a", b"alpha").unwrap();
+    store.upsert(repo, "b", b"beta").unwrap();
+    kms.set_current("k2", [2u8; 32]);
+    store.upsert(repo, "c", b"gamma").unwrap();
+
+    let job = store.reencrypt_repo(repo);
+    let done = job.join().expect("re-encryption");
+    assert_eq!((done.total, done.checked, done.rewritten), (3, 3, 2));
+    for (key, payload) in [("a", b"alpha".as_slice()), ("b", b"beta"), ("c", b"gamma")] {
+        assert_eq!(stored_key_id(&root, repo, key), "k2");
+        assert_eq!(store.get(repo, key).unwrap().unwrap(), payload);
+    }
+
+    let mut seen = Vec::new();
+    let again = store
+        .reencrypt(repo, |progress| seen.push(progress.checked))
+        .unwrap();
+    assert_eq!(again.rewritten, 0);
+    assert_eq!(seen, vec![1, 2, 3]);
+}
@@ -0,0 +1,127 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use storage_vector::store::Store;
+use storage_vector::{SegmentConfig, SegmentStore, ABSENT_CHECKSUM, TOMBSTONE_STATUS};
+
+fn small_segments() -> SegmentConfig {
+    SegmentConfig {
+        max_segment_bytes: 512,
+        ..SegmentConfig::default()
+    }
+}
+
+fn segment_files(dir: &std::path::Path) -> Vec<std::path::PathBuf> {
+    let mut files: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn records_survive_reopen() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = SegmentStore::open(dir.path(), SegmentConfig::default()).unwrap();
+    let first = store.upsert("repo", "a", b"one").unwrap();
+    let second = store.upsert("repo", "a", b"two").unwrap();
+    assert_eq!(second.payload_checksum_before, first.payload_checksum_after);
+    store.upsert("repo", "b", b"bee").unwrap();
+    let tombstone = store.delete("repo", "b").unwrap();
+    assert_eq!(tombstone.status, TOMBSTONE_STATUS);
+    assert_eq!(tombstone.payload_checksum_after, ABSENT_CHECKSUM);
+    drop(store);
+
+    let reopened = SegmentStore::open(dir.path(), SegmentConfig::default()).unwrap();
+    assert_eq!(reopened.get("repo", "a").unwrap(), Some(b"two".to_vec()));
+    assert_eq!(reopened.get("repo", "b").unwrap(), None);
+    assert_eq!(
+        reopened.list_keys("repo", "", None, 10).unwrap().keys,
+        vec!["a"]
+    );
+    assert_eq!(reopened.upsert("repo", "c", b"see").unwrap().sequence, 5);
+}
+
+#[test]
+fn torn_tail_is_truncated_on_open() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = SegmentStore::open(dir.path(), SegmentConfig::default()).unwrap();
+    store.upsert("repo", "a", b"kept").unwrap();
+    store.upsert("repo", "b", b"torn").unwrap();
+    drop(store);
+
+    let segment = segment_files(dir.path()).pop().unwrap();
+    let len = std::fs::metadata(&segment).unwrap().len();
+    let file = std::fs::OpenOptions::new()
+        .write(true)
+        .open(&segment)
+        .unwrap();
+    file.set_len(len - 3).unwrap();
+
+    let reopened = SegmentStore::open(dir.path(), SegmentConfig::default()).unwrap();
+    assert_eq!(reopened.get("repo", "a").unwrap(), Some(b"kept".to_vec()));
+    assert_eq!(reopened.get("repo", "b").unwrap(), None);
+    reopened.upsert("repo", "c", b"after").unwrap();
+    drop(reopened);
+    let again = SegmentStore::open(dir.path(), SegmentConfig::default()).unwrap();
+    assert_eq!(again.get("repo", "c").unwrap(), Some(b"after".to_vec()));
+}
+
+#[test]
+fn compaction_reclaims_sparse_segments_without_resurrecting_deletes() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = SegmentStore::open(dir.path(), small_segments()).unwrap();
+    for round in 0..4 {
+        for key in ["a", "b", "c", "d"] {
+            store
+                .upsert(
+                    "repo",
+                    key,
+                    format!("{key}-{round}-{}", "x".repeat(60)).as_bytes(),
+                )
+                .unwrap();
+        }
+    }
+    store.delete("repo", "d").unwrap();
+    let before = store.segment_count();
+    assert!(before > 2, "{before} segments");
+
+    let report = store.compact().unwrap();
+    assert!(report.segments_merged > 0);
+    assert!(report.reclaimed_bytes > 0);
+    assert!(store.segment_count() < before);
+    drop(store);
+
+    let reopened = SegmentStore::open(dir.path(), small_segments()).unwrap();
+    for key in ["a", "b", "c"] {
+        let value = reopened.get("repo", key).unwrap().unwrap();
+        assert!(value.starts_with(format!("{key}-3-").as_bytes()));
+    }
+    assert_eq!(reopened.get("repo", "d").unwrap(), None);
+    assert_eq!(reopened.list_repos().unwrap(), vec!["repo"]);
+}
+
+#[test]
+fn background_compactor_runs_until_dropped() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = Arc::new(SegmentStore::open(dir.path(), small_segments()).unwrap());
+    for round in 0..6 {
+        store
+            .upsert(
+                "repo",
+                "hot",
+                format!("{round}{}", "y".repeat(200)).as_bytes(),
+            )
+            .unwrap();
+    }
+    let before = store.segment_count();
+    let compactor = store.spawn_compactor(Duration::from_millis(10));
+    let deadline = std::time::Instant::now() + Duration::from_secs(5);
+    while store.segment_count() >= before && std::time::Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    drop(compactor);
+    assert!(store.segment_count() < before);
+    assert!(store.get("repo", "hot").unwrap().unwrap().starts_with(b"5"));
+}
@@ -0,0 +1,116 @@
+use storage_vector::store::{Store, VectorStore};
+use storage_vector::{SnapshotReport, StoreError};
+
+fn seeded(store: &VectorStore) {
+    store
+        .upsert_batch("repo-a", &[("a/1", &b"one"[..]), ("a/2", &b"two"[..])])
+        .unwrap();
+    store.upsert("repo-b", "b 1", b"three").unwrap();
+    store.upsert("repo-b", "gone", b"deleted").unwrap();
+    store.delete("repo-b", "gone").unwrap();
+}
+
+fn assert_restored(store: &VectorStore) {
+    assert_eq!(
+        store.get("repo-a", "a/1").unwrap().as_deref(),
+        Some(&b"one"[..])
+    );
+    assert_eq!(
+        store.get("repo-b", "b 1").unwrap().as_deref(),
+        Some(&b"three"[..])
+    );
+    assert_eq!(store.get("repo-b", "gone").unwrap(), None);
+    assert_eq!(store.max_sequence("repo-a"), Some(2));
+    assert_eq!(store.max_sequence("repo-b"), Some(5));
+    assert_eq!(store.upsert("repo-c", "k", b"next").unwrap().sequence, 6);
+}
+
+#[test]
+fn snapshots_round_trip_records_and_sequences() {
+    let data = tempfile::tempdir().unwrap();
+    let backup = tempfile::tempdir().unwrap();
+    let snapshot = backup.path().join("snap");
+    for source in [VectorStore::new(), VectorStore::with_fs_root(data.path())] {
+        seeded(&source);
+        let report = source.create_snapshot(&snapshot).unwrap();
+        assert_eq!(
+            report,
+            SnapshotReport {
+                records: 3,
+                bytes: 11
+            }
+        );
+        assert!(snapshot.join("manifest.json").exists());
+        assert!(snapshot
+            .join("records")
+            .join("repo-a")
+            .join("a%2F1")
+            .exists());
+
+        let restored_dir = tempfile::tempdir().unwrap();
+        for restored in [
+            VectorStore::new(),
+            VectorStore::with_fs_root(restored_dir.path()),
+        ] {
+            assert_eq!(restored.restore_snapshot(&snapshot).unwrap(), report);
+            assert_restored(&restored);
+        }
+        std::fs::remove_dir_all(&snapshot).unwrap();
+    }
+}
+
+#[test]
+fn snapshots_refuse_a_used_directory_and_corrupt_records() {
+    let store = VectorStore::new();
+    seeded(&store);
+    let backup = tempfile::tempdir().unwrap();
+    std::fs::write(backup.path().join("stray"), b"x").unwrap();
+    assert!(matches!(
+        store.create_snapshot(backup.path()),
+        Err(StoreError::Io(_))
+    ));
+
+    let snapshot = backup.path().join("snap");
+    store.create_snapshot(&snapshot).unwrap();
+    std::fs::write(
+        snapshot.join("records").join("repo-b").join("b%201"),
+        b"THREE",
+    )
+    .unwrap();
+    let target = VectorStore::new();
+    assert!(matches!(
+        target.restore_snapshot(&snapshot),
+        Err(StoreError::Integrity(_))
+    ));
+    // Nothing lands when any record fails verification.
+    assert_eq!(target.get("repo-a", "a/1").unwrap(), None);
+
+    let manifest = snapshot.join("manifest.json");
+    let text = std::fs::read_to_string(&manifest).unwrap();
+    std::fs::write(&manifest, text.replace("\"len\": 5", "\"len\": 6")).unwrap();
+    assert!(matches!(
+        target.restore_snapshot(&snapshot),
+        Err(StoreError::Integrity(_))
+    ));
+}
+
+#[cfg(feature = "snapshot-archive")]
+#[test]
+fn archives_hold_the_same_snapshot() {
+    let store = VectorStore::new();
+    seeded(&store);
+    let backup = tempfile::tempdir().unwrap();
+    let first = backup.path().join("first.tar.zst");
+    let second = backup.path().join("second.tar.zst");
+    let report = store.create_snapshot_archive(&first).unwrap();
+    store.create_snapshot_archive(&second).unwrap();
+    // Fixed headers keep archives of the same store byte-identical.
+    assert_eq!(
+        std::fs::read(&first).unwrap(),
+        std::fs::read(&second).unwrap()
+    );
+
+    let restored = VectorStore::new();
+    assert_eq!(restored.restore_snapshot_archive(&first).unwrap(), report);
+    assert_restored(&restored);
+}
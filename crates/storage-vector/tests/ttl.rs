@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use storage_ledger::{Ledger, LedgerConfig};
+use storage_vector::store::{Store, VectorStore};
+use storage_vector::{MaintenanceConfig, StoreConfig, ABSENT_CHECKSUM, EXPIRED_STATUS};
+
+const HOUR: Duration = Duration::from_secs(3600);
+
+fn ttl_config() -> StoreConfig {
+    StoreConfig {
+        default_ttl: Some(HOUR),
+        repo_ttls: HashMap::from([("ci-checkout".to_string(), Duration::ZERO)]),
+        ..StoreConfig::default()
+    }
+}
+
+#[test]
+fn expired_records_read_as_missing_and_are_swept_into_the_ledger() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("vs");
+    let ledger =
+        Arc::new(Ledger::open(dir.path().join("ledger"), LedgerConfig::default()).unwrap());
+    let store = VectorStore::with_fs_root(&root)
+        .with_config(ttl_config())
+        .with_expiry_ledger(ledger.clone());
+
+    let written = store.upsert("ci-checkout", "a", b"ephemeral").unwrap();
+    store
+        .upsert_with_ttl("ci-checkout", "pinned", b"kept", HOUR)
+        .unwrap();
+    store.upsert("main", "b", b"durable").unwrap();
+    assert_eq!(store.get("ci-checkout", "a").unwrap(), None);
+    let page = store.list_keys("ci-checkout", "", None, 10).unwrap();
+    assert_eq!(page.keys, vec!["pinned"]);
+    assert!(store.expires_at("main", "b").unwrap().is_some());
+
+    let expired = store.expire().unwrap();
+    assert_eq!(expired.len(), 1);
+    assert_eq!(expired[0].repo_id, "ci-checkout");
+    assert_eq!(expired[0].status, EXPIRED_STATUS);
+    assert_eq!(
+        expired[0].payload_checksum_before,
+        written.payload_checksum_after
+    );
+    assert_eq!(expired[0].payload_checksum_after, ABSENT_CHECKSUM);
+    let logged: Vec<_> = ledger.iter_from(0).map(Result::unwrap).collect();
+    assert_eq!(logged, expired);
+    assert!(store.expire().unwrap().is_empty());
+
+    // Deadlines survive a restart; a write without a TTL clears one.
+    drop(store);
+    let store = VectorStore::with_fs_root(&root);
+    assert!(store.expires_at("ci-checkout", "pinned").unwrap().is_some());
+    assert_eq!(
+        store.get("ci-checkout", "pinned").unwrap().unwrap(),
+        b"kept"
+    );
+    store.upsert("main", "b", b"now permanent").unwrap();
+    assert_eq!(store.expires_at("main", "b").unwrap(), None);
+    assert_eq!(
+        VectorStore::with_fs_root(&root)
+            .expires_at("main", "b")
+            .unwrap(),
+        None
+    );
+}
+
+#[test]
+fn maintenance_passes_expire_records() {
+    let store = VectorStore::new().with_config(ttl_config());
+    store
+        .upsert_batch("ci-checkout", &[("a", b"1"), ("b", b"2")])
+        .unwrap();
+    store.upsert("main", "c", b"3").unwrap();
+    store.delete("ci-checkout", "b").unwrap();
+
+    let report = store
+        .run_maintenance(&MaintenanceConfig::default())
+        .unwrap();
+    assert_eq!(report.records_expired, 1);
+    assert_eq!(report.compaction.tombstones_removed, 2);
+    assert_eq!(store.get("main", "c").unwrap().unwrap(), b"3");
+}
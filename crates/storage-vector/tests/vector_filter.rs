@@ -0,0 +1,138 @@
+use storage_vector::store::VectorStore;
+use storage_vector::{Field, FilterExpr, SearchFilter, SearchHit, VectorMetadata};
+
+fn seeded() -> VectorStore {
+    let store = VectorStore::new();
+    let chunks = [
+        ("lib", "src/lib.rs", "rust", 100, &["public"][..]),
+        ("main", "src/bin/main.rs", "rust", 200, &["binary"][..]),
+        (
+            "util",
+            "scripts/util.py",
+            "python",
+            300,
+            &["tooling", "public"][..],
+        ),
+        ("guide", "docs/guide.md", "markdown", 400, &[][..]),
+    ];
+    for (i, (key, path, language, modified_at, tags)) in chunks.into_iter().enumerate() {
+        let metadata = tags.iter().fold(
+            VectorMetadata::new(format!("repo-a::{path}::0"))
+                .with_repo_id("repo-a")
+                .with_path(path)
+                .with_language(language)
+                .with_chunk_hash(format!("hash-{key}"))
+                .with_modified_at(modified_at)
+                .with_attribute("lines", (i * 10).to_string()),
+            |metadata, tag| metadata.with_tag(*tag),
+        );
+        store
+            .insert_vector("repo-a", key, vec![1.0, i as f32], metadata)
+            .unwrap();
+    }
+    store
+}
+
+fn matching(store: &VectorStore, expr: FilterExpr) -> Vec<String> {
+    let mut keys: Vec<String> = store
+        .search("repo-a", &[1.0, 0.0], 10, Some(&SearchFilter::expr(expr)))
+        .unwrap()
+        .into_iter()
+        .map(|hit: SearchHit| hit.key)
+        .collect();
+    keys.sort();
+    keys
+}
+
+#[test]
+fn repo_and_path_prefix_scope_results() {
+    let store = seeded();
+    assert_eq!(
+        matching(&store, FilterExpr::repo_path("repo-a", "src/")),
+        vec!["lib", "main"]
+    );
+    assert!(matching(&store, FilterExpr::repo_path("repo-b", "src/")).is_empty());
+    assert_eq!(
+        matching(&store, FilterExpr::eq(Field::ChunkHash, "hash-guide")),
+        vec!["guide"]
+    );
+}
+
+#[test]
+fn ranges_tags_and_combinators() {
+    let store = seeded();
+    assert_eq!(
+        matching(
+            &store,
+            FilterExpr::range(Field::ModifiedAt, Some(150), Some(300))
+        ),
+        vec!["main", "util"]
+    );
+    // Numeric attributes can be ranged too; a missing field never matches.
+    assert_eq!(
+        matching(
+            &store,
+            FilterExpr::range(Field::Attribute("lines".into()), Some(20), None)
+        ),
+        vec!["guide", "util"]
+    );
+    assert!(matching(&store, FilterExpr::range(Field::EmbeddedAt, None, None)).is_empty());
+
+    assert_eq!(
+        matching(
+            &store,
+            FilterExpr::AllTags {
+                tags: vec!["public".into(), "tooling".into()]
+            }
+        ),
+        vec!["util"]
+    );
+    assert_eq!(
+        matching(
+            &store,
+            FilterExpr::AnyTag {
+                tags: vec!["binary".into(), "tooling".into()]
+            }
+        ),
+        vec!["main", "util"]
+    );
+    assert_eq!(
+        matching(
+            &store,
+            FilterExpr::and(vec![
+                FilterExpr::eq(Field::Language, "rust"),
+                FilterExpr::negate(FilterExpr::prefix(Field::Path, "src/bin/")),
+            ])
+        ),
+        vec!["lib"]
+    );
+    assert_eq!(
+        matching(
+            &store,
+            FilterExpr::or(vec![
+                FilterExpr::eq(Field::Language, "markdown"),
+                FilterExpr::eq(Field::Language, "python"),
+            ])
+        ),
+        vec!["guide", "util"]
+    );
+}
+
+#[test]
+fn expressions_round_trip_through_json() {
+    let expr = FilterExpr::and(vec![
+        FilterExpr::repo_path("repo-a", "src/"),
+        FilterExpr::range(Field::ModifiedAt, Some(1), None),
+    ]);
+    let json = serde_json::to_string(&expr).unwrap();
+    assert!(json.contains(r#""op":"and""#));
+    assert_eq!(serde_json::from_str::<FilterExpr>(&json).unwrap(), expr);
+
+    let parsed: FilterExpr =
+        serde_json::from_str(r#"{"op":"eq","field":{"attribute":"owner"},"value":"search-team"}"#)
+            .unwrap();
+    assert_eq!(
+        parsed,
+        FilterExpr::eq(Field::Attribute("owner".into()), "search-team")
+    );
+}
@@ -0,0 +1,95 @@
+use storage_vector::store::VectorStore;
+use storage_vector::{Metric, SearchFilter, StoreError, VectorMetadata};
+
+fn seeded(metric: Metric) -> VectorStore {
+    let store = VectorStore::new().with_metric(metric);
+    let vectors = [
+        ("a", vec![1.0, 0.0], "repo::src/a.rs::0", "rust"),
+        ("b", vec![0.0, 1.0], "repo::src/b.py::0", "python"),
+        ("c", vec![3.0, 3.0], "repo::docs/c.md::0", "markdown"),
+        ("d", vec![0.9, 0.1], "repo::src/d.rs::0", "rust"),
+    ];
+    for (key, vector, plan_id, language) in vectors {
+        store
+            .insert_vector(
+                "repo",
+                key,
+                vector,
+                VectorMetadata::new(plan_id).with_attribute("language", language),
+            )
+            .unwrap();
+    }
+    store
+}
+
+fn keys(hits: &[storage_vector::SearchHit]) -> Vec<&str> {
+    hits.iter().map(|hit| hit.key.as_str()).collect()
+}
+
+#[test]
+fn metrics_rank_neighbours_differently() {
+    let query = [1.0, 0.0];
+    let cosine = seeded(Metric::Cosine)
+        .search("repo", &query, 3, None)
+        .unwrap();
+    assert_eq!(keys(&cosine), vec!["a", "d", "c"]);
+    assert!((cosine[0].score - 1.0).abs() < 1e-6);
+    assert_eq!(cosine[0].metadata.plan_id, "repo::src/a.rs::0");
+
+    let dot = seeded(Metric::Dot).search("repo", &query, 2, None).unwrap();
+    assert_eq!(keys(&dot), vec!["c", "a"]);
+
+    let euclidean = seeded(Metric::Euclidean)
+        .search("repo", &query, 2, None)
+        .unwrap();
+    assert_eq!(keys(&euclidean), vec!["a", "d"]);
+    assert!(euclidean[1].score < 0.0);
+}
+
+#[test]
+fn filters_scope_hits_by_plan_and_attributes() {
+    let store = seeded(Metric::Cosine);
+    let filter = SearchFilter {
+        plan_id_prefix: Some("repo::src/".into()),
+        ..SearchFilter::default()
+    };
+    let hits = store
+        .search("repo", &[0.0, 1.0], 10, Some(&filter))
+        .unwrap();
+    assert_eq!(keys(&hits), vec!["b", "d", "a"]);
+
+    let rust_only = SearchFilter {
+        attributes: [("language".to_string(), "rust".to_string())].into(),
+        ..filter
+    };
+    let hits = store
+        .search("repo", &[0.0, 1.0], 10, Some(&rust_only))
+        .unwrap();
+    assert_eq!(keys(&hits), vec!["d", "a"]);
+}
+
+#[test]
+fn vectors_are_replaced_removed_and_validated() {
+    let store = seeded(Metric::Cosine);
+    store
+        .insert_vector("repo", "b", vec![1.0, 0.0], VectorMetadata::new("p"))
+        .unwrap();
+    let hits = store.search("repo", &[1.0, 0.0], 2, None).unwrap();
+    assert_eq!(keys(&hits), vec!["a", "b"]);
+
+    assert!(store.remove_vector("repo", "a").unwrap());
+    assert!(!store.remove_vector("repo", "a").unwrap());
+    assert!(store.search("other", &[1.0], 5, None).unwrap().is_empty());
+
+    let err = store
+        .insert_vector("repo", "e", vec![1.0, 2.0, 3.0], VectorMetadata::new("p"))
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        StoreError::DimensionMismatch {
+            expected: 2,
+            found: 3
+        }
+    ));
+    assert!(store.search("repo", &[f32::NAN, 0.0], 1, None).is_err());
+}
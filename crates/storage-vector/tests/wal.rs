@@ -0,0 +1,119 @@
+use std::path::Path;
+use std::time::Duration;
+
+use storage_vector::store::{Store, VectorStore};
+use storage_vector::{FsyncPolicy, StoreError, ABSENT_CHECKSUM};
+
+const RECORDS: [(&str, &[u8]); 3] = [("a", b"one"), ("b", b"two"), ("c", b"three")];
+
+/// Log a batch that cannot be applied: a directory squats on the staging
+/// file of `b`, as if the process died between logging and renaming.
+fn interrupted_batch(root: &Path) -> VectorStore {
+    let store = VectorStore::with_fs_root(root)
+        .with_wal(FsyncPolicy::Always)
+        .unwrap();
+    let blocker = root.join("repo").join("%tmp-b");
+    std::fs::create_dir_all(&blocker).unwrap();
+    assert!(matches!(
+        store.upsert_batch("repo", &RECORDS),
+        Err(StoreError::Io(_))
+    ));
+    std::fs::remove_dir(blocker).unwrap();
+    store
+}
+
+#[test]
+fn batches_apply_through_the_log_and_leave_it_empty() {
+    let dir = tempfile::tempdir().unwrap();
+    for policy in [
+        FsyncPolicy::Always,
+        FsyncPolicy::Interval(Duration::from_millis(50)),
+        FsyncPolicy::Never,
+    ] {
+        let store = VectorStore::with_fs_root(dir.path())
+            .with_wal(policy)
+            .unwrap();
+        let entries = store.upsert_batch("repo", &RECORDS).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(
+            store.get("repo", "c").unwrap().as_deref(),
+            Some(&b"three"[..])
+        );
+        assert_eq!(std::fs::metadata(dir.path().join("%wal")).unwrap().len(), 0);
+        assert!(store.take_recovered().is_empty());
+    }
+    assert!(matches!(
+        VectorStore::new().with_wal(FsyncPolicy::Always),
+        Err(StoreError::Unsupported(_))
+    ));
+}
+
+#[test]
+fn reopening_redoes_an_interrupted_batch_whole() {
+    let dir = tempfile::tempdir().unwrap();
+    drop(interrupted_batch(dir.path()));
+    assert!(std::fs::metadata(dir.path().join("%wal")).unwrap().len() > 0);
+
+    let store = VectorStore::with_fs_root(dir.path())
+        .with_wal(FsyncPolicy::Always)
+        .unwrap();
+    for (key, payload) in RECORDS {
+        assert_eq!(store.get("repo", key).unwrap().as_deref(), Some(payload));
+    }
+    let recovered = store.take_recovered();
+    let sequences: Vec<u64> = recovered.iter().map(|entry| entry.sequence).collect();
+    assert_eq!(sequences, vec![1, 2, 3]);
+    assert_eq!(recovered[0].payload_checksum_before, ABSENT_CHECKSUM);
+    assert!(store.take_recovered().is_empty());
+    assert_eq!(store.max_sequence("repo"), Some(3));
+    assert_eq!(store.upsert("repo", "d", b"four").unwrap().sequence, 4);
+    assert_eq!(std::fs::metadata(dir.path().join("%wal")).unwrap().len(), 0);
+}
+
+#[test]
+fn the_next_write_redoes_a_batch_left_by_a_failed_write() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = interrupted_batch(dir.path());
+    assert_eq!(store.get("repo", "a").unwrap(), None);
+
+    store.upsert("repo", "a", b"newer").unwrap();
+    assert_eq!(
+        store.get("repo", "a").unwrap().as_deref(),
+        Some(&b"newer"[..])
+    );
+    assert_eq!(
+        store.get("repo", "b").unwrap().as_deref(),
+        Some(&b"two"[..])
+    );
+    assert_eq!(store.take_recovered().len(), 3);
+}
+
+#[test]
+fn a_torn_log_record_is_rolled_back() {
+    let dir = tempfile::tempdir().unwrap();
+    drop(interrupted_batch(dir.path()));
+    let log = dir.path().join("%wal");
+    let bytes = std::fs::read(&log).unwrap();
+
+    // Cut short: the batch never finished logging, so none of it applies.
+    let torn = tempfile::tempdir().unwrap();
+    std::fs::write(torn.path().join("%wal"), &bytes[..bytes.len() - 1]).unwrap();
+    let store = VectorStore::with_fs_root(torn.path())
+        .with_wal(FsyncPolicy::Always)
+        .unwrap();
+    assert!(store.take_recovered().is_empty());
+    assert_eq!(store.get("repo", "a").unwrap(), None);
+    assert_eq!(
+        std::fs::metadata(torn.path().join("%wal")).unwrap().len(),
+        0
+    );
+
+    // A complete record followed by garbage keeps the complete one.
+    let mut trailing = bytes.clone();
+    trailing.extend_from_slice(&[0xFF; 20]);
+    std::fs::write(&log, trailing).unwrap();
+    let store = VectorStore::with_fs_root(dir.path())
+        .with_wal(FsyncPolicy::Never)
+        .unwrap();
+    assert_eq!(store.take_recovered().len(), 3);
+}
@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use runtime_transport_stdio::test_support::{codec, verify_token, TEST_SECRET};
+use runtime_transport_stdio::StdioFrame;
+
+fuzz_target!(|data: &[u8]| {
+    let frame = StdioFrame {
+        payload: data.to_vec(),
+    };
+    if let Ok((_, token)) = codec(64 * 1024, TEST_SECRET).decode(&frame) {
+        assert!(verify_token(TEST_SECRET, &token).is_ok());
+    }
+});
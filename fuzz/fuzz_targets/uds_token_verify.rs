@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use runtime_transport_uds::test_support::{verify_token, TEST_SECRET};
+
+fuzz_target!(|token: &str| {
+    assert!(verify_token(TEST_SECRET, token).is_err());
+});
@@ -6,14 +6,23 @@
 //! artifacts are synthetic but mimic the structure expected by the downstream
 //! sanitiser and checksum tooling.
 
+use std::collections::BTreeMap;
 use std::fs::{self, File};
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::SystemTime;
 
-use anyhow::{Context, Result};
+use aes_gcm::aead::{AeadInPlace, KeyInit};
+use aes_gcm::Aes256Gcm;
+use anyhow::{anyhow, Context, Result};
 use clap::{Parser, ValueEnum};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use storage_vector::encryption::{
+    decode_envelope, encode_envelope, CipherSuite, Encrypter, KeyHandle,
+};
+use storage_vector::kms::InMemoryKeyManager;
+use storage_vector::{Store, VectorStore};
 use tar::Builder as TarBuilder;
 
 const FIXED_MTIME: u64 = 1_704_889_600; // 2024-02-10T00:00:00Z for deterministic archives.
@@ -27,6 +36,10 @@ enum Scenario {
     OverflowLatency,
     Bulk,
     Fuzz,
+    EncryptedSnapshot,
+    Nested,
+    ZipSlip,
+    LongPath,
 }
 
 #[derive(Debug, Parser)]
@@ -43,6 +56,18 @@ struct Args {
     /// Directory output path for scenarios emitting a corpus of files.
     #[arg(long, value_name = "DIR")]
     output_dir: Option<PathBuf>,
+
+    /// Archives nested inside each other for the nested scenario.
+    #[arg(long, default_value_t = 8)]
+    depth: u32,
+
+    /// Entries emitted by the long-path scenario.
+    #[arg(long, default_value_t = 4096)]
+    entries: u64,
+
+    /// Minimum entry path length, in bytes, for the long-path scenario.
+    #[arg(long, default_value_t = 512)]
+    path_length: usize,
 }
 
 fn run(args: Args) -> Result<()> {
@@ -79,6 +104,30 @@ fn run(args: Args) -> Result<()> {
             write_bulk_corpus(&output_dir)
         }
         Scenario::Fuzz => emit_fuzz_stream(io::stdout().lock()),
+        Scenario::EncryptedSnapshot => {
+            let output = args
+                .output
+                .context("--output must be provided for the encrypted-snapshot scenario")?;
+            write_encrypted_snapshot(&output)
+        }
+        Scenario::Nested => {
+            let output = args
+                .output
+                .context("--output must be provided for the nested scenario")?;
+            write_nested_archive(&output, args.depth)
+        }
+        Scenario::ZipSlip => {
+            let output = args
+                .output
+                .context("--output must be provided for the zip-slip scenario")?;
+            write_zip_slip_archive(&output)
+        }
+        Scenario::LongPath => {
+            let output = args
+                .output
+                .context("--output must be provided for the long-path scenario")?;
+            write_long_path_archive(&output, args.entries, args.path_length)
+        }
     }
 }
 
@@ -301,6 +350,362 @@ fn write_bulk_corpus(output_dir: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Keys of the encrypted snapshot, in the order they became current.
+const SNAPSHOT_KEYS: [&str; 2] = ["fixture-key-1", "fixture-key-2"];
+
+/// Records sealed under each snapshot key. `repo-alpha/readme` is written
+/// under both, so only its second envelope survives, and the snapshot ends
+/// up holding records of both keys as a half-finished rotation would.
+const SNAPSHOT_RECORDS: [&[(&str, &str, &str)]; 2] = [
+    &[
+        ("repo-alpha", "readme", "alpha readme, first revision"),
+        ("repo-alpha", "src/lib.rs", "pub fn alpha() {}"),
+        ("repo-beta", "docs/guide.md", "# Beta guide"),
+    ],
+    &[
+        ("repo-alpha", "readme", "alpha readme, second revision"),
+        ("repo-beta", "src/main.rs", "fn main() {}"),
+    ],
+];
+
+/// Fixture key material: derived from the key id, never secret.
+fn snapshot_key(key_id: &str) -> [u8; 32] {
+    blake3::derive_key(
+        "embednexus archive_builder encrypted-snapshot",
+        key_id.as_bytes(),
+    )
+}
+
+/// AES-256-GCM with nonces derived from the key, associated data and
+/// plaintext, so equal inputs seal to equal envelopes. Only acceptable for
+/// fixtures: the store's own encrypter draws random nonces.
+struct FixtureEncrypter;
+
+impl Encrypter for FixtureEncrypter {
+    fn algorithm(&self) -> CipherSuite {
+        CipherSuite::AesGcm256
+    }
+
+    fn seal(&self, key: &KeyHandle, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+        let mut hasher = blake3::Hasher::new_keyed(&key.key_bytes);
+        hasher.update(aad);
+        hasher.update(plaintext);
+        let mut nonce = [0u8; 12];
+        nonce.copy_from_slice(&hasher.finalize().as_bytes()[..12]);
+        let cipher =
+            Aes256Gcm::new_from_slice(key.key_bytes.as_ref()).map_err(|e| e.to_string())?;
+        let mut buf = plaintext.to_vec();
+        let tag = cipher
+            .encrypt_in_place_detached(&nonce.into(), aad, &mut buf)
+            .map_err(|e| e.to_string())?;
+        Ok(encode_envelope(&key.key_id, &nonce, &tag.into(), &buf))
+    }
+
+    fn open(&self, key: &KeyHandle, envelope_bytes: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+        let (key_id, nonce, tag, mut buf) = decode_envelope(envelope_bytes)?;
+        if key_id != key.key_id {
+            return Err("key id mismatch".into());
+        }
+        let cipher =
+            Aes256Gcm::new_from_slice(key.key_bytes.as_ref()).map_err(|e| e.to_string())?;
+        cipher
+            .decrypt_in_place_detached(&nonce.into(), aad, &mut buf, &tag.into())
+            .map_err(|e| e.to_string())?;
+        Ok(buf)
+    }
+}
+
+/// A vector store snapshot archive whose records are sealed under two
+/// keys, plus `keys.json` (the key material and envelope layout) and
+/// `envelopes.jsonl` (the parsed envelope of every record). The extra
+/// entries sit beside the snapshot layout, so the archive restores with
+/// `VectorStore::restore_snapshot_archive` given the listed keys.
+fn write_encrypted_snapshot(path: &Path) -> Result<()> {
+    let kms = Arc::new(InMemoryKeyManager::new_with_secret(
+        SNAPSHOT_KEYS[0],
+        snapshot_key(SNAPSHOT_KEYS[0]),
+    ));
+    let store = VectorStore::builder()
+        .with_encrypter(Arc::new(FixtureEncrypter))
+        .with_key_manager(kms.clone())
+        .build();
+    let mut plaintexts = BTreeMap::new();
+    for (key_id, records) in SNAPSHOT_KEYS.iter().zip(SNAPSHOT_RECORDS) {
+        kms.set_current(*key_id, snapshot_key(key_id));
+        for &(repo_id, key, payload) in records {
+            store
+                .upsert(repo_id, key, payload.as_bytes())
+                .map_err(|err| anyhow!("sealing {repo_id}/{key}: {err}"))?;
+            plaintexts.insert((repo_id, key), payload);
+        }
+    }
+
+    let scratch = tempfile::tempdir().context("creating snapshot scratch directory")?;
+    let snapshot_dir = scratch.path().join("snapshot");
+    store
+        .create_snapshot(&snapshot_dir)
+        .map_err(|err| anyhow!("writing snapshot: {err}"))?;
+    let manifest_bytes =
+        fs::read(snapshot_dir.join("manifest.json")).context("reading snapshot manifest")?;
+    let manifest: SnapshotManifest =
+        serde_json::from_slice(&manifest_bytes).context("parsing snapshot manifest")?;
+
+    let mut envelopes = String::new();
+    let mut records_per_key = [0u32; SNAPSHOT_KEYS.len()];
+    for record in &manifest.records {
+        let sealed = fs::read(snapshot_dir.join(&record.path))
+            .with_context(|| format!("reading {}", record.path))?;
+        let (key_id, nonce, tag, ciphertext) =
+            decode_envelope(&sealed).map_err(|err| anyhow!("{}: {err}", record.path))?;
+        if let Some(idx) = SNAPSHOT_KEYS.iter().position(|id| *id == key_id) {
+            records_per_key[idx] += 1;
+        }
+        let plaintext = plaintexts
+            .get(&(record.repo_id.as_str(), record.key.as_str()))
+            .with_context(|| format!("unexpected record {}", record.path))?;
+        let line = EnvelopeRecord {
+            repo_id: &record.repo_id,
+            key: &record.key,
+            path: &record.path,
+            key_id: &key_id,
+            nonce: to_hex(&nonce),
+            tag: to_hex(&tag),
+            ciphertext_len: ciphertext.len(),
+            plaintext_checksum: blake3::hash(plaintext.as_bytes()).to_hex().to_string(),
+        };
+        envelopes.push_str(&serde_json::to_string(&line).context("serialising envelope")?);
+        envelopes.push('\n');
+    }
+
+    let keys = SnapshotKeys {
+        scenario: "encrypted-snapshot",
+        generated_at: fixed_timestamp_string(),
+        cipher: "aes-256-gcm",
+        envelope: "EVG1 | u16 key_id_len | key_id | 12-byte nonce | 16-byte tag | ciphertext",
+        aad: "u16 repo_len | repo_id | u16 key_id_len | key_id | u16 key_len | key",
+        current_key_id: SNAPSHOT_KEYS[SNAPSHOT_KEYS.len() - 1],
+        keys: SNAPSHOT_KEYS
+            .iter()
+            .zip(records_per_key)
+            .map(|(key_id, records)| SnapshotKey {
+                key_id,
+                key_hex: to_hex(&snapshot_key(key_id)),
+                records,
+            })
+            .collect(),
+    };
+
+    ensure_parent(path)?;
+    let file = File::create(path).with_context(|| format!("creating {}", path.display()))?;
+    let encoder =
+        zstd::stream::write::Encoder::new(file, 0).context("initialising zstd encoder")?;
+    let mut builder = TarBuilder::new(encoder.auto_finish());
+    for record in &manifest.records {
+        let bytes = fs::read(snapshot_dir.join(&record.path))
+            .with_context(|| format!("reading {}", record.path))?;
+        add_tar_entry(&mut builder, &record.path, &bytes)?;
+    }
+    let checksum =
+        fs::read(snapshot_dir.join("manifest.blake3")).context("reading manifest checksum")?;
+    add_tar_entry(&mut builder, "manifest.blake3", &checksum)?;
+    add_tar_entry(&mut builder, "manifest.json", &manifest_bytes)?;
+    let keys = serde_json::to_vec_pretty(&keys).context("serialising snapshot keys")?;
+    add_tar_entry(&mut builder, "keys.json", &keys)?;
+    add_tar_entry(&mut builder, "envelopes.jsonl", envelopes.as_bytes())?;
+    builder.finish().context("finishing tar archive")?;
+    Ok(())
+}
+
+/// `level-1.tar` holds `level-2.tar` and so on down to `level-<depth>.tar`,
+/// which holds the payload; every level carries a README naming it.
+fn write_nested_archive(path: &Path, depth: u32) -> Result<()> {
+    anyhow::ensure!(depth >= 1, "--depth must be at least 1");
+    let mut entries = 0;
+    let mut bytes = 0;
+    let payload = format!("innermost payload at nesting depth {depth}\n");
+    let mut inner_name = "payload.txt".to_string();
+    let mut inner = payload.into_bytes();
+    bytes += inner.len() as u64;
+    for level in (1..=depth).rev() {
+        let readme = format!("nesting level {level} of {depth}\n");
+        let mut builder = TarBuilder::new(Vec::new());
+        add_tar_entry(&mut builder, "README.txt", readme.as_bytes())?;
+        add_tar_entry(&mut builder, &inner_name, &inner)?;
+        inner = builder
+            .into_inner()
+            .context("finishing nested tar archive")?;
+        inner_name = format!("level-{level}.tar");
+        entries += 2;
+        bytes += readme.len() as u64;
+    }
+
+    let readme = format!("Nested archive scenario, {depth} levels deep\n");
+    entries += 2;
+    bytes += readme.len() as u64;
+    let metadata = PathologicalMetadata {
+        name: file_name(path),
+        bytes,
+        entries,
+        nesting_depth: depth,
+        expected_status: "QuotaNestingExceeded",
+        max_latency_ms: 0,
+        scenario: "nested",
+        generated_at: fixed_timestamp_string(),
+        cases: Vec::new(),
+    };
+    write_pathological_archive(path, &metadata, |builder| {
+        add_tar_entry(builder, "README.txt", readme.as_bytes())?;
+        add_tar_entry(builder, &inner_name, &inner)
+    })
+}
+
+/// Entry names that escape the extraction root, written verbatim since the
+/// tar crate refuses to build them. Each case records whether a safe
+/// extractor must reject it.
+const ZIP_SLIP_CASES: [(&str, ZipSlipKind, bool); 9] = [
+    ("safe/README.txt", ZipSlipKind::File, false),
+    ("safe/nested/../inside.txt", ZipSlipKind::File, false),
+    ("../escape-parent.txt", ZipSlipKind::File, true),
+    ("../../../../tmp/escape-deep.txt", ZipSlipKind::File, true),
+    ("/tmp/escape-absolute.txt", ZipSlipKind::File, true),
+    ("safe/../../escape-normalized.txt", ZipSlipKind::File, true),
+    ("..\\escape-windows.txt", ZipSlipKind::File, true),
+    ("safe/link", ZipSlipKind::Symlink("../../outside"), true),
+    ("safe/link/escape-through-link.txt", ZipSlipKind::File, true),
+];
+
+fn write_zip_slip_archive(path: &Path) -> Result<()> {
+    let mut bytes = 0;
+    let mut cases = Vec::new();
+    for (entry_path, kind, escapes) in ZIP_SLIP_CASES {
+        let (kind, target) = match kind {
+            ZipSlipKind::File => {
+                bytes += zip_slip_contents(entry_path).len() as u64;
+                ("file", None)
+            }
+            ZipSlipKind::Symlink(target) => ("symlink", Some(target)),
+        };
+        cases.push(PathologicalCase {
+            path: entry_path.to_string(),
+            kind,
+            target,
+            escapes,
+        });
+    }
+    let metadata = PathologicalMetadata {
+        name: file_name(path),
+        bytes,
+        entries: ZIP_SLIP_CASES.len() as u64,
+        nesting_depth: 0,
+        expected_status: "PathTraversalRejected",
+        max_latency_ms: 0,
+        scenario: "zip-slip",
+        generated_at: fixed_timestamp_string(),
+        cases,
+    };
+    write_pathological_archive(path, &metadata, |builder| {
+        for (entry_path, kind, _) in ZIP_SLIP_CASES {
+            match kind {
+                ZipSlipKind::File => add_raw_tar_entry(
+                    builder,
+                    entry_path,
+                    None,
+                    zip_slip_contents(entry_path).as_bytes(),
+                )?,
+                ZipSlipKind::Symlink(target) => {
+                    add_raw_tar_entry(builder, entry_path, Some(target), &[])?;
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+fn zip_slip_contents(entry_path: &str) -> String {
+    format!("zip-slip probe: {entry_path}\n")
+}
+
+/// `entries` files under one directory chain at least `path_length` bytes
+/// long, after a first entry whose single name component exceeds the
+/// 255-byte limit of common filesystems.
+fn write_long_path_archive(path: &Path, entries: u64, path_length: usize) -> Result<()> {
+    anyhow::ensure!(entries >= 1, "--entries must be at least 1");
+    const SEGMENT: &str = "deep-directory-segment/";
+    const LONG_COMPONENT: usize = 300;
+    let file_len = "entry-00000000.txt".len();
+    let mut prefix = String::new();
+    while prefix.len() + file_len < path_length {
+        prefix.push_str(SEGMENT);
+    }
+    let long_name = format!("{}.txt", "n".repeat(LONG_COMPONENT - 4));
+    let entry_path = |idx: u64| {
+        if idx == 0 {
+            long_name.clone()
+        } else {
+            format!("{prefix}entry-{idx:08}.txt")
+        }
+    };
+
+    let mut bytes = 0;
+    let contents = |idx: u64| format!("long-path entry {idx}\n");
+    for idx in 0..entries {
+        bytes += contents(idx).len() as u64;
+    }
+    let metadata = PathologicalMetadata {
+        name: file_name(path),
+        bytes,
+        entries,
+        nesting_depth: 0,
+        expected_status: "QuotaEntriesExceeded",
+        max_latency_ms: 0,
+        scenario: "long-path",
+        generated_at: fixed_timestamp_string(),
+        cases: (0..entries.min(2))
+            .map(|idx| PathologicalCase {
+                path: entry_path(idx),
+                kind: "file",
+                target: None,
+                escapes: false,
+            })
+            .collect(),
+    };
+    write_pathological_archive(path, &metadata, |builder| {
+        for idx in 0..entries {
+            add_long_tar_entry(builder, &entry_path(idx), contents(idx).as_bytes())?;
+        }
+        Ok(())
+    })
+}
+
+/// A zstd-compressed tar archive starting with `metadata.json`, then the
+/// entries `body` adds.
+fn write_pathological_archive(
+    path: &Path,
+    metadata: &PathologicalMetadata,
+    body: impl FnOnce(&mut TarBuilder<zstd::stream::AutoFinishEncoder<'static, File>>) -> Result<()>,
+) -> Result<()> {
+    ensure_parent(path)?;
+    let file = File::create(path).with_context(|| format!("creating {}", path.display()))?;
+    let encoder =
+        zstd::stream::write::Encoder::new(file, 0).context("initialising zstd encoder")?;
+    let mut builder = TarBuilder::new(encoder.auto_finish());
+    let json = serde_json::to_vec_pretty(metadata).context("serialising archive metadata")?;
+    add_tar_entry(&mut builder, "metadata.json", &json)?;
+    body(&mut builder)?;
+    builder.finish().context("finishing tar archive")?;
+    Ok(())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
 fn ensure_parent(path: &Path) -> Result<()> {
     if let Some(parent) = path.parent() {
         if !parent.as_os_str().is_empty() {
@@ -332,6 +737,57 @@ where
         .with_context(|| format!("writing tar entry {path}"))
 }
 
+/// Like [`add_tar_entry`], but copies `path` (and a symlink `target`) into
+/// the header verbatim, so `..` components and absolute paths survive.
+fn add_raw_tar_entry<W>(
+    builder: &mut TarBuilder<W>,
+    path: &str,
+    target: Option<&str>,
+    contents: &[u8],
+) -> Result<()>
+where
+    W: Write,
+{
+    let mut header = tar::Header::new_gnu();
+    let old = header.as_old_mut();
+    anyhow::ensure!(path.len() < old.name.len(), "raw tar path too long: {path}");
+    old.name[..path.len()].copy_from_slice(path.as_bytes());
+    if let Some(target) = target {
+        anyhow::ensure!(
+            target.len() < old.linkname.len(),
+            "raw link target too long: {target}"
+        );
+        old.linkname[..target.len()].copy_from_slice(target.as_bytes());
+        header.set_entry_type(tar::EntryType::Symlink);
+    }
+    header.set_mode(0o644);
+    header.set_uid(0);
+    header.set_gid(0);
+    header.set_size(contents.len() as u64);
+    header.set_mtime(FIXED_MTIME);
+    header.set_cksum();
+    builder
+        .append(&header, contents)
+        .with_context(|| format!("writing tar entry {path}"))
+}
+
+/// Like [`add_tar_entry`], with GNU long-name records for paths that do not
+/// fit the header.
+fn add_long_tar_entry<W>(builder: &mut TarBuilder<W>, path: &str, contents: &[u8]) -> Result<()>
+where
+    W: Write,
+{
+    let mut header = tar::Header::new_gnu();
+    header.set_mode(0o644);
+    header.set_uid(0);
+    header.set_gid(0);
+    header.set_size(contents.len() as u64);
+    header.set_mtime(FIXED_MTIME);
+    builder
+        .append_data(&mut header, path, contents)
+        .with_context(|| format!("writing tar entry {path}"))
+}
+
 #[derive(Serialize)]
 struct QuotaManifest {
     version: u32,
@@ -423,6 +879,83 @@ struct BulkManifest {
     notes: String,
 }
 
+/// The parts of the store's snapshot manifest the scenario reads back.
+#[derive(Deserialize)]
+struct SnapshotManifest {
+    records: Vec<SnapshotRecord>,
+}
+
+#[derive(Deserialize)]
+struct SnapshotRecord {
+    repo_id: String,
+    key: String,
+    path: String,
+}
+
+#[derive(Serialize)]
+struct SnapshotKeys {
+    scenario: &'static str,
+    generated_at: String,
+    cipher: &'static str,
+    envelope: &'static str,
+    aad: &'static str,
+    current_key_id: &'static str,
+    keys: Vec<SnapshotKey>,
+}
+
+#[derive(Serialize)]
+struct SnapshotKey {
+    key_id: &'static str,
+    key_hex: String,
+    records: u32,
+}
+
+#[derive(Serialize)]
+struct EnvelopeRecord<'a> {
+    repo_id: &'a str,
+    key: &'a str,
+    path: &'a str,
+    key_id: &'a str,
+    nonce: String,
+    tag: String,
+    ciphertext_len: usize,
+    plaintext_checksum: String,
+}
+
+/// `metadata.json` of the pathological scenarios; the leading fields match
+/// an ingestion `ArchiveDescriptor`. `bytes` counts the contents of
+/// regular files and `entries` every entry, at every nesting level, with
+/// `metadata.json` excluded.
+#[derive(Serialize)]
+struct PathologicalMetadata {
+    name: String,
+    bytes: u64,
+    entries: u64,
+    nesting_depth: u32,
+    expected_status: &'static str,
+    max_latency_ms: u64,
+    scenario: &'static str,
+    generated_at: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    cases: Vec<PathologicalCase>,
+}
+
+#[derive(Serialize)]
+struct PathologicalCase {
+    path: String,
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<&'static str>,
+    /// Whether extracting the entry would write outside the root.
+    escapes: bool,
+}
+
+#[derive(Clone, Copy)]
+enum ZipSlipKind {
+    File,
+    Symlink(&'static str),
+}
+
 enum OverflowProfile {
     Capacity,
     Latency,
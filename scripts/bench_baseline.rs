@@ -0,0 +1,176 @@
+//! Benchmark baseline tool.
+//!
+//! Collects the mean time of every Criterion benchmark under
+//! `target/criterion` and either records them as the checked-in baseline or
+//! compares them against it, failing when a benchmark got slower than the
+//! tolerance allows.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, Parser, Subcommand};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Parser)]
+#[command(
+    author,
+    version,
+    about = "Record or check Criterion benchmark baselines"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Overwrite the baseline with the latest Criterion results.
+    Record(Paths),
+    /// Fail when a baseline benchmark regressed or did not run.
+    Check {
+        #[command(flatten)]
+        paths: Paths,
+        /// Allowed slowdown over the baseline mean, as a fraction.
+        #[arg(long, default_value_t = 0.25)]
+        tolerance: f64,
+    },
+}
+
+#[derive(Debug, Args)]
+struct Paths {
+    /// Criterion output directory.
+    #[arg(long, value_name = "DIR", default_value = "target/criterion")]
+    criterion_dir: PathBuf,
+
+    /// Baseline JSON file.
+    #[arg(long, value_name = "FILE", default_value = "benches/baseline.json")]
+    baseline: PathBuf,
+}
+
+/// Mean nanoseconds per iteration, keyed by Criterion's full benchmark id.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Baseline {
+    benchmarks: BTreeMap<String, f64>,
+}
+
+#[derive(Deserialize)]
+struct BenchmarkId {
+    full_id: String,
+}
+
+#[derive(Deserialize)]
+struct Estimates {
+    mean: Estimate,
+}
+
+#[derive(Deserialize)]
+struct Estimate {
+    point_estimate: f64,
+}
+
+/// Latest results, from every `new/` directory Criterion wrote.
+fn collect(dir: &Path, results: &mut BTreeMap<String, f64>) -> Result<()> {
+    let entries = fs::read_dir(dir).with_context(|| format!("reading {}", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        if !path.is_dir() {
+            continue;
+        }
+        if path.file_name().is_some_and(|name| name == "new") {
+            let benchmark = path.join("benchmark.json");
+            if !benchmark.exists() {
+                continue;
+            }
+            let id: BenchmarkId = read_json(&benchmark)?;
+            let estimates: Estimates = read_json(&path.join("estimates.json"))?;
+            results.insert(id.full_id, estimates.mean.point_estimate);
+        } else {
+            collect(&path, results)?;
+        }
+    }
+    Ok(())
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<T> {
+    let bytes = fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    serde_json::from_slice(&bytes).with_context(|| format!("parsing {}", path.display()))
+}
+
+fn results(paths: &Paths) -> Result<BTreeMap<String, f64>> {
+    let mut results = BTreeMap::new();
+    collect(&paths.criterion_dir, &mut results)?;
+    if results.is_empty() {
+        bail!(
+            "no Criterion results under {}; run `cargo bench` first",
+            paths.criterion_dir.display()
+        );
+    }
+    Ok(results)
+}
+
+fn record(paths: &Paths) -> Result<()> {
+    let benchmarks = results(paths)?
+        .into_iter()
+        .map(|(id, mean)| (id, mean.round()))
+        .collect();
+    let baseline = Baseline { benchmarks };
+    if let Some(parent) = paths.baseline.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut json = serde_json::to_string_pretty(&baseline)?;
+    json.push('\n');
+    fs::write(&paths.baseline, json)
+        .with_context(|| format!("writing {}", paths.baseline.display()))?;
+    println!(
+        "recorded {} benchmarks to {}",
+        baseline.benchmarks.len(),
+        paths.baseline.display()
+    );
+    Ok(())
+}
+
+fn check(paths: &Paths, tolerance: f64) -> Result<()> {
+    let baseline: Baseline = read_json(&paths.baseline)?;
+    let results = results(paths)?;
+    let mut failures = 0;
+    for (id, &expected) in &baseline.benchmarks {
+        let Some(&actual) = results.get(id) else {
+            println!("MISSING    {id}");
+            failures += 1;
+            continue;
+        };
+        let change = actual / expected - 1.0;
+        let verdict = if change > tolerance {
+            failures += 1;
+            "REGRESSED"
+        } else {
+            "ok"
+        };
+        println!(
+            "{verdict:<10} {id}: {actual:.0} ns (baseline {expected:.0} ns, {:+.1}%)",
+            change * 100.0
+        );
+    }
+    for id in results
+        .keys()
+        .filter(|id| !baseline.benchmarks.contains_key(*id))
+    {
+        println!("new        {id}");
+    }
+    if failures > 0 {
+        bail!(
+            "{failures} benchmarks regressed more than {:.0}% or did not run",
+            tolerance * 100.0
+        );
+    }
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    match Cli::parse().command {
+        Command::Record(paths) => record(&paths),
+        Command::Check { paths, tolerance } => check(&paths, tolerance),
+    }
+}
@@ -0,0 +1,4 @@
+//! Library helpers behind the fixture tooling binaries, so tests can drive
+//! them in-process.
+
+pub mod manifest_replay;
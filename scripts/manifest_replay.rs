@@ -0,0 +1,396 @@
+//! Manifest replay harness behind the `manifest_replay_harness` binary.
+//!
+//! Fixture manifests are JSONL files of [`ReplayEntry`] records, as captured
+//! in `tests/fixtures/ingestion/delayed-ledger/`. [`replay`] buffers them in
+//! a [`ManifestEmitter`]'s [`OfflineReplayBuffer`](storage_ledger::OfflineReplayBuffer),
+//! keeps the downstream queue offline for the configured delay and flushes
+//! the buffer every retry interval until it drains. Time is simulated, so a
+//! run is instant and its log depends only on the inputs and settings.
+//!
+//! Faults come from two sources: `fail_once` fails the first delivery of a
+//! sequence, and a [`Scenario`] adds `queue_offline` faults with their own
+//! hit windows. Both apply only once the simulated outage is over; other
+//! fault points in the scenario are ignored here.
+//!
+//! Tests drive the harness in-process through [`load_manifests`],
+//! [`replay`] and [`ReplayReport::render`]; the binary only parses flags
+//! and writes the rendered log.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{bail, ensure, Context, Result};
+use fault_injection::{FaultInjector, FaultPoint, InjectedFault, Scenario, Site};
+use ingestion_manifest::{
+    ManifestCheckpoint, ManifestEmitter, ManifestEmitterConfig, ManifestQueue,
+};
+use storage_ledger::{BufferStats, ReplayEntry};
+
+/// Prefix of the per-flush delivery files written to the checkpoint
+/// directory; [`load_manifests`] skips files carrying it.
+pub const RETRY_WINDOW_PREFIX: &str = "retry-window-";
+
+/// Name of the manifest checkpoint written to the checkpoint directory.
+pub const CHECKPOINT_FILE: &str = "checkpoint.json";
+
+/// Status given to entries once the harness delivered them.
+pub const DRAINED_STATUS: &str = "drained";
+
+/// Settings of one harness run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HarnessConfig {
+    /// Simulated milliseconds the downstream queue stays offline.
+    pub delay_ms: u64,
+    /// Simulated milliseconds between flush attempts.
+    pub retry_interval_ms: u64,
+    pub retention_max_entries: usize,
+    pub retention_max_age: Duration,
+    /// Sequences whose first delivery fails after the queue is back online.
+    pub fail_once: BTreeSet<u64>,
+    /// Scenario whose `queue_offline` faults apply to deliveries. Each rule
+    /// must bound its `times` so the buffer eventually drains.
+    pub fault_scenario: Scenario,
+    /// Directory receiving [`CHECKPOINT_FILE`] and one
+    /// `retry-window-<flush>.jsonl` per flush that delivered entries.
+    pub checkpoint_dir: Option<PathBuf>,
+}
+
+impl Default for HarnessConfig {
+    fn default() -> Self {
+        Self {
+            delay_ms: 0,
+            retry_interval_ms: 15_000,
+            retention_max_entries: 128,
+            retention_max_age: Duration::from_millis(120_000),
+            fail_once: BTreeSet::new(),
+            fault_scenario: Scenario::default(),
+            checkpoint_dir: None,
+        }
+    }
+}
+
+/// One flush of the offline buffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlushAttempt {
+    /// 1-based flush number.
+    pub flush: u32,
+    /// Simulated time of the flush.
+    pub at_ms: u64,
+    /// Sequences the queue accepted, in delivery order.
+    pub delivered: Vec<u64>,
+    /// Why the flush stopped early, if it did.
+    pub error: Option<String>,
+    /// Entries left in the buffer afterwards.
+    pub buffered: usize,
+}
+
+/// Outcome of [`replay`].
+#[derive(Debug, Clone)]
+pub struct ReplayReport {
+    pub config: HarnessConfig,
+    pub loaded: usize,
+    pub flushes: Vec<FlushAttempt>,
+    /// Entries in delivery order, marked [`DRAINED_STATUS`], with the time
+    /// they spent in the harness added to `delayed_ms`.
+    pub delivered: Vec<ReplayEntry>,
+    /// Last delivered sequence per repository.
+    pub checkpoint: BTreeMap<String, u64>,
+    /// Faults the scenario raised, in order.
+    pub injected: Vec<InjectedFault>,
+    pub buffer_stats: BufferStats,
+}
+
+/// Queue standing in for the downstream ledger, with injected faults.
+#[derive(Debug, Default)]
+struct HarnessQueue {
+    state: Mutex<QueueState>,
+    injector: FaultInjector,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    online: bool,
+    fail_once: BTreeSet<u64>,
+    delivered: Vec<ReplayEntry>,
+}
+
+impl HarnessQueue {
+    fn state(&self) -> std::sync::MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl ManifestQueue for HarnessQueue {
+    fn send(&self, entry: ReplayEntry) -> Result<()> {
+        let mut state = self.state();
+        if !state.online {
+            bail!("simulated outage");
+        }
+        if let Some(fault) = self
+            .injector
+            .trip(FaultPoint::QueueOffline, Site::sequence(entry.sequence))
+        {
+            return Err(fault.into());
+        }
+        if state.fail_once.remove(&entry.sequence) {
+            bail!("injected fault for sequence {}", entry.sequence);
+        }
+        state.delivered.push(entry);
+        Ok(())
+    }
+}
+
+/// Read every `*.jsonl` file in `dir`, in file name order, skipping blank
+/// lines and the harness's own `retry-window-*` output.
+pub fn load_manifests(dir: &Path) -> Result<Vec<ReplayEntry>> {
+    let mut paths = Vec::new();
+    for item in fs::read_dir(dir).with_context(|| format!("reading {}", dir.display()))? {
+        let path = item?.path();
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("");
+        if name.ends_with(".jsonl") && !name.starts_with(RETRY_WINDOW_PREFIX) {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    let mut entries = Vec::new();
+    for path in paths {
+        let source =
+            fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+        for (index, line) in source.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = serde_json::from_str(line)
+                .with_context(|| format!("{} line {}", path.display(), index + 1))?;
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+/// Buffer `entries` and flush them once per retry interval until the
+/// buffer drains, writing checkpoint artifacts when configured.
+pub fn replay(entries: Vec<ReplayEntry>, config: &HarnessConfig) -> Result<ReplayReport> {
+    ensure!(
+        config.retry_interval_ms > 0,
+        "retry interval must be positive"
+    );
+    let mut scenario_faults = 0;
+    for rule in config.fault_scenario.rules(FaultPoint::QueueOffline) {
+        let Some(times) = rule.times else {
+            bail!("queue_offline faults must set `times` for the buffer to drain");
+        };
+        scenario_faults += times;
+    }
+    let checkpoint = match &config.checkpoint_dir {
+        Some(dir) => {
+            clear_artifacts(dir)?;
+            ManifestCheckpoint::open(dir.join(CHECKPOINT_FILE))?
+        }
+        None => ManifestCheckpoint::in_memory(),
+    };
+    let checkpoint = Arc::new(checkpoint);
+    let queue = Arc::new(HarnessQueue {
+        injector: FaultInjector::new(config.fault_scenario.clone()),
+        ..HarnessQueue::default()
+    });
+    queue.state().fail_once = config.fail_once.clone();
+    let mut emitter = ManifestEmitter::resume_from_checkpoint(
+        ManifestEmitterConfig {
+            sequence_start: 0,
+            encryption_key: String::new(),
+            retention_max_entries: config.retention_max_entries,
+            retention_max_age: config.retention_max_age,
+            offline_buffer_path: None,
+        },
+        Arc::clone(&checkpoint),
+        Arc::clone(&queue),
+    )?;
+    let loaded = entries.len();
+    for entry in entries {
+        emitter.buffer().push(ReplayEntry {
+            status: "buffered".into(),
+            ..entry
+        })?;
+    }
+
+    // Flushes while offline, plus one retry per injected fault and the
+    // flush that drains the rest.
+    let max_flushes = config.delay_ms.div_ceil(config.retry_interval_ms)
+        + config.fail_once.len() as u64
+        + scenario_faults
+        + 1;
+    let mut flushes = Vec::new();
+    let mut delivered = Vec::new();
+    let mut at_ms = 0;
+    for flush in 1..=max_flushes {
+        let flush = u32::try_from(flush).context("too many flushes")?;
+        queue.state().online = at_ms >= config.delay_ms;
+        let result = emitter.flush_offline();
+        let accepted: Vec<ReplayEntry> = queue
+            .state()
+            .delivered
+            .drain(..)
+            .map(|entry| ReplayEntry {
+                status: DRAINED_STATUS.into(),
+                delayed_ms: entry.delayed_ms.saturating_add(at_ms),
+                ..entry
+            })
+            .collect();
+        if let Some(dir) = config
+            .checkpoint_dir
+            .as_ref()
+            .filter(|_| !accepted.is_empty())
+        {
+            write_retry_window(dir, flush, &accepted)?;
+        }
+        flushes.push(FlushAttempt {
+            flush,
+            at_ms,
+            delivered: accepted.iter().map(|entry| entry.sequence).collect(),
+            error: result.err().map(|err| err.to_string()),
+            buffered: emitter.buffer().len(),
+        });
+        delivered.extend(accepted);
+        if emitter.buffer().is_empty() {
+            break;
+        }
+        at_ms += config.retry_interval_ms;
+    }
+    ensure!(
+        emitter.buffer().is_empty(),
+        "{} entries still buffered after {max_flushes} flushes",
+        emitter.buffer().len()
+    );
+
+    let repos: BTreeSet<&str> = delivered
+        .iter()
+        .map(|entry| entry.repo_id.as_str())
+        .collect();
+    let checkpoint = repos
+        .into_iter()
+        .filter_map(|repo| Some((repo.to_string(), checkpoint.last_emitted(repo)?)))
+        .collect();
+    Ok(ReplayReport {
+        config: config.clone(),
+        loaded,
+        flushes,
+        delivered,
+        checkpoint,
+        injected: queue.injector.injected(),
+        buffer_stats: emitter.buffer().stats(),
+    })
+}
+
+/// Remove the artifacts of a previous run from `dir`. Every run starts from
+/// scratch; a stale checkpoint would drop the fixture entries as already
+/// delivered.
+fn clear_artifacts(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    for item in fs::read_dir(dir).with_context(|| format!("reading {}", dir.display()))? {
+        let path = item?.path();
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("");
+        if name == CHECKPOINT_FILE || name.starts_with(RETRY_WINDOW_PREFIX) {
+            fs::remove_file(&path).with_context(|| format!("removing {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+fn write_retry_window(dir: &Path, flush: u32, entries: &[ReplayEntry]) -> Result<()> {
+    let path = dir.join(format!("{RETRY_WINDOW_PREFIX}{flush:02}.jsonl"));
+    let mut lines = String::new();
+    for entry in entries {
+        lines.push_str(&serde_json::to_string(entry)?);
+        lines.push('\n');
+    }
+    fs::write(&path, lines).with_context(|| format!("writing {}", path.display()))
+}
+
+impl ReplayReport {
+    /// The deterministic YAML log stored as the manifest replay golden.
+    #[must_use]
+    pub fn render(&self) -> String {
+        let config = &self.config;
+        let max_age_ms = config.retention_max_age.as_millis();
+        let mut out = String::from("# Manifest replay harness output (deterministic fixture)\n");
+        let _ = writeln!(out, "delay_ms: {}", config.delay_ms);
+        let _ = writeln!(out, "retry_interval_ms: {}", config.retry_interval_ms);
+        let _ = writeln!(out, "loaded: {}", self.loaded);
+        out.push_str("flushes:\n");
+        for flush in &self.flushes {
+            let _ = writeln!(out, "  - flush: {}", flush.flush);
+            let _ = writeln!(out, "    at_ms: {}", flush.at_ms);
+            let delivered: Vec<String> = flush.delivered.iter().map(u64::to_string).collect();
+            let _ = writeln!(out, "    delivered: [{}]", delivered.join(", "));
+            if let Some(error) = &flush.error {
+                let _ = writeln!(out, "    error: {error}");
+            }
+            let _ = writeln!(out, "    buffered: {}", flush.buffered);
+        }
+        out.push_str("delivered:\n");
+        for entry in &self.delivered {
+            let _ = writeln!(out, "  - repo: {}", entry.repo_id);
+            let _ = writeln!(out, "    sequence: {}", entry.sequence);
+            let _ = writeln!(out, "    status: {}", entry.status);
+            let _ = writeln!(out, "    delayed_ms: {}", entry.delayed_ms);
+            let _ = writeln!(
+                out,
+                "    checksum_before: {}",
+                entry.payload_checksum_before
+            );
+            let _ = writeln!(out, "    checksum_after: {}", entry.payload_checksum_after);
+        }
+        out.push_str("checkpoint:\n");
+        for (repo, sequence) in &self.checkpoint {
+            let _ = writeln!(out, "  {repo}: {sequence}");
+        }
+        out.push_str("notes:\n");
+        let _ = writeln!(
+            out,
+            "  - buffered entries flushed after offline window of {:.1}s",
+            config.delay_ms as f64 / 1000.0
+        );
+        if !config.fail_once.is_empty() {
+            let faults: Vec<String> = config.fail_once.iter().map(u64::to_string).collect();
+            let _ = writeln!(
+                out,
+                "  - injected delivery faults for sequences {}",
+                faults.join(", ")
+            );
+        }
+        if !config.fault_scenario.faults.is_empty() {
+            let _ = writeln!(
+                out,
+                "  - fault scenario {} raised {} faults",
+                config.fault_scenario.name,
+                self.injected.len()
+            );
+        }
+        let stats = &self.buffer_stats;
+        if stats.evicted == 0 && stats.expired == 0 {
+            let _ = writeln!(
+                out,
+                "  - retention ceiling satisfied (max_age_ms = {max_age_ms}, max_entries = {})",
+                config.retention_max_entries
+            );
+        } else {
+            let _ = writeln!(
+                out,
+                "  - retention ceiling dropped {} evicted and {} expired entries (max_age_ms = {max_age_ms}, max_entries = {})",
+                stats.evicted, stats.expired, config.retention_max_entries
+            );
+        }
+        out
+    }
+}
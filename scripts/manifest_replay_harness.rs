@@ -1,23 +1,97 @@
-//! Placeholder for the manifest replay integration harness.
+//! Manifest replay harness binary.
 //!
-//! Planned responsibilities:
-//! - Replay ingestion manifests with configurable delay and fault injection
-//!   settings to reproduce edge cases documented in the fixture plan.
-//! - Emit deterministic logs and checkpoint artifacts for validation by Python
-//!   tooling and test suites.
-//! - Provide command-line options for input directories, delay intervals, and
-//!   output destinations.
-//!
-//! Runtime requirements:
-//! - Rust toolchain (1.76+ recommended)
-//! - Candidate crates: async runtime (`tokio`), structured logging (`tracing`),
-//!   CLI parsing (`clap`), serialization (`serde`).
-//!
-//! Implementation notes:
-//! - Should integrate with the offline transport buffer utilities to coordinate
-//!   multi-stage replay scenarios.
-//! - Consider exposing library helpers so tests can drive the harness in-process.
+//! Replays the delayed ledger fixtures through the manifest emitter's
+//! offline buffer on a simulated clock and writes the deterministic replay
+//! log; see [`cursor_fixture_tools::manifest_replay`] for the model.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use cursor_fixture_tools::manifest_replay::{load_manifests, replay, HarnessConfig};
+use fault_injection::Scenario;
+
+#[derive(Debug, Parser)]
+#[command(author, version, about = "Deterministic manifest replay harness")]
+struct Args {
+    /// Directory of JSONL replay entry fixtures.
+    #[arg(long, value_name = "DIR")]
+    input_dir: PathBuf,
+
+    /// Replay log output path; stdout when omitted.
+    #[arg(long)]
+    golden_out: Option<PathBuf>,
+
+    /// Simulated milliseconds the downstream queue stays offline.
+    #[arg(long, default_value_t = 0)]
+    delay_ms: u64,
+
+    /// Simulated milliseconds between flush attempts.
+    #[arg(long, default_value_t = 15_000)]
+    retry_interval_ms: u64,
+
+    /// Offline buffer capacity.
+    #[arg(long, default_value_t = 128)]
+    max_entries: usize,
+
+    /// Offline buffer retention age.
+    #[arg(long, default_value_t = 120_000)]
+    max_age_ms: u64,
+
+    /// Fail the first delivery of this sequence once the queue is back
+    /// online; repeatable.
+    #[arg(long = "fail-sequence", value_name = "SEQUENCE")]
+    fail_sequences: Vec<u64>,
+
+    /// Fault scenario TOML whose `queue_offline` faults apply to
+    /// deliveries.
+    #[arg(long, value_name = "FILE")]
+    fault_scenario: Option<PathBuf>,
+
+    /// Directory receiving the manifest checkpoint and per-flush
+    /// `retry-window-*.jsonl` deliveries.
+    #[arg(long, value_name = "DIR")]
+    checkpoint_dir: Option<PathBuf>,
+}
+
+fn run(args: Args) -> Result<()> {
+    let fault_scenario = match &args.fault_scenario {
+        Some(path) => Scenario::load(path)?,
+        None => Scenario::default(),
+    };
+    let config = HarnessConfig {
+        delay_ms: args.delay_ms,
+        retry_interval_ms: args.retry_interval_ms,
+        retention_max_entries: args.max_entries,
+        retention_max_age: Duration::from_millis(args.max_age_ms),
+        fail_once: args.fail_sequences.into_iter().collect::<BTreeSet<_>>(),
+        fault_scenario,
+        checkpoint_dir: args.checkpoint_dir,
+    };
+    let entries = load_manifests(&args.input_dir)?;
+    let log = replay(entries, &config)?.render();
+    match args.golden_out {
+        Some(path) => {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("creating {}", parent.display()))?;
+            }
+            fs::write(&path, log).with_context(|| format!("writing {}", path.display()))
+        }
+        None => io::stdout()
+            .lock()
+            .write_all(log.as_bytes())
+            .context("writing replay log"),
+    }
+}
 
 fn main() {
-    panic!("manifest_replay_harness stub not implemented");
+    if let Err(err) = run(Args::parse()) {
+        eprintln!("error: {err:#}");
+        std::process::exit(1);
+    }
 }
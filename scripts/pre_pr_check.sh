@@ -47,6 +47,13 @@ if ensure_tool rustup; then
   done
 fi
 
+# rustfmt (same check CI runs, so unformatted sources fail before review)
+log "Running cargo fmt check"
+if ! cargo fmt --all -- --check; then
+  err "Sources are not rustfmt-clean. Run: cargo fmt --all"
+  exit 1
+fi
+
 # cargo-deny (policy checks)
 ensure_cargo_crate cargo-deny cargo-deny --locked
 log "Running cargo-deny checks (advisories, bans, sources, licenses)"
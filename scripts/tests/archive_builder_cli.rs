@@ -1,7 +1,11 @@
 use std::fs;
 use std::io::Read;
 use std::path::Path;
+use std::sync::Arc;
 
+use storage_vector::encryption::aes_gcm::AesGcmEncrypter;
+use storage_vector::kms::InMemoryKeyManager;
+use storage_vector::{ArchiveQuotaTracker, ArchiveSample, QuotaLimits, Store, VectorStore};
 use tempfile::tempdir;
 
 #[allow(deprecated)]
@@ -142,3 +146,173 @@ fn overflow_latency_archive_contains_csv() {
     }
     assert!(seen_latency, "latency.csv not found");
 }
+
+#[test]
+fn encrypted_snapshot_matches_the_fixture_and_restores_with_its_keys() {
+    let dir = tempdir().unwrap();
+    let output_path = dir.path().join("encrypted-snapshot.tar.zst");
+
+    cargo_bin()
+        .arg("--scenario")
+        .arg("encrypted-snapshot")
+        .arg("--output")
+        .arg(&output_path)
+        .assert()
+        .success();
+
+    let generated = fs::read(&output_path).unwrap();
+    let fixture = fs::read(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../tests/fixtures/archives/encrypted-snapshot.tar.zst"
+    ))
+    .unwrap();
+    assert_eq!(generated, fixture);
+
+    let mut decoder = zstd::Decoder::new(&generated[..]).unwrap();
+    let mut tar_bytes = Vec::new();
+    decoder.read_to_end(&mut tar_bytes).unwrap();
+    let mut keys_json = String::new();
+    for entry in tar::Archive::new(&tar_bytes[..]).entries().unwrap() {
+        let mut entry = entry.unwrap();
+        if entry.path().unwrap() == Path::new("keys.json") {
+            entry.read_to_string(&mut keys_json).unwrap();
+        }
+    }
+    let keys: serde_json::Value = serde_json::from_str(&keys_json).unwrap();
+    let kms = InMemoryKeyManager::new_with_secret("unused", [0; 32]);
+    for key in keys["keys"].as_array().unwrap() {
+        let hex = key["key_hex"].as_str().unwrap();
+        let mut bytes = [0u8; 32];
+        for (idx, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[idx * 2..idx * 2 + 2], 16).unwrap();
+        }
+        kms.set_current(key["key_id"].as_str().unwrap(), bytes);
+    }
+
+    // The store's own encrypter opens the fixture's envelopes.
+    let store = VectorStore::builder()
+        .with_encrypter(Arc::new(AesGcmEncrypter::new()))
+        .with_key_manager(Arc::new(kms))
+        .build();
+    let report = store.restore_snapshot_archive(&output_path).unwrap();
+    assert_eq!(report.records, 4);
+    assert_eq!(
+        store.get("repo-alpha", "readme").unwrap().as_deref(),
+        Some(&b"alpha readme, second revision"[..])
+    );
+    assert_eq!(
+        store.get("repo-beta", "docs/guide.md").unwrap().as_deref(),
+        Some(&b"# Beta guide"[..])
+    );
+}
+
+fn unpack_entries(bytes: &[u8]) -> Vec<(String, tar::EntryType, Vec<u8>)> {
+    let mut archive = tar::Archive::new(bytes);
+    archive
+        .entries()
+        .unwrap()
+        .map(|entry| {
+            let mut entry = entry.unwrap();
+            let path = String::from_utf8(entry.path_bytes().into_owned()).unwrap();
+            let kind = entry.header().entry_type();
+            let mut contents = Vec::new();
+            entry.read_to_end(&mut contents).unwrap();
+            (path, kind, contents)
+        })
+        .collect()
+}
+
+fn build_pathological(scenario: &str, extra: &[&str]) -> Vec<(String, tar::EntryType, Vec<u8>)> {
+    let dir = tempdir().unwrap();
+    let output_path = dir.path().join(format!("{scenario}.tar.zst"));
+    cargo_bin()
+        .arg("--scenario")
+        .arg(scenario)
+        .arg("--output")
+        .arg(&output_path)
+        .args(extra)
+        .assert()
+        .success();
+    let mut tar_bytes = Vec::new();
+    zstd::Decoder::new(fs::File::open(&output_path).unwrap())
+        .unwrap()
+        .read_to_end(&mut tar_bytes)
+        .unwrap();
+    unpack_entries(&tar_bytes)
+}
+
+fn quota_sample(metadata: &serde_json::Value) -> ArchiveSample {
+    ArchiveSample {
+        bytes: metadata["bytes"].as_u64().unwrap(),
+        entries: metadata["entries"].as_u64().unwrap(),
+        nesting_depth: metadata["nesting_depth"].as_u64().unwrap() as u32,
+        max_latency_ms: metadata["max_latency_ms"].as_u64().unwrap(),
+    }
+}
+
+#[test]
+fn nested_archive_reaches_the_requested_depth_and_trips_the_nesting_quota() {
+    let entries = build_pathological("nested", &["--depth", "5"]);
+    let metadata: serde_json::Value = serde_json::from_slice(&entries[0].2).unwrap();
+    assert_eq!(metadata["nesting_depth"], 5);
+
+    let mut level = entries;
+    let mut depth = 0;
+    let mut total = level.len() - 1;
+    while let Some((_, _, inner)) = level.iter().find(|(path, _, _)| path.ends_with(".tar")) {
+        let inner = unpack_entries(inner);
+        depth += 1;
+        total += inner.len();
+        level = inner;
+    }
+    assert_eq!(depth, 5);
+    assert!(level.iter().any(|(path, _, _)| path == "payload.txt"));
+    assert_eq!(metadata["entries"].as_u64(), Some(total as u64));
+
+    let mut tracker = ArchiveQuotaTracker::new(QuotaLimits {
+        nesting_max: Some(4),
+        ..QuotaLimits::default()
+    });
+    tracker.observe(&quota_sample(&metadata));
+    assert!(tracker.check().is_err());
+}
+
+#[test]
+fn zip_slip_archive_keeps_traversal_paths_verbatim() {
+    let entries = build_pathological("zip-slip", &[]);
+    let metadata: serde_json::Value = serde_json::from_slice(&entries[0].2).unwrap();
+    let cases = metadata["cases"].as_array().unwrap();
+    assert_eq!(cases.len(), entries.len() - 1);
+    for (case, (path, kind, _)) in cases.iter().zip(&entries[1..]) {
+        assert_eq!(case["path"].as_str(), Some(path.as_str()));
+        let symlink = *kind == tar::EntryType::Symlink;
+        assert_eq!(case["kind"] == "symlink", symlink, "{path}");
+    }
+    let escaping: Vec<&str> = cases
+        .iter()
+        .filter(|case| case["escapes"] == true)
+        .map(|case| case["path"].as_str().unwrap())
+        .collect();
+    assert!(escaping.contains(&"../escape-parent.txt"));
+    assert!(escaping.contains(&"/tmp/escape-absolute.txt"));
+    assert!(escaping.contains(&"safe/link"));
+    assert!(!escaping.contains(&"safe/README.txt"));
+}
+
+#[test]
+fn long_path_archive_emits_the_requested_entries_and_path_lengths() {
+    let entries = build_pathological("long-path", &["--entries", "40", "--path-length", "700"]);
+    let metadata: serde_json::Value = serde_json::from_slice(&entries[0].2).unwrap();
+    let files = &entries[1..];
+    assert_eq!(files.len(), 40);
+    assert_eq!(metadata["entries"], 40);
+    assert!(files[0].0.len() > 255 && !files[0].0.contains('/'));
+    assert!(files[1..].iter().all(|(path, _, _)| path.len() >= 700));
+
+    let mut tracker = ArchiveQuotaTracker::new(QuotaLimits {
+        entries_max: Some(32),
+        ..QuotaLimits::default()
+    });
+    tracker.observe(&quota_sample(&metadata));
+    assert!(tracker.check().is_err());
+}
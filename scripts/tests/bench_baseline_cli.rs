@@ -0,0 +1,71 @@
+use std::fs;
+use std::path::Path;
+
+use tempfile::tempdir;
+
+#[allow(deprecated)]
+fn cargo_bin() -> assert_cmd::Command {
+    assert_cmd::Command::cargo_bin("bench_baseline").expect("binary not built")
+}
+
+fn write_result(criterion: &Path, id: &str, mean: f64) {
+    let dir = criterion.join(id).join("new");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("benchmark.json"),
+        serde_json::json!({ "group_id": "g", "full_id": id }).to_string(),
+    )
+    .unwrap();
+    fs::write(
+        dir.join("estimates.json"),
+        serde_json::json!({ "mean": { "point_estimate": mean } }).to_string(),
+    )
+    .unwrap();
+}
+
+#[test]
+fn record_then_check_flags_regressions_and_missing_benchmarks() {
+    let dir = tempdir().unwrap();
+    let criterion = dir.path().join("criterion");
+    let baseline = dir.path().join("baseline.json");
+    write_result(&criterion, "codec/encode", 1000.4);
+    write_result(&criterion, "codec/decode", 2000.0);
+
+    let run = |command: &str, extra: &[&str]| {
+        let mut cmd = cargo_bin();
+        cmd.arg(command)
+            .arg("--criterion-dir")
+            .arg(&criterion)
+            .arg("--baseline")
+            .arg(&baseline)
+            .args(extra);
+        cmd.assert()
+    };
+
+    run("record", &[]).success();
+    let recorded: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&baseline).unwrap()).unwrap();
+    assert_eq!(recorded["benchmarks"]["codec/encode"], 1000.0);
+    run("check", &[]).success();
+
+    // 20% slower passes the default tolerance, 30% does not.
+    write_result(&criterion, "codec/encode", 1200.0);
+    run("check", &[]).success();
+    write_result(&criterion, "codec/encode", 1300.0);
+    let output = run("check", &[]).failure().get_output().stdout.clone();
+    assert!(String::from_utf8(output)
+        .unwrap()
+        .contains("REGRESSED  codec/encode"));
+    run("check", &["--tolerance", "0.5"]).success();
+
+    fs::remove_dir_all(criterion.join("codec/decode")).unwrap();
+    write_result(&criterion, "codec/verify", 10.0);
+    let output = run("check", &["--tolerance", "0.5"])
+        .failure()
+        .get_output()
+        .stdout
+        .clone();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("MISSING    codec/decode"));
+    assert!(output.contains("new        codec/verify"));
+}
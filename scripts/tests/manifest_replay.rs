@@ -0,0 +1,101 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use cursor_fixture_tools::manifest_replay::{
+    load_manifests, replay, HarnessConfig, CHECKPOINT_FILE, DRAINED_STATUS,
+};
+use fault_injection::Scenario;
+use tempfile::tempdir;
+
+fn fixture_dir() -> &'static Path {
+    Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../tests/fixtures/ingestion/delayed-ledger"
+    ))
+}
+
+#[test]
+fn delayed_ledger_replay_matches_the_golden_log() {
+    let entries = load_manifests(fixture_dir()).unwrap();
+    let config = HarnessConfig {
+        delay_ms: 45_000,
+        ..HarnessConfig::default()
+    };
+    let log = replay(entries, &config).unwrap().render();
+    let golden = fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../tests/golden/ingestion/manifest-replay.log"
+    ))
+    .unwrap();
+    assert_eq!(log, golden);
+}
+
+#[test]
+fn injected_faults_hold_back_the_rest_of_the_buffer_until_the_next_flush() {
+    let dir = tempdir().unwrap();
+    let config = HarnessConfig {
+        delay_ms: 20_000,
+        retry_interval_ms: 10_000,
+        fail_once: BTreeSet::from([2]),
+        checkpoint_dir: Some(dir.path().to_path_buf()),
+        ..HarnessConfig::default()
+    };
+    let entries = load_manifests(fixture_dir()).unwrap();
+    let report = replay(entries.clone(), &config).unwrap();
+
+    let delivered: Vec<_> = report.flushes.iter().map(|f| f.delivered.clone()).collect();
+    assert_eq!(delivered, vec![vec![], vec![], vec![1], vec![2, 3]]);
+    assert!(report.flushes[2]
+        .error
+        .as_deref()
+        .unwrap()
+        .contains("sequence 2"));
+    assert!(report
+        .delivered
+        .iter()
+        .all(|entry| entry.status == DRAINED_STATUS));
+    assert_eq!(report.delivered[1].delayed_ms, 3_800 + 30_000);
+    assert_eq!(report.checkpoint.get("repo-alpha"), Some(&2));
+
+    // Artifacts land in the checkpoint directory and are replaced, not
+    // appended to, by a rerun.
+    let window = fs::read_to_string(dir.path().join("retry-window-04.jsonl")).unwrap();
+    assert_eq!(window.lines().count(), 2);
+    assert!(dir.path().join(CHECKPOINT_FILE).is_file());
+    let rerun = replay(entries, &config).unwrap();
+    assert_eq!(rerun.render(), report.render());
+    assert!(load_manifests(dir.path()).unwrap().is_empty());
+}
+
+#[test]
+fn fault_scenarios_refuse_deliveries_within_their_hit_window() {
+    let scenario = Scenario::load(
+        &Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../tests/fixtures/ingestion/fault-scenarios/queue-flap.toml"),
+    )
+    .unwrap();
+    let config = HarnessConfig {
+        fault_scenario: scenario,
+        ..HarnessConfig::default()
+    };
+    let report = replay(load_manifests(fixture_dir()).unwrap(), &config).unwrap();
+
+    let delivered: Vec<_> = report.flushes.iter().map(|f| f.delivered.clone()).collect();
+    assert_eq!(delivered, vec![vec![1, 2], vec![], vec![3]]);
+    assert!(report.flushes[1]
+        .error
+        .as_deref()
+        .unwrap()
+        .ends_with("ledger queue flapped for sequence 3"));
+    assert_eq!(report.injected.len(), 2);
+    assert!(report
+        .render()
+        .contains("fault scenario queue-flap raised 2 faults"));
+
+    let unbounded = HarnessConfig {
+        fault_scenario: Scenario::from_toml_str("[[fault]]\npoint = \"queue_offline\"").unwrap(),
+        ..HarnessConfig::default()
+    };
+    assert!(replay(load_manifests(fixture_dir()).unwrap(), &unbounded).is_err());
+}
@@ -0,0 +1,65 @@
+"""Tests for the `embednexus` Python bindings (crates/embednexus-py).
+
+Build the module first, e.g. `maturin develop -m crates/embednexus-py/Cargo.toml`.
+"""
+
+from __future__ import annotations
+
+from pathlib import Path
+
+import pytest
+
+embednexus = pytest.importorskip("embednexus")
+
+
+def _workspace(root: Path) -> Path:
+    (root / "src").mkdir(parents=True)
+    (root / "src" / "lib.rs").write_text(
+        'fn connect() { let token = "hunter2"; }\n', encoding="utf-8"
+    )
+    (root / "README.md").write_text("# demo\n\nConnects to the service.\n", encoding="utf-8")
+    return root
+
+
+def test_stages_chain_from_scan_to_vectors(tmp_path: Path) -> None:
+    root = _workspace(tmp_path / "repo")
+
+    workspace = embednexus.scan_workspace("demo", root)
+    assert sorted(file["path"] for file in workspace["files"]) == ["README.md", "src/lib.rs"]
+
+    chunks = embednexus.plan_chunks(workspace)
+    assert {chunk["repo_id"] for chunk in chunks} == {"demo"}
+
+    sanitized = embednexus.sanitize_chunks(chunks)
+    assert all("hunter2" not in chunk["scrubbed_payload"] for chunk in sanitized)
+
+    batch = embednexus.embed_chunks(sanitized, dimensions=8)
+    assert len(batch["vectors"]) == len(sanitized)
+    assert all(len(vector) == 8 for vector in batch["vectors"])
+
+
+def test_incremental_scans_return_changed_files(tmp_path: Path) -> None:
+    root = _workspace(tmp_path / "repo")
+    state = tmp_path / "state"
+
+    first = embednexus.scan_workspace("demo", root, state_dir=state)
+    assert len(first["files"]) == 2
+    assert embednexus.scan_workspace("demo", root, state_dir=state)["files"] == []
+
+
+def test_malformed_input_raises_value_error() -> None:
+    with pytest.raises(ValueError):
+        embednexus.plan_chunks({"repo_id": "demo"})
+
+
+def test_runtime_runs_commands_against_its_state_dir(tmp_path: Path) -> None:
+    root = _workspace(tmp_path / "repo")
+    runtime = embednexus.Runtime(tmp_path / "state", dimensions=16)
+
+    runtime.call("workspace.register", {"repo_id": "demo", "root_path": str(root)})
+    listed = runtime.call("workspace.list")
+    assert [workspace["repo_id"] for workspace in listed["workspaces"]] == ["demo"]
+
+    with pytest.raises(embednexus.EmbedNexusError) as excinfo:
+        runtime.call("missing.command")
+    assert excinfo.value.args[1] == 404
@@ -1,8 +1,21 @@
-use runtime_router::{RecordingRouter, RouterError, RouterResponse};
-use runtime_transport_http::{HttpAdapter, HttpConfig, HttpRequest, TransportError as HttpError};
-use runtime_transport_stdio::{StdioAdapter, StdioConfig, TransportError as StdioError};
+use ingestion_sanitization::telemetry::telemetry_redactor;
+use ingestion_sanitization::SanitizationConfig;
+use runtime_principals::{InMemoryPrincipalStore, PrincipalStore};
+use runtime_router::{
+    CommandRouter, HandlerRouter, RecordingRouter, RouterCommand, RouterError, RouterResponse,
+    SessionContext,
+};
+use runtime_telemetry::{
+    ExportConfig, ExportPipeline, ExportRecord, ExporterConfig, PeerTelemetry, Severity,
+};
+use runtime_transport_http::{
+    HttpAdapter, HttpConfig, HttpError, HttpRequest, TransportError as HttpTransportError,
+};
+use runtime_transport_stdio::{
+    StdioAdapter, StdioConfig, StdioFrame, TransportError as StdioTransportError,
+};
 use runtime_transport_uds::{
-    PeerCredentials, TransportError as UdsError, UdsAdapter, UdsConfig, UdsRequest,
+    PeerCredentials, TransportError as UdsTransportError, UdsAdapter, UdsConfig, UdsRequest,
 };
 use serde_json::json;
 use std::sync::Arc;
@@ -77,7 +90,11 @@ async fn http_end_to_end_and_csrf_failure() {
         .dispatch(bad_request)
         .await
         .expect_err("missing csrf");
-    assert!(matches!(err, HttpError::Csrf(_)));
+    assert!(matches!(
+        err,
+        HttpTransportError::Adapter(HttpError::Csrf(_))
+    ));
+    assert_eq!((err.status_code(), err.kind()), (403, "csrf"));
 }
 
 #[tokio::test]
@@ -109,8 +126,9 @@ async fn stdio_and_uds_authentication_failures_surface() {
         .dispatch_frame(frame)
         .await
         .expect_err("router unauthorized should bubble");
-    assert!(matches!(err, StdioError::Router(_)));
+    assert!(matches!(err, StdioTransportError::Router(_)));
     assert_eq!(err.router_status_code(), Some(401));
+    let stdio_status = (err.status_code(), err.kind());
 
     uds.negotiate_peer(&peer())
         .expect("peer negotiation should succeed");
@@ -129,11 +147,388 @@ async fn stdio_and_uds_authentication_failures_surface() {
         .dispatch(request)
         .await
         .expect_err("router error surfaces");
-    assert!(matches!(err, UdsError::Router(_)));
+    assert!(matches!(err, UdsTransportError::Router(_)));
     assert_eq!(err.router_status_code(), Some(401));
+    // Both adapters map the router's refusal the same way.
+    assert_eq!((err.status_code(), err.kind()), stdio_status);
 
     let mut bad_peer = peer();
     bad_peer.uid = 77;
     let auth_err = uds.negotiate_peer(&bad_peer).expect_err("bad uid rejected");
-    assert!(matches!(auth_err, UdsError::Unauthorized(_)));
+    assert!(matches!(auth_err, UdsTransportError::Unauthorized(_)));
+    assert_eq!(auth_err.status_code(), 401);
+}
+
+#[tokio::test]
+async fn reload_swaps_principals_without_a_restart() {
+    let router = Arc::new(RecordingRouter::default());
+    let http = HttpAdapter::bind(http_config(), router.clone() as _).unwrap();
+    let stdio = StdioAdapter::bind(stdio_config(), router.clone() as _).unwrap();
+    let uds = UdsAdapter::bind(uds_config(), router.clone() as _).unwrap();
+
+    let http_token = http.issue_session_token("alice", &[]).unwrap();
+    let stdio_token = stdio.issue_session_token("alice").unwrap();
+    uds.negotiate_peer(&peer()).unwrap();
+    let uds_token = uds.issue_session_token("alice", &[]).unwrap();
+
+    http.reload(HttpConfig {
+        allowed_principals: vec!["bob".into()],
+        ..http_config()
+    })
+    .expect("principals reload");
+    stdio
+        .reload(StdioConfig {
+            allowed_principals: vec!["bob".into()],
+            ..stdio_config()
+        })
+        .expect("principals reload");
+    uds.reload(UdsConfig {
+        allowed_principals: vec!["bob".into()],
+        allowed_uids: vec![2000],
+        ..uds_config()
+    })
+    .expect("principals and uids reload");
+
+    // Tokens issued before the reload stop working for revoked principals.
+    let request = HttpRequest::new("POST", "/commands/search", json!({ "command": "search" }))
+        .with_header("Authorization", format!("Bearer {}", http_token.token))
+        .with_header("X-Csrf-Token", http_token.csrf_nonce.clone());
+    let err = http.dispatch(request).await.expect_err("alice revoked");
+    assert!(matches!(err, HttpTransportError::Unauthorized(_)));
+    let frame = stdio
+        .codec()
+        .encode(&json!({ "command": "search" }), &stdio_token)
+        .unwrap();
+    let err = stdio
+        .dispatch_frame(frame)
+        .await
+        .expect_err("alice revoked");
+    assert!(matches!(err, StdioTransportError::Unauthorized(_)));
+    // Peers negotiated under a uid no longer allowed are forgotten.
+    let request = UdsRequest::new(peer(), uds_token.token, json!({ "command": "search" }));
+    let err = uds.dispatch(request).await.expect_err("uid revoked");
+    assert!(matches!(err, UdsTransportError::Unauthorized(_)));
+    assert!(http.issue_session_token("bob", &[]).is_ok());
+    assert!(router.calls().await.is_empty());
+
+    // Listener and signing settings need a restart and leave the config as is.
+    let err = http
+        .reload(HttpConfig {
+            port: 9444,
+            ..http_config()
+        })
+        .expect_err("port needs a restart");
+    assert!(matches!(err, HttpTransportError::Configuration(_)));
+    assert!(http.issue_session_token("bob", &[]).is_ok());
+    let err = stdio
+        .reload(StdioConfig {
+            token_secret: "rotated".into(),
+            ..stdio_config()
+        })
+        .expect_err("secret needs a restart");
+    assert!(matches!(err, StdioTransportError::Configuration(_)));
+    let err = uds
+        .reload(UdsConfig {
+            socket_path: "/tmp/elsewhere.sock".into(),
+            ..uds_config()
+        })
+        .expect_err("socket path needs a restart");
+    assert!(matches!(err, UdsTransportError::Configuration(_)));
+}
+
+#[tokio::test]
+async fn a_shared_principal_store_replaces_the_static_allowlists() {
+    let router = Arc::new(RecordingRouter::default());
+    let store = Arc::new(InMemoryPrincipalStore::with_principals(["bob"]));
+    let http = HttpAdapter::bind(http_config(), router.clone() as _)
+        .unwrap()
+        .with_principals(store.clone());
+    let stdio = StdioAdapter::bind(stdio_config(), router.clone() as _)
+        .unwrap()
+        .with_principals(store.clone());
+    let uds = UdsAdapter::bind(uds_config(), router.clone() as _)
+        .unwrap()
+        .with_principals(store.clone());
+    uds.negotiate_peer(&peer()).unwrap();
+
+    // Only the store counts: alice is in every config but not in the store.
+    assert!(http.issue_session_token("alice", &[]).is_err());
+    assert!(stdio.issue_session_token("alice").is_err());
+    assert!(uds.issue_session_token("alice", &[]).is_err());
+
+    let http_token = http.issue_session_token("bob", &[]).unwrap();
+    let stdio_token = stdio.issue_session_token("bob").unwrap();
+    let uds_token = uds.issue_session_token("bob", &[]).unwrap();
+    let http_request = || {
+        HttpRequest::new("POST", "/commands/search", json!({ "command": "search" }))
+            .with_header("Authorization", format!("Bearer {}", http_token.token))
+            .with_header("X-Csrf-Token", http_token.csrf_nonce.clone())
+    };
+    let stdio_frame = || {
+        stdio
+            .codec()
+            .encode(&json!({ "command": "search" }), &stdio_token)
+            .unwrap()
+    };
+    let uds_request = || {
+        UdsRequest::new(
+            peer(),
+            uds_token.token.clone(),
+            json!({ "command": "search" }),
+        )
+    };
+    http.dispatch(http_request()).await.unwrap();
+    stdio.dispatch_frame(stdio_frame()).await.unwrap();
+    uds.dispatch(uds_request()).await.unwrap();
+    assert_eq!(router.calls().await.len(), 3);
+
+    // Disabling bob refuses the sessions already issued, on every adapter.
+    store.set_disabled("bob", true).unwrap();
+    assert!(matches!(
+        http.dispatch(http_request()).await,
+        Err(HttpTransportError::Unauthorized(_))
+    ));
+    assert!(matches!(
+        stdio.dispatch_frame(stdio_frame()).await,
+        Err(StdioTransportError::Unauthorized(_))
+    ));
+    assert!(matches!(
+        uds.dispatch(uds_request()).await,
+        Err(UdsTransportError::Unauthorized(_))
+    ));
+    assert_eq!(router.calls().await.len(), 3);
+
+    store.set_disabled("bob", false).unwrap();
+    uds.dispatch(uds_request()).await.unwrap();
+
+    // Sessions of a tenant's principal carry the tenant to the router.
+    store.add("carol", Some("team-c")).unwrap();
+    let carol = uds.issue_session_token("carol", &[]).unwrap();
+    uds.dispatch(UdsRequest::new(
+        peer(),
+        carol.token,
+        json!({ "command": "search" }),
+    ))
+    .await
+    .unwrap();
+    let calls = router.calls().await;
+    assert_eq!(calls[0].context.tenant_id, None);
+    assert_eq!(
+        calls.last().unwrap().context.tenant_id.as_deref(),
+        Some("team-c")
+    );
+}
+
+#[tokio::test]
+async fn adapters_count_traffic_per_peer_in_shared_telemetry() {
+    let router = Arc::new(RecordingRouter::default());
+    let telemetry = Arc::new(PeerTelemetry::default());
+    let http = HttpAdapter::bind(http_config(), router.clone() as _)
+        .unwrap()
+        .with_peer_telemetry(telemetry.clone());
+    let stdio = StdioAdapter::bind(stdio_config(), router.clone() as _)
+        .unwrap()
+        .with_peer_telemetry(telemetry.clone());
+    let uds = UdsAdapter::bind(uds_config(), router.clone() as _)
+        .unwrap()
+        .with_peer_telemetry(telemetry.clone());
+
+    let http_token = http.issue_session_token("alice", &[]).unwrap();
+    let request = HttpRequest::new("POST", "/commands/search", json!({ "command": "search" }))
+        .with_header("Authorization", format!("Bearer {}", http_token.token))
+        .with_remote_addr("10.0.0.7:50122");
+    http.dispatch(
+        request
+            .clone()
+            .with_header("X-Csrf-Token", http_token.csrf_nonce.clone()),
+    )
+    .await
+    .unwrap();
+    http.dispatch(request).await.expect_err("missing csrf");
+
+    let stdio_token = stdio.issue_session_token("alice").unwrap();
+    let frame = stdio
+        .codec()
+        .encode(&json!({ "command": "search" }), &stdio_token)
+        .unwrap();
+    let mut corrupt = frame.payload.clone();
+    *corrupt.last_mut().unwrap() ^= 0xff;
+    stdio.dispatch_frame(frame).await.unwrap();
+    stdio
+        .dispatch_frame(StdioFrame { payload: corrupt })
+        .await
+        .expect_err("checksum mismatch");
+
+    let mut stranger = peer();
+    stranger.uid = 77;
+    uds.negotiate_peer(&stranger).expect_err("bad uid rejected");
+
+    let http_peer = telemetry.peer("http://10.0.0.7:50122").unwrap();
+    assert_eq!((http_peer.frames_in, http_peer.frames_out), (2, 1));
+    assert_eq!(http_peer.auth_failures, 1);
+    assert_eq!(http_peer.last_error.as_deref(), Some("missing csrf token"));
+    let stdio_peer = telemetry.peer("stdio").unwrap();
+    assert_eq!((stdio_peer.frames_in, stdio_peer.frames_out), (2, 1));
+    assert_eq!(stdio_peer.decode_errors, 1);
+    assert!(stdio_peer.bytes_in > stdio_peer.bytes_out);
+
+    let mut commands = HandlerRouter::new();
+    runtime_telemetry::register_commands(&mut commands, telemetry);
+    let reply = commands
+        .dispatch(
+            SessionContext::new("root", vec!["admin".into()]),
+            RouterCommand::new("telemetry.peers", json!({})),
+        )
+        .await
+        .unwrap();
+    let peers: Vec<&str> = reply.payload["peers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|peer| peer["peer"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        peers,
+        vec![
+            "uds://integration-test/123",
+            "stdio",
+            "http://10.0.0.7:50122"
+        ]
+    );
+    assert_eq!(reply.payload["peers"][0]["auth_failures"], json!(1));
+}
+
+#[tokio::test]
+async fn adapters_export_telemetry_through_a_shared_pipeline() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("telemetry.jsonl");
+    let pipeline = Arc::new(
+        ExportPipeline::from_config(&ExportConfig::new(ExporterConfig::Jsonl {
+            path: path.clone(),
+        }))
+        .unwrap(),
+    );
+    let router = Arc::new(RecordingRouter::default());
+    let stdio = StdioAdapter::bind(stdio_config(), router.clone() as _)
+        .unwrap()
+        .with_exporter(pipeline.clone());
+    let uds = UdsAdapter::bind(uds_config(), router.clone() as _)
+        .unwrap()
+        .with_exporter(pipeline.clone());
+
+    let token = stdio.issue_session_token("alice").unwrap();
+    let frame = stdio
+        .codec()
+        .encode(&json!({ "command": "search" }), &token)
+        .unwrap();
+    stdio.dispatch_frame(frame).await.unwrap();
+    uds.negotiate_peer(&peer()).unwrap();
+    pipeline.flush();
+
+    let exported: Vec<ExportRecord> = std::fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let kinds: Vec<&str> = exported.iter().map(|record| record.kind.as_str()).collect();
+    let recorded = stdio.telemetry().events().len() + uds.telemetry().events().len();
+    assert_eq!(exported.len(), recorded);
+    assert!(kinds.contains(&"uds.peer.accepted"));
+    assert!(kinds.iter().any(|kind| kind.starts_with("stdio.")));
+    assert_eq!(pipeline.stats().dropped, 0);
+}
+
+#[tokio::test]
+async fn exported_telemetry_is_filtered_by_severity_but_keeps_auth_failures() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("telemetry.jsonl");
+    let pipeline = Arc::new(
+        ExportPipeline::from_config(&ExportConfig {
+            min_severity: Severity::Warn,
+            sampling: [("uds.auth.failure".to_string(), 0.0)].into(),
+            ..ExportConfig::new(ExporterConfig::Jsonl { path: path.clone() })
+        })
+        .unwrap(),
+    );
+    let router = Arc::new(RecordingRouter::default());
+    let stdio = StdioAdapter::bind(stdio_config(), router.clone() as _)
+        .unwrap()
+        .with_exporter(pipeline.clone());
+    let uds = UdsAdapter::bind(uds_config(), router.clone() as _)
+        .unwrap()
+        .with_exporter(pipeline.clone());
+
+    let token = stdio.issue_session_token("alice").unwrap();
+    let frame = stdio
+        .codec()
+        .encode(&json!({ "command": "search" }), &token)
+        .unwrap();
+    stdio.dispatch_frame(frame).await.unwrap();
+    uds.negotiate_peer(&peer()).unwrap();
+    let mut stranger = peer();
+    stranger.uid = 77;
+    uds.negotiate_peer(&stranger).expect_err("bad uid rejected");
+    pipeline.flush();
+
+    let exported: Vec<ExportRecord> = std::fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(exported.len(), 1);
+    assert_eq!(exported[0].kind, "uds.auth.failure");
+    assert_eq!(exported[0].severity, Severity::Warn);
+    let recorded = stdio.telemetry().events().len() + uds.telemetry().events().len();
+    assert_eq!(pipeline.stats().sampled_out, recorded as u64 - 1);
+}
+
+#[tokio::test]
+async fn redacted_telemetry_never_records_secrets_from_payloads() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("telemetry.jsonl");
+    let pipeline = Arc::new(
+        ExportPipeline::from_config(&ExportConfig::new(ExporterConfig::Jsonl {
+            path: path.clone(),
+        }))
+        .unwrap(),
+    );
+    let router = Arc::new(RecordingRouter::default());
+    // Handlers quote the offending field, here a secret from the payload.
+    router
+        .script_response(Err(RouterError::InvalidRequest {
+            detail: "unexpected field: API_KEY=sk_live_51HxY".into(),
+        }))
+        .await;
+    let redactor = telemetry_redactor(&SanitizationConfig::default()).unwrap();
+    let http = HttpAdapter::bind(http_config(), router.clone() as _)
+        .unwrap()
+        .with_redactor(redactor)
+        .with_exporter(pipeline.clone());
+
+    let token = http.issue_session_token("alice", &[]).unwrap();
+    let request = HttpRequest::new(
+        "POST",
+        "/commands/ingest",
+        json!({ "command": "ingest", "payload": { "text": "API_KEY=sk_live_51HxY" } }),
+    )
+    .with_header("Authorization", format!("Bearer {}", token.token))
+    .with_header("X-Csrf-Token", token.csrf_nonce.clone());
+    http.dispatch(request).await.expect_err("router refused");
+    pipeline.flush();
+
+    let events = http.telemetry().events();
+    let error = events
+        .iter()
+        .find(|event| event.kind == "http.router.error")
+        .unwrap();
+    assert!(error.message.contains("[REDACTED]"), "{}", error.message);
+    let exported = std::fs::read_to_string(&path).unwrap();
+    assert!(exported.contains("http.router.error"));
+    for recorded in events
+        .iter()
+        .map(|event| event.message.as_str())
+        .chain([exported.as_str()])
+    {
+        assert!(!recorded.contains("sk_live"), "{recorded}");
+    }
 }
@@ -0,0 +1,124 @@
+use std::time::Duration;
+
+use proptest::prelude::*;
+use runtime_transport_http::test_support as http;
+use runtime_transport_stdio::test_support as stdio;
+use runtime_transport_stdio::StdioFrame;
+use runtime_transport_uds::test_support as uds;
+
+const MAX_FRAME: usize = 1 << 20;
+
+fn valid_frame(json: &serde_json::Value) -> (StdioFrame, String) {
+    let token = stdio::issue_token(stdio::TEST_SECRET, "alice", Duration::from_secs(3600));
+    let frame = stdio::codec(MAX_FRAME, stdio::TEST_SECRET)
+        .encode(json, &token)
+        .expect("valid frame encodes");
+    (frame, token.token)
+}
+
+proptest! {
+    #[test]
+    fn stdio_decode_accepts_only_verified_tokens(frame in stdio::arb_frame()) {
+        let codec = stdio::codec(MAX_FRAME, stdio::TEST_SECRET);
+        if let Ok((_, token)) = codec.decode(&frame) {
+            prop_assert!(stdio::verify_token(stdio::TEST_SECRET, &token).is_ok());
+        }
+    }
+
+    #[test]
+    fn stdio_valid_frames_round_trip(json in stdio::arb_json()) {
+        let (frame, token) = valid_frame(&json);
+        let (decoded, decoded_token) = stdio::codec(MAX_FRAME, stdio::TEST_SECRET)
+            .decode(&frame)
+            .expect("valid frame decodes");
+        // Parse the original the same way so float formatting cannot differ.
+        let expected: serde_json::Value =
+            serde_json::from_str(&json.to_string()).expect("json reparses");
+        prop_assert_eq!(decoded, expected);
+        prop_assert_eq!(decoded_token, token);
+    }
+
+    #[test]
+    fn stdio_rejects_flipped_bytes(
+        json in stdio::arb_json(),
+        index in any::<prop::sample::Index>(),
+        mask in 1u8..,
+    ) {
+        let (mut frame, _) = valid_frame(&json);
+        let at = index.index(frame.payload.len());
+        frame.payload[at] ^= mask;
+        let codec = stdio::codec(MAX_FRAME, stdio::TEST_SECRET);
+        prop_assert!(codec.decode(&frame).is_err());
+    }
+
+    #[test]
+    fn stdio_rejects_truncated_frames(json in stdio::arb_json(), index in any::<prop::sample::Index>()) {
+        let (mut frame, _) = valid_frame(&json);
+        frame.payload.truncate(index.index(frame.payload.len()));
+        let codec = stdio::codec(MAX_FRAME, stdio::TEST_SECRET);
+        prop_assert!(codec.decode(&frame).is_err());
+    }
+
+    #[test]
+    fn stdio_rejects_frames_over_the_limit(json in stdio::arb_json()) {
+        let (frame, _) = valid_frame(&json);
+        let codec = stdio::codec(frame.payload.len() - 1, stdio::TEST_SECRET);
+        prop_assert!(codec.decode(&frame).is_err());
+    }
+
+    #[test]
+    fn forged_stdio_tokens_are_rejected(token in stdio::arb_token()) {
+        prop_assert!(stdio::verify_token(stdio::TEST_SECRET, &token).is_err());
+    }
+
+    #[test]
+    fn forged_http_tokens_are_rejected(token in http::arb_token()) {
+        prop_assert!(http::verify_token(http::TEST_SECRET, &token).is_err());
+    }
+
+    #[test]
+    fn forged_uds_tokens_are_rejected(token in uds::arb_token()) {
+        prop_assert!(uds::verify_token(uds::TEST_SECRET, &token).is_err());
+    }
+
+    #[test]
+    fn signed_stdio_tokens_are_accepted_until_expiry(
+        live in stdio::arb_signed_token(stdio::TEST_SECRET, true),
+        expired in stdio::arb_signed_token(stdio::TEST_SECRET, false),
+    ) {
+        prop_assert!(stdio::verify_token(stdio::TEST_SECRET, &live).is_ok());
+        prop_assert!(stdio::verify_token(stdio::TEST_SECRET, &expired).is_err());
+        prop_assert!(stdio::verify_token("another-secret", &live).is_err());
+    }
+
+    #[test]
+    fn signed_http_tokens_are_accepted_until_expiry(
+        live in http::arb_signed_token(http::TEST_SECRET, true),
+        expired in http::arb_signed_token(http::TEST_SECRET, false),
+    ) {
+        prop_assert!(http::verify_token(http::TEST_SECRET, &live).is_ok());
+        prop_assert!(http::verify_token(http::TEST_SECRET, &expired).is_err());
+        prop_assert!(http::verify_token("another-secret", &live).is_err());
+    }
+
+    #[test]
+    fn signed_uds_tokens_are_accepted_until_expiry(
+        live in uds::arb_signed_token(uds::TEST_SECRET, true),
+        expired in uds::arb_signed_token(uds::TEST_SECRET, false),
+    ) {
+        prop_assert!(uds::verify_token(uds::TEST_SECRET, &live).is_ok());
+        prop_assert!(uds::verify_token(uds::TEST_SECRET, &expired).is_err());
+        prop_assert!(uds::verify_token("another-secret", &live).is_err());
+    }
+}
+
+#[test]
+fn issued_tokens_verify_across_transports() {
+    let ttl = Duration::from_secs(60);
+    let token = stdio::issue_token(stdio::TEST_SECRET, "alice", ttl);
+    assert!(stdio::verify_token(stdio::TEST_SECRET, &token.token).is_ok());
+    let token = http::issue_token(http::TEST_SECRET, "alice", ttl);
+    assert!(http::verify_token(http::TEST_SECRET, &token.token).is_ok());
+    let token = uds::issue_token(uds::TEST_SECRET, "alice", ttl);
+    assert!(uds::verify_token(uds::TEST_SECRET, &token.token).is_ok());
+}